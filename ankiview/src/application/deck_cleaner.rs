@@ -0,0 +1,98 @@
+// src/application/deck_cleaner.rs
+use crate::application::NoteRepository;
+use crate::domain::DomainError;
+
+/// Anki's built-in deck; it can't be deleted
+const DEFAULT_DECK_ID: i64 = 1;
+
+pub struct DeckCleaner<R: NoteRepository> {
+    repository: R,
+}
+
+impl<R: NoteRepository> DeckCleaner<R> {
+    pub fn new(repository: R) -> Self {
+        Self { repository }
+    }
+
+    /// Normal decks without cards, counting the cards of their subdecks and
+    /// cards moved into filtered decks from them too, as (deck_id, name) pairs
+    /// sorted by name. The Default deck is never listed.
+    pub fn empty_decks(&mut self) -> Result<Vec<(i64, String)>, DomainError> {
+        let decks = self.repository.list_decks()?;
+
+        let mut empty: Vec<(i64, String)> = decks
+            .iter()
+            .filter(|(id, _, _)| *id != DEFAULT_DECK_ID)
+            .filter(|(_, name, _)| {
+                let prefix = format!("{}::", name);
+                decks
+                    .iter()
+                    .filter(|(_, other, _)| other == name || other.starts_with(&prefix))
+                    .all(|(_, _, cards)| *cards == 0)
+            })
+            .map(|(id, name, _)| (*id, name.clone()))
+            .collect();
+        empty.sort_by(|a, b| a.1.cmp(&b.1));
+        Ok(empty)
+    }
+
+    /// Delete the empty decks and return them
+    pub fn delete_empty_decks(&mut self) -> Result<Vec<(i64, String)>, DomainError> {
+        let empty = self.empty_decks()?;
+        if !empty.is_empty() {
+            let ids: Vec<i64> = empty.iter().map(|(id, _)| *id).collect();
+            self.repository.delete_decks(&ids)?;
+        }
+        Ok(empty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing::MockNoteRepository;
+
+    fn repository() -> MockNoteRepository {
+        MockNoteRepository::builder()
+            .with_deck(1, "Default", 0)
+            .with_deck(2, "Rust", 0)
+            .with_deck(3, "Rust::Ownership", 12)
+            .with_deck(4, "Rsut", 0)
+            .with_deck(5, "Physics", 0)
+            .with_deck(6, "Physics::Optics", 0)
+            .build()
+    }
+
+    #[test]
+    fn given_decks_when_listing_empty_then_skips_default_and_parents_of_used_decks() {
+        // Arrange
+        let mut cleaner = DeckCleaner::new(repository());
+
+        // Act
+        let empty = cleaner.empty_decks().unwrap();
+
+        // Assert
+        let names: Vec<_> = empty.iter().map(|(_, name)| name.as_str()).collect();
+        assert_eq!(names, vec!["Physics", "Physics::Optics", "Rsut"]);
+    }
+
+    #[test]
+    fn given_empty_decks_when_deleting_then_only_used_decks_remain() {
+        // Arrange
+        let mut cleaner = DeckCleaner::new(repository());
+
+        // Act
+        let deleted = cleaner.delete_empty_decks().unwrap();
+
+        // Assert
+        assert_eq!(deleted.len(), 3);
+        let remaining: Vec<_> = cleaner
+            .repository
+            .list_decks()
+            .unwrap()
+            .into_iter()
+            .map(|(id, _, _)| id)
+            .collect();
+        assert_eq!(remaining, vec![1, 2, 3]);
+    }
+}
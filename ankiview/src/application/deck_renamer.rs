@@ -0,0 +1,71 @@
+// src/application/deck_renamer.rs
+use crate::application::NoteRepository;
+use crate::domain::DomainError;
+
+pub struct DeckRenamer<R: NoteRepository> {
+    repository: R,
+}
+
+impl<R: NoteRepository> DeckRenamer<R> {
+    pub fn new(repository: R) -> Self {
+        Self { repository }
+    }
+
+    /// Rename deck `old` (full name, e.g. `Old::Name`) to `new`; its subdecks move along
+    pub fn rename_deck(&mut self, old: &str, new: &str) -> Result<(), DomainError> {
+        let (old, new) = (old.trim(), new.trim());
+        if old.is_empty() || new.is_empty() {
+            return Err(DomainError::CollectionError(
+                "Deck names cannot be empty".to_string(),
+            ));
+        }
+        if old == new {
+            return Ok(());
+        }
+        self.repository.rename_deck(old, new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing::MockNoteRepository;
+
+    #[test]
+    fn given_existing_deck_when_renaming_then_deck_and_subdecks_renamed() {
+        // Arrange
+        let mock = MockNoteRepository::builder()
+            .with_deck(2, "Old::Name", 3)
+            .with_deck(3, "Old::Name::Child", 1)
+            .build();
+        let mut renamer = DeckRenamer::new(mock);
+
+        // Act
+        renamer.rename_deck("Old::Name", "New::Name").unwrap();
+
+        // Assert
+        let names: Vec<_> = renamer
+            .repository
+            .list_decks()
+            .unwrap()
+            .into_iter()
+            .map(|(_, name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["New::Name", "New::Name::Child"]);
+    }
+
+    #[test]
+    fn given_unknown_deck_when_renaming_then_returns_deck_not_found() {
+        // Arrange
+        let mut renamer = DeckRenamer::new(MockNoteRepository::builder().build());
+
+        // Act
+        let result = renamer.rename_deck("Missing", "New");
+
+        // Assert
+        match result.expect_err("Should return error") {
+            DomainError::DeckNotFound(name) => assert_eq!(name, "Missing"),
+            _ => panic!("Expected DeckNotFound error"),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+// src/application/field_replacer.rs
+use crate::application::NoteRepository;
+use crate::domain::DomainError;
+use regex::Regex;
+
+/// One field changed by a find-and-replace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldReplacement {
+    pub note_id: i64,
+    /// Index of the field in its notetype (0 = first field)
+    pub field: usize,
+    pub before: String,
+    pub after: String,
+}
+
+pub struct FieldReplacer<R: NoteRepository> {
+    repository: R,
+}
+
+impl<R: NoteRepository> FieldReplacer<R> {
+    pub fn new(repository: R) -> Self {
+        Self { repository }
+    }
+
+    /// Replace every match of `find` with `replacement` (which may use `$1`/`${name}`
+    /// capture references) in all fields of the notes matching `query`.
+    /// With `dry_run` nothing is written; the returned changes are a preview.
+    pub fn replace(
+        &mut self,
+        query: Option<&str>,
+        find: &Regex,
+        replacement: &str,
+        dry_run: bool,
+    ) -> Result<Vec<FieldReplacement>, DomainError> {
+        let mut changes = Vec::new();
+
+        for (note_id, mut fields) in self.repository.note_fields(query)? {
+            let mut changed = false;
+            for (index, field) in fields.iter_mut().enumerate() {
+                let after = find.replace_all(field, replacement);
+                if after != field.as_str() {
+                    let after = after.into_owned();
+                    changes.push(FieldReplacement {
+                        note_id,
+                        field: index,
+                        before: std::mem::replace(field, after.clone()),
+                        after,
+                    });
+                    changed = true;
+                }
+            }
+
+            if changed && !dry_run {
+                self.repository.set_note_fields(note_id, &fields)?;
+            }
+        }
+
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Note;
+    use crate::util::testing::MockNoteRepository;
+
+    fn repository() -> MockNoteRepository {
+        let note = |id: i64, front: &str, back: &str| Note {
+            id,
+            front: front.to_string(),
+            back: back.to_string(),
+            tags: vec![],
+            model_name: "Basic".to_string(),
+        };
+        MockNoteRepository::builder()
+            .with_note(1, note(1, "What is colour?", "A colour is a hue"))
+            .with_note(2, note(2, "What is Rust?", "A language"))
+            .build()
+    }
+
+    #[test]
+    fn given_matching_fields_when_replacing_then_updates_notes() {
+        // Arrange
+        let mut replacer = FieldReplacer::new(repository());
+        let find = Regex::new(r"colou?r").unwrap();
+
+        // Act
+        let changes = replacer.replace(None, &find, "color", false).unwrap();
+
+        // Assert
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].note_id, 1);
+        assert_eq!(changes[0].field, 0);
+        assert_eq!(changes[0].before, "What is colour?");
+        assert_eq!(changes[0].after, "What is color?");
+        assert_eq!(changes[1].field, 1);
+        let note = replacer.repository.get_note(1).unwrap();
+        assert_eq!(note.back, "A color is a hue");
+        assert_eq!(
+            replacer.repository.get_note(2).unwrap().front,
+            "What is Rust?"
+        );
+    }
+
+    #[test]
+    fn given_dry_run_when_replacing_then_reports_changes_without_writing() {
+        // Arrange
+        let mut replacer = FieldReplacer::new(repository());
+        let find = Regex::new(r"What is (\w+)\?").unwrap();
+
+        // Act
+        let changes = replacer.replace(None, &find, "Define $1.", true).unwrap();
+
+        // Assert
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[1].after, "Define Rust.");
+        assert_eq!(
+            replacer.repository.get_note(2).unwrap().front,
+            "What is Rust?"
+        );
+    }
+}
@@ -0,0 +1,81 @@
+// src/application/leech_reporter.rs
+use crate::application::NoteRepository;
+use crate::domain::{DomainError, ProblemNote};
+
+pub struct LeechReporter<R: NoteRepository> {
+    repository: R,
+}
+
+impl<R: NoteRepository> LeechReporter<R> {
+    pub fn new(repository: R) -> Self {
+        Self { repository }
+    }
+
+    /// Notes tagged `leech` or with a card lapsed at least `min_lapses` times,
+    /// most lapses first (ties by note ID)
+    pub fn problem_notes(
+        &mut self,
+        min_lapses: u32,
+        deck: Option<&str>,
+    ) -> Result<Vec<ProblemNote>, DomainError> {
+        if min_lapses == 0 {
+            return Err(DomainError::CollectionError(
+                "--min-lapses must be at least 1".to_string(),
+            ));
+        }
+        let mut notes = self.repository.problem_notes(min_lapses, deck)?;
+        notes.sort_by(|a, b| b.lapses.cmp(&a.lapses).then(a.note_id.cmp(&b.note_id)));
+        Ok(notes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing::MockNoteRepository;
+
+    fn problem(note_id: i64, lapses: u32, leech: bool) -> ProblemNote {
+        ProblemNote {
+            note_id,
+            first_line: format!("Question {}", note_id),
+            deck: "Rust".to_string(),
+            lapses,
+            leech,
+        }
+    }
+
+    #[test]
+    fn given_lapsed_notes_when_reporting_then_sorted_by_lapses() {
+        // Arrange
+        let mock = MockNoteRepository::builder()
+            .with_problem_note(problem(1, 9, true))
+            .with_problem_note(problem(2, 3, false))
+            .with_problem_note(problem(3, 12, false))
+            .with_problem_note(problem(4, 2, true))
+            .build();
+        let mut reporter = LeechReporter::new(mock);
+
+        // Act
+        let notes = reporter.problem_notes(8, None).unwrap();
+
+        // Assert
+        let ids: Vec<_> = notes.iter().map(|n| n.note_id).collect();
+        assert_eq!(
+            ids,
+            vec![3, 1, 4],
+            "below the threshold only if tagged leech"
+        );
+    }
+
+    #[test]
+    fn given_zero_min_lapses_when_reporting_then_returns_error() {
+        // Arrange
+        let mut reporter = LeechReporter::new(MockNoteRepository::builder().build());
+
+        // Act
+        let result = reporter.problem_notes(0, None);
+
+        // Assert
+        assert!(matches!(result, Err(DomainError::CollectionError(_))));
+    }
+}
@@ -1,14 +1,34 @@
 // src/application/mod.rs
+pub mod deck_cleaner;
+pub mod deck_renamer;
+pub mod field_replacer;
+pub mod leech_reporter;
+pub mod note_cloner;
+pub mod note_counter;
 pub mod note_deleter;
 pub mod note_editor;
+pub mod note_exporter;
+pub mod note_flagger;
 pub mod note_lister;
+pub mod note_transferer;
 pub mod note_updater;
 pub mod note_viewer;
+pub mod rule_applier;
 pub mod tag_manager;
 
+pub use deck_cleaner::DeckCleaner;
+pub use deck_renamer::DeckRenamer;
+pub use field_replacer::{FieldReplacement, FieldReplacer};
+pub use leech_reporter::LeechReporter;
+pub use note_cloner::NoteCloner;
+pub use note_counter::NoteCounter;
 pub use note_deleter::NoteDeleter;
 pub use note_editor::NoteEditor;
+pub use note_exporter::NoteExporter;
+pub use note_flagger::NoteFlagger;
 pub use note_lister::NoteLister;
+pub use note_transferer::{NoteTransferer, TransferReport};
 pub use note_updater::NoteUpdater;
-pub use note_viewer::{NoteRepository, NoteViewer};
+pub use note_viewer::{NotePick, NoteRepository, NoteViewer};
+pub use rule_applier::{RuleApplier, RuleReport};
 pub use tag_manager::TagManager;
@@ -0,0 +1,66 @@
+// src/application/note_cloner.rs
+use crate::application::NoteRepository;
+use crate::domain::DomainError;
+
+pub struct NoteCloner<R: NoteRepository> {
+    repository: R,
+}
+
+impl<R: NoteRepository> NoteCloner<R> {
+    pub fn new(repository: R) -> Self {
+        Self { repository }
+    }
+
+    /// Copy a note into a new one (in `deck`, or the original's deck) and
+    /// return the new note's ID
+    pub fn clone_note(&mut self, note_id: i64, deck: Option<&str>) -> Result<i64, DomainError> {
+        self.repository.clone_note(note_id, deck)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Note;
+    use crate::util::testing::MockNoteRepository;
+
+    #[test]
+    fn given_existing_note_when_cloning_then_returns_new_note_with_same_content() {
+        // Arrange
+        let note = Note {
+            id: 123,
+            front: "Question".to_string(),
+            back: "Answer".to_string(),
+            tags: vec!["rust".to_string()],
+            model_name: "Basic".to_string(),
+        };
+        let mock = MockNoteRepository::builder().with_note(123, note).build();
+        let mut cloner = NoteCloner::new(mock);
+
+        // Act
+        let new_id = cloner.clone_note(123, None).expect("Clone should succeed");
+
+        // Assert
+        assert_ne!(new_id, 123);
+        let copy = cloner.repository.get_note(new_id).unwrap();
+        assert_eq!(copy.front, "Question");
+        assert_eq!(copy.back, "Answer");
+        assert_eq!(copy.tags, vec!["rust"]);
+    }
+
+    #[test]
+    fn given_nonexistent_note_when_cloning_then_returns_not_found() {
+        // Arrange
+        let mock = MockNoteRepository::builder().build();
+        let mut cloner = NoteCloner::new(mock);
+
+        // Act
+        let result = cloner.clone_note(999, Some("Scratch"));
+
+        // Assert
+        match result.expect_err("Should return error") {
+            DomainError::NoteNotFound(id) => assert_eq!(id, 999),
+            _ => panic!("Expected NoteNotFound error"),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+// src/application/note_counter.rs
+use crate::application::NoteRepository;
+use crate::domain::{CountGroup, DomainError};
+
+pub struct NoteCounter<R: NoteRepository> {
+    repository: R,
+}
+
+impl<R: NoteRepository> NoteCounter<R> {
+    pub fn new(repository: R) -> Self {
+        Self { repository }
+    }
+
+    /// Number of notes matching the Anki search `query` (all notes if `None`)
+    pub fn count(&mut self, query: Option<&str>) -> Result<usize, DomainError> {
+        self.repository.count_notes(query)
+    }
+
+    /// Matching notes per deck, tag or notetype; largest groups first (ties by name)
+    pub fn count_by(
+        &mut self,
+        query: Option<&str>,
+        group: CountGroup,
+    ) -> Result<Vec<(String, usize)>, DomainError> {
+        let mut counts = self.repository.count_notes_by(query, group)?;
+        counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Note;
+    use crate::util::testing::MockNoteRepository;
+
+    #[test]
+    fn given_tagged_notes_when_counting_by_tag_then_largest_group_first() {
+        // Arrange
+        let note = |id: i64, tags: &[&str]| Note {
+            id,
+            front: format!("Question {}", id),
+            back: "Answer".to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            model_name: "Basic".to_string(),
+        };
+        let mock = MockNoteRepository::builder()
+            .with_note(1, note(1, &["rust"]))
+            .with_note(2, note(2, &["rust", "async"]))
+            .with_note(3, note(3, &[]))
+            .build();
+        let mut counter = NoteCounter::new(mock);
+
+        // Act
+        let total = counter.count(None).unwrap();
+        let by_tag = counter.count_by(None, CountGroup::Tag).unwrap();
+
+        // Assert
+        assert_eq!(total, 3);
+        assert_eq!(
+            by_tag,
+            vec![
+                ("rust".to_string(), 2),
+                ("(untagged)".to_string(), 1),
+                ("async".to_string(), 1),
+            ]
+        );
+    }
+}
@@ -1,5 +1,6 @@
 // src/application/note_editor.rs
 use crate::application::NoteRepository;
+use crate::domain::DomainError;
 use crate::infrastructure::note_template::NoteTemplate;
 use anyhow::{Context, Result};
 use std::fs;
@@ -17,10 +18,7 @@ impl<R: NoteRepository> NoteEditor<R> {
 
     pub fn edit(&mut self, note_id: i64) -> Result<bool> {
         // Fetch the note
-        let note = self
-            .repository
-            .get_note(note_id)
-            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let note = self.repository.get_note(note_id)?;
 
         // Build template from note
         let template = NoteTemplate::from_note(&note);
@@ -71,7 +69,8 @@ impl<R: NoteRepository> NoteEditor<R> {
             fs::read_to_string(temp_file.path()).context("Failed to read edited template")?;
 
         // Parse and validate
-        let edited_template = NoteTemplate::from_string(&edited_text, &note)?;
+        let edited_template = NoteTemplate::from_string(&edited_text, &note)
+            .map_err(|e| DomainError::ParseError(e.to_string()))?;
         edited_template.validate(&note)?;
 
         // Apply changes
@@ -0,0 +1,112 @@
+// src/application/note_exporter.rs
+use crate::application::NoteRepository;
+use crate::domain::DomainError;
+use crate::util::text::TextFormat;
+
+pub struct NoteExporter<R: NoteRepository> {
+    repository: R,
+}
+
+impl<R: NoteRepository> NoteExporter<R> {
+    pub fn new(repository: R) -> Self {
+        Self { repository }
+    }
+
+    /// Tab-separated front, back and space-separated tags, one line per note
+    /// matching the Anki search `query`, as Quizlet and Anki's text importer
+    /// read them. Returns the TSV and the number of notes.
+    pub fn export_tsv(
+        &mut self,
+        query: Option<&str>,
+        format: TextFormat,
+    ) -> Result<(String, usize), DomainError> {
+        let notes = self.repository.search_notes(query)?;
+        let mut tsv = String::new();
+        for note in &notes {
+            let row = [
+                format.render(&note.front),
+                format.render(&note.back),
+                note.tags.join(" "),
+            ];
+            let row: Vec<String> = row.iter().map(|field| tsv_field(field)).collect();
+            tsv.push_str(&row.join("\t"));
+            tsv.push('\n');
+        }
+        Ok((tsv, notes.len()))
+    }
+}
+
+/// Fields with tabs, line breaks or quotes are quoted, doubling inner quotes,
+/// the way Anki's importer reads multi-line fields
+fn tsv_field(field: &str) -> String {
+    if field.contains(['\t', '\n', '\r', '"']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Note;
+    use crate::util::testing::MockNoteRepository;
+
+    fn repository() -> MockNoteRepository {
+        MockNoteRepository::builder()
+            .with_note(
+                2,
+                Note {
+                    id: 2,
+                    front: "<p>Say \"hi\"</p><p>twice</p>".to_string(),
+                    back: "hi hi".to_string(),
+                    tags: vec![],
+                    model_name: "Basic".to_string(),
+                },
+            )
+            .with_note(
+                1,
+                Note {
+                    id: 1,
+                    front: "What is <b>Rust</b>?".to_string(),
+                    back: "A language".to_string(),
+                    tags: vec!["rust".to_string(), "lang".to_string()],
+                    model_name: "Basic".to_string(),
+                },
+            )
+            .build()
+    }
+
+    #[test]
+    fn given_notes_when_exporting_html_then_writes_rows_by_id() {
+        // Arrange
+        let mut exporter = NoteExporter::new(repository());
+
+        // Act
+        let (tsv, count) = exporter.export_tsv(None, TextFormat::Html).unwrap();
+
+        // Assert
+        assert_eq!(count, 2);
+        assert_eq!(
+            tsv,
+            "What is <b>Rust</b>?\tA language\trust lang\n\
+             \"<p>Say \"\"hi\"\"</p><p>twice</p>\"\thi hi\t\n"
+        );
+    }
+
+    #[test]
+    fn given_multiline_text_when_exporting_then_quotes_field() {
+        // Arrange
+        let mut exporter = NoteExporter::new(repository());
+
+        // Act
+        let (tsv, _) = exporter.export_tsv(None, TextFormat::Markdown).unwrap();
+
+        // Assert
+        assert_eq!(
+            tsv,
+            "What is **Rust**?\tA language\trust lang\n\
+             \"Say \"\"hi\"\"\n\ntwice\"\thi hi\t\n"
+        );
+    }
+}
@@ -0,0 +1,87 @@
+// src/application/note_flagger.rs
+use crate::application::NoteRepository;
+use crate::domain::{DomainError, Flag, MARKED_TAG};
+
+pub struct NoteFlagger<R: NoteRepository> {
+    repository: R,
+}
+
+impl<R: NoteRepository> NoteFlagger<R> {
+    pub fn new(repository: R) -> Self {
+        Self { repository }
+    }
+
+    /// Set (or with `Flag::None` clear) the flag on all cards of a note
+    /// Returns the number of cards flagged
+    pub fn flag_note(&mut self, note_id: i64, flag: Flag) -> Result<usize, DomainError> {
+        self.repository.set_note_flag(note_id, flag)
+    }
+
+    /// Set (or clear) the flag on a single card, e.g. one found in the review log
+    pub fn flag_card(&mut self, card_id: i64, flag: Flag) -> Result<(), DomainError> {
+        self.repository.set_card_flag(card_id, flag)
+    }
+
+    /// Add or remove Anki's `marked` tag
+    pub fn set_marked(&mut self, note_id: i64, marked: bool) -> Result<(), DomainError> {
+        let tags = [MARKED_TAG.to_string()];
+        if marked {
+            self.repository.add_tags(note_id, &tags)
+        } else {
+            self.repository.remove_tags(note_id, &tags)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Note;
+    use crate::util::testing::MockNoteRepository;
+
+    fn repository() -> MockNoteRepository {
+        MockNoteRepository::builder()
+            .with_note(
+                123,
+                Note {
+                    id: 123,
+                    front: "Question".to_string(),
+                    back: "Answer".to_string(),
+                    tags: vec!["rust".to_string()],
+                    model_name: "Basic".to_string(),
+                },
+            )
+            .build()
+    }
+
+    #[test]
+    fn given_note_when_marking_and_unmarking_then_toggles_marked_tag() {
+        // Arrange
+        let mut flagger = NoteFlagger::new(repository());
+
+        // Act
+        flagger.set_marked(123, true).unwrap();
+        let marked = flagger.repository.get_note(123).unwrap().tags;
+        flagger.set_marked(123, false).unwrap();
+        let unmarked = flagger.repository.get_note(123).unwrap().tags;
+
+        // Assert
+        assert_eq!(marked, vec!["rust", "marked"]);
+        assert_eq!(unmarked, vec!["rust"]);
+    }
+
+    #[test]
+    fn given_nonexistent_note_when_flagging_then_returns_not_found() {
+        // Arrange
+        let mut flagger = NoteFlagger::new(repository());
+
+        // Act
+        let result = flagger.flag_note(999, Flag::Red);
+
+        // Assert
+        match result.expect_err("Should return error") {
+            DomainError::NoteNotFound(id) => assert_eq!(id, 999),
+            _ => panic!("Expected NoteNotFound error"),
+        }
+    }
+}
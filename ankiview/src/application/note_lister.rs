@@ -21,6 +21,17 @@ impl<R: NoteRepository> NoteLister<R> {
     pub fn list_notes(&mut self, search_query: Option<&str>) -> Result<Vec<Note>, DomainError> {
         self.repository.list_notes(search_query)
     }
+
+    /// Notes with cards due today (learning and review cards, not new ones),
+    /// with their number of due cards; most due cards first
+    pub fn list_due_notes(
+        &mut self,
+        search_query: Option<&str>,
+    ) -> Result<Vec<(Note, usize)>, DomainError> {
+        let mut notes = self.repository.list_due_notes(search_query)?;
+        notes.sort_by(|(a, a_due), (b, b_due)| b_due.cmp(a_due).then(a.id.cmp(&b.id)));
+        Ok(notes)
+    }
 }
 
 #[cfg(test)]
@@ -29,6 +40,33 @@ mod tests {
     use crate::domain::Note;
     use crate::util::testing::MockNoteRepository;
 
+    #[test]
+    fn given_due_cards_when_listing_due_notes_then_most_due_first() {
+        // Arrange
+        let note = |id: i64| Note {
+            id,
+            front: format!("Question {}", id),
+            back: "Answer".to_string(),
+            tags: vec![],
+            model_name: "Basic".to_string(),
+        };
+        let mock = MockNoteRepository::builder()
+            .with_note(1, note(1))
+            .with_note(2, note(2))
+            .with_note(3, note(3))
+            .with_due_cards(1, 1)
+            .with_due_cards(3, 2)
+            .build();
+        let mut lister = NoteLister::new(mock);
+
+        // Act
+        let due = lister.list_due_notes(None).expect("List should succeed");
+
+        // Assert
+        let rows: Vec<_> = due.iter().map(|(n, count)| (n.id, *count)).collect();
+        assert_eq!(rows, vec![(3, 2), (1, 1)]);
+    }
+
     #[test]
     fn given_no_search_when_listing_notes_then_returns_all_notes() {
         // Arrange
@@ -0,0 +1,271 @@
+// src/application/note_transferer.rs
+use crate::application::NoteRepository;
+use crate::util::text::is_plain_file_name;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+/// What a transfer did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransferReport {
+    /// (source ID, new target ID) per note, in source ID order
+    pub mapping: Vec<(i64, i64)>,
+    /// Media files written to the target's collection.media
+    pub media_copied: usize,
+    /// Referenced media files the source's collection.media doesn't have
+    pub media_missing: Vec<String>,
+    /// Referenced media names that aren't plain file names (e.g. `../x`),
+    /// never read or written
+    pub media_rejected: Vec<String>,
+    /// Notes deleted from the source (with `move_notes`)
+    pub deleted: usize,
+}
+
+pub struct NoteTransferer<S: NoteRepository, T: NoteRepository> {
+    source: S,
+    target: T,
+}
+
+impl<S: NoteRepository, T: NoteRepository> NoteTransferer<S, T> {
+    pub fn new(source: S, target: T) -> Self {
+        Self { source, target }
+    }
+
+    /// Add the source notes matching `query` to the target as new notes,
+    /// with their notetypes, decks and media files. Cards start new.
+    ///
+    /// Media files the target already has with different content are
+    /// checked before anything is written: the transfer fails listing them,
+    /// unless `force` overwrites them. With `move_notes`, the source notes
+    /// are deleted once all of them were added.
+    pub fn transfer(
+        &mut self,
+        query: Option<&str>,
+        source_media: &Path,
+        target_media: &Path,
+        force: bool,
+        move_notes: bool,
+    ) -> Result<TransferReport> {
+        let notes = self.source.transfer_notes(query)?;
+        let mut report = TransferReport::default();
+
+        let mut to_copy: Vec<&str> = Vec::new();
+        let mut conflicts: Vec<&str> = Vec::new();
+        for name in notes.iter().flat_map(|note| &note.media) {
+            if to_copy.contains(&name.as_str())
+                || report.media_missing.contains(name)
+                || report.media_rejected.contains(name)
+            {
+                continue;
+            }
+            // Names come from note HTML; keep reads and writes inside collection.media
+            if !is_plain_file_name(name) {
+                warn!(file = %name, "Skipping media reference that isn't a plain file name");
+                report.media_rejected.push(name.clone());
+                continue;
+            }
+            let source_path = source_media.join(name);
+            if !source_path.is_file() {
+                warn!(file = %name, "Referenced media file not in source collection");
+                report.media_missing.push(name.clone());
+                continue;
+            }
+            let target_path = target_media.join(name);
+            if target_path.exists() {
+                let same = fs::read(&source_path)? == fs::read(&target_path)?;
+                if same {
+                    continue;
+                }
+                conflicts.push(name);
+            }
+            to_copy.push(name);
+        }
+        if !conflicts.is_empty() && !force {
+            return Err(anyhow::anyhow!(
+                "Target collection.media has different files named {}. Use --force to overwrite.",
+                conflicts.join(", ")
+            ));
+        }
+
+        for name in to_copy {
+            fs::copy(source_media.join(name), target_media.join(name))
+                .with_context(|| format!("Failed to copy media file {}", name))?;
+            report.media_copied += 1;
+        }
+
+        for note in &notes {
+            let new_id = self.target.add_transferred_note(note)?;
+            debug!(note_id = note.id, new_note_id = new_id, "Transferred note");
+            report.mapping.push((note.id, new_id));
+        }
+
+        if move_notes {
+            for note in &notes {
+                self.source.delete_note(note.id)?;
+                report.deleted += 1;
+            }
+        }
+
+        info!(
+            notes = report.mapping.len(),
+            media = report.media_copied,
+            deleted = report.deleted,
+            "Transfer completed"
+        );
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Note;
+    use crate::util::testing::MockNoteRepository;
+    use tempfile::TempDir;
+
+    fn note(id: i64, front: &str) -> Note {
+        Note {
+            id,
+            front: front.to_string(),
+            back: "Answer".to_string(),
+            tags: vec!["shared".to_string()],
+            model_name: "Basic".to_string(),
+        }
+    }
+
+    fn media_dirs() -> (TempDir, TempDir) {
+        (TempDir::new().unwrap(), TempDir::new().unwrap())
+    }
+
+    #[test]
+    fn given_notes_with_media_when_transferring_then_adds_notes_and_copies_media() {
+        // Arrange
+        let (source_media, target_media) = media_dirs();
+        fs::write(source_media.path().join("tree.png"), b"tree").unwrap();
+        let source = MockNoteRepository::builder()
+            .with_note(1, note(1, "<img src=\"tree.png\"> What?"))
+            .with_note(2, note(2, "Why? <img src=\"gone.png\">"))
+            .build();
+        let target = MockNoteRepository::builder()
+            .with_note(10, note(10, "Existing"))
+            .build();
+        let mut transferer = NoteTransferer::new(source, target);
+
+        // Act
+        let report = transferer
+            .transfer(None, source_media.path(), target_media.path(), false, false)
+            .expect("Transfer should succeed");
+
+        // Assert
+        assert_eq!(report.mapping, vec![(1, 11), (2, 12)]);
+        assert_eq!(report.media_copied, 1);
+        assert_eq!(report.media_missing, vec!["gone.png"]);
+        assert_eq!(report.deleted, 0);
+        assert_eq!(
+            fs::read(target_media.path().join("tree.png")).unwrap(),
+            b"tree"
+        );
+        let copy = transferer.target.get_note(11).unwrap();
+        assert_eq!(copy.front, "<img src=\"tree.png\"> What?");
+        assert_eq!(copy.tags, vec!["shared"]);
+    }
+
+    #[test]
+    fn given_conflicting_media_when_transferring_without_force_then_nothing_is_written() {
+        // Arrange
+        let (source_media, target_media) = media_dirs();
+        fs::write(source_media.path().join("tree.png"), b"new").unwrap();
+        fs::write(target_media.path().join("tree.png"), b"old").unwrap();
+        let source = MockNoteRepository::builder()
+            .with_note(1, note(1, "<img src=\"tree.png\">"))
+            .build();
+        let target = MockNoteRepository::builder().build();
+        let mut transferer = NoteTransferer::new(source, target);
+
+        // Act
+        let err = transferer
+            .transfer(None, source_media.path(), target_media.path(), false, false)
+            .expect_err("Conflict should fail");
+
+        // Assert
+        assert!(err.to_string().contains("tree.png"));
+        assert!(transferer.target.note_ids(None).unwrap().is_empty());
+        assert_eq!(
+            fs::read(target_media.path().join("tree.png")).unwrap(),
+            b"old"
+        );
+
+        let report = transferer
+            .transfer(None, source_media.path(), target_media.path(), true, false)
+            .expect("Forced transfer should succeed");
+        assert_eq!(report.media_copied, 1);
+        assert_eq!(
+            fs::read(target_media.path().join("tree.png")).unwrap(),
+            b"new"
+        );
+    }
+
+    #[test]
+    fn given_media_path_outside_collection_media_when_transferring_then_skips_it() {
+        // Arrange
+        let (source_media, target_media) = media_dirs();
+        let outside = source_media.path().join("secret.txt");
+        fs::create_dir(source_media.path().join("media")).unwrap();
+        fs::write(&outside, b"secret").unwrap();
+        let source = MockNoteRepository::builder()
+            .with_note(
+                1,
+                note(
+                    1,
+                    &format!(
+                        "<img src=\"../secret.txt\"><img src=\"{}\">",
+                        outside.display()
+                    ),
+                ),
+            )
+            .build();
+        let target = MockNoteRepository::builder().build();
+        let mut transferer = NoteTransferer::new(source, target);
+
+        // Act
+        let report = transferer
+            .transfer(
+                None,
+                &source_media.path().join("media"),
+                target_media.path(),
+                true,
+                false,
+            )
+            .expect("Transfer should succeed");
+
+        // Assert
+        assert_eq!(report.media_copied, 0);
+        assert_eq!(
+            report.media_rejected,
+            vec!["../secret.txt".to_string(), outside.display().to_string()]
+        );
+        assert_eq!(fs::read_dir(target_media.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn given_move_when_transferring_then_deletes_source_notes() {
+        // Arrange
+        let (source_media, target_media) = media_dirs();
+        let source = MockNoteRepository::builder()
+            .with_note(1, note(1, "Q"))
+            .with_delete_success(1, 1)
+            .build();
+        let target = MockNoteRepository::builder().build();
+        let mut transferer = NoteTransferer::new(source, target);
+
+        // Act
+        let report = transferer
+            .transfer(None, source_media.path(), target_media.path(), false, true)
+            .expect("Move should succeed");
+
+        // Assert
+        assert_eq!(report.mapping, vec![(1, 2)]);
+        assert_eq!(report.deleted, 1);
+    }
+}
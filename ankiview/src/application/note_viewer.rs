@@ -1,6 +1,10 @@
 // src/application/note_viewer.rs
-use crate::domain::{DomainError, Note};
+use crate::domain::{
+    CountGroup, DomainError, Flag, Note, NotetypeInfo, ProblemNote, TemplateUpdate, TransferNote,
+};
 use anyhow::Result;
+use std::collections::BTreeMap;
+use std::hash::{BuildHasher, Hasher};
 
 pub trait NoteRepository {
     fn get_note(&mut self, id: i64) -> Result<Note, DomainError>;
@@ -14,10 +18,44 @@ pub trait NoteRepository {
     /// If search_query is Some(query), returns notes matching the query.
     fn list_notes(&mut self, search_query: Option<&str>) -> Result<Vec<Note>, DomainError>;
 
+    /// Notes with cards due today, as (note, number of due cards) pairs,
+    /// filtered by front field content like `list_notes`
+    fn list_due_notes(
+        &mut self,
+        search_query: Option<&str>,
+    ) -> Result<Vec<(Note, usize)>, DomainError>;
+
+    /// Notes matching an Anki search query (all notes if `None`), by ID
+    fn search_notes(&mut self, query: Option<&str>) -> Result<Vec<Note>, DomainError>;
+
+    /// IDs of the notes matching an Anki search query (all notes if `None`),
+    /// ascending, i.e. oldest first
+    fn note_ids(&mut self, query: Option<&str>) -> Result<Vec<i64>, DomainError>;
+
+    /// Number of notes matching an Anki search query (all notes if `None`)
+    fn count_notes(&mut self, query: Option<&str>) -> Result<usize, DomainError>;
+
+    /// Number of matching notes per deck, tag or notetype name
+    fn count_notes_by(
+        &mut self,
+        query: Option<&str>,
+        group: CountGroup,
+    ) -> Result<Vec<(String, usize)>, DomainError>;
+
     /// List all available note types (models) in the collection
     /// Returns a vector of (notetype_id, notetype_name) tuples
     fn list_notetypes(&mut self) -> Result<Vec<(i64, String)>, DomainError>;
 
+    /// Field and card template names of the notetype with this exact name
+    fn notetype_info(&mut self, name: &str) -> Result<NotetypeInfo, DomainError>;
+
+    /// Replace the CSS and/or one card template's HTML of a notetype
+    fn update_notetype_templates(
+        &mut self,
+        name: &str,
+        update: &TemplateUpdate,
+    ) -> Result<(), DomainError>;
+
     /// Add tags to an existing note (merge: existing tags preserved)
     fn add_tags(&mut self, id: i64, tags: &[String]) -> Result<(), DomainError>;
 
@@ -42,6 +80,115 @@ pub trait NoteRepository {
         old_tag: &str,
         new_tag: &str,
     ) -> Result<usize, DomainError>;
+
+    /// Fields of the notes matching an optional query (all notes if None),
+    /// as (note_id, fields) pairs ordered by note ID
+    fn note_fields(&mut self, query: Option<&str>) -> Result<Vec<(i64, Vec<String>)>, DomainError>;
+
+    /// Overwrite the fields of an existing note, keeping its tags
+    fn set_note_fields(&mut self, id: i64, fields: &[String]) -> Result<(), DomainError>;
+
+    /// Move all cards of the notes matching an optional query to `deck`
+    /// (created if missing). Returns the number of cards moved.
+    fn move_notes(&mut self, query: Option<&str>, deck: &str) -> Result<usize, DomainError>;
+
+    /// Set fields by name (field name → value) on the notes matching an optional query.
+    /// Fields the note's notetype doesn't have are ignored.
+    /// Returns the number of notes changed.
+    fn set_fields_by_name(
+        &mut self,
+        query: Option<&str>,
+        fields: &BTreeMap<String, String>,
+    ) -> Result<usize, DomainError>;
+
+    /// Set the flag on all cards of a note (`Flag::None` clears it)
+    /// Returns the number of cards of the note
+    fn set_note_flag(&mut self, id: i64, flag: Flag) -> Result<usize, DomainError>;
+
+    /// Set the flag on a single card (`Flag::None` clears it)
+    fn set_card_flag(&mut self, card_id: i64, flag: Flag) -> Result<(), DomainError>;
+
+    /// List normal (non-filtered) decks as (deck_id, name, cards) tuples,
+    /// counting only the cards directly in each deck (not in its subdecks),
+    /// including cards temporarily in a filtered deck whose home deck it is
+    fn list_decks(&mut self) -> Result<Vec<(i64, String, usize)>, DomainError>;
+
+    /// Delete decks, including their subdecks and any cards in them
+    fn delete_decks(&mut self, ids: &[i64]) -> Result<(), DomainError>;
+
+    /// Rename a deck by its full name; subdecks keep their place under it
+    fn rename_deck(&mut self, old: &str, new: &str) -> Result<(), DomainError>;
+
+    /// Notes tagged `leech` or with a card lapsed at least `min_lapses` times,
+    /// optionally only those with cards in `deck` (or its subdecks)
+    fn problem_notes(
+        &mut self,
+        min_lapses: u32,
+        deck: Option<&str>,
+    ) -> Result<Vec<ProblemNote>, DomainError>;
+
+    /// Copy a note's fields and tags into a new note of the same notetype.
+    /// The copy goes to `deck` or, if None, the deck of the original's first card;
+    /// its cards are new (no scheduling is copied).
+    /// Returns the ID of the new note.
+    fn clone_note(&mut self, id: i64, deck: Option<&str>) -> Result<i64, DomainError>;
+
+    /// Notes matching an Anki search query (all notes if `None`), by ID, with
+    /// their notetype definitions, for adding them to another collection
+    fn transfer_notes(&mut self, query: Option<&str>) -> Result<Vec<TransferNote>, DomainError>;
+
+    /// Add a note read from another collection as a new note: its notetype is
+    /// created if there is none of that name, and its deck if missing. An
+    /// existing notetype of that name must have the same fields.
+    /// Returns the ID of the new note.
+    fn add_transferred_note(&mut self, note: &TransferNote) -> Result<i64, DomainError>;
+}
+
+/// How `view` picks a note when no ID is given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotePick {
+    /// Any matching note, e.g. to spot-check cards after an import
+    Random,
+    /// The most recently added matching note
+    Latest,
+}
+
+impl NotePick {
+    /// Name of the pick in JSON-RPC requests (`pickNote`)
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotePick::Random => "random",
+            NotePick::Latest => "latest",
+        }
+    }
+
+    /// Pick one of `note_ids`, the notes matching `query`
+    pub fn choose(self, note_ids: &[i64], query: Option<&str>) -> Result<i64, DomainError> {
+        let picked = match self {
+            // Note IDs are creation timestamps
+            NotePick::Latest => note_ids.iter().max().copied(),
+            NotePick::Random if note_ids.is_empty() => None,
+            NotePick::Random => Some(note_ids[random_index(note_ids.len())]),
+        };
+        picked.ok_or_else(|| {
+            DomainError::NoMatchingNotes(query.unwrap_or("whole collection").to_string())
+        })
+    }
+}
+
+impl std::str::FromStr for NotePick {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(NotePick::Random),
+            "latest" => Ok(NotePick::Latest),
+            other => Err(format!(
+                "Invalid pick '{}': expected random or latest",
+                other
+            )),
+        }
+    }
 }
 
 pub struct NoteViewer<R: NoteRepository> {
@@ -56,4 +203,72 @@ impl<R: NoteRepository> NoteViewer<R> {
     pub fn view_note(&mut self, note_id: i64) -> Result<Note, DomainError> {
         self.repository.get_note(note_id)
     }
+
+    /// ID of a note matching the Anki search `query` (all notes if `None`)
+    pub fn pick_note_id(
+        &mut self,
+        query: Option<&str>,
+        pick: NotePick,
+    ) -> Result<i64, DomainError> {
+        let note_ids = self.repository.note_ids(query)?;
+        pick.choose(&note_ids, query)
+    }
+}
+
+/// Index below `len`; randomly seeded per call, good enough for picking a card
+fn random_index(len: usize) -> usize {
+    let seed = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    (seed % len as u64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing::MockNoteRepository;
+
+    fn note(id: i64) -> Note {
+        Note {
+            id,
+            front: format!("Question {}", id),
+            back: "Answer".to_string(),
+            tags: vec![],
+            model_name: "Basic".to_string(),
+        }
+    }
+
+    #[test]
+    fn given_notes_when_picking_then_latest_is_newest_and_random_is_one_of_them() {
+        // Arrange
+        let mock = MockNoteRepository::builder()
+            .with_note(1700000000000, note(1700000000000))
+            .with_note(1700000300000, note(1700000300000))
+            .with_note(1700000100000, note(1700000100000))
+            .build();
+        let mut viewer = NoteViewer::new(mock);
+
+        // Act
+        let latest = viewer.pick_note_id(None, NotePick::Latest).unwrap();
+        let random = viewer.pick_note_id(None, NotePick::Random).unwrap();
+
+        // Assert
+        assert_eq!(latest, 1700000300000);
+        assert!([1700000000000, 1700000100000, 1700000300000].contains(&random));
+    }
+
+    #[test]
+    fn given_no_matching_notes_when_picking_then_returns_error() {
+        // Arrange
+        let mut viewer = NoteViewer::new(MockNoteRepository::builder().build());
+
+        // Act
+        let result = viewer.pick_note_id(Some("deck:Empty"), NotePick::Random);
+
+        // Assert
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "No notes match: deck:Empty"
+        );
+    }
 }
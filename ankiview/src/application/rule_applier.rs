@@ -0,0 +1,145 @@
+// src/application/rule_applier.rs
+use crate::application::NoteRepository;
+use crate::domain::{DomainError, Rule};
+
+/// What one rule changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleReport {
+    pub query: String,
+    /// Tags added, summed over notes (a note gaining two tags counts twice)
+    pub tags_added: usize,
+    /// Tags removed, summed over notes
+    pub tags_removed: usize,
+    pub cards_moved: usize,
+    /// Notes with at least one field changed
+    pub fields_set: usize,
+}
+
+pub struct RuleApplier<R: NoteRepository> {
+    repository: R,
+}
+
+impl<R: NoteRepository> RuleApplier<R> {
+    pub fn new(repository: R) -> Self {
+        Self { repository }
+    }
+
+    /// Apply the rules in order; later rules see the changes of earlier ones.
+    /// All rules are validated before any is applied.
+    pub fn apply(&mut self, rules: &[Rule]) -> Result<Vec<RuleReport>, DomainError> {
+        for (index, rule) in rules.iter().enumerate() {
+            if rule.query.trim().is_empty() {
+                return Err(DomainError::ParseError(format!(
+                    "Rule {} has an empty query; use \"deck:*\" to match all notes",
+                    index + 1
+                )));
+            }
+            if !rule.has_actions() {
+                return Err(DomainError::ParseError(format!(
+                    "Rule {} ({}) has no actions",
+                    index + 1,
+                    rule.query
+                )));
+            }
+        }
+
+        let mut reports = Vec::with_capacity(rules.len());
+        for rule in rules {
+            let query = Some(rule.query.as_str());
+            let mut report = RuleReport {
+                query: rule.query.clone(),
+                ..Default::default()
+            };
+
+            for tag in &rule.add_tags {
+                report.tags_added += self.repository.replace_tag(query, "", tag)?;
+            }
+            for tag in &rule.remove_tags {
+                report.tags_removed += self.repository.replace_tag(query, tag, "")?;
+            }
+            if let Some(deck) = &rule.deck {
+                report.cards_moved = self.repository.move_notes(query, deck)?;
+            }
+            if !rule.set_fields.is_empty() {
+                report.fields_set = self
+                    .repository
+                    .set_fields_by_name(query, &rule.set_fields)?;
+            }
+
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Note;
+    use crate::util::testing::MockNoteRepository;
+
+    fn repository() -> MockNoteRepository {
+        let note = |id: i64, tags: &[&str]| Note {
+            id,
+            front: format!("Question {}", id),
+            back: "Answer".to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            model_name: "Basic".to_string(),
+        };
+        MockNoteRepository::builder()
+            .with_note(1, note(1, &["inbox"]))
+            .with_note(2, note(2, &[]))
+            .build()
+    }
+
+    #[test]
+    fn given_rule_when_applying_then_runs_every_action() {
+        // Arrange
+        let mut applier = RuleApplier::new(repository());
+        let rule = Rule {
+            query: "deck:Inbox".to_string(),
+            add_tags: vec!["rust".to_string()],
+            remove_tags: vec!["inbox".to_string()],
+            set_fields: [("Back".to_string(), "Moved".to_string())].into(),
+            ..Default::default()
+        };
+
+        // Act
+        let reports = applier.apply(&[rule]).unwrap();
+
+        // Assert
+        assert_eq!(reports[0].tags_added, 2);
+        assert_eq!(reports[0].tags_removed, 1);
+        assert_eq!(reports[0].fields_set, 2);
+        let note = applier.repository.get_note(1).unwrap();
+        assert_eq!(note.tags, vec!["rust"]);
+        assert_eq!(note.back, "Moved");
+    }
+
+    #[test]
+    fn given_rule_without_actions_when_applying_then_changes_nothing() {
+        // Arrange
+        let mut applier = RuleApplier::new(repository());
+        let rules = [
+            Rule {
+                query: "deck:A".to_string(),
+                add_tags: vec!["a".to_string()],
+                ..Default::default()
+            },
+            Rule {
+                query: "deck:B".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        // Act
+        let result = applier.apply(&rules);
+
+        // Assert
+        match result.expect_err("Should reject the rule") {
+            DomainError::ParseError(message) => assert!(message.contains("Rule 2")),
+            _ => panic!("Expected ParseError"),
+        }
+        assert!(applier.repository.get_note(2).unwrap().tags.is_empty());
+    }
+}
@@ -1,39 +1,111 @@
 // src/args.rs
-use clap::{Parser, Subcommand};
+use crate::domain::{CountGroup, Flag};
+use crate::inka::application::card_collector::{NewPosition, OnDuplicate, SourceFormat};
+use crate::inka::application::occluder::DEFAULT_NOTETYPE;
+use crate::inka::infrastructure::occlusion::{OcclusionMode, Rect};
+use crate::util::line_template::LineTemplate;
+use crate::util::text::TextFormat;
+use clap::{Parser, Subcommand, ValueEnum};
+use regex::Regex;
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)] // Read from `Cargo.toml`
 #[command(arg_required_else_help = true, disable_help_subcommand = true)]
 pub struct Args {
-    /// Path to Anki collection file (optional)
+    /// Path to Anki collection file (optional; repeat for list, count and review-stats
+    /// across several collections)
     #[arg(short, long, value_name = "COLLECTION", global = true)]
-    pub collection: Option<PathBuf>,
+    pub collection: Vec<PathBuf>,
 
-    /// Profile name (optional)
+    /// Profile name (optional; repeatable like --collection)
     #[arg(short, long, value_name = "PROFILE", global = true)]
-    pub profile: Option<String>,
+    pub profile: Vec<String>,
 
     /// Verbosity level (-v = debug, -vv = trace)
     #[arg(short, long, action = clap::ArgAction::Count, global = true)]
     pub verbose: u8,
 
+    /// Suppress informational output (errors and requested data are still printed)
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Also write logs to this file (rotated daily, e.g. ankiview.log.2024-01-31)
+    #[arg(long, value_name = "PATH", global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// Log output format: human-readable text or one JSON object per line
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = LogFormat::Text, global = true)]
+    pub log_format: LogFormat,
+
+    /// Print errors to stderr as JSON objects (kind, exit_code, message, causes)
+    #[arg(long, global = true)]
+    pub json_errors: bool,
+
+    /// Config file (default: nearest ankiview.toml in the collected directory or its parents)
+    #[arg(long, value_name = "PATH", global = true)]
+    pub config: Option<PathBuf>,
+
     /// Subcommand to execute (view, delete, or list)
     #[command(subcommand)]
     pub command: Command,
 }
 
+/// Format of tracing output (console and log file)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines (default)
+    Text,
+    /// Newline-delimited JSON with event fields (file, note_id, action) at the top level
+    Json,
+}
+
 #[derive(Subcommand, Debug, Clone)]
 pub enum Command {
     /// View a note in the browser
     View {
-        /// Note ID to view
-        #[arg(value_name = "NOTE_ID")]
-        note_id: i64,
+        /// Note ID to view, or a note link (ankiview://view/NOTE_ID) from a card
+        #[arg(
+            value_name = "NOTE_ID",
+            value_parser = parse_note_reference,
+            required_unless_present_any = ["random", "latest"]
+        )]
+        note_id: Option<i64>,
+
+        /// View a random note instead, e.g. to spot-check cards after an import
+        #[arg(long, conflicts_with_all = ["note_id", "latest", "in_anki"])]
+        random: bool,
+
+        /// View the most recently added note instead
+        #[arg(long, conflicts_with_all = ["note_id", "in_anki"])]
+        latest: bool,
+
+        /// Anki search query limiting --random and --latest (e.g. "deck:Spanish")
+        #[arg(long, value_name = "QUERY", conflicts_with = "note_id")]
+        query: Option<String>,
 
         /// Output note as JSON instead of opening in browser
         #[arg(long)]
         json: bool,
+
+        /// Show the note in Anki's own browse window instead (for editing there).
+        /// Needs a running Anki with the AnkiConnect add-on.
+        #[arg(long, conflicts_with = "json")]
+        in_anki: bool,
+
+        /// Command opening the rendered HTML, e.g. "firefox" or "chromium --new-window %s"
+        /// (default: viewer.browser in ankiview.toml, then $BROWSER, then the system opener)
+        #[arg(long, value_name = "CMD", conflicts_with_all = ["json", "in_anki"])]
+        browser: Option<String>,
+
+        /// Print the rendered HTML page to stdout instead of opening it (headless systems)
+        #[arg(long, conflicts_with_all = ["json", "in_anki", "browser"])]
+        stdout: bool,
+
+        /// Strip scripts, styles, iframes and javascript: links from the fields first
+        /// (for notes from untrusted shared decks; see also viewer.sanitize)
+        #[arg(long, conflicts_with_all = ["json", "in_anki"])]
+        sanitize: bool,
     },
 
     /// Delete a note from the collection
@@ -43,11 +115,163 @@ pub enum Command {
         note_id: i64,
     },
 
+    /// Show what collect and delete changed on a note, oldest first
+    ///
+    /// Only changes made while `[history] enabled = true` is set in ankiview.toml are journaled.
+    History {
+        /// Note ID to show the history of
+        #[arg(value_name = "NOTE_ID")]
+        note_id: i64,
+
+        /// Print the journal entries as JSON Lines
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Copy a note's fields and tags into a new note and print its ID
+    ///
+    /// The copy has the same note type; its cards are new, without the original's scheduling.
+    Clone {
+        /// Note ID to copy
+        #[arg(value_name = "NOTE_ID")]
+        note_id: i64,
+
+        /// Deck for the copy (default: the original note's deck)
+        #[arg(long, value_name = "NAME")]
+        deck: Option<String>,
+    },
+
+    /// Copy notes from one collection into another and print "OLD_ID<TAB>NEW_ID" lines
+    ///
+    /// Missing note types and decks are created in the target; referenced media files
+    /// are copied. Cards are new, without the original's scheduling.
+    /// Example: ankiview transfer --from a/collection.anki2 --to b/collection.anki2 --query "deck:Shared"
+    Transfer {
+        /// Collection file to copy notes from
+        #[arg(long, value_name = "PATH")]
+        from: PathBuf,
+
+        /// Collection file to add the notes to
+        #[arg(long, value_name = "PATH")]
+        to: PathBuf,
+
+        /// Anki search query selecting the notes (default: all notes)
+        #[arg(long, value_name = "QUERY")]
+        query: Option<String>,
+
+        /// Overwrite different media files of the same name in the target
+        #[arg(long)]
+        force: bool,
+
+        /// Delete the notes from the source collection once all are copied
+        #[arg(long = "move")]
+        move_notes: bool,
+    },
+
+    /// Set or clear the colored flag on a note's cards
+    Flag {
+        /// Note ID (or card ID with --card)
+        #[arg(value_name = "ID")]
+        id: i64,
+
+        /// ID is a card ID (as in the review log), so only that card is flagged
+        #[arg(long)]
+        card: bool,
+
+        #[command(flatten)]
+        color: FlagColor,
+    },
+
+    /// Mark a note (Anki's `marked` tag), or unmark it with --clear
+    Mark {
+        /// Note ID
+        #[arg(value_name = "NOTE_ID")]
+        note_id: i64,
+
+        /// Remove the mark instead
+        #[arg(long)]
+        clear: bool,
+    },
+
     /// List notes with ID and first line of front field
     List {
         /// Optional search term to filter notes by front field content
         #[arg(value_name = "SEARCH")]
         search: Option<String>,
+
+        /// Stop after printing N notes
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+
+        /// Only notes with cards due today, with their due card counts
+        #[arg(long, conflicts_with = "template")]
+        due: bool,
+
+        /// Print each note as TEMPLATE, e.g. "{id}\t{deck}\t{front:60}"
+        ///
+        /// Placeholders: {id}, {guid}, {notetype}, {deck}, {tags}, {front}, {back},
+        /// {created}, {modified} or a field name. {name:N} truncates to N characters,
+        /// {deck|SEP} and {tags|SEP} join with SEP; {{ and }} are literal braces.
+        #[arg(long, value_name = "TEMPLATE", conflicts_with = "due")]
+        template: Option<LineTemplate>,
+    },
+
+    /// Pick notes from a fuzzy-searchable list and print their IDs
+    ///
+    /// The list is drawn on stderr, so the IDs can be captured:
+    /// `ankiview view $(ankiview pick)`.
+    Pick {
+        /// Anki search query (e.g. "deck:Rust"); lists all notes if omitted
+        #[arg(long, value_name = "QUERY")]
+        query: Option<String>,
+
+        /// Keep picking until Esc and print one ID per line
+        #[arg(long)]
+        multi: bool,
+    },
+
+    /// Count the notes matching an Anki search query
+    ///
+    /// Prints just the number, or one `name<TAB>count` row per group with --by.
+    Count {
+        /// Anki search query (e.g. "tag:rust is:new"); counts all notes if omitted
+        #[arg(long, value_name = "QUERY")]
+        query: Option<String>,
+
+        /// Count per deck, tag or notetype instead of in total
+        #[arg(long, value_name = "deck|tag|notetype")]
+        by: Option<CountGroup>,
+    },
+
+    /// Export notes as front/back/tags TSV for Quizlet or Anki's text importer
+    ///
+    /// Fields with tabs, line breaks or quotes are quoted as Anki's importer expects.
+    ExportTsv {
+        /// Anki search query (e.g. "deck:Rust"); exports all notes if omitted
+        #[arg(long, value_name = "QUERY")]
+        query: Option<String>,
+
+        /// File to write; prints to stdout if omitted
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+
+        /// Keep field HTML, or convert it to plain text or markdown
+        #[arg(long, value_name = "html|text|markdown", default_value = "html")]
+        format: TextFormat,
+    },
+
+    /// Stream every note as one JSON object per line
+    ///
+    /// Each line has the note's ID, GUID, notetype, fields by name, tags, decks and
+    /// creation/modification times; notes are read one at a time.
+    ExportJsonl {
+        /// Anki search query (e.g. "deck:Rust"); exports all notes if omitted
+        #[arg(long, value_name = "QUERY")]
+        query: Option<String>,
+
+        /// File to write; prints to stdout if omitted
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
     },
 
     /// Collect markdown cards into Anki
@@ -89,12 +313,175 @@ pub enum Command {
         #[arg(short = 'u', long)]
         update_ids: bool,
 
+        /// With --update-ids, only link notes in the card's target deck.
+        /// Matches in other decks are reported as warnings and a new note is created;
+        /// without this flag they are linked anyway, with a warning.
+        #[arg(long, requires = "update_ids")]
+        same_deck: bool,
+
+        /// When a note's cards are in another deck than its section's `Deck:` line
+        /// (or --deck) names, move them there while updating the note.
+        /// Without this flag, updates leave cards where they are. Moves are listed in the summary.
+        #[arg(long)]
+        move_on_deck_change: bool,
+
         /// Card type (notetype) to use when creating notes.
         /// Specify exact notetype name (e.g., "Basic", "Inka Basic").
         /// Defaults to "Inka Basic" if not specified.
         /// Use 'list-card-types' command to see available card types.
         #[arg(long, value_name = "TYPE")]
         card_type: Option<String>,
+
+        /// Print how long each phase took (hash, parse, convert, media, repository, write, cache save).
+        /// Useful when reporting slow collect runs; use -v to also see per-phase tracing spans.
+        #[arg(long)]
+        timings: bool,
+
+        /// Treat warnings (orphaned or duplicate IDs, images without alt text,
+        /// unmapped media, sections without cards) as errors.
+        /// The offending file is left untouched and the command exits non-zero; for CI.
+        #[arg(long)]
+        strict: bool,
+
+        /// Don't change anything; list files whose cards would be created or updated
+        /// and exit non-zero if there are any. For pre-commit hooks and CI.
+        #[arg(long)]
+        check: bool,
+
+        /// Put new notes into this deck, ignoring the `Deck:` lines in markdown.
+        /// Handy for trialing a vault in a scratch deck. Existing notes stay
+        /// where they are, with a warning, unless --move-on-deck-change is given.
+        #[arg(long, value_name = "NAME")]
+        deck: Option<String>,
+
+        /// Prepend a namespace to every deck name, e.g. "Imported::" turns
+        /// "Rust" into "Imported::Rust". Combines with --deck.
+        #[arg(long, value_name = "PREFIX")]
+        deck_prefix: Option<String>,
+
+        /// Add TAG to every created or updated note, besides the section's `Tags:`.
+        /// Repeatable, e.g. --tag source::vault --tag sync::2024-06-01.
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+
+        /// Make the tags of updated notes follow the markdown: tags removed from a `Tags:` line
+        /// (or --tag) are removed from the note too. Applied tags are tracked with
+        /// ankiview::synced::<tag> tags; tags added in Anki are never removed.
+        #[arg(long)]
+        sync_tags: bool,
+
+        /// Put every tag collect applies into a namespace, e.g. "inka" turns `rust` into
+        /// `inka::rust`. With --sync-tags, all tags in the namespace are managed by collect,
+        /// so tags added in Anki outside it are never touched. Overrides defaults.tag_namespace.
+        #[arg(long, value_name = "NAMESPACE")]
+        tag_namespace: Option<String>,
+
+        /// Where the cards of newly created notes go in the new-card queue:
+        /// start (before existing new cards), end (Anki's default), or due position N.
+        /// Existing new cards are shifted back to make room.
+        #[arg(long, value_name = "start|end|N")]
+        new_position: Option<NewPosition>,
+
+        /// Before creating a note for a card without ID, look for a note in the target deck
+        /// that Anki would flag as a duplicate (same notetype, same first field) and warn.
+        /// create: create the note anyway; skip: leave the card alone; update: update that
+        /// note and write its ID into the markdown. Catches duplicates of cards that never had IDs.
+        #[arg(long, value_name = "create|skip|update")]
+        on_duplicate: Option<OnDuplicate>,
+
+        /// Source syntax: md (inka markdown sections) or org (org-mode headlines
+        /// tagged :drill: or :anki:). Directories are scanned for .md or .org files.
+        #[arg(long, value_name = "md|org", default_value = "md")]
+        format: SourceFormat,
+
+        /// Also create or update one index note per markdown file, tagged ankiview-index,
+        /// linking (nid:ID) to every card generated from the file
+        #[arg(long)]
+        index_notes: bool,
+
+        /// When a card's note was deleted and is recreated, copy the scheduling, flag and FSRS
+        /// memory state of its cards from this older copy of the collection (e.g. an unpacked
+        /// backup); recreated notes without it are tagged 'reimported'. The review log isn't
+        /// copied, so the cards' review history starts over
+        #[arg(long, value_name = "COLLECTION")]
+        restore_from: Option<PathBuf>,
+
+        /// Never ask how to resolve conflicts (orphaned IDs, cards matching several notes with
+        /// --update-ids, media name clashes); resolve them like without a terminal: recreate the
+        /// note, use the first match, fail on the media file. Implied when stdin isn't a terminal.
+        #[arg(long)]
+        non_interactive: bool,
+
+        /// Check cards against the `[lint]` rules of the config file and report the ones
+        /// breaking them as warnings (errors with --strict)
+        #[arg(long)]
+        lint: bool,
+
+        /// CI mode: no prompts or colors; errors and warnings are printed as GitHub Actions
+        /// annotations (`::error file=...::message`), followed by a one-line summary
+        #[arg(long)]
+        ci: bool,
+    },
+
+    /// Create Image Occlusion Enhanced notes from an image and rectangles
+    ///
+    /// Every rectangle becomes one note that asks for what it covers. The image
+    /// and the generated mask SVGs are copied into collection.media/. Needs the
+    /// notetype of the Image Occlusion Enhanced add-on (or one with its fields).
+    Occlude {
+        /// Image to occlude (PNG or JPEG)
+        #[arg(value_name = "IMAGE")]
+        image: PathBuf,
+
+        /// Rectangle to hide, in image pixels; repeat for every region
+        #[arg(long = "rect", value_name = "X,Y,WIDTH,HEIGHT", required = true)]
+        rects: Vec<Rect>,
+
+        /// hide-all: other regions stay covered; hide-one: only the asked region is
+        #[arg(long, value_name = "hide-all|hide-one", default_value = "hide-all")]
+        mode: OcclusionMode,
+
+        /// Deck for the new notes
+        #[arg(long, value_name = "NAME", default_value = "Default")]
+        deck: String,
+
+        /// Text for the Header field
+        #[arg(long, value_name = "TEXT")]
+        header: Option<String>,
+
+        /// Add TAG to the new notes; repeatable
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+
+        /// Notetype to use
+        #[arg(long, value_name = "NAME", default_value = DEFAULT_NOTETYPE)]
+        notetype: String,
+    },
+
+    /// Create notes from a markdown template with placeholders
+    ///
+    /// Templates live in ~/.config/ankiview/templates/NAME.md and are written like a section of
+    /// collected markdown, with {{placeholder}}s filled from --var.
+    Add {
+        /// Template name (file name without .md)
+        #[arg(long, value_name = "NAME")]
+        template: String,
+
+        /// Value for a placeholder; repeat for every placeholder
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var)]
+        vars: Vec<(String, String)>,
+
+        /// Deck for the new notes, overriding the template's Deck: line
+        #[arg(long, value_name = "NAME")]
+        deck: Option<String>,
+
+        /// Add TAG to the new notes; repeatable
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+
+        /// Notetype for question/answer cards (default: Inka Basic)
+        #[arg(long, value_name = "NAME")]
+        card_type: Option<String>,
     },
 
     /// List available card types (notetypes) in the collection
@@ -103,6 +490,95 @@ pub enum Command {
     /// Each card type defines the fields and card templates for flashcards.
     ListCardTypes,
 
+    /// Show the field names and card templates of a notetype with their ordinals
+    Fields {
+        /// Exact notetype name (see list-card-types)
+        #[arg(value_name = "NOTETYPE")]
+        notetype: String,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List decks without cards (e.g. left over from typos in `Deck:` lines)
+    ///
+    /// A deck counts as empty if neither it nor any of its subdecks has cards,
+    /// including cards that are in a filtered deck for now.
+    /// The Default deck and filtered decks are never listed.
+    EmptyDecks {
+        /// Delete the listed decks
+        #[arg(long)]
+        delete: bool,
+    },
+
+    /// List notes collected from markdown whose cards are gone from it
+    ///
+    /// Looks at the `File:` footer of every note: a note whose file is under DIR
+    /// but was deleted, or no longer contains the note's ID, is listed. Relative
+    /// footer paths are resolved from the current directory, so run this from
+    /// where you run `collect`. The counterpart of orphaned IDs in markdown.
+    Orphans {
+        /// Vault directory the notes were collected from
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+
+        /// Delete the listed notes, after asking for confirmation
+        #[arg(long)]
+        delete: bool,
+
+        /// Delete without asking (with --delete)
+        #[arg(long, short = 'y', requires = "delete")]
+        yes: bool,
+    },
+
+    /// Rename a deck; its subdecks and cards move along
+    RenameDeck {
+        /// Current full deck name, e.g. "Old::Name"
+        #[arg(value_name = "OLD")]
+        old: String,
+
+        /// New full deck name, e.g. "New::Name"
+        #[arg(value_name = "NEW")]
+        new: String,
+    },
+
+    /// List problem notes: tagged `leech` or with often-lapsed cards
+    ///
+    /// Shows note ID, lapses, deck and first line, most lapses first, so the
+    /// cards can be rewritten in the markdown (find them by their ID comment).
+    Leeches {
+        /// Also list untagged notes with a card lapsed at least N times
+        #[arg(long, value_name = "N", default_value_t = 8)]
+        min_lapses: u32,
+
+        /// Only notes with cards in this deck and its subdecks
+        #[arg(long, value_name = "NAME")]
+        deck: Option<String>,
+
+        /// Output the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Summarize recent reviews: reviews per day, retention and answer time
+    ///
+    /// Reads the review log directly. Days start at the collection's "Next day
+    /// starts at" hour in the time zone Anki last ran in, as in Anki's stats.
+    ReviewStats {
+        /// Only count cards of this deck and its subdecks
+        #[arg(long, value_name = "NAME")]
+        deck: Option<String>,
+
+        /// Number of days to look back, including today
+        #[arg(long, value_name = "N", default_value_t = 30)]
+        days: u32,
+
+        /// Output the statistics as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Manage tags on notes
     ///
     /// Add, remove, or replace tags on individual notes or across the collection.
@@ -111,6 +587,58 @@ pub enum Command {
         subcommand: TagCommand,
     },
 
+    /// Manage notetype styling
+    ///
+    /// Keep a notetype's CSS and card templates in files next to the markdown
+    /// and push them into the collection.
+    Notetype {
+        #[command(subcommand)]
+        subcommand: NotetypeCommand,
+    },
+
+    /// Maintain the hash cache collect uses to skip unchanged files
+    ///
+    /// Files are recognized by content, so a moved file keeps its entry; use
+    /// `prune` to drop the entries of deleted files.
+    Cache {
+        #[command(subcommand)]
+        subcommand: CacheCommand,
+    },
+
+    /// Find and replace text across the fields of many notes
+    ///
+    /// FIND is a regular expression matched against the stored field HTML;
+    /// WITH may refer to its capture groups as $1 or ${name}.
+    /// Prints every changed field; use --dry-run to preview without saving.
+    Replace {
+        /// Anki search query selecting the notes (default: all notes), e.g. "deck:CS"
+        #[arg(long)]
+        query: Option<String>,
+
+        /// Regular expression to search for
+        #[arg(long, value_name = "REGEX", value_parser = parse_regex)]
+        find: Regex,
+
+        /// Replacement text
+        #[arg(long = "with", value_name = "TEXT")]
+        replacement: String,
+
+        /// Show what would change without modifying any note
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Apply the maintenance rules of a TOML file
+    ///
+    /// Each `[[rule]]` selects notes with an Anki search `query` and adds or
+    /// removes tags, moves their cards to a deck, or sets fields by name.
+    /// Rules run in file order.
+    ApplyRules {
+        /// Rules file (TOML)
+        #[arg(value_name = "RULES")]
+        path: PathBuf,
+    },
+
     /// Edit a note in your $EDITOR
     ///
     /// Opens the note in a structured template showing all fields and tags.
@@ -120,6 +648,205 @@ pub enum Command {
         #[arg(value_name = "NOTE_ID")]
         note_id: i64,
     },
+
+    /// Preview a markdown file's cards in the browser before collecting them
+    ///
+    /// Converts every card like `collect` would (same config) without opening the collection
+    /// and serves the result on localhost. The page reloads whenever the file is saved.
+    Preview {
+        /// Markdown file to preview
+        #[arg(value_name = "FILE")]
+        path: PathBuf,
+
+        /// Port to listen on (0 picks a free one)
+        #[arg(long, default_value_t = 8777)]
+        port: u16,
+    },
+
+    /// Show how a markdown file's cards are parsed and converted (debugging aid)
+    ///
+    /// Prints each card's source, parsed field markdown, generated HTML and image mapping,
+    /// without opening the collection.
+    Convert {
+        /// Markdown file to convert
+        #[arg(value_name = "FILE")]
+        path: PathBuf,
+
+        /// Only show card N (1-based, in file order)
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        card: Option<u32>,
+    },
+
+    /// Check markdown cards against quality rules, without opening the collection
+    ///
+    /// Rules (configured in the `[lint]` section of ankiview.toml): front-length,
+    /// empty-back, image-alt, whole-cloze and todo. Prints `path:line: [rule] message`
+    /// per issue and exits non-zero if there are any.
+    Lint {
+        /// Markdown file or directory containing .md files
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+
+        /// Also check subdirectories
+        #[arg(short, long)]
+        recursive: bool,
+    },
+
+    /// Summarize the cards of a markdown vault, without opening the collection
+    ///
+    /// Counts cards per file and per deck, basic vs cloze, and the average
+    /// answer length of basic cards. Subdirectories are included.
+    VaultStats {
+        /// Directory containing .md files (or a single markdown file)
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+
+        /// Output the statistics as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Serve JSON-RPC requests over stdin/stdout (for editor integrations)
+    ///
+    /// Reads one JSON-RPC 2.0 request per line and writes one response per line.
+    /// The collection stays open between requests until stdin closes or `shutdown` is sent.
+    /// Methods: viewNote, listNotes, listCardTypes, collectFile, status, shutdown.
+    Serve,
+
+    /// Run a background daemon that keeps the collection open
+    ///
+    /// While the daemon runs, `view` and `list` are answered through its socket
+    /// instead of opening the collection. Other commands (and Anki itself) need the
+    /// daemon to be stopped first, since it holds the collection lock.
+    Daemon {
+        /// Socket path (defaults to ankiview.sock next to the collection). Another
+        /// path is recorded in ankiview.sock.path next to the collection, where
+        /// `view`, `list` and `daemon --stop` look it up
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+
+        /// Stop a running daemon instead of starting one
+        #[arg(long)]
+        stop: bool,
+    },
+}
+
+/// Flag color switches for `flag`; exactly one must be given
+#[derive(clap::Args, Debug, Clone)]
+#[group(required = true, multiple = false)]
+pub struct FlagColor {
+    #[arg(long)]
+    red: bool,
+    #[arg(long)]
+    orange: bool,
+    #[arg(long)]
+    green: bool,
+    #[arg(long)]
+    blue: bool,
+    #[arg(long)]
+    pink: bool,
+    #[arg(long)]
+    turquoise: bool,
+    #[arg(long)]
+    purple: bool,
+    /// Remove the flag
+    #[arg(long)]
+    clear: bool,
+}
+
+impl FlagColor {
+    pub fn flag(&self) -> Flag {
+        [
+            (self.red, Flag::Red),
+            (self.orange, Flag::Orange),
+            (self.green, Flag::Green),
+            (self.blue, Flag::Blue),
+            (self.pink, Flag::Pink),
+            (self.turquoise, Flag::Turquoise),
+            (self.purple, Flag::Purple),
+        ]
+        .into_iter()
+        .find_map(|(set, flag)| set.then_some(flag))
+        .unwrap_or(Flag::None)
+    }
+}
+
+/// Note ID, also accepted as a link between notes (`ankiview://view/123`)
+/// so the OS can hand clicked card links to `ankiview view`
+fn parse_note_reference(value: &str) -> Result<i64, String> {
+    let id = value
+        .strip_prefix(crate::inka::infrastructure::markdown::converter::NOTE_LINK_PREFIX)
+        .unwrap_or(value)
+        .trim_end_matches('/');
+    id.parse()
+        .map_err(|_| format!("'{}' is not a note ID or ankiview://view/ link", value))
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum NotetypeCommand {
+    /// Replace a notetype's CSS and/or card template HTML with file contents
+    ///
+    /// Parts without a file are left unchanged.
+    Push {
+        /// Exact notetype name (see list-card-types)
+        #[arg(value_name = "NOTETYPE")]
+        name: String,
+
+        /// Stylesheet shared by all card templates
+        #[arg(long, value_name = "FILE")]
+        css: Option<PathBuf>,
+
+        /// Front (question) template HTML
+        #[arg(long, value_name = "FILE")]
+        front: Option<PathBuf>,
+
+        /// Back (answer) template HTML
+        #[arg(long, value_name = "FILE")]
+        back: Option<PathBuf>,
+
+        /// Card template to update with --front/--back (default: the first)
+        #[arg(long, value_name = "NAME")]
+        template: Option<String>,
+
+        /// Define the `[math] macros` of the config file at the top of every
+        /// card template, replacing earlier ones (none configured: remove them)
+        #[arg(long)]
+        math_macros: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CacheCommand {
+    /// List cached files with (the start of) their content hash
+    ///
+    /// Files that no longer exist are marked `(deleted)`; `prune` removes them.
+    /// The cache is only read: without one nothing is created or migrated.
+    Show {
+        /// Output all entries as a JSON object (path -> full hash)
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print the location of the cache file
+    Path,
+
+    /// Remove the entries of files that no longer exist
+    Prune,
+
+    /// Remove all entries; the next collect processes every file
+    Clear,
+}
+
+fn parse_regex(value: &str) -> Result<Regex, String> {
+    Regex::new(value).map_err(|e| e.to_string())
+}
+
+fn parse_var(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(name, value)| (name.trim().to_string(), value.to_string()))
+        .filter(|(name, _)| !name.is_empty())
+        .ok_or_else(|| format!("'{}' is not NAME=VALUE", value))
 }
 
 #[derive(Subcommand, Debug, Clone)]
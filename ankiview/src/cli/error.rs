@@ -0,0 +1,226 @@
+// src/cli/error.rs
+//
+// Maps command failures to stable exit codes and renders them for stderr,
+// so scripts can branch on the outcome without matching error strings.
+use crate::domain::DomainError;
+use serde_json::json;
+
+/// Category of a failed command. Each kind has a distinct process exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Any failure without a more specific category
+    General,
+    /// Note, collection file or input path does not exist
+    NotFound,
+    /// Collection is locked by a running Anki (or another process)
+    AnkiRunning,
+    /// Markdown card or edit template could not be parsed (or failed `--strict` checks)
+    ParseError,
+    /// Media file could not be copied into collection.media/
+    MediaError,
+    /// Invalid command-line usage
+    Usage,
+}
+
+impl ErrorKind {
+    /// Process exit code for this kind.
+    pub fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::General => 1,
+            ErrorKind::NotFound => 2,
+            ErrorKind::AnkiRunning => 3,
+            ErrorKind::ParseError => 4,
+            ErrorKind::MediaError => 5,
+            // EX_USAGE from sysexits.h; clap's default (2) would clash with NotFound
+            ErrorKind::Usage => 64,
+        }
+    }
+
+    /// Stable identifier used in `--json-errors` output.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::General => "general",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::AnkiRunning => "anki_running",
+            ErrorKind::ParseError => "parse_error",
+            ErrorKind::MediaError => "media_error",
+            ErrorKind::Usage => "usage",
+        }
+    }
+
+    /// Inverse of [`ErrorKind::as_str`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            ErrorKind::General,
+            ErrorKind::NotFound,
+            ErrorKind::AnkiRunning,
+            ErrorKind::ParseError,
+            ErrorKind::MediaError,
+            ErrorKind::Usage,
+        ]
+        .into_iter()
+        .find(|kind| kind.as_str() == name)
+    }
+
+    /// Classify an error by the first `DomainError` found in its chain.
+    pub fn classify(err: &anyhow::Error) -> Self {
+        if let Some(remote) = err.chain().find_map(|c| c.downcast_ref::<RemoteError>()) {
+            return remote.kind;
+        }
+
+        let domain_error = err.downcast_ref::<DomainError>().or_else(|| {
+            err.chain()
+                .find_map(|cause| cause.downcast_ref::<DomainError>())
+        });
+
+        match domain_error {
+            Some(DomainError::NoteNotFound(_))
+            | Some(DomainError::NoMatchingNotes(_))
+            | Some(DomainError::DeckNotFound(_))
+            | Some(DomainError::NotetypeNotFound(_))
+            | Some(DomainError::CollectionNotFound(_))
+            | Some(DomainError::PathNotFound(_)) => ErrorKind::NotFound,
+            Some(DomainError::CollectionLocked(_)) => ErrorKind::AnkiRunning,
+            Some(DomainError::ParseError(_)) | Some(DomainError::StrictWarning(_)) => {
+                ErrorKind::ParseError
+            }
+            Some(DomainError::MediaError(_)) => ErrorKind::MediaError,
+            _ if crate::util::lock::is_sqlite_busy_error(err) => ErrorKind::AnkiRunning,
+            _ => ErrorKind::General,
+        }
+    }
+}
+
+/// Error that was already classified elsewhere, e.g. relayed from `ankiview daemon`.
+#[derive(Debug)]
+pub struct RemoteError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl std::fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for RemoteError {}
+
+/// Render an error as a single-line JSON object for `--json-errors`.
+///
+/// `message` is the outermost error; `causes` lists the underlying errors in order.
+pub fn error_to_json(err: &anyhow::Error, kind: ErrorKind) -> String {
+    let causes: Vec<String> = err.chain().skip(1).map(|c| c.to_string()).collect();
+    json!({
+        "error": {
+            "kind": kind.as_str(),
+            "exit_code": kind.exit_code(),
+            "message": err.to_string(),
+            "causes": causes,
+        }
+    })
+    .to_string()
+}
+
+/// Print an error to stderr (plain or JSON) and return its kind.
+pub fn report_error(err: &anyhow::Error, json_errors: bool) -> ErrorKind {
+    let kind = ErrorKind::classify(err);
+    if json_errors {
+        eprintln!("{}", error_to_json(err, kind));
+    } else {
+        eprintln!("Error: {:?}", err);
+    }
+    kind
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn given_note_not_found_when_classifying_then_not_found() {
+        let err: anyhow::Error = DomainError::NoteNotFound(42).into();
+
+        assert_eq!(ErrorKind::classify(&err), ErrorKind::NotFound);
+        assert_eq!(ErrorKind::classify(&err).exit_code(), 2);
+    }
+
+    #[test]
+    fn given_wrapped_domain_error_when_classifying_then_finds_inner_kind() {
+        let err = Err::<(), _>(DomainError::NoteNotFound(42))
+            .context("Failed to delete note 42")
+            .unwrap_err();
+
+        assert_eq!(ErrorKind::classify(&err), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn given_domain_error_as_context_when_classifying_then_uses_context_kind() {
+        let err = anyhow::anyhow!("No such file or directory")
+            .context(DomainError::MediaError(
+                "Failed to copy media file 'a.png'".into(),
+            ))
+            .context("Failed to process file");
+
+        assert_eq!(ErrorKind::classify(&err), ErrorKind::MediaError);
+    }
+
+    #[test]
+    fn given_locked_collection_when_classifying_then_anki_running() {
+        let err: anyhow::Error = DomainError::CollectionLocked("locked".into()).into();
+
+        assert_eq!(ErrorKind::classify(&err).exit_code(), 3);
+    }
+
+    #[test]
+    fn given_parse_error_when_classifying_then_parse_error() {
+        let err: anyhow::Error = DomainError::ParseError("bad card".into()).into();
+
+        assert_eq!(ErrorKind::classify(&err).exit_code(), 4);
+    }
+
+    #[test]
+    fn given_plain_error_when_classifying_then_general() {
+        let err = anyhow::anyhow!("something went wrong");
+
+        assert_eq!(ErrorKind::classify(&err), ErrorKind::General);
+        assert_eq!(ErrorKind::classify(&err).exit_code(), 1);
+    }
+
+    #[test]
+    fn given_remote_error_when_classifying_then_keeps_relayed_kind() {
+        let err: anyhow::Error = RemoteError {
+            kind: ErrorKind::NotFound,
+            message: "Note not found: 1".to_string(),
+        }
+        .into();
+
+        assert_eq!(ErrorKind::classify(&err), ErrorKind::NotFound);
+        assert_eq!(err.to_string(), "Note not found: 1");
+    }
+
+    #[test]
+    fn given_kind_name_when_parsing_then_round_trips() {
+        assert_eq!(
+            ErrorKind::from_name(ErrorKind::AnkiRunning.as_str()),
+            Some(ErrorKind::AnkiRunning)
+        );
+        assert_eq!(ErrorKind::from_name("bogus"), None);
+    }
+
+    #[test]
+    fn given_error_with_context_when_rendering_json_then_includes_kind_message_and_causes() {
+        let err = Err::<(), _>(DomainError::NoteNotFound(7))
+            .context("Failed to delete note 7")
+            .unwrap_err();
+
+        let output = error_to_json(&err, ErrorKind::classify(&err));
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+        assert_eq!(value["error"]["kind"], "not_found");
+        assert_eq!(value["error"]["exit_code"], 2);
+        assert_eq!(value["error"]["message"], "Failed to delete note 7");
+        assert_eq!(value["error"]["causes"][0], "Note not found: 7");
+    }
+}
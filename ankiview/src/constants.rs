@@ -19,11 +19,20 @@ pub const ID_SEARCH_RANGE_BEFORE: usize = 50;
 /// Used in: `inka/infrastructure/file_writer.rs`
 pub const ID_SEARCH_RANGE_AFTER: usize = 100;
 
-/// Delay in milliseconds after writing HTML file before opening browser.
+/// Default delay in milliseconds after the browser command returned before the
+/// temporary HTML file may be removed.
 ///
-/// On macOS, the browser needs a brief moment for the file to be fully written
-/// and indexed before opening. Without this delay, the browser may open an empty
-/// or incomplete file.
+/// Openers like `open` and `xdg-open` hand the file to a running browser and exit
+/// before it has been read. Without this delay, the browser may open an empty
+/// or missing file. Configurable with `viewer.launch_delay_ms`.
 ///
 /// Used in: `infrastructure/renderer.rs`
 pub const BROWSER_LAUNCH_DELAY_MS: u64 = 500;
+
+/// Days a rendered note stays in `viewer.output_dir` without being viewed again.
+///
+/// Rendering a note refreshes its file; older files are removed whenever `view`
+/// writes to the directory. Configurable with `viewer.keep_days`.
+///
+/// Used in: `infrastructure/renderer.rs`
+pub const RENDERED_NOTE_KEEP_DAYS: u64 = 30;
@@ -0,0 +1,29 @@
+// src/domain/count_group.rs
+
+/// What `count --by` groups the matching notes by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountGroup {
+    /// Deck of the note's cards; a note with cards in several decks counts in each
+    Deck,
+    /// Each tag of the note; untagged notes are grouped under `UNTAGGED`
+    Tag,
+    Notetype,
+}
+
+impl CountGroup {
+    /// Group name for notes without tags
+    pub const UNTAGGED: &'static str = "(untagged)";
+}
+
+impl std::str::FromStr for CountGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "deck" => Ok(Self::Deck),
+            "tag" => Ok(Self::Tag),
+            "notetype" => Ok(Self::Notetype),
+            _ => Err(format!("'{}' is not deck, tag or notetype", s)),
+        }
+    }
+}
@@ -5,8 +5,28 @@ use thiserror::Error;
 pub enum DomainError {
     #[error("Note not found: {0}")]
     NoteNotFound(i64),
+    #[error("No notes match: {0}")]
+    NoMatchingNotes(String),
+    #[error("Deck not found: {0}")]
+    DeckNotFound(String),
+    #[error("Notetype not found: {0}")]
+    NotetypeNotFound(String),
     #[error("Profile error: {0}")]
     ProfileError(String),
     #[error("Collection error: {0}")]
     CollectionError(String),
+    #[error("Collection file not found: {0}")]
+    CollectionNotFound(String),
+    #[error("Path does not exist: {0}")]
+    PathNotFound(String),
+    /// Collection is held by Anki or another process (message from `util::lock`)
+    #[error("{0}")]
+    CollectionLocked(String),
+    #[error("{0}")]
+    ParseError(String),
+    #[error("{0}")]
+    MediaError(String),
+    /// Warnings promoted to errors by `collect --strict`
+    #[error("{0}")]
+    StrictWarning(String),
 }
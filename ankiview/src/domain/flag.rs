@@ -0,0 +1,17 @@
+// src/domain/flag.rs
+
+/// Tag Anki's browser uses for marked notes
+pub const MARKED_TAG: &str = "marked";
+
+/// Anki's colored card flags; the discriminant is the value stored on the card
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    None = 0,
+    Red = 1,
+    Orange = 2,
+    Green = 3,
+    Blue = 4,
+    Pink = 5,
+    Turquoise = 6,
+    Purple = 7,
+}
@@ -1,6 +1,22 @@
 // src/domain/mod.rs
+pub mod count_group;
 pub mod error;
+pub mod flag;
 pub mod note;
+pub mod note_record;
+pub mod notetype_info;
+pub mod problem_note;
+pub mod review_stats;
+pub mod rule;
+pub mod transfer_note;
 
+pub use count_group::CountGroup;
 pub use error::DomainError;
+pub use flag::{Flag, MARKED_TAG};
 pub use note::Note;
+pub use note_record::NoteRecord;
+pub use notetype_info::{NotetypeInfo, TemplateUpdate};
+pub use problem_note::ProblemNote;
+pub use review_stats::{DailyReviews, ReviewStats};
+pub use rule::{parse_rules, Rule};
+pub use transfer_note::{CardTemplateDefinition, NotetypeDefinition, TransferNote};
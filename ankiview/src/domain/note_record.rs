@@ -0,0 +1,63 @@
+// src/domain/note_record.rs
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+
+/// Complete note for `export-jsonl`: every field by name, plus where the
+/// note lives and when it was created and last changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NoteRecord {
+    pub id: i64,
+    pub guid: String,
+    pub notetype: String,
+    /// Field names and values in notetype order
+    #[serde(serialize_with = "serialize_fields")]
+    pub fields: Vec<(String, String)>,
+    pub tags: Vec<String>,
+    /// Decks of the note's cards, sorted and without duplicates
+    pub decks: Vec<String>,
+    /// Creation time in Unix seconds (Anki derives note IDs from it)
+    pub created: i64,
+    /// Last modification in Unix seconds
+    pub modified: i64,
+}
+
+/// Fields as a JSON object keeping notetype order
+fn serialize_fields<S: Serializer>(
+    fields: &[(String, String)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(fields.len()))?;
+    for (name, value) in fields {
+        map.serialize_entry(name, value)?;
+    }
+    map.end()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_record_when_serializing_then_fields_keep_notetype_order() {
+        let record = NoteRecord {
+            id: 1700000000123,
+            guid: "abc".to_string(),
+            notetype: "Basic".to_string(),
+            fields: vec![
+                ("Front".to_string(), "Q".to_string()),
+                ("Back".to_string(), "A".to_string()),
+            ],
+            tags: vec!["rust".to_string()],
+            decks: vec!["Default".to_string()],
+            created: 1700000000,
+            modified: 1700000100,
+        };
+
+        let json = serde_json::to_string(&record).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"id":1700000000123,"guid":"abc","notetype":"Basic","fields":{"Front":"Q","Back":"A"},"tags":["rust"],"decks":["Default"],"created":1700000000,"modified":1700000100}"#
+        );
+    }
+}
@@ -0,0 +1,29 @@
+// src/domain/notetype_info.rs
+use serde::Serialize;
+
+/// Fields and card templates of a notetype, for the `fields` command
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NotetypeInfo {
+    pub id: i64,
+    pub name: String,
+    pub cloze: bool,
+    /// Field names in ordinal order (index = ordinal)
+    pub fields: Vec<String>,
+    /// Card template names in ordinal order (index = ordinal)
+    pub templates: Vec<String>,
+}
+
+/// New styling for a notetype from `notetype push`; `None` leaves a part unchanged
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TemplateUpdate {
+    pub css: Option<String>,
+    /// Front (question) template HTML
+    pub front: Option<String>,
+    /// Back (answer) template HTML
+    pub back: Option<String>,
+    /// Card template to update; the first one if `None`
+    pub template: Option<String>,
+    /// TeX macro definitions to put at the top of every card template,
+    /// replacing earlier ones; empty removes them
+    pub math_macros: Option<String>,
+}
@@ -0,0 +1,16 @@
+// src/domain/problem_note.rs
+use serde::Serialize;
+
+/// A note whose cards keep being forgotten, for the leech report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProblemNote {
+    pub note_id: i64,
+    /// First line of the front field, as plain text
+    pub first_line: String,
+    /// Deck of the card with the most lapses
+    pub deck: String,
+    /// Most lapses of any of the note's cards
+    pub lapses: u32,
+    /// Tagged `leech` by Anki
+    pub leech: bool,
+}
@@ -0,0 +1,25 @@
+// src/domain/review_stats.rs
+use serde::Serialize;
+
+/// Review activity over the last `days` days, from the review log
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReviewStats {
+    /// Deck the stats are limited to (with its subdecks); None for the whole collection
+    pub deck: Option<String>,
+    pub days: u32,
+    /// Answered reviews of any kind (learning, review, relearning, filtered deck)
+    pub total_reviews: usize,
+    /// Reviews per UTC calendar day (`YYYY-MM-DD`), oldest first, including days without reviews
+    pub reviews_per_day: Vec<DailyReviews>,
+    /// Share of mature and young reviews answered Hard, Good or Easy (0.0–1.0);
+    /// None if there were no such reviews
+    pub retention: Option<f64>,
+    /// Average time spent on an answer, in seconds; None without reviews
+    pub average_answer_seconds: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DailyReviews {
+    pub date: String,
+    pub reviews: usize,
+}
@@ -0,0 +1,92 @@
+// src/domain/rule.rs
+use crate::domain::DomainError;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// A maintenance rule from a rules file: the notes matching `query` get
+/// every configured action applied
+///
+/// ```toml
+/// [[rule]]
+/// query = "deck:Inbox tag:rust"
+/// add_tags = ["lang::rust"]
+/// remove_tags = ["inbox"]
+/// deck = "Programming::Rust"
+/// set_fields = { Source = "The Book" }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Anki search query selecting the notes
+    pub query: String,
+    #[serde(default)]
+    pub add_tags: Vec<String>,
+    #[serde(default)]
+    pub remove_tags: Vec<String>,
+    /// Move all cards of the matching notes to this deck (created if missing)
+    pub deck: Option<String>,
+    /// Field name → new value; notes whose notetype lacks the field are skipped
+    #[serde(default)]
+    pub set_fields: BTreeMap<String, String>,
+}
+
+impl Rule {
+    pub fn has_actions(&self) -> bool {
+        !self.add_tags.is_empty()
+            || !self.remove_tags.is_empty()
+            || self.deck.is_some()
+            || !self.set_fields.is_empty()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<Rule>,
+}
+
+/// Parse the `[[rule]]` tables of a rules file
+pub fn parse_rules(content: &str) -> Result<Vec<Rule>, DomainError> {
+    toml::from_str::<RulesFile>(content)
+        .map(|file| file.rule)
+        .map_err(|e| DomainError::ParseError(format!("Invalid rules file: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_rules_file_when_parsing_then_reads_all_actions() {
+        let content = r#"
+[[rule]]
+query = "deck:Inbox tag:rust"
+add_tags = ["lang::rust"]
+remove_tags = ["inbox"]
+deck = "Programming::Rust"
+set_fields = { Source = "The Book" }
+
+[[rule]]
+query = "tag:obsolete"
+remove_tags = ["obsolete"]
+"#;
+
+        let rules = parse_rules(content).unwrap();
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].query, "deck:Inbox tag:rust");
+        assert_eq!(rules[0].add_tags, vec!["lang::rust"]);
+        assert_eq!(rules[0].deck.as_deref(), Some("Programming::Rust"));
+        assert_eq!(rules[0].set_fields["Source"], "The Book");
+        assert!(rules[1].has_actions());
+        assert_eq!(rules[1].deck, None);
+    }
+
+    #[test]
+    fn given_misspelled_action_when_parsing_then_fails() {
+        let result = parse_rules("[[rule]]\nquery = \"deck:A\"\nadd_tag = [\"x\"]\n");
+
+        assert!(matches!(result, Err(DomainError::ParseError(_))));
+    }
+}
@@ -0,0 +1,37 @@
+// src/domain/transfer_note.rs
+
+/// A note read from one collection to be added to another by `transfer`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferNote {
+    /// ID in the collection it was read from
+    pub id: i64,
+    pub notetype: NotetypeDefinition,
+    /// Field contents in notetype order
+    pub fields: Vec<String>,
+    pub tags: Vec<String>,
+    /// Deck of the note's first card
+    pub deck: String,
+    /// collection.media files referenced by the fields, without duplicates
+    pub media: Vec<String>,
+}
+
+/// Everything needed to recreate a notetype in another collection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotetypeDefinition {
+    pub name: String,
+    pub cloze: bool,
+    /// Field names in ordinal order
+    pub fields: Vec<String>,
+    pub templates: Vec<CardTemplateDefinition>,
+    pub css: String,
+}
+
+/// A card template of a [`NotetypeDefinition`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardTemplateDefinition {
+    pub name: String,
+    /// Front (question) template HTML
+    pub front: String,
+    /// Back (answer) template HTML
+    pub back: String,
+}
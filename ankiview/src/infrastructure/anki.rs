@@ -1,13 +1,25 @@
 // src/infrastructure/anki.rs
 use crate::application::NoteRepository;
-use crate::domain::{DomainError, Note};
+use crate::domain::{
+    CardTemplateDefinition, CountGroup, DomainError, Flag, Note, NoteRecord, NotetypeDefinition,
+    NotetypeInfo, ProblemNote, TemplateUpdate, TransferNote,
+};
+use crate::infrastructure::backup::CardScheduling;
 use anki::collection::{Collection, CollectionBuilder};
 use anki::notes::NoteId;
+use anki::notetype::NotetypeId;
 use anyhow::{Context, Result};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use tracing::{debug, info, instrument};
 
+/// Notetype of basic cards when no card type is given
+pub const DEFAULT_BASIC_NOTETYPE: &str = "Inka Basic";
+
+/// Notes read per query when listing
+const NOTE_BATCH_SIZE: usize = 500;
+
 pub struct AnkiRepository {
     collection: Collection,
     media_dir: PathBuf,
@@ -20,11 +32,7 @@ impl AnkiRepository {
 
         // Check if file exists
         if !path.exists() {
-            return Err(DomainError::CollectionError(format!(
-                "Collection file not found: {}",
-                path.display()
-            ))
-            .into());
+            return Err(DomainError::CollectionNotFound(path.display().to_string()).into());
         }
 
         // Check if we have read permissions
@@ -59,7 +67,7 @@ impl AnkiRepository {
         let collection = CollectionBuilder::new(path.clone()).build().map_err(|e| {
             let err: anyhow::Error = e.into();
             if crate::util::lock::is_sqlite_busy_error(&err) {
-                anyhow::anyhow!(crate::util::lock::locked_message(&path))
+                DomainError::CollectionLocked(crate::util::lock::locked_message(&path)).into()
             } else {
                 err.context(
                     "Failed to open Anki collection.\n\n\
@@ -116,13 +124,23 @@ impl AnkiRepository {
         ))
     }
 
+    /// Number of fields of a notetype
+    pub fn notetype_field_count(&mut self, notetype_id: i64) -> Result<usize> {
+        let notetype = self
+            .collection
+            .get_notetype(NotetypeId(notetype_id))
+            .context("Failed to get notetype")?
+            .ok_or_else(|| anyhow::anyhow!("Notetype {} not found", notetype_id))?;
+        Ok(notetype.fields.len())
+    }
+
     /// Find or create a Basic note type with front/back fields
     /// Returns the notetype ID
     ///
     /// # Arguments
     /// * `preferred_name` - Optional exact notetype name to use. Defaults to "Inka Basic" if None.
     pub fn find_or_create_basic_notetype(&mut self, preferred_name: Option<&str>) -> Result<i64> {
-        let notetype_name = preferred_name.unwrap_or("Inka Basic");
+        let notetype_name = preferred_name.unwrap_or(DEFAULT_BASIC_NOTETYPE);
 
         // Try to find the preferred notetype by exact name
         match self.find_notetype_by_name(notetype_name) {
@@ -179,60 +197,119 @@ impl AnkiRepository {
         tags: &[String],
         card_type: Option<&str>,
     ) -> Result<i64> {
-        use anki::notes::Note;
-        use anki::notetype::NotetypeId;
-
         // Find or create the Basic notetype
         let notetype_id = self.find_or_create_basic_notetype(card_type)?;
 
-        // Get the notetype to create the note
-        let notetype = self
-            .collection
-            .get_notetype(NotetypeId(notetype_id))
-            .context("Failed to get notetype")?
-            .context("Notetype not found")?;
+        // Find or create the deck
+        let deck_id = self.get_or_create_deck_id(deck_name)?;
+
+        let note_id = self.add_note_with_fields(
+            notetype_id,
+            deck_id,
+            &[front.to_string(), back.to_string()],
+            tags,
+        )?;
+        debug!(note_id, "Created Basic note");
+        Ok(note_id)
+    }
+
+    /// Create a new Cloze note in the collection
+    /// Returns the created note ID
+    pub fn create_cloze_note(
+        &mut self,
+        text: &str,
+        deck_name: &str,
+        tags: &[String],
+    ) -> Result<i64> {
+        // Find or create the Cloze notetype
+        let notetype_id = self.find_or_create_cloze_notetype()?;
 
         // Find or create the deck
+        let deck_id = self.get_or_create_deck_id(deck_name)?;
+
+        let note_id = self.add_note_with_fields(notetype_id, deck_id, &[text.to_string()], tags)?;
+        debug!(note_id, "Created Cloze note");
+        Ok(note_id)
+    }
+
+    /// Find or create a normal deck by name
+    /// Returns the deck ID
+    pub fn get_or_create_deck_id(&mut self, deck_name: &str) -> Result<i64> {
         let deck_id = self
             .collection
             .get_or_create_normal_deck(deck_name)
             .context("Failed to get or create deck")?
             .id;
+        Ok(deck_id.0)
+    }
 
-        // Create a new note
-        let mut note = Note::new(&notetype);
-        note.set_field(0, front)
-            .context("Failed to set front field")?;
-        note.set_field(1, back)
-            .context("Failed to set back field")?;
-
-        // Add tags
-        for tag in tags {
-            note.tags.push(tag.clone());
+    /// Find or create a normal deck by name like [`Self::get_or_create_deck_id`];
+    /// a deck created here gets the deck options preset named `options`
+    /// instead of Anki's Default preset. Existing decks keep their options.
+    /// Returns the deck ID
+    pub fn get_or_create_deck_with_options(
+        &mut self,
+        deck_name: &str,
+        options: Option<&str>,
+    ) -> Result<i64> {
+        let Some(options) = options else {
+            return self.get_or_create_deck_id(deck_name);
+        };
+        if let Some(id) = self
+            .collection
+            .get_deck_id(deck_name)
+            .context("Failed to look up deck")?
+        {
+            return Ok(id.0);
         }
 
-        // Add the note to the collection
-        self.collection
-            .add_note(&mut note, deck_id)
-            .context("Failed to add note to collection")?;
+        let presets = self
+            .collection
+            .storage
+            .all_deck_config()
+            .context("Failed to list deck options presets")?;
+        let preset = presets
+            .iter()
+            .find(|preset| preset.name == options)
+            .ok_or_else(|| {
+                let names: Vec<_> = presets.iter().map(|p| p.name.as_str()).collect();
+                anyhow::anyhow!(
+                    "Deck options preset '{}' not found. Available presets: {}",
+                    options,
+                    names.join(", ")
+                )
+            })?;
 
-        debug!(note_id = note.id.0, "Created Basic note");
-        Ok(note.id.0)
+        let mut deck = self
+            .collection
+            .get_or_create_normal_deck(deck_name)
+            .context("Failed to get or create deck")?;
+        deck.normal_mut()
+            .context("Created deck is not a normal deck")?
+            .config_id = preset.id.0;
+        self.collection
+            .update_deck(&mut deck)
+            .context("Failed to set deck options")?;
+        info!(
+            deck = deck_name,
+            preset = options,
+            "Created deck with options preset"
+        );
+        Ok(deck.id.0)
     }
 
-    /// Create a new Cloze note in the collection
+    /// Add a note of an already-resolved notetype to an already-resolved deck
+    /// Fields are set in order; fields not provided stay empty.
     /// Returns the created note ID
-    pub fn create_cloze_note(
+    pub fn add_note_with_fields(
         &mut self,
-        text: &str,
-        deck_name: &str,
+        notetype_id: i64,
+        deck_id: i64,
+        fields: &[String],
         tags: &[String],
     ) -> Result<i64> {
+        use anki::decks::DeckId;
         use anki::notes::Note;
-        use anki::notetype::NotetypeId;
-
-        // Find or create the Cloze notetype
-        let notetype_id = self.find_or_create_cloze_notetype()?;
 
         // Get the notetype to create the note
         let notetype = self
@@ -241,17 +318,12 @@ impl AnkiRepository {
             .context("Failed to get notetype")?
             .context("Notetype not found")?;
 
-        // Find or create the deck
-        let deck_id = self
-            .collection
-            .get_or_create_normal_deck(deck_name)
-            .context("Failed to get or create deck")?
-            .id;
-
         // Create a new note
         let mut note = Note::new(&notetype);
-        note.set_field(0, text)
-            .context("Failed to set text field")?;
+        for (idx, value) in fields.iter().enumerate() {
+            note.set_field(idx, value)
+                .with_context(|| format!("Failed to set field {}", idx))?;
+        }
 
         // Add tags
         for tag in tags {
@@ -260,10 +332,9 @@ impl AnkiRepository {
 
         // Add the note to the collection
         self.collection
-            .add_note(&mut note, deck_id)
+            .add_note(&mut note, DeckId(deck_id))
             .context("Failed to add note to collection")?;
 
-        debug!(note_id = note.id.0, "Created Cloze note");
         Ok(note.id.0)
     }
 
@@ -279,7 +350,7 @@ impl AnkiRepository {
             .storage
             .get_note(NoteId(note_id))
             .context("Failed to get note from storage")?
-            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
+            .ok_or(DomainError::NoteNotFound(note_id))?;
 
         // Update each field
         for (index, field_value) in fields.iter().enumerate() {
@@ -296,6 +367,106 @@ impl AnkiRepository {
         Ok(())
     }
 
+    /// Put the new cards of `note_ids` (in that order, then by template) at due
+    /// positions `start`, `start + 1`, ..., shifting existing new cards at or after
+    /// `start` back. Cards that are no longer new are left alone.
+    /// Returns the number of cards repositioned.
+    pub fn reposition_new_cards(&mut self, note_ids: &[i64], start: u32) -> Result<usize> {
+        use anki::card::CardType;
+
+        let mut card_ids = Vec::new();
+        for &note_id in note_ids {
+            let mut cards = self
+                .collection
+                .storage
+                .all_cards_of_note(NoteId(note_id))
+                .context("Failed to get cards of note")?;
+            cards.sort_by_key(|card| card.template_idx);
+            card_ids.extend(
+                cards
+                    .iter()
+                    .filter(|card| card.ctype == CardType::New)
+                    .map(|card| card.id),
+            );
+        }
+        if card_ids.is_empty() {
+            return Ok(0);
+        }
+
+        self.collection
+            .reposition_new_cards(&card_ids, start, 1, false, true)
+            .context("Failed to reposition new cards")?;
+        debug!(cards = card_ids.len(), start, "Repositioned new cards");
+        Ok(card_ids.len())
+    }
+
+    /// Give the cards of `note_id` the scheduling of the card with the same
+    /// template in `scheduling` (e.g. from a backup of a deleted note).
+    /// Returns the number of cards changed.
+    pub fn restore_card_scheduling(
+        &mut self,
+        note_id: i64,
+        scheduling: &[CardScheduling],
+    ) -> Result<usize> {
+        use anki::card::{CardQueue, CardType, FsrsMemoryState};
+        use anki::timestamp::TimestampSecs;
+
+        let cards = self
+            .collection
+            .storage
+            .all_cards_of_note(NoteId(note_id))
+            .context("Failed to get cards of note")?;
+        let mut restored = 0;
+        for mut card in cards {
+            let Some(old) = scheduling
+                .iter()
+                .find(|old| old.template_idx == card.template_idx)
+            else {
+                continue;
+            };
+            card.ctype = match old.ctype {
+                1 => CardType::Learn,
+                2 => CardType::Review,
+                3 => CardType::Relearn,
+                _ => CardType::New,
+            };
+            card.queue = match old.queue {
+                1 => CardQueue::Learn,
+                2 => CardQueue::Review,
+                3 => CardQueue::DayLearn,
+                4 => CardQueue::PreviewRepeat,
+                -1 => CardQueue::Suspended,
+                -2 => CardQueue::SchedBuried,
+                -3 => CardQueue::UserBuried,
+                _ => CardQueue::New,
+            };
+            card.due = old.due;
+            card.interval = old.interval;
+            card.ease_factor = old.ease_factor;
+            card.reps = old.reps;
+            card.lapses = old.lapses;
+            card.flags = old.flags;
+            let data = &old.data;
+            card.original_position = data.original_position;
+            let memory_state = data.stability.zip(data.difficulty);
+            card.memory_state = memory_state.map(|(stability, difficulty)| FsrsMemoryState {
+                stability,
+                difficulty,
+            });
+            card.desired_retention = data.desired_retention;
+            card.decay = data.decay;
+            card.last_review_time = data.last_review_time.map(TimestampSecs);
+            card.custom_data = data.custom_data.clone();
+            self.collection
+                .update_card(&mut card)
+                .context("Failed to update card scheduling")?;
+            restored += 1;
+        }
+
+        debug!(note_id, restored, "Restored card scheduling");
+        Ok(restored)
+    }
+
     /// Check if a note exists by ID
     pub fn note_exists(&self, note_id: i64) -> Result<bool> {
         use anki::notes::NoteId;
@@ -310,8 +481,94 @@ impl AnkiRepository {
         Ok(exists)
     }
 
+    /// Current field values and tags of a note, or None if it doesn't exist
+    pub fn note_content(&self, note_id: i64) -> Result<Option<(Vec<String>, Vec<String>)>> {
+        use anki::notes::NoteId;
+
+        let note = self
+            .collection
+            .storage
+            .get_note(NoteId(note_id))
+            .context("Failed to get note from storage")?;
+
+        Ok(note.map(|note| (note.fields().clone(), note.tags.clone())))
+    }
+
+    /// Move all cards of a note to a deck; returns how many were moved.
+    /// Cards in a filtered deck stay there and get `deck_id` as their home
+    /// deck, so they return to it when the filtered deck is emptied
+    pub fn move_note_cards(&mut self, note_id: i64, deck_id: i64) -> Result<usize> {
+        use anki::decks::DeckId;
+
+        let mut card_ids = Vec::new();
+        let mut moved = 0;
+        for mut card in self
+            .collection
+            .storage
+            .all_cards_of_note(NoteId(note_id))
+            .context("Failed to get cards of note")?
+        {
+            if card.original_deck_id.0 == 0 {
+                card_ids.push(card.id);
+            } else if card.original_deck_id.0 != deck_id {
+                card.original_deck_id = DeckId(deck_id);
+                self.collection
+                    .update_card(&mut card)
+                    .context("Failed to change home deck of card")?;
+                moved += 1;
+            }
+        }
+        moved += self
+            .collection
+            .set_deck(&card_ids, DeckId(deck_id))
+            .context("Failed to move cards")?
+            .output;
+
+        debug!(note_id, deck_id, moved, "Moved cards of note");
+        Ok(moved)
+    }
+
+    /// Home deck (of the note's first card) and notetype name of a note, or
+    /// None if it doesn't exist. For a card in a filtered deck that is the deck
+    /// it came from, not the filtered deck
+    pub fn note_location(&mut self, note_id: i64) -> Result<Option<(String, String)>> {
+        let Some(note) = self
+            .collection
+            .storage
+            .get_note(NoteId(note_id))
+            .context("Failed to get note from storage")?
+        else {
+            return Ok(None);
+        };
+
+        let deck = match self
+            .collection
+            .storage
+            .all_cards_of_note(NoteId(note_id))
+            .context("Failed to get cards of note")?
+            .first()
+        {
+            Some(card) => self
+                .collection
+                .get_deck(home_deck_id(card))
+                .context("Failed to get deck")?
+                .map(|deck| deck.human_name())
+                .unwrap_or_default(),
+            None => String::new(),
+        };
+        let notetype = self
+            .collection
+            .get_notetype(note.notetype_id)
+            .context("Failed to get notetype")?
+            .map(|notetype| notetype.name.clone())
+            .unwrap_or_default();
+
+        Ok(Some((deck, notetype)))
+    }
+
     /// Search for notes by HTML content (for --update-ids)
-    /// Returns a vector of note IDs that match the given HTML fields
+    /// Returns the IDs of the notes that match the given HTML fields, newest
+    /// (most recently created) first
     pub fn search_by_html(&mut self, fields: &[String]) -> Result<Vec<i64>> {
         use anki::search::SearchNode;
 
@@ -349,13 +606,70 @@ impl AnkiRepository {
             }
         }
 
+        // Note IDs are creation timestamps
+        matching_ids.sort_unstable_by(|a, b| b.cmp(a));
         Ok(matching_ids)
     }
+
+    /// Notes of a notetype in `deck_name` (or its subdecks) that Anki would
+    /// flag as duplicates of `first_field`: same text once HTML is stripped.
+    /// The `File:` footer is ignored on both sides, so a card from another
+    /// file or a note made in Anki still matches. Newest first.
+    pub fn find_duplicates(
+        &mut self,
+        notetype_id: i64,
+        deck_name: &str,
+        first_field: &str,
+    ) -> Result<Vec<i64>> {
+        use anki::search::{JoinSearches, SearchNode};
+
+        let wanted = duplicate_key(first_field);
+        let search = SearchNode::NotetypeId(NotetypeId(notetype_id))
+            .and(SearchNode::from_deck_name(deck_name));
+        let mut note_ids = Vec::new();
+        for id in self
+            .collection
+            .search_notes_unordered(search)
+            .context("Failed to search for duplicate notes")?
+        {
+            let Some(note) = self
+                .collection
+                .storage
+                .get_note(id)
+                .context("Failed to get note from storage")?
+            else {
+                continue;
+            };
+            if note
+                .fields()
+                .first()
+                .is_some_and(|field| duplicate_key(field) == wanted)
+            {
+                note_ids.push(id.0);
+            }
+        }
+
+        note_ids.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(note_ids)
+    }
 }
 
 // --- Tag and field update helpers (used by NoteRepository trait impl) ---
 
 impl AnkiRepository {
+    /// IDs of the notes matching an Anki search query (all notes if None or empty)
+    fn search_note_ids(&mut self, query: Option<&str>) -> Result<Vec<NoteId>, DomainError> {
+        use anki::search::SearchNode;
+
+        match query {
+            Some(q) if !q.is_empty() => self.collection.search_notes_unordered(q),
+            _ => self
+                .collection
+                .search_notes_unordered(SearchNode::WholeCollection),
+        }
+        .map_err(|e| DomainError::CollectionError(e.to_string()))
+    }
+
     /// Add tags to a note, merging with existing tags (no duplicates)
     fn merge_tags_on_note(&mut self, note_id: i64, new_tags: &[String]) -> Result<()> {
         let mut note = self
@@ -363,7 +677,7 @@ impl AnkiRepository {
             .storage
             .get_note(NoteId(note_id))
             .context("Failed to get note from storage")?
-            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
+            .ok_or(DomainError::NoteNotFound(note_id))?;
 
         // Merge: add only tags not already present
         for tag in new_tags {
@@ -387,7 +701,7 @@ impl AnkiRepository {
             .storage
             .get_note(NoteId(note_id))
             .context("Failed to get note from storage")?
-            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
+            .ok_or(DomainError::NoteNotFound(note_id))?;
 
         note.tags.retain(|t| !tags_to_remove.contains(t));
 
@@ -411,7 +725,7 @@ impl AnkiRepository {
             .storage
             .get_note(NoteId(note_id))
             .context("Failed to get note from storage")?
-            .ok_or_else(|| anyhow::anyhow!("Note not found: {}", note_id))?;
+            .ok_or(DomainError::NoteNotFound(note_id))?;
 
         for (index, field_value) in fields.iter().enumerate() {
             note.set_field(index, field_value)
@@ -427,6 +741,251 @@ impl AnkiRepository {
         debug!(note_id, "Updated note fields and tags");
         Ok(())
     }
+
+    /// Read the notes `note_ids` in one query; IDs of notes that no longer
+    /// exist are left out
+    fn read_notes(&mut self, note_ids: &[NoteId]) -> Result<Vec<anki::notes::Note>, DomainError> {
+        use anki::search::SearchNode;
+
+        if note_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids = note_ids
+            .iter()
+            .map(|id| id.0.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        self.read_searched_notes(SearchNode::NoteIds(ids))
+    }
+
+    /// Read the notes matching an Anki search query (all notes if None or
+    /// empty) in one query
+    fn read_matching_notes(
+        &mut self,
+        query: Option<&str>,
+    ) -> Result<Vec<anki::notes::Note>, DomainError> {
+        use anki::search::SearchNode;
+
+        match query {
+            Some(q) if !q.is_empty() => self.read_searched_notes(q),
+            _ => self.read_searched_notes(SearchNode::WholeCollection),
+        }
+    }
+
+    fn read_searched_notes(
+        &mut self,
+        search: impl anki::search::TryIntoSearch,
+    ) -> Result<Vec<anki::notes::Note>, DomainError> {
+        let notes = self
+            .collection
+            .search_notes_into_table(search)
+            .and_then(|_| self.collection.storage.all_searched_notes());
+        // Clear the table also when reading failed, the search error is the one to report
+        let cleared = self.collection.storage.clear_searched_notes_table();
+        let notes = notes.map_err(|e| DomainError::CollectionError(e.to_string()))?;
+        cleared.map_err(|e| DomainError::CollectionError(e.to_string()))?;
+        Ok(notes)
+    }
+
+    /// Read the cards matching an Anki search in one query
+    fn read_searched_cards(
+        &mut self,
+        search: impl anki::search::TryIntoSearch,
+    ) -> Result<Vec<anki::card::Card>, DomainError> {
+        use anki::search::SortMode;
+
+        let cards = self
+            .collection
+            .search_cards_into_table(search, SortMode::NoOrder)
+            .and_then(|_| self.collection.storage.all_searched_cards());
+        let cleared = self.collection.storage.clear_searched_cards_table();
+        let cards = cards.map_err(|e| DomainError::CollectionError(e.to_string()))?;
+        cleared.map_err(|e| DomainError::CollectionError(e.to_string()))?;
+        Ok(cards)
+    }
+
+    /// Lazily list notes, optionally filtered by front field content.
+    ///
+    /// Only the matching note IDs and notetype names are loaded up front; the
+    /// notes are read `NOTE_BATCH_SIZE` at a time as the iterator reaches them,
+    /// so callers can stream output or stop early (e.g. `list --limit`)
+    /// without materializing every note.
+    pub fn list_notes_iter(
+        &mut self,
+        search_query: Option<&str>,
+    ) -> Result<impl Iterator<Item = Result<Note, DomainError>> + '_, DomainError> {
+        // Get note IDs based on search query
+        let note_ids: Vec<NoteId> = match search_query {
+            None => {
+                // No search - get all notes (fastest method)
+                self.collection
+                    .storage
+                    .get_all_note_ids()
+                    .map_err(|e| DomainError::CollectionError(e.to_string()))?
+                    .into_iter()
+                    .collect()
+            }
+            Some(query) => {
+                // Build search query for front field
+                let search_str = if query.is_empty() {
+                    // Empty query string = all notes
+                    "".to_string()
+                } else {
+                    // Search in front field for the query string
+                    format!("front:*{}*", query)
+                };
+
+                // Use unordered search (faster, no sort needed)
+                self.collection
+                    .search_notes_unordered(&search_str)
+                    .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            }
+        };
+
+        // Resolve notetype names once up front instead of one notetype lookup per note
+        let model_names: HashMap<NotetypeId, String> = self
+            .collection
+            .get_all_notetypes()
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            .into_iter()
+            .map(|nt| (nt.id, nt.name.clone()))
+            .collect();
+
+        // Fetch note rows on demand, one query per batch. A failed batch ends
+        // the listing with its error.
+        let batches: Vec<Vec<NoteId>> = note_ids
+            .chunks(NOTE_BATCH_SIZE)
+            .map(<[NoteId]>::to_vec)
+            .collect();
+        Ok(batches.into_iter().flat_map(move |batch| {
+            let notes = match self.read_notes(&batch) {
+                Ok(notes) => notes,
+                Err(e) => return vec![Err(e)],
+            };
+            if notes.len() < batch.len() {
+                // Deleted since the search (e.g. by Anki syncing meanwhile)
+                debug!(
+                    missing = batch.len() - notes.len(),
+                    "Skipping notes that no longer exist"
+                );
+            }
+            notes
+                .iter()
+                .map(|note| {
+                    model_names
+                        .get(&note.notetype_id)
+                        .map(|model_name| to_domain_note(note, model_name))
+                        .ok_or_else(|| {
+                            DomainError::CollectionError("Notetype not found".to_string())
+                        })
+                })
+                .collect()
+        }))
+    }
+
+    /// Lazily read complete notes matching an Anki search query (all notes if
+    /// `None`), ordered by ID.
+    ///
+    /// Like `list_notes_iter`, only note IDs, notetypes and deck names are
+    /// loaded up front, so a whole collection can be streamed.
+    pub fn note_records_iter(
+        &mut self,
+        query: Option<&str>,
+    ) -> Result<impl Iterator<Item = Result<NoteRecord, DomainError>> + '_, DomainError> {
+        let mut note_ids = self.search_note_ids(query)?;
+        note_ids.sort();
+
+        let notetypes: HashMap<NotetypeId, (String, Vec<String>)> = self
+            .collection
+            .get_all_notetypes()
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            .into_iter()
+            .map(|nt| {
+                let field_names = nt.fields.iter().map(|f| f.name.clone()).collect();
+                (nt.id, (nt.name.clone(), field_names))
+            })
+            .collect();
+        let deck_names: HashMap<_, String> = self
+            .collection
+            .storage
+            .get_all_decks()
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            .into_iter()
+            .map(|deck| (deck.id, deck.human_name()))
+            .collect();
+
+        let storage = &self.collection.storage;
+        Ok(note_ids.into_iter().filter_map(move |note_id| {
+            let note = match storage.get_note(note_id) {
+                Ok(Some(note)) => note,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(DomainError::CollectionError(e.to_string()))),
+            };
+            let Some((notetype, field_names)) = notetypes.get(&note.notetype_id) else {
+                return Some(Err(DomainError::CollectionError(
+                    "Notetype not found".to_string(),
+                )));
+            };
+            let cards = match storage.all_cards_of_note(note_id) {
+                Ok(cards) => cards,
+                Err(e) => return Some(Err(DomainError::CollectionError(e.to_string()))),
+            };
+            let mut decks: Vec<String> = cards
+                .iter()
+                .filter_map(|card| deck_names.get(&card.deck_id).cloned())
+                .collect();
+            decks.sort();
+            decks.dedup();
+
+            Some(Ok(NoteRecord {
+                id: note.id.0,
+                guid: note.guid.clone(),
+                notetype: notetype.clone(),
+                fields: field_names
+                    .iter()
+                    .cloned()
+                    .zip(note.fields().iter().cloned())
+                    .collect(),
+                tags: note.tags.clone(),
+                decks,
+                created: note.id.0 / 1000,
+                modified: note.mtime.0,
+            }))
+        }))
+    }
+}
+
+/// What makes first fields duplicates, like Anki's duplicate check: the text
+/// and media names, ignoring markup and the `File:` footer of collected notes
+fn duplicate_key(html: &str) -> (String, Vec<String>) {
+    let html = crate::util::text::strip_file_footer(html);
+    (
+        crate::util::text::html_to_text(&html),
+        crate::util::text::referenced_media(&html),
+    )
+}
+
+/// Deck a card belongs to: the deck it came from while it is in a filtered
+/// deck, otherwise its current deck
+fn home_deck_id(card: &anki::card::Card) -> anki::decks::DeckId {
+    if card.original_deck_id.0 != 0 {
+        card.original_deck_id
+    } else {
+        card.deck_id
+    }
+}
+
+/// Convert an anki note into the domain model (first two fields as front/back)
+fn to_domain_note(note: &anki::notes::Note, model_name: &str) -> Note {
+    let fields = note.fields();
+
+    Note {
+        id: note.id.0,
+        front: fields.first().cloned().unwrap_or_default(),
+        back: fields.get(1).cloned().unwrap_or_default(),
+        tags: note.tags.to_vec(),
+        model_name: model_name.to_string(),
+    }
 }
 
 impl NoteRepository for AnkiRepository {
@@ -445,15 +1004,7 @@ impl NoteRepository for AnkiRepository {
             .map_err(|e| DomainError::CollectionError(e.to_string()))?
             .ok_or_else(|| DomainError::CollectionError("Notetype not found".to_string()))?;
 
-        let fields: Vec<_> = note.fields().iter().map(|f| f.to_string()).collect();
-
-        Ok(Note {
-            id: note.id.0,
-            front: fields.first().cloned().unwrap_or_default(),
-            back: fields.get(1).cloned().unwrap_or_default(),
-            tags: note.tags.to_vec(),
-            model_name: model.name.clone(),
-        })
+        Ok(to_domain_note(&note, &model.name))
     }
 
     #[instrument(level = "debug", skip(self))]
@@ -496,52 +1047,173 @@ impl NoteRepository for AnkiRepository {
 
     #[instrument(level = "debug", skip(self))]
     fn list_notes(&mut self, search_query: Option<&str>) -> Result<Vec<Note>, DomainError> {
-        // Get note IDs based on search query
-        let note_ids: Vec<NoteId> = match search_query {
-            None => {
-                // No search - get all notes (fastest method)
-                self.collection
-                    .storage
-                    .get_all_note_ids()
-                    .map_err(|e| DomainError::CollectionError(e.to_string()))?
-                    .into_iter()
-                    .collect()
-            }
-            Some(query) => {
-                // Build search query for front field
-                let search_str = if query.is_empty() {
-                    // Empty query string = all notes
-                    "".to_string()
-                } else {
-                    // Search in front field for the query string
-                    format!("front:*{}*", query)
-                };
+        let notes = self
+            .list_notes_iter(search_query)?
+            .collect::<Result<Vec<_>, _>>()?;
+        debug!(count = notes.len(), "Listed notes");
+        Ok(notes)
+    }
 
-                // Use unordered search (faster, no sort needed)
-                self.collection
-                    .search_notes_unordered(&search_str)
-                    .map_err(|e| DomainError::CollectionError(e.to_string()))?
-            }
+    #[instrument(level = "debug", skip(self))]
+    fn list_due_notes(
+        &mut self,
+        search_query: Option<&str>,
+    ) -> Result<Vec<(Note, usize)>, DomainError> {
+        use anki::search::SortMode;
+
+        // is:due covers learning cards and review cards due today (or overdue)
+        let query = match search_query {
+            Some(query) if !query.is_empty() => format!("is:due front:*{}*", query),
+            _ => "is:due".to_string(),
         };
+        let card_ids = self
+            .collection
+            .search_cards(query.as_str(), SortMode::NoOrder)
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?;
 
-        // Fetch full note data for each ID
-        let mut notes = Vec::new();
-        for note_id in note_ids {
-            // Use existing get_note logic
-            match self.get_note(note_id.0) {
-                Ok(note) => notes.push(note),
-                Err(DomainError::NoteNotFound(_)) => {
-                    // Skip notes that don't exist (race condition or corrupted DB)
-                    debug!(note_id = note_id.0, "Skipping note that doesn't exist");
-                    continue;
-                }
-                Err(e) => return Err(e), // Propagate other errors
+        let mut due_counts: HashMap<NoteId, usize> = HashMap::new();
+        for card_id in card_ids {
+            if let Some(card) = self
+                .collection
+                .storage
+                .get_card(card_id)
+                .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            {
+                *due_counts.entry(card.note_id).or_default() += 1;
             }
         }
 
+        let mut notes = Vec::with_capacity(due_counts.len());
+        for (note_id, due) in due_counts {
+            notes.push((self.get_note(note_id.0)?, due));
+        }
+        debug!(count = notes.len(), "Listed notes with due cards");
         Ok(notes)
     }
 
+    #[instrument(level = "debug", skip(self))]
+    fn search_notes(&mut self, query: Option<&str>) -> Result<Vec<Note>, DomainError> {
+        use std::collections::hash_map::Entry;
+
+        let mut note_ids = self.search_note_ids(query)?;
+        note_ids.sort();
+
+        let mut model_names: HashMap<NotetypeId, String> = HashMap::new();
+        let mut notes = Vec::with_capacity(note_ids.len());
+        for note_id in note_ids {
+            let Some(note) = self
+                .collection
+                .storage
+                .get_note(note_id)
+                .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            else {
+                continue;
+            };
+            let model_name = match model_names.entry(note.notetype_id) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let model = self
+                        .collection
+                        .get_notetype(note.notetype_id)
+                        .map_err(|e| DomainError::CollectionError(e.to_string()))?
+                        .ok_or_else(|| {
+                            DomainError::CollectionError("Notetype not found".to_string())
+                        })?;
+                    entry.insert(model.name.clone())
+                }
+            };
+            notes.push(to_domain_note(&note, model_name));
+        }
+        debug!(count = notes.len(), "Searched notes");
+        Ok(notes)
+    }
+
+    fn note_ids(&mut self, query: Option<&str>) -> Result<Vec<i64>, DomainError> {
+        let mut note_ids: Vec<i64> = self
+            .search_note_ids(query)?
+            .into_iter()
+            .map(|id| id.0)
+            .collect();
+        note_ids.sort();
+        Ok(note_ids)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    fn count_notes(&mut self, query: Option<&str>) -> Result<usize, DomainError> {
+        Ok(self.search_note_ids(query)?.len())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    fn count_notes_by(
+        &mut self,
+        query: Option<&str>,
+        group: CountGroup,
+    ) -> Result<Vec<(String, usize)>, DomainError> {
+        use anki::search::SearchNode;
+        use std::collections::hash_map::Entry;
+        use std::collections::HashSet;
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        match group {
+            CountGroup::Deck => {
+                let cards = match query {
+                    Some(q) if !q.is_empty() => self.read_searched_cards(q)?,
+                    _ => self.read_searched_cards(SearchNode::WholeCollection)?,
+                };
+
+                // A note counts once per deck, however many of its cards are in it
+                let note_decks: HashSet<_> = cards
+                    .iter()
+                    .map(|card| (card.note_id, card.deck_id))
+                    .collect();
+
+                let mut deck_names: HashMap<_, String> = HashMap::new();
+                for (_, deck_id) in note_decks {
+                    let deck = match deck_names.entry(deck_id) {
+                        Entry::Occupied(entry) => entry.get().clone(),
+                        Entry::Vacant(entry) => {
+                            let name = self
+                                .collection
+                                .get_deck(deck_id)
+                                .map_err(|e| DomainError::CollectionError(e.to_string()))?
+                                .map(|deck| deck.human_name())
+                                .unwrap_or_default();
+                            entry.insert(name).clone()
+                        }
+                    };
+                    *counts.entry(deck).or_default() += 1;
+                }
+            }
+            CountGroup::Tag | CountGroup::Notetype => {
+                let notetype_names: HashMap<NotetypeId, String> = self
+                    .collection
+                    .get_all_notetypes()
+                    .map_err(|e| DomainError::CollectionError(e.to_string()))?
+                    .into_iter()
+                    .map(|nt| (nt.id, nt.name.clone()))
+                    .collect();
+
+                for note in self.read_matching_notes(query)? {
+                    let names = match group {
+                        CountGroup::Tag if note.tags.is_empty() => {
+                            vec![CountGroup::UNTAGGED.to_string()]
+                        }
+                        CountGroup::Tag => note.tags.clone(),
+                        _ => vec![notetype_names
+                            .get(&note.notetype_id)
+                            .cloned()
+                            .unwrap_or_default()],
+                    };
+                    for name in names {
+                        *counts.entry(name).or_default() += 1;
+                    }
+                }
+            }
+        }
+        debug!(groups = counts.len(), "Counted notes");
+        Ok(counts.into_iter().collect())
+    }
+
     #[instrument(level = "debug", skip(self))]
     fn list_notetypes(&mut self) -> Result<Vec<(i64, String)>, DomainError> {
         let all_notetypes = self
@@ -557,6 +1229,86 @@ impl NoteRepository for AnkiRepository {
         Ok(notetypes)
     }
 
+    #[instrument(level = "debug", skip(self))]
+    fn notetype_info(&mut self, name: &str) -> Result<NotetypeInfo, DomainError> {
+        use anki::notetype::NotetypeKind;
+
+        let notetype = self
+            .collection
+            .get_all_notetypes()
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            .into_iter()
+            .find(|nt| nt.name == name)
+            .ok_or_else(|| DomainError::NotetypeNotFound(name.to_string()))?;
+
+        Ok(NotetypeInfo {
+            id: notetype.id.0,
+            name: notetype.name.clone(),
+            cloze: notetype.config.kind() == NotetypeKind::Cloze,
+            fields: notetype.fields.iter().map(|f| f.name.clone()).collect(),
+            templates: notetype.templates.iter().map(|t| t.name.clone()).collect(),
+        })
+    }
+
+    #[instrument(level = "debug", skip(self, update))]
+    fn update_notetype_templates(
+        &mut self,
+        name: &str,
+        update: &TemplateUpdate,
+    ) -> Result<(), DomainError> {
+        let mut notetype = (*self
+            .collection
+            .get_notetype_by_name(name)
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            .ok_or_else(|| DomainError::NotetypeNotFound(name.to_string()))?)
+        .clone();
+
+        if let Some(css) = &update.css {
+            notetype.config.css = css.clone();
+        }
+        if update.front.is_some() || update.back.is_some() {
+            let index = match &update.template {
+                None => 0,
+                Some(template) => notetype
+                    .templates
+                    .iter()
+                    .position(|t| &t.name == template)
+                    .ok_or_else(|| {
+                        let available: Vec<&str> =
+                            notetype.templates.iter().map(|t| t.name.as_str()).collect();
+                        DomainError::CollectionError(format!(
+                            "Notetype '{}' has no template '{}'. Available templates: {}",
+                            name,
+                            template,
+                            available.join(", ")
+                        ))
+                    })?,
+            };
+            let config = &mut notetype.templates[index].config;
+            if let Some(front) = &update.front {
+                config.q_format = front.clone();
+            }
+            if let Some(back) = &update.back {
+                config.a_format = back.clone();
+            }
+        }
+        if let Some(definitions) = &update.math_macros {
+            for template in &mut notetype.templates {
+                let config = &mut template.config;
+                config.q_format =
+                    crate::util::math::with_template_macros(&config.q_format, definitions);
+                config.a_format =
+                    crate::util::math::with_template_macros(&config.a_format, definitions);
+            }
+        }
+
+        self.collection
+            .update_notetype(&mut notetype, false)
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?;
+        debug!(notetype = name, "Updated notetype templates");
+        Ok(())
+    }
+
     #[instrument(level = "debug", skip(self))]
     fn add_tags(&mut self, id: i64, tags: &[String]) -> Result<(), DomainError> {
         self.merge_tags_on_note(id, tags)
@@ -581,72 +1333,505 @@ impl NoteRepository for AnkiRepository {
     }
 
     #[instrument(level = "debug", skip(self))]
-    fn replace_tag(
-        &mut self,
-        query: Option<&str>,
-        old_tag: &str,
-        new_tag: &str,
-    ) -> Result<usize, DomainError> {
-        use anki::search::SearchNode;
+    fn note_fields(&mut self, query: Option<&str>) -> Result<Vec<(i64, Vec<String>)>, DomainError> {
+        let mut notes: Vec<(i64, Vec<String>)> = self
+            .read_matching_notes(query)?
+            .into_iter()
+            .map(|note| (note.id.0, note.fields().clone()))
+            .collect();
+        notes.sort_by_key(|(id, _)| *id);
+        Ok(notes)
+    }
+
+    fn set_note_fields(&mut self, id: i64, fields: &[String]) -> Result<(), DomainError> {
+        self.update_note(id, fields)
+            .map_err(|e| DomainError::CollectionError(e.to_string()))
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    fn move_notes(&mut self, query: Option<&str>, deck: &str) -> Result<usize, DomainError> {
+        use anki::card::CardId;
+        use anki::decks::DeckId;
+
+        let mut card_ids: Vec<CardId> = Vec::new();
+        for note_id in self.search_note_ids(query)? {
+            let cards = self
+                .collection
+                .storage
+                .all_cards_of_note(note_id)
+                .map_err(|e| DomainError::CollectionError(e.to_string()))?;
+            card_ids.extend(cards.iter().map(|card| card.id));
+        }
+        if card_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let deck_id = self
+            .get_or_create_deck_id(deck)
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?;
+        let moved = self
+            .collection
+            .set_deck(&card_ids, DeckId(deck_id))
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            .output;
+
+        debug!(moved, deck, "Moved cards");
+        Ok(moved)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    fn set_fields_by_name(
+        &mut self,
+        query: Option<&str>,
+        fields: &std::collections::BTreeMap<String, String>,
+    ) -> Result<usize, DomainError> {
+        let mut affected = 0;
+
+        for note_id in self.search_note_ids(query)? {
+            let mut note = match self.collection.storage.get_note(note_id) {
+                Ok(Some(n)) => n,
+                _ => continue,
+            };
+            let notetype = self
+                .collection
+                .get_notetype(note.notetype_id)
+                .map_err(|e| DomainError::CollectionError(e.to_string()))?
+                .ok_or_else(|| {
+                    DomainError::CollectionError(format!(
+                        "Notetype of note {} not found",
+                        note_id.0
+                    ))
+                })?;
+
+            let mut changed = false;
+            for (index, field) in notetype.fields.iter().enumerate() {
+                let Some(value) = fields.get(&field.name) else {
+                    continue;
+                };
+                if note.fields()[index] != *value {
+                    note.set_field(index, value.as_str())
+                        .map_err(|e| DomainError::CollectionError(e.to_string()))?;
+                    changed = true;
+                }
+            }
+
+            if changed {
+                self.collection
+                    .update_note(&mut note)
+                    .map_err(|e| DomainError::CollectionError(e.to_string()))?;
+                affected += 1;
+            }
+        }
+
+        debug!(affected, "Set fields by name");
+        Ok(affected)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    fn replace_tag(
+        &mut self,
+        query: Option<&str>,
+        old_tag: &str,
+        new_tag: &str,
+    ) -> Result<usize, DomainError> {
+        let note_ids = self.search_note_ids(query)?;
+
+        let mut affected = 0;
+
+        for note_id in note_ids {
+            let mut note = match self.collection.storage.get_note(note_id) {
+                Ok(Some(n)) => n,
+                _ => continue,
+            };
+
+            let had_old_tag = !old_tag.is_empty() && note.tags.iter().any(|t| t == old_tag);
+            let mut changed = false;
+
+            if old_tag.is_empty() {
+                // Bulk add mode: add new_tag if not present
+                if !note.tags.iter().any(|t| t == new_tag) {
+                    note.tags.push(new_tag.to_string());
+                    changed = true;
+                }
+            } else if new_tag.is_empty() {
+                // Bulk remove mode: remove old_tag
+                if had_old_tag {
+                    note.tags.retain(|t| t != old_tag);
+                    changed = true;
+                }
+            } else {
+                // Rename mode: replace old_tag with new_tag
+                if had_old_tag {
+                    note.tags.retain(|t| t != old_tag);
+                    if !note.tags.iter().any(|t| t == new_tag) {
+                        note.tags.push(new_tag.to_string());
+                    }
+                    changed = true;
+                }
+            }
+
+            if changed {
+                self.collection
+                    .update_note(&mut note)
+                    .map_err(|e| DomainError::CollectionError(e.to_string()))?;
+                affected += 1;
+            }
+        }
+
+        debug!(affected, old_tag, new_tag, "Tag replace completed");
+        Ok(affected)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    fn set_note_flag(&mut self, id: i64, flag: Flag) -> Result<usize, DomainError> {
+        let card_ids: Vec<_> = self
+            .collection
+            .storage
+            .all_cards_of_note(NoteId(id))
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            .iter()
+            .map(|card| card.id)
+            .collect();
+        if card_ids.is_empty() {
+            return Err(DomainError::NoteNotFound(id));
+        }
+
+        self.collection
+            .set_card_flag(&card_ids, flag as u32)
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?;
+        info!(note_id = id, ?flag, cards = card_ids.len(), "Set flag");
+        Ok(card_ids.len())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    fn set_card_flag(&mut self, card_id: i64, flag: Flag) -> Result<(), DomainError> {
+        use anki::card::CardId;
+
+        let exists = self
+            .collection
+            .storage
+            .get_card(CardId(card_id))
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            .is_some();
+        if !exists {
+            return Err(DomainError::CollectionError(format!(
+                "Card not found: {}",
+                card_id
+            )));
+        }
+
+        self.collection
+            .set_card_flag(&[CardId(card_id)], flag as u32)
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?;
+        info!(card_id, ?flag, "Set flag");
+        Ok(())
+    }
+
+    fn list_decks(&mut self) -> Result<Vec<(i64, String, usize)>, DomainError> {
+        use anki::decks::DeckKind;
+        use anki::search::SortMode;
+
+        let decks = self
+            .collection
+            .storage
+            .get_all_decks()
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?;
+
+        // Cards in a filtered deck still belong to their home deck (`odid`),
+        // and deleting it would delete them
+        let mut home_cards: HashMap<i64, usize> = HashMap::new();
+        let filtered_cards = self
+            .collection
+            .search_cards("deck:filtered", SortMode::NoOrder)
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?;
+        for card_id in filtered_cards {
+            let card = self
+                .collection
+                .storage
+                .get_card(card_id)
+                .map_err(|e| DomainError::CollectionError(e.to_string()))?;
+            if let Some(card) = card {
+                *home_cards.entry(card.original_deck_id.0).or_default() += 1;
+            }
+        }
+
+        let mut result = Vec::new();
+        for deck in decks {
+            if !matches!(deck.kind, DeckKind::Normal(_)) {
+                continue;
+            }
+            let cards = self
+                .collection
+                .storage
+                .all_cards_in_single_deck(deck.id)
+                .map_err(|e| DomainError::CollectionError(e.to_string()))?
+                .len()
+                + home_cards.get(&deck.id.0).copied().unwrap_or(0);
+            result.push((deck.id.0, deck.human_name(), cards));
+        }
+        debug!(count = result.len(), "Listed decks");
+        Ok(result)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    fn delete_decks(&mut self, ids: &[i64]) -> Result<(), DomainError> {
+        use anki::decks::DeckId;
+
+        let deck_ids: Vec<DeckId> = ids.iter().map(|&id| DeckId(id)).collect();
+        let cards = self
+            .collection
+            .remove_decks_and_child_cards(&deck_ids)
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            .output;
+        info!(decks = ids.len(), cards, "Deleted decks");
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    fn rename_deck(&mut self, old: &str, new: &str) -> Result<(), DomainError> {
+        let deck_id = self
+            .collection
+            .get_deck_id(old)
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            .ok_or_else(|| DomainError::DeckNotFound(old.to_string()))?;
+        // Anki would silently make the name unique, merging is not what's asked for
+        if self
+            .collection
+            .get_deck_id(new)
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            .is_some()
+        {
+            return Err(DomainError::CollectionError(format!(
+                "Deck '{}' already exists",
+                new
+            )));
+        }
+
+        self.collection
+            .rename_deck(deck_id, new)
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?;
+        info!(old, new, "Renamed deck");
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    fn problem_notes(
+        &mut self,
+        min_lapses: u32,
+        deck: Option<&str>,
+    ) -> Result<Vec<ProblemNote>, DomainError> {
+        use std::collections::hash_map::Entry;
+
+        let mut query = format!("(prop:lapses>={} OR tag:leech)", min_lapses);
+        if let Some(deck) = deck {
+            query = format!("\"deck:{}\" {}", deck.replace('"', "\\\""), query);
+        }
+
+        // Keep the card with the most lapses per note
+        let mut worst_cards: HashMap<NoteId, anki::card::Card> = HashMap::new();
+        for card in self.read_searched_cards(query.as_str())? {
+            match worst_cards.get(&card.note_id) {
+                Some(worst) if worst.lapses >= card.lapses => {}
+                _ => {
+                    worst_cards.insert(card.note_id, card);
+                }
+            }
+        }
+
+        let note_ids: Vec<NoteId> = worst_cards.keys().copied().collect();
+        let mut deck_names: HashMap<_, String> = HashMap::new();
+        let mut notes = Vec::with_capacity(worst_cards.len());
+        for note in self.read_notes(&note_ids)? {
+            let note_id = note.id;
+            let card = &worst_cards[&note_id];
+            let deck = match deck_names.entry(card.deck_id) {
+                Entry::Occupied(entry) => entry.get().clone(),
+                Entry::Vacant(entry) => {
+                    let name = self
+                        .collection
+                        .get_deck(card.deck_id)
+                        .map_err(|e| DomainError::CollectionError(e.to_string()))?
+                        .map(|deck| deck.human_name())
+                        .unwrap_or_default();
+                    entry.insert(name).clone()
+                }
+            };
+            let front = note
+                .fields()
+                .first()
+                .map(String::as_str)
+                .unwrap_or_default();
+            notes.push(ProblemNote {
+                note_id: note_id.0,
+                first_line: crate::util::text::extract_first_line(front),
+                deck,
+                lapses: card.lapses,
+                leech: note.tags.iter().any(|t| t.eq_ignore_ascii_case("leech")),
+            });
+        }
+        debug!(count = notes.len(), min_lapses, "Found problem notes");
+        Ok(notes)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    fn clone_note(&mut self, id: i64, deck: Option<&str>) -> Result<i64, DomainError> {
+        let note = self
+            .collection
+            .storage
+            .get_note(NoteId(id))
+            .map_err(|_| DomainError::NoteNotFound(id))?
+            .ok_or(DomainError::NoteNotFound(id))?;
 
-        // Get note IDs based on query
-        let note_ids = match query {
-            Some(q) if !q.is_empty() => self
-                .collection
-                .search_notes_unordered(q)
+        let deck_id = match deck {
+            Some(name) => self
+                .get_or_create_deck_id(name)
                 .map_err(|e| DomainError::CollectionError(e.to_string()))?,
-            _ => {
-                let search_node = SearchNode::WholeCollection;
-                self.collection
-                    .search_notes_unordered(search_node)
-                    .map_err(|e| DomainError::CollectionError(e.to_string()))?
-            }
+            None => self
+                .collection
+                .storage
+                .all_cards_of_note(NoteId(id))
+                .map_err(|e| DomainError::CollectionError(e.to_string()))?
+                .first()
+                .map(|card| card.deck_id.0)
+                .unwrap_or(1), // Default deck
         };
 
-        let mut affected = 0;
+        let new_id = self
+            .add_note_with_fields(note.notetype_id.0, deck_id, note.fields(), &note.tags)
+            .map_err(|e| DomainError::CollectionError(format!("Failed to clone note: {}", e)))?;
+        info!(note_id = id, new_note_id = new_id, "Cloned note");
+        Ok(new_id)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    fn transfer_notes(&mut self, query: Option<&str>) -> Result<Vec<TransferNote>, DomainError> {
+        use anki::notetype::NotetypeKind;
+
+        let mut note_ids = self.search_note_ids(query)?;
+        note_ids.sort();
+
+        let notetypes: HashMap<NotetypeId, NotetypeDefinition> = self
+            .collection
+            .get_all_notetypes()
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            .into_iter()
+            .map(|nt| {
+                let definition = NotetypeDefinition {
+                    name: nt.name.clone(),
+                    cloze: nt.config.kind() == NotetypeKind::Cloze,
+                    fields: nt.fields.iter().map(|f| f.name.clone()).collect(),
+                    templates: nt
+                        .templates
+                        .iter()
+                        .map(|t| CardTemplateDefinition {
+                            name: t.name.clone(),
+                            front: t.config.q_format.clone(),
+                            back: t.config.a_format.clone(),
+                        })
+                        .collect(),
+                    css: nt.config.css.clone(),
+                };
+                (nt.id, definition)
+            })
+            .collect();
+        let deck_names: HashMap<_, String> = self
+            .collection
+            .storage
+            .get_all_decks()
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            .into_iter()
+            .map(|deck| (deck.id, deck.human_name()))
+            .collect();
 
+        let storage = &self.collection.storage;
+        let mut notes = Vec::with_capacity(note_ids.len());
         for note_id in note_ids {
-            let mut note = match self.collection.storage.get_note(note_id) {
-                Ok(Some(n)) => n,
-                _ => continue,
+            let Some(note) = storage
+                .get_note(note_id)
+                .map_err(|e| DomainError::CollectionError(e.to_string()))?
+            else {
+                continue;
             };
+            let notetype = notetypes
+                .get(&note.notetype_id)
+                .ok_or_else(|| DomainError::CollectionError("Notetype not found".to_string()))?;
+            let deck = storage
+                .all_cards_of_note(note_id)
+                .map_err(|e| DomainError::CollectionError(e.to_string()))?
+                .first()
+                .and_then(|card| deck_names.get(&card.deck_id).cloned())
+                .unwrap_or_else(|| "Default".to_string());
+
+            notes.push(TransferNote {
+                id: note.id.0,
+                notetype: notetype.clone(),
+                fields: note.fields().to_vec(),
+                tags: note.tags.clone(),
+                deck,
+                media: crate::util::text::referenced_media(&note.fields().join("\n")),
+            });
+        }
+        debug!(count = notes.len(), "Read notes for transfer");
+        Ok(notes)
+    }
 
-            let had_old_tag = !old_tag.is_empty() && note.tags.iter().any(|t| t == old_tag);
-            let mut changed = false;
+    #[instrument(level = "debug", skip(self, note), fields(note_id = note.id))]
+    fn add_transferred_note(&mut self, note: &TransferNote) -> Result<i64, DomainError> {
+        use anki::notetype::{CardTemplate, NoteField, Notetype, NotetypeKind};
 
-            if old_tag.is_empty() {
-                // Bulk add mode: add new_tag if not present
-                if !note.tags.iter().any(|t| t == new_tag) {
-                    note.tags.push(new_tag.to_string());
-                    changed = true;
+        let definition = &note.notetype;
+        let existing = self
+            .collection
+            .get_notetype_by_name(&definition.name)
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?;
+        let notetype_id = match existing {
+            Some(notetype) => {
+                let fields: Vec<&str> = notetype.fields.iter().map(|f| f.name.as_str()).collect();
+                if fields != definition.fields {
+                    return Err(DomainError::CollectionError(format!(
+                        "Notetype '{}' has fields {} in the target collection, but {} in the source",
+                        definition.name,
+                        fields.join(", "),
+                        definition.fields.join(", ")
+                    )));
                 }
-            } else if new_tag.is_empty() {
-                // Bulk remove mode: remove old_tag
-                if had_old_tag {
-                    note.tags.retain(|t| t != old_tag);
-                    changed = true;
+                notetype.id.0
+            }
+            None => {
+                let mut notetype = Notetype {
+                    name: definition.name.clone(),
+                    ..Default::default()
+                };
+                notetype.config.css = definition.css.clone();
+                if definition.cloze {
+                    notetype.config.kind = NotetypeKind::Cloze as i32;
                 }
-            } else {
-                // Rename mode: replace old_tag with new_tag
-                if had_old_tag {
-                    note.tags.retain(|t| t != old_tag);
-                    if !note.tags.iter().any(|t| t == new_tag) {
-                        note.tags.push(new_tag.to_string());
-                    }
-                    changed = true;
+                for field in &definition.fields {
+                    notetype.fields.push(NoteField::new(field.as_str()));
+                }
+                for template in &definition.templates {
+                    notetype.templates.push(CardTemplate::new(
+                        template.name.as_str(),
+                        template.front.as_str(),
+                        template.back.as_str(),
+                    ));
                 }
-            }
-
-            if changed {
                 self.collection
-                    .update_note(&mut note)
+                    .add_notetype(&mut notetype, false)
                     .map_err(|e| DomainError::CollectionError(e.to_string()))?;
-                affected += 1;
+                info!(name = %definition.name, "Created notetype");
+                notetype.id.0
             }
-        }
+        };
 
-        debug!(affected, old_tag, new_tag, "Tag replace completed");
-        Ok(affected)
+        let deck_id = self
+            .get_or_create_deck_id(&note.deck)
+            .map_err(|e| DomainError::CollectionError(e.to_string()))?;
+        let new_id = self
+            .add_note_with_fields(notetype_id, deck_id, &note.fields, &note.tags)
+            .map_err(|e| DomainError::CollectionError(format!("Failed to add note: {}", e)))?;
+        debug!(new_note_id = new_id, "Added transferred note");
+        Ok(new_id)
     }
 }
 
@@ -697,6 +1882,94 @@ mod tests {
         assert_eq!(first_id, second_id);
     }
 
+    #[test]
+    fn given_cloze_notetype_when_getting_info_then_lists_fields_and_templates() {
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+        let cloze_id = repo.find_or_create_cloze_notetype().unwrap();
+        let notetypes = repo.list_notetypes().unwrap();
+        let (_, name) = notetypes.iter().find(|(id, _)| *id == cloze_id).unwrap();
+
+        let info = repo.notetype_info(name).unwrap();
+
+        assert_eq!(info.id, cloze_id);
+        assert!(info.cloze);
+        assert_eq!(info.fields, vec!["Text", "Back Extra"]);
+        assert_eq!(info.templates.len(), 1);
+    }
+
+    #[test]
+    fn given_css_and_front_when_updating_templates_then_notetype_is_changed() {
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+        let notetype_id = repo.find_or_create_basic_notetype(Some("Basic")).unwrap();
+        let update = TemplateUpdate {
+            css: Some(".card { color: red; }".to_string()),
+            front: Some("<div>{{Front}}</div>".to_string()),
+            ..Default::default()
+        };
+
+        repo.update_notetype_templates("Basic", &update).unwrap();
+
+        let notetype = repo
+            .collection
+            .get_notetype(NotetypeId(notetype_id))
+            .unwrap()
+            .unwrap();
+        assert_eq!(notetype.config.css, ".card { color: red; }");
+        assert_eq!(
+            notetype.templates[0].config.q_format,
+            "<div>{{Front}}</div>"
+        );
+        assert!(notetype.templates[0].config.a_format.contains("{{Back}}"));
+    }
+
+    #[test]
+    fn given_math_macros_when_updating_templates_then_every_template_defines_them() {
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+        let notetype_id = repo.find_or_create_basic_notetype(Some("Basic")).unwrap();
+        let update = TemplateUpdate {
+            math_macros: Some(r"\def\R{\mathbb{R}}".to_string()),
+            ..Default::default()
+        };
+
+        repo.update_notetype_templates("Basic", &update).unwrap();
+        repo.update_notetype_templates("Basic", &update).unwrap();
+
+        let notetype = repo
+            .collection
+            .get_notetype(NotetypeId(notetype_id))
+            .unwrap()
+            .unwrap();
+        let config = &notetype.templates[0].config;
+        assert_eq!(config.q_format.matches(r"\def\R").count(), 1);
+        assert!(config.q_format.contains("{{Front}}"));
+        assert_eq!(config.a_format.matches(r"\def\R").count(), 1);
+    }
+
+    #[test]
+    fn given_unknown_template_when_updating_templates_then_fails() {
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+        let update = TemplateUpdate {
+            front: Some("{{Front}}".to_string()),
+            template: Some("Card 9".to_string()),
+            ..Default::default()
+        };
+
+        let err = repo
+            .update_notetype_templates("Basic", &update)
+            .unwrap_err();
+
+        assert!(err.to_string().contains("Available templates"));
+    }
+
+    #[test]
+    fn given_unknown_notetype_when_getting_info_then_returns_not_found() {
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+
+        let result = repo.notetype_info("No Such Type");
+
+        assert!(matches!(result, Err(DomainError::NotetypeNotFound(_))));
+    }
+
     #[test]
     fn given_new_collection_when_finding_cloze_notetype_then_creates_and_returns_id() {
         let (_temp_dir, mut repo) = create_test_collection().unwrap();
@@ -748,6 +2021,37 @@ mod tests {
         assert!(note.back.contains("Back"));
     }
 
+    #[test]
+    fn given_basic_note_when_reading_records_then_has_fields_by_name_and_deck() {
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+        let note_id = repo
+            .create_basic_note(
+                "Front",
+                "Back",
+                "Rust::Basics",
+                &["rust".to_string()],
+                Some("Basic"),
+            )
+            .unwrap();
+
+        let records: Vec<NoteRecord> = repo
+            .note_records_iter(None)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.id, note_id);
+        assert_eq!(record.notetype, "Basic");
+        assert_eq!(record.fields[0].0, "Front");
+        assert_eq!(record.fields[1].0, "Back");
+        assert!(record.fields[1].1.contains("Back"));
+        assert_eq!(record.tags, vec!["rust"]);
+        assert_eq!(record.decks, vec!["Rust::Basics"]);
+        assert_eq!(record.created, note_id / 1000);
+    }
+
     #[test]
     fn given_cloze_text_when_creating_note_then_returns_note_id() {
         let (_temp_dir, mut repo) = create_test_collection().unwrap();
@@ -1021,6 +2325,267 @@ mod tests {
 
     // --- T011: Integration tests for replace_tag ---
 
+    #[test]
+    fn given_note_when_cloning_then_copies_fields_and_tags_into_new_note() {
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+        let id = repo
+            .create_basic_note("Q", "A", "Default", &["rust".to_string()], Some("Basic"))
+            .unwrap();
+
+        let new_id = repo.clone_note(id, Some("Scratch")).unwrap();
+
+        assert_ne!(new_id, id);
+        let copy = repo.get_note(new_id).unwrap();
+        assert_eq!(copy.front, "Q");
+        assert_eq!(copy.back, "A");
+        assert_eq!(copy.tags, vec!["rust".to_string()]);
+    }
+
+    #[test]
+    fn given_note_when_transferring_to_other_collection_then_notetype_and_deck_are_created() {
+        let (_source_dir, mut source) = create_test_collection().unwrap();
+        let (_target_dir, mut target) = create_test_collection().unwrap();
+        let id = source
+            .create_basic_note(
+                "Q <img src=\"tree.png\">",
+                "A",
+                "Shared::Rust",
+                &["rust".to_string()],
+                Some("Basic"),
+            )
+            .unwrap();
+
+        let notes = source.transfer_notes(Some("deck:Shared")).unwrap();
+        let mut note = notes[0].clone();
+        note.notetype.name = "Basic (shared)".to_string();
+        let new_id = target.add_transferred_note(&note).unwrap();
+
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].id, id);
+        assert_eq!(notes[0].deck, "Shared::Rust");
+        assert_eq!(notes[0].media, vec!["tree.png"]);
+        let copy = target.get_note(new_id).unwrap();
+        assert_eq!(copy.front, "Q <img src=\"tree.png\">");
+        assert_eq!(copy.model_name, "Basic (shared)");
+        assert_eq!(copy.tags, vec!["rust".to_string()]);
+        let info = target.notetype_info("Basic (shared)").unwrap();
+        assert_eq!(info.fields, vec!["Front", "Back"]);
+    }
+
+    #[test]
+    fn given_notetype_with_other_fields_when_adding_transferred_note_then_fails() {
+        let (_source_dir, mut source) = create_test_collection().unwrap();
+        let (_target_dir, mut target) = create_test_collection().unwrap();
+        source
+            .create_basic_note("Q", "A", "Default", &[], Some("Basic"))
+            .unwrap();
+
+        let mut note = source.transfer_notes(None).unwrap().remove(0);
+        note.notetype.fields = vec!["Question".to_string(), "Answer".to_string()];
+        let err = target.add_transferred_note(&note).unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("Notetype 'Basic' has fields Front, Back"));
+    }
+
+    #[test]
+    fn given_query_when_reading_note_fields_then_returns_matching_notes_in_order() {
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+        let id1 = repo
+            .create_basic_note("Q1", "A1", "Default", &["rust".to_string()], Some("Basic"))
+            .unwrap();
+        repo.create_basic_note("Q2", "A2", "Default", &[], Some("Basic"))
+            .unwrap();
+
+        let notes = repo.note_fields(Some("tag:rust")).unwrap();
+
+        assert_eq!(notes, vec![(id1, vec!["Q1".to_string(), "A1".to_string()])]);
+    }
+
+    #[test]
+    fn given_rule_fields_when_setting_by_name_then_updates_matching_notes() {
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+        let id = repo
+            .create_basic_note("Q1", "A1", "Default", &["inbox".to_string()], Some("Basic"))
+            .unwrap();
+        repo.create_basic_note("Q2", "A2", "Default", &[], Some("Basic"))
+            .unwrap();
+        let fields = [
+            ("Back".to_string(), "Moved".to_string()),
+            ("Source".to_string(), "ignored".to_string()),
+        ]
+        .into();
+
+        let affected = repo.set_fields_by_name(Some("tag:inbox"), &fields).unwrap();
+        let moved = repo.move_notes(Some("tag:inbox"), "Sorted").unwrap();
+
+        assert_eq!(affected, 1);
+        assert_eq!(moved, 1);
+        assert_eq!(repo.get_note(id).unwrap().back, "Moved");
+    }
+
+    #[test]
+    fn given_note_when_flagging_then_flags_all_its_cards() {
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+        let id = repo
+            .create_basic_note("Q", "A", "Default", &[], Some("Basic"))
+            .unwrap();
+
+        let flagged = repo.set_note_flag(id, Flag::Red).unwrap();
+
+        assert_eq!(flagged, 1);
+        let cards = repo
+            .collection
+            .storage
+            .all_cards_of_note(NoteId(id))
+            .unwrap();
+        assert_eq!(cards[0].flags(), Flag::Red as u8);
+    }
+
+    #[test]
+    fn given_unknown_deck_options_preset_when_creating_deck_then_lists_presets() {
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+
+        let err = repo
+            .get_or_create_deck_with_options("Imported", Some("Slow"))
+            .unwrap_err();
+
+        assert!(err.to_string().contains("Available presets: Default"));
+        let id = repo
+            .get_or_create_deck_with_options("Imported", Some("Default"))
+            .unwrap();
+        assert_eq!(repo.get_or_create_deck_id("Imported").unwrap(), id);
+    }
+
+    #[test]
+    fn given_deck_options_preset_when_creating_deck_then_only_new_deck_uses_it() {
+        // The fixture's second preset, next to Default (ID 1)
+        const PATTERN_PRESET_ID: i64 = 1688966502175;
+        let temp_dir = TempDir::new().unwrap();
+        let collection_path = temp_dir.path().join("collection.anki2");
+        std::fs::copy(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/fixtures/test_collection/User 1/collection.anki2"),
+            &collection_path,
+        )
+        .unwrap();
+        let mut repo = AnkiRepository::new(&collection_path).unwrap();
+        let config_id = |repo: &mut AnkiRepository, id: i64| {
+            repo.collection
+                .get_deck(anki::decks::DeckId(id))
+                .unwrap()
+                .unwrap()
+                .config_id()
+                .map(|config_id| config_id.0)
+        };
+
+        let created = repo
+            .get_or_create_deck_with_options("Imported", Some("pattern"))
+            .unwrap();
+        let existing = repo
+            .get_or_create_deck_with_options("algo", Some("pattern"))
+            .unwrap();
+
+        assert_eq!(config_id(&mut repo, created), Some(PATTERN_PRESET_ID));
+        assert_eq!(config_id(&mut repo, existing), Some(1));
+    }
+
+    #[test]
+    fn given_new_deck_when_listing_decks_then_reports_cards_per_deck() {
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+        repo.create_basic_note("Q", "A", "Rust::Ownership", &[], Some("Basic"))
+            .unwrap();
+
+        let decks = repo.list_decks().unwrap();
+
+        let cards_in = |name: &str| {
+            decks
+                .iter()
+                .find(|(_, deck, _)| deck == name)
+                .map(|(_, _, cards)| *cards)
+        };
+        assert_eq!(cards_in("Rust"), Some(0));
+        assert_eq!(cards_in("Rust::Ownership"), Some(1));
+    }
+
+    #[test]
+    fn given_card_in_filtered_deck_when_listing_decks_then_counts_it_in_home_deck() {
+        use anki::decks::DeckId;
+
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+        let note_id = repo
+            .create_basic_note("Q", "A", "Home", &[], Some("Basic"))
+            .unwrap();
+        let home = repo.get_or_create_deck_id("Home").unwrap();
+        let filtered = repo.get_or_create_deck_id("Filtered").unwrap();
+        // What building a filtered deck does to the card
+        let mut card = repo
+            .collection
+            .storage
+            .all_cards_of_note(NoteId(note_id))
+            .unwrap()
+            .remove(0);
+        card.original_deck_id = DeckId(home);
+        card.deck_id = DeckId(filtered);
+        repo.collection.update_card(&mut card).unwrap();
+
+        let decks = repo.list_decks().unwrap();
+
+        let cards_in = |id: i64| {
+            decks
+                .iter()
+                .find(|(deck_id, _, _)| *deck_id == id)
+                .map(|(_, _, cards)| *cards)
+        };
+        assert_eq!(cards_in(home), Some(1));
+    }
+
+    #[test]
+    fn given_deck_when_renaming_then_subdecks_move_along() {
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+        repo.create_basic_note("Q", "A", "Old::Child", &[], Some("Basic"))
+            .unwrap();
+
+        repo.rename_deck("Old", "New").unwrap();
+
+        let names: Vec<_> = repo
+            .list_decks()
+            .unwrap()
+            .into_iter()
+            .map(|(_, name, _)| name)
+            .collect();
+        assert!(names.contains(&"New::Child".to_string()));
+        assert!(!names.contains(&"Old".to_string()));
+        assert!(matches!(
+            repo.rename_deck("Old", "Other"),
+            Err(DomainError::DeckNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn given_leech_tagged_note_when_reporting_problems_then_listed_with_deck() {
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+        let id = repo
+            .create_basic_note(
+                "Hard\nquestion",
+                "A",
+                "Rust",
+                &["leech".to_string()],
+                Some("Basic"),
+            )
+            .unwrap();
+        repo.create_basic_note("Easy", "A", "Rust", &[], Some("Basic"))
+            .unwrap();
+
+        let notes = repo.problem_notes(8, Some("Rust")).unwrap();
+
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].note_id, id);
+        assert_eq!(notes[0].deck, "Rust");
+        assert!(notes[0].leech);
+    }
+
     #[test]
     fn given_notes_with_tag_when_replacing_then_renamed() {
         let (_temp_dir, mut repo) = create_test_collection().unwrap();
@@ -1073,4 +2638,41 @@ mod tests {
         assert_eq!(affected, 1);
         assert!(!repo.get_note(id1).unwrap().tags.contains(&"obsolete".to_string()));
     }
+
+    #[test]
+    fn given_card_in_filtered_deck_when_locating_and_moving_then_uses_home_deck() {
+        use anki::decks::DeckId;
+
+        let (_temp_dir, mut repo) = create_test_collection().unwrap();
+        let note_id = repo
+            .create_basic_note("Q", "A", "Home", &[], Some("Basic"))
+            .unwrap();
+        let home = repo.get_or_create_deck_id("Home").unwrap();
+        let filtered = repo.get_or_create_deck_id("Filtered").unwrap();
+        let target = repo.get_or_create_deck_id("Target").unwrap();
+        // What building a filtered deck does to the card
+        let mut card = repo
+            .collection
+            .storage
+            .all_cards_of_note(NoteId(note_id))
+            .unwrap()
+            .remove(0);
+        card.original_deck_id = DeckId(home);
+        card.deck_id = DeckId(filtered);
+        repo.collection.update_card(&mut card).unwrap();
+
+        let (deck, _) = repo.note_location(note_id).unwrap().unwrap();
+        let moved = repo.move_note_cards(note_id, target).unwrap();
+
+        assert_eq!(deck, "Home");
+        assert_eq!(moved, 1);
+        let card = repo
+            .collection
+            .storage
+            .all_cards_of_note(NoteId(note_id))
+            .unwrap()
+            .remove(0);
+        assert_eq!(card.deck_id, DeckId(filtered));
+        assert_eq!(card.original_deck_id, DeckId(target));
+    }
 }
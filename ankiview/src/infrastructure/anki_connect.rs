@@ -0,0 +1,169 @@
+// src/infrastructure/anki_connect.rs
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use tracing::debug;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Client for the AnkiConnect add-on's HTTP API in a running Anki
+///
+/// Used where Anki itself has to act (e.g. showing a note in its browser);
+/// everything else goes through the collection file.
+pub struct AnkiConnectClient {
+    address: String,
+}
+
+#[derive(Deserialize)]
+struct Reply {
+    #[serde(default)]
+    result: Value,
+    error: Option<String>,
+}
+
+impl Default for AnkiConnectClient {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_ADDRESS)
+    }
+}
+
+impl AnkiConnectClient {
+    /// AnkiConnect's default listen address
+    pub const DEFAULT_ADDRESS: &'static str = "127.0.0.1:8765";
+
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+        }
+    }
+
+    /// Open Anki's browse window on `query` (e.g. `nid:123`)
+    /// Returns the IDs of the cards it shows
+    pub fn gui_browse(&self, query: &str) -> Result<Vec<i64>> {
+        let result = self.request("guiBrowse", json!({ "query": query }))?;
+        serde_json::from_value(result).context("Unexpected guiBrowse result from AnkiConnect")
+    }
+
+    fn request(&self, action: &str, params: Value) -> Result<Value> {
+        let body = json!({ "action": action, "version": 6, "params": params }).to_string();
+        debug!(address = %self.address, action, "AnkiConnect request");
+
+        let unreachable = || {
+            format!(
+                "Could not reach AnkiConnect at {}; is Anki running with the AnkiConnect add-on?",
+                self.address
+            )
+        };
+        let address = self
+            .address
+            .to_socket_addrs()
+            .with_context(unreachable)?
+            .next()
+            .with_context(unreachable)?;
+        let mut stream =
+            TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).with_context(unreachable)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.address,
+            body.len(),
+            body
+        )
+        .context("Failed to send AnkiConnect request")?;
+
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .context("Failed to read AnkiConnect response")?;
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .context("Malformed AnkiConnect response")?;
+        let status = head.lines().next().unwrap_or_default();
+        if !status.contains(" 200") {
+            anyhow::bail!("AnkiConnect answered {}", status);
+        }
+
+        let reply: Reply =
+            serde_json::from_str(body).context("Failed to parse AnkiConnect response")?;
+        if let Some(error) = reply.error {
+            anyhow::bail!("AnkiConnect {} failed: {}", action, error);
+        }
+        Ok(reply.result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Answer one request with `reply`; returns the address and the request text
+    fn serve_once(reply: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = [0u8; 4096];
+            let len = stream.read(&mut buffer).unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                reply.len(),
+                reply
+            )
+            .unwrap();
+            String::from_utf8_lossy(&buffer[..len]).into_owned()
+        });
+        (address, handle)
+    }
+
+    #[test]
+    fn given_anki_connect_when_browsing_then_sends_gui_browse_and_returns_cards() {
+        let (address, server) = serve_once(r#"{"result": [1502098034045], "error": null}"#);
+
+        let cards = AnkiConnectClient::new(address)
+            .gui_browse("nid:1502098034044")
+            .unwrap();
+
+        assert_eq!(cards, vec![1502098034045]);
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST / HTTP/1.1"));
+        assert!(request.contains(r#""action":"guiBrowse""#));
+        assert!(request.contains(r#""query":"nid:1502098034044""#));
+    }
+
+    #[test]
+    fn given_anki_connect_error_when_browsing_then_returns_error() {
+        let (address, _server) =
+            serve_once(r#"{"result": null, "error": "collection is not available"}"#);
+
+        let err = AnkiConnectClient::new(address)
+            .gui_browse("nid:1")
+            .unwrap_err();
+
+        assert!(err.to_string().contains("collection is not available"));
+    }
+
+    #[test]
+    fn given_nothing_listening_when_browsing_then_explains_anki_must_run() {
+        // Bind and drop to get a port nobody listens on
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+
+        let err = AnkiConnectClient::new(address)
+            .gui_browse("nid:1")
+            .unwrap_err();
+
+        assert!(err.to_string().contains("is Anki running"));
+    }
+}
@@ -0,0 +1,194 @@
+// src/infrastructure/backup.rs
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags};
+use serde::Deserialize;
+use std::path::Path;
+use tracing::debug;
+
+/// Scheduling of one card as stored in a collection's `cards` table
+#[derive(Debug, Clone, PartialEq)]
+pub struct CardScheduling {
+    /// Card template (or cloze number - 1) the card belongs to
+    pub template_idx: u16,
+    /// `cards.type`: 0 new, 1 learning, 2 review, 3 relearning
+    pub ctype: u8,
+    /// `cards.queue`: as `ctype`, plus -1 suspended, -2/-3 buried, 3 day learning
+    pub queue: i8,
+    pub due: i32,
+    pub interval: u32,
+    pub ease_factor: u16,
+    pub reps: u32,
+    pub lapses: u32,
+    /// `cards.flags`: the card's flag color
+    pub flags: u8,
+    /// `cards.data`: FSRS memory state and custom data
+    pub data: CardData,
+}
+
+/// The JSON object in `cards.data`, with Anki's short keys
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct CardData {
+    #[serde(rename = "pos")]
+    pub original_position: Option<u32>,
+    #[serde(rename = "s")]
+    pub stability: Option<f32>,
+    #[serde(rename = "d")]
+    pub difficulty: Option<f32>,
+    #[serde(rename = "dr")]
+    pub desired_retention: Option<f32>,
+    pub decay: Option<f32>,
+    #[serde(rename = "lrt")]
+    pub last_review_time: Option<i64>,
+    #[serde(rename = "cd", default)]
+    pub custom_data: String,
+}
+
+impl CardData {
+    /// Parse `cards.data`; empty (cards never reviewed with FSRS) or
+    /// unreadable data gives no memory state
+    fn parse(data: &str) -> Self {
+        if data.is_empty() {
+            return Self::default();
+        }
+        serde_json::from_str(data).unwrap_or_else(|err| {
+            debug!(%err, data, "Ignoring unreadable card data in backup");
+            Self::default()
+        })
+    }
+}
+
+/// Read-only access to an older copy of a collection, e.g. an unpacked backup
+///
+/// Reads the SQLite file directly, so the copy never has to be opened (and
+/// possibly upgraded) as a collection.
+pub struct BackupReader {
+    connection: Connection,
+}
+
+impl BackupReader {
+    pub fn open(backup_path: &Path) -> Result<Self> {
+        if !backup_path.is_file() {
+            anyhow::bail!("Backup collection not found: {}", backup_path.display());
+        }
+        let connection = Connection::open_with_flags(
+            backup_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .with_context(|| format!("Failed to open {}", backup_path.display()))?;
+        // Packaged backups (.colpkg) are zip files; fail here rather than on the first note
+        connection
+            .query_row("SELECT count(*) FROM cards", [], |row| row.get::<_, i64>(0))
+            .with_context(|| {
+                format!(
+                    "{} is not an unpacked collection (.anki2 / .anki21)",
+                    backup_path.display()
+                )
+            })?;
+        Ok(Self { connection })
+    }
+
+    /// Scheduling of the cards of `note_id` in the backup, by template; empty
+    /// if the backup doesn't have the note
+    pub fn card_scheduling(&self, note_id: i64) -> Result<Vec<CardScheduling>> {
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT ord, type, queue, due, ivl, factor, reps, lapses, flags, data \
+                 FROM cards WHERE nid = ?1 ORDER BY ord",
+            )
+            .context("Failed to read cards of backup")?;
+        let cards = statement
+            .query_map([note_id], |row| {
+                Ok(CardScheduling {
+                    template_idx: row.get(0)?,
+                    ctype: row.get(1)?,
+                    queue: row.get(2)?,
+                    due: row.get(3)?,
+                    interval: row.get(4)?,
+                    ease_factor: row.get(5)?,
+                    reps: row.get(6)?,
+                    lapses: row.get(7)?,
+                    flags: row.get(8)?,
+                    data: CardData::parse(&row.get::<_, String>(9)?),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        debug!(
+            note_id,
+            cards = cards.len(),
+            "Read card scheduling from backup"
+        );
+        Ok(cards)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Backup with just the columns the scheduling query uses
+    fn create_backup(dir: &TempDir) -> std::path::PathBuf {
+        let path = dir.path().join("backup.anki2");
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                r#"CREATE TABLE cards (id INTEGER PRIMARY KEY, nid INTEGER, ord INTEGER,
+                                     type INTEGER, queue INTEGER, due INTEGER, ivl INTEGER,
+                                     factor INTEGER, reps INTEGER, lapses INTEGER,
+                                     flags INTEGER, data TEXT);
+                 INSERT INTO cards VALUES
+                     (11, 1, 1, 0, 0, 7, 0, 0, 0, 0, 0, ''),
+                     (10, 1, 0, 2, 2, 1234, 21, 2500, 9, 1, 3,
+                      '{"s":12.5,"d":4.2,"lrt":1700000000,"cd":"{\"x\":1}"}'),
+                     (20, 2, 0, 0, -1, 3, 0, 0, 0, 0, 0, '');"#,
+            )
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn given_note_in_backup_when_reading_scheduling_then_returns_cards_by_template() {
+        let temp_dir = TempDir::new().unwrap();
+        let reader = BackupReader::open(&create_backup(&temp_dir)).unwrap();
+
+        let cards = reader.card_scheduling(1).unwrap();
+
+        assert_eq!(cards.len(), 2);
+        assert_eq!(
+            cards[0],
+            CardScheduling {
+                template_idx: 0,
+                ctype: 2,
+                queue: 2,
+                due: 1234,
+                interval: 21,
+                ease_factor: 2500,
+                reps: 9,
+                lapses: 1,
+                flags: 3,
+                data: CardData {
+                    stability: Some(12.5),
+                    difficulty: Some(4.2),
+                    last_review_time: Some(1_700_000_000),
+                    custom_data: r#"{"x":1}"#.to_string(),
+                    ..Default::default()
+                },
+            }
+        );
+        assert_eq!(cards[1].template_idx, 1);
+        assert_eq!(cards[1].data, CardData::default());
+        assert!(reader.card_scheduling(3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn given_file_that_is_no_collection_when_opening_then_errors() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("backup.colpkg");
+        std::fs::write(&path, b"PK\x03\x04 not a database").unwrap();
+
+        let err = BackupReader::open(&path).err().unwrap();
+
+        assert!(format!("{:#}", err).contains("is not an unpacked collection"));
+    }
+}
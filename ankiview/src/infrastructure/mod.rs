@@ -1,6 +1,9 @@
 // src/infrastructure/mod.rs
 pub mod anki;
+pub mod anki_connect;
+pub mod backup;
 pub mod note_template;
 pub mod renderer;
+pub mod revlog;
 
 pub use anki::AnkiRepository;
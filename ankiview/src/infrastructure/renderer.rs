@@ -1,18 +1,51 @@
 // src/infrastructure/renderer.rs
 use anyhow::{Context, Result};
-use regex::Regex;
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::sync::Arc;
+use std::time::Duration;
 use tempfile::Builder;
-use tracing::instrument;
+use tracing::{debug, instrument, warn};
 
 use crate::constants::BROWSER_LAUNCH_DELAY_MS;
 
+/// Browser command to use: `--browser`, then `viewer.browser` from the config,
+/// then the first entry of `$BROWSER` (a `:`-separated list by convention).
+/// `None` means the platform's opener (`open`, `xdg-open`, `start`).
+pub fn browser_command(
+    flag: Option<&str>,
+    config: Option<&str>,
+    env: Option<&str>,
+) -> Option<String> {
+    flag.or(config)
+        .or_else(|| env.and_then(|value| value.split(':').find(|entry| !entry.trim().is_empty())))
+        .map(str::to_string)
+}
+
+/// Whether `open_in_browser` could show the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenOutcome {
+    Opened,
+    /// No usable browser (headless system or missing opener), with the reason.
+    /// The file is kept so it can be opened by hand.
+    NoBrowser(String),
+}
+
+/// Why no graphical browser can be started, judging by the environment:
+/// Linux and BSD desktops set DISPLAY (X11) or WAYLAND_DISPLAY
+pub fn headless_reason(display: Option<&str>, wayland_display: Option<&str>) -> Option<String> {
+    let unset = |value: Option<&str>| value.is_none_or(|v| v.trim().is_empty());
+    (cfg!(all(unix, not(target_os = "macos"))) && unset(display) && unset(wayland_display))
+        .then(|| "neither DISPLAY nor WAYLAND_DISPLAY is set".to_string())
+}
+
 #[derive(Debug)]
 pub struct ContentRenderer {
-    latex_regex: Regex,
+    // Shell command opening the file (`%s` is the path), or the platform's opener
+    browser: Option<String>,
+    launch_delay: Duration,
     // Keep last temp dir alive to prevent deletion
     _temp_dir: Option<Arc<tempfile::TempDir>>,
 }
@@ -20,14 +53,23 @@ pub struct ContentRenderer {
 impl ContentRenderer {
     pub fn new() -> Self {
         Self {
-            latex_regex: Regex::new(r"```(?:tex|latex)?\n(\$\$[\s\S]*?\$\$)\n```").unwrap(),
+            browser: None,
+            launch_delay: Duration::from_millis(BROWSER_LAUNCH_DELAY_MS),
             _temp_dir: None,
         }
     }
 
-    #[instrument(level = "trace")]
-    pub fn process_latex(&self, content: &str) -> String {
-        self.latex_regex.replace_all(content, "$1").into_owned()
+    /// Open files with this shell command instead of the platform's opener.
+    /// `%s` is replaced by the file path; without it the path is appended.
+    pub fn with_browser(mut self, command: Option<String>) -> Self {
+        self.browser = command;
+        self
+    }
+
+    /// How long the file is kept after the browser command returned
+    pub fn with_launch_delay(mut self, delay: Duration) -> Self {
+        self.launch_delay = delay;
+        self
     }
 
     pub fn create_temp_file(&mut self, content: &str) -> Result<PathBuf> {
@@ -50,42 +92,257 @@ impl ContentRenderer {
         Ok(file_path)
     }
 
+    /// Write a rendered note to `<dir>/<note_id>.html`, replacing an earlier
+    /// rendering, and remove renderings older than `keep`. Unlike temp files,
+    /// these outlive the process, so a browser can reload them.
+    pub fn create_output_file(
+        &mut self,
+        dir: &Path,
+        note_id: i64,
+        content: &str,
+        keep: Duration,
+    ) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create output directory {}", dir.display()))?;
+        remove_stale_renderings(dir, keep);
+
+        let file_path = dir.join(format!("{}.html", note_id));
+        std::fs::write(&file_path, content)
+            .with_context(|| format!("Failed to write {}", file_path.display()))?;
+        // Nothing to keep alive or wait for
+        self._temp_dir = None;
+        Ok(file_path)
+    }
+
     // Change the method signature to &mut self since we need to modify _temp_dir
     #[instrument(level = "debug")]
-    pub fn open_in_browser(&mut self, path: &PathBuf) -> Result<()> {
+    pub fn open_in_browser(&mut self, path: &PathBuf) -> Result<OpenOutcome> {
         let path_str = path.to_str().context("Failed to convert path to string")?;
 
-        #[cfg(target_os = "macos")]
-        {
-            std::process::Command::new("open")
-                .arg(path_str)
-                .spawn()
-                .context("Failed to open browser")?;
-        }
-        #[cfg(target_os = "windows")]
-        {
-            std::process::Command::new("cmd")
-                .args(["/C", "start", path_str])
-                .spawn()
-                .context("Failed to open browser")?;
+        // An explicitly configured browser is always tried and its failures are errors;
+        // the platform opener is skipped or given up on where there is no GUI
+        let status = match &self.browser {
+            Some(browser) => {
+                // Wait for the command (or a browser started in the foreground) so
+                // the file outlives it and a failing command is reported
+                let status = shell_command(browser, path_str)
+                    .status()
+                    .context("Failed to open browser")?;
+                if !status.success() {
+                    anyhow::bail!("Browser command failed ({}): {}", status, browser);
+                }
+                status
+            }
+            None => {
+                let headless = headless_reason(
+                    std::env::var("DISPLAY").ok().as_deref(),
+                    std::env::var("WAYLAND_DISPLAY").ok().as_deref(),
+                );
+                let status = match headless {
+                    Some(reason) => Err(reason),
+                    None => platform_opener(path_str)
+                        .status()
+                        .map_err(|e| format!("failed to start the system opener: {}", e)),
+                };
+                match status {
+                    Ok(status) if status.success() => status,
+                    Ok(status) => {
+                        return Ok(self.no_browser(format!("system opener failed ({})", status)))
+                    }
+                    Err(reason) => return Ok(self.no_browser(reason)),
+                }
+            }
+        };
+        debug!(?status, "Browser command finished");
+
+        // Openers hand the file to a running browser and return before it is read;
+        // only temp files disappear afterwards
+        if self._temp_dir.is_some() && !self.launch_delay.is_zero() {
+            std::thread::sleep(self.launch_delay);
         }
-        #[cfg(target_os = "linux")]
+
+        Ok(OpenOutcome::Opened)
+    }
+
+    /// Give up on opening: keep the temp directory on disk for the user
+    fn no_browser(&mut self, reason: String) -> OpenOutcome {
+        debug!(%reason, "No browser available");
+        if let Some(temp_dir) = self
+            ._temp_dir
+            .take()
+            .and_then(|dir| Arc::try_unwrap(dir).ok())
         {
-            std::process::Command::new("xdg-open")
-                .arg(path_str)
-                .spawn()
-                .context("Failed to open browser")?;
+            let _ = temp_dir.keep();
         }
+        OpenOutcome::NoBrowser(reason)
+    }
+}
 
-        // Keep the temp directory alive briefly
-        std::thread::sleep(std::time::Duration::from_millis(BROWSER_LAUNCH_DELAY_MS));
+/// Remove `<note id>.html` files in `dir` last written more than `keep` ago;
+/// other files are left alone. Failures only cost disk space, so they are logged
+fn remove_stale_renderings(dir: &Path, keep: Duration) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        let is_rendering = path.extension().is_some_and(|ext| ext == "html")
+            && path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| stem.parse::<i64>().is_ok());
+        let stale = std::fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > keep);
+        if is_rendering && stale {
+            debug!(file = %path.display(), "Removing stale rendering");
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!(file = %path.display(), error = %e, "Failed to remove stale rendering");
+            }
+        }
+    }
+}
 
-        Ok(())
+/// Run `browser` through the platform shell with `%s` (or an appended
+/// argument) standing for `path`
+fn shell_command(browser: &str, path: &str) -> Command {
+    if cfg!(windows) {
+        let quoted = format!("\"{}\"", path);
+        let line = if browser.contains("%s") {
+            browser.replace("%s", &quoted)
+        } else {
+            format!("{} {}", browser, quoted)
+        };
+        let mut command = Command::new("cmd");
+        command.args(["/C", &line]);
+        command
+    } else {
+        // The path goes in as $1, so it needs no quoting
+        let line = if browser.contains("%s") {
+            browser.replace("%s", "\"$1\"")
+        } else {
+            format!("{} \"$1\"", browser)
+        };
+        let mut command = Command::new("sh");
+        command.args(["-c", &line, "ankiview-browser", path]);
+        command
     }
 }
 
+fn platform_opener(path: &str) -> Command {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start"]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(path);
+    command
+}
+
 impl Default for ContentRenderer {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_flag_config_and_env_when_choosing_browser_then_flag_wins_then_config_then_env() {
+        assert_eq!(
+            browser_command(Some("firefox"), Some("chromium"), Some("lynx")).as_deref(),
+            Some("firefox")
+        );
+        assert_eq!(
+            browser_command(None, Some("chromium"), Some("lynx")).as_deref(),
+            Some("chromium")
+        );
+        assert_eq!(
+            browser_command(None, None, Some(":w3m:lynx")).as_deref(),
+            Some("w3m")
+        );
+        assert_eq!(browser_command(None, None, None), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn given_browser_with_placeholder_when_opening_then_waits_for_command_with_path() {
+        let out_dir = tempfile::TempDir::new().unwrap();
+        let copy = out_dir.path().join("copy.html");
+        let mut renderer = ContentRenderer::new()
+            .with_browser(Some(format!("cp %s '{}'", copy.display())))
+            .with_launch_delay(Duration::ZERO);
+
+        let path = renderer.create_temp_file("<p>note</p>").unwrap();
+        renderer.open_in_browser(&path).unwrap();
+
+        assert_eq!(std::fs::read_to_string(copy).unwrap(), "<p>note</p>");
+    }
+
+    #[test]
+    fn given_output_dir_when_rendering_then_writes_stable_file_and_drops_stale_ones() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let hour_ago = std::time::SystemTime::now() - Duration::from_secs(3600);
+        for name in ["42.html", "notes.html"] {
+            File::create(dir.path().join(name))
+                .unwrap()
+                .set_modified(hour_ago)
+                .unwrap();
+        }
+        let mut renderer = ContentRenderer::new();
+
+        let path = renderer
+            .create_output_file(dir.path(), 7, "<p>note</p>", Duration::from_secs(60))
+            .unwrap();
+
+        assert_eq!(path, dir.path().join("7.html"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "<p>note</p>");
+        assert!(!dir.path().join("42.html").exists());
+        assert!(dir.path().join("notes.html").exists());
+    }
+
+    #[test]
+    fn given_no_display_when_checking_for_gui_then_headless_only_on_linux_like_systems() {
+        let expected = cfg!(all(unix, not(target_os = "macos")));
+
+        assert_eq!(headless_reason(None, Some("")).is_some(), expected);
+        assert_eq!(headless_reason(Some(":0"), None), None);
+        assert_eq!(headless_reason(None, Some("wayland-0")), None);
+    }
+
+    #[test]
+    fn given_no_browser_when_giving_up_then_keeps_temp_file() {
+        let mut renderer = ContentRenderer::new();
+        let path = renderer.create_temp_file("<p>note</p>").unwrap();
+
+        let outcome = renderer.no_browser("headless".to_string());
+        drop(renderer);
+
+        assert_eq!(outcome, OpenOutcome::NoBrowser("headless".to_string()));
+        assert!(path.exists());
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn given_failing_browser_when_opening_then_errors() {
+        let mut renderer = ContentRenderer::new()
+            .with_browser(Some("false".to_string()))
+            .with_launch_delay(Duration::ZERO);
+
+        let path = renderer.create_temp_file("<p>note</p>").unwrap();
+        let err = renderer.open_in_browser(&path).unwrap_err();
+
+        assert!(err.to_string().starts_with("Browser command failed"));
+    }
+}
@@ -0,0 +1,344 @@
+// src/infrastructure/revlog.rs
+use crate::domain::{DailyReviews, DomainError, ReviewStats};
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use std::path::Path;
+use tracing::debug;
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+// Anki's default for "Next day starts at", in hours past midnight
+const DEFAULT_ROLLOVER_HOURS: i64 = 4;
+
+// `revlog.type` of entries that are not answers (manual reschedules, etc.)
+const REVLOG_MANUAL: i64 = 4;
+// `revlog.type` of answers given to cards in review (not learning) state
+const REVLOG_REVIEW: i64 = 1;
+
+/// Read-only access to a collection's review log
+///
+/// Reads the SQLite file directly; the anki crate has no public API for
+/// aggregated revlog queries.
+pub struct RevlogReader {
+    connection: Connection,
+}
+
+/// Where Anki days start: the collection's rollover hour in the local time
+/// Anki last ran in
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DayBoundary {
+    /// Milliseconds from UTC midnight to the start of an Anki day
+    offset_ms: i64,
+}
+
+impl DayBoundary {
+    /// Day (counted from the Unix epoch) that `timestamp_ms` falls on
+    fn day_of(&self, timestamp_ms: i64) -> i64 {
+        (timestamp_ms - self.offset_ms).div_euclid(MS_PER_DAY)
+    }
+
+    fn start_of(&self, day: i64) -> i64 {
+        day * MS_PER_DAY + self.offset_ms
+    }
+}
+
+/// One answer from the review log
+struct Review {
+    timestamp_ms: i64,
+    ease: i64,
+    kind: i64,
+    time_ms: i64,
+}
+
+impl RevlogReader {
+    pub fn open(collection_path: &Path) -> Result<Self> {
+        if !collection_path.exists() {
+            return Err(
+                DomainError::CollectionNotFound(collection_path.display().to_string()).into(),
+            );
+        }
+        // Anki keeps its collection locked while open; report that like other commands
+        crate::util::lock::check_collection_not_locked(collection_path)?;
+
+        let connection = Connection::open_with_flags(
+            collection_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .with_context(|| format!("Failed to open {}", collection_path.display()))?;
+        Ok(Self { connection })
+    }
+
+    /// Review statistics for the `days` days up to `now_ms` (milliseconds since
+    /// the epoch), limited to `deck` and its subdecks if given
+    pub fn review_stats(&self, deck: Option<&str>, days: u32, now_ms: i64) -> Result<ReviewStats> {
+        let deck_ids = match deck {
+            Some(name) => Some(self.deck_ids(name)?),
+            None => None,
+        };
+
+        let boundary = self.day_boundary()?;
+        let today = boundary.day_of(now_ms);
+        let first_day = today - i64::from(days.max(1)) + 1;
+        let reviews = self.reviews_since(boundary.start_of(first_day), deck_ids.as_deref())?;
+        debug!(
+            count = reviews.len(),
+            ?deck,
+            days,
+            ?boundary,
+            "Read review log"
+        );
+
+        let mut per_day = vec![0usize; (today - first_day + 1) as usize];
+        for review in &reviews {
+            let day = boundary.day_of(review.timestamp_ms);
+            if let Some(count) = per_day.get_mut((day - first_day) as usize) {
+                *count += 1;
+            }
+        }
+
+        let graded: Vec<_> = reviews.iter().filter(|r| r.kind == REVLOG_REVIEW).collect();
+        let retention = (!graded.is_empty())
+            .then(|| graded.iter().filter(|r| r.ease > 1).count() as f64 / graded.len() as f64);
+        let average_answer_seconds = (!reviews.is_empty()).then(|| {
+            reviews.iter().map(|r| r.time_ms).sum::<i64>() as f64 / reviews.len() as f64 / 1000.0
+        });
+
+        Ok(ReviewStats {
+            deck: deck.map(str::to_string),
+            days: days.max(1),
+            total_reviews: reviews.len(),
+            reviews_per_day: per_day
+                .into_iter()
+                .enumerate()
+                .map(|(offset, reviews)| DailyReviews {
+                    date: civil_date(first_day + offset as i64),
+                    reviews,
+                })
+                .collect(),
+            retention,
+            average_answer_seconds,
+        })
+    }
+
+    /// Day boundary from the collection's `rollover` hour and `localOffset`
+    /// (minutes west of UTC, updated whenever Anki opens the collection),
+    /// falling back to the offset the collection was created with
+    fn day_boundary(&self) -> Result<DayBoundary> {
+        let rollover_hours = self
+            .config_number("rollover")?
+            .unwrap_or(DEFAULT_ROLLOVER_HOURS);
+        let minutes_west = match self.config_number("localOffset")? {
+            Some(minutes) => minutes,
+            None => self.config_number("creationOffset")?.unwrap_or(0),
+        };
+        Ok(DayBoundary {
+            offset_ms: rollover_hours * 3_600_000 + minutes_west * 60_000,
+        })
+    }
+
+    /// A number from the collection's `config` table; `None` if unset
+    fn config_number(&self, key: &str) -> Result<Option<i64>> {
+        let value: Option<Vec<u8>> = self
+            .connection
+            .query_row("SELECT val FROM config WHERE KEY = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()
+            .with_context(|| format!("Failed to read collection setting '{}'", key))?;
+        Ok(value.and_then(|value| {
+            serde_json::from_slice(&value)
+                .inspect_err(|err| debug!(%err, key, "Ignoring unreadable collection setting"))
+                .ok()
+        }))
+    }
+
+    /// IDs of the deck named `name` and its subdecks
+    fn deck_ids(&self, name: &str) -> Result<Vec<i64>> {
+        // Deck names are stored with \x1f between the levels. Compared in Rust
+        // because the column uses Anki's own `unicase` collation.
+        let native = name.replace("::", "\x1f");
+        let prefix = format!("{}\x1f", native);
+        let mut statement = self
+            .connection
+            .prepare("SELECT id, name FROM decks")
+            .context("Failed to read decks")?;
+        let ids = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+            .into_iter()
+            .filter(|(_, deck)| {
+                deck.eq_ignore_ascii_case(&native)
+                    || deck
+                        .get(..prefix.len())
+                        .is_some_and(|p| p.eq_ignore_ascii_case(&prefix))
+            })
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        if ids.is_empty() {
+            return Err(DomainError::DeckNotFound(name.to_string()).into());
+        }
+        Ok(ids)
+    }
+
+    fn reviews_since(&self, since_ms: i64, deck_ids: Option<&[i64]>) -> Result<Vec<Review>> {
+        let mut sql = format!(
+            "SELECT r.id, r.ease, r.type, r.time FROM revlog r \
+             JOIN cards c ON c.id = r.cid \
+             WHERE r.id >= ?1 AND r.ease > 0 AND r.type != {}",
+            REVLOG_MANUAL
+        );
+        if let Some(ids) = deck_ids {
+            // Cards in filtered decks count for their home deck
+            let ids = ids.iter().map(i64::to_string).collect::<Vec<_>>().join(",");
+            sql.push_str(&format!(
+                " AND (c.did IN ({ids}) OR c.odid IN ({ids}))",
+                ids = ids
+            ));
+        }
+
+        let mut statement = self
+            .connection
+            .prepare(&sql)
+            .context("Failed to read review log")?;
+        let reviews = statement
+            .query_map([since_ms], |row| {
+                Ok(Review {
+                    timestamp_ms: row.get(0)?,
+                    ease: row.get(1)?,
+                    kind: row.get(2)?,
+                    time_ms: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(reviews)
+    }
+}
+
+/// `YYYY-MM-DD` of a day counted from the Unix epoch (proleptic Gregorian calendar)
+pub(crate) fn civil_date(days_since_epoch: i64) -> String {
+    // Howard Hinnant's days_from_civil inverse
+    let z = days_since_epoch + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // 2024-06-10 12:00 UTC
+    const NOW_MS: i64 = 1_718_020_800_000;
+
+    /// Collection with just the columns the review log queries use
+    fn create_collection(dir: &TempDir) -> std::path::PathBuf {
+        let path = dir.path().join("collection.anki2");
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE config (KEY TEXT PRIMARY KEY, usn INTEGER, mtime_secs INTEGER,
+                                      val BLOB);
+                 CREATE TABLE decks (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+                 CREATE TABLE cards (id INTEGER PRIMARY KEY, did INTEGER, odid INTEGER);
+                 CREATE TABLE revlog (id INTEGER PRIMARY KEY, cid INTEGER, ease INTEGER,
+                                      time INTEGER, type INTEGER);
+                 INSERT INTO decks VALUES (1, 'Default'), (2, 'Rust'), (3, 'Rust\x1fOwnership');
+                 INSERT INTO cards VALUES (10, 1, 0), (20, 3, 0);",
+            )
+            .unwrap();
+        let mut insert = connection
+            .prepare("INSERT INTO revlog VALUES (?1, ?2, ?3, ?4, ?5)")
+            .unwrap();
+        for (offset_ms, cid, ease, time, kind) in [
+            (0, 20, 3, 4000, 1),
+            (1, 20, 1, 8000, 1),
+            (2, 10, 3, 3000, 0),
+            (-MS_PER_DAY, 20, 4, 3000, 1),
+            (-40 * MS_PER_DAY, 20, 3, 5000, 1),
+            (3, 20, 0, 0, 4),
+        ] {
+            insert
+                .execute((NOW_MS + offset_ms, cid, ease, time, kind))
+                .unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn given_review_log_when_computing_stats_then_counts_recent_answers() {
+        let dir = TempDir::new().unwrap();
+        let reader = RevlogReader::open(&create_collection(&dir)).unwrap();
+
+        let stats = reader.review_stats(None, 30, NOW_MS).unwrap();
+
+        assert_eq!(stats.total_reviews, 4, "old and manual entries are skipped");
+        assert_eq!(stats.reviews_per_day.len(), 30);
+        let last = stats.reviews_per_day.last().unwrap();
+        assert_eq!((last.date.as_str(), last.reviews), ("2024-06-10", 3));
+        assert_eq!(stats.reviews_per_day[28].date, "2024-06-09");
+        assert_eq!(stats.retention, Some(2.0 / 3.0));
+        assert_eq!(stats.average_answer_seconds, Some(4.5));
+    }
+
+    #[test]
+    fn given_rollover_and_time_zone_when_computing_stats_then_days_start_at_local_rollover() {
+        let dir = TempDir::new().unwrap();
+        let path = create_collection(&dir);
+        let connection = Connection::open(&path).unwrap();
+        // UTC+2, days start at 04:00 local time (02:00 UTC)
+        connection
+            .execute_batch(
+                "INSERT INTO config VALUES ('rollover', 0, 0, CAST('4' AS BLOB)),
+                                           ('localOffset', 0, 0, CAST('-120' AS BLOB));",
+            )
+            .unwrap();
+        // 2024-06-10 01:30 UTC is 03:30 local time, still the Anki day of June 9th
+        connection
+            .execute(
+                "INSERT INTO revlog VALUES (?1, 20, 3, 1000, 1)",
+                [NOW_MS - 21 * MS_PER_DAY / 48],
+            )
+            .unwrap();
+        let reader = RevlogReader::open(&path).unwrap();
+
+        let stats = reader.review_stats(None, 2, NOW_MS).unwrap();
+
+        assert_eq!(stats.reviews_per_day[0].date, "2024-06-09");
+        assert_eq!(stats.reviews_per_day[0].reviews, 2);
+        assert_eq!(stats.reviews_per_day[1].reviews, 3);
+    }
+
+    #[test]
+    fn given_deck_when_computing_stats_then_includes_subdecks_only() {
+        let dir = TempDir::new().unwrap();
+        let reader = RevlogReader::open(&create_collection(&dir)).unwrap();
+
+        let stats = reader.review_stats(Some("rust"), 7, NOW_MS).unwrap();
+
+        assert_eq!(stats.total_reviews, 3);
+        assert!(matches!(
+            reader
+                .review_stats(Some("Missing"), 7, NOW_MS)
+                .unwrap_err()
+                .downcast_ref::<DomainError>(),
+            Some(DomainError::DeckNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn given_days_since_epoch_when_formatting_then_returns_calendar_date() {
+        assert_eq!(civil_date(0), "1970-01-01");
+        assert_eq!(civil_date(19_782), "2024-02-29");
+        assert_eq!(civil_date(-1), "1969-12-31");
+    }
+}
@@ -1,15 +1,45 @@
 use crate::application::NoteRepository;
-use crate::infrastructure::anki::AnkiRepository;
+use crate::domain::DomainError;
+use crate::infrastructure::anki::{AnkiRepository, DEFAULT_BASIC_NOTETYPE};
+use crate::infrastructure::backup::BackupReader;
+use crate::inka::application::conflicts::{
+    Conflict, ConflictResolver, DefaultResolver, Resolution,
+};
+use crate::inka::application::events::{EventSink, NoopEventSink, SourceLocation};
+use crate::inka::application::lint::{self, LintRule};
+use crate::inka::application::timings::{CollectTimings, Phase};
+use crate::inka::infrastructure::config::{
+    Config, LintConfig, MarkdownConfig, MathConfig, MediaConfig,
+};
 use crate::inka::infrastructure::file_writer;
-use crate::inka::infrastructure::hasher::HashCache;
+use crate::inka::infrastructure::hasher::{CacheLocation, HashCache};
+use crate::inka::infrastructure::history::{self, History, HistoryAction, HistoryEntry};
 use crate::inka::infrastructure::markdown::card_parser;
-use crate::inka::infrastructure::markdown::converter;
-use crate::inka::infrastructure::markdown::section_parser;
-use crate::inka::infrastructure::media_handler;
+use crate::inka::infrastructure::markdown::frontmatter::{self, Frontmatter};
+use crate::inka::infrastructure::markdown::section_parser::{
+    self, CardFormat, SectionParser, Span,
+};
+use crate::inka::infrastructure::markdown::{cloze_converter, converter};
+use crate::inka::infrastructure::media_handler::{self, MediaConflictAction};
+use crate::inka::infrastructure::org_parser;
+use crate::util::text::extract_first_line;
 use anyhow::{Context, Result};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use tracing::{debug, warn};
+use std::time::Instant;
+use tracing::{debug, debug_span, info, warn};
+
+/// Tag of the index notes `index_notes` keeps per markdown file
+pub const INDEX_TAG: &str = "ankiview-index";
+
+/// Namespace of the tags recording which tags `sync_tags` applied to a note,
+/// e.g. `ankiview::synced::rust` next to `rust`
+pub const SYNCED_TAG_PREFIX: &str = "ankiview::synced::";
+
+/// Tag of notes recreated for an orphaned ID without their old scheduling,
+/// so their reset cards can be found
+pub const REIMPORTED_TAG: &str = "reimported";
 
 /// Configuration for CardCollector behavior
 #[derive(Debug, Clone)]
@@ -20,10 +50,155 @@ pub struct CollectorConfig {
     pub full_sync: bool,
     /// Search Anki for existing notes when markdown lacks ID comments
     pub update_ids: bool,
+    /// With `update_ids`, only link notes in the card's target deck; matches
+    /// elsewhere are reported instead of adopted
+    pub same_deck: bool,
+    /// Move the cards of an updated note to the card's deck when they are in
+    /// another one (e.g. after the section's `Deck:` line changed)
+    pub move_on_deck_change: bool,
     /// Continue processing on errors instead of failing fast
     pub ignore_errors: bool,
     /// Specific card type (notetype) to use, defaults to "Inka Basic"
     pub card_type: Option<String>,
+    /// Fail a file (before changing anything) if it produces any warning
+    pub strict: bool,
+    /// Only report which files would change Anki; write nothing
+    pub check: bool,
+    /// Size limits for images copied into collection.media/
+    pub media: MediaConfig,
+    /// Deck for new notes instead of the sections' `Deck:` lines
+    pub deck: Option<String>,
+    /// Namespace prepended to every deck name (e.g. `Imported`)
+    pub deck_prefix: Option<String>,
+    /// Deck options preset for decks this run creates (None: Anki's Default)
+    pub deck_options: Option<String>,
+    /// Tags added to every created or updated note, besides the section tags
+    pub extra_tags: Vec<String>,
+    /// Also remove tags an earlier run applied that the markdown no longer has
+    /// (tracked with [`SYNCED_TAG_PREFIX`] tags, or by `tag_namespace`); tags
+    /// added in Anki stay
+    pub sync_tags: bool,
+    /// Namespace prepended to every tag from markdown and `extra_tags`
+    /// (e.g. `inka` turns `rust` into `inka::rust`)
+    pub tag_namespace: Option<String>,
+    /// Card syntaxes recognized in sections (numbered list, Q:/A:)
+    pub card_formats: Vec<CardFormat>,
+    /// Lines opening and closing a section (`---` by default)
+    pub section_start: String,
+    pub section_end: String,
+    /// Smart punctuation and emoji shortcodes in card HTML
+    pub markdown: MarkdownConfig,
+    /// MathJax or Anki LaTeX delimiters for math
+    pub math: MathConfig,
+    /// Where the cards of newly created notes go in the new-card queue
+    /// (None: Anki's default, the end)
+    pub new_position: Option<NewPosition>,
+    /// What to do with a card without ID whose first field matches a note in
+    /// its deck, as Anki's duplicate check sees it (None: don't look)
+    pub on_duplicate: Option<OnDuplicate>,
+    /// Markdown or org-mode source files
+    pub format: SourceFormat,
+    /// Record created and updated notes in the collection's history journal
+    pub history: bool,
+    /// Keep one index note per markdown file linking to its cards
+    pub index_notes: bool,
+    /// URI template making the file footer a link (see [`source_uri`])
+    pub source_link: Option<String>,
+    /// Directory `{relpath}` is relative to (None: the current directory)
+    pub source_root: Option<PathBuf>,
+    /// Where the hash cache and history are kept (`cache.location`; default:
+    /// the user cache directory)
+    pub cache: CacheLocation,
+    /// Report cards breaking these quality rules as warnings (`--lint`)
+    pub lint: Option<LintConfig>,
+    /// Warn about images without alt text, with or without `lint`
+    /// (`[lint] image_alt`)
+    pub image_alt: bool,
+    /// Older copy of the collection (e.g. an unpacked backup) to copy the
+    /// scheduling of notes recreated for an orphaned ID from
+    pub restore_from: Option<PathBuf>,
+}
+
+/// Syntax of the files a collect run reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SourceFormat {
+    /// `.md` files with inka sections
+    #[default]
+    Markdown,
+    /// `.org` files with `:drill:`/`:anki:` tagged headlines
+    Org,
+}
+
+impl SourceFormat {
+    /// Extension of the files collected from directories
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Org => "org",
+        }
+    }
+}
+
+impl std::str::FromStr for SourceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "md" | "markdown" => Ok(Self::Markdown),
+            "org" => Ok(Self::Org),
+            _ => Err(format!("'{}' is not md or org", s)),
+        }
+    }
+}
+
+/// Position in the new-card queue for the cards a collect run created
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewPosition {
+    /// Before all existing new cards, which are shifted back
+    Start,
+    /// After all existing new cards (what Anki does anyway)
+    End,
+    /// From due position N on; existing new cards at N or later are shifted back
+    At(u32),
+}
+
+impl std::str::FromStr for NewPosition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "start" => Ok(Self::Start),
+            "end" => Ok(Self::End),
+            _ => s
+                .parse()
+                .map(Self::At)
+                .map_err(|_| format!("'{}' is not start, end or a position number", s)),
+        }
+    }
+}
+
+/// Outcome for a new card that duplicates a note already in its deck
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnDuplicate {
+    /// Create a note anyway (Anki shows both as duplicates)
+    Create,
+    /// Leave the card without ID and create nothing
+    Skip,
+    /// Update the existing note and write its ID into the source
+    Update,
+}
+
+impl std::str::FromStr for OnDuplicate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "create" => Ok(Self::Create),
+            "skip" => Ok(Self::Skip),
+            "update" => Ok(Self::Update),
+            _ => Err(format!("'{}' is not create, skip or update", s)),
+        }
+    }
 }
 
 impl CollectorConfig {
@@ -33,8 +208,35 @@ impl CollectorConfig {
             force: false,
             full_sync: false,
             update_ids: false,
+            same_deck: false,
+            move_on_deck_change: false,
             ignore_errors: false,
             card_type: None,
+            strict: false,
+            check: false,
+            media: MediaConfig::default(),
+            deck: None,
+            deck_prefix: None,
+            deck_options: None,
+            extra_tags: Vec::new(),
+            sync_tags: false,
+            tag_namespace: None,
+            card_formats: CardFormat::DEFAULT.to_vec(),
+            section_start: SectionParser::DEFAULT_DELIMITER.to_string(),
+            section_end: SectionParser::DEFAULT_DELIMITER.to_string(),
+            markdown: MarkdownConfig::default(),
+            math: MathConfig::default(),
+            new_position: None,
+            on_duplicate: None,
+            format: SourceFormat::Markdown,
+            history: false,
+            index_notes: false,
+            source_link: None,
+            source_root: None,
+            cache: CacheLocation::default(),
+            lint: None,
+            image_alt: true,
+            restore_from: None,
         }
     }
 }
@@ -45,23 +247,126 @@ impl Default for CollectorConfig {
     }
 }
 
+/// Repository owned by the collector, or borrowed from a caller that keeps
+/// the collection open across several collect runs (e.g. `ankiview serve`)
+enum RepositoryHandle<'r> {
+    Owned(AnkiRepository),
+    Borrowed(&'r mut AnkiRepository),
+}
+
+impl std::ops::Deref for RepositoryHandle<'_> {
+    type Target = AnkiRepository;
+
+    fn deref(&self) -> &AnkiRepository {
+        match self {
+            RepositoryHandle::Owned(repository) => repository,
+            RepositoryHandle::Borrowed(repository) => repository,
+        }
+    }
+}
+
+impl std::ops::DerefMut for RepositoryHandle<'_> {
+    fn deref_mut(&mut self) -> &mut AnkiRepository {
+        match self {
+            RepositoryHandle::Owned(repository) => repository,
+            RepositoryHandle::Borrowed(repository) => repository,
+        }
+    }
+}
+
 /// Main use case for collecting markdown cards into Anki
-pub struct CardCollector {
+pub struct CardCollector<'r> {
     _collection_path: PathBuf,
     media_dir: PathBuf,
-    repository: AnkiRepository,
+    repository: RepositoryHandle<'r>,
     force: bool,
+    media: MediaConfig,
     hash_cache: Option<HashCache>,
+    // Journal of created and updated notes, if enabled
+    history: Option<History>,
+    index_notes: bool,
+    source_link: Option<String>,
+    source_root: Option<PathBuf>,
+    lint: Option<LintConfig>,
+    image_alt: bool,
+    // Source of the scheduling of recreated notes, if given
+    backup: Option<BackupReader>,
+    // Change status precomputed in parallel for a batch of files
+    prechecked: HashMap<PathBuf, bool>,
     update_ids: bool,
+    same_deck: bool,
+    move_on_deck_change: bool,
     ignore_errors: bool,
+    strict: bool,
+    check: bool,
+    // Changes a `check` run found but didn't make (`path: description`)
+    pending_changes: Vec<String>,
     errors: Vec<String>,
+    warnings: Vec<String>,
+    // Cards `--update-ids` found in several notes (`path: message`)
+    multi_matches: Vec<String>,
+    // Notes whose cards followed a deck change (`path: message`)
+    deck_moves: Vec<String>,
+    // Items that start like a card but aren't one (`path:line: first line`)
+    malformed_cards: Vec<String>,
+    // Notes recreated for an orphaned ID (`path: message`)
+    recreated_notes: Vec<String>,
+    // Note ID -> file it was first seen in, to detect IDs reused across files
+    seen_ids: HashMap<i64, PathBuf>,
     card_type: Option<String>,
+    deck_options: Option<String>,
+    sync_tags: bool,
+    tag_namespace: Option<String>,
+    // Sections and cards of markdown files, with deck overrides and extra tags
+    reader: CardReader,
+    markdown: MarkdownConfig,
+    math: MathConfig,
+    new_position: Option<NewPosition>,
+    on_duplicate: Option<OnDuplicate>,
+    // Notes created in this run, in creation order, for `new_position`
+    created_ids: Vec<i64>,
+    format: SourceFormat,
+    events: Box<dyn EventSink>,
+    resolver: Box<dyn ConflictResolver>,
+    // Resolved on first use and reused for every note created in this run
+    basic_notetype_id: Option<i64>,
+    cloze_notetype_id: Option<i64>,
+    basic_field_count: Option<usize>,
+    deck_ids: HashMap<String, i64>,
+    // Index notes by front (note ID, back), read on first use
+    index_notes_by_front: Option<HashMap<String, (i64, String)>>,
+    timings: CollectTimings,
 }
 
-impl CardCollector {
+impl CardCollector<'static> {
     /// Create a new CardCollector with Anki collection path and configuration
     pub fn new(collection_path: impl AsRef<Path>, config: CollectorConfig) -> Result<Self> {
-        let collection_path = collection_path.as_ref().to_path_buf();
+        let collection_path = collection_path.as_ref();
+        let repository = AnkiRepository::new(collection_path)?;
+        CardCollector::build(collection_path, RepositoryHandle::Owned(repository), config)
+    }
+}
+
+impl<'r> CardCollector<'r> {
+    /// Create a CardCollector that works on an already-open repository
+    pub fn with_repository(
+        collection_path: impl AsRef<Path>,
+        repository: &'r mut AnkiRepository,
+        config: CollectorConfig,
+    ) -> Result<Self> {
+        Self::build(
+            collection_path.as_ref(),
+            RepositoryHandle::Borrowed(repository),
+            config,
+        )
+    }
+
+    fn build(
+        collection_path: &Path,
+        mut repository: RepositoryHandle<'r>,
+        config: CollectorConfig,
+    ) -> Result<Self> {
+        let collection_path = collection_path.to_path_buf();
 
         // Determine media directory path
         let media_dir = collection_path
@@ -74,25 +379,27 @@ impl CardCollector {
             std::fs::create_dir_all(&media_dir).context("Failed to create media directory")?;
         }
 
-        // Determine hash cache path (in same directory as collection)
-        let cache_path = collection_path
-            .parent()
-            .expect("Invalid collection path")
-            .join("ankiview_hashes.json");
-
-        // Load hash cache unless full_sync is enabled
+        // Hash cache in the user cache directory (or the configured one), not
+        // in Anki's profile directory; loaded unless full_sync is enabled
         let hash_cache = if config.full_sync {
             None
         } else {
-            Some(HashCache::load(&cache_path).context("Failed to load hash cache")?)
+            Some(config.cache.open(&collection_path)?)
         };
 
-        // Open repository
-        let mut repository = AnkiRepository::new(&collection_path)?;
+        let history = config
+            .history
+            .then(|| History::for_collection(&collection_path, &config.cache));
+        let backup = config
+            .restore_from
+            .as_deref()
+            .map(BackupReader::open)
+            .transpose()?;
 
         // Validate card type early if provided
+        let mut basic_notetype_id = None;
         if let Some(ref card_type_name) = config.card_type {
-            repository
+            let notetype_id = repository
                 .find_notetype_by_name(card_type_name)
                 .with_context(|| {
                     format!(
@@ -101,6 +408,7 @@ impl CardCollector {
                     )
                 })?;
             debug!(card_type = %card_type_name, "Validated card type");
+            basic_notetype_id = Some(notetype_id);
         }
 
         Ok(Self {
@@ -108,93 +416,382 @@ impl CardCollector {
             media_dir,
             repository,
             force: config.force,
+            media: config.media,
             hash_cache,
+            history,
+            index_notes: config.index_notes,
+            source_link: config.source_link,
+            source_root: config.source_root,
+            lint: config.lint,
+            image_alt: config.image_alt,
+            backup,
+            prechecked: HashMap::new(),
             update_ids: config.update_ids,
+            same_deck: config.same_deck,
+            move_on_deck_change: config.move_on_deck_change,
             ignore_errors: config.ignore_errors,
+            strict: config.strict,
+            check: config.check,
+            pending_changes: Vec::new(),
             errors: Vec::new(),
+            warnings: Vec::new(),
+            multi_matches: Vec::new(),
+            deck_moves: Vec::new(),
+            malformed_cards: Vec::new(),
+            recreated_notes: Vec::new(),
+            seen_ids: HashMap::new(),
             card_type: config.card_type,
+            deck_options: config.deck_options,
+            sync_tags: config.sync_tags,
+            tag_namespace: config
+                .tag_namespace
+                .map(|namespace| namespace.trim_end_matches("::").to_string())
+                .filter(|namespace| !namespace.is_empty()),
+            reader: CardReader::new(
+                &config.section_start,
+                &config.section_end,
+                config.card_formats,
+            )
+            .with_text_direction(config.markdown.lang.clone(), config.markdown.dir.clone())
+            .with_deck_override(config.deck, config.deck_prefix)
+            .with_extra_tags(config.extra_tags),
+            markdown: config.markdown,
+            math: config.math,
+            new_position: config.new_position,
+            on_duplicate: config.on_duplicate,
+            created_ids: Vec::new(),
+            format: config.format,
+            events: Box::new(NoopEventSink),
+            resolver: Box::new(DefaultResolver),
+            basic_notetype_id,
+            cloze_notetype_id: None,
+            basic_field_count: None,
+            deck_ids: HashMap::new(),
+            index_notes_by_front: None,
+            timings: CollectTimings::default(),
         })
     }
 
+    /// Report progress events to `sink` instead of discarding them
+    pub fn with_event_sink(mut self, sink: impl EventSink + 'static) -> Self {
+        self.events = Box::new(sink);
+        self
+    }
+
+    /// Decide orphaned IDs, ambiguous `--update-ids` matches and media name
+    /// conflicts with `resolver` (e.g. by asking the user) instead of the
+    /// `DefaultResolver`
+    pub fn with_conflict_resolver(mut self, resolver: impl ConflictResolver + 'static) -> Self {
+        self.resolver = Box::new(resolver);
+        self
+    }
+
     /// Get accumulated errors from processing
     pub fn errors(&self) -> &[String] {
         &self.errors
     }
 
-    /// Add file path footer to HTML content
+    /// Get accumulated warnings (`path: message`) from processing
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Cards `--update-ids` found in several notes and what was done with
+    /// them (`path: message`), so the duplicates can be cleaned up in Anki
+    pub fn multi_matches(&self) -> &[String] {
+        &self.multi_matches
+    }
+
+    /// Notes whose cards `move_on_deck_change` moved (`path: message`)
+    pub fn deck_moves(&self) -> &[String] {
+        &self.deck_moves
+    }
+
+    /// Items that start like a card (`1.`, `Q:`, a card heading) but have
+    /// neither an answer nor a cloze deletion, so no note was made for them
+    /// (`path:line: first line`); also reported as warnings
+    pub fn malformed_cards(&self) -> &[String] {
+        &self.malformed_cards
+    }
+
+    /// Notes recreated for an orphaned ID and whether their scheduling was
+    /// restored or they were tagged [`REIMPORTED_TAG`] (`path: message`)
+    pub fn recreated_notes(&self) -> &[String] {
+        &self.recreated_notes
+    }
+
+    /// Changes found by a `check` run (`path: description`); empty if in sync
+    pub fn pending_changes(&self) -> &[String] {
+        &self.pending_changes
+    }
+
+    /// Time spent per phase so far
+    pub fn timings(&self) -> &CollectTimings {
+        &self.timings
+    }
+
+    /// Persist the hash cache now instead of when the collector is dropped
+    pub fn save_cache(&mut self) -> Result<()> {
+        self.timed(Phase::CacheSave, |this| match &mut this.hash_cache {
+            // A check run never changes the cache, don't create it either
+            Some(cache) if !this.check => cache.save(),
+            _ => Ok(()),
+        })
+    }
+
+    /// Move the cards of the notes created so far to the configured position
+    /// in the new-card queue. Returns the number of cards repositioned.
+    pub fn reposition_new_cards(&mut self) -> Result<usize> {
+        let start = match self.new_position {
+            None | Some(NewPosition::End) => return Ok(0),
+            Some(NewPosition::Start) => 0,
+            Some(NewPosition::At(position)) => position,
+        };
+        if self.created_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let note_ids = std::mem::take(&mut self.created_ids);
+        let moved = self.timed(Phase::Repository, |this| {
+            this.repository.reposition_new_cards(&note_ids, start)
+        })?;
+        info!(cards = moved, start, "Repositioned new cards");
+        Ok(moved)
+    }
+
+    /// Run `f` inside a tracing span and add its duration to `phase`
+    fn timed<T>(&mut self, phase: Phase, f: impl FnOnce(&mut Self) -> T) -> T {
+        let _span = debug_span!("collect_phase", phase = phase.as_str()).entered();
+        let started = Instant::now();
+        let result = f(self);
+        self.timings.add(phase, started.elapsed());
+        result
+    }
+
     /// Process a single card (basic or cloze) with common logic
     ///
-    /// Returns (updated_content, note_id) tuple
-    #[allow(clippy::too_many_arguments)]
+    /// Returns the note ID to write into the source file, if it changed
     fn process_card(
         &mut self,
-        note_str: &str,
+        markdown_path: &Path,
         existing_id: Option<i64>,
         fields_html: Vec<String>,
         deck_name: &str,
         tags: &[String],
-        content: String,
         is_cloze: bool,
-    ) -> Result<(String, i64)> {
-        let mut content = content;
+    ) -> Result<Option<i64>> {
+        let tags = &self.namespaced_tags(tags);
+        if self.check {
+            self.check_card(markdown_path, existing_id, &fields_html, deck_name, tags)?;
+            return Ok(None);
+        }
 
         // Create or update note based on existing_id and mode
-        let note_id = if let Some(id) = existing_id {
+        let new_id = if let Some(id) = existing_id {
             // Check if note still exists before updating
             if self.repository.note_exists(id)? {
+                let before = self.history_hash(id)?;
                 // Update existing note
                 self.repository.update_note(id, &fields_html)?;
-                // Merge tags from markdown (additive only, never removes)
-                if !tags.is_empty() {
-                    self.repository.add_tags(id, tags)?;
-                }
-                id
+                // Merge tags from markdown (removes only with `sync_tags`)
+                self.merge_tags(id, tags)?;
+                self.follow_deck_change(markdown_path, id, deck_name)?;
+                self.record_history(HistoryAction::Updated, markdown_path, id, before)?;
+                info!(file = %markdown_path.display(), note_id = id, action = "updated", "Updated note");
+                self.events.on_card_updated(markdown_path, id);
+                None
             } else {
-                // Note was deleted - create new note and replace ID
-                eprintln!(
-                    "Warning: Note ID {} found in markdown but doesn't exist in Anki. Creating new note with new ID.",
-                    id
-                );
+                // Note was deleted (reported to the event sink by `check_sections`)
+                let conflict = Conflict::OrphanedId {
+                    path: markdown_path,
+                    note_id: id,
+                };
+                if self.resolver.resolve(&conflict) != Resolution::Replace {
+                    info!(file = %markdown_path.display(), note_id = id, action = "skipped", "Left card with orphaned ID alone");
+                    return Ok(None);
+                }
+                // Create new note and replace ID
                 warn!(
                     old_id = id,
                     "Note ID found in markdown but note doesn't exist in Anki, creating new note"
                 );
                 let new_id = self.create_note(&fields_html, deck_name, tags, is_cloze)?;
-                // Strip ID comment from note_str before using as pattern
-                let note_pattern = file_writer::strip_id_comment(note_str);
-                content = file_writer::replace_anki_id(&content, &note_pattern, new_id);
-                new_id
+                self.restore_scheduling(markdown_path, id, new_id)?;
+                self.record_history(HistoryAction::Created, markdown_path, new_id, None)?;
+                info!(file = %markdown_path.display(), note_id = new_id, old_id = id, action = "recreated", "Recreated deleted note");
+                self.events.on_card_recreated(markdown_path, id, new_id);
+                Some(new_id)
             }
         } else if self.update_ids {
             // --update-ids mode: search for existing note by HTML content
-            let matching_ids = self.repository.search_by_html(&fields_html)?;
-
-            if let Some(&id) = matching_ids.first() {
+            let mut matching_ids = self.repository.search_by_html(&fields_html)?;
+            if self.same_deck {
+                let mut elsewhere = Vec::new();
+                for &id in &matching_ids {
+                    if !self.note_in_deck(id, deck_name)? {
+                        elsewhere.push(id);
+                    }
+                }
+                if !elsewhere.is_empty() {
+                    matching_ids.retain(|id| !elsewhere.contains(id));
+                    let notes = self.describe_matches(&elsewhere)?;
+                    self.record_warning(
+                        markdown_path,
+                        format!(
+                            "card matches {} outside deck '{}'; not linked",
+                            notes, deck_name
+                        ),
+                    );
+                }
+            }
+            let resolution = if matching_ids.len() > 1 {
+                self.resolver.resolve(&Conflict::DuplicateMatch {
+                    path: markdown_path,
+                    note_ids: &matching_ids,
+                })
+            } else {
+                Resolution::Keep
+            };
+
+            let matched = match resolution {
+                Resolution::Keep => matching_ids.first().copied(),
+                Resolution::Use(id) if matching_ids.contains(&id) => Some(id),
+                _ => None,
+            };
+            if matching_ids.len() > 1 {
+                let outcome = match (resolution, matched) {
+                    (Resolution::Skip, _) => "skipped the card".to_string(),
+                    (_, Some(id)) => format!("used note {}", id),
+                    (_, None) => "created a new note".to_string(),
+                };
+                let notes = self.describe_matches(&matching_ids)?;
+                self.record_multi_match(markdown_path, &notes, &outcome);
+            }
+            if resolution == Resolution::Skip {
+                info!(file = %markdown_path.display(), matches = matching_ids.len(), action = "skipped", "Skipped card matching several notes");
+                return Ok(None);
+            }
+            if let Some(id) = matched {
                 // Found existing note, inject ID
                 debug!(note_id = id, "Found existing note for card, injecting ID");
-                content = file_writer::inject_anki_id(&content, note_str, id);
+                if !self.move_on_deck_change && !self.note_in_deck(id, deck_name)? {
+                    let note = self.describe_matches(&[id])?;
+                    self.record_warning(
+                        markdown_path,
+                        format!(
+                            "card matches {} outside deck '{}'; linked anyway, its cards stay where they are",
+                            note, deck_name
+                        ),
+                    );
+                }
+                let before = self.history_hash(id)?;
                 // Update the existing note with current content
                 self.repository.update_note(id, &fields_html)?;
-                // Merge tags from markdown (additive only, never removes)
-                if !tags.is_empty() {
-                    self.repository.add_tags(id, tags)?;
-                }
-                id
+                // Merge tags from markdown (removes only with `sync_tags`)
+                self.merge_tags(id, tags)?;
+                self.follow_deck_change(markdown_path, id, deck_name)?;
+                self.record_history(HistoryAction::Updated, markdown_path, id, before)?;
+                info!(file = %markdown_path.display(), note_id = id, action = "matched", "Matched existing note by content");
+                self.events.on_card_matched(markdown_path, id);
+                Some(id)
             } else {
-                // No match found, create new note
-                let id = self.create_note(&fields_html, deck_name, tags, is_cloze)?;
-                content = file_writer::inject_anki_id(&content, note_str, id);
-                id
+                // No match found (or a new note was chosen), create new note
+                self.create_card(markdown_path, &fields_html, deck_name, tags, is_cloze)?
             }
         } else {
             // Normal mode: create new note
-            let id = self.create_note(&fields_html, deck_name, tags, is_cloze)?;
-            // Inject ID back into markdown
-            content = file_writer::inject_anki_id(&content, note_str, id);
-            id
+            self.create_card(markdown_path, &fields_html, deck_name, tags, is_cloze)?
         };
 
-        Ok((content, note_id))
+        Ok(new_id)
+    }
+
+    /// Create the note of a card without ID. With `on_duplicate`, a note of
+    /// the same notetype in `deck_name` with the same first field is reported
+    /// first and, depending on the mode, updated or left alone instead.
+    fn create_card(
+        &mut self,
+        markdown_path: &Path,
+        fields_html: &[String],
+        deck_name: &str,
+        tags: &[String],
+        is_cloze: bool,
+    ) -> Result<Option<i64>> {
+        if let Some(mode) = self.on_duplicate {
+            let notetype_id = if is_cloze {
+                self.cloze_notetype_id()?
+            } else {
+                self.basic_notetype_id()?
+            };
+            let duplicates =
+                self.repository
+                    .find_duplicates(notetype_id, deck_name, &fields_html[0])?;
+            if let Some(&id) = duplicates.first() {
+                let outcome = match mode {
+                    OnDuplicate::Create => "created another note".to_string(),
+                    OnDuplicate::Skip => "skipped the card".to_string(),
+                    OnDuplicate::Update => format!("updated note {}", id),
+                };
+                let message = format!(
+                    "card duplicates note {} in deck '{}'; {}",
+                    id, deck_name, outcome
+                );
+                self.record_warning(markdown_path, message);
+                match mode {
+                    OnDuplicate::Create => {}
+                    OnDuplicate::Skip => return Ok(None),
+                    OnDuplicate::Update => {
+                        let before = self.history_hash(id)?;
+                        self.repository.update_note(id, fields_html)?;
+                        self.merge_tags(id, tags)?;
+                        self.record_history(HistoryAction::Updated, markdown_path, id, before)?;
+                        info!(file = %markdown_path.display(), note_id = id, action = "matched", "Updated duplicate note");
+                        self.events.on_card_matched(markdown_path, id);
+                        return Ok(Some(id));
+                    }
+                }
+            }
+        }
+
+        let id = self.create_note(fields_html, deck_name, tags, is_cloze)?;
+        self.record_history(HistoryAction::Created, markdown_path, id, None)?;
+        info!(file = %markdown_path.display(), note_id = id, action = "created", "Created note");
+        self.events.on_card_created(markdown_path, id);
+        Ok(Some(id))
+    }
+
+    /// Content hash of a note for the history journal; None without a journal
+    fn history_hash(&self, note_id: i64) -> Result<Option<String>> {
+        if self.history.is_none() {
+            return Ok(None);
+        }
+        Ok(self
+            .repository
+            .note_content(note_id)?
+            .map(|(fields, tags)| history::content_hash(&fields, &tags)))
+    }
+
+    /// Append a created or updated note to the history journal, if enabled
+    fn record_history(
+        &self,
+        action: HistoryAction,
+        markdown_path: &Path,
+        note_id: i64,
+        before: Option<String>,
+    ) -> Result<()> {
+        if self.history.is_none() {
+            return Ok(());
+        }
+        let entry = HistoryEntry {
+            file: Some(markdown_path.display().to_string()),
+            before,
+            after: self.history_hash(note_id)?,
+            ..HistoryEntry::now(action, note_id)
+        };
+        if let Some(history) = &self.history {
+            history.append(&entry)?;
+        }
+        Ok(())
     }
 
     /// Create a note (basic or cloze) in Anki
@@ -205,40 +802,212 @@ impl CardCollector {
         tags: &[String],
         is_cloze: bool,
     ) -> Result<i64> {
-        if is_cloze {
-            self.repository
-                .create_cloze_note(&fields_html[0], deck_name, tags)
+        let notetype_id = if is_cloze {
+            self.cloze_notetype_id()?
         } else {
-            self.repository.create_basic_note(
-                &fields_html[0],
-                &fields_html[1],
-                deck_name,
-                tags,
-                self.card_type.as_deref(),
-            )
+            self.basic_notetype_id()?
+        };
+        let deck_id = self.deck_id(deck_name)?;
+        let fields = if is_cloze {
+            &fields_html[..1]
+        } else {
+            fields_html
+        };
+        let (tags, _) = if self.sync_tags {
+            tag_changes(&[], tags, self.tag_namespace.as_deref())
+        } else {
+            (tags.to_vec(), Vec::new())
+        };
+        let id = self
+            .repository
+            .add_note_with_fields(notetype_id, deck_id, fields, &tags)?;
+        self.created_ids.push(id);
+        Ok(id)
+    }
+
+    /// Basic notetype ID, looked up once per collector
+    fn basic_notetype_id(&mut self) -> Result<i64> {
+        if let Some(id) = self.basic_notetype_id {
+            return Ok(id);
+        }
+        let id = self
+            .repository
+            .find_or_create_basic_notetype(self.card_type.as_deref())?;
+        self.basic_notetype_id = Some(id);
+        Ok(id)
+    }
+
+    /// Field count of the basic notetype; fields past front/back are filled
+    /// from `>>` groups only when the notetype has them. Only looks the
+    /// notetype up, so a missing one is reported rather than created
+    fn basic_field_count(&mut self) -> Result<usize> {
+        if let Some(count) = self.basic_field_count {
+            return Ok(count);
+        }
+        let notetype_id = match self.basic_notetype_id {
+            Some(id) => id,
+            None => self.repository.find_notetype_by_name(
+                self.card_type.as_deref().unwrap_or(DEFAULT_BASIC_NOTETYPE),
+            )?,
+        };
+        let count = self.repository.notetype_field_count(notetype_id)?;
+        self.basic_field_count = Some(count);
+        Ok(count)
+    }
+
+    /// Cloze notetype ID, looked up once per collector
+    fn cloze_notetype_id(&mut self) -> Result<i64> {
+        if let Some(id) = self.cloze_notetype_id {
+            return Ok(id);
+        }
+        let id = self.repository.find_or_create_cloze_notetype()?;
+        self.cloze_notetype_id = Some(id);
+        Ok(id)
+    }
+
+    /// Deck ID for `deck_name`, creating the deck on first use
+    fn deck_id(&mut self, deck_name: &str) -> Result<i64> {
+        if let Some(&id) = self.deck_ids.get(deck_name) {
+            return Ok(id);
         }
+        let id = self
+            .repository
+            .get_or_create_deck_with_options(deck_name, self.deck_options.as_deref())?;
+        self.deck_ids.insert(deck_name.to_string(), id);
+        Ok(id)
     }
 
     fn add_file_path_footer(&self, html: &str, file_path: &Path) -> String {
+        let path = file_path.display().to_string();
+        let label = match &self.source_link {
+            Some(template) => format!(
+                r#"<a href="{}">{}</a>"#,
+                html_escape::encode_double_quoted_attribute(&source_uri(
+                    template,
+                    file_path,
+                    self.source_root.as_deref()
+                )),
+                html_escape::encode_text(&path)
+            ),
+            None => path,
+        };
         let footer = format!(
             r#"<p><span style="font-size: 9pt;">File: {}</span></p>"#,
-            file_path.display()
+            label
         );
         format!("{}{}", html, footer)
     }
 
+    /// Path naming a source file in its index note: relative to
+    /// `source_root` if the file is below it, else canonical, so the same file
+    /// gets the same index note however it was passed
+    fn index_path(&self, file_path: &Path) -> String {
+        let canonical = file_path
+            .canonicalize()
+            .unwrap_or_else(|_| file_path.to_path_buf());
+        let root = self
+            .source_root
+            .as_ref()
+            .and_then(|root| root.canonicalize().ok());
+        match root.and_then(|root| canonical.strip_prefix(root).ok().map(Path::to_path_buf)) {
+            Some(relative) => relative.to_string_lossy().replace('\\', "/"),
+            None => canonical.display().to_string(),
+        }
+    }
+
+    /// Create or update the index note of a source file: `Index: <path>` on
+    /// the front (see [`Self::index_path`]), a list of `nid:` links to `cards`
+    /// (note ID, first line of the front) on the back. Found again by its tag
+    /// and front; the index notes are read once per run.
+    fn update_index_note(
+        &mut self,
+        file_path: &Path,
+        cards: &[(i64, String)],
+        deck_name: &str,
+    ) -> Result<()> {
+        let front = format!("Index: {}", self.index_path(file_path));
+        let items: String = cards
+            .iter()
+            .map(|(id, title)| {
+                format!(
+                    r#"<li><a href="nid:{}">{}</a></li>"#,
+                    id,
+                    html_escape::encode_text(title)
+                )
+            })
+            .collect();
+        let back = format!("<ul>{}</ul>", items);
+
+        if self.index_notes_by_front.is_none() {
+            let mut index_notes = HashMap::new();
+            for id in self
+                .repository
+                .note_ids(Some(&format!("tag:{}", INDEX_TAG)))?
+            {
+                if let Some((mut fields, _)) = self.repository.note_content(id)? {
+                    fields.resize(2, String::new());
+                    let back = fields.pop().unwrap_or_default();
+                    let front = fields.pop().unwrap_or_default();
+                    // Keep the oldest index note of a file
+                    index_notes.entry(front).or_insert((id, back));
+                }
+            }
+            self.index_notes_by_front = Some(index_notes);
+        }
+        let existing = self
+            .index_notes_by_front
+            .as_ref()
+            .and_then(|index_notes| index_notes.get(&front))
+            .map(|(id, existing_back)| (*id, *existing_back == back));
+
+        let fields = [front.clone(), back.clone()];
+        let id = match existing {
+            Some((id, true)) => {
+                debug!(note_id = id, "Index note up to date");
+                return Ok(());
+            }
+            Some((id, false)) => {
+                self.repository.update_note(id, &fields)?;
+                info!(file = %file_path.display(), note_id = id, action = "updated", "Updated index note");
+                id
+            }
+            None => {
+                let tags = [INDEX_TAG.to_string()];
+                let id = self.create_note(&fields, deck_name, &tags, false)?;
+                info!(file = %file_path.display(), note_id = id, action = "created", "Created index note");
+                id
+            }
+        };
+        if let Some(index_notes) = self.index_notes_by_front.as_mut() {
+            index_notes.insert(front, (id, back));
+        }
+        Ok(())
+    }
+
     /// Process a single markdown file and add/update cards in Anki
     /// Returns the number of cards processed
     pub fn process_file(&mut self, markdown_path: impl AsRef<Path>) -> Result<usize> {
         let markdown_path = markdown_path.as_ref();
 
+        self.events.on_file_start(markdown_path);
+
+        let result = match self.format {
+            SourceFormat::Markdown => self.process_file_impl(markdown_path),
+            SourceFormat::Org => self.process_org_file_impl(markdown_path),
+        };
         // Handle error according to ignore_errors flag
-        match self.process_file_impl(markdown_path) {
+        match result {
             Ok(count) => Ok(count),
             Err(e) => {
+                self.events.on_error(markdown_path, &e);
                 if self.ignore_errors {
-                    // Collect error and continue
-                    let error_msg = format!("{}: {:#}", markdown_path.display(), e);
+                    // Collect error and continue; errors about a card already
+                    // start with its `path:line`
+                    let error_msg = if e.downcast_ref::<SourceLocation>().is_some() {
+                        format!("{:#}", e)
+                    } else {
+                        format!("{}: {:#}", markdown_path.display(), e)
+                    };
                     self.errors.push(error_msg);
                     Ok(0)
                 } else {
@@ -248,328 +1017,2230 @@ impl CardCollector {
         }
     }
 
+    /// Whether a source file changed since it was last collected (always
+    /// true without a hash cache); reports skipped files to the event sink
+    fn has_changed(&mut self, path: &Path) -> Result<bool> {
+        let has_changed = self.timed(Phase::Hash, |this| {
+            let Some(cache) = &this.hash_cache else {
+                return Ok(true);
+            };
+            match this.prechecked.remove(path) {
+                Some(has_changed) => Ok(has_changed),
+                None => cache
+                    .file_has_changed(path)
+                    .context("Failed to check file hash"),
+            }
+        })?;
+
+        if !has_changed {
+            debug!(file = %path.display(), action = "skipped", "Skipping unchanged file");
+            // A moved file: its cache entry moves along
+            if let Some(cache) = &mut self.hash_cache {
+                if !cache.contains(path) {
+                    cache
+                        .update_hash(path)
+                        .context("Failed to update file hash")?;
+                }
+            }
+            self.events.on_file_skipped(path);
+        }
+        Ok(has_changed)
+    }
+
+    /// Write a source file back (with injected IDs) in its original encoding
+    /// and record its new hash
+    fn save_source(
+        &mut self,
+        path: &Path,
+        content: &str,
+        source_encoding: file_writer::SourceEncoding,
+    ) -> Result<()> {
+        let preserved = self.timed(Phase::Write, |_| {
+            file_writer::write_markdown_file_with_encoding(path, content, source_encoding)
+                .with_context(|| format!("Failed to write markdown file: {}", path.display()))
+        })?;
+        if !preserved {
+            let message = format!(
+                "Content can't be stored as {}; file was converted to UTF-8",
+                source_encoding.encoding.name()
+            );
+            warn!(file = %path.display(), "{}", message);
+            self.events.on_warning(path, &message);
+            self.warnings
+                .push(format!("{}: {}", path.display(), message));
+        }
+
+        // After successful processing, update hash cache
+        self.timed(Phase::Hash, |this| match &mut this.hash_cache {
+            Some(cache) => cache
+                .update_hash(path)
+                .context("Failed to update file hash"),
+            None => Ok(()),
+        })
+    }
+
     /// Internal implementation of process_file
     fn process_file_impl(&mut self, markdown_path: &Path) -> Result<usize> {
         // Check if file has changed (skip if unchanged and cache exists)
-        if let Some(cache) = &self.hash_cache {
-            let has_changed = cache
-                .file_has_changed(markdown_path)
-                .context("Failed to check file hash")?;
-
-            if !has_changed {
-                // File unchanged, skip processing
-                debug!(?markdown_path, "Skipping unchanged file");
-                return Ok(0);
-            }
+        if !self.has_changed(markdown_path)? {
+            return Ok(0);
         }
 
-        // Read markdown file
-        let mut content = file_writer::read_markdown_file(markdown_path)
-            .with_context(|| format!("Failed to read markdown file: {}", markdown_path.display()))?;
-
-        // Parse sections first to identify inka2 blocks
-        let parser = section_parser::SectionParser::new();
-        let sections = parser.parse(&content);
+        // Read markdown file and parse sections first to identify inka2 blocks
+        let (mut content, source_encoding, frontmatter, sections) =
+            self.timed(Phase::Parse, |this| {
+                let (content, source_encoding) =
+                    file_writer::read_markdown_file_with_encoding(markdown_path).with_context(
+                        || format!("Failed to read markdown file: {}", markdown_path.display()),
+                    )?;
+                // Convert sections to owned Strings to avoid borrowing issues when mutating content.
+                // Sections and cards marked <!--inka-ignore--> are dropped here, so they
+                // get no warnings, media or notes
+                let sections = this.reader.sections(&content);
+                // Frontmatter `deck:`/`tags:` apply to all sections of the file
+                let frontmatter = frontmatter::parse_frontmatter(&content).unwrap_or_default();
+                anyhow::Ok((content, source_encoding, frontmatter, sections))
+            })?;
+        if source_encoding.encoding != encoding_rs::UTF_8 {
+            debug!(file = %markdown_path.display(), encoding = source_encoding.encoding.name(), "Decoded non-UTF-8 markdown");
+        }
 
         if sections.is_empty() {
+            self.events.on_file_done(markdown_path, 0);
             return Ok(0);
         }
 
         // Concatenate all section content to extract media only from sections
         let mut all_section_content = String::new();
         for section in &sections {
-            all_section_content.push_str(section);
+            all_section_content.push_str(&section.text);
             all_section_content.push('\n'); // Maintain separation between sections
         }
 
-        // Extract and handle media files only from section content
-        let image_paths = media_handler::extract_image_paths(&all_section_content);
-        let mut path_mapping = HashMap::new();
-
-        for image_path in image_paths {
-            // Resolve relative paths relative to markdown file location
-            let markdown_dir = markdown_path
-                .parent()
-                .ok_or_else(|| anyhow::anyhow!("Cannot determine markdown file directory"))?;
-            let absolute_image_path = markdown_dir.join(&image_path);
+        // Report warnings before anything is written, so --strict leaves the file untouched
+        let warnings = self.timed(Phase::Parse, |this| {
+            this.check_sections(markdown_path, &sections, &all_section_content)
+        })?;
+        for (line, warning) in &warnings {
+            match line {
+                Some(line) => {
+                    self.events.on_warning_at(markdown_path, *line, warning);
+                    warn!(file = %markdown_path.display(), line, "{}", warning);
+                    self.warnings.push(format!(
+                        "{}:{}: {}",
+                        markdown_path.display(),
+                        line,
+                        warning
+                    ));
+                }
+                None => {
+                    self.events.on_warning(markdown_path, warning);
+                    warn!(file = %markdown_path.display(), "{}", warning);
+                    self.warnings
+                        .push(format!("{}: {}", markdown_path.display(), warning));
+                }
+            }
+        }
+        if self.strict && !warnings.is_empty() {
+            let messages: Vec<String> = warnings
+                .iter()
+                .map(|(line, warning)| match line {
+                    Some(line) => format!("line {}: {}", line, warning),
+                    None => warning.clone(),
+                })
+                .collect();
+            return Err(DomainError::StrictWarning(format!(
+                "{} warning{} treated as error{} (--strict): {}",
+                warnings.len(),
+                if warnings.len() == 1 { "" } else { "s" },
+                if warnings.len() == 1 { "" } else { "s" },
+                messages.join("; ")
+            ))
+            .into());
+        }
+
+        // Extract and handle media files only from section content
+        let image_paths = media_handler::extract_image_paths(&all_section_content);
+        let path_mapping = self.timed(Phase::Media, |this| {
+            if this.check {
+                this.check_media_files(markdown_path, image_paths)
+            } else {
+                this.copy_media_files(markdown_path, image_paths)
+            }
+        })?;
+
+        let mut card_count = 0;
+        // Each injected ID comment moves the following cards down a line
+        let mut injected_ids = 0;
+        // (note ID, card title) for the index note, and the deck it goes to
+        let mut index_entries: Vec<(i64, String)> = Vec::new();
+        let mut index_deck = None;
+
+        let cards = self.reader.section_cards(&sections, &frontmatter);
+        for card in &cards {
+            // Items that are no card were reported by check_sections
+            let Some(kind) = card.kind else {
+                continue;
+            };
+            // Errors about the card name the line it starts on
+            let location = || SourceLocation::new(markdown_path, card.line);
+            // Extract existing ID if present
+            let existing_id = card_parser::extract_anki_id(&card.text);
+
+            let field_count = match kind {
+                CardKind::Basic => {
+                    self.timed(Phase::Repository, |this| this.basic_field_count())?
+                }
+                CardKind::Cloze => 1,
+            };
+            let fields_md = self
+                .timed(Phase::Parse, |_| card.fields(field_count))
+                .context(DomainError::ParseError(match kind {
+                    CardKind::Basic => "Failed to parse basic card fields".to_string(),
+                    CardKind::Cloze => "Failed to parse cloze card field".to_string(),
+                }))
+                .with_context(location)?;
+
+            let fields_html = self.timed(Phase::Convert, |this| {
+                // Convert to HTML (after transforming cloze syntax) and update media paths
+                let mut fields_html: Vec<String> = fields_md
+                    .iter()
+                    .map(|field_md| {
+                        let field_md = match kind {
+                            CardKind::Basic => field_md.clone(),
+                            CardKind::Cloze => cloze_converter::convert_cloze_syntax(field_md),
+                        };
+                        let html =
+                            converter::markdown_to_html_with(&field_md, &this.markdown, &this.math);
+                        let html = media_handler::update_media_paths_in_html(&html, &path_mapping);
+                        converter::apply_text_direction(
+                            &html,
+                            card.lang.as_deref(),
+                            card.dir.as_deref(),
+                        )
+                    })
+                    .collect();
+
+                // Add file path footer to the back field, or the cloze text
+                let footer_field = match kind {
+                    CardKind::Basic => 1,
+                    CardKind::Cloze => 0,
+                };
+                fields_html[footer_field] =
+                    this.add_file_path_footer(&fields_html[footer_field], markdown_path);
+                fields_html
+            });
+            let title = extract_first_line(&fields_html[0]);
+
+            let new_id = self
+                .timed(Phase::Repository, |this| {
+                    this.process_card(
+                        markdown_path,
+                        existing_id,
+                        fields_html,
+                        &card.deck,
+                        &card.tags,
+                        kind == CardKind::Cloze,
+                    )
+                })
+                .with_context(location)?;
+            if let Some(id) = new_id {
+                let note_pos = file_writer::line_offset(&content, card.line + injected_ids);
+                content = write_markdown_id(&content, note_pos, existing_id, id);
+                if existing_id.is_none() {
+                    injected_ids += 1;
+                }
+            }
+            if let Some(id) = new_id.or(existing_id) {
+                index_entries.push((id, title));
+                index_deck.get_or_insert_with(|| card.deck.clone());
+            }
+            card_count += 1;
+        }
+
+        if self.check {
+            self.events.on_file_done(markdown_path, card_count);
+            return Ok(card_count);
+        }
+
+        if self.index_notes {
+            if let Some(deck_name) = index_deck {
+                self.timed(Phase::Repository, |this| {
+                    this.update_index_note(markdown_path, &index_entries, &deck_name)
+                })?;
+            }
+        }
+
+        // Write updated content back to file if IDs were injected, in its original encoding
+        self.save_source(markdown_path, &content, source_encoding)?;
+
+        self.events.on_file_done(markdown_path, card_count);
+        Ok(card_count)
+    }
+
+    /// `process_file` for org-mode files: every `:drill:`/`:anki:` headline is
+    /// a card, and note IDs are kept in its `:ANKI_NOTE_ID:` property
+    fn process_org_file_impl(&mut self, org_path: &Path) -> Result<usize> {
+        if !self.has_changed(org_path)? {
+            return Ok(0);
+        }
+
+        let (mut content, source_encoding, cards) = self.timed(Phase::Parse, |_| {
+            let (content, source_encoding) =
+                file_writer::read_markdown_file_with_encoding(org_path)
+                    .with_context(|| format!("Failed to read org file: {}", org_path.display()))?;
+            let cards = org_parser::parse_org_cards(&content);
+            anyhow::Ok((content, source_encoding, cards))
+        })?;
+        if cards.is_empty() {
+            self.events.on_file_done(org_path, 0);
+            return Ok(0);
+        }
+
+        let all_card_content: String = cards
+            .iter()
+            .flat_map(|card| {
+                [
+                    card.front_md.as_str(),
+                    card.back_md.as_deref().unwrap_or(""),
+                ]
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let image_paths = media_handler::extract_image_paths(&all_card_content);
+        let path_mapping = self.timed(Phase::Media, |this| {
+            if this.check {
+                this.check_media_files(org_path, image_paths)
+            } else {
+                this.copy_media_files(org_path, image_paths)
+            }
+        })?;
+
+        let mut new_ids = Vec::new();
+        let mut index_entries: Vec<(i64, String)> = Vec::new();
+        let mut index_deck = None;
+        for card in &cards {
+            if let Some(value) = &card.malformed_id {
+                self.record_warning(
+                    org_path,
+                    format!(
+                        "line {}: {} '{}' is not a note ID; the card gets a new note",
+                        card.headline_line + 1,
+                        org_parser::NOTE_ID_PROPERTY,
+                        value
+                    ),
+                );
+            }
+            let deck_name = self.reader.target_deck(card.deck.clone());
+            let tags = self.reader.merge_tags(card.tags.clone(), &[]);
+
+            let fields_html = self.timed(Phase::Convert, |this| {
+                // Org clozes are already in Anki's `{{c1::...}}` syntax
+                let fields_md = [Some(&card.front_md), card.back_md.as_ref()];
+                let mut fields_html: Vec<String> = fields_md
+                    .into_iter()
+                    .flatten()
+                    .map(|field_md| {
+                        let html =
+                            converter::markdown_to_html_with(field_md, &this.markdown, &this.math);
+                        let html = media_handler::update_media_paths_in_html(&html, &path_mapping);
+                        converter::apply_text_direction(
+                            &html,
+                            this.markdown.lang.as_deref(),
+                            this.markdown.dir.as_deref(),
+                        )
+                    })
+                    .collect();
+                let last = fields_html.len() - 1;
+                fields_html[last] = this.add_file_path_footer(&fields_html[last], org_path);
+                fields_html
+            });
+            let title = extract_first_line(&fields_html[0]);
+
+            let new_id = self.timed(Phase::Repository, |this| {
+                this.process_card(
+                    org_path,
+                    card.id,
+                    fields_html,
+                    &deck_name,
+                    &tags,
+                    card.is_cloze(),
+                )
+            })?;
+            if let Some(id) = new_id {
+                new_ids.push((card.headline_line, id));
+            }
+            if let Some(id) = new_id.or(card.id) {
+                index_entries.push((id, title));
+                index_deck.get_or_insert_with(|| deck_name.clone());
+            }
+        }
+
+        if self.check {
+            self.events.on_file_done(org_path, cards.len());
+            return Ok(cards.len());
+        }
+
+        if self.index_notes {
+            if let Some(deck_name) = index_deck {
+                self.timed(Phase::Repository, |this| {
+                    this.update_index_note(org_path, &index_entries, &deck_name)
+                })?;
+            }
+        }
+
+        // Bottom-up, so inserted drawers don't shift the lines of cards still to do
+        for (headline_line, id) in new_ids.into_iter().rev() {
+            content = org_parser::set_note_id(&content, headline_line, id);
+        }
+        self.save_source(org_path, &content, source_encoding)?;
+
+        self.events.on_file_done(org_path, cards.len());
+        Ok(cards.len())
+    }
+
+    /// Find problems that don't stop processing (unless `strict` is set):
+    /// sections without cards, orphaned or duplicate note IDs, images without
+    /// alt text (with `image_alt`), and images whose path won't be rewritten to the media file
+    fn check_sections(
+        &mut self,
+        markdown_path: &Path,
+        sections: &[Span<String>],
+        all_section_content: &str,
+    ) -> Result<Vec<(Option<usize>, String)>> {
+        let mut warnings = Vec::new();
+        let mut file_ids = std::collections::HashSet::new();
+
+        for section in sections {
+            let notes =
+                section_parser::extract_note_spans_for(&section.text, self.reader.formats());
+            if notes.is_empty() {
+                let deck_name = section_parser::extract_deck_name(&section.text)
+                    .unwrap_or_else(|| "Default".to_string());
+                warnings.push((
+                    Some(section.line),
+                    format!("Section for deck '{}' contains no cards", deck_name),
+                ));
+            }
+
+            for note in &notes {
+                let line = section.line + note.line - 1;
+                if !card_parser::is_basic_card(&note.text, self.reader.formats())
+                    && !card_parser::is_cloze_card(&note.text)
+                {
+                    let first_line = note
+                        .text
+                        .lines()
+                        .map(str::trim)
+                        .find(|text| !text.is_empty() && !text.starts_with("<!--ID:"))
+                        .unwrap_or_default();
+                    warnings.push((
+                        Some(line),
+                        format!(
+                            "'{}' produced no card: it has no answer ('>' or 'A:') and no cloze deletion",
+                            first_line
+                        ),
+                    ));
+                    self.malformed_cards.push(format!(
+                        "{}:{}: {}",
+                        markdown_path.display(),
+                        line,
+                        first_line
+                    ));
+                }
+
+                let Some(id) = card_parser::extract_anki_id(&note.text) else {
+                    continue;
+                };
+                let line = Some(line);
+                if !file_ids.insert(id) {
+                    warnings.push((
+                        line,
+                        format!("Note ID {} is used more than once in this file", id),
+                    ));
+                    continue;
+                }
+                match self.seen_ids.get(&id) {
+                    Some(first_path) if first_path != markdown_path => {
+                        warnings.push((
+                            line,
+                            format!("Note ID {} is also used in {}", id, first_path.display()),
+                        ));
+                        continue;
+                    }
+                    Some(_) => {}
+                    None => {
+                        self.seen_ids.insert(id, markdown_path.to_path_buf());
+                    }
+                }
+                if !self.repository.note_exists(id)? {
+                    warnings.push((
+                        line,
+                        format!(
+                            "Note ID {} found in markdown but doesn't exist in Anki. Creating new note with new ID.",
+                            id
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if self.image_alt {
+            for image_path in media_handler::find_images_without_alt(all_section_content) {
+                warnings.push((None, format!("Image '{}' has no alt text", image_path)));
+            }
+        }
+
+        if let Some(config) = &self.lint {
+            for section in sections {
+                for issue in lint::lint_section(&section.text, self.reader.formats(), config) {
+                    // Reported above, with or without lint
+                    if issue.rule == LintRule::ImageAlt {
+                        continue;
+                    }
+                    warnings.push((
+                        Some(section.line + issue.line - 1),
+                        format!("[{}] {}", issue.rule, issue.message),
+                    ));
+                }
+            }
+            if config.spell.enabled {
+                let lang = self.markdown.lang.as_deref();
+                for issue in lint::spell_check_sections(
+                    sections,
+                    self.reader.formats(),
+                    lang,
+                    &config.spell,
+                )? {
+                    warnings.push((
+                        Some(issue.line),
+                        format!("[{}] {}", issue.rule, issue.message),
+                    ));
+                }
+            }
+        }
+
+        // Images whose src in the generated HTML doesn't normalize to a
+        // markdown path are not rewritten and won't display in Anki
+        let markdown_paths = media_handler::extract_image_paths(all_section_content);
+        let html = converter::markdown_to_html(all_section_content);
+        for src in media_handler::extract_image_paths(&html) {
+            if !markdown_paths.contains(&src) && !src.starts_with("data:") {
+                warnings.push((
+                    None,
+                    format!("Image '{}' cannot be mapped to a media file", src),
+                ));
+            }
+        }
+
+        Ok(warnings)
+    }
+
+    /// Copy the images referenced by a markdown file into collection.media/
+    /// Returns the mapping from markdown image path to media filename
+    fn copy_media_files(
+        &mut self,
+        markdown_path: &Path,
+        image_paths: Vec<String>,
+    ) -> Result<HashMap<String, String>> {
+        let mut path_mapping = HashMap::new();
+
+        for image_path in image_paths {
+            // Resolve relative paths relative to markdown file location
+            let markdown_dir = markdown_path
+                .parent()
+                .ok_or_else(|| anyhow::anyhow!("Cannot determine markdown file directory"))?;
+            let absolute_image_path = media_handler::resolve_media_path(markdown_dir, &image_path);
+
+            // Copy image to media directory; --force overwrites without asking
+            let force = self.force;
+            let resolver = &mut self.resolver;
+            match media_handler::copy_media_to_anki_with(
+                &absolute_image_path,
+                &self.media_dir,
+                &self.media,
+                |filename| {
+                    if force {
+                        return MediaConflictAction::Overwrite;
+                    }
+                    let conflict = Conflict::MediaName {
+                        path: markdown_path,
+                        filename,
+                    };
+                    match resolver.resolve(&conflict) {
+                        Resolution::Keep => MediaConflictAction::KeepExisting,
+                        Resolution::Replace => MediaConflictAction::Overwrite,
+                        Resolution::Skip | Resolution::Use(_) => MediaConflictAction::Fail,
+                    }
+                },
+            ) {
+                Ok(filename) => {
+                    debug!("Copied media file: {} -> {}", image_path, filename);
+                    path_mapping.insert(image_path.clone(), filename);
+                }
+                Err(e) => {
+                    return Err(e.context(DomainError::MediaError(format!(
+                        "Failed to copy media file '{}'",
+                        image_path
+                    ))));
+                }
+            }
+        }
+
+        Ok(path_mapping)
+    }
+
+    /// `check` counterpart of `copy_media_files`: records images that would be
+    /// copied instead of copying them
+    fn check_media_files(
+        &mut self,
+        markdown_path: &Path,
+        image_paths: Vec<String>,
+    ) -> Result<HashMap<String, String>> {
+        let mut path_mapping = HashMap::new();
+
+        for image_path in image_paths {
+            let markdown_dir = markdown_path
+                .parent()
+                .ok_or_else(|| anyhow::anyhow!("Cannot determine markdown file directory"))?;
+            let absolute_image_path = media_handler::resolve_media_path(markdown_dir, &image_path);
+
+            let (filename, needs_copy) =
+                media_handler::media_needs_copy(&absolute_image_path, &self.media_dir, &self.media)
+                    .map_err(|e| {
+                        e.context(DomainError::MediaError(format!(
+                            "Failed to check media file '{}'",
+                            image_path
+                        )))
+                    })?;
+            if needs_copy {
+                self.record_pending(
+                    markdown_path,
+                    format!("media file '{}' would be copied", image_path),
+                );
+            }
+            path_mapping.insert(image_path, filename);
+        }
+
+        Ok(path_mapping)
+    }
+
+    /// Record what collecting this card would change, without changing it
+    fn check_card(
+        &mut self,
+        markdown_path: &Path,
+        existing_id: Option<i64>,
+        fields_html: &[String],
+        deck_name: &str,
+        tags: &[String],
+    ) -> Result<()> {
+        let change = match existing_id {
+            Some(id) => match self.repository.note_content(id)? {
+                None => Some(format!("note {} doesn't exist and would be recreated", id)),
+                Some((fields, note_tags)) => {
+                    let fields_differ = fields_html
+                        .iter()
+                        .enumerate()
+                        .any(|(idx, html)| fields.get(idx) != Some(html));
+                    let tags_missing = tags
+                        .iter()
+                        .any(|tag| !note_tags.iter().any(|t| t.eq_ignore_ascii_case(tag)));
+                    let tags_removed = self.sync_tags
+                        && !tag_changes(&note_tags, tags, self.tag_namespace.as_deref())
+                            .1
+                            .is_empty();
+                    if fields_differ {
+                        Some(format!("note {} would be updated", id))
+                    } else if tags_missing {
+                        Some(format!("note {} would get new tags", id))
+                    } else if tags_removed {
+                        Some(format!("note {} would lose tags removed from markdown", id))
+                    } else if self.move_on_deck_change && !self.note_in_deck(id, deck_name)? {
+                        Some(format!(
+                            "cards of note {} would move to deck '{}'",
+                            id, deck_name
+                        ))
+                    } else {
+                        None
+                    }
+                }
+            },
+            None if self.update_ids => {
+                let mut matching_ids = self.repository.search_by_html(fields_html)?;
+                if self.same_deck {
+                    let mut in_deck = Vec::new();
+                    for id in matching_ids {
+                        if self.note_in_deck(id, deck_name)? {
+                            in_deck.push(id);
+                        }
+                    }
+                    matching_ids = in_deck;
+                }
+                match matching_ids.as_slice() {
+                    [] => Some("a new note would be created".to_string()),
+                    [id] => Some(format!(
+                        "ID of matching {} would be injected",
+                        self.describe_matches(&[*id])?
+                    )),
+                    [id, ..] => Some(format!(
+                        "ID of matching {} would be injected ({} notes match)",
+                        self.describe_matches(&[*id])?,
+                        matching_ids.len()
+                    )),
+                }
+            }
+            None => Some("a new note would be created".to_string()),
+        };
+
+        if let Some(change) = change {
+            self.record_pending(markdown_path, change);
+        }
+        Ok(())
+    }
+
+    /// Add the markdown's tags to a note; with `sync_tags`, also remove the
+    /// ones an earlier run applied that are gone from the markdown
+    fn merge_tags(&mut self, note_id: i64, tags: &[String]) -> Result<()> {
+        if !self.sync_tags {
+            if !tags.is_empty() {
+                self.repository.add_tags(note_id, tags)?;
+            }
+            return Ok(());
+        }
+        let Some((_, current)) = self.repository.note_content(note_id)? else {
+            return Ok(());
+        };
+        let (added, removed) = tag_changes(&current, tags, self.tag_namespace.as_deref());
+        if !added.is_empty() {
+            self.repository.add_tags(note_id, &added)?;
+        }
+        if !removed.is_empty() {
+            self.repository.remove_tags(note_id, &removed)?;
+            debug!(note_id, ?removed, "Removed tags no longer in markdown");
+        }
+        Ok(())
+    }
+
+    /// `tags` in `tag_namespace`, if set; tags already in it are kept as they are
+    fn namespaced_tags(&self, tags: &[String]) -> Vec<String> {
+        let Some(namespace) = &self.tag_namespace else {
+            return tags.to_vec();
+        };
+        let prefix = format!("{}::", namespace);
+        tags.iter()
+            .map(|tag| match strip_prefix_ignore_case(tag, &prefix) {
+                Some(_) => tag.clone(),
+                None => format!("{}{}", prefix, tag),
+            })
+            .collect()
+    }
+
+    /// Whether a note's cards are in `deck_name`; cards in a filtered deck
+    /// count as in their home deck
+    fn note_in_deck(&mut self, note_id: i64, deck_name: &str) -> Result<bool> {
+        Ok(match self.repository.note_location(note_id)? {
+            Some((deck, _)) => same_deck_name(&deck, deck_name),
+            None => false,
+        })
+    }
+
+    /// With `move_on_deck_change`, move the cards of an updated note that are
+    /// in another deck than the card's to `deck_name`. Without it, notes a
+    /// `deck`/`deck_prefix` override would move stay put with a warning
+    fn follow_deck_change(
+        &mut self,
+        markdown_path: &Path,
+        note_id: i64,
+        deck_name: &str,
+    ) -> Result<()> {
+        let deck_overridden = self.reader.overrides_deck();
+        if !self.move_on_deck_change && !deck_overridden {
+            return Ok(());
+        }
+        let Some((deck, _)) = self.repository.note_location(note_id)? else {
+            return Ok(());
+        };
+        if same_deck_name(&deck, deck_name) {
+            return Ok(());
+        }
+        if !self.move_on_deck_change {
+            self.record_warning(
+                markdown_path,
+                format!(
+                    "note {} stays in '{}': the deck override only applies to new notes \
+                     (pass --move-on-deck-change to move it to '{}')",
+                    note_id, deck, deck_name
+                ),
+            );
+            return Ok(());
+        }
+        let deck_id = self.deck_id(deck_name)?;
+        self.repository.move_note_cards(note_id, deck_id)?;
+        info!(file = %markdown_path.display(), note_id, from = %deck, to = %deck_name, "Moved cards to the card's deck");
+        self.deck_moves.push(format!(
+            "{}: note {} moved from '{}' to '{}'",
+            markdown_path.display(),
+            note_id,
+            deck,
+            deck_name
+        ));
+        Ok(())
+    }
+
+    /// Give `new_id`, recreated for the deleted note `old_id`, the old note's
+    /// scheduling from the backup; without one (or the note in it), tag it
+    /// [`REIMPORTED_TAG`] (in `tag_namespace`), since its cards start over as new
+    fn restore_scheduling(&mut self, markdown_path: &Path, old_id: i64, new_id: i64) -> Result<()> {
+        let scheduling = match &self.backup {
+            Some(backup) => backup.card_scheduling(old_id)?,
+            None => Vec::new(),
+        };
+        let restored = if scheduling.is_empty() {
+            0
+        } else {
+            self.repository
+                .restore_card_scheduling(new_id, &scheduling)?
+        };
+
+        let outcome = if restored > 0 {
+            format!("scheduling of {} card(s) restored from backup", restored)
+        } else {
+            let tags = self.namespaced_tags(&[REIMPORTED_TAG.to_string()]);
+            self.repository.add_tags(new_id, &tags)?;
+            format!("tagged '{}', its cards start as new", tags[0])
+        };
+        info!(file = %markdown_path.display(), old_id, note_id = new_id, restored, "Recreated note for orphaned ID");
+        self.recreated_notes.push(format!(
+            "{}: note {} recreated as {}; {}",
+            markdown_path.display(),
+            old_id,
+            new_id,
+            outcome
+        ));
+        Ok(())
+    }
+
+    /// `note 1 (deck 'A', notetype 'B'), note 2 (...)`, for messages about
+    /// the notes `--update-ids` matched
+    fn describe_matches(&mut self, note_ids: &[i64]) -> Result<String> {
+        let mut notes = Vec::new();
+        for &id in note_ids {
+            notes.push(match self.repository.note_location(id)? {
+                Some((deck, notetype)) => {
+                    format!("note {} (deck '{}', notetype '{}')", id, deck, notetype)
+                }
+                None => format!("note {}", id),
+            });
+        }
+        Ok(notes.join(", "))
+    }
+
+    fn record_warning(&mut self, markdown_path: &Path, message: String) {
+        warn!(file = %markdown_path.display(), "{}", message);
+        self.events.on_warning(markdown_path, &message);
+        self.warnings
+            .push(format!("{}: {}", markdown_path.display(), message));
+    }
+
+    fn record_multi_match(&mut self, markdown_path: &Path, notes: &str, outcome: &str) {
+        self.record_warning(
+            markdown_path,
+            format!("card matches {}; {}", notes, outcome),
+        );
+        self.multi_matches.extend(self.warnings.last().cloned());
+    }
+
+    fn record_pending(&mut self, markdown_path: &Path, change: String) {
+        debug!(file = %markdown_path.display(), change = %change, "Out of sync");
+        self.pending_changes
+            .push(format!("{}: {}", markdown_path.display(), change));
+    }
+
+    /// Process a markdown file, or the `.md` files of a directory
+    /// (`.org` files with `SourceFormat::Org`; subdirectories only if `recursive`)
+    /// Returns the number of cards processed
+    pub fn process_path(&mut self, path: impl AsRef<Path>, recursive: bool) -> Result<usize> {
+        let path = path.as_ref();
+
+        if path.is_file() {
+            // Single file
+            self.process_file(path)
+        } else {
+            let files = source_files(path, self.format, recursive)?;
+            self.process_files(&files)
+        }
+    }
+
+    /// Process a directory recursively
+    /// Returns the number of cards processed
+    pub fn process_directory(&mut self, dir_path: impl AsRef<Path>) -> Result<usize> {
+        let dir_path = dir_path.as_ref();
+
+        if !dir_path.is_dir() {
+            return Err(anyhow::anyhow!("Path is not a directory: {:?}", dir_path));
+        }
+
+        let files = source_files(dir_path, self.format, true)?;
+        self.process_files(&files)
+    }
+
+    /// Process markdown files in order, after hashing them all in parallel
+    fn process_files(&mut self, files: &[PathBuf]) -> Result<usize> {
+        self.timed(Phase::Hash, |this| {
+            if let Some(cache) = &this.hash_cache {
+                this.prechecked = cache.changed_files(files);
+            }
+        });
+
+        let result = files
+            .iter()
+            .try_fold(0, |total, path| Ok(total + self.process_file(path)?));
+        // Don't let stale entries leak into later calls after an early error
+        self.prechecked.clear();
+        result
+    }
+}
+
+/// The file at `path`, or the source files of `format` in a directory
+/// (subdirectories only if `recursive`), sorted. Every command reading a vault
+/// lists its files with this
+pub fn source_files(path: &Path, format: SourceFormat, recursive: bool) -> Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if !path.is_dir() {
+        return Err(DomainError::PathNotFound(format!("{:?}", path)).into());
+    }
+    let max_depth = if recursive { usize::MAX } else { 1 };
+    let mut files = Vec::new();
+    for entry in walkdir::WalkDir::new(path)
+        .follow_links(false)
+        .max_depth(max_depth)
+    {
+        let entry = entry.with_context(|| format!("Failed to list {}", path.display()))?;
+        if entry.path().is_file()
+            && entry.path().extension().and_then(|s| s.to_str()) == Some(format.extension())
+        {
+            files.push(entry.into_path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Kind of note a card of a section makes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardKind {
+    /// Question with an answer (`>` lines or `Q:`/`A:`)
+    Basic,
+    /// Text with `{cloze}` deletions
+    Cloze,
+}
+
+/// Card of a markdown file with the deck, tags and text direction `collect`
+/// gives its note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceCard {
+    /// 1-based line of the file the card starts on
+    pub line: usize,
+    /// The card as written in the section
+    pub text: String,
+    /// `None` for an item with neither an answer nor a cloze deletion, which
+    /// makes no note
+    pub kind: Option<CardKind>,
+    pub deck: String,
+    pub tags: Vec<String>,
+    pub lang: Option<String>,
+    pub dir: Option<String>,
+}
+
+impl SourceCard {
+    /// Field markdown: front, back and `>>` groups for a basic notetype with
+    /// `basic_field_count` fields, or the cloze text
+    pub fn fields(&self, basic_field_count: usize) -> Result<Vec<String>> {
+        match self.kind {
+            Some(CardKind::Basic) => {
+                card_parser::parse_basic_card_field_list(&self.text, basic_field_count)
+            }
+            Some(CardKind::Cloze) => Ok(vec![card_parser::parse_cloze_card_field(&self.text)?]),
+            None => Err(anyhow::anyhow!(
+                "Card has no answer ('>' or 'A:') and no cloze deletion: {}",
+                self.first_line()
+            )),
+        }
+    }
+
+    /// First line of the card as written, to name it in messages
+    pub fn first_line(&self) -> &str {
+        self.text.lines().next().unwrap_or_default()
+    }
+}
+
+/// Reads the sections and cards of markdown the way `collect` does: ignored
+/// sections and cards are dropped, decks come from `Deck:` lines, the
+/// frontmatter and the deck overrides, and tags and text direction are merged
+/// the same way. Preview, vault statistics and template notes read cards with
+/// it, so they can't drift from what `collect` writes
+pub struct CardReader {
+    parser: SectionParser,
+    formats: Vec<CardFormat>,
+    deck: Option<String>,
+    deck_prefix: Option<String>,
+    extra_tags: Vec<String>,
+    lang: Option<String>,
+    dir: Option<String>,
+}
+
+impl CardReader {
+    pub fn new(section_start: &str, section_end: &str, formats: Vec<CardFormat>) -> Self {
+        Self {
+            parser: SectionParser::with_delimiters(section_start, section_end),
+            formats,
+            deck: None,
+            deck_prefix: None,
+            extra_tags: Vec::new(),
+            lang: None,
+            dir: None,
+        }
+    }
+
+    /// Reader with the section delimiters, card formats and default text
+    /// direction of `settings`
+    pub fn from_settings(settings: &Config) -> Self {
+        Self::new(
+            &settings.cards.section_start,
+            &settings.cards.section_end,
+            settings.cards.formats.clone(),
+        )
+        .with_text_direction(
+            settings.markdown.lang.clone(),
+            settings.markdown.dir.clone(),
+        )
+    }
+
+    /// Use `formats` instead of the configured card formats
+    pub fn with_formats(mut self, formats: Vec<CardFormat>) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    /// `lang`/`dir` for sections without `Lang:`/`Dir:` lines
+    pub fn with_text_direction(mut self, lang: Option<String>, dir: Option<String>) -> Self {
+        self.lang = lang;
+        self.dir = dir;
+        self
+    }
+
+    /// `--deck` and `--deck-prefix`, see [`CardReader::target_deck`]
+    pub fn with_deck_override(mut self, deck: Option<String>, prefix: Option<String>) -> Self {
+        self.deck = deck;
+        self.deck_prefix = prefix;
+        self
+    }
+
+    /// Tags added to every card, after its section's and frontmatter's
+    pub fn with_extra_tags(mut self, tags: Vec<String>) -> Self {
+        self.extra_tags = tags;
+        self
+    }
+
+    pub fn formats(&self) -> &[CardFormat] {
+        &self.formats
+    }
+
+    /// Whether `--deck` or `--deck-prefix` is set
+    pub fn overrides_deck(&self) -> bool {
+        self.deck.is_some() || self.deck_prefix.is_some()
+    }
+
+    /// Deck for a card whose section (or frontmatter, or org headline) names
+    /// `section_deck`, with the overrides applied; "Default" without any
+    pub fn target_deck(&self, section_deck: Option<String>) -> String {
+        target_deck(
+            section_deck,
+            self.deck.as_deref(),
+            self.deck_prefix.as_deref(),
+        )
+    }
+
+    /// `tags` followed by the frontmatter's and the extra tags, without duplicates
+    pub fn merge_tags(&self, mut tags: Vec<String>, frontmatter_tags: &[String]) -> Vec<String> {
+        for tag in frontmatter_tags.iter().chain(&self.extra_tags) {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        tags
+    }
+
+    /// Sections of `content` with the line they start on; sections marked
+    /// `<!--inka-ignore-->` are dropped and ignored cards cut out
+    pub fn sections(&self, content: &str) -> Vec<Span<String>> {
+        self.parser
+            .parse_spans(content)
+            .iter()
+            .filter_map(|section| {
+                section_parser::active_section(section.text, &self.formats).map(|text| Span {
+                    line: section.line,
+                    text,
+                })
+            })
+            .collect()
+    }
+
+    /// Cards of `sections` (see [`CardReader::sections`]) in file order, with
+    /// the file's `frontmatter` applied
+    pub fn section_cards(
+        &self,
+        sections: &[Span<String>],
+        frontmatter: &Frontmatter,
+    ) -> Vec<SourceCard> {
+        let mut cards = Vec::new();
+        for section in sections {
+            let deck = self.target_deck(
+                section_parser::extract_deck_name(&section.text)
+                    .or_else(|| frontmatter.deck.clone()),
+            );
+            let tags = self.merge_tags(
+                section_parser::extract_tags(&section.text),
+                &frontmatter.tags,
+            );
+            let lang = section_parser::extract_lang(&section.text).or_else(|| self.lang.clone());
+            let dir = section_parser::extract_dir(&section.text).or_else(|| self.dir.clone());
+
+            for note in section_parser::extract_note_spans_for(&section.text, &self.formats) {
+                let kind = if card_parser::is_basic_card(&note.text, &self.formats) {
+                    Some(CardKind::Basic)
+                } else if card_parser::is_cloze_card(&note.text) {
+                    Some(CardKind::Cloze)
+                } else {
+                    None
+                };
+                cards.push(SourceCard {
+                    line: section.line + note.line - 1,
+                    text: note.text,
+                    kind,
+                    deck: deck.clone(),
+                    tags: tags.clone(),
+                    lang: lang.clone(),
+                    dir: dir.clone(),
+                });
+            }
+        }
+        cards
+    }
+
+    /// Cards of the markdown `content`
+    pub fn cards(&self, content: &str) -> Vec<SourceCard> {
+        let frontmatter = frontmatter::parse_frontmatter(content).unwrap_or_default();
+        self.section_cards(&self.sections(content), &frontmatter)
+    }
+}
+
+/// Write a card's note ID into the markdown at the card starting at byte
+/// `note_pos`: the ID comment of a recreated note is replaced, a card without
+/// one gets it injected
+fn write_markdown_id(content: &str, note_pos: usize, old_id: Option<i64>, id: i64) -> String {
+    match old_id {
+        Some(_) => file_writer::replace_anki_id_at(content, note_pos, id),
+        None => file_writer::inject_anki_id_at(content, note_pos, id),
+    }
+}
+
+/// Characters escaped in paths put into a URI; `/` stays so paths remain readable
+const URI_PATH: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'&')
+    .add(b'+')
+    .add(b'<')
+    .add(b'=')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Fill a footer link template: `{abspath}` is the absolute path of the
+/// source file, `{relpath}` the path relative to `root` (the current
+/// directory if None; the absolute path if the file isn't below it) and
+/// `{filename}` its file name, all percent-encoded
+pub fn source_uri(template: &str, file_path: &Path, root: Option<&Path>) -> String {
+    let absolute = std::path::absolute(file_path).unwrap_or_else(|_| file_path.to_path_buf());
+    let root = root
+        .map(Path::to_path_buf)
+        .or_else(|| std::env::current_dir().ok())
+        .and_then(|root| std::path::absolute(root).ok());
+    let relative = root
+        .as_deref()
+        .and_then(|root| absolute.strip_prefix(root).ok())
+        .unwrap_or(&absolute);
+    let encode = |path: &Path| {
+        let path = path.to_string_lossy().replace('\\', "/");
+        utf8_percent_encode(&path, URI_PATH).to_string()
+    };
+    let filename = file_path.file_name().map(Path::new).unwrap_or(file_path);
+
+    template
+        .replace("{abspath}", &encode(&absolute))
+        .replace("{relpath}", &encode(relative))
+        .replace("{filename}", &encode(filename))
+}
+
+/// Tags to add to and remove from a note with tags `current` so it has the
+/// `wanted` tags and none of the tags an earlier sync applied that aren't
+/// wanted anymore. Without `namespace`, applied tags are recorded with a
+/// [`SYNCED_TAG_PREFIX`] tag each; with it, every tag in the namespace counts
+/// as applied. Anki tags ignore case.
+fn tag_changes(
+    current: &[String],
+    wanted: &[String],
+    namespace: Option<&str>,
+) -> (Vec<String>, Vec<String>) {
+    let has = |tags: &[String], tag: &str| tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+
+    let mut added = Vec::new();
+    for tag in wanted {
+        let record = match namespace {
+            Some(_) => None,
+            None => Some(format!("{}{}", SYNCED_TAG_PREFIX, tag)),
+        };
+        for tag in std::iter::once(tag.clone()).chain(record) {
+            if !has(current, &tag) && !has(&added, &tag) {
+                added.push(tag);
+            }
+        }
+    }
+    let mut removed = Vec::new();
+    for tag in current {
+        if let Some(namespace) = namespace {
+            if strip_prefix_ignore_case(tag, &format!("{}::", namespace)).is_some()
+                && !has(wanted, tag)
+            {
+                removed.push(tag.clone());
+            }
+            continue;
+        }
+        let Some(applied) = strip_prefix_ignore_case(tag, SYNCED_TAG_PREFIX) else {
+            continue;
+        };
+        if has(wanted, applied) {
+            continue;
+        }
+        removed.push(tag.clone());
+        removed.extend(
+            current
+                .iter()
+                .filter(|t| t.eq_ignore_ascii_case(applied))
+                .cloned(),
+        );
+    }
+    (added, removed)
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    text.get(..prefix.len())
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &text[prefix.len()..])
+}
+
+/// Anki deck names ignore case
+fn same_deck_name(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+/// Deck a section's new notes go to: `--deck` over the section's `Deck:`
+/// line (or the frontmatter's `deck:`) over "Default", with `--deck-prefix` prepended as a `::` namespace
+fn target_deck(section_deck: Option<String>, deck: Option<&str>, prefix: Option<&str>) -> String {
+    let deck = deck
+        .map(str::to_string)
+        .or(section_deck)
+        .unwrap_or_else(|| "Default".to_string());
+    match prefix.map(|p| p.trim_end_matches("::")) {
+        Some(prefix) if !prefix.is_empty() => format!("{}::{}", prefix, deck),
+        _ => deck,
+    }
+}
+
+impl Drop for CardCollector<'_> {
+    fn drop(&mut self) {
+        // Save hash cache if it exists
+        if self.check {
+            return;
+        }
+        if let Some(cache) = &mut self.hash_cache {
+            if let Err(e) = cache.save() {
+                // Use eprintln since we can't return Result from Drop
+                eprintln!("Warning: Failed to save hash cache: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anki::collection::CollectionBuilder;
+    use std::fs;
+    use tempfile::TempDir;
+
+    // Test helper that creates a temporary test collection
+    fn create_test_collection() -> (tempfile::TempDir, std::path::PathBuf, std::path::PathBuf) {
+        use std::path::PathBuf;
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let fixture_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/test_collection/User 1/collection.anki2");
+        let collection_path = temp_dir.path().join("collection.anki2");
+
+        std::fs::copy(&fixture_path, &collection_path).unwrap();
+
+        let media_dir = temp_dir.path().join("collection.media");
+        std::fs::create_dir_all(&media_dir).unwrap();
+
+        (temp_dir, collection_path, media_dir)
+    }
+
+    #[test]
+    fn given_markdown_with_basic_card_when_processing_then_creates_note() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+
+        let markdown_path = temp_dir.path().join("test.md");
+        let markdown_content = r#"---
+Deck: TestDeck
+
+1. What is Rust?
+> A systems programming language
+---"#;
+        fs::write(&markdown_path, markdown_content).unwrap();
+
+        let mut collector =
+            CardCollector::new(&collection_path, CollectorConfig::default()).unwrap();
+        let count = collector.process_file(&markdown_path).unwrap();
+
+        assert_eq!(count, 1);
+    }
+
+    /// Records event names for assertions; shared handle survives the move into the collector
+    #[derive(Clone, Default)]
+    struct RecordingSink(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+    impl EventSink for RecordingSink {
+        fn on_file_start(&mut self, _path: &Path) {
+            self.0.lock().unwrap().push("file_start".to_string());
+        }
+        fn on_file_done(&mut self, _path: &Path, cards: usize) {
+            self.0.lock().unwrap().push(format!("file_done:{}", cards));
+        }
+        fn on_card_created(&mut self, _path: &Path, _note_id: i64) {
+            self.0.lock().unwrap().push("card_created".to_string());
+        }
+        fn on_card_updated(&mut self, _path: &Path, _note_id: i64) {
+            self.0.lock().unwrap().push("card_updated".to_string());
+        }
+        fn on_error(&mut self, _path: &Path, _error: &anyhow::Error) {
+            self.0.lock().unwrap().push("error".to_string());
+        }
+    }
+
+    #[test]
+    fn given_event_sink_when_processing_twice_then_reports_created_then_updated() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+
+        let markdown_path = temp_dir.path().join("test.md");
+        fs::write(
+            &markdown_path,
+            "---\nDeck: TestDeck\n\n1. What is Rust?\n> A systems programming language\n---",
+        )
+        .unwrap();
+
+        let sink = RecordingSink::default();
+        let config = CollectorConfig {
+            full_sync: true,
+            ..Default::default()
+        };
+        let mut collector = CardCollector::new(&collection_path, config)
+            .unwrap()
+            .with_event_sink(sink.clone());
+        collector.process_file(&markdown_path).unwrap();
+        collector.process_file(&markdown_path).unwrap();
+
+        assert_eq!(
+            *sink.0.lock().unwrap(),
+            vec![
+                "file_start",
+                "card_created",
+                "file_done:1",
+                "file_start",
+                "card_updated",
+                "file_done:1"
+            ]
+        );
+    }
+
+    #[test]
+    fn given_event_sink_and_ignore_errors_when_media_missing_then_reports_error() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+
+        let markdown_path = temp_dir.path().join("test.md");
+        fs::write(
+            &markdown_path,
+            "---\nDeck: TestDeck\n\n1. Question?\n> ![img](missing.png)\n---",
+        )
+        .unwrap();
+
+        let sink = RecordingSink::default();
+        let config = CollectorConfig {
+            ignore_errors: true,
+            ..Default::default()
+        };
+        let mut collector = CardCollector::new(&collection_path, config)
+            .unwrap()
+            .with_event_sink(sink.clone());
+        collector.process_file(&markdown_path).unwrap();
+
+        assert_eq!(*sink.0.lock().unwrap(), vec!["file_start", "error"]);
+    }
+
+    #[test]
+    fn given_markdown_with_cloze_card_when_processing_then_creates_note() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+
+        let markdown_path = temp_dir.path().join("cloze.md");
+        let markdown_content = r#"---
+Deck: TestDeck
+
+1. Rust is a {systems programming} language.
+---"#;
+        fs::write(&markdown_path, markdown_content).unwrap();
+
+        let mut collector =
+            CardCollector::new(&collection_path, CollectorConfig::default()).unwrap();
+        let count = collector.process_file(&markdown_path).unwrap();
+
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn given_org_drill_card_when_processing_then_writes_note_id_property() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+
+        let org_path = temp_dir.path().join("drill.org");
+        let org_content = "#+ANKI_DECK: TestDeck\n\
+* Capital of France :drill:\n\
+What is the capital of France?\n\
+** Answer\n\
+Paris\n";
+        fs::write(&org_path, org_content).unwrap();
+
+        let config = CollectorConfig {
+            format: SourceFormat::Org,
+            ..Default::default()
+        };
+        let mut collector = CardCollector::new(&collection_path, config).unwrap();
+        let count = collector.process_file(&org_path).unwrap();
+
+        assert_eq!(count, 1);
+        let updated = fs::read_to_string(&org_path).unwrap();
+        assert!(updated.contains(":ANKI_NOTE_ID:"));
+    }
+
+    #[test]
+    fn given_markdown_with_multiple_cards_when_processing_then_creates_all() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+
+        let markdown_path = temp_dir.path().join("multi.md");
+        let markdown_content = r#"---
+Deck: TestDeck
+
+1. What is Rust?
+> A systems programming language
+
+2. What is Cargo?
+> Rust's package manager
+
+3. Rust was created by {Mozilla}.
+---"#;
+        fs::write(&markdown_path, markdown_content).unwrap();
+
+        let mut collector =
+            CardCollector::new(&collection_path, CollectorConfig::default()).unwrap();
+        let count = collector.process_file(&markdown_path).unwrap();
+
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn given_index_notes_when_processing_twice_then_keeps_one_index_note_linking_all_cards() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+        let markdown_path = temp_dir.path().join("indexed.md");
+        fs::write(
+            &markdown_path,
+            "---\nDeck: TestDeck\n\n1. What is Rust?\n> A language\n\n2. Rust was created by {Mozilla}.\n---\n",
+        )
+        .unwrap();
+        let config = CollectorConfig {
+            index_notes: true,
+            full_sync: true,
+            ..Default::default()
+        };
+        let mut collector = CardCollector::new(&collection_path, config).unwrap();
+
+        collector.process_file(&markdown_path).unwrap();
+        collector.process_file(&markdown_path).unwrap();
+
+        let index_ids = collector
+            .repository
+            .note_ids(Some(&format!("tag:{}", INDEX_TAG)))
+            .unwrap();
+        assert_eq!(index_ids.len(), 1);
+        let (fields, _) = collector
+            .repository
+            .note_content(index_ids[0])
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            fields[0],
+            format!("Index: {}", markdown_path.canonicalize().unwrap().display())
+        );
+        let content = fs::read_to_string(&markdown_path).unwrap();
+        let ids: Vec<i64> = content
+            .lines()
+            .filter_map(card_parser::extract_anki_id)
+            .collect();
+        assert_eq!(ids.len(), 2);
+        for id in ids {
+            assert!(fields[1].contains(&format!("href=\"nid:{}\"", id)));
+        }
+        assert!(fields[1].contains(">What is Rust?</a>"));
+    }
+
+    #[test]
+    fn given_org_file_below_source_root_when_indexing_then_keys_index_by_relative_path() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+        fs::create_dir(temp_dir.path().join("notes")).unwrap();
+        let org_path = temp_dir.path().join("notes").join("drill.org");
+        fs::write(
+            &org_path,
+            "#+ANKI_DECK: TestDeck\n* Capital of France :drill:\nWhat is the capital?\n** Answer\nParis\n",
+        )
+        .unwrap();
+        let config = CollectorConfig {
+            format: SourceFormat::Org,
+            index_notes: true,
+            source_root: Some(temp_dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let mut collector = CardCollector::new(&collection_path, config).unwrap();
+
+        collector.process_file(&org_path).unwrap();
+
+        let index_ids = collector
+            .repository
+            .note_ids(Some(&format!("tag:{}", INDEX_TAG)))
+            .unwrap();
+        assert_eq!(index_ids.len(), 1);
+        let (fields, _) = collector
+            .repository
+            .note_content(index_ids[0])
+            .unwrap()
+            .unwrap();
+        assert_eq!(fields[0], "Index: notes/drill.org");
+        let id = org_parser::parse_org_cards(&fs::read_to_string(&org_path).unwrap())[0]
+            .id
+            .unwrap();
+        assert!(fields[1].contains(&format!("href=\"nid:{}\"", id)));
+    }
+
+    #[test]
+    fn given_cards_in_several_decks_when_processing_then_each_note_lands_in_its_deck() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+
+        let first_path = temp_dir.path().join("first.md");
+        fs::write(
+            &first_path,
+            "---\nDeck: DeckA\n\n1. Question A1?\n> Answer A1\n\n2. A {cloze} in A.\n---",
+        )
+        .unwrap();
+        let second_path = temp_dir.path().join("second.md");
+        fs::write(
+            &second_path,
+            "---\nDeck: DeckB\n\n1. Question B1?\n> Answer B1\n---\n\n---\nDeck: DeckA\n\n1. Question A2?\n> Answer A2\n---",
+        )
+        .unwrap();
+
+        {
+            let mut collector =
+                CardCollector::new(&collection_path, CollectorConfig::default()).unwrap();
+            assert_eq!(collector.process_file(&first_path).unwrap(), 2);
+            assert_eq!(collector.process_file(&second_path).unwrap(), 2);
+        }
+
+        let mut repository = AnkiRepository::new(&collection_path).unwrap();
+        let deck_a = repository.list_notes(Some("deck:DeckA")).unwrap();
+        let deck_b = repository.list_notes(Some("deck:DeckB")).unwrap();
+        assert_eq!(deck_a.len(), 3);
+        assert_eq!(deck_b.len(), 1);
+        assert!(deck_b[0].front.contains("Question B1?"));
+    }
+
+    #[test]
+    fn given_deck_override_and_prefix_when_resolving_then_combines_them() {
+        assert_eq!(target_deck(Some("Rust".into()), None, None), "Rust");
+        assert_eq!(target_deck(None, None, None), "Default");
+        assert_eq!(
+            target_deck(Some("Rust".into()), Some("Scratch"), None),
+            "Scratch"
+        );
+        assert_eq!(
+            target_deck(Some("Rust".into()), None, Some("Imported::")),
+            "Imported::Rust"
+        );
+        assert_eq!(
+            target_deck(None, Some("Scratch"), Some("Imported")),
+            "Imported::Scratch"
+        );
+    }
+
+    #[test]
+    fn given_frontmatter_and_overrides_when_reading_cards_then_resolves_them_like_collect() {
+        let content = "---\ndeck: Notes\ntags: [vault]\n---\n\n---\nTags: rust\n\n1. Question?\n> Answer\n2. Not a card\n---\n";
+        let reader = CardReader::new("---", "---", CardFormat::DEFAULT.to_vec())
+            .with_deck_override(None, Some("Imported".to_string()))
+            .with_extra_tags(vec!["new".to_string()]);
+
+        let cards = reader.cards(content);
+
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[0].line, 9);
+        assert_eq!(cards[0].kind, Some(CardKind::Basic));
+        assert_eq!(cards[0].deck, "Imported::Notes");
+        assert_eq!(cards[0].tags, vec!["rust", "vault", "new"]);
+        assert_eq!(cards[0].fields(2).unwrap(), vec!["Question?", "Answer"]);
+        assert_eq!(cards[1].kind, None);
+        assert!(cards[1].fields(2).is_err());
+    }
+
+    #[test]
+    fn given_deck_override_when_processing_then_ignores_deck_lines() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+        let markdown_path = temp_dir.path().join("cards.md");
+        fs::write(
+            &markdown_path,
+            "---\nDeck: DeckA\n\n1. Question A1?\n> Answer A1\n---\n\n---\nDeck: DeckB\n\n1. Question B1?\n> Answer B1\n---",
+        )
+        .unwrap();
+
+        {
+            let config = CollectorConfig {
+                deck: Some("Scratch".to_string()),
+                deck_prefix: Some("Trial::".to_string()),
+                ..Default::default()
+            };
+            let mut collector = CardCollector::new(&collection_path, config).unwrap();
+            assert_eq!(collector.process_file(&markdown_path).unwrap(), 2);
+        }
+
+        let mut repository = AnkiRepository::new(&collection_path).unwrap();
+        let scratch = repository
+            .list_notes(Some("\"deck:Trial::Scratch\""))
+            .unwrap();
+        assert_eq!(scratch.len(), 2);
+        assert!(repository
+            .list_notes(Some("deck:DeckA"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn given_deck_override_when_updating_existing_note_then_keeps_its_deck_and_warns() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+        let markdown_path = temp_dir.path().join("cards.md");
+        fs::write(
+            &markdown_path,
+            "---\nDeck: DeckA\n\n1. Question A1?\n> Answer A1\n---",
+        )
+        .unwrap();
+        CardCollector::new(&collection_path, CollectorConfig::default())
+            .unwrap()
+            .process_file(&markdown_path)
+            .unwrap();
+
+        let config = CollectorConfig {
+            deck: Some("Scratch".to_string()),
+            force: true,
+            ..Default::default()
+        };
+        let mut collector = CardCollector::new(&collection_path, config).unwrap();
+        collector.process_file(&markdown_path).unwrap();
+
+        assert_eq!(collector.warnings().len(), 1);
+        assert!(collector.warnings()[0].contains("stays in 'DeckA'"));
+        assert_eq!(
+            collector
+                .repository
+                .list_notes(Some("deck:DeckA"))
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn given_extra_tags_when_processing_then_merges_them_with_section_tags() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+        let markdown_path = temp_dir.path().join("cards.md");
+        fs::write(
+            &markdown_path,
+            "---\nDeck: Default\nTags: rust vault\n\n1. Question?\n> Answer\n---",
+        )
+        .unwrap();
+
+        {
+            let config = CollectorConfig {
+                extra_tags: vec!["source::vault".to_string(), "vault".to_string()],
+                ..Default::default()
+            };
+            let mut collector = CardCollector::new(&collection_path, config).unwrap();
+            collector.process_file(&markdown_path).unwrap();
+        }
+
+        let mut repository = AnkiRepository::new(&collection_path).unwrap();
+        let notes = repository.list_notes(Some("tag:source::vault")).unwrap();
+        assert_eq!(notes.len(), 1);
+        let mut tags = notes[0].tags.clone();
+        tags.sort();
+        assert_eq!(tags, vec!["rust", "source::vault", "vault"]);
+    }
+
+    #[test]
+    fn given_strict_mode_when_file_has_warning_then_errors_without_touching_file() {
+        let (temp_dir, collection_path, media_dir) = create_test_collection();
+
+        let image_path = temp_dir.path().join("diagram.png");
+        fs::write(&image_path, b"fake png").unwrap();
+        let markdown_path = temp_dir.path().join("strict.md");
+        let markdown_content = "---\nDeck: TestDeck\n\n1. What is shown?\n> ![](diagram.png)\n---";
+        fs::write(&markdown_path, markdown_content).unwrap();
+
+        let mut collector = CardCollector::new(
+            &collection_path,
+            CollectorConfig {
+                strict: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let err = collector.process_file(&markdown_path).unwrap_err();
+
+        assert!(format!("{:#}", err).contains("has no alt text"));
+        assert_eq!(
+            fs::read_to_string(&markdown_path).unwrap(),
+            markdown_content
+        );
+        assert!(!media_dir.join("diagram.png").exists());
+        assert_eq!(collector.warnings().len(), 1);
+    }
+
+    #[test]
+    fn given_image_alt_rule_off_when_processing_strictly_then_accepts_image_without_alt() {
+        let (temp_dir, collection_path, media_dir) = create_test_collection();
+        fs::write(temp_dir.path().join("diagram.png"), b"fake png").unwrap();
+        let markdown_path = temp_dir.path().join("strict.md");
+        fs::write(
+            &markdown_path,
+            "---\nDeck: TestDeck\n\n1. What is shown?\n> ![](diagram.png)\n---",
+        )
+        .unwrap();
+
+        let mut collector = CardCollector::new(
+            &collection_path,
+            CollectorConfig {
+                strict: true,
+                image_alt: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(collector.process_file(&markdown_path).unwrap(), 1);
+        assert!(collector.warnings().is_empty());
+        assert!(media_dir.join("diagram.png").exists());
+    }
+
+    #[test]
+    fn given_directory_when_listing_source_files_then_returns_sorted_files_of_format() {
+        let temp_dir = TempDir::new().unwrap();
+        let sub_dir = temp_dir.path().join("sub");
+        fs::create_dir(&sub_dir).unwrap();
+        for file in ["b.md", "a.md", "c.org", "sub/d.md"] {
+            fs::write(temp_dir.path().join(file), "").unwrap();
+        }
+        let missing = temp_dir.path().join("missing");
+
+        let flat = source_files(temp_dir.path(), SourceFormat::Markdown, false).unwrap();
+        let all = source_files(temp_dir.path(), SourceFormat::Markdown, true).unwrap();
+        let org = source_files(temp_dir.path(), SourceFormat::Org, true).unwrap();
+
+        assert_eq!(
+            flat,
+            vec![temp_dir.path().join("a.md"), temp_dir.path().join("b.md")]
+        );
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[2], sub_dir.join("d.md"));
+        assert_eq!(org, vec![temp_dir.path().join("c.org")]);
+        assert!(source_files(&missing, SourceFormat::Markdown, true).is_err());
+    }
+
+    #[test]
+    fn given_check_mode_when_processing_then_reports_pending_changes_without_writing() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+
+        let markdown_path = temp_dir.path().join("check.md");
+        let markdown_content = "---\nDeck: TestDeck\n\n1. What is Rust?\n> A language\n---";
+        fs::write(&markdown_path, markdown_content).unwrap();
+
+        let check = CollectorConfig {
+            check: true,
+            ..Default::default()
+        };
+        {
+            let mut collector = CardCollector::new(&collection_path, check.clone()).unwrap();
+            assert_eq!(collector.process_file(&markdown_path).unwrap(), 1);
+            assert_eq!(collector.pending_changes().len(), 1);
+            assert!(collector.pending_changes()[0].ends_with("a new note would be created"));
+        }
+        assert_eq!(
+            fs::read_to_string(&markdown_path).unwrap(),
+            markdown_content
+        );
+
+        // After a real collect, the same file is in sync
+        {
+            let mut collector =
+                CardCollector::new(&collection_path, CollectorConfig::default()).unwrap();
+            collector.process_file(&markdown_path).unwrap();
+        }
+        let mut collector = CardCollector::new(
+            &collection_path,
+            CollectorConfig {
+                full_sync: true,
+                ..check
+            },
+        )
+        .unwrap();
+        collector.process_file(&markdown_path).unwrap();
+        assert!(collector.pending_changes().is_empty());
+    }
+
+    #[test]
+    fn given_markdown_with_id_when_processing_second_time_then_updates_note() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
 
-            // Copy image to media directory
-            match media_handler::copy_media_to_anki(
-                &absolute_image_path,
-                &self.media_dir,
-                self.force,
-            ) {
-                Ok(filename) => {
-                    debug!("Copied media file: {} -> {}", image_path, filename);
-                    path_mapping.insert(image_path.clone(), filename);
-                }
-                Err(e) => {
-                    return Err(e)
-                        .with_context(|| format!("Failed to copy media file '{}'", image_path));
-                }
-            }
-        }
+        let markdown_path = temp_dir.path().join("update.md");
+        let markdown_content = r#"---
+Deck: TestDeck
 
-        // Convert sections to owned Strings to avoid borrowing issues when mutating content
-        let sections: Vec<String> = sections.iter().map(|s| s.to_string()).collect();
+1. What is Rust?
+> A systems programming language
+---"#;
+        fs::write(&markdown_path, markdown_content).unwrap();
 
-        let mut card_count = 0;
+        let mut collector =
+            CardCollector::new(&collection_path, CollectorConfig::default()).unwrap();
 
-        for section in &sections {
-            // Extract metadata
-            let deck_name =
-                section_parser::extract_deck_name(section).unwrap_or_else(|| "Default".to_string());
-            let tags = section_parser::extract_tags(section);
-
-            // Extract note strings
-            let note_strings = section_parser::extract_note_strings(section);
-
-            for note_str in note_strings {
-                // Extract existing ID if present
-                let existing_id = card_parser::extract_anki_id(&note_str);
-
-                // Determine card type and process
-                if card_parser::is_basic_card(&note_str) {
-                    // Parse basic card fields
-                    let (front_md, back_md) = card_parser::parse_basic_card_fields(&note_str)
-                        .context("Failed to parse basic card fields")?;
-
-                    // Convert to HTML
-                    let mut front_html = converter::markdown_to_html(&front_md);
-                    let mut back_html = converter::markdown_to_html(&back_md);
-
-                    // Update media paths in HTML
-                    front_html =
-                        media_handler::update_media_paths_in_html(&front_html, &path_mapping);
-                    back_html =
-                        media_handler::update_media_paths_in_html(&back_html, &path_mapping);
-
-                    // Add file path footer to back field
-                    back_html = self.add_file_path_footer(&back_html, markdown_path);
-
-                    // Process basic card
-                    let (updated_content, _id) = self.process_card(
-                        &note_str,
-                        existing_id,
-                        vec![front_html, back_html],
-                        &deck_name,
-                        &tags,
-                        content,
-                        false,
-                    )?;
-                    content = updated_content;
-                    card_count += 1;
-                } else if card_parser::is_cloze_card(&note_str) {
-                    // Parse cloze card
-                    let text_md = card_parser::parse_cloze_card_field(&note_str)
-                        .context("Failed to parse cloze card field")?;
+        // First run creates note
+        let count1 = collector.process_file(&markdown_path).unwrap();
+        assert_eq!(count1, 1);
 
-                    // Transform cloze syntax
-                    let text_transformed = crate::inka::infrastructure::markdown::cloze_converter::convert_cloze_syntax(&text_md);
+        // Markdown should now have ID
+        let updated_content = fs::read_to_string(&markdown_path).unwrap();
+        assert!(updated_content.contains("<!--ID:"));
 
-                    // Convert to HTML
-                    let mut text_html = converter::markdown_to_html(&text_transformed);
+        // Modify the answer
+        let modified = updated_content.replace(
+            "A systems programming language",
+            "A safe systems programming language",
+        );
+        fs::write(&markdown_path, &modified).unwrap();
 
-                    // Update media paths in HTML
-                    text_html =
-                        media_handler::update_media_paths_in_html(&text_html, &path_mapping);
+        // Second run updates note
+        let count2 = collector.process_file(&markdown_path).unwrap();
+        assert_eq!(count2, 1);
+    }
 
-                    // Add file path footer to text field
-                    text_html = self.add_file_path_footer(&text_html, markdown_path);
+    #[test]
+    fn given_changed_deck_line_when_moving_on_deck_change_then_moves_cards() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+        let markdown_path = temp_dir.path().join("move.md");
+        fs::write(
+            &markdown_path,
+            "---\nDeck: OldDeck\n\n1. What is Rust?\n> A language\n---",
+        )
+        .unwrap();
+        CardCollector::new(&collection_path, CollectorConfig::default())
+            .unwrap()
+            .process_file(&markdown_path)
+            .unwrap();
+        let content = fs::read_to_string(&markdown_path).unwrap();
+        fs::write(&markdown_path, content.replace("OldDeck", "NewDeck")).unwrap();
 
-                    // Process cloze card
-                    let (updated_content, _id) = self.process_card(
-                        &note_str,
-                        existing_id,
-                        vec![text_html],
-                        &deck_name,
-                        &tags,
-                        content,
-                        true,
-                    )?;
-                    content = updated_content;
-                    card_count += 1;
-                }
-            }
-        }
+        let config = CollectorConfig {
+            full_sync: true,
+            move_on_deck_change: true,
+            ..Default::default()
+        };
+        let deck_moves = {
+            let mut collector = CardCollector::new(&collection_path, config).unwrap();
+            collector.process_file(&markdown_path).unwrap();
+            collector.deck_moves().to_vec()
+        };
 
-        // Write updated content back to file if IDs were injected
-        file_writer::write_markdown_file(markdown_path, &content)
-            .with_context(|| format!("Failed to write markdown file: {}", markdown_path.display()))?;
+        assert_eq!(deck_moves.len(), 1);
+        assert!(deck_moves[0].ends_with("moved from 'OldDeck' to 'NewDeck'"));
+        let mut repository = AnkiRepository::new(&collection_path).unwrap();
+        assert_eq!(
+            repository.list_notes(Some("deck:NewDeck")).unwrap().len(),
+            1
+        );
+        assert!(repository
+            .list_notes(Some("deck:OldDeck"))
+            .unwrap()
+            .is_empty());
+    }
 
-        // After successful processing, update hash cache
-        if let Some(cache) = &mut self.hash_cache {
-            cache
-                .update_hash(markdown_path)
-                .context("Failed to update file hash")?;
-        }
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
 
-        Ok(card_count)
+    #[test]
+    fn given_synced_tags_when_computing_changes_then_removes_only_synced_ones() {
+        let current = tags(&[
+            "rust",
+            "ankiview::synced::rust",
+            "Old",
+            "ankiview::synced::old",
+            "manual",
+        ]);
+
+        let (added, removed) = tag_changes(&current, &tags(&["rust", "new"]), None);
+
+        assert_eq!(added, tags(&["new", "ankiview::synced::new"]));
+        assert_eq!(removed, tags(&["ankiview::synced::old", "Old"]));
     }
 
-    /// Process a directory recursively
-    /// Returns the number of cards processed
-    pub fn process_directory(&mut self, dir_path: impl AsRef<Path>) -> Result<usize> {
-        let dir_path = dir_path.as_ref();
+    #[test]
+    fn given_unchanged_tags_when_computing_changes_then_changes_nothing() {
+        let current = tags(&["Rust", "ankiview::synced::rust"]);
 
-        if !dir_path.is_dir() {
-            return Err(anyhow::anyhow!("Path is not a directory: {:?}", dir_path));
-        }
+        let (added, removed) = tag_changes(&current, &tags(&["rust"]), None);
 
-        let mut total_count = 0;
+        assert!(added.is_empty());
+        assert!(removed.is_empty());
+    }
 
-        // Walk directory recursively
-        for entry in walkdir::WalkDir::new(dir_path)
-            .follow_links(false)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
+    #[test]
+    fn given_tag_namespace_when_computing_changes_then_manages_only_tags_in_it() {
+        let current = tags(&["inka::rust", "inka::old", "old", "manual"]);
 
-            // Only process markdown files
-            if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("md") {
-                total_count += self.process_file(path)?;
-            }
-        }
+        let (added, removed) =
+            tag_changes(&current, &tags(&["inka::rust", "inka::new"]), Some("inka"));
 
-        Ok(total_count)
+        assert_eq!(added, tags(&["inka::new"]));
+        assert_eq!(removed, tags(&["inka::old"]));
     }
-}
 
-impl Drop for CardCollector {
-    fn drop(&mut self) {
-        // Save hash cache if it exists
-        if let Some(cache) = &self.hash_cache {
-            if let Err(e) = cache.save() {
-                // Use eprintln since we can't return Result from Drop
-                eprintln!("Warning: Failed to save hash cache: {}", e);
-            }
-        }
-    }
-}
+    #[test]
+    fn given_tag_namespace_when_collecting_then_prefixes_tags_once() {
+        let (_temp_dir, collection_path, _media_dir) = create_test_collection();
+        let config = CollectorConfig {
+            tag_namespace: Some("inka::".to_string()),
+            ..Default::default()
+        };
+        let collector = CardCollector::new(&collection_path, config).unwrap();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use anki::collection::CollectionBuilder;
-    use std::fs;
-    use tempfile::TempDir;
+        assert_eq!(
+            collector.namespaced_tags(&tags(&["rust", "Inka::vault"])),
+            tags(&["inka::rust", "Inka::vault"])
+        );
+    }
 
-    // Test helper that creates a temporary test collection
-    fn create_test_collection() -> (tempfile::TempDir, std::path::PathBuf, std::path::PathBuf) {
-        use std::path::PathBuf;
-        let temp_dir = tempfile::tempdir().unwrap();
+    #[test]
+    fn given_sync_tags_when_tag_removed_from_markdown_then_removes_it_from_note() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+        let markdown_path = temp_dir.path().join("tags.md");
+        fs::write(
+            &markdown_path,
+            "---\nDeck: Default\nTags: rust old\n\n1. Question?\n> Answer\n---",
+        )
+        .unwrap();
+        let config = CollectorConfig {
+            full_sync: true,
+            sync_tags: true,
+            ..Default::default()
+        };
+        CardCollector::new(&collection_path, config.clone())
+            .unwrap()
+            .process_file(&markdown_path)
+            .unwrap();
+        let mut repository = AnkiRepository::new(&collection_path).unwrap();
+        let id = repository.list_notes(Some("tag:rust")).unwrap()[0].id;
+        repository.add_tags(id, &tags(&["manual"])).unwrap();
+        drop(repository);
+
+        let content = fs::read_to_string(&markdown_path).unwrap();
+        fs::write(&markdown_path, content.replace("rust old", "rust")).unwrap();
+        CardCollector::new(&collection_path, config)
+            .unwrap()
+            .process_file(&markdown_path)
+            .unwrap();
 
-        let fixture_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .join("tests/fixtures/test_collection/User 1/collection.anki2");
-        let collection_path = temp_dir.path().join("collection.anki2");
+        let repository = AnkiRepository::new(&collection_path).unwrap();
+        let (_fields, mut note_tags) = repository.note_content(id).unwrap().unwrap();
+        note_tags.sort();
+        assert_eq!(
+            note_tags,
+            tags(&["ankiview::synced::rust", "manual", "rust"])
+        );
+    }
 
-        std::fs::copy(&fixture_path, &collection_path).unwrap();
+    #[test]
+    fn given_history_enabled_when_creating_then_updating_then_journals_both() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+        let markdown_path = temp_dir.path().join("history.md");
+        fs::write(&markdown_path, "---\n1. What is Rust?\n> A language\n---\n").unwrap();
+        let config = CollectorConfig {
+            history: true,
+            full_sync: true,
+            ..Default::default()
+        };
+        let mut collector = CardCollector::new(&collection_path, config).unwrap();
 
-        let media_dir = temp_dir.path().join("collection.media");
-        std::fs::create_dir_all(&media_dir).unwrap();
+        collector.process_file(&markdown_path).unwrap();
+        let content = fs::read_to_string(&markdown_path).unwrap();
+        fs::write(
+            &markdown_path,
+            content.replace("A language", "A safe language"),
+        )
+        .unwrap();
+        collector.process_file(&markdown_path).unwrap();
 
-        (temp_dir, collection_path, media_dir)
+        let note_id =
+            card_parser::extract_anki_id(&fs::read_to_string(&markdown_path).unwrap()).unwrap();
+        let entries = History::for_collection(&collection_path, &CacheLocation::default())
+            .entries_for(note_id)
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].action, HistoryAction::Created);
+        assert_eq!(entries[0].before, None);
+        assert_eq!(entries[1].action, HistoryAction::Updated);
+        assert_eq!(entries[1].before, entries[0].after);
+        assert_ne!(entries[1].after, entries[1].before);
+        assert_eq!(
+            entries[1].file.as_deref(),
+            Some(markdown_path.display().to_string().as_str())
+        );
     }
 
     #[test]
-    fn given_markdown_with_basic_card_when_processing_then_creates_note() {
+    fn given_skipping_resolver_when_note_id_is_orphaned_then_keeps_id_in_markdown() {
+        struct SkipAll;
+        impl ConflictResolver for SkipAll {
+            fn resolve(&mut self, _conflict: &Conflict) -> Resolution {
+                Resolution::Skip
+            }
+        }
         let (temp_dir, collection_path, _media_dir) = create_test_collection();
 
-        let markdown_path = temp_dir.path().join("test.md");
+        let markdown_path = temp_dir.path().join("orphan.md");
         let markdown_content = r#"---
 Deck: TestDeck
 
+<!--ID:1234567890-->
 1. What is Rust?
 > A systems programming language
 ---"#;
         fs::write(&markdown_path, markdown_content).unwrap();
 
-        let mut collector =
-            CardCollector::new(&collection_path, CollectorConfig::default()).unwrap();
-        let count = collector.process_file(&markdown_path).unwrap();
+        let mut collector = CardCollector::new(&collection_path, CollectorConfig::default())
+            .unwrap()
+            .with_conflict_resolver(SkipAll);
+        collector.process_file(&markdown_path).unwrap();
 
-        assert_eq!(count, 1);
+        assert!(collector.created_ids.is_empty());
+        let updated_content = fs::read_to_string(&markdown_path).unwrap();
+        assert!(updated_content.contains("<!--ID:1234567890-->"));
     }
 
     #[test]
-    fn given_markdown_with_cloze_card_when_processing_then_creates_note() {
+    fn given_numbered_item_without_answer_when_processing_then_warns_with_line() {
         let (temp_dir, collection_path, _media_dir) = create_test_collection();
-
-        let markdown_path = temp_dir.path().join("cloze.md");
-        let markdown_content = r#"---
-Deck: TestDeck
-
-1. Rust is a {systems programming} language.
----"#;
-        fs::write(&markdown_path, markdown_content).unwrap();
+        let markdown_path = temp_dir.path().join("malformed.md");
+        fs::write(
+            &markdown_path,
+            "# Notes\n\n---\nDeck: TestDeck\n\n1. Complete?\n> Yes\n\n2. Missing answer\n---",
+        )
+        .unwrap();
 
         let mut collector =
             CardCollector::new(&collection_path, CollectorConfig::default()).unwrap();
         let count = collector.process_file(&markdown_path).unwrap();
 
         assert_eq!(count, 1);
+        assert_eq!(
+            collector.malformed_cards(),
+            [format!("{}:9: 2. Missing answer", markdown_path.display())]
+        );
+        assert!(collector.warnings()[0].contains(":9: '2. Missing answer' produced no card"));
     }
 
     #[test]
-    fn given_markdown_with_multiple_cards_when_processing_then_creates_all() {
+    fn given_same_deck_when_content_matches_note_in_other_deck_then_creates_new_note() {
         let (temp_dir, collection_path, _media_dir) = create_test_collection();
+        let card = "1. What is Rust?\n> A systems programming language";
+        let original = temp_dir.path().join("original.md");
+        fs::write(&original, format!("---\nDeck: DeckA\n\n{}\n---", card)).unwrap();
+        CardCollector::new(&collection_path, CollectorConfig::default())
+            .unwrap()
+            .process_file(&original)
+            .unwrap();
 
-        let markdown_path = temp_dir.path().join("multi.md");
-        let markdown_content = r#"---
-Deck: TestDeck
-
-1. What is Rust?
-> A systems programming language
-
-2. What is Cargo?
-> Rust's package manager
-
-3. Rust was created by {Mozilla}.
----"#;
-        fs::write(&markdown_path, markdown_content).unwrap();
+        // Same file, so the footer and thus the HTML match; ID lost, deck changed
+        fs::write(&original, format!("---\nDeck: DeckB\n\n{}\n---", card)).unwrap();
+        let config = CollectorConfig {
+            full_sync: true,
+            update_ids: true,
+            same_deck: true,
+            ..Default::default()
+        };
+        let mut collector = CardCollector::new(&collection_path, config).unwrap();
+        collector.process_file(&original).unwrap();
 
-        let mut collector =
-            CardCollector::new(&collection_path, CollectorConfig::default()).unwrap();
-        let count = collector.process_file(&markdown_path).unwrap();
+        assert_eq!(collector.created_ids.len(), 1);
+        assert_eq!(collector.warnings().len(), 1);
+        assert!(
+            collector.warnings()[0]
+                .contains("(deck 'DeckA', notetype 'Inka Basic') outside deck 'DeckB'; not linked"),
+            "{}",
+            collector.warnings()[0]
+        );
+    }
 
-        assert_eq!(count, 3);
+    /// Collects `content` from a fresh file with `on_duplicate`; returns the
+    /// collector's created IDs, its warnings and the rewritten file
+    fn collect_duplicate(
+        collection_path: &Path,
+        dir: &Path,
+        content: &str,
+        on_duplicate: OnDuplicate,
+    ) -> (Vec<i64>, Vec<String>, String) {
+        let markdown_path = dir.join("copy.md");
+        fs::write(&markdown_path, content).unwrap();
+        let config = CollectorConfig {
+            full_sync: true,
+            on_duplicate: Some(on_duplicate),
+            ..Default::default()
+        };
+        let mut collector = CardCollector::new(collection_path, config).unwrap();
+        collector.process_file(&markdown_path).unwrap();
+        (
+            collector.created_ids.clone(),
+            collector.warnings().to_vec(),
+            fs::read_to_string(&markdown_path).unwrap(),
+        )
     }
 
     #[test]
-    fn given_markdown_with_id_when_processing_second_time_then_updates_note() {
+    fn given_card_duplicating_note_in_deck_when_skipping_then_creates_nothing() {
         let (temp_dir, collection_path, _media_dir) = create_test_collection();
+        let original = temp_dir.path().join("original.md");
+        fs::write(
+            &original,
+            "---\nDeck: TestDeck\n\n1. What is <b>Rust</b>?\n> A language\n---",
+        )
+        .unwrap();
+        CardCollector::new(&collection_path, CollectorConfig::default())
+            .unwrap()
+            .process_file(&original)
+            .unwrap();
 
-        let markdown_path = temp_dir.path().join("update.md");
-        let markdown_content = r#"---
-Deck: TestDeck
+        let (created, warnings, content) = collect_duplicate(
+            &collection_path,
+            temp_dir.path(),
+            "---\nDeck: TestDeck\n\n1. What is Rust?\n> A systems language\n---",
+            OnDuplicate::Skip,
+        );
 
-1. What is Rust?
-> A systems programming language
----"#;
-        fs::write(&markdown_path, markdown_content).unwrap();
+        assert!(created.is_empty());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("skipped the card"), "{}", warnings[0]);
+        assert!(!content.contains("<!--ID:"));
+    }
 
-        let mut collector =
-            CardCollector::new(&collection_path, CollectorConfig::default()).unwrap();
+    #[test]
+    fn given_cloze_duplicating_note_from_other_file_when_skipping_then_creates_nothing() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+        let original = temp_dir.path().join("original.md");
+        fs::write(
+            &original,
+            "---\nDeck: TestDeck\n\n1. Rust is {memory safe}.\n---",
+        )
+        .unwrap();
+        CardCollector::new(&collection_path, CollectorConfig::default())
+            .unwrap()
+            .process_file(&original)
+            .unwrap();
 
-        // First run creates note
-        let count1 = collector.process_file(&markdown_path).unwrap();
-        assert_eq!(count1, 1);
+        // The footers name different files
+        let (created, warnings, _content) = collect_duplicate(
+            &collection_path,
+            temp_dir.path(),
+            "---\nDeck: TestDeck\n\n1. Rust is {memory safe}.\n---",
+            OnDuplicate::Skip,
+        );
 
-        // Markdown should now have ID
-        let updated_content = fs::read_to_string(&markdown_path).unwrap();
-        assert!(updated_content.contains("<!--ID:"));
+        assert!(created.is_empty());
+        assert_eq!(warnings.len(), 1);
+    }
 
-        // Modify the answer
-        let modified = updated_content.replace(
-            "A systems programming language",
-            "A safe systems programming language",
+    #[test]
+    fn given_card_duplicating_note_in_deck_when_updating_then_writes_its_id() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+        let original = temp_dir.path().join("original.md");
+        fs::write(
+            &original,
+            "---\nDeck: TestDeck\n\n1. What is Rust?\n> A language\n---",
+        )
+        .unwrap();
+        CardCollector::new(&collection_path, CollectorConfig::default())
+            .unwrap()
+            .process_file(&original)
+            .unwrap();
+        let id = fs::read_to_string(&original)
+            .unwrap()
+            .split("<!--ID:")
+            .nth(1)
+            .and_then(|rest| rest.split("-->").next())
+            .unwrap()
+            .to_string();
+
+        let (created, _warnings, content) = collect_duplicate(
+            &collection_path,
+            temp_dir.path(),
+            "---\nDeck: TestDeck\n\n1. What is Rust?\n> A systems language\n---",
+            OnDuplicate::Update,
         );
-        fs::write(&markdown_path, &modified).unwrap();
 
-        // Second run updates note
-        let count2 = collector.process_file(&markdown_path).unwrap();
-        assert_eq!(count2, 1);
+        assert!(created.is_empty());
+        assert!(content.contains(&format!("<!--ID:{}-->", id)));
+        let repository = AnkiRepository::new(&collection_path).unwrap();
+        let (fields, _tags) = repository
+            .note_content(id.parse().unwrap())
+            .unwrap()
+            .unwrap();
+        assert!(fields[1].contains("A systems language"));
     }
 
     #[test]
@@ -759,6 +3430,36 @@ Deck: TestDeck
         assert_eq!(count, 1, "Should create one card");
     }
 
+    #[test]
+    fn given_identical_cards_when_processing_then_each_gets_its_own_id() {
+        let (temp_dir, collection_path, _media_dir) = create_test_collection();
+        let markdown_path = temp_dir.path().join("identical.md");
+        fs::write(
+            &markdown_path,
+            "---\nDeck: TestDeck\n\n1. Same?\n> Yes\n\n1. Same?\n> Yes\n---\n",
+        )
+        .unwrap();
+
+        let mut collector =
+            CardCollector::new(&collection_path, CollectorConfig::default()).unwrap();
+        collector.process_file(&markdown_path).unwrap();
+
+        let content = fs::read_to_string(&markdown_path).unwrap();
+        let ids: Vec<&str> = content
+            .lines()
+            .filter(|line| line.starts_with("<!--ID:"))
+            .collect();
+        assert_eq!(ids.len(), 2, "{}", content);
+        assert_ne!(ids[0], ids[1]);
+        assert_eq!(
+            content,
+            format!(
+                "---\nDeck: TestDeck\n\n{}\n1. Same?\n> Yes\n\n{}\n1. Same?\n> Yes\n---\n",
+                ids[0], ids[1]
+            )
+        );
+    }
+
     #[test]
     fn given_cloze_card_when_processing_then_creates_note_with_footer() {
         let (temp_dir, collection_path, _media_dir) = create_test_collection();
@@ -790,6 +3491,29 @@ Deck: TestDeck
         assert!(result.contains(r#"<p><span style="font-size: 9pt;">File: /tmp/test.md</span></p>"#));
     }
 
+    #[test]
+    fn given_link_templates_when_building_source_uri_then_fills_encoded_paths() {
+        let path = Path::new("/vault/notes/rust & c.md");
+        let root = Some(Path::new("/vault"));
+
+        assert_eq!(
+            source_uri("obsidian://open?path={abspath}", path, root),
+            "obsidian://open?path=/vault/notes/rust%20%26%20c.md"
+        );
+        assert_eq!(
+            source_uri("obsidian://open?vault=vault&file={relpath}", path, root),
+            "obsidian://open?vault=vault&file=notes/rust%20%26%20c.md"
+        );
+        assert_eq!(
+            source_uri(
+                "vscode://file{abspath}#{filename}",
+                path,
+                Some(Path::new("/other"))
+            ),
+            "vscode://file/vault/notes/rust%20%26%20c.md#rust%20%26%20c.md"
+        );
+    }
+
     #[test]
     fn given_markdown_with_image_outside_section_when_processing_then_ignores_it() {
         let (temp_dir, collection_path, media_dir) = create_test_collection();
@@ -0,0 +1,129 @@
+use std::path::Path;
+
+/// Situation during `collect` that has more than one sensible outcome
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conflict<'a> {
+    /// A card carries the ID of a note that no longer exists in Anki
+    OrphanedId { path: &'a Path, note_id: i64 },
+    /// `--update-ids` found several notes with an ID-less card's content
+    /// (newest first)
+    DuplicateMatch { path: &'a Path, note_ids: &'a [i64] },
+    /// collection.media has a different file with the name of a media file
+    MediaName { path: &'a Path, filename: &'a str },
+}
+
+/// Kind of a conflict, for remembering "always" answers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConflictKind {
+    OrphanedId,
+    DuplicateMatch,
+    MediaName,
+}
+
+impl Conflict<'_> {
+    pub fn kind(&self) -> ConflictKind {
+        match self {
+            Conflict::OrphanedId { .. } => ConflictKind::OrphanedId,
+            Conflict::DuplicateMatch { .. } => ConflictKind::DuplicateMatch,
+            Conflict::MediaName { .. } => ConflictKind::MediaName,
+        }
+    }
+
+    /// What each resolution means for this conflict; `None` if it doesn't apply
+    pub fn describe(&self, resolution: Resolution) -> Option<String> {
+        match (self, resolution) {
+            (Conflict::OrphanedId { .. }, Resolution::Keep) => None,
+            (Conflict::OrphanedId { .. }, Resolution::Replace) => {
+                Some("create a new note and replace the ID".to_string())
+            }
+            (Conflict::OrphanedId { .. }, Resolution::Skip) => {
+                Some("leave the card and its ID alone".to_string())
+            }
+            (Conflict::DuplicateMatch { note_ids, .. }, Resolution::Keep) => Some(format!(
+                "use note {} (newest)",
+                note_ids.first().copied().unwrap_or_default()
+            )),
+            (Conflict::DuplicateMatch { note_ids, .. }, Resolution::Use(id))
+                if note_ids.contains(&id) =>
+            {
+                Some(format!("use note {}", id))
+            }
+            (_, Resolution::Use(_)) => None,
+            (Conflict::DuplicateMatch { .. }, Resolution::Replace) => {
+                Some("create a new note instead".to_string())
+            }
+            (Conflict::DuplicateMatch { .. }, Resolution::Skip) => {
+                Some("skip the card".to_string())
+            }
+            (Conflict::MediaName { .. }, Resolution::Keep) => Some("keep Anki's file".to_string()),
+            (Conflict::MediaName { .. }, Resolution::Replace) => {
+                Some("overwrite it with this file".to_string())
+            }
+            (Conflict::MediaName { .. }, Resolution::Skip) => {
+                Some("fail this markdown file".to_string())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Conflict<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Conflict::OrphanedId { path, note_id } => write!(
+                f,
+                "{}: note {} no longer exists in Anki",
+                path.display(),
+                note_id
+            ),
+            Conflict::DuplicateMatch { path, note_ids } => write!(
+                f,
+                "{}: card matches {} notes ({})",
+                path.display(),
+                note_ids.len(),
+                note_ids
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Conflict::MediaName { path, filename } => write!(
+                f,
+                "{}: a different \"{}\" already exists in Anki's media folder",
+                path.display(),
+                filename
+            ),
+        }
+    }
+}
+
+/// Answer to a conflict; see `Conflict::describe` for what it does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Keep,
+    Replace,
+    Skip,
+    /// Use this one of the notes of a `DuplicateMatch`
+    Use(i64),
+}
+
+/// Decides conflicts for `CardCollector`
+pub trait ConflictResolver {
+    fn resolve(&mut self, conflict: &Conflict) -> Resolution;
+}
+
+/// Resolves conflicts without asking, the way `collect` always has:
+/// orphaned IDs get a new note, the newest duplicate match is used, and
+/// conflicting media fails (`--force` overwrites media before any resolver
+/// is asked). Default for `CardCollector`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultResolver;
+
+impl ConflictResolver for DefaultResolver {
+    fn resolve(&mut self, conflict: &Conflict) -> Resolution {
+        match conflict {
+            Conflict::OrphanedId { .. } => Resolution::Replace,
+            Conflict::DuplicateMatch { .. } => Resolution::Keep,
+            Conflict::MediaName { .. } => Resolution::Skip,
+        }
+    }
+}
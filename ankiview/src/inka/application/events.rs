@@ -0,0 +1,74 @@
+use std::path::{Path, PathBuf};
+
+/// Receiver for structured progress events emitted by `CardCollector`.
+///
+/// All methods have empty default implementations, so a sink only needs to
+/// override the events it cares about. Events are emitted in processing
+/// order: `on_file_start`, then card events, then `on_file_done` (or
+/// `on_file_skipped` / `on_error` instead).
+pub trait EventSink {
+    /// A markdown file is about to be processed
+    fn on_file_start(&mut self, _path: &Path) {}
+
+    /// File was skipped because its hash is unchanged
+    fn on_file_skipped(&mut self, _path: &Path) {}
+
+    /// File was processed successfully
+    fn on_file_done(&mut self, _path: &Path, _cards: usize) {}
+
+    /// A new note was created
+    fn on_card_created(&mut self, _path: &Path, _note_id: i64) {}
+
+    /// An existing note was updated from markdown
+    fn on_card_updated(&mut self, _path: &Path, _note_id: i64) {}
+
+    /// A note referenced by ID no longer existed in Anki and was recreated
+    fn on_card_recreated(&mut self, _path: &Path, _old_id: i64, _new_id: i64) {}
+
+    /// An ID-less card was matched to an existing note by content (`--update-ids`)
+    fn on_card_matched(&mut self, _path: &Path, _note_id: i64) {}
+
+    /// Non-fatal problem worth surfacing to the user
+    fn on_warning(&mut self, _path: &Path, _message: &str) {}
+
+    /// Non-fatal problem at a line of the file; reported to `on_warning`
+    /// with the line in front of the message unless overridden
+    fn on_warning_at(&mut self, path: &Path, line: usize, message: &str) {
+        self.on_warning(path, &format!("line {}: {}", line, message));
+    }
+
+    /// Processing a file failed (reported whether or not `ignore_errors` is set)
+    fn on_error(&mut self, _path: &Path, _error: &anyhow::Error) {}
+}
+
+/// Line of a source file a card starts on
+///
+/// Attached as context to errors about a card, so they read
+/// `notes/trees.md:42: Failed to parse ...`; sinks can find it with
+/// `error.downcast_ref::<SourceLocation>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub path: PathBuf,
+    pub line: usize,
+}
+
+impl SourceLocation {
+    pub fn new(path: &Path, line: usize) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            line,
+        }
+    }
+}
+
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.path.display(), self.line)
+    }
+}
+
+/// Sink that ignores all events (default for `CardCollector`)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopEventSink;
+
+impl EventSink for NoopEventSink {}
@@ -0,0 +1,359 @@
+use crate::inka::infrastructure::config::{Config, LintConfig, SpellConfig};
+use crate::inka::infrastructure::markdown::section_parser::{
+    self, CardFormat, SectionParser, Span,
+};
+use crate::inka::infrastructure::markdown::{card_parser, cloze_converter};
+use crate::inka::infrastructure::{media_handler, spell_checker};
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::LazyLock;
+
+static ANKI_CLOZE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)\{\{c\d+::.*?\}\}").expect("Failed to compile cloze regex"));
+// Parts of a card that aren't prose: comments and HTML tags, code, math,
+// link targets, URLs and cloze numbers
+static NON_PROSE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?s)<!--.*?-->|```.*?```|`[^`\n]*`|\$\$.*?\$\$|\$[^$\n]*\$|\\\(.*?\\\)|\\\[.*?\\\]|<[^>\n]+>|\]\([^)\n]*\)|https?://\S+|\bc\d+::",
+    )
+    .expect("Failed to compile non-prose regex")
+});
+static WORD_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\p{L}+(?:['’]\p{L}+)*").expect("Failed to compile word regex"));
+
+/// Card quality rule checked by `lint`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintRule {
+    /// Front longer than `max_front_length`
+    FrontLength,
+    /// Basic card without an answer
+    EmptyBack,
+    /// Image without alt text
+    ImageAlt,
+    /// Cloze card whose deletions cover all of its text
+    WholeCloze,
+    /// Card containing one of the `todo_markers`
+    Todo,
+    /// Word the spell checker doesn't know (`[lint.spell]`)
+    Spelling,
+}
+
+impl std::fmt::Display for LintRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            LintRule::FrontLength => "front-length",
+            LintRule::EmptyBack => "empty-back",
+            LintRule::ImageAlt => "image-alt",
+            LintRule::WholeCloze => "whole-cloze",
+            LintRule::Todo => "todo",
+            LintRule::Spelling => "spelling",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Rule a card breaks, at the line the card starts on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintIssue {
+    pub line: usize,
+    pub rule: LintRule,
+    pub message: String,
+}
+
+/// Issues of the cards in `section`, with lines relative to the section
+pub fn lint_section(section: &str, formats: &[CardFormat], config: &LintConfig) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    for note in section_parser::extract_note_spans_for(section, formats) {
+        for (rule, message) in lint_card(&note.text, formats, config) {
+            issues.push(LintIssue {
+                line: note.line,
+                rule,
+                message,
+            });
+        }
+    }
+    issues
+}
+
+/// Misspelled words of the cards in `sections`, with lines of the file
+///
+/// A section's cards are checked with the dictionary of its deck or language
+/// (`Lang:`, else `default_lang`); the spell checker runs once per dictionary.
+pub fn spell_check_sections(
+    sections: &[Span<String>],
+    formats: &[CardFormat],
+    default_lang: Option<&str>,
+    spell: &SpellConfig,
+) -> Result<Vec<LintIssue>> {
+    let accepted: HashSet<String> = spell.words.iter().map(|word| word.to_lowercase()).collect();
+    let mut words_by_dictionary: BTreeMap<&str, BTreeSet<(usize, String)>> = BTreeMap::new();
+    for section in sections {
+        let deck = section_parser::extract_deck_name(&section.text);
+        let lang = section_parser::extract_lang(&section.text);
+        let dictionary = spell.dictionary_for(deck.as_deref(), lang.as_deref().or(default_lang));
+        let words = words_by_dictionary.entry(dictionary).or_default();
+        for note in section_parser::extract_note_spans_for(&section.text, formats) {
+            for (offset, word) in card_words(&note.text) {
+                if !accepted.contains(&word.to_lowercase()) {
+                    words.insert((section.line + note.line - 1 + offset, word));
+                }
+            }
+        }
+    }
+
+    let mut issues = Vec::new();
+    for (dictionary, words) in words_by_dictionary {
+        let misspelled = spell_checker::misspelled_words(
+            &spell.command,
+            dictionary,
+            words.iter().map(|(_, word)| word.as_str()),
+        )?;
+        for (line, word) in words {
+            if misspelled.contains(&word) {
+                issues.push(LintIssue {
+                    line,
+                    rule: LintRule::Spelling,
+                    message: format!("Unknown word '{}' ({})", word, dictionary),
+                });
+            }
+        }
+    }
+    issues.sort_by_key(|issue| issue.line);
+    Ok(issues)
+}
+
+/// Issues of every card in the markdown `content`, with lines of the file,
+/// using the section and card settings `collect` would
+pub fn lint_content(content: &str, settings: &Config) -> Result<Vec<LintIssue>> {
+    let parser =
+        SectionParser::with_delimiters(&settings.cards.section_start, &settings.cards.section_end);
+    let formats = &settings.cards.formats;
+
+    let mut issues = Vec::new();
+    let mut sections = Vec::new();
+    for section in parser.parse_spans(content) {
+        let Some(active) = section_parser::active_section(section.text, formats) else {
+            continue;
+        };
+        for issue in lint_section(&active, formats, &settings.lint) {
+            issues.push(LintIssue {
+                line: section.line + issue.line - 1,
+                ..issue
+            });
+        }
+        sections.push(Span {
+            line: section.line,
+            text: active,
+        });
+    }
+
+    if settings.lint.spell.enabled {
+        issues.extend(spell_check_sections(
+            &sections,
+            formats,
+            settings.markdown.lang.as_deref(),
+            &settings.lint.spell,
+        )?);
+        issues.sort_by_key(|issue| issue.line);
+    }
+    Ok(issues)
+}
+
+/// Words of a card's prose with their 0-based line in the card; code, math,
+/// URLs and markup are left out, as are single letters
+fn card_words(note_str: &str) -> Vec<(usize, String)> {
+    let prose = NON_PROSE_REGEX.replace_all(note_str, |caps: &regex::Captures| {
+        // Keep the line breaks, so the words after it keep their lines
+        caps[0].matches('\n').collect::<String>()
+    });
+    prose
+        .lines()
+        .enumerate()
+        .flat_map(|(offset, line)| {
+            WORD_REGEX
+                .find_iter(line)
+                .map(|word| word.as_str())
+                .filter(|word| word.chars().count() > 1)
+                .map(move |word| (offset, word.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn lint_card(
+    note_str: &str,
+    formats: &[CardFormat],
+    config: &LintConfig,
+) -> Vec<(LintRule, String)> {
+    let mut issues = Vec::new();
+
+    if card_parser::is_basic_card(note_str, formats) {
+        // Cards that don't parse are reported by collect itself
+        if let Ok(fields) = card_parser::parse_basic_card_field_list(note_str, 2) {
+            let front_length = fields[0].trim().chars().count();
+            if config.max_front_length > 0 && front_length > config.max_front_length {
+                issues.push((
+                    LintRule::FrontLength,
+                    format!(
+                        "Front has {} characters (at most {})",
+                        front_length, config.max_front_length
+                    ),
+                ));
+            }
+            if config.empty_back && fields[1].trim().is_empty() {
+                issues.push((LintRule::EmptyBack, "Card has no answer".to_string()));
+            }
+        }
+    } else if card_parser::is_cloze_card(note_str) && config.whole_cloze {
+        if let Ok(text) = card_parser::parse_cloze_card_field(note_str) {
+            let anki_text = cloze_converter::convert_cloze_syntax(&text);
+            let context = ANKI_CLOZE_REGEX.replace_all(&anki_text, "");
+            if !context.chars().any(char::is_alphanumeric) {
+                issues.push((
+                    LintRule::WholeCloze,
+                    "Cloze deletes the whole text; keep some context outside the deletion"
+                        .to_string(),
+                ));
+            }
+        }
+    }
+
+    if config.image_alt {
+        for image in media_handler::find_images_without_alt(note_str) {
+            issues.push((
+                LintRule::ImageAlt,
+                format!("Image '{}' has no alt text", image),
+            ));
+        }
+    }
+
+    for marker in config
+        .todo_markers
+        .iter()
+        .filter(|marker| !marker.is_empty())
+    {
+        let pattern = format!(r"\b{}\b", regex::escape(marker));
+        if Regex::new(&pattern).is_ok_and(|regex| regex.is_match(note_str)) {
+            issues.push((LintRule::Todo, format!("Card contains '{}'", marker)));
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(issues: &[LintIssue]) -> Vec<(usize, LintRule)> {
+        issues
+            .iter()
+            .map(|issue| (issue.line, issue.rule))
+            .collect()
+    }
+
+    #[test]
+    fn given_cards_breaking_rules_when_linting_then_reports_each_with_file_line() {
+        let content = "# Notes\n\n---\nDeck: Test\n\n1. Fine?\n> Yes\n2. No answer\n>\n3. {Everything deleted}\n4. What is this? TODO\n> ![](diagram.png)\n---\n";
+
+        let issues = lint_content(content, &Config::default()).unwrap();
+
+        assert_eq!(
+            rules(&issues),
+            vec![
+                (8, LintRule::EmptyBack),
+                (10, LintRule::WholeCloze),
+                (11, LintRule::ImageAlt),
+                (11, LintRule::Todo),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_long_front_when_linting_then_applies_configured_limit() {
+        let section = "1. What does the borrow checker enforce?\n> Aliasing XOR mutability";
+        let strict = LintConfig {
+            max_front_length: 10,
+            ..Default::default()
+        };
+        let off = LintConfig {
+            max_front_length: 0,
+            ..Default::default()
+        };
+
+        let issues = lint_section(section, CardFormat::DEFAULT, &strict);
+
+        assert_eq!(rules(&issues), vec![(1, LintRule::FrontLength)]);
+        assert!(lint_section(section, CardFormat::DEFAULT, &off).is_empty());
+    }
+
+    #[test]
+    fn given_card_with_code_and_math_when_extracting_words_then_keeps_prose_lines() {
+        let note = "1. What does `Vec::new` return in $O(1)$?\n> An empty {c1::vektor}\n> ```rust\n> let v = Vec::new();\n> ```\n> See <https://doc.rust-lang.org>";
+
+        let words = card_words(note);
+
+        assert_eq!(
+            words,
+            vec![
+                (0, "What".to_string()),
+                (0, "does".to_string()),
+                (0, "return".to_string()),
+                (0, "in".to_string()),
+                (1, "An".to_string()),
+                (1, "empty".to_string()),
+                (1, "vektor".to_string()),
+                (5, "See".to_string()),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn given_misspelled_words_when_spell_checking_then_uses_deck_dictionary_and_reports_lines() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Stand-in for hunspell: `-d de_DE` only rejects "Hund", others "teh"
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let script = temp_dir.path().join("hunspell");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\nif [ \"$5\" = de_DE ]; then grep -x Hund; else grep -x teh; fi || true\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let mut settings = Config::default();
+        settings.lint.spell.enabled = true;
+        settings.lint.spell.command = script.display().to_string();
+        settings
+            .lint
+            .spell
+            .decks
+            .insert("German".to_string(), "de_DE".to_string());
+        let content = "---\nDeck: Rust\n\n1. What is teh answer?\n> Hund and teh\n---\n\n---\nDeck: German::Nouns\n\n1. Hund\n> dog, not teh\n---\n";
+
+        let issues = lint_content(content, &settings).unwrap();
+
+        assert_eq!(
+            issues
+                .iter()
+                .map(|issue| (issue.line, issue.message.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (4, "Unknown word 'teh' (en_US)"),
+                (5, "Unknown word 'teh' (en_US)"),
+                (11, "Unknown word 'Hund' (de_DE)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_cloze_with_context_when_linting_then_reports_nothing() {
+        let section = "1. Rust is {memory safe} without a garbage collector";
+
+        assert!(lint_section(section, CardFormat::DEFAULT, &LintConfig::default()).is_empty());
+    }
+}
@@ -1,2 +1,11 @@
 // Application module placeholder
 pub mod card_collector;
+pub mod conflicts;
+pub mod events;
+pub mod lint;
+pub mod occluder;
+pub mod orphans;
+pub mod preview;
+pub mod template_notes;
+pub mod timings;
+pub mod vault_stats;
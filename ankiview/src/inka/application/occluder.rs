@@ -0,0 +1,191 @@
+use crate::application::NoteRepository;
+use crate::infrastructure::AnkiRepository;
+use crate::inka::infrastructure::config::MediaConfig;
+use crate::inka::infrastructure::media_handler::copy_media_to_anki;
+use crate::inka::infrastructure::occlusion::{note_masks, original_mask, OcclusionMode, Rect};
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use tracing::debug;
+
+/// Notetype the Image Occlusion Enhanced add-on installs
+pub const DEFAULT_NOTETYPE: &str = "Image Occlusion Enhanced";
+
+/// Image plus rectangles to turn into one note per rectangle
+#[derive(Debug, Clone)]
+pub struct OcclusionRequest {
+    pub image: PathBuf,
+    pub rects: Vec<Rect>,
+    pub mode: OcclusionMode,
+    pub deck: String,
+    pub header: Option<String>,
+    pub tags: Vec<String>,
+    /// Notetype with Image Occlusion Enhanced's fields
+    pub notetype: String,
+}
+
+/// Fields an Image Occlusion Enhanced notetype needs, by name
+struct OcclusionFields {
+    count: usize,
+    id: usize,
+    header: Option<usize>,
+    image: usize,
+    question_mask: usize,
+    answer_mask: usize,
+    original_mask: Option<usize>,
+}
+
+impl OcclusionFields {
+    fn resolve(notetype: &str, names: &[String]) -> Result<Self> {
+        let find = |candidates: &[&str]| {
+            names
+                .iter()
+                .position(|name| candidates.contains(&name.as_str()))
+        };
+        let require = |candidates: &[&str]| {
+            find(candidates).with_context(|| {
+                format!(
+                    "Notetype '{}' has no '{}' field; is it an Image Occlusion Enhanced notetype? \
+                     Fields: {}",
+                    notetype,
+                    candidates[0],
+                    names.join(", ")
+                )
+            })
+        };
+        Ok(Self {
+            count: names.len(),
+            id: require(&["ID (hidden)", "ID"])?,
+            header: find(&["Header"]),
+            image: require(&["Image"])?,
+            question_mask: require(&["Question Mask"])?,
+            answer_mask: require(&["Answer Mask"])?,
+            original_mask: find(&["Original Mask"]),
+        })
+    }
+}
+
+/// Create Image Occlusion Enhanced-style notes: the image and the mask SVGs
+/// go into collection.media/, and every rectangle becomes one note asking it.
+/// Returns the created note IDs in rectangle order
+pub fn create_occlusion_notes(
+    repository: &mut AnkiRepository,
+    request: &OcclusionRequest,
+) -> Result<Vec<i64>> {
+    if request.rects.is_empty() {
+        return Err(anyhow::anyhow!("Give at least one --rect to occlude"));
+    }
+    let (width, height) = image::image_dimensions(&request.image)
+        .with_context(|| format!("Failed to read image {}", request.image.display()))?;
+    for rect in &request.rects {
+        if rect.x.saturating_add(rect.width) > width || rect.y.saturating_add(rect.height) > height
+        {
+            return Err(anyhow::anyhow!(
+                "Rectangle {},{},{},{} lies outside the {}x{} image",
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+                width,
+                height
+            ));
+        }
+    }
+
+    let info = repository.notetype_info(&request.notetype)?;
+    let fields = OcclusionFields::resolve(&request.notetype, &info.fields)?;
+    let deck_id = repository.get_or_create_deck_id(&request.deck)?;
+
+    // Image copied unchanged: the masks are drawn in its pixel coordinates
+    let media_dir = repository.media_dir().to_path_buf();
+    let image = copy_media_to_anki(&request.image, &media_dir, false, &MediaConfig::default())?;
+
+    // Same image and rectangles give the same mask names, so a rerun
+    // rewrites identical files instead of piling up new ones
+    let mut hasher = Sha256::new();
+    hasher.update(image.as_bytes());
+    for rect in &request.rects {
+        hasher.update(format!(
+            "{},{},{},{};",
+            rect.x, rect.y, rect.width, rect.height
+        ));
+    }
+    let occlusion_id = format!("{:x}", hasher.finalize())[..16].to_string();
+
+    let write_mask = |name: String, svg: String| -> Result<String> {
+        std::fs::write(media_dir.join(&name), svg)
+            .with_context(|| format!("Failed to write mask {}", name))?;
+        Ok(format!("<img src=\"{}\" />", name))
+    };
+    let original = write_mask(
+        format!("{}-O.svg", occlusion_id),
+        original_mask(width, height, &request.rects),
+    )?;
+
+    let mut note_ids = Vec::with_capacity(request.rects.len());
+    for index in 0..request.rects.len() {
+        let (question, answer) = note_masks(width, height, &request.rects, index, request.mode);
+        let mut values = vec![String::new(); fields.count];
+        values[fields.id] = format!("{}-{}", occlusion_id, index + 1);
+        if let (Some(field), Some(header)) = (fields.header, &request.header) {
+            values[field] = html_escape::encode_text(header).into_owned();
+        }
+        values[fields.image] = format!("<img src=\"{}\" />", image);
+        values[fields.question_mask] =
+            write_mask(format!("{}-{}-Q.svg", occlusion_id, index + 1), question)?;
+        values[fields.answer_mask] =
+            write_mask(format!("{}-{}-A.svg", occlusion_id, index + 1), answer)?;
+        if let Some(field) = fields.original_mask {
+            values[field] = original.clone();
+        }
+
+        let note_id = repository.add_note_with_fields(info.id, deck_id, &values, &request.tags)?;
+        note_ids.push(note_id);
+    }
+    debug!(image = %image, notes = note_ids.len(), "Created image occlusion notes");
+    Ok(note_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn given_image_occlusion_enhanced_fields_when_resolving_then_finds_all() {
+        let fields = names(&[
+            "ID (hidden)",
+            "Header",
+            "Image",
+            "Question Mask",
+            "Footer",
+            "Remarks",
+            "Sources",
+            "Extra 1",
+            "Extra 2",
+            "Answer Mask",
+            "Original Mask",
+        ]);
+
+        let resolved = OcclusionFields::resolve(DEFAULT_NOTETYPE, &fields).unwrap();
+
+        assert_eq!(resolved.count, 11);
+        assert_eq!(resolved.id, 0);
+        assert_eq!(resolved.image, 2);
+        assert_eq!(resolved.question_mask, 3);
+        assert_eq!(resolved.answer_mask, 9);
+        assert_eq!(resolved.original_mask, Some(10));
+    }
+
+    #[test]
+    fn given_basic_notetype_when_resolving_then_explains_missing_field() {
+        let result = OcclusionFields::resolve("Basic", &names(&["Front", "Back"]));
+
+        let message = result.err().expect("Should fail").to_string();
+        assert!(message.contains("no 'ID (hidden)' field"));
+        assert!(message.contains("Fields: Front, Back"));
+    }
+}
@@ -0,0 +1,273 @@
+use crate::application::NoteRepository;
+use crate::inka::application::card_collector::{source_files, SourceFormat};
+use crate::inka::infrastructure::file_writer;
+use crate::inka::infrastructure::org_parser::NOTE_ID_PROPERTY;
+use crate::util::text::file_footer_path;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Why a collected note counts as orphaned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanReason {
+    /// The file in the note's footer doesn't exist anymore
+    MissingFile,
+    /// The file exists but no longer contains the note's ID
+    MissingId,
+}
+
+impl std::fmt::Display for OrphanReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrphanReason::MissingFile => write!(f, "file missing"),
+            OrphanReason::MissingId => write!(f, "ID not in file"),
+        }
+    }
+}
+
+/// A note collected from a file of the vault that no longer has its card
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OrphanNote {
+    pub note_id: i64,
+    /// Source file as written in the note's footer
+    pub file: String,
+    pub reason: OrphanReason,
+}
+
+/// Finds the notes `collect` created whose cards are gone from the markdown,
+/// the counterpart of cards whose note is gone from Anki
+pub struct OrphanFinder<R: NoteRepository> {
+    repository: R,
+}
+
+impl<R: NoteRepository> OrphanFinder<R> {
+    pub fn new(repository: R) -> Self {
+        Self { repository }
+    }
+
+    /// Notes whose footer names a file under `vault` that is missing or no
+    /// longer contains the note's ID. Relative footer paths (and `vault`) are
+    /// resolved against `base`, the directory collect was run from.
+    ///
+    /// A note whose ID still appears in another file of the vault isn't
+    /// orphaned: its file was moved or the card was moved between files.
+    pub fn find(&mut self, vault: &Path, base: &Path) -> Result<Vec<OrphanNote>> {
+        let vault = base.join(vault);
+        // Contents of the source files, None for missing ones
+        let mut sources: HashMap<PathBuf, Option<String>> = HashMap::new();
+        // Contents of all source files of the vault, read on first use
+        let mut vault_sources: Option<Vec<String>> = None;
+        let mut orphans = Vec::new();
+
+        for (note_id, fields) in self.repository.note_fields(None)? {
+            let Some(file) = fields.iter().find_map(|field| file_footer_path(field)) else {
+                continue;
+            };
+            let path = base.join(&file);
+            if !path.starts_with(&vault) {
+                continue;
+            }
+            let content = match sources.entry(path.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(read_source(&path)?),
+            };
+            let reason = match content {
+                None => OrphanReason::MissingFile,
+                Some(content) if !contains_note_id(content, note_id) => OrphanReason::MissingId,
+                Some(_) => continue,
+            };
+            if vault_sources.is_none() {
+                vault_sources = Some(read_sources(&vault)?);
+            }
+            if vault_sources
+                .iter()
+                .flatten()
+                .any(|content| contains_note_id(content, note_id))
+            {
+                continue;
+            }
+            debug!(note_id, file = %file, %reason, "Found orphaned note");
+            orphans.push(OrphanNote {
+                note_id,
+                file,
+                reason,
+            });
+        }
+        Ok(orphans)
+    }
+
+    /// Delete the notes of `orphans`; returns how many cards were deleted
+    pub fn delete(&mut self, orphans: &[OrphanNote]) -> Result<usize> {
+        let mut cards = 0;
+        for orphan in orphans {
+            cards += self.repository.delete_note(orphan.note_id)?;
+        }
+        Ok(cards)
+    }
+}
+
+/// Content of the source file at `path`, `None` if it doesn't exist
+fn read_source(path: &Path) -> Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let (content, _) = file_writer::read_markdown_file_with_encoding(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(Some(content))
+}
+
+/// Contents of the markdown and org files under `dir`
+fn read_sources(dir: &Path) -> Result<Vec<String>> {
+    let mut files = source_files(dir, SourceFormat::Markdown, true)?;
+    if dir.is_dir() {
+        files.extend(source_files(dir, SourceFormat::Org, true)?);
+    }
+    let mut contents = Vec::new();
+    for file in files {
+        contents.extend(read_source(&file)?);
+    }
+    Ok(contents)
+}
+
+/// Whether `content` carries `note_id` as a card's ID: a `<!--ID:…-->` line
+/// in markdown or an `:ANKI_NOTE_ID:` property in org
+fn contains_note_id(content: &str, note_id: i64) -> bool {
+    let id = note_id.to_string();
+    content.lines().map(str::trim).any(|line| {
+        let markdown = line
+            .strip_prefix("<!--ID:")
+            .and_then(|rest| rest.strip_suffix("-->"));
+        let org = line
+            .strip_prefix(':')
+            .and_then(|rest| rest.split_once(':'))
+            .filter(|(name, _)| name.eq_ignore_ascii_case(NOTE_ID_PROPERTY))
+            .map(|(_, value)| value.trim());
+        markdown.or(org) == Some(id.as_str())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Note;
+    use crate::util::testing::MockNoteRepository;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn collected(id: i64, file: &str) -> Note {
+        Note {
+            id,
+            front: "Question".to_string(),
+            back: format!(
+                r#"<p>Answer</p><p><span style="font-size: 9pt;">File: {}</span></p>"#,
+                file
+            ),
+            tags: vec![],
+            model_name: "Inka Basic".to_string(),
+        }
+    }
+
+    #[test]
+    fn given_notes_from_vault_files_when_finding_then_reports_missing_files_and_ids() {
+        // Arrange
+        let base = TempDir::new().unwrap();
+        fs::create_dir(base.path().join("vault")).unwrap();
+        fs::write(base.path().join("vault/kept.md"), "<!--ID:1-->\n1. Q\n> A").unwrap();
+        fs::write(base.path().join("vault/edited.md"), "1. Q\n> A").unwrap();
+        let mock = MockNoteRepository::builder()
+            .with_note(1, collected(1, "vault/kept.md"))
+            .with_note(2, collected(2, "vault/edited.md"))
+            .with_note(3, collected(3, "vault/deleted.md"))
+            .with_note(4, collected(4, "elsewhere/deleted.md"))
+            .build();
+        let mut finder = OrphanFinder::new(mock);
+
+        // Act
+        let orphans = finder.find(Path::new("vault"), base.path()).unwrap();
+
+        // Assert
+        assert_eq!(
+            orphans,
+            vec![
+                OrphanNote {
+                    note_id: 2,
+                    file: "vault/edited.md".to_string(),
+                    reason: OrphanReason::MissingId,
+                },
+                OrphanNote {
+                    note_id: 3,
+                    file: "vault/deleted.md".to_string(),
+                    reason: OrphanReason::MissingFile,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn given_note_whose_file_was_moved_when_finding_then_is_not_orphaned() {
+        let base = TempDir::new().unwrap();
+        fs::create_dir_all(base.path().join("vault/sub")).unwrap();
+        fs::write(
+            base.path().join("vault/sub/moved.md"),
+            "<!--ID:7-->\n1. Q\n> A",
+        )
+        .unwrap();
+        let mock = MockNoteRepository::builder()
+            .with_note(7, collected(7, "vault/old.md"))
+            .build();
+        let mut finder = OrphanFinder::new(mock);
+
+        let orphans = finder.find(Path::new("vault"), base.path()).unwrap();
+
+        assert!(orphans.is_empty());
+    }
+
+    #[test]
+    fn given_id_only_inside_other_text_when_finding_then_is_orphaned() {
+        let base = TempDir::new().unwrap();
+        fs::create_dir(base.path().join("vault")).unwrap();
+        fs::write(
+            base.path().join("vault/cards.md"),
+            "<!--ID:12-->\n1. Q\n> Answer 1\n\n* Q :anki:\n:PROPERTIES:\n:ANKI_NOTE_ID: 30\n:END:\n",
+        )
+        .unwrap();
+        let mock = MockNoteRepository::builder()
+            .with_note(1, collected(1, "vault/cards.md"))
+            .with_note(3, collected(3, "vault/cards.md"))
+            .with_note(30, collected(30, "vault/cards.md"))
+            .build();
+        let mut finder = OrphanFinder::new(mock);
+
+        let orphans = finder.find(Path::new("vault"), base.path()).unwrap();
+
+        let ids: Vec<i64> = orphans.iter().map(|orphan| orphan.note_id).collect();
+        assert_eq!(ids, vec![1, 3]);
+    }
+
+    #[test]
+    fn given_unreadable_source_when_finding_then_errors() {
+        let base = TempDir::new().unwrap();
+        fs::create_dir(base.path().join("vault")).unwrap();
+        fs::create_dir(base.path().join("vault/dir.md")).unwrap();
+        let mock = MockNoteRepository::builder()
+            .with_note(1, collected(1, "vault/dir.md"))
+            .build();
+        let mut finder = OrphanFinder::new(mock);
+
+        let err = finder.find(Path::new("vault"), base.path()).unwrap_err();
+
+        assert!(err.to_string().contains("Failed to read"));
+    }
+
+    #[test]
+    fn given_linked_footer_when_parsing_then_returns_decoded_path() {
+        let html = r#"<p><span style="font-size: 9pt;">File: <a href="obsidian://open?file=a%26b.md">/notes/a&amp;b.md</a></span></p>"#;
+
+        assert_eq!(file_footer_path(html), Some("/notes/a&b.md".to_string()));
+        assert_eq!(file_footer_path("<p>No footer</p>"), None);
+    }
+}
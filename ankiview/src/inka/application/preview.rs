@@ -0,0 +1,101 @@
+use crate::inka::application::card_collector::{CardKind, CardReader};
+use crate::inka::infrastructure::config::Config;
+use crate::inka::infrastructure::markdown::{card_parser, cloze_converter, converter};
+use crate::inka::infrastructure::media_handler;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Card of a markdown file converted like `collect` would, without touching
+/// the collection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreviewCard {
+    pub deck: String,
+    pub tags: Vec<String>,
+    /// ID already injected into the markdown, if any
+    pub note_id: Option<i64>,
+    /// The card as written in the section
+    pub source: String,
+    /// Field markdown as parsed from the card (front and back, or the cloze text)
+    pub markdown: Vec<String>,
+    /// Field HTML as it would be written to the note, before media paths are mapped
+    pub fields: Vec<String>,
+    /// Image paths referenced by the card, as written
+    pub images: Vec<String>,
+    pub cloze: bool,
+}
+
+/// Convert every card of `content` (the markdown file at `path`) with the
+/// section, card and markdown settings `collect` would use. Image sources are
+/// left relative to the markdown file instead of being mapped to collection.media.
+/// Without the collection the notetype's fields are unknown, so every `>>`
+/// group of a basic card is shown as a field of its own
+pub fn preview_cards(content: &str, path: &Path, settings: &Config) -> Result<Vec<PreviewCard>> {
+    let to_html = |md: &str, lang: Option<&str>, dir: Option<&str>| {
+        let html = converter::markdown_to_html_with(md, &settings.markdown, &settings.math);
+        converter::apply_text_direction(&html, lang, dir)
+    };
+
+    let mut cards = Vec::new();
+    for card in CardReader::from_settings(settings).cards(content) {
+        let Some(kind) = card.kind else {
+            continue;
+        };
+        let markdown = card
+            .fields(usize::MAX)
+            .with_context(|| format!("Failed to parse card: {}", card.first_line()))?;
+        let fields = markdown
+            .iter()
+            .map(|field| match kind {
+                CardKind::Basic => field.clone(),
+                CardKind::Cloze => cloze_converter::convert_cloze_syntax(field),
+            })
+            .map(|field| to_html(&field, card.lang.as_deref(), card.dir.as_deref()))
+            .collect();
+        cards.push(PreviewCard {
+            note_id: card_parser::extract_anki_id(&card.text),
+            images: media_handler::extract_image_paths(&card.text),
+            cloze: kind == CardKind::Cloze,
+            deck: card.deck,
+            tags: card.tags,
+            source: card.text,
+            markdown,
+            fields,
+        });
+    }
+    tracing::debug!(file = %path.display(), cards = cards.len(), "Converted cards for preview");
+    Ok(cards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_basic_and_cloze_cards_when_previewing_then_converts_both() {
+        let content = "---\nDeck: Rust\nTags: lang\n\n1. What is *ownership*?\n> Each value has one owner\n2. Rust is {memory safe}\n---\n";
+
+        let cards = preview_cards(content, Path::new("notes.md"), &Config::default()).unwrap();
+
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[0].deck, "Rust");
+        assert_eq!(cards[0].tags, vec!["lang"]);
+        assert!(!cards[0].cloze);
+        assert!(cards[0].fields[0].contains("<em>ownership</em>"));
+        assert!(cards[0].fields[1].contains("Each value has one owner"));
+        assert!(cards[1].cloze);
+        assert!(cards[1].fields[0].contains("{{c1::memory safe}}"));
+    }
+
+    #[test]
+    fn given_card_with_extra_field_group_when_previewing_then_shows_it_as_field() {
+        let content = "---\n1. What is a borrow?\n> A reference\n>\n>> The Book, ch. 4\n---\n";
+
+        let cards = preview_cards(content, Path::new("notes.md"), &Config::default()).unwrap();
+
+        assert_eq!(
+            cards[0].markdown,
+            vec!["What is a borrow?", "A reference", "The Book, ch. 4"]
+        );
+        assert_eq!(cards[0].fields.len(), 3);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::infrastructure::AnkiRepository;
+use crate::inka::application::card_collector::{CardKind, CardReader};
+use crate::inka::infrastructure::config::Config;
+use crate::inka::infrastructure::markdown::frontmatter::Frontmatter;
+use crate::inka::infrastructure::markdown::section_parser::{CardFormat, Span};
+use crate::inka::infrastructure::markdown::{cloze_converter, converter};
+use anyhow::{Context, Result};
+use tracing::debug;
+
+/// Rendered template plus the command line's overrides
+#[derive(Debug, Clone, Default)]
+pub struct TemplateRequest {
+    /// Template with its placeholders filled in
+    pub markdown: String,
+    /// Overrides the template's `Deck:` line
+    pub deck: Option<String>,
+    /// Added to the template's `Tags:`
+    pub tags: Vec<String>,
+    /// Basic notetype to use, like `collect --card-type`
+    pub card_type: Option<String>,
+}
+
+/// Create a note for every card of a rendered template. The template is the
+/// body of one inka section (optionally still between `---` lines): `Deck:`
+/// and `Tags:` lines followed by numbered or `Q:`/`A:` cards. Section
+/// delimiters, markdown extensions and math output come from `settings`, as
+/// for collected markdown.
+/// Returns the created note IDs in card order
+pub fn create_template_notes(
+    repository: &mut AnkiRepository,
+    request: &TemplateRequest,
+    settings: &Config,
+) -> Result<Vec<i64>> {
+    let reader = CardReader::from_settings(settings)
+        .with_formats(vec![CardFormat::Numbered, CardFormat::Qa])
+        .with_deck_override(request.deck.clone(), None)
+        .with_extra_tags(request.tags.clone());
+    let mut sections = reader.sections(&request.markdown);
+    sections.truncate(1);
+    if sections.is_empty() {
+        sections.push(Span {
+            line: 1,
+            text: request.markdown.clone(),
+        });
+    }
+    let cards = reader.section_cards(&sections, &Frontmatter::default());
+    if cards.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Template has no cards; write them like in collected markdown (\"1. Question\" and \"> Answer\")"
+        ));
+    }
+
+    let to_html = |md: &str, lang: Option<&str>, dir: Option<&str>| {
+        let html = converter::markdown_to_html_with(md, &settings.markdown, &settings.math);
+        converter::apply_text_direction(&html, lang, dir)
+    };
+
+    let mut note_ids = Vec::with_capacity(cards.len());
+    for card in &cards {
+        let deck_id = repository.get_or_create_deck_id(&card.deck)?;
+        let (notetype_id, field_count) = match card.kind {
+            Some(CardKind::Basic) => {
+                let notetype_id =
+                    repository.find_or_create_basic_notetype(request.card_type.as_deref())?;
+                (notetype_id, repository.notetype_field_count(notetype_id)?)
+            }
+            Some(CardKind::Cloze) => (repository.find_or_create_cloze_notetype()?, 1),
+            None => {
+                return Err(anyhow::anyhow!(
+                    "Template card is neither a question with an answer nor a cloze: {}",
+                    card.first_line()
+                ))
+            }
+        };
+        let fields: Vec<String> = card
+            .fields(field_count)
+            .context("Failed to parse template card")?
+            .iter()
+            .map(|field| match card.kind {
+                Some(CardKind::Cloze) => cloze_converter::convert_cloze_syntax(field),
+                _ => field.clone(),
+            })
+            .map(|field| to_html(&field, card.lang.as_deref(), card.dir.as_deref()))
+            .collect();
+        note_ids.push(repository.add_note_with_fields(
+            notetype_id,
+            deck_id,
+            &fields,
+            &card.tags,
+        )?);
+    }
+    debug!(deck = %cards[0].deck, notes = note_ids.len(), "Created notes from template");
+    Ok(note_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::NoteRepository;
+    use std::path::PathBuf;
+
+    #[test]
+    fn given_rendered_template_when_creating_then_adds_note_with_deck_and_tags() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let fixture_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/test_collection/User 1/collection.anki2");
+        let collection_path = temp_dir.path().join("collection.anki2");
+        std::fs::copy(&fixture_path, &collection_path).unwrap();
+        let mut repository = AnkiRepository::new(&collection_path).unwrap();
+        let request = TemplateRequest {
+            markdown: "Deck: Spanish\nTags: vocab\n\n1. What does *perro* mean?\n> dog\n"
+                .to_string(),
+            tags: vec!["animals".to_string()],
+            card_type: Some("Basic".to_string()),
+            ..Default::default()
+        };
+
+        let note_ids =
+            create_template_notes(&mut repository, &request, &Config::default()).unwrap();
+
+        assert_eq!(note_ids.len(), 1);
+        let note = repository.get_note(note_ids[0]).unwrap();
+        assert!(note.front.contains("<em>perro</em>"));
+        assert_eq!(note.tags, vec!["vocab", "animals"]);
+    }
+
+    #[test]
+    fn given_markdown_settings_when_creating_then_converts_with_them() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let fixture_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/test_collection/User 1/collection.anki2");
+        let collection_path = temp_dir.path().join("collection.anki2");
+        std::fs::copy(&fixture_path, &collection_path).unwrap();
+        let mut repository = AnkiRepository::new(&collection_path).unwrap();
+        let mut settings = Config::default();
+        settings.markdown.smart_punctuation = true;
+        let request = TemplateRequest {
+            markdown: "1. What is \"perro\"?\n> dog\n".to_string(),
+            card_type: Some("Basic".to_string()),
+            ..Default::default()
+        };
+
+        let note_ids = create_template_notes(&mut repository, &request, &settings).unwrap();
+
+        let note = repository.get_note(note_ids[0]).unwrap();
+        assert!(note.front.contains("“perro”"));
+    }
+}
@@ -0,0 +1,152 @@
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use std::time::Duration;
+
+/// Phase of a collect run that is timed separately (`--timings`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Checking files against the hash cache and hashing processed files
+    Hash,
+    /// Reading markdown and splitting it into sections and cards
+    Parse,
+    /// Converting markdown to HTML
+    Convert,
+    /// Copying images into collection.media/
+    Media,
+    /// Creating, updating and searching notes in the collection
+    Repository,
+    /// Writing injected note IDs back to markdown files
+    Write,
+    /// Persisting the hash cache
+    CacheSave,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 7] = [
+        Phase::Hash,
+        Phase::Parse,
+        Phase::Convert,
+        Phase::Media,
+        Phase::Repository,
+        Phase::Write,
+        Phase::CacheSave,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Phase::Hash => "hash",
+            Phase::Parse => "parse",
+            Phase::Convert => "convert",
+            Phase::Media => "media",
+            Phase::Repository => "repository",
+            Phase::Write => "write",
+            Phase::CacheSave => "cache_save",
+        }
+    }
+}
+
+/// Wall-clock time accumulated per phase over a collect run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CollectTimings {
+    phases: [Duration; Phase::ALL.len()],
+    total: Duration,
+}
+
+impl CollectTimings {
+    pub fn add(&mut self, phase: Phase, elapsed: Duration) {
+        self.phases[phase as usize] += elapsed;
+    }
+
+    pub fn get(&self, phase: Phase) -> Duration {
+        self.phases[phase as usize]
+    }
+
+    /// Duration of the whole run, including opening the collection
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    pub fn set_total(&mut self, total: Duration) {
+        self.total = total;
+    }
+
+    /// Human-readable breakdown, one phase per line
+    pub fn report(&self) -> String {
+        let mut lines = vec!["Timings:".to_string()];
+        for phase in Phase::ALL {
+            lines.push(format!(
+                "  {:<12} {:>10.1} ms",
+                phase.as_str(),
+                millis(self.get(phase))
+            ));
+        }
+        let other = self
+            .total
+            .saturating_sub(self.phases.iter().sum::<Duration>());
+        lines.push(format!("  {:<12} {:>10.1} ms", "other", millis(other)));
+        lines.push(format!("  {:<12} {:>10.1} ms", "total", millis(self.total)));
+        lines.join("\n")
+    }
+}
+
+/// Serialized as `{"hash": <ms>, ..., "total": <ms>}`
+impl Serialize for CollectTimings {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(Phase::ALL.len() + 1))?;
+        for phase in Phase::ALL {
+            map.serialize_entry(phase.as_str(), &millis(self.get(phase)))?;
+        }
+        map.serialize_entry("total", &millis(self.total))?;
+        map.end()
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_repeated_phase_when_adding_then_accumulates() {
+        let mut timings = CollectTimings::default();
+
+        timings.add(Phase::Parse, Duration::from_millis(3));
+        timings.add(Phase::Parse, Duration::from_millis(4));
+
+        assert_eq!(timings.get(Phase::Parse), Duration::from_millis(7));
+        assert_eq!(timings.get(Phase::Media), Duration::ZERO);
+    }
+
+    #[test]
+    fn given_timings_when_reporting_then_lists_every_phase_and_remainder() {
+        let mut timings = CollectTimings::default();
+        timings.add(Phase::Repository, Duration::from_millis(20));
+        timings.set_total(Duration::from_millis(50));
+
+        let report = timings.report();
+
+        for phase in Phase::ALL {
+            assert!(
+                report.contains(phase.as_str()),
+                "missing {}",
+                phase.as_str()
+            );
+        }
+        assert!(report.contains("other              30.0 ms"), "{}", report);
+        assert!(report.contains("total              50.0 ms"), "{}", report);
+    }
+
+    #[test]
+    fn given_timings_when_serializing_then_emits_milliseconds_per_phase() {
+        let mut timings = CollectTimings::default();
+        timings.add(Phase::Hash, Duration::from_micros(1500));
+
+        let value = serde_json::to_value(&timings).unwrap();
+
+        assert_eq!(value["hash"], 1.5);
+        assert_eq!(value["total"], 0.0);
+    }
+}
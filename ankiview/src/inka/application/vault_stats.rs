@@ -0,0 +1,132 @@
+use crate::inka::application::card_collector::{CardKind, CardReader};
+use crate::inka::infrastructure::config::Config;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Card counts of a file, a deck or the whole vault
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CardCounts {
+    pub cards: usize,
+    pub basic: usize,
+    pub cloze: usize,
+    /// Words in all fields of the cards, as written in markdown
+    pub words: usize,
+    /// Share of cloze cards, `None` without cards
+    pub cloze_ratio: Option<f64>,
+    /// Mean length of the basic cards' answers in characters, `None` without
+    /// basic cards
+    pub average_answer_length: Option<f64>,
+    /// Mean length of the basic cards' answers in words, `None` without
+    /// basic cards
+    pub average_answer_words: Option<f64>,
+    #[serde(skip)]
+    answer_chars: usize,
+    #[serde(skip)]
+    answer_words: usize,
+}
+
+/// Size of one card: words in all its fields, and the characters and words
+/// of a basic card's answer
+#[derive(Debug, Clone, Copy)]
+struct CardSize {
+    words: usize,
+    answer: Option<(usize, usize)>,
+}
+
+impl CardCounts {
+    fn add(&mut self, size: CardSize) {
+        self.cards += 1;
+        self.words += size.words;
+        match size.answer {
+            Some((chars, words)) => {
+                self.basic += 1;
+                self.answer_chars += chars;
+                self.answer_words += words;
+            }
+            None => self.cloze += 1,
+        }
+        self.cloze_ratio = Some(self.cloze as f64 / self.cards as f64);
+        self.average_answer_length =
+            (self.basic > 0).then(|| self.answer_chars as f64 / self.basic as f64);
+        self.average_answer_words =
+            (self.basic > 0).then(|| self.answer_words as f64 / self.basic as f64);
+    }
+}
+
+/// Cards of the markdown files of a vault, counted without the collection
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct VaultStats {
+    pub files: usize,
+    pub total: CardCounts,
+    pub by_file: BTreeMap<String, CardCounts>,
+    pub by_deck: BTreeMap<String, CardCounts>,
+}
+
+impl VaultStats {
+    /// Count the cards of `content` (the markdown file at `path`), with decks
+    /// resolved like `collect` does: `Deck:` line, frontmatter, then
+    /// "Default". Cards that don't parse aren't counted
+    pub fn add_file(&mut self, path: &Path, content: &str, settings: &Config) {
+        let file = self.by_file.entry(path.display().to_string()).or_default();
+        let word_count = |text: &str| text.split_whitespace().count();
+        for card in CardReader::from_settings(settings).cards(content) {
+            // `>>` groups are extra fields, not part of the answer
+            let Ok(fields) = card.fields(usize::MAX) else {
+                continue;
+            };
+            let size = CardSize {
+                words: fields.iter().map(|field| word_count(field)).sum(),
+                answer: (card.kind == Some(CardKind::Basic))
+                    .then(|| (fields[1].trim().chars().count(), word_count(&fields[1]))),
+            };
+            file.add(size);
+            self.by_deck.entry(card.deck).or_default().add(size);
+            self.total.add(size);
+        }
+        self.files = self.by_file.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_files_with_basic_and_cloze_cards_when_counting_then_groups_by_file_and_deck() {
+        let mut stats = VaultStats::default();
+
+        stats.add_file(
+            Path::new("notes/rust.md"),
+            "---\nDeck: Rust\n\n1. What is ownership?\n> One owner\n2. Rust is {memory safe}\n---\n",
+            &Config::default(),
+        );
+        stats.add_file(
+            Path::new("notes/misc.md"),
+            "---\n1. Capital of France?\n> Paris\n---\n",
+            &Config::default(),
+        );
+
+        assert_eq!(stats.files, 2);
+        assert_eq!(stats.total.cards, 3);
+        assert_eq!(stats.total.cloze, 1);
+        assert_eq!(stats.total.average_answer_length, Some(7.0));
+        assert_eq!(stats.total.average_answer_words, Some(1.5));
+        assert_eq!(stats.by_file["notes/rust.md"].words, 9);
+        assert_eq!(stats.by_file["notes/rust.md"].cloze_ratio, Some(0.5));
+        assert_eq!(stats.by_deck["Rust"].cards, 2);
+        assert_eq!(stats.by_deck["Default"].basic, 1);
+        assert_eq!(stats.by_deck["Default"].cloze_ratio, Some(0.0));
+    }
+
+    #[test]
+    fn given_file_without_cards_when_counting_then_lists_it_empty() {
+        let mut stats = VaultStats::default();
+
+        stats.add_file(Path::new("empty.md"), "# Just notes\n", &Config::default());
+
+        assert_eq!(stats.files, 1);
+        assert_eq!(stats.by_file["empty.md"], CardCounts::default());
+        assert!(stats.by_deck.is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+use crate::inka::application::events::{EventSink, SourceLocation};
+use std::io::Write;
+use std::path::Path;
+
+/// Event sink used by `collect --ci`: reports errors and warnings as GitHub
+/// Actions workflow commands (`::error file=...::message`), which the job log
+/// shows as annotations on the source file.
+pub struct CiEventSink<O> {
+    output: O,
+}
+
+impl CiEventSink<std::io::Stdout> {
+    pub fn stdout() -> Self {
+        Self::new(std::io::stdout())
+    }
+}
+
+impl<O: Write> CiEventSink<O> {
+    pub fn new(output: O) -> Self {
+        Self { output }
+    }
+
+    fn annotate(&mut self, level: &str, path: &Path, line: Option<usize>, message: &str) {
+        let _ = writeln!(self.output, "{}", annotation(level, path, line, message));
+    }
+}
+
+impl<O: Write> EventSink for CiEventSink<O> {
+    fn on_warning(&mut self, path: &Path, message: &str) {
+        self.annotate("warning", path, None, message);
+    }
+
+    fn on_warning_at(&mut self, path: &Path, line: usize, message: &str) {
+        self.annotate("warning", path, Some(line), message);
+    }
+
+    fn on_error(&mut self, path: &Path, error: &anyhow::Error) {
+        let Some(location) = error.downcast_ref::<SourceLocation>() else {
+            self.annotate("error", path, None, &format!("{:#}", error));
+            return;
+        };
+        // The location goes into the annotation's properties, not the message
+        let location_text = location.to_string();
+        let message = error
+            .chain()
+            .map(|cause| cause.to_string())
+            .filter(|cause| *cause != location_text)
+            .collect::<Vec<_>>()
+            .join(": ");
+        self.annotate("error", &location.path, Some(location.line), &message);
+    }
+}
+
+/// Workflow command `::<level> file=<path>,line=<line>::<message>`
+pub fn annotation(level: &str, path: &Path, line: Option<usize>, message: &str) -> String {
+    let line = line
+        .map(|line| format!(",line={}", line))
+        .unwrap_or_default();
+    format!(
+        "::{} file={}{}::{}",
+        level,
+        escape_property(&path.display().to_string()),
+        line,
+        escape_data(message)
+    )
+}
+
+// Escaping as done by @actions/core, so multi-line messages stay one command
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn given_error_and_warning_when_reporting_then_writes_escaped_annotations() {
+        let mut output = Vec::new();
+        let mut sink = CiEventSink::new(&mut output);
+
+        sink.on_error(
+            Path::new("notes/a,b.md"),
+            &anyhow!("bad answer").context("Failed to parse"),
+        );
+        sink.on_warning(Path::new("notes/c.md"), "100% done\nnext line");
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "::error file=notes/a%2Cb.md::Failed to parse: bad answer\n\
+             ::warning file=notes/c.md::100%25 done%0Anext line\n"
+        );
+    }
+
+    #[test]
+    fn given_error_at_source_line_when_reporting_then_annotates_line() {
+        let mut output = Vec::new();
+        let mut sink = CiEventSink::new(&mut output);
+        let path = Path::new("notes/trees.md");
+
+        sink.on_error(
+            path,
+            &anyhow!("bad answer")
+                .context("Failed to parse")
+                .context(SourceLocation::new(path, 42)),
+        );
+        sink.on_warning_at(path, 7, "Note ID 1 is used more than once in this file");
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "::error file=notes/trees.md,line=42::Failed to parse: bad answer\n\
+             ::warning file=notes/trees.md,line=7::Note ID 1 is used more than once in this file\n"
+        );
+    }
+}
@@ -0,0 +1,19 @@
+use crate::inka::application::events::EventSink;
+use std::path::Path;
+
+/// Event sink used by the `collect` command: surfaces warnings on stderr.
+///
+/// Per-card progress is left to tracing (`-v`, `--log-format json`); the
+/// final summary and collected errors are printed by the command handler.
+#[derive(Debug, Default)]
+pub struct ConsoleEventSink;
+
+impl EventSink for ConsoleEventSink {
+    fn on_warning(&mut self, path: &Path, message: &str) {
+        eprintln!("Warning: {}: {}", path.display(), message);
+    }
+
+    fn on_warning_at(&mut self, path: &Path, line: usize, message: &str) {
+        eprintln!("Warning: {}:{}: {}", path.display(), line, message);
+    }
+}
@@ -1 +1,4 @@
 // CLI module placeholder
+pub mod ci;
+pub mod console;
+pub mod prompt;
@@ -0,0 +1,184 @@
+use crate::inka::application::conflicts::{Conflict, ConflictKind, ConflictResolver, Resolution};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+const CHOICES: [(char, &str, Resolution); 3] = [
+    ('k', "keep", Resolution::Keep),
+    ('r', "replace", Resolution::Replace),
+    ('s', "skip", Resolution::Skip),
+];
+
+/// Conflict resolver used by `collect` on a terminal: asks on stderr and
+/// reads the answer from stdin.
+///
+/// An upper-case answer (`K`, `R`, `S`) also applies to the remaining
+/// conflicts of the same kind in this run; for a card matching several notes
+/// a number picks one of them. Without an answer (end of input) it falls
+/// back to the `DefaultResolver`'s choice.
+pub struct PromptResolver<I, O> {
+    input: I,
+    output: O,
+    always: HashMap<ConflictKind, Resolution>,
+}
+
+impl PromptResolver<std::io::StdinLock<'static>, std::io::Stderr> {
+    pub fn stdio() -> Self {
+        Self::new(std::io::stdin().lock(), std::io::stderr())
+    }
+}
+
+impl<I: BufRead, O: Write> PromptResolver<I, O> {
+    pub fn new(input: I, output: O) -> Self {
+        Self {
+            input,
+            output,
+            always: HashMap::new(),
+        }
+    }
+
+    fn ask(&mut self, conflict: &Conflict) -> Option<(Resolution, bool)> {
+        let choices: Vec<_> = CHOICES
+            .iter()
+            .filter_map(|&(key, name, resolution)| {
+                conflict
+                    .describe(resolution)
+                    .map(|description| (key, name, resolution, description))
+            })
+            .collect();
+
+        writeln!(self.output, "Conflict: {}", conflict).ok()?;
+        for (key, name, _, description) in &choices {
+            writeln!(self.output, "  [{}]{}: {}", key, &name[1..], description).ok()?;
+        }
+        let matches: &[i64] = match conflict {
+            Conflict::DuplicateMatch { note_ids, .. } => note_ids,
+            _ => &[],
+        };
+        for (index, note_id) in matches.iter().enumerate() {
+            writeln!(self.output, "  [{}] use note {}", index + 1, note_id).ok()?;
+        }
+
+        let mut keys = choices
+            .iter()
+            .map(|(key, ..)| key.to_string())
+            .collect::<Vec<_>>();
+        if !matches.is_empty() {
+            keys.push(format!("1-{}", matches.len()));
+        }
+        let keys = keys.join("/");
+        loop {
+            write!(
+                self.output,
+                "Choose {} (upper case: same for all remaining): ",
+                keys
+            )
+            .ok()?;
+            self.output.flush().ok()?;
+
+            let mut answer = String::new();
+            if self.input.read_line(&mut answer).ok()? == 0 {
+                return None;
+            }
+            let answer = answer.trim();
+            if let Some(note_id) = answer
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| n.checked_sub(1))
+                .and_then(|index| matches.get(index))
+            {
+                return Some((Resolution::Use(*note_id), false));
+            }
+            let mut chars = answer.chars();
+            if let (Some(c), None) = (chars.next(), chars.next()) {
+                let key = c.to_ascii_lowercase();
+                if let Some((.., resolution, _)) = choices.iter().find(|(k, ..)| *k == key) {
+                    return Some((*resolution, c.is_ascii_uppercase()));
+                }
+            }
+            writeln!(self.output, "Please answer one of {}.", keys).ok()?;
+        }
+    }
+}
+
+impl<I: BufRead, O: Write> ConflictResolver for PromptResolver<I, O> {
+    fn resolve(&mut self, conflict: &Conflict) -> Resolution {
+        if let Some(&resolution) = self.always.get(&conflict.kind()) {
+            return resolution;
+        }
+        match self.ask(conflict) {
+            Some((resolution, always)) => {
+                if always {
+                    self.always.insert(conflict.kind(), resolution);
+                }
+                resolution
+            }
+            None => crate::inka::application::conflicts::DefaultResolver.resolve(conflict),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn orphan(note_id: i64) -> Conflict<'static> {
+        Conflict::OrphanedId {
+            path: Path::new("notes.md"),
+            note_id,
+        }
+    }
+
+    #[test]
+    fn given_invalid_then_valid_answer_when_resolving_then_asks_again() {
+        let mut output = Vec::new();
+        let mut resolver = PromptResolver::new("k\ns\n".as_bytes(), &mut output);
+
+        let resolution = resolver.resolve(&orphan(1));
+
+        assert_eq!(resolution, Resolution::Skip);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Conflict: notes.md: note 1 no longer exists in Anki"));
+        // Keep doesn't apply to orphaned IDs
+        assert!(!output.contains("[k]eep"));
+        assert!(output.contains("Please answer one of r/s."));
+    }
+
+    #[test]
+    fn given_upper_case_answer_when_resolving_then_applies_to_later_conflicts_of_kind() {
+        let mut resolver = PromptResolver::new("R\n".as_bytes(), Vec::new());
+
+        assert_eq!(resolver.resolve(&orphan(1)), Resolution::Replace);
+        assert_eq!(resolver.resolve(&orphan(2)), Resolution::Replace);
+    }
+
+    #[test]
+    fn given_number_when_resolving_duplicate_match_then_uses_that_note() {
+        let mut output = Vec::new();
+        let mut resolver = PromptResolver::new("2\n".as_bytes(), &mut output);
+        let duplicates = Conflict::DuplicateMatch {
+            path: Path::new("notes.md"),
+            note_ids: &[30, 20, 10],
+        };
+
+        let resolution = resolver.resolve(&duplicates);
+
+        assert_eq!(resolution, Resolution::Use(20));
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("[k]eep: use note 30 (newest)"));
+        assert!(output.contains("[3] use note 10"));
+        assert!(output.contains("Choose k/r/s/1-3"));
+    }
+
+    #[test]
+    fn given_end_of_input_when_resolving_then_uses_default() {
+        let mut resolver = PromptResolver::new("".as_bytes(), Vec::new());
+        let media = Conflict::MediaName {
+            path: Path::new("notes.md"),
+            filename: "image.png",
+        };
+
+        assert_eq!(resolver.resolve(&orphan(1)), Resolution::Replace);
+        assert_eq!(resolver.resolve(&media), Resolution::Skip);
+    }
+}
@@ -1,6 +1,15 @@
+use crate::inka::infrastructure::hasher::CacheLocation;
+use crate::inka::infrastructure::markdown::section_parser::{CardFormat, SectionParser};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// File name looked up in the collected directory and its parents
+pub const CONFIG_FILE_NAME: &str = "ankiview.toml";
+/// Name of the user-wide config file in the `ankiview` config directory
+pub const USER_CONFIG_FILE_NAME: &str = "config.toml";
 
 /// TOML configuration for inka collection
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
@@ -11,6 +20,26 @@ pub struct Config {
     pub anki: AnkiConfig,
     #[serde(default)]
     pub highlight: HighlightConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub media: MediaConfig,
+    #[serde(default)]
+    pub cards: CardsConfig,
+    #[serde(default)]
+    pub markdown: MarkdownConfig,
+    #[serde(default)]
+    pub math: MathConfig,
+    #[serde(default)]
+    pub viewer: ViewerConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub footer: FooterConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub lint: LintConfig,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
@@ -21,6 +50,17 @@ pub struct Defaults {
     pub deck: String,
     #[serde(default = "default_folder")]
     pub folder: String,
+    /// Deck options preset for decks created by collect (default: Anki's "Default")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deck_options: Option<String>,
+    /// Namespace for the tags collect applies, e.g. "inka" turns `rust` into
+    /// `inka::rust` (see `collect --tag-namespace`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_namespace: Option<String>,
+    /// Collections `list`, `count` and `review-stats` work on without
+    /// `--collection`/`--profile`: profile names or collection.anki2 paths
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collections: Vec<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
@@ -45,6 +85,197 @@ pub struct HighlightConfig {
     pub style: String,
 }
 
+/// Shell commands run around `collect`; honoured only from the user config
+/// or `--config`, never from an `ankiview.toml` found in the vault
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
+pub struct HooksConfig {
+    /// Runs before the collection is opened; a failure aborts the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_collect: Option<String>,
+    /// Runs after the collection is closed again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_collect: Option<String>,
+}
+
+/// Limits and conversions for images copied into collection.media/
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
+pub struct MediaConfig {
+    /// Downscale images wider than this many pixels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_width: Option<u32>,
+    /// Recompress (and if necessary downscale) images larger than this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<u64>,
+    /// Copy SVGs as PNGs (older AnkiDroid versions don't render SVG)
+    #[serde(default)]
+    pub rasterize_svg: bool,
+}
+
+/// Optional rendering extensions for card markdown
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
+pub struct MarkdownConfig {
+    /// Curly quotes, en/em dashes (`--`, `---`) and ellipses (`...`)
+    #[serde(default)]
+    pub smart_punctuation: bool,
+    /// Replace `:shortcode:` (e.g. `:rocket:`) with the emoji
+    #[serde(default)]
+    pub emoji: bool,
+    /// Language of card text (e.g. "he") for sections without a `Lang:` line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    /// Text direction ("rtl", "ltr", "auto") for sections without a `Dir:` line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<String>,
+}
+
+/// How `$...$` / `$$...$$` math is written into note fields, and how
+/// MathJax renders it
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
+pub struct MathConfig {
+    #[serde(default)]
+    pub mode: MathMode,
+    /// TeX macros by name (without backslash), e.g. `R = '\mathbb{R}'`;
+    /// `#1`, `#2`, ... in the body are arguments
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub macros: BTreeMap<String, String>,
+    /// Inline math delimiters for `view` and `preview`, e.g. `[["$", "$"]]`
+    /// (default: `\(...\)` and `$...$`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inline_delimiters: Vec<[String; 2]>,
+    /// Display math delimiters for `view` and `preview`
+    /// (default: `\[...\]` and `$$...$$`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub display_delimiters: Vec<[String; 2]>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum MathMode {
+    /// `\(...\)` / `\[...\]`, rendered by MathJax in the card
+    #[default]
+    Mathjax,
+    /// `[$]...[/$]` / `[$$]...[/$$]` and `[latex]` for environments, rendered
+    /// to images by Anki's LaTeX pipeline
+    AnkiLatex,
+}
+
+/// How `view` opens rendered notes
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
+pub struct ViewerConfig {
+    /// Command opening the HTML file, e.g. "firefox" or "chromium --new-window %s"
+    /// (default: `$BROWSER`, then the platform's opener); like hooks, only
+    /// honoured from the user config or `--config`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub browser: Option<String>,
+    /// Keep the HTML file this long after the browser command returned
+    /// (default 500 ms; 0 for commands that only return once the page is loaded)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launch_delay_ms: Option<u64>,
+    /// Write rendered notes to `<output_dir>/<note id>.html` instead of a temp
+    /// directory, so re-viewing a note refreshes the same URL. Relative paths
+    /// are relative to the config file; `~/` is the home directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<PathBuf>,
+    /// Remove renderings in `output_dir` not viewed for this many days (default 30)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_days: Option<u64>,
+    /// Always view notes as with `view --sanitize`
+    #[serde(default)]
+    pub sanitize: bool,
+}
+
+/// Journal of the notes ankiview changes (see `ankiview history`)
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
+pub struct HistoryConfig {
+    /// Record every note `collect` creates or updates and `delete` removes
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// The `File: <path>` footer `collect` appends to every card
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
+pub struct FooterConfig {
+    /// URI template turning the path into a link back to the source, e.g.
+    /// "obsidian://open?path={abspath}" or "vscode://file{abspath}".
+    /// `{abspath}`, `{relpath}` (relative to the config file) and
+    /// `{filename}` are replaced, percent-encoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+/// Where `collect` keeps its hash cache
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
+pub struct CacheConfig {
+    /// "user" (default): per collection in the user cache directory;
+    /// "vault": in `.ankiview/` next to the config file, so the cache belongs
+    /// to the project; any other value is a directory (relative to the config
+    /// file, `~/` is the home directory)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+
+/// Card quality rules of `lint` and `collect --lint`
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct LintConfig {
+    /// Longest front in characters (default 200; 0: no limit)
+    #[serde(default = "default_max_front_length")]
+    pub max_front_length: usize,
+    /// Basic cards need an answer
+    #[serde(default = "default_true")]
+    pub empty_back: bool,
+    /// Images need alt text
+    #[serde(default = "default_true")]
+    pub image_alt: bool,
+    /// A cloze card needs text outside its deletions
+    #[serde(default = "default_true")]
+    pub whole_cloze: bool,
+    /// Words marking unfinished cards (default "TODO", "FIXME"; empty: off)
+    #[serde(default = "default_todo_markers")]
+    pub todo_markers: Vec<String>,
+    /// Spell checking of card text (`[lint.spell]`)
+    #[serde(default)]
+    pub spell: SpellConfig,
+}
+
+/// Spell checking of card text with hunspell, off by default
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct SpellConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Program run as `<command> -l -i UTF-8 -d <dictionary>` (default
+    /// "hunspell"); like hooks, only honoured from the user config or `--config`
+    #[serde(default = "default_spell_command")]
+    pub command: String,
+    /// Dictionary of cards without a more specific one (default "en_US")
+    #[serde(default = "default_spell_dictionary")]
+    pub dictionary: String,
+    /// Dictionary per deck, e.g. `"Languages::Spanish" = "es_ES"`; applies to
+    /// subdecks, the most specific deck wins
+    #[serde(default)]
+    pub decks: BTreeMap<String, String>,
+    /// Dictionary per section language (`Lang: de`), e.g. `de = "de_DE"`
+    #[serde(default)]
+    pub languages: BTreeMap<String, String>,
+    /// Words accepted in every dictionary (case-insensitive)
+    #[serde(default)]
+    pub words: Vec<String>,
+}
+
+/// How cards are written in markdown sections
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct CardsConfig {
+    /// Recognized card syntaxes: "numbered" (`1.` / `>`), "qa" (`Q:` / `A:`),
+    /// "heading" (`## Front` + body) and "heading-card" (only `## Front #card`)
+    #[serde(default = "default_card_formats")]
+    pub formats: Vec<CardFormat>,
+    /// Line opening a section, e.g. "<!--inka-start-->" (default "---")
+    #[serde(default = "default_section_delimiter")]
+    pub section_start: String,
+    /// Line closing a section, e.g. "<!--inka-end-->" (default "---")
+    #[serde(default = "default_section_delimiter")]
+    pub section_end: String,
+}
+
 // Default value functions
 fn default_profile() -> String {
     String::new()
@@ -76,6 +307,27 @@ fn default_cloze_field() -> String {
 fn default_highlight_style() -> String {
     "monokai".to_string()
 }
+fn default_card_formats() -> Vec<CardFormat> {
+    CardFormat::DEFAULT.to_vec()
+}
+fn default_section_delimiter() -> String {
+    SectionParser::DEFAULT_DELIMITER.to_string()
+}
+fn default_max_front_length() -> usize {
+    200
+}
+fn default_true() -> bool {
+    true
+}
+fn default_todo_markers() -> Vec<String> {
+    vec!["TODO".to_string(), "FIXME".to_string()]
+}
+fn default_spell_command() -> String {
+    "hunspell".to_string()
+}
+fn default_spell_dictionary() -> String {
+    "en_US".to_string()
+}
 
 impl Default for Defaults {
     fn default() -> Self {
@@ -83,6 +335,9 @@ impl Default for Defaults {
             profile: default_profile(),
             deck: default_deck(),
             folder: default_folder(),
+            deck_options: None,
+            tag_namespace: None,
+            collections: Vec::new(),
         }
     }
 }
@@ -100,6 +355,145 @@ impl Default for AnkiConfig {
     }
 }
 
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            max_front_length: default_max_front_length(),
+            empty_back: true,
+            image_alt: true,
+            whole_cloze: true,
+            todo_markers: default_todo_markers(),
+            spell: SpellConfig::default(),
+        }
+    }
+}
+
+impl Default for SpellConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            command: default_spell_command(),
+            dictionary: default_spell_dictionary(),
+            decks: BTreeMap::new(),
+            languages: BTreeMap::new(),
+            words: Vec::new(),
+        }
+    }
+}
+
+impl Default for CardsConfig {
+    fn default() -> Self {
+        Self {
+            formats: default_card_formats(),
+            section_start: default_section_delimiter(),
+            section_end: default_section_delimiter(),
+        }
+    }
+}
+
+/// Entry of `defaults.collections`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CollectionEntry {
+    Profile(String),
+    Path(PathBuf),
+}
+
+impl Defaults {
+    /// `collections` with entries that have a path separator or end in
+    /// `.anki2` as paths (`~/` expanded, relative paths resolved against the
+    /// directory of `config_file`) and the others as profile names
+    pub fn resolved_collections(&self, config_file: Option<&Path>) -> Vec<CollectionEntry> {
+        self.collections
+            .iter()
+            .map(|entry| {
+                if !entry.contains(['/', '\\']) && !entry.ends_with(".anki2") {
+                    return CollectionEntry::Profile(entry.clone());
+                }
+                let path = Path::new(entry);
+                CollectionEntry::Path(match path.strip_prefix("~") {
+                    Ok(rest) => dirs::home_dir().map_or(path.to_path_buf(), |home| home.join(rest)),
+                    Err(_) => match config_file.and_then(Path::parent) {
+                        Some(base) if path.is_relative() => base.join(path),
+                        _ => path.to_path_buf(),
+                    },
+                })
+            })
+            .collect()
+    }
+}
+
+impl CacheConfig {
+    /// Directory of the hash cache and history, and the root cached paths are
+    /// relative to: the config file's directory when the cache isn't the
+    /// user's, so it can be shared with the vault
+    pub fn resolved(&self, config_file: Option<&Path>) -> CacheLocation {
+        let dir = self.resolved_dir(config_file);
+        let root = dir.as_ref().map(|_| {
+            config_file
+                .and_then(Path::parent)
+                .unwrap_or(Path::new("."))
+                .to_path_buf()
+        });
+        CacheLocation { dir, root }
+    }
+
+    /// Directory of the hash cache, `None` for the user cache directory
+    pub fn resolved_dir(&self, config_file: Option<&Path>) -> Option<PathBuf> {
+        let base = config_file.and_then(Path::parent);
+        match self.location.as_deref().map(str::trim) {
+            None | Some("" | "user") => None,
+            Some("vault") => Some(base.unwrap_or(Path::new(".")).join(".ankiview")),
+            Some(location) => {
+                let dir = Path::new(location);
+                if let Ok(rest) = dir.strip_prefix("~") {
+                    return dirs::home_dir().map(|home| home.join(rest));
+                }
+                match base {
+                    Some(base) if dir.is_relative() => Some(base.join(dir)),
+                    _ => Some(dir.to_path_buf()),
+                }
+            }
+        }
+    }
+}
+
+impl SpellConfig {
+    /// Dictionary for cards of `deck` in a section with language `lang`:
+    /// the most specific matching deck, then the language, then `dictionary`
+    pub fn dictionary_for(&self, deck: Option<&str>, lang: Option<&str>) -> &str {
+        let by_deck = deck.and_then(|deck| {
+            self.decks
+                .iter()
+                .filter(|(name, _)| {
+                    deck == name.as_str()
+                        || deck
+                            .strip_prefix(name.as_str())
+                            .is_some_and(|rest| rest.starts_with("::"))
+                })
+                .max_by_key(|(name, _)| name.len())
+                .map(|(_, dictionary)| dictionary)
+        });
+        by_deck
+            .or_else(|| lang.and_then(|lang| self.languages.get(lang)))
+            .unwrap_or(&self.dictionary)
+    }
+}
+
+impl ViewerConfig {
+    /// `output_dir` with `~/` expanded and relative paths resolved against
+    /// the directory of `config_file`
+    pub fn resolved_output_dir(&self, config_file: Option<&Path>) -> Option<PathBuf> {
+        let dir = self.output_dir.as_deref()?;
+        if let Ok(rest) = dir.strip_prefix("~") {
+            return dirs::home_dir().map(|home| home.join(rest));
+        }
+        match config_file.and_then(Path::parent) {
+            Some(base) if dir.is_relative() => Some(base.join(dir)),
+            _ => Some(dir.to_path_buf()),
+        }
+    }
+}
+
 impl Default for HighlightConfig {
     fn default() -> Self {
         Self {
@@ -129,6 +523,88 @@ impl Config {
         Ok(())
     }
 
+    /// Load `explicit_path` if given. Otherwise load the user config (see
+    /// `user_config_file`) with the nearest `ankiview.toml` in `start` or one
+    /// of its parent directories on top: its settings override the user
+    /// config's, tables are merged key by key. Falls back to defaults.
+    /// Returns the config and the file it was loaded from, the `ankiview.toml`
+    /// if there is one.
+    ///
+    /// A discovered `ankiview.toml` comes with the vault, so settings that run
+    /// programs are taken from the user config instead (see `drop_untrusted`).
+    pub fn discover(explicit_path: Option<&Path>, start: &Path) -> Result<(Self, Option<PathBuf>)> {
+        Self::discover_in(explicit_path, start, user_config_file())
+    }
+
+    fn discover_in(
+        explicit_path: Option<&Path>,
+        start: &Path,
+        user_config: Option<PathBuf>,
+    ) -> Result<(Self, Option<PathBuf>)> {
+        if let Some(path) = explicit_path {
+            let config = Self::load(path)
+                .with_context(|| format!("Failed to load config: {}", path.display()))?;
+            return Ok((config, Some(path.to_path_buf())));
+        }
+
+        let Some(vault_config) = find_config_file(start) else {
+            return match user_config {
+                Some(path) => {
+                    let config = Self::load(&path)
+                        .with_context(|| format!("Failed to load config: {}", path.display()))?;
+                    Ok((config, Some(path)))
+                }
+                None => Ok((Self::default(), None)),
+            };
+        };
+
+        let (mut table, trusted) = match &user_config {
+            Some(path) => {
+                let table = load_table(path)?;
+                let trusted = toml::Value::Table(table.clone())
+                    .try_into()
+                    .with_context(|| format!("Failed to load config: {}", path.display()))?;
+                (table, trusted)
+            }
+            None => (toml::Table::new(), Self::default()),
+        };
+        merge_tables(&mut table, load_table(&vault_config)?);
+        let mut config: Config = toml::Value::Table(table)
+            .try_into()
+            .with_context(|| format!("Failed to load config: {}", vault_config.display()))?;
+        config.drop_untrusted(&vault_config, &trusted);
+        Ok((config, Some(vault_config)))
+    }
+
+    /// Put back the settings that run programs as `trusted` (the user config,
+    /// or the defaults without one) has them, where a vault's `ankiview.toml`
+    /// changed them for whoever collects the vault
+    fn drop_untrusted(&mut self, path: &Path, trusted: &Config) {
+        if self.hooks != trusted.hooks {
+            warn!(
+                config = %path.display(),
+                "Ignoring [hooks] of a discovered ankiview.toml; set them in the user config or pass --config"
+            );
+            self.hooks = trusted.hooks.clone();
+        }
+        if self.viewer.browser != trusted.viewer.browser {
+            warn!(
+                config = %path.display(),
+                browser = self.viewer.browser.as_deref().unwrap_or_default(),
+                "Ignoring viewer.browser of a discovered ankiview.toml; set it in the user config or pass --config"
+            );
+            self.viewer.browser = trusted.viewer.browser.clone();
+        }
+        if self.lint.spell.command != trusted.lint.spell.command {
+            warn!(
+                config = %path.display(),
+                command = %self.lint.spell.command,
+                "Ignoring lint.spell.command of a discovered ankiview.toml; set it in the user config or pass --config"
+            );
+            self.lint.spell.command = trusted.lint.spell.command.clone();
+        }
+    }
+
     /// Create default configuration file at path
     pub fn create_default(path: impl AsRef<Path>) -> Result<Self> {
         let config = Self::default();
@@ -137,6 +613,65 @@ impl Config {
     }
 }
 
+/// File whose `[hooks]` a config from `Config::discover` uses, to run them in
+/// its directory: the user config when a vault's `ankiview.toml` was
+/// discovered (see `Config::drop_untrusted`), otherwise the loaded file
+pub fn hooks_file(explicit_path: Option<&Path>, config_file: Option<&Path>) -> Option<PathBuf> {
+    match config_file {
+        Some(file)
+            if explicit_path.is_none() && file.file_name() == Some(CONFIG_FILE_NAME.as_ref()) =>
+        {
+            user_config_file()
+        }
+        file => file.map(Path::to_path_buf),
+    }
+}
+
+/// Read a config file as a TOML table, for layering it with another
+fn load_table(path: &Path) -> Result<toml::Table> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    toml::from_str(&content)
+        .with_context(|| format!("Failed to parse TOML config: {}", path.display()))
+}
+
+/// Put `overlay` on top of `base`: tables are merged key by key, any other
+/// value of `overlay` replaces the one in `base`
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                merge_tables(base, overlay)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// `ankiview/config.toml` in the user config directory (`$XDG_CONFIG_HOME`,
+/// `~/.config`), if it exists
+pub fn user_config_file() -> Option<PathBuf> {
+    crate::util::xdg::config_home()
+        .map(|dir| dir.join("ankiview").join(USER_CONFIG_FILE_NAME))
+        .filter(|path| path.is_file())
+}
+
+/// Find `ankiview.toml` in `start` (or its directory, if `start` is a file)
+/// or the closest parent directory that has one
+pub fn find_config_file(start: &Path) -> Option<PathBuf> {
+    let start = std::path::absolute(start).ok()?;
+    let dir = if start.is_file() {
+        start.parent()?
+    } else {
+        start.as_path()
+    };
+    dir.ancestors()
+        .map(|ancestor| ancestor.join(CONFIG_FILE_NAME))
+        .find(|candidate| candidate.is_file())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -220,6 +755,7 @@ deck = "MyDeck"
 
         // Specified value
         assert_eq!(config.defaults.deck, "MyDeck");
+        assert_eq!(config.defaults.deck_options, None);
         // Default values
         assert_eq!(config.defaults.profile, "");
         assert_eq!(config.anki.basic_type, "Inka Basic");
@@ -243,6 +779,9 @@ deck = "MyDeck"
                 profile: "Test Profile".to_string(),
                 deck: "Test Deck".to_string(),
                 folder: "/test/folder".to_string(),
+                deck_options: Some("Slow".to_string()),
+                tag_namespace: Some("inka".to_string()),
+                collections: vec!["Work".to_string()],
             },
             anki: AnkiConfig {
                 path: "/test/collection.anki2".to_string(),
@@ -251,6 +790,7 @@ deck = "MyDeck"
             highlight: HighlightConfig {
                 style: "nord".to_string(),
             },
+            ..Default::default()
         };
 
         original.save(&config_path).unwrap();
@@ -258,4 +798,318 @@ deck = "MyDeck"
 
         assert_eq!(loaded, original);
     }
+
+    #[test]
+    fn given_relative_output_dir_when_resolving_then_uses_config_file_directory() {
+        let viewer = ViewerConfig {
+            output_dir: Some(PathBuf::from("rendered")),
+            ..Default::default()
+        };
+
+        let resolved = viewer.resolved_output_dir(Some(Path::new("/vault/ankiview.toml")));
+
+        assert_eq!(resolved, Some(PathBuf::from("/vault/rendered")));
+        assert_eq!(ViewerConfig::default().resolved_output_dir(None), None);
+    }
+
+    #[test]
+    fn given_cache_locations_when_resolving_then_picks_directory() {
+        let config_file = Some(Path::new("/vault/ankiview.toml"));
+        let location = |location: &str| CacheConfig {
+            location: Some(location.to_string()),
+        };
+
+        assert_eq!(CacheConfig::default().resolved_dir(config_file), None);
+        assert_eq!(location("user").resolved_dir(config_file), None);
+        assert_eq!(
+            location("vault").resolved_dir(config_file),
+            Some(PathBuf::from("/vault/.ankiview"))
+        );
+        assert_eq!(
+            location("build/cache").resolved_dir(config_file),
+            Some(PathBuf::from("/vault/build/cache"))
+        );
+        assert_eq!(
+            location("vault").resolved(config_file).root,
+            Some(PathBuf::from("/vault"))
+        );
+        assert_eq!(
+            CacheConfig::default().resolved(config_file),
+            CacheLocation::default()
+        );
+    }
+
+    #[test]
+    fn given_collections_when_resolving_then_tells_profiles_from_paths() {
+        let defaults: Defaults = toml::from_str(
+            r#"collections = ["Work", "Personal/collection.anki2", "/anki/Old/collection.anki2"]"#,
+        )
+        .unwrap();
+
+        let resolved = defaults.resolved_collections(Some(Path::new("/vault/ankiview.toml")));
+
+        assert_eq!(
+            resolved,
+            vec![
+                CollectionEntry::Profile("Work".to_string()),
+                CollectionEntry::Path(PathBuf::from("/vault/Personal/collection.anki2")),
+                CollectionEntry::Path(PathBuf::from("/anki/Old/collection.anki2")),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_hooks_section_when_loading_then_reads_commands() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("hooks.toml");
+        fs::write(&config_path, "[hooks]\npost_collect = \"./sync.sh\"\n").unwrap();
+
+        let config = Config::load(&config_path).unwrap();
+
+        assert_eq!(config.hooks.post_collect.as_deref(), Some("./sync.sh"));
+        assert_eq!(config.hooks.pre_collect, None);
+    }
+
+    #[test]
+    fn given_cards_section_when_loading_then_reads_formats() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("cards.toml");
+        fs::write(&config_path, "[cards]\nformats = [\"numbered\", \"qa\"]\n").unwrap();
+
+        let config = Config::load(&config_path).unwrap();
+
+        assert_eq!(
+            config.cards.formats,
+            vec![CardFormat::Numbered, CardFormat::Qa]
+        );
+        assert_eq!(Config::default().cards.formats, vec![CardFormat::Numbered]);
+    }
+
+    #[test]
+    fn given_markdown_section_when_loading_then_reads_options() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("markdown.toml");
+        fs::write(&config_path, "[markdown]\nsmart_punctuation = true\n").unwrap();
+
+        let config = Config::load(&config_path).unwrap();
+
+        assert!(config.markdown.smart_punctuation);
+        assert!(!config.markdown.emoji);
+    }
+
+    #[test]
+    fn given_math_mode_when_loading_then_reads_it() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("math.toml");
+        fs::write(&config_path, "[math]\nmode = \"anki-latex\"\n").unwrap();
+
+        let config = Config::load(&config_path).unwrap();
+
+        assert_eq!(config.math.mode, MathMode::AnkiLatex);
+        assert_eq!(Config::default().math.mode, MathMode::Mathjax);
+    }
+
+    #[test]
+    fn given_math_macros_and_delimiters_when_loading_then_reads_them() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("math.toml");
+        fs::write(
+            &config_path,
+            "[math]\ninline_delimiters = [[\"$\", \"$\"]]\n\n[math.macros]\nR = '\\mathbb{R}'\nnorm = '\\lVert #1 \\rVert'\n",
+        )
+        .unwrap();
+
+        let config = Config::load(&config_path).unwrap();
+
+        assert_eq!(config.math.macros["R"], r"\mathbb{R}");
+        assert_eq!(config.math.macros["norm"], r"\lVert #1 \rVert");
+        assert_eq!(
+            config.math.inline_delimiters,
+            vec![["$".to_string(), "$".to_string()]]
+        );
+        assert!(config.math.display_delimiters.is_empty());
+    }
+
+    #[test]
+    fn given_spell_dictionaries_when_resolving_then_prefers_deck_then_language() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("spell.toml");
+        fs::write(
+            &config_path,
+            "[lint.spell]\nenabled = true\n\n[lint.spell.decks]\nLanguages = \"en_GB\"\n\"Languages::Spanish\" = \"es_ES\"\n\n[lint.spell.languages]\nde = \"de_DE\"\n",
+        )
+        .unwrap();
+
+        let spell = Config::load(&config_path).unwrap().lint.spell;
+
+        assert!(spell.enabled);
+        assert_eq!(spell.command, "hunspell");
+        assert_eq!(
+            spell.dictionary_for(Some("Languages::Spanish::Verbs"), Some("de")),
+            "es_ES"
+        );
+        assert_eq!(
+            spell.dictionary_for(Some("Languages::French"), None),
+            "en_GB"
+        );
+        assert_eq!(
+            spell.dictionary_for(Some("LanguagesOld"), Some("de")),
+            "de_DE"
+        );
+        assert_eq!(spell.dictionary_for(None, None), "en_US");
+    }
+
+    #[test]
+    fn given_section_delimiters_when_loading_then_reads_them() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("cards.toml");
+        fs::write(
+            &config_path,
+            "[cards]\nsection_start = \"<!--inka-start-->\"\nsection_end = \"<!--inka-end-->\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load(&config_path).unwrap();
+
+        assert_eq!(config.cards.section_start, "<!--inka-start-->");
+        assert_eq!(config.cards.section_end, "<!--inka-end-->");
+        assert_eq!(config.cards.formats, vec![CardFormat::Numbered]);
+        assert_eq!(Config::default().cards.section_start, "---");
+    }
+
+    #[test]
+    fn given_config_in_parent_dir_when_discovering_from_subdir_then_loads_it() {
+        let temp_dir = TempDir::new().unwrap();
+        let subdir = temp_dir.path().join("notes/rust");
+        fs::create_dir_all(&subdir).unwrap();
+        let note = subdir.join("card.md");
+        fs::write(&note, "").unwrap();
+        fs::write(
+            temp_dir.path().join(CONFIG_FILE_NAME),
+            "[defaults]\ndeck = \"Vault\"\n",
+        )
+        .unwrap();
+
+        let (from_dir, path) = Config::discover(None, &subdir).unwrap();
+        let (from_file, _) = Config::discover(None, &note).unwrap();
+
+        assert_eq!(from_dir.defaults.deck, "Vault");
+        assert_eq!(from_file.defaults.deck, "Vault");
+        assert_eq!(path, Some(temp_dir.path().join(CONFIG_FILE_NAME)));
+    }
+
+    #[test]
+    fn given_no_config_file_when_discovering_then_uses_defaults() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let (config, path) = Config::discover_in(None, temp_dir.path(), None).unwrap();
+
+        assert_eq!(config.defaults.deck, "Default");
+        assert_eq!(path, None);
+    }
+
+    #[test]
+    fn given_commands_in_discovered_config_when_discovering_then_ignores_them() {
+        let temp_dir = TempDir::new().unwrap();
+        let vault = temp_dir.path().join("vault");
+        fs::create_dir(&vault).unwrap();
+        let config_path = vault.join(CONFIG_FILE_NAME);
+        fs::write(
+            &config_path,
+            "[hooks]\npre_collect = \"./run.sh\"\n[viewer]\nbrowser = \"./open.sh\"\n\
+             [lint.spell]\ncommand = \"./check.sh\"\n",
+        )
+        .unwrap();
+        let user_config = temp_dir.path().join(USER_CONFIG_FILE_NAME);
+        fs::copy(&config_path, &user_config).unwrap();
+
+        let (discovered, _) = Config::discover_in(None, &vault, None).unwrap();
+        let (explicit, _) = Config::discover_in(Some(&config_path), &vault, None).unwrap();
+        let (user, _) = Config::discover_in(None, temp_dir.path(), Some(user_config)).unwrap();
+
+        assert_eq!(discovered.hooks, HooksConfig::default());
+        assert_eq!(discovered.viewer.browser, None);
+        assert_eq!(explicit.viewer.browser.as_deref(), Some("./open.sh"));
+        assert_eq!(discovered.lint.spell.command, "hunspell");
+        assert_eq!(explicit.lint.spell.command, "./check.sh");
+        assert_eq!(explicit.hooks.pre_collect.as_deref(), Some("./run.sh"));
+        assert_eq!(user.hooks.pre_collect, explicit.hooks.pre_collect);
+    }
+
+    #[test]
+    fn given_only_user_config_when_discovering_then_loads_it() {
+        let temp_dir = TempDir::new().unwrap();
+        let vault = temp_dir.path().join("vault");
+        fs::create_dir(&vault).unwrap();
+        let user_config = temp_dir.path().join(USER_CONFIG_FILE_NAME);
+        fs::write(&user_config, "[defaults]\ndeck = \"Personal\"\n").unwrap();
+
+        let (config, path) = Config::discover_in(None, &vault, Some(user_config.clone())).unwrap();
+        fs::write(
+            vault.join(CONFIG_FILE_NAME),
+            "[defaults]\ndeck = \"Vault\"\n",
+        )
+        .unwrap();
+        let (nearer, _) = Config::discover_in(None, &vault, Some(user_config.clone())).unwrap();
+
+        assert_eq!(config.defaults.deck, "Personal");
+        assert_eq!(path, Some(user_config));
+        assert_eq!(nearer.defaults.deck, "Vault");
+    }
+
+    #[test]
+    fn given_user_and_vault_config_when_discovering_then_layers_vault_over_user() {
+        let temp_dir = TempDir::new().unwrap();
+        let vault = temp_dir.path().join("vault");
+        fs::create_dir(&vault).unwrap();
+        let user_config = temp_dir.path().join(USER_CONFIG_FILE_NAME);
+        fs::write(
+            &user_config,
+            "[defaults]\ndeck = \"Personal\"\ntag_namespace = \"inka\"\n\
+             [highlight]\nstyle = \"nord\"\n",
+        )
+        .unwrap();
+        let vault_config = vault.join(CONFIG_FILE_NAME);
+        fs::write(&vault_config, "[defaults]\ndeck = \"Vault\"\n").unwrap();
+        let explicit = temp_dir.path().join("explicit.toml");
+        fs::write(&explicit, "[defaults]\ndeck = \"Explicit\"\n").unwrap();
+
+        let (config, path) = Config::discover_in(None, &vault, Some(user_config.clone())).unwrap();
+        let (explicit, _) =
+            Config::discover_in(Some(&explicit), &vault, Some(user_config)).unwrap();
+
+        assert_eq!(config.defaults.deck, "Vault");
+        assert_eq!(config.defaults.tag_namespace.as_deref(), Some("inka"));
+        assert_eq!(config.highlight.style, "nord");
+        assert_eq!(path, Some(vault_config));
+        assert_eq!(explicit.defaults.deck, "Explicit");
+        assert_eq!(explicit.highlight.style, "monokai");
+    }
+
+    #[test]
+    fn given_user_hooks_and_vault_config_when_discovering_then_keeps_user_commands() {
+        let temp_dir = TempDir::new().unwrap();
+        let vault = temp_dir.path().join("vault");
+        fs::create_dir(&vault).unwrap();
+        let user_config = temp_dir.path().join(USER_CONFIG_FILE_NAME);
+        fs::write(
+            &user_config,
+            "[hooks]\npost_collect = \"./sync.sh\"\n[viewer]\nbrowser = \"firefox\"\n",
+        )
+        .unwrap();
+        fs::write(
+            vault.join(CONFIG_FILE_NAME),
+            "[defaults]\ndeck = \"Vault\"\n[hooks]\npre_collect = \"./run.sh\"\n\
+             [viewer]\nbrowser = \"./open.sh\"\n[lint.spell]\ncommand = \"./check.sh\"\n",
+        )
+        .unwrap();
+
+        let (config, _) = Config::discover_in(None, &vault, Some(user_config)).unwrap();
+
+        assert_eq!(config.defaults.deck, "Vault");
+        assert_eq!(config.hooks.post_collect.as_deref(), Some("./sync.sh"));
+        assert_eq!(config.hooks.pre_collect, None);
+        assert_eq!(config.viewer.browser.as_deref(), Some("firefox"));
+        assert_eq!(config.lint.spell.command, "hunspell");
+    }
 }
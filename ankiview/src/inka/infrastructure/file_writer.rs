@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use encoding_rs::{Encoding, UTF_8};
 use std::path::Path;
 
 use crate::constants::{ID_SEARCH_RANGE_AFTER, ID_SEARCH_RANGE_BEFORE};
@@ -13,9 +14,67 @@ pub fn strip_id_comment(note_str: &str) -> String {
         .join("\n")
 }
 
+/// How a markdown file was stored on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceEncoding {
+    pub encoding: &'static Encoding,
+    /// File started with a byte order mark
+    pub bom: bool,
+}
+
+impl Default for SourceEncoding {
+    fn default() -> Self {
+        Self {
+            encoding: UTF_8,
+            bom: false,
+        }
+    }
+}
+
 /// Read markdown file content
+/// Files that aren't UTF-8 (e.g. Windows-1252) are decoded to UTF-8
 pub fn read_markdown_file(path: impl AsRef<Path>) -> Result<String> {
-    std::fs::read_to_string(path.as_ref()).context("Failed to read markdown file")
+    read_markdown_file_with_encoding(path).map(|(content, _)| content)
+}
+
+/// Read markdown file content and the encoding it is stored in
+///
+/// A byte order mark wins; otherwise valid UTF-8 is taken as UTF-8 and
+/// anything else is guessed with chardetng.
+pub fn read_markdown_file_with_encoding(
+    path: impl AsRef<Path>,
+) -> Result<(String, SourceEncoding)> {
+    let bytes = std::fs::read(path.as_ref()).context("Failed to read markdown file")?;
+
+    let source = detect_encoding(&bytes);
+    let (content, had_errors) = source.encoding.decode_with_bom_removal(&bytes);
+    if had_errors {
+        anyhow::bail!(
+            "Failed to read markdown file: not valid {}",
+            source.encoding.name()
+        );
+    }
+
+    Ok((content.into_owned(), source))
+}
+
+fn detect_encoding(bytes: &[u8]) -> SourceEncoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return SourceEncoding {
+            encoding,
+            bom: true,
+        };
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return SourceEncoding::default();
+    }
+
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(bytes, true);
+    SourceEncoding {
+        encoding: detector.guess(None, true),
+        bom: false,
+    }
 }
 
 /// Write markdown content to file
@@ -23,6 +82,34 @@ pub fn write_markdown_file(path: impl AsRef<Path>, content: &str) -> Result<()>
     std::fs::write(path.as_ref(), content).context("Failed to write markdown file")
 }
 
+/// Write markdown content back in the encoding it was read with
+///
+/// Falls back to UTF-8 when the content can't be represented in that encoding
+/// (or it's UTF-16, which can't be encoded). Returns false in that case.
+pub fn write_markdown_file_with_encoding(
+    path: impl AsRef<Path>,
+    content: &str,
+    source: SourceEncoding,
+) -> Result<bool> {
+    if source.encoding == UTF_8 {
+        let mut bytes = Vec::with_capacity(content.len() + 3);
+        if source.bom {
+            bytes.extend_from_slice(b"\xEF\xBB\xBF");
+        }
+        bytes.extend_from_slice(content.as_bytes());
+        std::fs::write(path.as_ref(), bytes).context("Failed to write markdown file")?;
+        return Ok(true);
+    }
+
+    let (bytes, used_encoding, had_errors) = source.encoding.encode(content);
+    if had_errors || used_encoding != source.encoding {
+        write_markdown_file(path, content)?;
+        return Ok(false);
+    }
+    std::fs::write(path.as_ref(), bytes).context("Failed to write markdown file")?;
+    Ok(true)
+}
+
 /// Inject Anki ID before a note in markdown content
 /// If the note already has an ID, returns content unchanged
 pub fn inject_anki_id(content: &str, note_pattern: &str, anki_id: i64) -> String {
@@ -109,6 +196,53 @@ pub fn replace_anki_id(content: &str, note_pattern: &str, new_id: i64) -> String
     result
 }
 
+/// Byte offset of the start of 1-based `line` in `content`; the end of
+/// `content` if it has fewer lines
+pub fn line_offset(content: &str, line: usize) -> usize {
+    if line <= 1 {
+        return 0;
+    }
+    content
+        .match_indices('\n')
+        .nth(line - 2)
+        .map_or(content.len(), |(pos, _)| pos + 1)
+}
+
+/// Inject an Anki ID before the note whose first line starts at byte
+/// `note_pos`. Unlike [`inject_anki_id`] this targets one card even if an
+/// identical card comes earlier in the file. If the note already starts
+/// with an ID comment, returns content unchanged
+pub fn inject_anki_id_at(content: &str, note_pos: usize, anki_id: i64) -> String {
+    if content[note_pos..].trim_start().starts_with("<!--ID:") {
+        return content.to_string();
+    }
+    let id_comment = format!("<!--ID:{}-->\n", anki_id);
+    let mut result = String::with_capacity(content.len() + id_comment.len());
+    result.push_str(&content[..note_pos]);
+    result.push_str(&id_comment);
+    result.push_str(&content[note_pos..]);
+    result
+}
+
+/// Replace the ID comment of the note whose first line (its ID comment)
+/// starts at byte `note_pos`; injects one if the note has none
+pub fn replace_anki_id_at(content: &str, note_pos: usize, new_id: i64) -> String {
+    let rest = &content[note_pos..];
+    let id_line = rest.trim_start_matches([' ', '\t']);
+    if !id_line.starts_with("<!--ID:") {
+        return inject_anki_id_at(content, note_pos, new_id);
+    }
+    let Some(id_end) = id_line.find("-->").map(|end| end + "-->".len()) else {
+        return inject_anki_id_at(content, note_pos, new_id);
+    };
+    let id_start = note_pos + rest.len() - id_line.len();
+    let mut result = String::with_capacity(content.len());
+    result.push_str(&content[..id_start]);
+    result.push_str(&format!("<!--ID:{}-->", new_id));
+    result.push_str(&content[id_start + id_end..]);
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -130,6 +264,64 @@ mod tests {
         assert_eq!(result, content);
     }
 
+    #[test]
+    fn given_windows_1252_file_when_reading_then_decodes_to_utf8() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("legacy.md");
+        // "Café – naïve" in Windows-1252
+        fs::write(&file_path, b"1. Caf\xe9 \x96 na\xefve?\n> Oui\n").unwrap();
+
+        let (content, source) = read_markdown_file_with_encoding(&file_path).unwrap();
+
+        assert_eq!(content, "1. Café – naïve?\n> Oui\n");
+        assert_eq!(source.encoding, encoding_rs::WINDOWS_1252);
+    }
+
+    #[test]
+    fn given_legacy_encoding_when_writing_back_then_preserves_it() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("legacy.md");
+        fs::write(&file_path, b"1. Caf\xe9?\n> Oui\n").unwrap();
+        let (content, source) = read_markdown_file_with_encoding(&file_path).unwrap();
+
+        let updated = format!("<!--ID:123-->\n{}", content);
+        let preserved = write_markdown_file_with_encoding(&file_path, &updated, source).unwrap();
+
+        assert!(preserved);
+        assert_eq!(
+            fs::read(&file_path).unwrap(),
+            b"<!--ID:123-->\n1. Caf\xe9?\n> Oui\n"
+        );
+    }
+
+    #[test]
+    fn given_unencodable_content_when_writing_back_then_falls_back_to_utf8() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("legacy.md");
+        let source = SourceEncoding {
+            encoding: encoding_rs::WINDOWS_1252,
+            bom: false,
+        };
+
+        let preserved = write_markdown_file_with_encoding(&file_path, "日本語", source).unwrap();
+
+        assert!(!preserved);
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "日本語");
+    }
+
+    #[test]
+    fn given_utf8_bom_when_round_tripping_then_keeps_bom() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("bom.md");
+        fs::write(&file_path, b"\xEF\xBB\xBF# Title").unwrap();
+
+        let (content, source) = read_markdown_file_with_encoding(&file_path).unwrap();
+        write_markdown_file_with_encoding(&file_path, &content, source).unwrap();
+
+        assert_eq!(content, "# Title");
+        assert_eq!(fs::read(&file_path).unwrap(), b"\xEF\xBB\xBF# Title");
+    }
+
     #[test]
     fn given_file_with_ids_when_reading_then_preserves_ids() {
         let temp_dir = TempDir::new().unwrap();
@@ -221,6 +413,32 @@ Deck: Test
         );
     }
 
+    #[test]
+    fn given_identical_notes_when_injecting_at_offset_then_targets_that_note() {
+        let content = "1. Question?\n> Answer\n\n1. Question?\n> Answer\n";
+
+        let second = line_offset(content, 4);
+        let result = inject_anki_id_at(content, second, 2222222222);
+        let result = inject_anki_id_at(&result, 0, 1111111111);
+
+        assert_eq!(
+            result,
+            "<!--ID:1111111111-->\n1. Question?\n> Answer\n\n<!--ID:2222222222-->\n1. Question?\n> Answer\n"
+        );
+        assert_eq!(inject_anki_id_at(&result, 0, 3333333333), result);
+    }
+
+    #[test]
+    fn given_identical_notes_with_ids_when_replacing_at_offset_then_replaces_that_id() {
+        let content = "<!--ID:1-->\n1. Q\n> A\n\n<!--ID:2-->\n1. Q\n> A";
+
+        let result = replace_anki_id_at(content, line_offset(content, 5), 3);
+
+        assert_eq!(result, "<!--ID:1-->\n1. Q\n> A\n\n<!--ID:3-->\n1. Q\n> A");
+        assert_eq!(line_offset(content, 1), 0);
+        assert_eq!(line_offset(content, 99), content.len());
+    }
+
     #[test]
     fn given_content_when_writing_then_creates_file() {
         let temp_dir = TempDir::new().unwrap();
@@ -1,16 +1,21 @@
 use anyhow::{Context, Result};
+use rayon::prelude::*;
+use rusqlite::{params, Connection, OpenFlags};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
 
 /// Calculate SHA256 hash of a file's content
+///
+/// The file is streamed through the hasher as raw bytes, so it doesn't have
+/// to be valid UTF-8 and is never held in memory as a whole.
 pub fn calculate_file_hash(path: impl AsRef<Path>) -> Result<String> {
-    let content =
-        std::fs::read_to_string(path.as_ref()).context("Failed to read file for hashing")?;
+    let mut file = std::fs::File::open(path.as_ref()).context("Failed to read file for hashing")?;
 
     let mut hasher = Sha256::new();
-    hasher.update(content.as_bytes());
+    std::io::copy(&mut file, &mut hasher).context("Failed to read file for hashing")?;
     let result = hasher.finalize();
 
     // Convert to lowercase hex string
@@ -24,77 +29,524 @@ pub fn has_file_changed(path: impl AsRef<Path>, previous_hash: &str) -> Result<b
 }
 
 /// Hash cache for tracking file changes
-/// Stores filepath -> hash mapping in a JSON file
+/// Stores filepath -> hash mapping in an SQLite database, or in a JSON file
+/// if the cache path ends in `.json`
+///
+/// With SQLite, `save` only writes entries changed since loading, so large
+/// vaults don't rewrite the whole cache on every run.
+///
+/// Files are recognized by content: a file that isn't cached under its path
+/// but has the hash of an entry whose file is gone counts as moved, not new,
+/// and takes over that entry on `update_hash`.
+///
+/// With a root (see [`HashCache::with_root`]) files below it are cached by
+/// their path relative to it, so a cache kept in the vault works from any
+/// checkout and working directory.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HashCache {
     cache_path: std::path::PathBuf,
+    #[serde(skip)]
+    root: Option<PathBuf>,
     hashes: HashMap<String, String>,
+    #[serde(skip)]
+    dirty: HashSet<String>,
+    #[serde(skip)]
+    removed: HashSet<String>,
+    #[serde(skip)]
+    cleared: bool,
 }
 
 impl HashCache {
     /// Load hash cache from file, or create empty cache if file doesn't exist
+    ///
+    /// An SQLite cache that doesn't exist yet is seeded from a legacy JSON cache
+    /// with the same file stem (e.g. `ankiview_hashes.json`), if present.
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
         let cache_path = path.as_ref().to_path_buf();
 
-        let hashes = if cache_path.exists() {
-            let content =
-                std::fs::read_to_string(&cache_path).context("Failed to read hash cache file")?;
-            serde_json::from_str(&content).context("Failed to parse hash cache JSON")?
+        let mut cache = Self {
+            root: None,
+            hashes: HashMap::new(),
+            dirty: HashSet::new(),
+            removed: HashSet::new(),
+            cleared: false,
+            cache_path,
+        };
+
+        if cache.is_json() {
+            if cache.cache_path.exists() {
+                cache.hashes = read_json(&cache.cache_path)?;
+            }
+        } else if cache.cache_path.exists() {
+            cache.hashes = read_db(&open_db(&cache.cache_path)?)?;
+        } else {
+            let legacy_path = cache.cache_path.with_extension("json");
+            if legacy_path.exists() {
+                cache.hashes = read_json(&legacy_path)?;
+                cache.dirty = cache.hashes.keys().cloned().collect();
+                info!(
+                    from = %legacy_path.display(),
+                    to = %cache.cache_path.display(),
+                    entries = cache.hashes.len(),
+                    "Migrating JSON hash cache"
+                );
+            }
+        }
+
+        Ok(cache)
+    }
+
+    /// Load the cache at `path` without writing anything: no table is
+    /// created and no legacy cache migrated. `None` if there is no cache
+    pub fn load_existing(path: impl AsRef<Path>) -> Result<Option<Self>> {
+        let cache_path = path.as_ref().to_path_buf();
+        if !cache_path.exists() {
+            return Ok(None);
+        }
+        let mut cache = Self {
+            root: None,
+            hashes: HashMap::new(),
+            dirty: HashSet::new(),
+            removed: HashSet::new(),
+            cleared: false,
+            cache_path,
+        };
+        cache.hashes = if cache.is_json() {
+            read_json(&cache.cache_path)?
         } else {
-            HashMap::new()
+            let conn = Connection::open_with_flags(
+                &cache.cache_path,
+                OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            )
+            .with_context(|| {
+                format!("Failed to open hash cache: {}", cache.cache_path.display())
+            })?;
+            read_db(&conn)?
         };
+        Ok(Some(cache))
+    }
 
-        Ok(Self { cache_path, hashes })
+    /// Cache files below `root` by their path relative to it
+    pub fn with_root(mut self, root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref();
+        self.root = Some(std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf()));
+        self
+    }
+
+    /// Key of a file: its path relative to the root with `/` separators if
+    /// it is below the root, otherwise the path as given
+    fn key(&self, filepath: &Path) -> Result<String> {
+        let relative = self.root.as_deref().and_then(|root| {
+            let absolute = std::path::absolute(filepath).ok()?;
+            let relative = absolute.strip_prefix(root).ok()?;
+            relative
+                .components()
+                .map(|component| component.as_os_str().to_str())
+                .collect::<Option<Vec<_>>>()
+                .map(|components| components.join("/"))
+        });
+        match relative {
+            Some(key) => Ok(key),
+            None => filepath
+                .to_str()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow::anyhow!("Invalid file path")),
+        }
+    }
+
+    /// File a cached path stands for
+    pub fn file_path(&self, key: &str) -> PathBuf {
+        match &self.root {
+            Some(root) => root.join(key),
+            None => PathBuf::from(key),
+        }
     }
 
     /// Save hash cache to file
-    pub fn save(&self) -> Result<()> {
+    ///
+    /// Does nothing if no entry changed since the last load or save.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.cleared
+            && self.dirty.is_empty()
+            && self.removed.is_empty()
+            && self.cache_path.exists()
+        {
+            return Ok(());
+        }
+
+        if self.is_json() {
+            self.export_json(&self.cache_path)?;
+            self.dirty.clear();
+            self.removed.clear();
+            self.cleared = false;
+            return Ok(());
+        }
+
+        let mut conn = open_db(&self.cache_path)?;
+        let tx = conn
+            .transaction()
+            .context("Failed to write hash cache file")?;
+        if self.cleared {
+            tx.execute("DELETE FROM file_hashes", [])
+                .context("Failed to write hash cache file")?;
+        }
+        {
+            let mut stmt = tx
+                .prepare("DELETE FROM file_hashes WHERE path = ?1")
+                .context("Failed to write hash cache file")?;
+            for path in &self.removed {
+                stmt.execute(params![path])
+                    .context("Failed to write hash cache file")?;
+            }
+        }
+        {
+            let mut stmt = tx
+                .prepare("INSERT OR REPLACE INTO file_hashes (path, hash) VALUES (?1, ?2)")
+                .context("Failed to write hash cache file")?;
+            for path in &self.dirty {
+                if let Some(hash) = self.hashes.get(path) {
+                    stmt.execute(params![path, hash])
+                        .context("Failed to write hash cache file")?;
+                }
+            }
+        }
+        tx.commit().context("Failed to write hash cache file")?;
+        debug!(
+            entries = self.dirty.len(),
+            removed = self.removed.len(),
+            "Saved hash cache"
+        );
+        self.dirty.clear();
+        self.removed.clear();
+        self.cleared = false;
+
+        Ok(())
+    }
+
+    /// Write all cached hashes as a JSON object (path -> hash) to `path`
+    pub fn export_json(&self, path: impl AsRef<Path>) -> Result<()> {
         let json =
             serde_json::to_string_pretty(&self.hashes).context("Failed to serialize hash cache")?;
 
-        std::fs::write(&self.cache_path, json).context("Failed to write hash cache file")?;
+        std::fs::write(path.as_ref(), json).context("Failed to write hash cache file")?;
 
         Ok(())
     }
 
+    fn is_json(&self) -> bool {
+        self.cache_path.extension().and_then(|e| e.to_str()) == Some("json")
+    }
+
     /// Check if file has changed compared to cached hash
-    /// Returns true if file is new or content has changed
+    /// Returns true if file is new or content has changed; a moved file with
+    /// unchanged content is not changed
     pub fn file_has_changed(&self, filepath: impl AsRef<Path>) -> Result<bool> {
-        let path_str = filepath
-            .as_ref()
-            .to_str()
-            .ok_or_else(|| anyhow::anyhow!("Invalid file path"))?
-            .to_string();
+        let path_str = self.key(filepath.as_ref())?;
 
-        // If not in cache, it's a new file (changed)
         let Some(cached_hash) = self.hashes.get(&path_str) else {
-            return Ok(true);
+            // Not cached under this path: new, unless it was moved here
+            let hash = calculate_file_hash(filepath)?;
+            return Ok(self.moved_from(&hash).is_none());
         };
 
         // Compare current hash with cached hash
         has_file_changed(filepath, cached_hash)
     }
 
+    /// Whether the cache has an entry for the file's path
+    pub fn contains(&self, filepath: impl AsRef<Path>) -> bool {
+        self.key(filepath.as_ref())
+            .is_ok_and(|key| self.hashes.contains_key(&key))
+    }
+
+    /// Cached path with content `hash` whose file no longer exists, i.e. the
+    /// path a file with that content was moved away from
+    fn moved_from(&self, hash: &str) -> Option<&str> {
+        self.hashes
+            .iter()
+            .find(|(path, cached)| *cached == hash && !self.file_path(path).exists())
+            .map(|(path, _)| path.as_str())
+    }
+
+    /// Check many files against the cache, hashing them in parallel
+    ///
+    /// Returns the change status of every file that could be checked;
+    /// files that failed (e.g. unreadable) are left out so the caller can
+    /// report them when processing the file individually.
+    pub fn changed_files(&self, filepaths: &[PathBuf]) -> HashMap<PathBuf, bool> {
+        filepaths
+            .par_iter()
+            .filter_map(|path| {
+                self.file_has_changed(path)
+                    .ok()
+                    .map(|changed| (path.clone(), changed))
+            })
+            .collect()
+    }
+
     /// Update hash for a file in the cache
     pub fn update_hash(&mut self, filepath: impl AsRef<Path>) -> Result<()> {
-        let path_str = filepath
-            .as_ref()
-            .to_str()
-            .ok_or_else(|| anyhow::anyhow!("Invalid file path"))?
-            .to_string();
+        let path_str = self.key(filepath.as_ref())?;
 
         let hash = calculate_file_hash(filepath)?;
-        self.hashes.insert(path_str, hash);
+        if !self.hashes.contains_key(&path_str) {
+            if let Some(old_path) = self.moved_from(&hash).map(str::to_string) {
+                debug!(from = %old_path, to = %path_str, "Hash cache entry follows moved file");
+                self.remove(&old_path);
+            }
+        }
+        if self.hashes.get(&path_str) != Some(&hash) {
+            self.hashes.insert(path_str.clone(), hash);
+            self.removed.remove(&path_str);
+            self.dirty.insert(path_str);
+        }
 
         Ok(())
     }
 
+    /// Remove the entries of files that no longer exist; returns their paths
+    pub fn prune(&mut self) -> Vec<String> {
+        let mut stale: Vec<String> = self
+            .hashes
+            .keys()
+            .filter(|path| !self.file_path(path).exists())
+            .cloned()
+            .collect();
+        stale.sort();
+        for path in &stale {
+            self.remove(path);
+        }
+        stale
+    }
+
+    /// Cached files and their hashes, sorted by path
+    pub fn entries(&self) -> Vec<(&str, &str)> {
+        let mut entries: Vec<(&str, &str)> = self
+            .hashes
+            .iter()
+            .map(|(path, hash)| (path.as_str(), hash.as_str()))
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    /// Number of cached files
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    fn remove(&mut self, path: &str) {
+        self.hashes.remove(path);
+        self.dirty.remove(path);
+        self.removed.insert(path.to_string());
+    }
+
     /// Clear all hashes from cache
     pub fn clear(&mut self) {
         self.hashes.clear();
+        self.dirty.clear();
+        self.removed.clear();
+        self.cleared = true;
+    }
+}
+
+/// Cache file older versions kept next to collection.anki2
+const LEGACY_CACHE_FILE: &str = "ankiview_hashes.db";
+
+/// Hash cache of a collection: `ankiview/<profile>-<key>.db` in the user cache
+/// directory (`$XDG_CACHE_HOME`, `~/.cache`), keyed by the
+/// collection's absolute path. Next to the collection if there is no cache
+/// directory
+pub fn cache_path_for(collection_path: &Path) -> PathBuf {
+    cache_path_in(
+        crate::util::xdg::cache_home()
+            .map(|dir| dir.join("ankiview"))
+            .as_deref(),
+        collection_path,
+    )
+}
+
+/// Hash cache of a collection in a configured directory (`cache.location`):
+/// `<dir>/<profile>.db`, without the machine-specific key of
+/// [`cache_path_for`] so the cache can be shared along with the directory.
+/// [`cache_path_for`] if `dir` is `None`
+pub fn cache_path_at(dir: Option<&Path>, collection_path: &Path) -> PathBuf {
+    match dir {
+        Some(dir) => dir.join(format!("{}.db", profile_name(collection_path))),
+        None => cache_path_for(collection_path),
+    }
+}
+
+/// Where a collection's hash cache and history are kept (`cache.location`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheLocation {
+    /// Directory of the cache; `None` for the user cache directory
+    pub dir: Option<PathBuf>,
+    /// Directory cached paths are relative to (the vault); `None` to cache
+    /// paths as given
+    pub root: Option<PathBuf>,
+}
+
+impl CacheLocation {
+    /// Hash cache file of a collection, see [`cache_path_at`]
+    pub fn cache_path(&self, collection_path: &Path) -> PathBuf {
+        cache_path_at(self.dir.as_deref(), collection_path)
+    }
+
+    /// Load the hash cache of a collection, creating its directory and moving
+    /// a legacy cache from the profile directory to the user cache directory
+    pub fn open(&self, collection_path: &Path) -> Result<HashCache> {
+        let cache_path = self.cache_path(collection_path);
+        if self.dir.is_none() {
+            migrate_legacy_cache(collection_path, &cache_path)
+                .context("Failed to move hash cache")?;
+        }
+        if let Some(parent) = cache_path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create cache directory")?;
+        }
+        let cache = HashCache::load(&cache_path).context("Failed to load hash cache")?;
+        Ok(self.rooted(cache))
+    }
+
+    /// Load the hash cache of a collection read-only, see
+    /// [`HashCache::load_existing`]; `None` if there is none
+    pub fn open_existing(&self, collection_path: &Path) -> Result<Option<HashCache>> {
+        let cache = HashCache::load_existing(self.cache_path(collection_path))
+            .context("Failed to load hash cache")?;
+        Ok(cache.map(|cache| self.rooted(cache)))
+    }
+
+    fn rooted(&self, cache: HashCache) -> HashCache {
+        match &self.root {
+            Some(root) => cache.with_root(root),
+            None => cache,
+        }
     }
 }
 
+fn cache_path_in(cache_dir: Option<&Path>, collection_path: &Path) -> PathBuf {
+    let collection_dir = collection_path.parent().unwrap_or(Path::new("."));
+    let Some(cache_dir) = cache_dir else {
+        return collection_dir.join(LEGACY_CACHE_FILE);
+    };
+    let absolute =
+        std::path::absolute(collection_path).unwrap_or_else(|_| collection_path.to_path_buf());
+    let key = format!(
+        "{:x}",
+        Sha256::digest(absolute.to_string_lossy().as_bytes())
+    );
+    cache_dir.join(format!(
+        "{}-{}.db",
+        profile_name(collection_path),
+        &key[..12]
+    ))
+}
+
+/// Name of the profile directory holding the collection
+fn profile_name(collection_path: &Path) -> String {
+    collection_path
+        .parent()
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "collection".to_string())
+}
+
+/// Move the cache older versions kept next to the collection
+/// (`ankiview_hashes.db`, or the even older `ankiview_hashes.json`) to
+/// `cache_path`, unless there is a cache there already
+pub fn migrate_legacy_cache(collection_path: &Path, cache_path: &Path) -> Result<()> {
+    let Some(collection_dir) = collection_path.parent() else {
+        return Ok(());
+    };
+    let legacy_db = collection_dir.join(LEGACY_CACHE_FILE);
+    let legacy_json = legacy_db.with_extension("json");
+    if cache_path.exists() || cache_path == legacy_db {
+        return Ok(());
+    }
+    if !legacy_db.exists() && !legacy_json.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = cache_path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    if legacy_db.exists() {
+        // SQLite's write-ahead log and shared memory files hold changes not
+        // yet in the database file; they move along under the new name
+        for suffix in ["", "-wal", "-shm"] {
+            let from = with_suffix(&legacy_db, suffix);
+            if suffix.is_empty() || from.exists() {
+                move_file(&from, &with_suffix(cache_path, suffix))?;
+            }
+        }
+    } else {
+        let mut cache = HashCache::load(cache_path)?;
+        cache.hashes = read_json(&legacy_json)?;
+        cache.dirty = cache.hashes.keys().cloned().collect();
+        cache.save()?;
+        std::fs::remove_file(&legacy_json)
+            .with_context(|| format!("Failed to remove {}", legacy_json.display()))?;
+    }
+    info!(
+        to = %cache_path.display(),
+        "Moved hash cache out of the Anki profile directory"
+    );
+    Ok(())
+}
+
+/// `path` with `suffix` appended to its file name, e.g. `hashes.db-wal`
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    // Across file systems rename fails; copy and remove instead
+    if std::fs::rename(from, to).is_err() {
+        std::fs::copy(from, to)
+            .with_context(|| format!("Failed to move hash cache to {}", to.display()))?;
+        std::fs::remove_file(from)
+            .with_context(|| format!("Failed to remove {}", from.display()))?;
+    }
+    Ok(())
+}
+
+fn read_db(conn: &Connection) -> Result<HashMap<String, String>> {
+    let mut stmt = conn
+        .prepare("SELECT path, hash FROM file_hashes")
+        .context("Failed to read hash cache")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .context("Failed to read hash cache")?;
+    rows.collect::<rusqlite::Result<_>>()
+        .context("Failed to read hash cache")
+}
+
+fn read_json(path: &Path) -> Result<HashMap<String, String>> {
+    let content = std::fs::read_to_string(path).context("Failed to read hash cache file")?;
+    serde_json::from_str(&content).context("Failed to parse hash cache JSON")
+}
+
+/// Open (creating if needed) the SQLite hash cache
+fn open_db(path: &Path) -> Result<Connection> {
+    let conn = Connection::open(path)
+        .with_context(|| format!("Failed to open hash cache: {}", path.display()))?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS file_hashes (
+            path TEXT PRIMARY KEY NOT NULL,
+            hash TEXT NOT NULL
+        ) WITHOUT ROWID;",
+    )
+    .context("Failed to initialize hash cache")?;
+    Ok(conn)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -145,6 +597,18 @@ mod tests {
         assert_ne!(hash1, hash2);
     }
 
+    #[test]
+    fn given_non_utf8_file_when_calculating_hash_then_hashes_raw_bytes() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("latin1.md");
+        fs::write(&file_path, b"caf\xe9").unwrap();
+
+        let hash = calculate_file_hash(&file_path).unwrap();
+
+        let expected = format!("{:x}", Sha256::digest(b"caf\xe9"));
+        assert_eq!(hash, expected);
+    }
+
     #[test]
     fn given_nonexistent_file_when_calculating_hash_then_returns_error() {
         let result = calculate_file_hash("/nonexistent/file.md");
@@ -223,7 +687,7 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let cache_path = temp_dir.path().join("cache.json");
 
-        let cache = HashCache::load(&cache_path).unwrap();
+        let mut cache = HashCache::load(&cache_path).unwrap();
         cache.save().unwrap();
 
         assert!(cache_path.exists());
@@ -318,4 +782,317 @@ mod tests {
         assert!(!cache.file_has_changed(&file1).unwrap());
         assert!(!cache.file_has_changed(&file2).unwrap());
     }
+
+    #[test]
+    fn given_mixed_files_when_checking_in_parallel_then_reports_each_and_skips_unreadable() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_path = temp_dir.path().join("cache.json");
+        let unchanged = temp_dir.path().join("unchanged.md");
+        let new_file = temp_dir.path().join("new.md");
+        let missing = temp_dir.path().join("missing.md");
+        fs::write(&unchanged, "Stable").unwrap();
+        fs::write(&new_file, "Fresh").unwrap();
+
+        let mut cache = HashCache::load(&cache_path).unwrap();
+        cache.update_hash(&unchanged).unwrap();
+        // Cached but deleted since: hashing fails
+        fs::write(&missing, "Gone").unwrap();
+        cache.update_hash(&missing).unwrap();
+        fs::remove_file(&missing).unwrap();
+
+        let changed = cache.changed_files(&[unchanged.clone(), new_file.clone(), missing.clone()]);
+
+        assert_eq!(changed.get(&unchanged), Some(&false));
+        assert_eq!(changed.get(&new_file), Some(&true));
+        assert!(!changed.contains_key(&missing));
+    }
+
+    #[test]
+    fn given_sqlite_cache_when_saving_and_reloading_then_keeps_hashes() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_path = temp_dir.path().join("hashes.db");
+        let file_path = temp_dir.path().join("stable.md");
+        fs::write(&file_path, "Stable content").unwrap();
+
+        let mut cache = HashCache::load(&cache_path).unwrap();
+        cache.update_hash(&file_path).unwrap();
+        cache.save().unwrap();
+
+        let cache = HashCache::load(&cache_path).unwrap();
+        assert_eq!(cache.hashes.len(), 1);
+        assert!(!cache.file_has_changed(&file_path).unwrap());
+    }
+
+    #[test]
+    fn given_sqlite_cache_when_saving_incrementally_then_keeps_untouched_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_path = temp_dir.path().join("hashes.db");
+        let file1 = temp_dir.path().join("file1.md");
+        let file2 = temp_dir.path().join("file2.md");
+        fs::write(&file1, "Content 1").unwrap();
+        fs::write(&file2, "Content 2").unwrap();
+
+        let mut cache = HashCache::load(&cache_path).unwrap();
+        cache.update_hash(&file1).unwrap();
+        cache.save().unwrap();
+
+        // Second run only touches file2
+        let mut cache = HashCache::load(&cache_path).unwrap();
+        cache.update_hash(&file2).unwrap();
+        assert_eq!(cache.dirty.len(), 1);
+        cache.save().unwrap();
+
+        let cache = HashCache::load(&cache_path).unwrap();
+        assert_eq!(cache.hashes.len(), 2);
+    }
+
+    #[test]
+    fn given_cleared_sqlite_cache_when_saving_then_removes_all_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_path = temp_dir.path().join("hashes.db");
+        let file_path = temp_dir.path().join("file.md");
+        fs::write(&file_path, "Content").unwrap();
+
+        let mut cache = HashCache::load(&cache_path).unwrap();
+        cache.update_hash(&file_path).unwrap();
+        cache.save().unwrap();
+
+        let mut cache = HashCache::load(&cache_path).unwrap();
+        cache.clear();
+        cache.save().unwrap();
+
+        let cache = HashCache::load(&cache_path).unwrap();
+        assert_eq!(cache.hashes.len(), 0);
+    }
+
+    #[test]
+    fn given_legacy_json_cache_when_loading_sqlite_cache_then_migrates_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let json_path = temp_dir.path().join("ankiview_hashes.json");
+        let db_path = temp_dir.path().join("ankiview_hashes.db");
+        let file_path = temp_dir.path().join("file.md");
+        fs::write(&file_path, "Content").unwrap();
+
+        let mut legacy = HashCache::load(&json_path).unwrap();
+        legacy.update_hash(&file_path).unwrap();
+        legacy.save().unwrap();
+
+        let mut cache = HashCache::load(&db_path).unwrap();
+        cache.save().unwrap();
+
+        let cache = HashCache::load(&db_path).unwrap();
+        assert!(!cache.file_has_changed(&file_path).unwrap());
+    }
+
+    #[test]
+    fn given_sqlite_cache_when_exporting_json_then_writes_path_hash_map() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_path = temp_dir.path().join("hashes.db");
+        let export_path = temp_dir.path().join("export.json");
+        let file_path = temp_dir.path().join("file.md");
+        fs::write(&file_path, "Content").unwrap();
+
+        let mut cache = HashCache::load(&cache_path).unwrap();
+        cache.update_hash(&file_path).unwrap();
+        cache.export_json(&export_path).unwrap();
+
+        let exported: HashMap<String, String> =
+            serde_json::from_str(&fs::read_to_string(&export_path).unwrap()).unwrap();
+        assert_eq!(
+            exported.get(file_path.to_str().unwrap()),
+            Some(&calculate_file_hash(&file_path).unwrap())
+        );
+    }
+
+    #[test]
+    fn given_missing_cache_when_loading_existing_then_none_and_nothing_created() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_path = temp_dir.path().join("cache").join("hashes.db");
+
+        assert!(HashCache::load_existing(&cache_path).unwrap().is_none());
+        assert!(!temp_dir.path().join("cache").exists());
+    }
+
+    #[test]
+    fn given_saved_cache_when_loading_existing_then_reads_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_path = temp_dir.path().join("hashes.db");
+        let file_path = temp_dir.path().join("notes.md");
+        fs::write(&file_path, "Content").unwrap();
+        let mut cache = HashCache::load(&cache_path).unwrap();
+        cache.update_hash(&file_path).unwrap();
+        cache.save().unwrap();
+
+        let cache = HashCache::load_existing(&cache_path).unwrap().unwrap();
+
+        assert!(cache.contains(&file_path));
+        assert!(!cache.file_has_changed(&file_path).unwrap());
+    }
+
+    #[test]
+    fn given_root_when_caching_then_keys_by_relative_path_from_any_checkout() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_path = temp_dir.path().join("hashes.db");
+        let vault = temp_dir.path().join("vault");
+        fs::create_dir_all(vault.join("notes")).unwrap();
+        fs::write(vault.join("notes/rust.md"), "Content").unwrap();
+
+        let mut cache = HashCache::load(&cache_path).unwrap().with_root(&vault);
+        cache.update_hash(vault.join("notes/rust.md")).unwrap();
+        cache.save().unwrap();
+        assert_eq!(cache.entries()[0].0, "notes/rust.md");
+
+        // The same vault checked out elsewhere
+        let checkout = temp_dir.path().join("checkout");
+        fs::rename(&vault, &checkout).unwrap();
+        let mut cache = HashCache::load(&cache_path).unwrap().with_root(&checkout);
+        assert!(!cache
+            .file_has_changed(checkout.join("notes/rust.md"))
+            .unwrap());
+        assert!(cache.prune().is_empty());
+    }
+
+    #[test]
+    fn given_moved_file_when_checking_then_unchanged_and_entry_follows_it() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_path = temp_dir.path().join("hashes.db");
+        let old_path = temp_dir.path().join("old.md");
+        let new_path = temp_dir.path().join("new.md");
+        fs::write(&old_path, "Stable content").unwrap();
+
+        let mut cache = HashCache::load(&cache_path).unwrap();
+        cache.update_hash(&old_path).unwrap();
+        cache.save().unwrap();
+        fs::rename(&old_path, &new_path).unwrap();
+
+        let mut cache = HashCache::load(&cache_path).unwrap();
+        assert!(!cache.file_has_changed(&new_path).unwrap());
+        cache.update_hash(&new_path).unwrap();
+        cache.save().unwrap();
+
+        let cache = HashCache::load(&cache_path).unwrap();
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains(&new_path));
+        assert!(!cache.contains(&old_path));
+    }
+
+    #[test]
+    fn given_copied_file_when_checking_then_returns_changed() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_path = temp_dir.path().join("hashes.db");
+        let original = temp_dir.path().join("original.md");
+        let copy = temp_dir.path().join("copy.md");
+        fs::write(&original, "Same content").unwrap();
+        fs::write(&copy, "Same content").unwrap();
+
+        let mut cache = HashCache::load(&cache_path).unwrap();
+        cache.update_hash(&original).unwrap();
+
+        // The original still exists, so the copy is a new file
+        assert!(cache.file_has_changed(&copy).unwrap());
+    }
+
+    #[test]
+    fn given_deleted_files_when_pruning_then_removes_their_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_path = temp_dir.path().join("hashes.db");
+        let kept = temp_dir.path().join("kept.md");
+        let deleted = temp_dir.path().join("deleted.md");
+        fs::write(&kept, "Kept").unwrap();
+        fs::write(&deleted, "Deleted").unwrap();
+
+        let mut cache = HashCache::load(&cache_path).unwrap();
+        cache.update_hash(&kept).unwrap();
+        cache.update_hash(&deleted).unwrap();
+        cache.save().unwrap();
+        fs::remove_file(&deleted).unwrap();
+
+        let mut cache = HashCache::load(&cache_path).unwrap();
+        let pruned = cache.prune();
+        cache.save().unwrap();
+
+        assert_eq!(pruned, vec![deleted.to_str().unwrap().to_string()]);
+        let cache = HashCache::load(&cache_path).unwrap();
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains(&kept));
+    }
+
+    #[test]
+    fn given_collection_when_choosing_cache_path_then_keys_by_profile_and_path() {
+        let cache_dir = Path::new("/cache/ankiview");
+
+        let work = cache_path_in(Some(cache_dir), Path::new("/anki/Work/collection.anki2"));
+        let other = cache_path_in(Some(cache_dir), Path::new("/backup/Work/collection.anki2"));
+        let fallback = cache_path_in(None, Path::new("/anki/Work/collection.anki2"));
+
+        assert_eq!(work.parent(), Some(cache_dir));
+        let name = work.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("Work-") && name.ends_with(".db"));
+        assert_ne!(work, other);
+        assert_eq!(fallback, Path::new("/anki/Work/ankiview_hashes.db"));
+    }
+
+    #[test]
+    fn given_configured_directory_when_choosing_cache_path_then_names_by_profile() {
+        let collection = Path::new("/anki/Work/collection.anki2");
+
+        assert_eq!(
+            cache_path_at(Some(Path::new("/vault/.ankiview")), collection),
+            Path::new("/vault/.ankiview/Work.db")
+        );
+        assert_eq!(cache_path_at(None, collection), cache_path_for(collection));
+    }
+
+    #[test]
+    fn given_legacy_caches_when_migrating_then_moves_them_to_cache_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("stable.md");
+        fs::write(&file_path, "Stable content").unwrap();
+        for (profile, legacy) in [
+            ("Db", "ankiview_hashes.db"),
+            ("Json", "ankiview_hashes.json"),
+        ] {
+            let profile = temp_dir.path().join(profile);
+            fs::create_dir(&profile).unwrap();
+            let mut legacy_cache = HashCache::load(profile.join(legacy)).unwrap();
+            legacy_cache.update_hash(&file_path).unwrap();
+            legacy_cache.save().unwrap();
+            let collection = profile.join("collection.anki2");
+            let cache_path = cache_path_in(Some(&temp_dir.path().join("cache")), &collection);
+
+            migrate_legacy_cache(&collection, &cache_path).unwrap();
+
+            assert!(!profile.join(legacy).exists());
+            let cache = HashCache::load(&cache_path).unwrap();
+            assert!(!cache.file_has_changed(&file_path).unwrap());
+        }
+    }
+
+    #[test]
+    fn given_legacy_cache_with_wal_files_when_migrating_then_moves_them_along() {
+        let temp_dir = TempDir::new().unwrap();
+        let profile = temp_dir.path().join("User 1");
+        fs::create_dir(&profile).unwrap();
+        HashCache::load(profile.join(LEGACY_CACHE_FILE))
+            .unwrap()
+            .save()
+            .unwrap();
+        fs::write(profile.join("ankiview_hashes.db-wal"), "wal").unwrap();
+        fs::write(profile.join("ankiview_hashes.db-shm"), "shm").unwrap();
+        let cache_path = temp_dir.path().join("cache").join("User 1.db");
+
+        migrate_legacy_cache(&profile.join("collection.anki2"), &cache_path).unwrap();
+
+        assert!(cache_path.exists());
+        assert_eq!(
+            fs::read_to_string(with_suffix(&cache_path, "-wal")).unwrap(),
+            "wal"
+        );
+        assert_eq!(
+            fs::read_to_string(with_suffix(&cache_path, "-shm")).unwrap(),
+            "shm"
+        );
+        assert!(!profile.join("ankiview_hashes.db-wal").exists());
+        assert!(!profile.join("ankiview_hashes.db-shm").exists());
+    }
 }
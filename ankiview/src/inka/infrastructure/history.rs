@@ -0,0 +1,204 @@
+use crate::inka::infrastructure::hasher;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// What ankiview did to a note
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl HistoryAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HistoryAction::Created => "created",
+            HistoryAction::Updated => "updated",
+            HistoryAction::Deleted => "deleted",
+        }
+    }
+}
+
+/// One line of the history journal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Unix seconds
+    pub timestamp: i64,
+    pub action: HistoryAction,
+    pub note_id: i64,
+    /// Markdown file the change came from (none for `delete`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// [`content_hash`] of the note before the change (none when created)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    /// [`content_hash`] of the note after the change (none when deleted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Entry stamped with the current time
+    pub fn now(action: HistoryAction, note_id: i64) -> Self {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or_default();
+        Self {
+            timestamp,
+            action,
+            note_id,
+            file: None,
+            before: None,
+            after: None,
+        }
+    }
+}
+
+/// Append-only JSON Lines journal of the notes ankiview created, updated and
+/// deleted in a collection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Journal of a collection, next to its hash cache in `location`
+    /// (`<profile>-<key>.history.jsonl`, or `<profile>.history.jsonl` in a
+    /// configured directory)
+    pub fn for_collection(collection_path: &Path, location: &hasher::CacheLocation) -> Self {
+        Self::new(
+            location
+                .cache_path(collection_path)
+                .with_extension("history.jsonl"),
+        )
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append `entry` as one line, creating the journal if needed
+    pub fn append(&self, entry: &HistoryEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open history: {}", self.path.display()))?;
+        let line = serde_json::to_string(entry).context("Failed to serialize history entry")?;
+        writeln!(file, "{}", line).context("Failed to write history entry")
+    }
+
+    /// Entries of one note, oldest first; empty if there is no journal yet
+    pub fn entries_for(&self, note_id: i64) -> Result<Vec<HistoryEntry>> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to open history: {}", self.path.display()))
+            }
+        };
+        let mut entries = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.context("Failed to read history")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: HistoryEntry = serde_json::from_str(&line).with_context(|| {
+                format!(
+                    "Invalid history entry at {}:{}",
+                    self.path.display(),
+                    index + 1
+                )
+            })?;
+            if entry.note_id == note_id {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Short hash of a note's fields and tags, to tell whether two journal
+/// entries saw the same content
+pub fn content_hash(fields: &[String], tags: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    for field in fields {
+        hasher.update(field.as_bytes());
+        hasher.update([0x1f]);
+    }
+    hasher.update([0x1e]);
+    hasher.update(tags.join(" ").as_bytes());
+    format!("{:x}", hasher.finalize())[..16].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn given_entries_when_appending_then_reads_back_entries_of_one_note() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = History::new(temp_dir.path().join("cache/col.history.jsonl"));
+        let created = HistoryEntry {
+            file: Some("notes/rust.md".to_string()),
+            after: Some("aaaa".to_string()),
+            ..HistoryEntry::now(HistoryAction::Created, 1)
+        };
+        let deleted = HistoryEntry {
+            before: Some("aaaa".to_string()),
+            ..HistoryEntry::now(HistoryAction::Deleted, 1)
+        };
+
+        history.append(&created).unwrap();
+        history
+            .append(&HistoryEntry::now(HistoryAction::Created, 2))
+            .unwrap();
+        history.append(&deleted).unwrap();
+
+        assert_eq!(history.entries_for(1).unwrap(), vec![created, deleted]);
+        assert!(history.entries_for(3).unwrap().is_empty());
+        let content = std::fs::read_to_string(history.path()).unwrap();
+        assert!(content
+            .lines()
+            .next()
+            .unwrap()
+            .contains("\"action\":\"created\""));
+    }
+
+    #[test]
+    fn given_missing_journal_when_reading_then_returns_no_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = History::new(temp_dir.path().join("missing.jsonl"));
+
+        assert!(history.entries_for(1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn given_changed_tags_when_hashing_content_then_hash_differs() {
+        let fields = vec!["Q".to_string(), "A".to_string()];
+
+        let plain = content_hash(&fields, &[]);
+        let tagged = content_hash(&fields, &["rust".to_string()]);
+
+        assert_eq!(plain.len(), 16);
+        assert_ne!(plain, tagged);
+        assert_eq!(plain, content_hash(&fields, &[]));
+    }
+}
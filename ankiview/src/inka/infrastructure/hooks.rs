@@ -0,0 +1,70 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Command;
+use tracing::{debug, info};
+
+/// Run a hook command through the platform shell
+///
+/// `env` is added to the inherited environment; the hook's stdout/stderr go
+/// straight to the terminal. A non-zero exit status is an error.
+pub fn run_hook(name: &str, command: &str, env: &[(&str, String)], cwd: &Path) -> Result<()> {
+    info!(hook = name, command, "Running hook");
+
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell
+        .arg(command)
+        .current_dir(cwd)
+        .env("ANKIVIEW_HOOK", name);
+    for (key, value) in env {
+        shell.env(key, value);
+    }
+
+    let status = shell
+        .status()
+        .with_context(|| format!("Failed to start {} hook: {}", name, command))?;
+    debug!(hook = name, ?status, "Hook finished");
+
+    if !status.success() {
+        anyhow::bail!("{} hook failed ({}): {}", name, status, command);
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn given_hook_when_running_then_passes_environment() {
+        let temp_dir = TempDir::new().unwrap();
+        let out = temp_dir.path().join("out.txt");
+
+        run_hook(
+            "post_collect",
+            "echo \"$ANKIVIEW_HOOK $ANKIVIEW_CARDS\" > out.txt",
+            &[("ANKIVIEW_CARDS", "3".to_string())],
+            temp_dir.path(),
+        )
+        .unwrap();
+
+        assert_eq!(std::fs::read_to_string(out).unwrap(), "post_collect 3\n");
+    }
+
+    #[test]
+    fn given_failing_hook_when_running_then_errors_with_hook_name() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let err = run_hook("pre_collect", "exit 3", &[], temp_dir.path()).unwrap_err();
+
+        assert!(err.to_string().contains("pre_collect hook failed"));
+    }
+}
@@ -0,0 +1,303 @@
+use crate::inka::infrastructure::config::MediaConfig;
+use anyhow::{Context, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType as PngFilter, PngEncoder};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageReader};
+use resvg::{tiny_skia, usvg};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+use tracing::{debug, warn};
+
+const JPEG_QUALITY: u8 = 85;
+const MIN_JPEG_QUALITY: u8 = 55;
+// Stop downscaling for `max_bytes` below this width; the card would be useless
+const MIN_WIDTH: u32 = 64;
+
+// Loading system fonts is slow, so it happens once for all SVGs of a run
+static FONT_DB: LazyLock<Arc<usvg::fontdb::Database>> = LazyLock::new(|| {
+    let mut fontdb = usvg::fontdb::Database::new();
+    fontdb.load_system_fonts();
+    Arc::new(fontdb)
+});
+
+/// Content a media file gets in collection.media/: the file itself, or a
+/// downscaled/recompressed PNG or JPEG if it exceeds the `[media]` limits,
+/// or a PNG rendering of an SVG with `rasterize_svg`
+pub fn media_content(source_path: &Path, limits: &MediaConfig) -> Result<Vec<u8>> {
+    let mut bytes = std::fs::read(source_path)
+        .with_context(|| format!("Failed to read media file: {}", source_path.display()))?;
+
+    if rasterizes(source_path, limits) {
+        bytes = rasterize_svg(&bytes, limits.max_width)
+            .with_context(|| format!("Failed to rasterize SVG: {}", source_path.display()))?;
+    }
+    let Some(format) = resizable_format(&media_path(source_path, limits)) else {
+        return Ok(bytes);
+    };
+    match shrink_image(&bytes, format, limits) {
+        Ok(Some(shrunk)) => {
+            debug!(
+                path = %source_path.display(),
+                before = bytes.len(),
+                after = shrunk.len(),
+                "Shrunk image"
+            );
+            Ok(shrunk)
+        }
+        Ok(None) => Ok(bytes),
+        Err(e) => {
+            // Not decodable as its extension claims: copy it unchanged
+            warn!(path = %source_path.display(), error = %e, "Failed to shrink image");
+            Ok(bytes)
+        }
+    }
+}
+
+/// Path with the extension the file gets in collection.media/ (`.png` for
+/// rasterized SVGs)
+pub fn media_path(source_path: &Path, limits: &MediaConfig) -> PathBuf {
+    if rasterizes(source_path, limits) {
+        source_path.with_extension("png")
+    } else {
+        source_path.to_path_buf()
+    }
+}
+
+fn rasterizes(path: &Path, limits: &MediaConfig) -> bool {
+    limits.rasterize_svg
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("svg"))
+}
+
+/// Render an SVG to PNG at its own size, or narrower to fit `max_width`
+fn rasterize_svg(svg: &[u8], max_width: Option<u32>) -> Result<Vec<u8>> {
+    let options = usvg::Options {
+        fontdb: FONT_DB.clone(),
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_data(svg, &options).context("Failed to parse SVG")?;
+
+    let size = tree.size();
+    let scale = match max_width {
+        Some(max_width) if size.width() > max_width as f32 => max_width as f32 / size.width(),
+        _ => 1.0,
+    };
+    let width = (size.width() * scale).ceil() as u32;
+    let height = (size.height() * scale).ceil() as u32;
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| anyhow::anyhow!("Invalid SVG size {}x{}", width, height))?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    pixmap.encode_png().context("Failed to encode PNG")
+}
+
+/// Formats that are re-encoded; GIF/SVG/WebP etc. are always copied as-is
+fn resizable_format(path: &Path) -> Option<ImageFormat> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => Some(ImageFormat::Png),
+        "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+        _ => None,
+    }
+}
+
+/// Downscale to `max_width` and re-encode until the image fits `max_bytes`
+/// (lower JPEG quality first, then smaller dimensions)
+/// Returns `None` if the image is within limits already
+fn shrink_image(
+    bytes: &[u8],
+    format: ImageFormat,
+    limits: &MediaConfig,
+) -> Result<Option<Vec<u8>>> {
+    let exceeds_bytes = |len: usize| limits.max_bytes.is_some_and(|max| len as u64 > max);
+
+    let too_large = exceeds_bytes(bytes.len());
+    let too_wide = match limits.max_width {
+        Some(max_width) => {
+            let (width, _) = ImageReader::with_format(Cursor::new(bytes), format)
+                .into_dimensions()
+                .context("Failed to read image dimensions")?;
+            width > max_width
+        }
+        None => false,
+    };
+    if !too_wide && !too_large {
+        return Ok(None);
+    }
+
+    let mut image =
+        image::load_from_memory_with_format(bytes, format).context("Failed to decode image")?;
+    if let Some(max_width) = limits.max_width.filter(|_| too_wide) {
+        image = image.resize(max_width, u32::MAX, FilterType::Lanczos3);
+    }
+
+    let mut quality = JPEG_QUALITY;
+    let mut encoded = encode(&image, format, quality)?;
+    while exceeds_bytes(encoded.len()) && image.width() > MIN_WIDTH {
+        if format == ImageFormat::Jpeg && quality > MIN_JPEG_QUALITY {
+            quality -= 10;
+        } else {
+            image = image.resize(image.width() * 3 / 4, u32::MAX, FilterType::Lanczos3);
+        }
+        encoded = encode(&image, format, quality)?;
+    }
+
+    // Recompressing alone must not make the file bigger
+    if !too_wide && encoded.len() >= bytes.len() {
+        return Ok(None);
+    }
+    Ok(Some(encoded))
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat, quality: u8) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    match format {
+        ImageFormat::Jpeg => {
+            // JPEG has no alpha channel
+            DynamicImage::ImageRgb8(image.to_rgb8())
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut buffer, quality))
+        }
+        _ => image.write_with_encoder(PngEncoder::new_with_quality(
+            &mut buffer,
+            CompressionType::Best,
+            PngFilter::Adaptive,
+        )),
+    }
+    .context("Failed to encode image")?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+    use tempfile::TempDir;
+
+    fn write_image(path: &Path, width: u32, height: u32, noisy: bool) {
+        // Deterministic noise compresses badly, like a screenshot of a photo
+        let mut seed: u32 = 42;
+        let image = ImageBuffer::from_fn(width, height, |_, _| {
+            if !noisy {
+                return Rgb([200u8, 100, 50]);
+            }
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let [r, g, b, _] = seed.to_le_bytes();
+            Rgb([r, g, b])
+        });
+        image.save(path).unwrap();
+    }
+
+    #[test]
+    fn given_wide_png_when_max_width_set_then_downscales_keeping_aspect_ratio() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("shot.png");
+        write_image(&source, 800, 400, false);
+        let limits = MediaConfig {
+            max_width: Some(200),
+            ..Default::default()
+        };
+
+        let content = media_content(&source, &limits).unwrap();
+
+        let image = image::load_from_memory(&content).unwrap();
+        assert_eq!((image.width(), image.height()), (200, 100));
+    }
+
+    #[test]
+    fn given_large_jpeg_when_max_bytes_set_then_fits_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("photo.jpg");
+        write_image(&source, 400, 400, true);
+        let original_len = std::fs::metadata(&source).unwrap().len();
+        let limits = MediaConfig {
+            max_bytes: Some(original_len / 4),
+            ..Default::default()
+        };
+
+        let content = media_content(&source, &limits).unwrap();
+
+        assert!(content.len() as u64 <= original_len / 4);
+        assert_eq!(
+            image::guess_format(&content).unwrap(),
+            ImageFormat::Jpeg,
+            "format must match the file extension"
+        );
+    }
+
+    #[test]
+    fn given_image_within_limits_when_processing_then_returns_original_bytes() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("small.png");
+        write_image(&source, 100, 50, false);
+        let limits = MediaConfig {
+            max_width: Some(200),
+            max_bytes: Some(1_000_000),
+            ..Default::default()
+        };
+
+        let content = media_content(&source, &limits).unwrap();
+
+        assert_eq!(content, std::fs::read(&source).unwrap());
+    }
+
+    #[test]
+    fn given_svg_with_rasterize_when_processing_then_returns_png() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("diagram.svg");
+        std::fs::write(
+            &source,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="200"><rect width="400" height="200" fill="red"/></svg>"#,
+        )
+        .unwrap();
+        let limits = MediaConfig {
+            max_width: Some(100),
+            rasterize_svg: true,
+            ..Default::default()
+        };
+
+        let content = media_content(&source, &limits).unwrap();
+
+        assert_eq!(
+            media_path(&source, &limits),
+            temp_dir.path().join("diagram.png")
+        );
+        let image = image::load_from_memory_with_format(&content, ImageFormat::Png).unwrap();
+        assert_eq!((image.width(), image.height()), (100, 50));
+    }
+
+    #[test]
+    fn given_svg_without_rasterize_when_processing_then_copies_unchanged() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("diagram.svg");
+        std::fs::write(&source, "<svg/>").unwrap();
+
+        let content = media_content(&source, &MediaConfig::default()).unwrap();
+
+        assert_eq!(content, b"<svg/>");
+        assert_eq!(media_path(&source, &MediaConfig::default()), source);
+    }
+
+    #[test]
+    fn given_other_format_when_processing_then_copies_unchanged() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("anim.gif");
+        std::fs::write(&source, b"not really a gif, but large enough").unwrap();
+        let limits = MediaConfig {
+            max_width: Some(1),
+            max_bytes: Some(1),
+            ..Default::default()
+        };
+
+        let content = media_content(&source, &limits).unwrap();
+
+        assert_eq!(content, b"not really a gif, but large enough");
+    }
+}
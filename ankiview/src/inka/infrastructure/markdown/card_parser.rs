@@ -1,34 +1,60 @@
+use super::section_parser::CardFormat;
+use super::{cloze_converter, heading_parser};
 use anyhow::Result;
 use regex::Regex;
 use std::sync::LazyLock;
 
 static BASIC_CARD_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"(?m)(?:^<!--ID:\S+-->\n)?^\d+\.[\s\S]+?(?:^>.*?(?:\n|$))+")
+    Regex::new(r"(?m)(?:^<!--ID:\S+-->\n)?^\d+\.[\s\S]+?(?:^[ \t]*>.*?(?:\n|$))+")
         .expect("Failed to compile basic card regex")
 });
-static ID_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"(?m)^<!--ID:(\S+)-->$").expect("Failed to compile ID regex")
+static QA_CARD_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^[ \t]*Q:[\s\S]*?^[ \t]*A:").expect("Failed to compile Q/A card regex")
 });
+static ID_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^<!--ID:(\S+)-->$").expect("Failed to compile ID regex"));
+
+/// Whether the note is a basic card; the `Q:`/`A:` syntax only counts if
+/// `formats` includes [`CardFormat::Qa`]
+pub fn is_basic_card(note_str: &str, formats: &[CardFormat]) -> bool {
+    heading_parser::is_heading_note(note_str)
+        || BASIC_CARD_REGEX.is_match(note_str)
+        || (formats.contains(&CardFormat::Qa) && QA_CARD_REGEX.is_match(note_str))
+}
 
-pub fn is_basic_card(note_str: &str) -> bool {
-    BASIC_CARD_REGEX.is_match(note_str)
+/// First line that isn't an ID comment starts with `Q:`
+fn is_qa_note(note_str: &str) -> bool {
+    note_str
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.starts_with("<!--ID:"))
+        .is_some_and(|line| line.starts_with("Q:"))
 }
 
 pub fn is_cloze_card(note_str: &str) -> bool {
-    // A cloze card has curly braces (for cloze deletions)
-    // and doesn't have the answer marker (>)
-    note_str.contains('{')
+    // A cloze card has cloze deletions (not just escaped braces or braces in
+    // code) and doesn't have the answer marker (>)
+    cloze_converter::has_cloze(note_str)
         && !note_str
             .lines()
             .any(|line| line.trim_start().starts_with('>'))
 }
 
 pub fn parse_basic_card_fields(note_str: &str) -> Result<(String, String)> {
+    if heading_parser::is_heading_note(note_str) {
+        return heading_parser::parse_heading_card_fields(note_str);
+    }
+    if is_qa_note(note_str) {
+        return parse_qa_card_fields(note_str);
+    }
+
     // Find the first line with a number and dot
     let lines: Vec<&str> = note_str.lines().collect();
     let mut question_lines = Vec::new();
     let mut answer_lines = Vec::new();
     let mut in_answer = false;
+    // Inside a fenced code block of the question, where `>` starts no answer
+    let mut in_fence = false;
 
     for line in lines {
         let trimmed = line.trim();
@@ -38,8 +64,12 @@ pub fn parse_basic_card_fields(note_str: &str) -> Result<(String, String)> {
             continue;
         }
 
+        if !in_answer && is_fence(trimmed) {
+            in_fence = !in_fence;
+        }
+
         // Check if this is the start of an answer
-        if trimmed.starts_with('>') {
+        if trimmed.starts_with('>') && !in_fence {
             in_answer = true;
             answer_lines.push(line);
         } else if in_answer {
@@ -53,14 +83,9 @@ pub fn parse_basic_card_fields(note_str: &str) -> Result<(String, String)> {
 
     // Extract the question text (remove the "1. " prefix)
     let front = question_lines.join("\n");
-    let front = if let Some(stripped) = front.trim().strip_prefix(|c: char| c.is_ascii_digit()) {
-        if let Some(after_dot) = stripped.strip_prefix('.') {
-            after_dot.trim().to_string()
-        } else {
-            front
-        }
-    } else {
-        front
+    let front = match strip_card_number(front.trim()) {
+        Some(question) => question.trim().to_string(),
+        None => front,
     };
 
     if front.is_empty() {
@@ -78,22 +103,126 @@ pub fn parse_basic_card_fields(note_str: &str) -> Result<(String, String)> {
     Ok((front, back))
 }
 
+/// Fields of a basic card for a notetype with `field_count` fields: front,
+/// back, then one field per group of `>>` lines (e.g. "Extra" or "Source")
+///
+/// Groups are separated by a line that is just `>`; groups beyond the last
+/// field are joined into it. With two fields the `>>` lines stay part of the
+/// back, as nested quotes.
+pub fn parse_basic_card_field_list(note_str: &str, field_count: usize) -> Result<Vec<String>> {
+    if field_count <= 2 {
+        let (front, back) = parse_basic_card_fields(note_str)?;
+        return Ok(vec![front, back]);
+    }
+
+    let mut card_lines = Vec::new();
+    let mut groups: Vec<Vec<&str>> = Vec::new();
+    let mut in_group = false;
+    for line in note_str.lines() {
+        let trimmed = line.trim_start();
+        if let Some(content) = trimmed.strip_prefix(">>") {
+            if !in_group {
+                groups.push(Vec::new());
+                in_group = true;
+            }
+            let content = content.strip_prefix(' ').unwrap_or(content);
+            groups.last_mut().expect("group started").push(content);
+        } else if !groups.is_empty() && trimmed.trim_end() == ">" {
+            // Blank `>` marker: the next `>>` line starts a new group
+            in_group = false;
+        } else {
+            in_group = false;
+            card_lines.push(line);
+        }
+    }
+
+    let (front, back) = parse_basic_card_fields(&card_lines.join("\n"))?;
+    // A `>` separating the answer from the first group leaves a blank line
+    let mut fields = vec![front, back.trim_end().to_string()];
+    for group in groups {
+        let text = group.join("\n").trim().to_string();
+        if fields.len() < field_count {
+            fields.push(text);
+        } else {
+            let last = fields.last_mut().expect("at least front and back");
+            last.push_str("\n\n");
+            last.push_str(&text);
+        }
+    }
+    Ok(fields)
+}
+
+/// `Q: question` / `A: answer` card; both parts may span several lines
+fn parse_qa_card_fields(note_str: &str) -> Result<(String, String)> {
+    let mut question_lines = Vec::new();
+    let mut answer_lines = Vec::new();
+    let mut in_answer = false;
+
+    for line in note_str.lines() {
+        let trimmed = line.trim_start();
+
+        // Skip ID comments
+        if trimmed.starts_with("<!--ID:") {
+            continue;
+        }
+
+        if !in_answer {
+            if let Some(answer) = trimmed.strip_prefix("A:") {
+                in_answer = true;
+                answer_lines.push(answer.trim_start());
+            } else {
+                question_lines.push(trimmed.strip_prefix("Q:").unwrap_or(line));
+            }
+        } else {
+            answer_lines.push(line);
+        }
+    }
+
+    let front = question_lines.join("\n").trim().to_string();
+    if front.is_empty() {
+        anyhow::bail!("Failed to extract question from Q/A card");
+    }
+    let back = answer_lines.join("\n").trim().to_string();
+    if back.is_empty() {
+        anyhow::bail!("Failed to extract answer from Q/A card");
+    }
+
+    Ok((front, back))
+}
+
+/// Opening or closing line of a fenced code block (after the card number,
+/// for a question that starts with the fence)
+pub fn is_fence(trimmed: &str) -> bool {
+    let line = strip_card_number(trimmed).map_or(trimmed, str::trim_start);
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
 fn clean_answer(answer_raw: &str) -> String {
+    // Answer markers may be indented
+    fn unindent(line: &str) -> &str {
+        match line.trim_start_matches([' ', '\t']) {
+            quoted if quoted.starts_with('>') => quoted,
+            _ => line,
+        }
+    }
+    // Whether the first answer line with text has a space or tab after '>':
+    // stripping it from every line or from none keeps the indentation of
+    // nested lists relative to that line
+    let spaced = answer_raw
+        .lines()
+        .filter_map(|line| unindent(line).strip_prefix('>'))
+        .find(|text| !text.trim().is_empty())
+        .is_none_or(|text| text.starts_with([' ', '\t']));
+
     answer_raw
         .lines()
         .map(|line| {
-            // Remove '>' and first space/tab after it
-            if line.len() > 1 && line.starts_with('>') {
-                let without_prefix = &line[1..];
-                if without_prefix.starts_with(' ') || without_prefix.starts_with('\t') {
-                    &without_prefix[1..]
-                } else {
-                    without_prefix
-                }
-            } else if let Some(stripped) = line.strip_prefix('>') {
-                stripped
-            } else {
-                line
+            let line = unindent(line);
+            // Remove '>' and the space/tab after it
+            match line.strip_prefix('>') {
+                Some(text) if spaced => text.strip_prefix([' ', '\t']).unwrap_or(text),
+                Some(text) => text,
+                None => line,
             }
         })
         .collect::<Vec<_>>()
@@ -118,12 +247,10 @@ pub fn parse_cloze_card_field(note_str: &str) -> Result<String> {
 
     // Extract the text (remove the "1. " prefix)
     let text = text_lines.join("\n");
-    let text = if let Some(stripped) = text.trim().strip_prefix(|c: char| c.is_ascii_digit()) {
-        if let Some(after_dot) = stripped.strip_prefix('.') {
-            after_dot.trim().to_string()
-        } else {
-            text
-        }
+    let text = if let Some(question) = text.trim().strip_prefix("Q:") {
+        question.trim().to_string()
+    } else if let Some(stripped) = strip_card_number(text.trim()) {
+        stripped.trim().to_string()
     } else {
         text
     };
@@ -135,6 +262,16 @@ pub fn parse_cloze_card_field(note_str: &str) -> Result<String> {
     Ok(text)
 }
 
+/// Text after the card number of a numbered card (`12. Question` gives
+/// ` Question`), or None if `text` doesn't start with digits and a dot
+pub fn strip_card_number(text: &str) -> Option<&str> {
+    let rest = text.trim_start_matches(|c: char| c.is_ascii_digit());
+    if rest.len() == text.len() {
+        return None;
+    }
+    rest.strip_prefix('.')
+}
+
 pub fn extract_anki_id(note_str: &str) -> Option<i64> {
     ID_REGEX
         .captures(note_str)
@@ -145,12 +282,13 @@ pub fn extract_anki_id(note_str: &str) -> Option<i64> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::inka::infrastructure::markdown::converter;
 
     #[test]
     fn given_note_with_answer_when_checking_type_then_is_basic() {
         let note_str = "1. Question?\n> Answer!";
 
-        assert!(is_basic_card(note_str));
+        assert!(is_basic_card(note_str, CardFormat::DEFAULT));
         assert!(!is_cloze_card(note_str));
     }
 
@@ -158,14 +296,14 @@ mod tests {
     fn given_note_with_multiline_answer_when_checking_then_is_basic() {
         let note_str = "1. Q\n> Line 1\n> Line 2\n> Line 3";
 
-        assert!(is_basic_card(note_str));
+        assert!(is_basic_card(note_str, CardFormat::DEFAULT));
     }
 
     #[test]
     fn given_note_without_answer_when_checking_then_not_basic() {
         let note_str = "1. Just a question?";
 
-        assert!(!is_basic_card(note_str));
+        assert!(!is_basic_card(note_str, CardFormat::DEFAULT));
     }
 
     #[test]
@@ -177,6 +315,36 @@ mod tests {
         assert_eq!(back, "It's 4");
     }
 
+    #[test]
+    fn given_extra_group_and_three_fields_when_parsing_then_fills_third_field() {
+        let note_str = "1. Question\n> Answer\n>\n>> Source: the book\n>> page 3";
+
+        let fields = parse_basic_card_field_list(note_str, 3).unwrap();
+
+        assert_eq!(
+            fields,
+            vec!["Question", "Answer", "Source: the book\npage 3"]
+        );
+    }
+
+    #[test]
+    fn given_more_groups_than_fields_when_parsing_then_joins_into_last_field() {
+        let note_str = "1. Question\n> Answer\n>> Extra\n>\n>> Source";
+
+        let fields = parse_basic_card_field_list(note_str, 3).unwrap();
+
+        assert_eq!(fields, vec!["Question", "Answer", "Extra\n\nSource"]);
+    }
+
+    #[test]
+    fn given_extra_group_and_two_fields_when_parsing_then_keeps_it_in_back() {
+        let note_str = "1. Question\n> Answer\n>> nested";
+
+        let fields = parse_basic_card_field_list(note_str, 2).unwrap();
+
+        assert_eq!(fields, vec!["Question", "Answer\n> nested"]);
+    }
+
     #[test]
     fn given_basic_with_multiline_when_parsing_then_preserves_lines() {
         let note_str = "1. Multi\nline\nquestion\n> Multi\n> line\n> answer";
@@ -203,6 +371,90 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn given_multi_digit_numbers_when_parsing_then_strips_whole_number() {
+        let (front, back) = parse_basic_card_fields("12. Question\n> Answer").unwrap();
+        assert_eq!(front, "Question");
+        assert_eq!(back, "Answer");
+
+        assert_eq!(
+            parse_cloze_card_field("<!--ID:1-->\n105. Rust is {safe}").unwrap(),
+            "Rust is {safe}"
+        );
+        assert_eq!(strip_card_number("007.x"), Some("x"));
+        assert_eq!(strip_card_number(".x"), None);
+        assert_eq!(strip_card_number("12) x"), None);
+    }
+
+    #[test]
+    fn given_image_only_answer_when_parsing_then_back_is_the_image() {
+        let note_str = "1. Diagram of X?\n>\t![](img.png)  ";
+
+        assert!(is_basic_card(note_str, CardFormat::DEFAULT));
+        let (front, back) = parse_basic_card_fields(note_str).unwrap();
+
+        assert_eq!(front, "Diagram of X?");
+        assert_eq!(back, "![](img.png)  ");
+    }
+
+    #[test]
+    fn given_indented_answer_markers_when_parsing_then_strips_them() {
+        let note_str = "1.\n![](front.png)\r\n  > ```rust\n  > fn main() {}\n  > ```";
+
+        assert!(is_basic_card(note_str, CardFormat::DEFAULT));
+        let (front, back) = parse_basic_card_fields(note_str).unwrap();
+
+        assert_eq!(front, "![](front.png)");
+        assert_eq!(back, "```rust\nfn main() {}\n```");
+    }
+
+    #[test]
+    fn given_table_answer_when_converting_then_renders_aligned_table() {
+        let note_str =
+            "1. Complexity?\n> Costs:\n> | op | cost |\n> |:---|---:|\n> | push | O(1) |";
+
+        let (_, back) = parse_basic_card_fields(note_str).unwrap();
+        let html = converter::markdown_to_html(&back);
+
+        assert!(html.starts_with("<p>Costs:</p>"));
+        assert!(html.contains(
+            "<thead><tr><th style=\"text-align: left\">op</th><th style=\"text-align: right\">cost</th></tr></thead>"
+        ));
+        assert!(html.contains(
+            "<td style=\"text-align: left\">push</td><td style=\"text-align: right\">O(1)</td>"
+        ));
+    }
+
+    #[test]
+    fn given_nested_list_answer_when_converting_then_keeps_nesting() {
+        let spaced = "1. Types?\n> - scalar\n>   - integer\n>     - i32\n> - compound";
+        let unspaced = "1. Types?\n>- scalar\n>  - integer\n>    - i32\n>- compound";
+        let tabs = "1. Types?\n>\t- scalar\n>\t\t- integer\n>\t\t\t- i32\n>\t- compound";
+        let expected =
+            "<ul><li>scalar<ul><li>integer<ul><li>i32</li></ul></li></ul></li><li>compound</li></ul>";
+
+        for note_str in [spaced, unspaced, tabs] {
+            let (_, back) = parse_basic_card_fields(note_str).unwrap();
+
+            assert_eq!(
+                converter::markdown_to_html(&back),
+                expected,
+                "{:?}",
+                note_str
+            );
+        }
+    }
+
+    #[test]
+    fn given_code_only_question_with_quote_lines_when_parsing_then_keeps_them_in_front() {
+        let note_str = "1. ```sh\n> ls\n```\n> Lists the directory";
+
+        let (front, back) = parse_basic_card_fields(note_str).unwrap();
+
+        assert_eq!(front, "```sh\n> ls\n```");
+        assert_eq!(back, "Lists the directory");
+    }
+
     #[test]
     fn given_cloze_note_string_when_parsing_then_extracts_text() {
         let note_str = "1. Paris is the {{c1::capital}} of {{c2::France}}";
@@ -250,4 +502,46 @@ mod tests {
 
         assert_eq!(id, None);
     }
+
+    #[test]
+    fn given_qa_note_when_parsing_then_extracts_question_and_answer() {
+        let note_str = "<!--ID:123-->\nQ: What is Rust?\nBe precise.\nA: A systems language\n\n    fn main() {}";
+
+        assert!(is_basic_card(note_str, &[CardFormat::Qa]));
+        assert!(!is_basic_card(note_str, CardFormat::DEFAULT));
+        let (front, back) = parse_basic_card_fields(note_str).unwrap();
+
+        assert_eq!(front, "What is Rust?\nBe precise.");
+        assert_eq!(back, "A systems language\n\n    fn main() {}");
+    }
+
+    #[test]
+    fn given_qa_lines_in_numbered_note_without_qa_format_when_checking_then_not_basic() {
+        let note_str = "1. Remember\nQ: this\nA: that";
+
+        assert!(!is_basic_card(note_str, CardFormat::DEFAULT));
+        assert!(is_basic_card(
+            note_str,
+            &[CardFormat::Numbered, CardFormat::Qa]
+        ));
+    }
+
+    #[test]
+    fn given_qa_note_without_answer_when_parsing_then_is_cloze_text() {
+        let note_str = "Q: Rust has {ownership} semantics";
+
+        assert!(!is_basic_card(note_str, &[CardFormat::Qa]));
+        assert!(is_cloze_card(note_str));
+        assert_eq!(
+            parse_cloze_card_field(note_str).unwrap(),
+            "Rust has {ownership} semantics"
+        );
+    }
+
+    #[test]
+    fn given_only_escaped_or_code_braces_when_checking_then_is_not_cloze_card() {
+        assert!(!is_cloze_card(r"1. The set \{a, b\} has two elements"));
+        assert!(!is_cloze_card("1. Call `f({})` first"));
+        assert!(is_cloze_card(r"1. The set \{a, b\} has {two} elements"));
+    }
 }
@@ -2,107 +2,148 @@ use regex::Regex;
 use std::sync::LazyLock;
 
 static ANKI_CLOZE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"\{\{c\d+::[\s\S]*?\}\}").expect("Failed to compile Anki cloze regex")
-});
-static EXPLICIT_SHORT_CLOZE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"\{c?(\d+)::([\s\S]*?)\}").expect("Failed to compile explicit short cloze regex")
-});
-static IMPLICIT_SHORT_CLOZE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"\{([\s\S]*?)\}").expect("Failed to compile implicit short cloze regex")
-});
-static CODE_BLOCK_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"```[\s\S]+?```").expect("Failed to compile code block regex")
+    Regex::new(r"\{\{c(\d+)::[\s\S]*?\}\}").expect("Failed to compile Anki cloze regex")
 });
+static CODE_BLOCK_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"```[\s\S]+?```").expect("Failed to compile code block regex"));
 static INLINE_CODE_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"`[\S\s]+?`").expect("Failed to compile inline code regex"));
-static BLOCK_MATH_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"\$\$[\s\S]+?\$\$").expect("Failed to compile block math regex")
-});
-static INLINE_MATH_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"\$[^\s$][^$]*?\$").expect("Failed to compile inline math regex")
-});
+static BLOCK_MATH_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\$\$[\s\S]+?\$\$").expect("Failed to compile block math regex"));
+static INLINE_MATH_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\$[^\s$][^$]*?\$").expect("Failed to compile inline math regex"));
 
 pub fn is_anki_cloze(text: &str) -> bool {
     ANKI_CLOZE_REGEX.is_match(text)
 }
 
+/// Whether `text` has a cloze deletion, short or in Anki format, outside
+/// code and math
+pub fn has_cloze(text: &str) -> bool {
+    let (text, _) = protect_code_blocks(text);
+    let (text, _) = protect_math_blocks(&text);
+    is_anki_cloze(&text) || convert_short_clozes(&text) != text
+}
+
 pub fn convert_cloze_syntax(text: &str) -> String {
     // Protect code and math blocks
     let (text, code_blocks) = protect_code_blocks(text);
     let (text, math_blocks) = protect_math_blocks(&text);
 
-    // Find all cloze-like patterns
-    let mut result = text.clone();
-    let mut counter = 1;
+    let result = convert_short_clozes(&text);
 
-    // Process each potential cloze deletion
-    let all_clozes: Vec<_> = find_all_clozes(&text);
+    // Restore protected blocks
+    let result = restore_math_blocks(&result, math_blocks);
+    restore_code_blocks(&result, code_blocks)
+}
 
-    for cloze in all_clozes {
-        if is_anki_cloze(&cloze) {
-            // Already in Anki format, skip
-            continue;
+/// Replace the short clozes `{text}`, `{1::text}` and `{c1::text}` with
+/// Anki's `{{c1::text}}`, numbering implicit ones after the highest index
+/// of the Anki clozes already in `text`.
+///
+/// A cloze ends at the brace that balances its opening one, so nested braces
+/// and line breaks are part of its text. Escaped braces (`\{`, `\}`) never
+/// open or close anything. Left unchanged: Anki clozes and other `{{...}}`,
+/// ruby annotations (`[漢字]{かんじ}`) and braces without a match.
+fn convert_short_clozes(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut result = String::with_capacity(text.len());
+    let mut counter = ANKI_CLOZE_REGEX
+        .captures_iter(text)
+        .filter_map(|caps| caps[1].parse::<usize>().ok())
+        .max()
+        .unwrap_or(0)
+        + 1;
+    // Start of the text not yet copied to `result`
+    let mut copied = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'{' => {
+                let Some(end) = closing_brace(bytes, i) else {
+                    // Unmatched, so a literal brace
+                    i += 1;
+                    continue;
+                };
+                let content = &text[i + 1..end];
+                let anki_format = bytes.get(i + 1) == Some(&b'{') || is_anki_cloze(content);
+                let ruby = i > 0 && bytes[i - 1] == b']';
+                if !anki_format && !ruby {
+                    result.push_str(&text[copied..i]);
+                    result.push_str(&anki_cloze(content, &mut counter));
+                    copied = end + 1;
+                }
+                i = end + 1;
+            }
+            _ => i += 1,
         }
+    }
 
-        // Try explicit short syntax: {1::text} or {c1::text}
-        if let Some(caps) = EXPLICIT_SHORT_CLOZE_REGEX.captures(&cloze) {
-            let index = caps.get(1).unwrap().as_str();
-            let content = caps.get(2).unwrap().as_str();
-            let replacement = format!("{{{{c{}::{}}}}}", index, content);
-            result = result.replacen(&cloze, &replacement, 1);
-            continue;
-        }
+    result.push_str(&text[copied..]);
+    result
+}
 
-        // Try implicit short syntax: {text}
-        if let Some(caps) = IMPLICIT_SHORT_CLOZE_REGEX.captures(&cloze) {
-            let content = caps.get(1).unwrap().as_str();
-            let replacement = format!("{{{{c{}::{}}}}}", counter, content);
-            result = result.replacen(&cloze, &replacement, 1);
-            counter += 1;
+/// Index of the `}` balancing the `{` at `open`, skipping escaped braces
+fn closing_brace(bytes: &[u8], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = open;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
         }
+        i += 1;
     }
+    None
+}
 
-    // Restore protected blocks
-    let result = restore_math_blocks(&result, math_blocks);
-    restore_code_blocks(&result, code_blocks)
+/// `{{cN::content}}` for the inside of a short cloze
+fn anki_cloze(content: &str, counter: &mut usize) -> String {
+    let (index, content) = match explicit_index(content) {
+        Some((index, content)) => (index.to_string(), content),
+        None => {
+            let index = counter.to_string();
+            *counter += 1;
+            (index, content)
+        }
+    };
+    format!("{{{{c{}::{}}}}}", index, separate_closing_braces(content))
 }
 
-fn find_all_clozes(text: &str) -> Vec<String> {
-    // Find all {...} patterns that aren't already {{c...}}
-    let mut clozes = Vec::new();
-    let mut chars = text.chars().peekable();
-    let mut current = String::new();
-    let mut in_cloze = false;
-    let mut brace_count = 0;
-
-    while let Some(c) = chars.next() {
-        if c == '{' {
-            if chars.peek() == Some(&'{') {
-                // Skip Anki format
-                current.push(c);
-                current.push(chars.next().unwrap());
-                continue;
-            }
-            in_cloze = true;
-            brace_count = 1;
-            current.push(c);
-        } else if c == '}' && in_cloze {
-            current.push(c);
-            brace_count -= 1;
-            if brace_count == 0 {
-                clozes.push(current.clone());
-                current.clear();
-                in_cloze = false;
-            }
-        } else if in_cloze {
-            current.push(c);
-            if c == '{' {
-                brace_count += 1;
+/// Split `1::text` or `c1::text` into index and text
+fn explicit_index(content: &str) -> Option<(&str, &str)> {
+    let rest = content.strip_prefix('c').unwrap_or(content);
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    let text = rest[digits..].strip_prefix("::")?;
+    Some((&rest[..digits], text))
+}
+
+/// Anki ends a cloze at the first `}}`, so a space goes after each `}` that
+/// is followed by another one or by the end of the cloze
+fn separate_closing_braces(content: &str) -> String {
+    let mut separated = String::with_capacity(content.len());
+    for (i, c) in content.char_indices() {
+        separated.push(c);
+        if c == '}' {
+            let rest = &content[i + 1..];
+            if rest.is_empty() || rest.starts_with('}') || rest.starts_with("\\}") {
+                separated.push(' ');
             }
         }
     }
-
-    clozes
+    separated
 }
 
 fn protect_code_blocks(text: &str) -> (String, Vec<String>) {
@@ -241,4 +282,55 @@ mod tests {
             "Equation {{c1::answer}} is $$x^{2}$$ and inline $y^{3}$"
         );
     }
+
+    #[test]
+    fn given_cloze_with_ruby_annotation_when_converting_then_keeps_annotation() {
+        let input = "[東京]{とうきょう} is the capital of {Japan}";
+        let output = convert_cloze_syntax(input);
+
+        assert_eq!(output, "[東京]{とうきょう} is the capital of {{c1::Japan}}");
+    }
+
+    #[test]
+    fn given_nested_braces_when_converting_then_cloze_keeps_them() {
+        let input = "Define {f(x) = {a: b} for all x} and {2::{x} ∪ {y}}";
+        let output = convert_cloze_syntax(input);
+
+        assert_eq!(
+            output,
+            "Define {{c1::f(x) = {a: b} for all x}} and {{c2::{x} ∪ {y} }}"
+        );
+    }
+
+    #[test]
+    fn given_cloze_spanning_lines_when_converting_then_converts_whole_cloze() {
+        let input = "Rust {owns\nmemory} and {frees\nit}";
+        let output = convert_cloze_syntax(input);
+
+        assert_eq!(output, "Rust {{c1::owns\nmemory}} and {{c2::frees\nit}}");
+    }
+
+    #[test]
+    fn given_escaped_braces_when_converting_then_they_do_not_open_or_close_clozes() {
+        let input = r"Set \{a\} has {one \} element}";
+        let output = convert_cloze_syntax(input);
+
+        assert_eq!(output, r"Set \{a\} has {{c1::one \} element}}");
+    }
+
+    #[test]
+    fn given_anki_cloze_before_short_cloze_when_converting_then_converts_only_short_one() {
+        let input = "{{c1::first}} then {second} and {unclosed";
+        let output = convert_cloze_syntax(input);
+
+        assert_eq!(output, "{{c1::first}} then {{c2::second}} and {unclosed");
+    }
+
+    #[test]
+    fn given_anki_clozes_when_converting_short_clozes_then_numbers_after_highest_index() {
+        let input = "{a} {{c3::b}} {c} {{c1::d}}";
+        let output = convert_cloze_syntax(input);
+
+        assert_eq!(output, "{{c4::a}} {{c3::b}} {{c5::c}} {{c1::d}}");
+    }
 }
@@ -1,49 +1,416 @@
-use pulldown_cmark::{html, Options, Parser};
+use crate::inka::infrastructure::config::{MarkdownConfig, MathConfig, MathMode};
+use crate::util::math::code_block_math;
+use html_escape::{encode_double_quoted_attribute, encode_text};
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd, TextMergeStream};
 use regex::Regex;
 use std::sync::LazyLock;
 
-static NEWLINE_TAG_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"\n?(<.+?>)\n?").expect("Failed to compile newline tag regex"));
-static INLINE_MATH_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"\$([^\s$][^$]*[^\s$])\$").expect("Failed to compile inline math regex")
+// Block-level tag with the newlines around it, which only format the HTML
+static BLOCK_TAG_NEWLINES_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\n*(</?(?:blockquote|br|dd|div|dl|dt|h[1-6]|hr|li|ol|p|pre|section|table|tbody|td|th|thead|tr|ul)\b[^>]*>)\n*",
+    )
+    .expect("Failed to compile block tag newlines regex")
 });
-// Match $$ blocks in HTML context (may have newlines and whitespace)
-static BLOCK_MATH_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"\$\$\s*((?:.|\n)+?)\s*\$\$").expect("Failed to compile block math regex")
+// Newline next to any other (inline) tag, where it separates words
+static INLINE_TAG_NEWLINE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\n(<[^>\n]+>)|(<[^>\n]+>)\n").expect("Failed to compile inline tag newline regex")
+});
+static PRE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?s)\n*<pre\b.*?</pre>\n*").expect("Failed to compile pre regex")
+});
+// Anki LaTeX regions, whose environments are already wrapped
+static ANKI_LATEX_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?s)\[latex\].*?\[/latex\]|\[\$\$\].*?\[/\$\$\]|\[\$\].*?\[/\$\]")
+        .expect("Failed to compile Anki LaTeX regex")
+});
+// Match rendered images, optionally followed by a `{width=300}` attribute block
+static IMAGE_SIZE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"<img src="([^"]*)" alt="([^"]*)"((?: title="[^"]*")?) />(?:\{\s*((?:(?:width|height)\s*=\s*"?\w+"?\s*)+)\})?"#,
+    )
+    .expect("Failed to compile image size regex")
+});
+// Obsidian-style size hint at the end of the alt text: `alt|300` or `alt|300x200`
+static ALT_SIZE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(.*?)\s*\|\s*(\d+)(?:x(\d+))?\s*$").expect("Failed to compile alt size regex")
+});
+static SIZE_ATTRIBUTE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(width|height)\s*=\s*"?(\w+)"?"#).expect("Failed to compile size attribute regex")
+});
+// `[[1686433857327]]` or `[[1686433857327|label]]` reference to another note
+static NOTE_REFERENCE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[\[(\d+)(?:\|([^\[\]]+))?\]\]").expect("Failed to compile note reference regex")
+});
+// `[漢字]{かんじ}` ruby annotation
+static RUBY_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[([^\[\]\s]+)\]\{([^{}]+)\}").expect("Failed to compile ruby regex")
+});
+// Math left in text: `$...$` pulldown-cmark didn't take as math, and a LaTeX
+// command or environment right before `[...]{...}` (`\sqrt[3]{x}`,
+// `\begin{array}[t]{cc}`), whose optional argument looks like ruby
+static TEXT_MATH_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?s)\$\$.*?\$\$|\$[^$]+\$").expect("Failed to compile text math regex")
+});
+static LATEX_COMMAND_END_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\\[A-Za-z]+\*?$|\\begin\{[^{}]*\}$")
+        .expect("Failed to compile LaTeX command regex")
+});
+static EMOJI_SHORTCODE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r":([a-z0-9_+-]+):").expect("Failed to compile emoji shortcode regex")
 });
 
 pub fn markdown_to_html(text: &str) -> String {
+    markdown_to_html_with(text, &MarkdownConfig::default(), &MathConfig::default())
+}
+
+/// `markdown_to_html` with the optional `[markdown]` extensions and the
+/// `[math]` output mode
+pub fn markdown_to_html_with(text: &str, config: &MarkdownConfig, math: &MathConfig) -> String {
     // Parse markdown with pulldown-cmark first
     let mut options = Options::empty();
     options.insert(Options::ENABLE_STRIKETHROUGH);
     options.insert(Options::ENABLE_TABLES);
     options.insert(Options::ENABLE_FOOTNOTES);
     options.insert(Options::ENABLE_TASKLISTS);
+    options.insert(Options::ENABLE_DEFINITION_LIST);
+    options.insert(Options::ENABLE_MATH);
+    if config.smart_punctuation {
+        options.insert(Options::ENABLE_SMART_PUNCTUATION);
+    }
 
     let parser = Parser::new_ext(text, options);
 
-    // Convert events to HTML
+    // Convert events to HTML, rewriting text outside code: code spans and
+    // math are separate events; code blocks are collected, so their text
+    // stays literal and a block holding only math can be typeset instead
     let mut html_output = String::new();
-    html::push_html(&mut html_output, parser);
+    let mut code_block: Option<(Event, String)> = None;
+    // Open `\begin{...}` environments, which can span several text events
+    let mut open_environments = 0;
+    let events = TextMergeStream::new(parser).flat_map(|event| match event {
+        Event::Start(Tag::CodeBlock(_)) => {
+            code_block = Some((event, String::new()));
+            vec![]
+        }
+        Event::Text(text) if code_block.is_some() => {
+            if let Some((_, code)) = &mut code_block {
+                code.push_str(&text);
+            }
+            vec![]
+        }
+        Event::End(TagEnd::CodeBlock) => {
+            let (start, code) = code_block.take().expect("code block started");
+            match code_block_math(&code) {
+                Some((tex, display)) => {
+                    vec![Event::Html(render_math(tex, display, math.mode).into())]
+                }
+                None => vec![start, Event::Text(code.into()), event],
+            }
+        }
+        Event::InlineMath(tex) => vec![Event::InlineHtml(
+            render_math(&tex, false, math.mode).into(),
+        )],
+        Event::DisplayMath(tex) => {
+            vec![Event::InlineHtml(render_math(&tex, true, math.mode).into())]
+        }
+        // `[text](anki:123)`; the link's end event closes the anchor
+        Event::Start(Tag::Link { ref dest_url, .. }) if dest_url.starts_with("anki:") => {
+            match dest_url["anki:".len()..].parse::<i64>() {
+                Ok(note_id) => vec![Event::InlineHtml(note_link_start(note_id).into())],
+                Err(_) => vec![event],
+            }
+        }
+        Event::Text(text) => {
+            let text = convert_ruby_syntax(text, &mut open_environments);
+            let text = if config.emoji {
+                replace_emoji_shortcodes(text)
+            } else {
+                text
+            };
+            link_note_references(text)
+        }
+        _ => vec![event],
+    });
+    html::push_html(&mut html_output, events);
 
-    // Post-process: Convert math delimiters and remove newlines around tags
-    let html_output = convert_math_delimiters(&html_output);
+    // Post-process: Apply image size hints, wrap tables, wrap LaTeX
+    // environments and remove newlines around tags
+    let html_output = apply_image_size_hints(&html_output);
+    let html_output = wrap_tables(&html_output);
+    let html_output = match math.mode {
+        MathMode::Mathjax => html_output,
+        MathMode::AnkiLatex => wrap_bare_latex_environments(&html_output),
+    };
     remove_newlines_around_tags(&html_output)
 }
 
-/// Convert $ and $$ delimiters to MathJax format after HTML rendering
-fn convert_math_delimiters(html: &str) -> String {
-    // First handle block math ($$...$$) to avoid conflicts with inline
-    let html = BLOCK_MATH_REGEX.replace_all(html, r"\[$1\]");
+/// `$...$` (inline) or `$$...$$` (display) math as HTML: MathJax delimiters,
+/// or Anki's `[$]`/`[$$]` LaTeX tags. A LaTeX environment
+/// (`\begin{align}...`), which can't go into math mode, gets `[latex]`.
+fn render_math(tex: &str, display: bool, mode: MathMode) -> String {
+    let tex = encode_text(tex.trim());
+    match (mode, display) {
+        (MathMode::Mathjax, false) => format!(r"\({}\)", tex),
+        (MathMode::Mathjax, true) => format!(r"\[{}\]", tex),
+        (MathMode::AnkiLatex, false) => format!("[$]{}[/$]", tex),
+        (MathMode::AnkiLatex, true) if tex.starts_with(r"\begin{") => {
+            format!("[latex]{}[/latex]", tex)
+        }
+        (MathMode::AnkiLatex, true) => format!("[$$]{}[/$$]", tex),
+    }
+}
+
+/// Turn `![alt](img.png){width=300}` and Obsidian `![alt|300x200](img.png)`
+/// size hints into `width`/`height` attributes on the rendered `<img>`
+fn apply_image_size_hints(html: &str) -> String {
+    IMAGE_SIZE_REGEX
+        .replace_all(html, |cap: &regex::Captures| {
+            let mut alt = &cap[2];
+            let mut width = None;
+            let mut height = None;
+
+            if let Some(size) = ALT_SIZE_REGEX.captures(alt) {
+                alt = size.get(1).map_or("", |m| m.as_str());
+                width = size.get(2).map(|m| m.as_str());
+                height = size.get(3).map(|m| m.as_str());
+            }
+            if let Some(block) = cap.get(4) {
+                for attr in SIZE_ATTRIBUTE_REGEX.captures_iter(block.as_str()) {
+                    let value = attr.get(2).map(|m| m.as_str());
+                    match &attr[1] {
+                        "width" => width = value,
+                        _ => height = value,
+                    }
+                }
+            }
+
+            let mut tag = format!(r#"<img src="{}" alt="{}"{}"#, &cap[1], alt, &cap[3]);
+            if let Some(width) = width {
+                tag.push_str(&format!(r#" width="{}""#, width));
+            }
+            if let Some(height) = height {
+                tag.push_str(&format!(r#" height="{}""#, height));
+            }
+            tag.push_str(" />");
+            tag
+        })
+        .into_owned()
+}
+
+/// Wrap field HTML in a container carrying `dir` and `lang`, so Arabic or
+/// Hebrew cards display right-to-left; a right-to-left `lang` implies
+/// `dir="rtl"`. Returns the HTML unchanged if neither is set.
+pub fn apply_text_direction(html: &str, lang: Option<&str>, dir: Option<&str>) -> String {
+    let dir = dir.or_else(|| lang.filter(|lang| is_rtl_language(lang)).map(|_| "rtl"));
+    if lang.is_none() && dir.is_none() {
+        return html.to_string();
+    }
+
+    let mut attributes = String::new();
+    if let Some(dir) = dir {
+        attributes.push_str(&format!(
+            r#" dir="{}""#,
+            encode_double_quoted_attribute(dir)
+        ));
+    }
+    if let Some(lang) = lang {
+        attributes.push_str(&format!(
+            r#" lang="{}""#,
+            encode_double_quoted_attribute(lang)
+        ));
+    }
+    format!("<div{}>{}</div>", attributes, html)
+}
+
+fn is_rtl_language(lang: &str) -> bool {
+    let primary = lang.split(['-', '_']).next().unwrap_or(lang);
+    ["ar", "he", "fa", "ur", "yi", "ps", "sd", "ug", "dv", "ckb"]
+        .iter()
+        .any(|rtl| primary.eq_ignore_ascii_case(rtl))
+}
+
+/// Put tables into a horizontally scrolling container, so wide comparison
+/// tables don't overflow the card on mobile. The style is inline because
+/// card templates don't carry our CSS.
+fn wrap_tables(html: &str) -> String {
+    if !html.contains("<table>") {
+        return html.to_string();
+    }
+    html.replace(
+        "<table>",
+        r#"<div class="table-wrapper" style="overflow-x: auto;"><table>"#,
+    )
+    .replace("</table>", "</table></div>")
+}
+
+/// URI scheme of links between notes, which `ankiview view` accepts in place
+/// of a note ID. Anki itself doesn't follow them; the link only names the note
+pub const NOTE_LINK_PREFIX: &str = "ankiview://view/";
+
+fn note_link_start(note_id: i64) -> String {
+    format!(
+        r#"<a class="note-ref" href="{}{}">"#,
+        NOTE_LINK_PREFIX, note_id
+    )
+}
+
+/// Split text at `[[note-id]]` references, which become links to the note
+fn link_note_references(text: CowStr<'_>) -> Vec<Event<'_>> {
+    if !NOTE_REFERENCE_REGEX.is_match(&text) {
+        return vec![Event::Text(text)];
+    }
+    let mut events = Vec::new();
+    let mut last = 0;
+    for cap in NOTE_REFERENCE_REGEX.captures_iter(&text) {
+        let whole = cap.get(0).expect("match");
+        let Ok(note_id) = cap[1].parse::<i64>() else {
+            continue;
+        };
+        if whole.start() > last {
+            events.push(Event::Text(text[last..whole.start()].to_string().into()));
+        }
+        let label = cap.get(2).map_or_else(
+            || format!("Note {}", note_id),
+            |m| m.as_str().trim().to_string(),
+        );
+        events.push(Event::InlineHtml(
+            format!(
+                "{}{}</a>",
+                note_link_start(note_id),
+                html_escape::encode_text(&label)
+            )
+            .into(),
+        ));
+        last = whole.end();
+    }
+    if last < text.len() {
+        events.push(Event::Text(text[last..].to_string().into()));
+    }
+    events
+}
+
+/// Turn `[漢字]{かんじ}` into Anki's furigana syntax ` 漢字[かんじ]`, which
+/// `{{furigana:Field}}` templates render as ruby text
+///
+/// Anki's base text reaches back to the previous space, so one is inserted
+/// unless the annotation starts the text. Math is left alone: `$...$`, LaTeX
+/// environments (`open_environments` carries the ones still open from earlier
+/// text) and a LaTeX command's optional argument (`\sqrt[3]{x}`).
+fn convert_ruby_syntax<'a>(text: CowStr<'a>, open_environments: &mut usize) -> CowStr<'a> {
+    let environment_depth = |before: &str| {
+        (*open_environments + before.matches(r"\begin{").count())
+            .saturating_sub(before.matches(r"\end{").count())
+    };
+    let converted = if RUBY_REGEX.is_match(&text) {
+        let math_spans: Vec<_> = TEXT_MATH_REGEX
+            .find_iter(&text)
+            .map(|m| m.range())
+            .collect();
+        let converted = RUBY_REGEX.replace_all(&text, |cap: &regex::Captures| {
+            let whole = cap.get(0).expect("match");
+            let before = &text[..whole.start()];
+            if environment_depth(before) > 0
+                || math_spans.iter().any(|span| span.contains(&whole.start()))
+                || LATEX_COMMAND_END_REGEX.is_match(before)
+            {
+                return whole.as_str().to_string();
+            }
+            let separator = match before.chars().next_back() {
+                Some(c) if !c.is_whitespace() => " ",
+                _ => "",
+            };
+            format!("{}{}[{}]", separator, &cap[1], &cap[2])
+        });
+        Some(converted.into_owned())
+    } else {
+        None
+    };
+    *open_environments = environment_depth(&text);
+    match converted {
+        Some(converted) => converted.into(),
+        None => text,
+    }
+}
+
+/// Replace known `:shortcode:`s with their emoji; unknown ones stay as written
+fn replace_emoji_shortcodes(text: CowStr<'_>) -> CowStr<'_> {
+    if !EMOJI_SHORTCODE_REGEX.is_match(&text) {
+        return text;
+    }
+    EMOJI_SHORTCODE_REGEX
+        .replace_all(&text, |cap: &regex::Captures| {
+            emojis::get_by_shortcode(&cap[1])
+                .map_or_else(|| cap[0].to_string(), |emoji| emoji.as_str().to_string())
+        })
+        .into_owned()
+        .into()
+}
 
-    // Then handle inline math ($...$)
-    INLINE_MATH_REGEX
-        .replace_all(&html, r"\($1\)")
-        .to_string()
+/// Wrap LaTeX environments written without `$$` delimiters in `[latex]`,
+/// leaving the math already in Anki LaTeX tags alone
+fn wrap_bare_latex_environments(html: &str) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut last = 0;
+    for region in ANKI_LATEX_REGEX.find_iter(html) {
+        result.push_str(&wrap_latex_environments(&html[last..region.start()]));
+        result.push_str(region.as_str());
+        last = region.end();
+    }
+    result.push_str(&wrap_latex_environments(&html[last..]));
+    result
 }
 
+/// Wrap each `\begin{env}...\end{env}` in `[latex]...[/latex]`
+fn wrap_latex_environments(html: &str) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(r"\begin{") {
+        let name_start = start + r"\begin{".len();
+        let end = rest[name_start..].find('}').and_then(|name_len| {
+            let end_tag = format!(r"\end{{{}}}", &rest[name_start..name_start + name_len]);
+            rest[name_start..]
+                .find(&end_tag)
+                .map(|pos| name_start + pos + end_tag.len())
+        });
+        let Some(end) = end else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        result.push_str("[latex]");
+        result.push_str(&rest[start..end]);
+        result.push_str("[/latex]");
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Anki renders newlines next to tags as visible breaks: drop the ones
+/// around block-level tags and turn the ones next to inline tags into spaces,
+/// so words stay apart. Code blocks only lose their final newline.
 fn remove_newlines_around_tags(html: &str) -> String {
-    NEWLINE_TAG_REGEX.replace_all(html, "$1").to_string()
+    let mut result = String::with_capacity(html.len());
+    let mut last = 0;
+    for pre in PRE_REGEX.find_iter(html) {
+        result.push_str(&remove_newlines_outside_code(&html[last..pre.start()]));
+        let end = pre.end();
+        let pre = pre.as_str().trim_matches('\n');
+        result.push_str(&pre.replace("\n</code></pre>", "</code></pre>"));
+        last = end;
+    }
+    result.push_str(&remove_newlines_outside_code(&html[last..]));
+    result
+}
+
+fn remove_newlines_outside_code(html: &str) -> String {
+    let html = BLOCK_TAG_NEWLINES_REGEX.replace_all(html, "$1");
+    INLINE_TAG_NEWLINE_REGEX
+        .replace_all(&html, |cap: &regex::Captures| match cap.get(1) {
+            Some(tag) => format!(" {}", tag.as_str()),
+            None => format!("{} ", &cap[2]),
+        })
+        .into_owned()
 }
 
 #[cfg(test)]
@@ -70,6 +437,38 @@ mod tests {
         assert!(!html.contains(">\n"));
     }
 
+    #[test]
+    fn given_line_break_before_inline_tag_when_converting_then_words_stay_apart() {
+        let input = "- one\n  **bold** item\n  - nested\n    *em*\n    text\n- two";
+        let html = markdown_to_html(input);
+
+        assert_eq!(
+            html,
+            "<ul><li>one <strong>bold</strong> item<ul><li>nested <em>em</em> text</li></ul></li><li>two</li></ul>"
+        );
+    }
+
+    #[test]
+    fn given_footnote_and_code_when_converting_then_keeps_their_newlines() {
+        let input = "Claim[^1]\n\n```\n\nfirst\nsecond\n```\n\n[^1]: Source\n";
+        let html = markdown_to_html(input);
+
+        assert!(html.starts_with(
+            "<p>Claim<sup class=\"footnote-reference\"><a href=\"#1\">1</a></sup></p><pre><code>\nfirst\nsecond</code></pre>"
+        ), "{}", html);
+        assert!(html.ends_with("</sup><p>Source</p></div>"), "{}", html);
+    }
+
+    #[test]
+    fn given_escaped_braces_when_converting_then_renders_literal_braces() {
+        let input = crate::inka::infrastructure::markdown::cloze_converter::convert_cloze_syntax(
+            r"The set \{a, b\} has {two} elements",
+        );
+        let html = markdown_to_html(&input);
+
+        assert_eq!(html, "<p>The set {a, b} has {{c1::two}} elements</p>");
+    }
+
     #[test]
     fn given_markdown_with_math_when_converting_then_uses_mathjax_delimiters() {
         let input = "Inline $f(x)$ and block:\n$$\ng(x)\n$$";
@@ -79,6 +478,78 @@ mod tests {
         assert!(html.contains(r"\[g(x)\]"));
     }
 
+    #[test]
+    fn given_math_in_latex_fence_when_converting_then_renders_math_not_code() {
+        let input = "Cases:\n```latex\n$$\nx < 0 \\\\ x \\geq 0\n$$\n```\n```sh\necho $HOME\n```";
+        let html = markdown_to_html(input);
+
+        assert_eq!(
+            html,
+            r#"<p>Cases:</p>\[x &lt; 0 \\ x \geq 0\]<pre><code class="language-sh">echo $HOME</code></pre>"#
+        );
+    }
+
+    #[test]
+    fn given_dollars_in_code_when_converting_then_code_is_not_math() {
+        let input = "Run `echo $A $B`:\n```sh\necho $HOME$PATH\n```";
+        let html = markdown_to_html(input);
+
+        assert_eq!(
+            html,
+            r#"<p>Run <code>echo $A $B</code>:</p><pre><code class="language-sh">echo $HOME$PATH</code></pre>"#
+        );
+    }
+
+    #[test]
+    fn given_markdown_syntax_inside_math_when_converting_then_keeps_tex() {
+        let input = r"Product $a_1 * b_2 * c_3$ and $$\begin{matrix} a \\ b \end{matrix}$$";
+        let html = markdown_to_html(input);
+
+        assert_eq!(
+            html,
+            r"<p>Product \(a_1 * b_2 * c_3\) and \[\begin{matrix} a \\ b \end{matrix}\]</p>"
+        );
+    }
+
+    #[test]
+    fn given_anki_latex_mode_when_converting_then_uses_anki_tags() {
+        let math = MathConfig {
+            mode: MathMode::AnkiLatex,
+            ..Default::default()
+        };
+        let input = "Inline $f(x)$ and block:\n$$\ng(x)\n$$";
+
+        let html = markdown_to_html_with(input, &MarkdownConfig::default(), &math);
+
+        assert!(html.contains("[$]f(x)[/$]"), "{}", html);
+        assert!(html.contains("[$$]g(x)[/$$]"), "{}", html);
+    }
+
+    #[test]
+    fn given_latex_environment_in_anki_latex_mode_when_converting_then_wraps_in_latex_tag() {
+        let math = MathConfig {
+            mode: MathMode::AnkiLatex,
+            ..Default::default()
+        };
+        let input = "\\begin{tikzpicture}\n\\draw (0,0) -- (1,1);\n\\end{tikzpicture}\n\n$$\n\\begin{align}\na &= b\n\\end{align}\n$$";
+
+        let html = markdown_to_html_with(input, &MarkdownConfig::default(), &math);
+
+        assert!(
+            html.contains(
+                "[latex]\\begin{tikzpicture}\n\\draw (0,0) -- (1,1);\n\\end{tikzpicture}[/latex]"
+            ),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains("[latex]\\begin{align}\na &amp;= b\n\\end{align}[/latex]"),
+            "{}",
+            html
+        );
+        assert!(!html.contains("[$$]"), "{}", html);
+    }
+
     #[test]
     fn given_complex_math_when_converting_then_preserves_latex() {
         let input = r"$$
@@ -138,4 +609,168 @@ $$";
         assert!(html.contains("generic code block"));
         assert!(html.contains("</code></pre>"));
     }
+
+    #[test]
+    fn given_attribute_block_when_converting_then_sets_image_size() {
+        let input = "![Screenshot](shot.png){width=300}";
+        let html = markdown_to_html(input);
+
+        assert!(
+            html.contains(r#"<img src="shot.png" alt="Screenshot" width="300" />"#),
+            "{}",
+            html
+        );
+        assert!(!html.contains('{'));
+    }
+
+    #[test]
+    fn given_obsidian_size_hint_when_converting_then_sets_size_and_keeps_alt() {
+        let html = markdown_to_html("![Diagram|300](a.png) ![Graph | 200x100](b.png)");
+
+        assert!(
+            html.contains(r#"<img src="a.png" alt="Diagram" width="300" />"#),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains(r#"<img src="b.png" alt="Graph" width="200" height="100" />"#),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn given_image_without_size_hint_when_converting_then_keeps_alt_and_title() {
+        let html = markdown_to_html(r#"![A|B choice](c.png "Title") {not a size}"#);
+
+        assert!(
+            html.contains(r#"<img src="c.png" alt="A|B choice" title="Title" /> {not a size}"#),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn given_smart_punctuation_when_converting_then_curls_quotes_and_dashes() {
+        let config = MarkdownConfig {
+            smart_punctuation: true,
+            ..Default::default()
+        };
+
+        let html =
+            markdown_to_html_with(r#"It's "Rust" -- fast..."#, &config, &MathConfig::default());
+
+        assert_eq!(html, "<p>It’s “Rust” – fast…</p>");
+        assert_eq!(markdown_to_html(r#""Rust""#), r#"<p>"Rust"</p>"#);
+    }
+
+    #[test]
+    fn given_emoji_option_when_converting_then_replaces_known_shortcodes() {
+        let config = MarkdownConfig {
+            emoji: true,
+            ..Default::default()
+        };
+
+        let html = markdown_to_html_with(
+            "Ship it :rocket: at 10:30:00, not :nonsense:\n\n```\n:rocket:\n```\n\n`:rocket:`",
+            &config,
+            &MathConfig::default(),
+        );
+
+        assert!(
+            html.contains("Ship it 🚀 at 10:30:00, not :nonsense:"),
+            "{}",
+            html
+        );
+        assert!(html.contains("<code>:rocket:</code>"), "{}", html);
+        assert!(
+            html.contains(">:rocket:"),
+            "code blocks stay literal: {}",
+            html
+        );
+    }
+
+    #[test]
+    fn given_table_when_converting_then_wraps_it_in_scroll_container() {
+        let input = "| Rust | Go |\n|------|----|\n| yes | no |";
+
+        let html = markdown_to_html(input);
+
+        assert!(
+            html.starts_with(r#"<div class="table-wrapper" style="overflow-x: auto;"><table>"#),
+            "{}",
+            html
+        );
+        assert!(html.ends_with("</table></div>"), "{}", html);
+    }
+
+    #[test]
+    fn given_definition_list_when_converting_then_renders_dl() {
+        let input = "Ownership\n: Each value has a single owner";
+
+        let html = markdown_to_html(input);
+
+        assert_eq!(
+            html,
+            "<dl><dt>Ownership</dt><dd>Each value has a single owner</dd></dl>"
+        );
+    }
+
+    #[test]
+    fn given_ruby_annotation_when_converting_then_emits_anki_furigana() {
+        let html = markdown_to_html("これは[漢字]{かんじ}です。[日本]{にほん}\n\n`[漢字]{かんじ}`");
+
+        assert_eq!(
+            html,
+            "<p>これは 漢字[かんじ]です。 日本[にほん]</p><p><code>[漢字]{かんじ}</code></p>"
+        );
+    }
+
+    #[test]
+    fn given_ruby_like_math_when_converting_then_keeps_it() {
+        let html = markdown_to_html(
+            "[漢字]{かんじ} $ \\sqrt[3]{x} $ and \\sqrt[n]{y}\n\\begin{array}\n[a]{b}\n\\end{array}\nin [日本]{にほん}",
+        );
+
+        assert!(html.starts_with("<p>漢字[かんじ] $ \\sqrt[3]{x} $ and \\sqrt[n]{y}"));
+        assert!(html.contains("[a]{b}"));
+        assert!(html.ends_with("in 日本[にほん]</p>"));
+    }
+
+    #[test]
+    fn given_anki_furigana_when_converting_then_keeps_it() {
+        let html = markdown_to_html("日本語 漢字[かんじ]");
+
+        assert_eq!(html, "<p>日本語 漢字[かんじ]</p>");
+    }
+
+    #[test]
+    fn given_rtl_language_when_applying_direction_then_wraps_with_dir_and_lang() {
+        assert_eq!(
+            apply_text_direction("<p>שלום</p>", Some("he"), None),
+            r#"<div dir="rtl" lang="he"><p>שלום</p></div>"#
+        );
+        assert_eq!(
+            apply_text_direction("<p>x</p>", Some("de-AT"), Some("auto")),
+            r#"<div dir="auto" lang="de-AT"><p>x</p></div>"#
+        );
+        assert_eq!(apply_text_direction("<p>x</p>", None, None), "<p>x</p>");
+    }
+
+    #[test]
+    fn given_note_references_when_converting_then_renders_note_links() {
+        let input = "See [[1686433857327]], [[42|ownership]] and [borrowing](anki:7).\n\n`[[1]]`";
+
+        let html = markdown_to_html(input);
+
+        assert_eq!(
+            html,
+            concat!(
+                r#"<p>See <a class="note-ref" href="ankiview://view/1686433857327">Note 1686433857327</a>, "#,
+                r#"<a class="note-ref" href="ankiview://view/42">ownership</a> and "#,
+                r#"<a class="note-ref" href="ankiview://view/7">borrowing</a>.</p>"#,
+                "<p><code>[[1]]</code></p>"
+            )
+        );
+    }
 }
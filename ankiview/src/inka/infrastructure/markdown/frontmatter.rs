@@ -0,0 +1,176 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+// Lines a YAML frontmatter block is made of: `key: value`, indented
+// continuations, list items and comments. Card lines (`1.`, `>`) don't match.
+static YAML_LINE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:[ \t]*$|#|[A-Za-z_][\w .-]*:(?:[ \t]|$)|[ \t]+\S|-[ \t])")
+        .expect("Failed to compile YAML line regex")
+});
+
+// `Q:`/`A:` lines look like YAML but start Q/A cards
+static CARD_MARKER_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[ \t]*[QA]:").expect("Failed to compile card marker regex"));
+
+/// Metadata of a leading YAML frontmatter block that applies to the file's sections
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frontmatter {
+    /// `deck:` — deck for sections without a `Deck:` line
+    pub deck: Option<String>,
+    /// `tags:` — added to every section's tags
+    pub tags: Vec<String>,
+}
+
+/// Byte length of the frontmatter block at the start of `content` (up to and
+/// including its closing `---` line), or 0 if the file doesn't start with one
+///
+/// A leading `---` block counts as frontmatter only if all of its lines look
+/// like YAML, at least one is a `key: value` line and none is a `Q:`/`A:` card
+/// line, so an inka section at the very top of a file is still a section.
+pub fn frontmatter_len(content: &str) -> usize {
+    let Some(body) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return 0;
+    };
+    let mut offset = content.len() - body.len();
+    let mut has_key = false;
+    for line in body.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line == "---" || line == "..." {
+            return if has_key { offset } else { 0 };
+        }
+        if !YAML_LINE_REGEX.is_match(line) || CARD_MARKER_REGEX.is_match(line) {
+            return 0;
+        }
+        has_key |= !line.trim().is_empty() && !line.starts_with([' ', '\t', '#', '-']);
+    }
+    // Unterminated block
+    0
+}
+
+/// Deck and tags from the frontmatter at the start of `content`, if any
+pub fn parse_frontmatter(content: &str) -> Option<Frontmatter> {
+    let len = frontmatter_len(content);
+    if len == 0 {
+        return None;
+    }
+    let block = &content[content.find('\n').map_or(0, |end| end + 1)..len];
+
+    let mut frontmatter = Frontmatter::default();
+    let mut lines = block.lines().peekable();
+    while let Some(line) = lines.next() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        if line.starts_with([' ', '\t']) {
+            continue;
+        }
+        let value = value.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "deck" if !value.is_empty() => frontmatter.deck = Some(unquote(value).to_string()),
+            "tags" | "tag" => {
+                if value.is_empty() {
+                    // Block list: `tags:` followed by `  - tag` lines
+                    while let Some(item) = lines
+                        .peek()
+                        .and_then(|next| next.trim_start().strip_prefix("- "))
+                    {
+                        frontmatter.tags.push(clean_tag(item));
+                        lines.next();
+                    }
+                } else {
+                    // Flow list `[a, b]`, or `a, b` / `a b`
+                    let value = value.trim_start_matches('[').trim_end_matches(']');
+                    frontmatter.tags.extend(
+                        value
+                            .split(|c: char| c == ',' || c.is_whitespace())
+                            .filter(|tag| !tag.is_empty())
+                            .map(clean_tag),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    frontmatter.tags.retain(|tag| !tag.is_empty());
+    Some(frontmatter)
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value)
+}
+
+/// Obsidian writes tags with or without a leading `#`
+fn clean_tag(tag: &str) -> String {
+    unquote(tag.trim()).trim_start_matches('#').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_frontmatter_when_parsing_then_reads_deck_and_tags() {
+        let content = "---\ntitle: Rust\ndeck: \"Programming::Rust\"\ntags: [rust, '#lang']\n---\n\n# Notes\n";
+
+        let frontmatter = parse_frontmatter(content).unwrap();
+
+        assert_eq!(frontmatter.deck.as_deref(), Some("Programming::Rust"));
+        assert_eq!(frontmatter.tags, vec!["rust", "lang"]);
+        assert_eq!(
+            &content[frontmatter_len(content)..],
+            "\n# Notes\n",
+            "length covers the closing delimiter line"
+        );
+    }
+
+    #[test]
+    fn given_block_list_tags_when_parsing_then_reads_all() {
+        let content = "---\ntags:\n  - rust\n  - ownership\nauthor: me\n---\n";
+
+        let frontmatter = parse_frontmatter(content).unwrap();
+
+        assert_eq!(frontmatter.tags, vec!["rust", "ownership"]);
+        assert_eq!(frontmatter.deck, None);
+    }
+
+    #[test]
+    fn given_inka_section_at_file_start_when_checking_then_not_frontmatter() {
+        let content = "---\nDeck: Rust\n\n1. Question?\n> Answer\n---\n";
+
+        assert_eq!(frontmatter_len(content), 0);
+        assert_eq!(parse_frontmatter(content), None);
+    }
+
+    #[test]
+    fn given_qa_section_at_file_start_when_checking_then_not_frontmatter() {
+        let content = "---\nQ: What is Rust?\nA: A language\n---\n";
+
+        assert_eq!(frontmatter_len(content), 0);
+        assert_eq!(frontmatter_len("---\n\n# comment\n---\n"), 0);
+    }
+
+    #[test]
+    fn given_crlf_frontmatter_when_parsing_then_reads_it() {
+        let content = "---\r\ndeck: Rust\r\ntags: [rust]\r\n---\r\n\r\n# Notes\r\n";
+
+        let frontmatter = parse_frontmatter(content).unwrap();
+
+        assert_eq!(frontmatter.deck.as_deref(), Some("Rust"));
+        assert_eq!(frontmatter.tags, vec!["rust"]);
+        assert_eq!(&content[frontmatter_len(content)..], "\r\n# Notes\r\n");
+    }
+
+    #[test]
+    fn given_rule_not_at_file_start_when_checking_then_not_frontmatter() {
+        assert_eq!(frontmatter_len("# Title\n---\ntitle: x\n---\n"), 0);
+        assert_eq!(frontmatter_len("---\ntitle: x\n"), 0);
+    }
+}
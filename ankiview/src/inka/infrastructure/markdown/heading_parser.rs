@@ -0,0 +1,133 @@
+use super::card_parser;
+use anyhow::Result;
+use regex::Regex;
+use std::sync::LazyLock;
+
+// ATX heading: `## Title`, optionally closed (`## Title ##`)
+static HEADING_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[ \t]{0,3}#{1,6}[ \t]+(.*?)(?:[ \t]+#+)?[ \t]*$")
+        .expect("Failed to compile heading regex")
+});
+// Opt-in marker at the end of a heading: `## Title #card`
+static CARD_MARKER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[ \t]+#card[ \t]*$").expect("Failed to compile card marker regex")
+});
+
+/// Heading text, or `None` if `line` isn't an ATX heading
+fn heading_text(line: &str) -> Option<&str> {
+    HEADING_REGEX
+        .captures(line)
+        .and_then(|cap| cap.get(1))
+        .map(|m| m.as_str())
+        .filter(|text| !text.is_empty())
+}
+
+/// Level (number of `#`) of an ATX heading
+fn heading_level(line: &str) -> Option<usize> {
+    heading_text(line)?;
+    Some(line.trim_start().chars().take_while(|&c| c == '#').count())
+}
+
+/// Any heading starts a card (`heading` format)
+pub fn is_heading_line(line: &str) -> bool {
+    heading_text(line).is_some()
+}
+
+/// Only headings ending in `#card` start a card (`heading-card` format)
+pub fn is_card_heading_line(line: &str) -> bool {
+    heading_text(line).is_some_and(|text| CARD_MARKER_REGEX.is_match(text))
+}
+
+/// First line that isn't an ID comment is a heading
+pub fn is_heading_note(note_str: &str) -> bool {
+    note_str
+        .lines()
+        .find(|line| !line.trim().starts_with("<!--ID:"))
+        .is_some_and(is_heading_line)
+}
+
+/// Heading (without `#card` marker) is the front, the content up to the next
+/// card or the next heading of the same or a higher level is the back
+pub fn parse_heading_card_fields(note_str: &str) -> Result<(String, String)> {
+    let mut lines = note_str
+        .lines()
+        .skip_while(|line| line.trim().starts_with("<!--ID:"));
+
+    let heading = lines.next().unwrap_or_default();
+    let front = heading_text(heading)
+        .map(|text| CARD_MARKER_REGEX.replace(text, "").trim().to_string())
+        .filter(|front| !front.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Failed to extract question from heading card"))?;
+    let level = heading_level(heading).unwrap_or(1);
+
+    let mut body = Vec::new();
+    let mut in_fence = false;
+    for line in lines {
+        let indent = line.len() - line.trim_start().len();
+        if indent <= 3 && card_parser::is_fence(line.trim()) {
+            in_fence = !in_fence;
+        } else if !in_fence && heading_level(line).is_some_and(|other| other <= level) {
+            break;
+        }
+        body.push(line);
+    }
+    let back = body.join("\n").trim().to_string();
+    if back.is_empty() {
+        anyhow::bail!("Failed to extract answer from heading card '{}'", front);
+    }
+
+    Ok((front, back))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_heading_note_when_parsing_then_heading_is_front_and_body_is_back() {
+        let note_str =
+            "<!--ID:123-->\n## What is ownership? ##\n\nEach value has one *owner*.\n\n> quoted";
+
+        assert!(is_heading_note(note_str));
+        let (front, back) = parse_heading_card_fields(note_str).unwrap();
+
+        assert_eq!(front, "What is ownership?");
+        assert_eq!(back, "Each value has one *owner*.\n\n> quoted");
+    }
+
+    #[test]
+    fn given_card_marker_when_parsing_then_strips_marker() {
+        assert!(is_card_heading_line("### Borrowing #card"));
+        assert!(!is_card_heading_line("### Borrowing"));
+        assert!(!is_card_heading_line("#card"));
+
+        let (front, _) = parse_heading_card_fields("### Borrowing #card\nReferences").unwrap();
+
+        assert_eq!(front, "Borrowing");
+    }
+
+    #[test]
+    fn given_non_heading_lines_when_checking_then_not_headings() {
+        assert!(!is_heading_line("#hashtag"));
+        assert!(!is_heading_line("1. Question"));
+        assert!(!is_heading_line("    ## indented code"));
+        assert!(is_heading_line("# Top"));
+    }
+
+    #[test]
+    fn given_following_heading_when_parsing_then_back_stops_at_same_or_higher_level() {
+        let note_str = "## Borrowing #card\nReferences.\n### Details\nMore.\n```\n# not a heading\n```\n## Notes\nnot part of the card\n# Chapter";
+
+        let (_, back) = parse_heading_card_fields(note_str).unwrap();
+
+        assert_eq!(
+            back,
+            "References.\n### Details\nMore.\n```\n# not a heading\n```"
+        );
+    }
+
+    #[test]
+    fn given_heading_without_body_when_parsing_then_errors() {
+        assert!(parse_heading_card_fields("## Lonely heading").is_err());
+    }
+}
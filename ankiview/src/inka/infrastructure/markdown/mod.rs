@@ -2,4 +2,6 @@
 pub mod card_parser;
 pub mod cloze_converter;
 pub mod converter;
+pub mod frontmatter;
+pub mod heading_parser;
 pub mod section_parser;
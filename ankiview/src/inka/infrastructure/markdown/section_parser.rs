@@ -1,25 +1,95 @@
+use super::{card_parser, cloze_converter, frontmatter, heading_parser};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::sync::LazyLock;
 
+/// Syntax cards can be written in inside a section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CardFormat {
+    /// `1. Question` followed by `> Answer` lines, or a numbered cloze
+    Numbered,
+    /// `Q: Question` followed by `A: Answer`
+    Qa,
+    /// Every `## Heading` is a front, the content up to the next card its back
+    Heading,
+    /// Like `Heading`, but only for headings ending in `#card`
+    HeadingCard,
+}
+
+impl CardFormat {
+    /// Formats recognized without configuration
+    pub const DEFAULT: &'static [CardFormat] = &[CardFormat::Numbered];
+
+    fn is_note_start(self, line: &str) -> bool {
+        match self {
+            // Indented numbers are list items of the card above
+            CardFormat::Numbered => card_parser::strip_card_number(line).is_some(),
+            CardFormat::Qa => line.trim_start().starts_with("Q:"),
+            // Headings allow up to three spaces; more make an indented code block
+            CardFormat::Heading => heading_parser::is_heading_line(line),
+            CardFormat::HeadingCard => heading_parser::is_card_heading_line(line),
+        }
+    }
+}
+
+/// Text of a section or card with the 1-based line it starts on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span<T> {
+    pub line: usize,
+    pub text: T,
+}
+
+/// 1-based line of byte `offset` in `text`
+fn line_at(text: &str, offset: usize) -> usize {
+    text[..offset].matches('\n').count() + 1
+}
+
 pub struct SectionParser {
     section_regex: Regex,
 }
 
 impl SectionParser {
+    /// Line that opens and closes a section unless configured otherwise
+    pub const DEFAULT_DELIMITER: &'static str = "---";
+
     pub fn new() -> Self {
-        // Regex pattern: ^---\n(.+?)^---$
+        Self::with_delimiters(Self::DEFAULT_DELIMITER, Self::DEFAULT_DELIMITER)
+    }
+
+    /// Sections between a `start` and an `end` line, e.g. `<!--inka-start-->`
+    /// and `<!--inka-end-->` in files that use `---` for frontmatter or rules
+    pub fn with_delimiters(start: &str, end: &str) -> Self {
+        // Regex pattern: ^<start>\n(.+?)^<end>$
         // Multiline and dotall flags
-        let section_regex =
-            Regex::new(r"(?ms)^---\n(.+?)^---$").expect("Failed to compile section regex");
+        let section_regex = Regex::new(&format!(
+            r"(?ms)^{}\n(.+?)^{}$",
+            regex::escape(start),
+            regex::escape(end)
+        ))
+        .expect("Failed to compile section regex");
 
         Self { section_regex }
     }
 
+    /// Sections of `input`; a leading YAML frontmatter block is not one
     pub fn parse<'a>(&self, input: &'a str) -> Vec<&'a str> {
+        self.parse_spans(input)
+            .into_iter()
+            .map(|span| span.text)
+            .collect()
+    }
+
+    /// Sections of `input` with the line of `input` their content starts on
+    pub fn parse_spans<'a>(&self, input: &'a str) -> Vec<Span<&'a str>> {
+        let body_start = frontmatter::frontmatter_len(input);
         self.section_regex
-            .captures_iter(input)
+            .captures_iter(&input[body_start..])
             .filter_map(|cap| cap.get(1))
-            .map(|m| m.as_str())
+            .map(|m| Span {
+                line: line_at(input, body_start + m.start()),
+                text: m.as_str(),
+            })
             .collect()
     }
 }
@@ -30,11 +100,18 @@ impl Default for SectionParser {
     }
 }
 
-static DECK_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"(?m)^Deck:[ \t]*(.+?)$").expect("Failed to compile deck regex")
+static DECK_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^Deck:[ \t]*(.+?)$").expect("Failed to compile deck regex"));
+static TAGS_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^Tags:[ \t]*(.+?)$").expect("Failed to compile tags regex"));
+static LANG_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^Lang:[ \t]*(\S+)").expect("Failed to compile lang regex"));
+static DIR_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?mi)^Dir:[ \t]*(rtl|ltr|auto)\b").expect("Failed to compile dir regex")
 });
-static TAGS_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"(?m)^Tags:[ \t]*(.+?)$").expect("Failed to compile tags regex")
+// Marks a draft card or section that collection leaves alone
+static SKIP_MARKER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"<!--\s*(?:inka-ignore|skip)\s*-->").expect("Failed to compile skip marker regex")
 });
 
 pub fn extract_deck_name(section: &str) -> Option<String> {
@@ -57,33 +134,145 @@ pub fn extract_tags(section: &str) -> Vec<String> {
         .unwrap_or_default()
 }
 
-pub fn extract_note_strings(section: &str) -> Vec<String> {
+/// Language of the section's cards (`Lang: ar`)
+pub fn extract_lang(section: &str) -> Option<String> {
+    LANG_REGEX
+        .captures(section)
+        .and_then(|cap| cap.get(1))
+        .map(|m| m.as_str().to_string())
+}
+
+/// Text direction of the section's cards (`Dir: rtl`, `ltr` or `auto`)
+pub fn extract_dir(section: &str) -> Option<String> {
+    DIR_REGEX
+        .captures(section)
+        .and_then(|cap| cap.get(1))
+        .map(|m| m.as_str().to_ascii_lowercase())
+}
+
+/// Whether `text` carries an `<!--inka-ignore-->` (or `<!--skip-->`) marker
+pub fn is_skipped(text: &str) -> bool {
+    SKIP_MARKER_REGEX.is_match(text)
+}
+
+/// The section without its skipped cards, or `None` if the section itself is
+/// marked (anywhere before its first card) or all of its cards are. Skipped
+/// cards are replaced by their line breaks, so the remaining lines keep their
+/// line numbers
+pub fn active_section(section: &str, formats: &[CardFormat]) -> Option<String> {
+    let positions = note_positions(section, formats);
+    let header = &section[..positions.first().copied().unwrap_or(section.len())];
+    if is_skipped(header) {
+        return None;
+    }
+
+    let notes = extract_note_strings_for(section, formats);
+    let skipped: Vec<&String> = notes.iter().filter(|note| is_skipped(note)).collect();
+    if skipped.is_empty() {
+        return Some(section.to_string());
+    }
+    if skipped.len() == notes.len() {
+        return None;
+    }
+    let mut active = section.to_string();
+    for note in skipped {
+        let line_breaks = "\n".repeat(note.matches('\n').count());
+        active = active.replacen(note.as_str(), &line_breaks, 1);
+    }
+    Some(active)
+}
+
+/// Start of the note whose first line begins at `pos`: ID comments and skip
+/// markers on the lines directly above belong to the note
+fn attached_start(section: &str, pos: usize) -> usize {
+    let mut start = pos;
+    while let Some(before) = section[..start].strip_suffix('\n') {
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line = before[line_start..].trim();
+        let attached = line.starts_with("<!--ID:")
+            || SKIP_MARKER_REGEX
+                .find(line)
+                .is_some_and(|m| m.as_str() == line);
+        if !attached {
+            break;
+        }
+        start = before.len() - before[line_start..].trim_start().len();
+    }
+    start
+}
+
+fn note_positions(section: &str, formats: &[CardFormat]) -> Vec<usize> {
     // Find all positions where notes start (either "1. " or "<!--ID:...-->\n1. ")
     let mut note_positions: Vec<usize> = Vec::new();
 
-    // Find all lines starting with digits followed by a dot
-    for line in section.lines() {
-        if let Some(trimmed) = line.trim_start().strip_prefix(|c: char| c.is_ascii_digit()) {
-            if trimmed.starts_with('.') {
-                // Found a note start, get its position in the original string
-                if let Some(pos) = section.find(line) {
-                    // Check if there's an ID comment before this line
-                    let before = &section[..pos];
-                    if let Some(last_line) = before.lines().last() {
-                        if last_line.trim().starts_with("<!--ID:") {
-                            // Include the ID comment
-                            if let Some(id_pos) = section[..pos].rfind("<!--ID:") {
-                                note_positions.push(id_pos);
-                                continue;
-                            }
-                        }
-                    }
-                    note_positions.push(pos);
-                }
-            }
+    // Find all lines starting a note in one of the formats ("1." or "Q:"),
+    // by their own offset so repeated identical lines each count
+    let lines: Vec<&str> = section.split_inclusive('\n').collect();
+    // A Q/A answer runs up to the next card, so numbered lines in it are
+    // list items unless they make up a card of their own
+    let mut in_qa_card = false;
+    let mut in_qa_answer = false;
+    // Lines of a fenced code block (e.g. `# comment` in shell code) start nothing
+    let mut in_fence = false;
+    let mut pos = 0;
+    for (index, line) in lines.iter().enumerate() {
+        let text = line.trim_end_matches(['\n', '\r']);
+        let starts = formats.iter().find(|format| {
+            !in_fence
+                && format.is_note_start(text)
+                && !(in_qa_answer
+                    && **format == CardFormat::Numbered
+                    && !is_numbered_card(&lines[index..]))
+        });
+        if let Some(&format) = starts {
+            // Include ID comments and skip markers right before this line
+            note_positions.push(attached_start(section, pos));
+            in_qa_card = format == CardFormat::Qa;
+            in_qa_answer = false;
+        } else if in_qa_card && text.trim_start().starts_with("A:") {
+            in_qa_answer = true;
+        }
+        // Checked on the untrimmed line: a fence indented four spaces is code
+        let indent = text.len() - text.trim_start().len();
+        if indent <= 3 && card_parser::is_fence(text.trim()) {
+            in_fence = !in_fence;
         }
+        pos += line.len();
     }
 
+    note_positions
+}
+
+/// Whether the numbered item starting `lines` is a card of its own: a `>`
+/// answer or a cloze before the next blank line
+fn is_numbered_card(lines: &[&str]) -> bool {
+    let item: Vec<&str> = lines
+        .iter()
+        .map(|line| line.trim_end_matches(['\n', '\r']))
+        .take_while(|line| !line.trim().is_empty())
+        .collect();
+    item.iter().any(|line| line.trim_start().starts_with('>'))
+        || cloze_converter::has_cloze(&item.join("\n"))
+}
+
+/// Note strings of numbered cards
+pub fn extract_note_strings(section: &str) -> Vec<String> {
+    extract_note_strings_for(section, CardFormat::DEFAULT)
+}
+
+/// Note strings of cards in any of `formats`, in section order
+pub fn extract_note_strings_for(section: &str, formats: &[CardFormat]) -> Vec<String> {
+    extract_note_spans_for(section, formats)
+        .into_iter()
+        .map(|span| span.text)
+        .collect()
+}
+
+/// Note strings of cards in any of `formats` with the line of the section
+/// they start on (their ID comment, if any), in section order
+pub fn extract_note_spans_for(section: &str, formats: &[CardFormat]) -> Vec<Span<String>> {
+    let note_positions = note_positions(section, formats);
+
     // Extract note strings by slicing between positions
     let mut notes = Vec::new();
     for i in 0..note_positions.len() {
@@ -94,8 +283,10 @@ pub fn extract_note_strings(section: &str) -> Vec<String> {
             section.len()
         };
 
-        let note_str = section[start..end].trim_end().to_string();
-        notes.push(note_str);
+        notes.push(Span {
+            line: line_at(section, start),
+            text: section[start..end].trim_end().to_string(),
+        });
     }
 
     notes
@@ -151,6 +342,64 @@ Deck: Second
         assert!(sections[1].contains("Second"));
     }
 
+    #[test]
+    fn given_frontmatter_when_parsing_then_skips_it() {
+        let input = r#"---
+title: Ownership
+tags: [rust]
+---
+
+# Ownership
+
+---
+Deck: Rust
+1. Q1
+> A1
+---"#;
+
+        let sections = SectionParser::new().parse(input);
+
+        assert_eq!(sections.len(), 1);
+        assert!(sections[0].contains("Deck: Rust"));
+    }
+
+    #[test]
+    fn given_file_starting_with_card_section_when_parsing_then_keeps_it() {
+        let input =
+            "---\nDeck: Rust\nQ: What is Rust?\nA: A language\n---\n\n---\n1. Q2\n> A2\n---";
+
+        let sections = SectionParser::new().parse(input);
+
+        assert_eq!(sections.len(), 2);
+        assert!(sections[0].contains("Q: What is Rust?"));
+        assert!(sections[1].contains("1. Q2"));
+    }
+
+    #[test]
+    fn given_custom_delimiters_when_parsing_then_ignores_dashes() {
+        let input = r#"---
+title: Notes
+---
+
+<!--inka-start-->
+Deck: Custom
+1. Q1
+> A1
+
+---
+
+2. Q2
+> A2
+<!--inka-end-->"#;
+
+        let sections =
+            SectionParser::with_delimiters("<!--inka-start-->", "<!--inka-end-->").parse(input);
+
+        assert_eq!(sections.len(), 1);
+        assert!(sections[0].contains("Deck: Custom"));
+        assert!(sections[0].contains("Q2"));
+    }
+
     #[test]
     fn given_markdown_without_sections_when_parsing_then_returns_empty() {
         let input = "Just regular markdown\nNo sections here";
@@ -209,6 +458,16 @@ Deck: Second
         assert_eq!(tags, Vec::<String>::new());
     }
 
+    #[test]
+    fn given_section_with_lang_and_dir_when_extracting_then_returns_them() {
+        let section = "Deck: Arabic\nLang: ar\nDir: RTL\n1. Q\n> A";
+
+        assert_eq!(extract_lang(section).as_deref(), Some("ar"));
+        assert_eq!(extract_dir(section).as_deref(), Some("rtl"));
+        assert_eq!(extract_lang("1. Q\n> A"), None);
+        assert_eq!(extract_dir("Dir: sideways\n1. Q"), None);
+    }
+
     #[test]
     fn given_section_with_two_notes_when_extracting_then_returns_two_strings() {
         let section = "Deck: Test\n1. First Q\n> First A\n2. Second Q\n> Second A";
@@ -229,6 +488,55 @@ Deck: Second
         assert!(notes[1].contains("<!--ID:456-->"));
     }
 
+    #[test]
+    fn given_file_with_frontmatter_when_parsing_spans_then_reports_file_lines() {
+        let input = "---\ntags: [a]\n---\n# Title\n\n---\nDeck: Test\n\n1. Q1\n> A1\n\n<!--ID:7-->\n2. Q2\n> A2\n---\n";
+
+        let sections = SectionParser::new().parse_spans(input);
+        let notes = extract_note_spans_for(sections[0].text, CardFormat::DEFAULT);
+
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].line, 7);
+        let lines: Vec<usize> = notes.iter().map(|note| note.line).collect();
+        assert_eq!(lines, vec![3, 6]);
+        assert_eq!(notes[1].text, "<!--ID:7-->\n2. Q2\n> A2");
+    }
+
+    #[test]
+    fn given_skipped_card_when_taking_active_section_then_later_cards_keep_their_lines() {
+        let section = "1. Q1\n> A1\n<!--inka-ignore-->\n2. Q2\n> A2\n3. Q3\n> A3";
+
+        let active = active_section(section, CardFormat::DEFAULT).unwrap();
+        let notes = extract_note_spans_for(&active, CardFormat::DEFAULT);
+
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[1].line, 6);
+        assert_eq!(notes[1].text, "3. Q3\n> A3");
+    }
+
+    #[test]
+    fn given_double_digit_numbers_when_extracting_then_each_card_is_separate() {
+        let section: String = (1..=12)
+            .map(|n| format!("{}. Q{}\n> A{}\n", n, n, n))
+            .collect();
+        let notes = extract_note_strings(&section);
+
+        assert_eq!(notes.len(), 12);
+        assert_eq!(notes[9], "10. Q10\n> A10");
+        assert_eq!(notes[11], "12. Q12\n> A12");
+    }
+
+    #[test]
+    fn given_repeated_identical_lines_when_extracting_then_each_note_keeps_its_own() {
+        let section = "1. Same\n> A1\n1. Same\n> A2\n1. Same\n> A3";
+        let notes = extract_note_strings(section);
+
+        assert_eq!(
+            notes,
+            vec!["1. Same\n> A1", "1. Same\n> A2", "1. Same\n> A3"]
+        );
+    }
+
     #[test]
     fn given_section_with_cloze_and_basic_when_extracting_then_finds_both() {
         let section = "1. Basic Q\n> Basic A\n2. Cloze {{c1::text}}";
@@ -236,4 +544,104 @@ Deck: Second
 
         assert_eq!(notes.len(), 2);
     }
+
+    #[test]
+    fn given_skip_marker_above_card_when_extracting_then_marker_belongs_to_that_card() {
+        let section = "1. Q1\n> A1\n\n<!--inka-ignore-->\n<!--ID:456-->\n2. Q2\n> A2";
+        let notes = extract_note_strings(section);
+
+        assert_eq!(notes.len(), 2);
+        assert!(!is_skipped(&notes[0]));
+        assert!(is_skipped(&notes[1]));
+        assert!(notes[1].starts_with("<!--inka-ignore-->\n<!--ID:456-->"));
+    }
+
+    #[test]
+    fn given_skipped_cards_when_getting_active_section_then_removes_only_them() {
+        let section = "Deck: Test\n\n1. Keep me\n> A1\n\n<!--skip-->\n2. Draft\n> A2\n\n3. WIP <!--inka-ignore-->\n> A3\n\n4. Keep too\n> A4";
+
+        let active = active_section(section, CardFormat::DEFAULT).unwrap();
+        let notes = extract_note_strings(&active);
+
+        assert_eq!(notes.len(), 2);
+        assert!(notes[0].contains("Keep me"));
+        assert!(notes[1].contains("Keep too"));
+        assert!(active.contains("Deck: Test"));
+    }
+
+    #[test]
+    fn given_marker_in_section_header_when_getting_active_section_then_skips_section() {
+        let section = "Deck: Test\n<!-- inka-ignore -->\n\n1. Q1\n> A1";
+
+        assert_eq!(active_section(section, CardFormat::DEFAULT), None);
+    }
+
+    #[test]
+    fn given_only_skipped_cards_when_getting_active_section_then_skips_section() {
+        let section = "Deck: Test\n\n<!--skip-->\n1. Q1\n> A1";
+
+        assert_eq!(active_section(section, CardFormat::DEFAULT), None);
+        assert_eq!(
+            active_section("Deck: Test\n1. Q1\n> A1", CardFormat::DEFAULT).as_deref(),
+            Some("Deck: Test\n1. Q1\n> A1")
+        );
+    }
+
+    #[test]
+    fn given_qa_format_when_extracting_then_splits_on_question_lines() {
+        let section = "Deck: Test\n\nQ: First?\nA: One\n\n<!--ID:456-->\nQ: Second?\nspans lines\nA: Two\n\n1. Numbered\n> Three";
+
+        let notes = extract_note_strings_for(section, &[CardFormat::Numbered, CardFormat::Qa]);
+
+        assert_eq!(notes.len(), 3);
+        assert_eq!(notes[0], "Q: First?\nA: One");
+        assert!(notes[1].starts_with("<!--ID:456-->\nQ: Second?"));
+        assert_eq!(notes[2], "1. Numbered\n> Three");
+    }
+
+    #[test]
+    fn given_ordered_list_in_qa_answer_when_extracting_then_keeps_it_in_the_card() {
+        let section = "Q: Steps?\nA: In order:\n\n1. Borrow\n2. Return\n   3. Indented\n\n1. Next card\n> Answer";
+
+        let notes = extract_note_strings_for(section, &[CardFormat::Numbered, CardFormat::Qa]);
+
+        assert_eq!(notes.len(), 2);
+        assert_eq!(
+            notes[0],
+            "Q: Steps?\nA: In order:\n\n1. Borrow\n2. Return\n   3. Indented"
+        );
+        assert_eq!(notes[1], "1. Next card\n> Answer");
+    }
+
+    #[test]
+    fn given_headings_in_code_when_extracting_then_they_start_no_cards() {
+        let section = "## Shell #card\n```sh\n# comment\n## another #card\n```\n\n    ## indented code\n## Next #card\nBody";
+
+        let every = extract_note_strings_for(section, &[CardFormat::Heading]);
+        let marked = extract_note_strings_for(section, &[CardFormat::HeadingCard]);
+
+        assert_eq!(every.len(), 2);
+        assert_eq!(marked, every);
+        assert!(every[0].ends_with("    ## indented code"));
+        assert_eq!(every[1], "## Next #card\nBody");
+    }
+
+    #[test]
+    fn given_qa_cards_without_qa_format_when_extracting_then_ignores_them() {
+        let section = "Q: First?\nA: One";
+
+        assert!(extract_note_strings(section).is_empty());
+    }
+
+    #[test]
+    fn given_heading_formats_when_extracting_then_splits_on_headings() {
+        let section = "Deck: Test\n\n## Ownership\nOne owner.\n\n## Notes\nnot a card\n\n<!--ID:7-->\n## Borrowing #card\nReferences.";
+
+        let every = extract_note_strings_for(section, &[CardFormat::Heading]);
+        let marked = extract_note_strings_for(section, &[CardFormat::HeadingCard]);
+
+        assert_eq!(every.len(), 3);
+        assert_eq!(every[0], "## Ownership\nOne owner.");
+        assert_eq!(marked, vec!["<!--ID:7-->\n## Borrowing #card\nReferences."]);
+    }
 }
@@ -1,5 +1,11 @@
+use crate::inka::infrastructure::config::MediaConfig;
+use crate::inka::infrastructure::image_processing::{media_content, media_path};
+use html_escape::{decode_html_entities, encode_double_quoted_attribute};
+use percent_encoding::percent_decode_str;
 use regex::Regex;
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
+use unicode_normalization::UnicodeNormalization;
 
 // Match markdown images: ![alt](path)
 static MD_IMAGE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
@@ -11,18 +17,57 @@ static HTML_IMAGE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r#"<img[^>]+src="([^"]+)""#).expect("Failed to compile HTML image regex")
 });
 
+// Match markdown images with empty alt text: ![](path), also with only a
+// size hint: ![|300](path)
+static MD_IMAGE_NO_ALT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"!\[\s*(?:\|[\dx\s]*)?\]\(([^)]+)\)")
+        .expect("Failed to compile markdown image regex")
+});
+
+// Match whole HTML img tags and non-empty alt attributes within them
+static HTML_IMAGE_TAG_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<img\b[^>]*>").expect("Failed to compile HTML image regex"));
+static HTML_ALT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\balt\s*=\s*"[^"]*[^"\s][^"]*""#).expect("Failed to compile HTML alt regex")
+});
+
+/// Normalize an image path so the same file matches across markdown, HTML
+/// and the filesystem: HTML entities (`&amp;`) and percent-escapes (`%20`) are
+/// decoded, unicode is NFC-composed (macOS hands out NFD filenames) and
+/// Windows `\` separators become `/`
+pub fn normalize_media_path(path: &str) -> String {
+    percent_decode_str(&decode_html_entities(path))
+        .decode_utf8_lossy()
+        .nfc()
+        .map(|c| if c == '\\' { '/' } else { c })
+        .collect()
+}
+
+/// Destination of a markdown image, without `<...>` brackets or a title
+fn markdown_image_destination(destination: &str) -> &str {
+    let destination = destination.trim();
+    if let Some(rest) = destination.strip_prefix('<') {
+        return rest.split_once('>').map_or(rest, |(path, _)| path);
+    }
+    match destination.split_once(char::is_whitespace) {
+        Some((path, title)) if title.trim_start().starts_with(['"', '\'', '(']) => path,
+        _ => destination,
+    }
+}
+
 /// Extract image paths from markdown content
 /// Supports both markdown syntax ![alt](path) and HTML <img src="path">
+/// Paths are returned normalized (see `normalize_media_path`)
 pub fn extract_image_paths(markdown: &str) -> Vec<String> {
     let mut paths = Vec::new();
 
     // Extract markdown format images
     for cap in MD_IMAGE_REGEX.captures_iter(markdown) {
         if let Some(path_match) = cap.get(1) {
-            let path = path_match.as_str();
+            let path = markdown_image_destination(path_match.as_str());
             // Skip HTTP(S) URLs
             if !path.starts_with("http://") && !path.starts_with("https://") {
-                paths.push(path.to_string());
+                paths.push(normalize_media_path(path));
             }
         }
     }
@@ -33,7 +78,7 @@ pub fn extract_image_paths(markdown: &str) -> Vec<String> {
             let path = path_match.as_str();
             // Skip HTTP(S) URLs
             if !path.starts_with("http://") && !path.starts_with("https://") {
-                paths.push(path.to_string());
+                paths.push(normalize_media_path(path));
             }
         }
     }
@@ -41,92 +86,223 @@ pub fn extract_image_paths(markdown: &str) -> Vec<String> {
     paths
 }
 
+/// Resolve an image path relative to the markdown file's directory
+///
+/// If the literal path doesn't exist, the containing directory is searched
+/// for an entry whose name differs only in unicode normalization.
+pub fn resolve_media_path(markdown_dir: &Path, image_path: &str) -> PathBuf {
+    let candidate = if Path::new(image_path).is_absolute() {
+        PathBuf::from(image_path)
+    } else {
+        // Join component-wise so `/` and `\` both work on every platform
+        let mut candidate = markdown_dir.to_path_buf();
+        candidate.extend(image_path.split(['/', '\\']).filter(|c| !c.is_empty()));
+        candidate
+    };
+    if candidate.exists() {
+        return candidate;
+    }
+
+    let (Some(parent), Some(name)) = (
+        candidate.parent(),
+        candidate.file_name().and_then(|n| n.to_str()),
+    ) else {
+        return candidate;
+    };
+    let wanted: String = name.nfc().collect();
+    let Ok(entries) = std::fs::read_dir(parent) else {
+        return candidate;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .find(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|n| n.nfc().eq(wanted.chars()))
+        })
+        .map_or(candidate, |entry| entry.path())
+}
+
+/// Name a media file gets in collection.media/: the source basename in NFC,
+/// as Anki normalizes media filenames itself (`.png` for rasterized SVGs)
+pub fn media_filename(source_path: &Path, limits: &MediaConfig) -> anyhow::Result<String> {
+    media_path(source_path, limits)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n.nfc().collect())
+        .ok_or_else(|| anyhow::anyhow!("Invalid filename"))
+}
+
+/// Find images without alt text (markdown `![](path)` or `<img>` lacking `alt`)
+/// Returns the image paths
+pub fn find_images_without_alt(markdown: &str) -> Vec<String> {
+    let mut paths: Vec<String> = MD_IMAGE_NO_ALT_REGEX
+        .captures_iter(markdown)
+        .map(|cap| cap[1].to_string())
+        .collect();
+
+    for tag in HTML_IMAGE_TAG_REGEX.find_iter(markdown) {
+        let tag = tag.as_str();
+        if HTML_ALT_REGEX.is_match(tag) {
+            continue;
+        }
+        if let Some(cap) = HTML_IMAGE_REGEX.captures(tag) {
+            paths.push(cap[1].to_string());
+        }
+    }
+
+    paths
+}
+
+/// What to do when collection.media already has a different file with the
+/// name of a media file being copied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaConflictAction {
+    /// Stop with an error (the default without `--force`)
+    Fail,
+    /// Overwrite Anki's file
+    Overwrite,
+    /// Leave Anki's file and reference it as is
+    KeepExisting,
+}
+
 /// Copy a media file to Anki's collection.media directory
+/// Large images are shrunk to `limits` on the way (see `media_content`)
 /// Returns the filename (not full path) that Anki will use
 pub fn copy_media_to_anki(
     source_path: &std::path::Path,
     media_dir: &std::path::Path,
     force: bool,
+    limits: &MediaConfig,
+) -> anyhow::Result<String> {
+    copy_media_to_anki_with(source_path, media_dir, limits, |_| {
+        if force {
+            MediaConflictAction::Overwrite
+        } else {
+            MediaConflictAction::Fail
+        }
+    })
+}
+
+/// `copy_media_to_anki` asking `on_conflict` (with the media filename) what
+/// to do about a different file of the same name
+pub fn copy_media_to_anki_with(
+    source_path: &std::path::Path,
+    media_dir: &std::path::Path,
+    limits: &MediaConfig,
+    on_conflict: impl FnOnce(&str) -> MediaConflictAction,
 ) -> anyhow::Result<String> {
     use anyhow::Context;
 
     // Extract filename from source path
-    let filename = source_path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .ok_or_else(|| anyhow::anyhow!("Invalid filename"))?;
+    let filename = media_filename(source_path, limits)?;
+    let content = media_content(source_path, limits)?;
 
-    let dest_path = media_dir.join(filename);
+    let dest_path = media_dir.join(&filename);
 
     // Check if file exists in media directory
     if dest_path.exists() {
         // Use filecmp equivalent - compare file contents
-        let files_identical = files_are_identical(source_path, &dest_path)
-            .context("Failed to compare file contents")?;
+        let files_identical =
+            file_has_content(&dest_path, &content).context("Failed to compare file contents")?;
 
         if files_identical {
             // Same file already exists - optimization, skip copy
-            return Ok(filename.to_string());
+            return Ok(filename);
         }
 
         // Files have different content
-        if !force {
-            // Error on conflict without --force
-            return Err(anyhow::anyhow!(
-                "Different file with the same name \"{}\" already exists in Anki Media folder. \
-                 Use --force to overwrite.",
-                filename
-            ));
+        match on_conflict(&filename) {
+            MediaConflictAction::Fail => {
+                return Err(anyhow::anyhow!(
+                    "Different file with the same name \"{}\" already exists in Anki Media folder. \
+                     Use --force to overwrite.",
+                    filename
+                ));
+            }
+            MediaConflictAction::KeepExisting => return Ok(filename),
+            MediaConflictAction::Overwrite => {}
         }
-
-        // force=true: overwrite existing file
     }
 
-    // Copy file (either new or force overwrite)
-    std::fs::copy(source_path, &dest_path).context("Failed to copy media file")?;
+    // Copy file (either new or overwriting a conflicting one)
+    std::fs::write(&dest_path, content).context("Failed to copy media file")?;
 
-    Ok(filename.to_string())
+    Ok(filename)
 }
 
-/// Compare two files for identical content
-fn files_are_identical(path1: &std::path::Path, path2: &std::path::Path) -> anyhow::Result<bool> {
-    use std::io::Read;
+/// Filename a media file would get in Anki's collection.media directory, and
+/// whether `copy_media_to_anki` would have to write it (missing or different)
+pub fn media_needs_copy(
+    source_path: &std::path::Path,
+    media_dir: &std::path::Path,
+    limits: &MediaConfig,
+) -> anyhow::Result<(String, bool)> {
+    use anyhow::Context;
 
-    let mut file1 = std::fs::File::open(path1)?;
-    let mut file2 = std::fs::File::open(path2)?;
+    let filename = media_filename(source_path, limits)?;
 
-    // Quick size check first
-    let meta1 = file1.metadata()?;
-    let meta2 = file2.metadata()?;
+    if !source_path.exists() {
+        return Err(anyhow::anyhow!(
+            "Media file not found: {}",
+            source_path.display()
+        ));
+    }
+
+    let dest_path = media_dir.join(&filename);
+    let needs_copy = !dest_path.exists()
+        || !file_has_content(&dest_path, &media_content(source_path, limits)?)
+            .context("Failed to compare file contents")?;
+
+    Ok((filename, needs_copy))
+}
 
-    if meta1.len() != meta2.len() {
+/// Compare a file with the content it should have
+fn file_has_content(path: &std::path::Path, content: &[u8]) -> anyhow::Result<bool> {
+    // Quick size check first
+    if std::fs::metadata(path)?.len() != content.len() as u64 {
         return Ok(false);
     }
 
     // Compare contents byte by byte
-    let mut buf1 = Vec::new();
-    let mut buf2 = Vec::new();
-
-    file1.read_to_end(&mut buf1)?;
-    file2.read_to_end(&mut buf2)?;
-
-    Ok(buf1 == buf2)
+    Ok(std::fs::read(path)? == content)
 }
 
 /// Update image paths in HTML to use Anki media filenames
 /// Takes a mapping of original paths to Anki filenames
+///
+/// Only parsed `<img src>` attributes are rewritten, matched after
+/// normalization: `a&amp;b%20c.png` in the HTML matches a mapping for
+/// `a&b c.png`. The new filename is attribute-escaped.
 pub fn update_media_paths_in_html(
     html: &str,
     path_mapping: &std::collections::HashMap<String, String>,
 ) -> String {
-    let mut result = html.to_string();
-
-    // Replace each original path with its Anki filename
-    for (original_path, anki_filename) in path_mapping {
-        result = result.replace(original_path, anki_filename);
-    }
-
-    result
+    let normalized: std::collections::HashMap<String, &String> = path_mapping
+        .iter()
+        .map(|(original_path, anki_filename)| (normalize_media_path(original_path), anki_filename))
+        .collect();
+
+    HTML_IMAGE_REGEX
+        .replace_all(html, |cap: &regex::Captures| {
+            let tag = cap.get(0).expect("whole match");
+            let src = cap.get(1).expect("src group");
+            match normalized.get(&normalize_media_path(src.as_str())) {
+                Some(anki_filename) => {
+                    let start = src.start() - tag.start();
+                    let end = src.end() - tag.start();
+                    format!(
+                        "{}{}{}",
+                        &tag.as_str()[..start],
+                        encode_double_quoted_attribute(anki_filename.as_str()),
+                        &tag.as_str()[end..]
+                    )
+                }
+                None => tag.as_str().to_string(),
+            }
+        })
+        .into_owned()
 }
 
 #[cfg(test)]
@@ -214,7 +390,8 @@ HTTPS: ![secure](https://example.com/photo.png)
         let media_dir = temp_dir.path().join("collection.media");
         fs::create_dir(&media_dir).unwrap();
 
-        let filename = copy_media_to_anki(&source_file, &media_dir, false).unwrap();
+        let filename =
+            copy_media_to_anki(&source_file, &media_dir, false, &MediaConfig::default()).unwrap();
 
         // Should return just the filename
         assert_eq!(filename, "test_image.png");
@@ -245,7 +422,8 @@ HTTPS: ![secure](https://example.com/photo.png)
         fs::write(&existing_file, b"same content").unwrap();
 
         // Copy should succeed and return filename
-        let filename = copy_media_to_anki(&source_file, &media_dir, false).unwrap();
+        let filename =
+            copy_media_to_anki(&source_file, &media_dir, false, &MediaConfig::default()).unwrap();
         assert_eq!(filename, "image.png");
 
         // Should not overwrite (content stays same but we verify no error)
@@ -270,7 +448,7 @@ HTTPS: ![secure](https://example.com/photo.png)
         fs::write(&existing_file, b"old content").unwrap();
 
         // Copy should fail with error about conflict
-        let result = copy_media_to_anki(&source_file, &media_dir, false);
+        let result = copy_media_to_anki(&source_file, &media_dir, false, &MediaConfig::default());
         assert!(result.is_err());
         let error_msg = result.unwrap_err().to_string();
         assert!(error_msg.contains("already exists"));
@@ -294,7 +472,8 @@ HTTPS: ![secure](https://example.com/photo.png)
         fs::write(&existing_file, b"old content").unwrap();
 
         // Copy with force should succeed
-        let filename = copy_media_to_anki(&source_file, &media_dir, true).unwrap();
+        let filename =
+            copy_media_to_anki(&source_file, &media_dir, true, &MediaConfig::default()).unwrap();
         assert_eq!(filename, "image.png");
 
         // Should overwrite with new content
@@ -302,6 +481,32 @@ HTTPS: ![secure](https://example.com/photo.png)
         assert_eq!(content, b"new content");
     }
 
+    #[test]
+    fn given_different_file_when_keeping_existing_then_leaves_anki_file() {
+        use std::fs;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let source_file = temp_dir.path().join("image.png");
+        fs::write(&source_file, b"new content").unwrap();
+        let media_dir = temp_dir.path().join("collection.media");
+        fs::create_dir(&media_dir).unwrap();
+        let existing_file = media_dir.join("image.png");
+        fs::write(&existing_file, b"old content").unwrap();
+
+        let mut asked = None;
+        let filename =
+            copy_media_to_anki_with(&source_file, &media_dir, &MediaConfig::default(), |name| {
+                asked = Some(name.to_string());
+                MediaConflictAction::KeepExisting
+            })
+            .unwrap();
+
+        assert_eq!(filename, "image.png");
+        assert_eq!(asked.as_deref(), Some("image.png"));
+        assert_eq!(fs::read(&existing_file).unwrap(), b"old content");
+    }
+
     #[test]
     fn given_nonexistent_source_when_copying_then_returns_error() {
         use std::fs;
@@ -313,7 +518,7 @@ HTTPS: ![secure](https://example.com/photo.png)
         let media_dir = temp_dir.path().join("collection.media");
         fs::create_dir(&media_dir).unwrap();
 
-        let result = copy_media_to_anki(&nonexistent, &media_dir, false);
+        let result = copy_media_to_anki(&nonexistent, &media_dir, false, &MediaConfig::default());
         assert!(result.is_err());
     }
 
@@ -332,7 +537,8 @@ HTTPS: ![secure](https://example.com/photo.png)
         let media_dir = temp_dir.path().join("collection.media");
         fs::create_dir(&media_dir).unwrap();
 
-        let filename = copy_media_to_anki(&source_file, &media_dir, false).unwrap();
+        let filename =
+            copy_media_to_anki(&source_file, &media_dir, false, &MediaConfig::default()).unwrap();
 
         // Should return just filename, not path
         assert_eq!(filename, "photo.jpg");
@@ -417,4 +623,217 @@ HTTPS: ![secure](https://example.com/photo.png)
 
         assert!(updated.contains(r#"src="diagram.png""#));
     }
+
+    #[test]
+    fn given_encoded_and_bracketed_paths_when_extracting_then_returns_decoded_paths() {
+        let markdown = r#"![a](my%20image.png) ![b](<my image.png>) ![c](<Bild äöü.png> "Title")
+<img src="Bild%20%C3%A4%C3%B6%C3%BC.png">"#;
+
+        let paths = extract_image_paths(markdown);
+
+        assert_eq!(
+            paths,
+            vec![
+                "my image.png",
+                "my image.png",
+                "Bild äöü.png",
+                "Bild äöü.png"
+            ]
+        );
+    }
+
+    #[test]
+    fn given_nfd_path_when_normalizing_then_returns_nfc() {
+        let nfd = "Bild a\u{308}o\u{308}u\u{308}.png";
+
+        assert_eq!(normalize_media_path(nfd), "Bild äöü.png");
+    }
+
+    #[test]
+    fn given_percent_encoded_src_when_updating_then_replaces_path() {
+        use std::collections::HashMap;
+
+        let html = r#"<p><img src="img/Bild%20%C3%A4%C3%B6%C3%BC.png" alt="a" /> <img src="my%20image.png" alt="b" /></p>"#;
+        let mut mapping = HashMap::new();
+        mapping.insert("img/Bild äöü.png".to_string(), "Bild äöü.png".to_string());
+        mapping.insert("my image.png".to_string(), "my image.png".to_string());
+
+        let updated = update_media_paths_in_html(html, &mapping);
+
+        assert!(updated.contains(r#"src="Bild äöü.png""#), "{}", updated);
+        assert!(updated.contains(r#"src="my image.png""#), "{}", updated);
+    }
+
+    #[test]
+    fn given_nfd_filename_on_disk_when_resolving_then_finds_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let nfd_name = "Bild a\u{308}o\u{308}u\u{308}.png";
+        std::fs::write(temp_dir.path().join(nfd_name), b"image").unwrap();
+
+        let resolved = resolve_media_path(temp_dir.path(), "Bild äöü.png");
+
+        assert!(resolved.exists());
+        assert_eq!(resolved.file_name().unwrap().to_str().unwrap(), nfd_name);
+    }
+
+    #[test]
+    fn given_nfd_source_when_copying_then_media_filename_is_nfc() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("Bild a\u{308}o\u{308}u\u{308}.png");
+        std::fs::write(&source, b"image").unwrap();
+        let media_dir = temp_dir.path().join("collection.media");
+        std::fs::create_dir(&media_dir).unwrap();
+
+        let filename =
+            copy_media_to_anki(&source, &media_dir, false, &MediaConfig::default()).unwrap();
+
+        assert_eq!(filename, "Bild äöü.png");
+        assert!(media_dir.join("Bild äöü.png").exists());
+    }
+
+    #[test]
+    fn given_space_in_filename_when_copying_then_keeps_name() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("my image.png");
+        std::fs::write(&source, b"image").unwrap();
+        let media_dir = temp_dir.path().join("collection.media");
+        std::fs::create_dir(&media_dir).unwrap();
+
+        let filename =
+            copy_media_to_anki(&source, &media_dir, false, &MediaConfig::default()).unwrap();
+
+        assert_eq!(filename, "my image.png");
+        assert!(media_dir.join("my image.png").exists());
+    }
+
+    #[test]
+    fn given_windows_separators_when_extracting_then_returns_forward_slashes() {
+        let markdown = r#"![a](images\diagram.png) <img src="..\shared\logo.png">"#;
+
+        let paths = extract_image_paths(markdown);
+
+        assert_eq!(paths, vec!["images/diagram.png", "../shared/logo.png"]);
+    }
+
+    #[test]
+    fn given_escaped_backslash_src_when_updating_then_replaces_path() {
+        use std::collections::HashMap;
+
+        // pulldown-cmark renders `images\diagram.png` as `images%5Cdiagram.png`
+        let html = r#"<p><img src="images%5Cdiagram.png" alt="a" /></p>"#;
+        let mut mapping = HashMap::new();
+        mapping.insert("images/diagram.png".to_string(), "diagram.png".to_string());
+
+        let updated = update_media_paths_in_html(html, &mapping);
+
+        assert!(updated.contains(r#"src="diagram.png""#), "{}", updated);
+    }
+
+    #[test]
+    fn given_backslash_path_when_resolving_then_finds_file_in_subdirectory() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("images")).unwrap();
+        std::fs::write(temp_dir.path().join("images").join("diagram.png"), b"image").unwrap();
+
+        let resolved = resolve_media_path(temp_dir.path(), r"images\diagram.png");
+
+        assert_eq!(resolved, temp_dir.path().join("images").join("diagram.png"));
+        assert!(resolved.exists());
+    }
+
+    #[test]
+    fn given_ampersand_in_path_when_updating_then_matches_escaped_src() {
+        use std::collections::HashMap;
+
+        let markdown = "![Q&A](images/q&a.png)";
+        let html = crate::inka::infrastructure::markdown::converter::markdown_to_html(markdown);
+        let mut mapping = HashMap::new();
+        for path in extract_image_paths(markdown) {
+            mapping.insert(path, "q&a.png".to_string());
+        }
+
+        let updated = update_media_paths_in_html(&html, &mapping);
+
+        assert!(updated.contains(r#"src="q&amp;a.png""#), "{}", updated);
+        assert!(!updated.contains("images/"), "{}", updated);
+    }
+
+    #[test]
+    fn given_path_also_in_alt_when_updating_then_only_rewrites_src() {
+        use std::collections::HashMap;
+
+        let html = r#"<img alt="see photo.png" src="photo.png">"#;
+        let mut mapping = HashMap::new();
+        mapping.insert("photo.png".to_string(), "photo-1.png".to_string());
+
+        let updated = update_media_paths_in_html(html, &mapping);
+
+        assert_eq!(updated, r#"<img alt="see photo.png" src="photo-1.png">"#);
+    }
+
+    #[test]
+    fn given_entity_in_html_img_when_extracting_then_decodes_path() {
+        let markdown = r#"<img src="R&amp;D.png">"#;
+
+        assert_eq!(extract_image_paths(markdown), vec!["R&D.png"]);
+    }
+
+    #[test]
+    fn given_svg_with_rasterize_when_copying_then_stores_png() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("diagram.svg");
+        std::fs::write(
+            &source,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"/>"#,
+        )
+        .unwrap();
+        let media_dir = temp_dir.path().join("collection.media");
+        std::fs::create_dir(&media_dir).unwrap();
+        let limits = MediaConfig {
+            rasterize_svg: true,
+            ..Default::default()
+        };
+
+        let filename = copy_media_to_anki(&source, &media_dir, false, &limits).unwrap();
+
+        assert_eq!(filename, "diagram.png");
+        let (_, needs_copy) = media_needs_copy(&source, &media_dir, &limits).unwrap();
+        assert!(!needs_copy);
+    }
+
+    #[test]
+    fn given_images_with_and_without_alt_when_checking_then_returns_only_missing() {
+        let markdown = r#"![Diagram](a.png) ![](b.png) ![ ](c.png) ![|300](g.png) ![G|300](h.png)
+<img src="d.png" alt="D"> <img src="e.png"> <img alt="" src="f.png">"#;
+
+        let missing = find_images_without_alt(markdown);
+
+        assert_eq!(missing, vec!["b.png", "c.png", "g.png", "e.png", "f.png"]);
+    }
+
+    #[test]
+    fn given_media_file_when_checking_copy_then_reports_missing_and_identical() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let media_dir = temp_dir.path().join("collection.media");
+        std::fs::create_dir_all(&media_dir).unwrap();
+        let source = temp_dir.path().join("photo.png");
+        std::fs::write(&source, b"image").unwrap();
+
+        let (filename, needs_copy) =
+            media_needs_copy(&source, &media_dir, &MediaConfig::default()).unwrap();
+        assert_eq!(filename, "photo.png");
+        assert!(needs_copy);
+
+        std::fs::write(media_dir.join("photo.png"), b"image").unwrap();
+        let (_, needs_copy) =
+            media_needs_copy(&source, &media_dir, &MediaConfig::default()).unwrap();
+        assert!(!needs_copy);
+        // Nothing was written by the check itself
+        assert!(media_needs_copy(
+            &temp_dir.path().join("missing.png"),
+            &media_dir,
+            &MediaConfig::default()
+        )
+        .is_err());
+    }
 }
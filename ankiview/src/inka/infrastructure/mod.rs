@@ -1,5 +1,12 @@
 pub mod config;
 pub mod file_writer;
 pub mod hasher;
+pub mod history;
+pub mod hooks;
+pub mod image_processing;
 pub mod markdown;
 pub mod media_handler;
+pub mod occlusion;
+pub mod org_parser;
+pub mod spell_checker;
+pub mod templates;
@@ -0,0 +1,167 @@
+use std::fmt::Write;
+
+// Colors Image Occlusion Enhanced uses for the asked and the other shapes
+const QUESTION_FILL: &str = "#FF7E7E";
+const SHAPE_FILL: &str = "#FFEBA2";
+const STROKE: &str = "#2D2D2D";
+
+/// Rectangle on the image in pixels, parsed from `X,Y,WIDTH,HEIGHT`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl std::str::FromStr for Rect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<u32> = s
+            .split(',')
+            .map(|v| v.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("'{}' is not X,Y,WIDTH,HEIGHT in pixels", s))?;
+        match values[..] {
+            [x, y, width, height] if width > 0 && height > 0 => Ok(Self {
+                x,
+                y,
+                width,
+                height,
+            }),
+            [_, _, _, _] => Err(format!("'{}' has an empty width or height", s)),
+            _ => Err(format!("'{}' is not X,Y,WIDTH,HEIGHT in pixels", s)),
+        }
+    }
+}
+
+/// What the other rectangles do while one is asked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OcclusionMode {
+    /// All rectangles stay covered; only the asked one is revealed on the back
+    #[default]
+    HideAll,
+    /// Only the asked rectangle is covered
+    HideOne,
+}
+
+impl std::str::FromStr for OcclusionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hide-all" => Ok(Self::HideAll),
+            "hide-one" => Ok(Self::HideOne),
+            _ => Err(format!("'{}' is not hide-all or hide-one", s)),
+        }
+    }
+}
+
+/// Question and answer mask of the note asking rectangle `asked`
+pub fn note_masks(
+    width: u32,
+    height: u32,
+    rects: &[Rect],
+    asked: usize,
+    mode: OcclusionMode,
+) -> (String, String) {
+    let shapes = |include_asked: bool| {
+        rects
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| match mode {
+                OcclusionMode::HideAll => include_asked || *index != asked,
+                OcclusionMode::HideOne => include_asked && *index == asked,
+            })
+            .map(|(index, rect)| (rect, index == asked))
+            .collect::<Vec<_>>()
+    };
+    (
+        mask_svg(width, height, &shapes(true)),
+        mask_svg(width, height, &shapes(false)),
+    )
+}
+
+/// Mask with every rectangle, shared by all notes of an image
+pub fn original_mask(width: u32, height: u32, rects: &[Rect]) -> String {
+    let shapes: Vec<_> = rects.iter().map(|rect| (rect, false)).collect();
+    mask_svg(width, height, &shapes)
+}
+
+/// SVG the size of the image with the given rectangles; asked ones in red
+fn mask_svg(width: u32, height: u32, shapes: &[(&Rect, bool)]) -> String {
+    let mut svg = format!(
+        "<svg width=\"{}\" height=\"{}\" xmlns=\"http://www.w3.org/2000/svg\">\n <g>\n  <title>Masks</title>\n",
+        width, height
+    );
+    for (rect, asked) in shapes {
+        let (fill, class) = if *asked {
+            (QUESTION_FILL, "qshape")
+        } else {
+            (SHAPE_FILL, "shape")
+        };
+        // Writing to a String can't fail
+        let _ = writeln!(
+            svg,
+            "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" stroke=\"{}\" fill=\"{}\" class=\"{}\"/>",
+            rect.x, rect.y, rect.width, rect.height, STROKE, fill, class
+        );
+    }
+    svg.push_str(" </g>\n</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rects() -> Vec<Rect> {
+        vec![
+            "10,10,50,20".parse().unwrap(),
+            "10,40,50,20".parse().unwrap(),
+        ]
+    }
+
+    #[test]
+    fn given_rect_spec_when_parsing_then_reads_pixels() {
+        let rect: Rect = "5, 6,70,80".parse().unwrap();
+
+        assert_eq!(
+            rect,
+            Rect {
+                x: 5,
+                y: 6,
+                width: 70,
+                height: 80
+            }
+        );
+        assert!("5,6,0,80".parse::<Rect>().is_err());
+        assert!("5,6,70".parse::<Rect>().is_err());
+    }
+
+    #[test]
+    fn given_hide_all_when_building_masks_then_answer_keeps_other_shapes() {
+        let (question, answer) = note_masks(200, 100, &rects(), 1, OcclusionMode::HideAll);
+
+        assert!(question.starts_with("<svg width=\"200\" height=\"100\""));
+        assert_eq!(question.matches("<rect").count(), 2);
+        assert!(question.contains("y=\"40\" width=\"50\" height=\"20\" stroke=\"#2D2D2D\" fill=\"#FF7E7E\" class=\"qshape\""));
+        assert_eq!(answer.matches("<rect").count(), 1);
+        assert!(answer.contains("y=\"10\""));
+        assert!(!answer.contains("qshape"));
+    }
+
+    #[test]
+    fn given_hide_one_when_building_masks_then_only_asked_shape_is_covered() {
+        let (question, answer) = note_masks(200, 100, &rects(), 0, OcclusionMode::HideOne);
+
+        assert_eq!(question.matches("<rect").count(), 1);
+        assert!(question.contains("class=\"qshape\""));
+        assert_eq!(answer.matches("<rect").count(), 0);
+        assert_eq!(
+            original_mask(200, 100, &rects()).matches("<rect").count(),
+            2
+        );
+    }
+}
@@ -0,0 +1,520 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Property holding the Anki note ID in a card headline's drawer (as in anki-editor)
+pub const NOTE_ID_PROPERTY: &str = "ANKI_NOTE_ID";
+/// Headline tags marking a card (org-drill and anki-editor style)
+const CARD_TAGS: [&str; 2] = ["drill", "anki"];
+
+// `** Title   :tag1:tag2:`
+static HEADLINE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\*+)\s+(.*?)(?:\s+(:[\w@#%:]+:))?\s*$")
+        .expect("Failed to compile org headline regex")
+});
+static PROPERTY_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*:([\w-]+):\s*(.*?)\s*$").expect("Failed to compile org property regex")
+});
+static PLANNING_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:SCHEDULED|DEADLINE|CLOSED):").expect("Failed to compile org planning regex")
+});
+// `[[target][description]]` and `[[target]]`
+static LINK_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[\[([^\]]+)\](?:\[([^\]]*)\])?\]").expect("Failed to compile org link regex")
+});
+// Emphasis marker and its markdown, verbatim/code first so their content
+// isn't taken for emphasis
+const EMPHASIS: [(char, &str, &str); 5] = [
+    ('=', "`", "`"),
+    ('~', "`", "`"),
+    ('*', "**", "**"),
+    ('/', "*", "*"),
+    ('+', "~~", "~~"),
+];
+// Markers need whitespace or punctuation around them, so `a*b*c` and paths
+// like `/usr/bin/` stay as they are
+static EMPHASIS_REGEXES: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    EMPHASIS
+        .iter()
+        .map(|(marker, _, _)| {
+            let m = regex::escape(&marker.to_string());
+            Regex::new(&format!(
+                r#"(^|[\s('"{{])(?:{m})([^\s{m}](?:[^{m}\n]*?[^\s{m}])?)(?:{m})($|[\s.,;:!?)'"}}\-])"#
+            ))
+            .expect("Failed to compile org emphasis regex")
+        })
+        .collect()
+});
+// org-drill cloze `[text]` or `[text||hint]`; not links, footnotes or checkboxes
+static DRILL_CLOZE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(^|[^\[\]])\[([^\[\]\n]+?)(?:\|\|([^\[\]\n]+?))?\]($|[^\[\(\]])")
+        .expect("Failed to compile org-drill cloze regex")
+});
+
+const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp"];
+
+/// A card from a `:drill:` or `:anki:` tagged headline, with markdown fields
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrgCard {
+    /// Line index (0-based) of the card's headline
+    pub headline_line: usize,
+    /// `:ANKI_NOTE_ID:` property
+    pub id: Option<i64>,
+    /// `:ANKI_NOTE_ID:` value that isn't a note ID (then `id` is `None`)
+    pub malformed_id: Option<String>,
+    /// `:ANKI_DECK:` property, or the file's `#+ANKI_DECK:` keyword
+    pub deck: Option<String>,
+    /// Headline tags without the card marker tags
+    pub tags: Vec<String>,
+    /// Question, or the cloze text for cloze cards
+    pub front_md: String,
+    /// Answer; `None` for cloze cards
+    pub back_md: Option<String>,
+}
+
+impl OrgCard {
+    pub fn is_cloze(&self) -> bool {
+        self.back_md.is_none()
+    }
+}
+
+struct Headline<'a> {
+    level: usize,
+    title: &'a str,
+    tags: Vec<&'a str>,
+}
+
+fn parse_headline(line: &str) -> Option<Headline<'_>> {
+    let caps = HEADLINE_REGEX.captures(line)?;
+    Some(Headline {
+        level: caps[1].len(),
+        title: caps.get(2).map_or("", |m| m.as_str()),
+        tags: caps
+            .get(3)
+            .map(|m| m.as_str().split(':').filter(|t| !t.is_empty()).collect())
+            .unwrap_or_default(),
+    })
+}
+
+/// Cards of an org file: every headline tagged `:drill:` or `:anki:`
+///
+/// The text under the headline (or the headline itself, if there is none) is
+/// the question; the subheadings hold the answer, their titles dropped. With
+/// `Front`/`Back` subheadings (anki-editor) those are used instead. A card
+/// without an answer is a cloze card if it has `[org-drill]` or `{{c1::Anki}}`
+/// clozes; otherwise it is skipped.
+pub fn parse_org_cards(content: &str) -> Vec<OrgCard> {
+    let lines: Vec<&str> = content.lines().collect();
+    let file_deck = lines.iter().find_map(|line| {
+        let (key, value) = line.trim().split_once(':')?;
+        key.eq_ignore_ascii_case("#+ANKI_DECK")
+            .then(|| value.trim().to_string())
+            .filter(|deck| !deck.is_empty())
+    });
+
+    let mut cards = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let Some(headline) = parse_headline(line) else {
+            continue;
+        };
+        if !headline.tags.iter().any(|tag| CARD_TAGS.contains(tag)) {
+            continue;
+        }
+
+        // The card's subtree ends at the next headline of the same or a higher level
+        let end = lines[index + 1..]
+            .iter()
+            .position(|l| parse_headline(l).is_some_and(|h| h.level <= headline.level))
+            .map_or(lines.len(), |offset| index + 1 + offset);
+        let (properties, body_start) = read_drawer(&lines, index + 1, end);
+        let first_child = lines[body_start..end]
+            .iter()
+            .position(|l| parse_headline(l).is_some())
+            .map_or(end, |offset| body_start + offset);
+
+        let body = org_to_markdown(&lines[body_start..first_child]);
+        let children = child_sections(&lines[first_child..end]);
+        let named = |name: &str| {
+            children
+                .iter()
+                .find(|(title, _)| title.eq_ignore_ascii_case(name))
+                .map(|(_, text)| text.clone())
+        };
+
+        let (front_md, back_md) = match (named("Front"), named("Back")) {
+            (Some(front), Some(back)) => (front, Some(back)),
+            _ => {
+                let front = if body.is_empty() {
+                    org_to_markdown(&[headline.title])
+                } else {
+                    body
+                };
+                let answer: Vec<&str> = children
+                    .iter()
+                    .map(|(_, text)| text.as_str())
+                    .filter(|text| !text.is_empty())
+                    .collect();
+                if answer.is_empty() {
+                    match drill_clozes_to_anki(&front) {
+                        Some(cloze) => (cloze, None),
+                        None => continue,
+                    }
+                } else {
+                    (front, Some(answer.join("\n\n")))
+                }
+            }
+        };
+
+        let property = |name: &str| {
+            properties
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+        };
+        let id_property = property(NOTE_ID_PROPERTY);
+        let id = id_property.as_deref().and_then(|id| id.trim().parse().ok());
+        cards.push(OrgCard {
+            headline_line: index,
+            id,
+            malformed_id: id_property.filter(|_| id.is_none()),
+            deck: property("ANKI_DECK").or_else(|| file_deck.clone()),
+            tags: headline
+                .tags
+                .iter()
+                .filter(|tag| !CARD_TAGS.contains(tag))
+                .map(|tag| tag.to_string())
+                .collect(),
+            front_md,
+            back_md,
+        });
+    }
+    cards
+}
+
+/// Properties of the drawer following a headline (after planning lines) and
+/// the index of the first line after it
+fn read_drawer(lines: &[&str], start: usize, end: usize) -> (Vec<(String, String)>, usize) {
+    let mut line = start;
+    while line < end && PLANNING_REGEX.is_match(lines[line]) {
+        line += 1;
+    }
+    if line >= end || !lines[line].trim().eq_ignore_ascii_case(":PROPERTIES:") {
+        return (Vec::new(), line);
+    }
+
+    let mut properties = Vec::new();
+    for (offset, property_line) in lines[line + 1..end].iter().enumerate() {
+        if property_line.trim().eq_ignore_ascii_case(":END:") {
+            return (properties, line + 1 + offset + 1);
+        }
+        if let Some(caps) = PROPERTY_REGEX.captures(property_line) {
+            properties.push((caps[1].to_string(), caps[2].to_string()));
+        }
+    }
+    // Unterminated drawer: treat it as text
+    (Vec::new(), line)
+}
+
+/// `(title, markdown)` of each direct child headline of a card
+fn child_sections(lines: &[&str]) -> Vec<(String, String)> {
+    let Some(level) = lines
+        .first()
+        .and_then(|l| parse_headline(l))
+        .map(|h| h.level)
+    else {
+        return Vec::new();
+    };
+    let mut sections = Vec::new();
+    let mut start = 0;
+    for index in 1..=lines.len() {
+        let boundary =
+            index == lines.len() || parse_headline(lines[index]).is_some_and(|h| h.level <= level);
+        if !boundary {
+            continue;
+        }
+        let title = parse_headline(lines[start]).map_or("", |h| h.title);
+        let (_, body_start) = read_drawer(lines, start + 1, index);
+        // Deeper headlines inside the answer are kept as text, without their stars
+        let text: Vec<&str> = lines[body_start..index]
+            .iter()
+            .map(|line| parse_headline(line).map_or(*line, |h| h.title))
+            .collect();
+        sections.push((title.to_string(), org_to_markdown(&text)));
+        start = index;
+    }
+    sections
+}
+
+/// Turn org-drill `[text]`/`[text||hint]` clozes into Anki `{{cN::text}}`
+/// clozes. Returns `None` if the text has no clozes of either kind.
+fn drill_clozes_to_anki(text: &str) -> Option<String> {
+    let mut counter = 0;
+    let converted = DRILL_CLOZE_REGEX.replace_all(text, |caps: &regex::Captures| {
+        let answer = &caps[2];
+        // Markdown checkboxes and footnote references are not clozes
+        if matches!(answer, " " | "x" | "X" | "-") || answer.starts_with('^') {
+            return caps[0].to_string();
+        }
+        counter += 1;
+        let hint = caps
+            .get(3)
+            .map(|hint| format!("::{}", hint.as_str()))
+            .unwrap_or_default();
+        format!(
+            "{}{{{{c{}::{}{}}}}}{}",
+            &caps[1], counter, answer, hint, &caps[4]
+        )
+    });
+    if counter > 0 || converted.contains("{{c") {
+        Some(converted.into_owned())
+    } else {
+        None
+    }
+}
+
+/// Convert org markup to the markdown the card converter understands:
+/// source/example/quote blocks, links and images, and emphasis
+pub fn org_to_markdown(lines: &[&str]) -> String {
+    let lines = dedent(lines);
+    let mut markdown = Vec::with_capacity(lines.len());
+    let mut block: Option<&str> = None;
+
+    for line in &lines {
+        let trimmed = line.trim();
+        let keyword = trimmed.to_ascii_lowercase();
+        match block {
+            Some(kind) if keyword == format!("#+end_{}", kind) => {
+                if kind != "quote" {
+                    markdown.push("```".to_string());
+                }
+                block = None;
+            }
+            Some("quote") => markdown.push(format!("> {}", inline_to_markdown(line))),
+            Some(_) => markdown.push(line.to_string()),
+            None if keyword.starts_with("#+begin_src")
+                || keyword.starts_with("#+begin_example") =>
+            {
+                let kind = if keyword.starts_with("#+begin_src") {
+                    "src"
+                } else {
+                    "example"
+                };
+                let lang = trimmed.split_whitespace().nth(1).unwrap_or_default();
+                markdown.push(format!("```{}", if kind == "src" { lang } else { "" }));
+                block = Some(kind);
+            }
+            None if keyword.starts_with("#+begin_quote") => block = Some("quote"),
+            // Other keywords and comments
+            None if trimmed.starts_with("#+") || trimmed == "#" || trimmed.starts_with("# ") => {}
+            None => markdown.push(inline_to_markdown(line)),
+        }
+    }
+    if matches!(block, Some("src" | "example")) {
+        markdown.push("```".to_string());
+    }
+
+    markdown.join("\n").trim().to_string()
+}
+
+/// Links, images and emphasis of one line
+fn inline_to_markdown(line: &str) -> String {
+    let linked = LINK_REGEX.replace_all(line, |caps: &regex::Captures| {
+        let target = &caps[1];
+        let path = target.strip_prefix("file:").unwrap_or(target);
+        let is_image = path
+            .rsplit_once('.')
+            .is_some_and(|(_, ext)| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+        match caps.get(2).map(|d| d.as_str()) {
+            _ if is_image && caps.get(2).is_none() => format!("![]({})", path),
+            Some(description) => format!("[{}]({})", description, path),
+            None => format!("<{}>", path),
+        }
+    });
+    let mut text = linked.into_owned();
+    for (regex, (_, open, close)) in EMPHASIS_REGEXES.iter().zip(EMPHASIS) {
+        // Twice, as adjacent spans share the whitespace between them
+        for _ in 0..2 {
+            text = regex
+                .replace_all(&text, |caps: &regex::Captures| {
+                    format!("{}{}{}{}{}", &caps[1], open, &caps[2], close, &caps[3])
+                })
+                .into_owned();
+        }
+    }
+    text
+}
+
+/// Lines without their common indentation (org-indent style bodies)
+fn dedent<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or_else(|| line.trim_start()))
+        .collect()
+}
+
+/// Set the `:ANKI_NOTE_ID:` property of the card headline at `headline_line`,
+/// adding a property drawer if the headline has none
+pub fn set_note_id(content: &str, headline_line: usize, id: i64) -> String {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let refs: Vec<&str> = lines.iter().map(String::as_str).collect();
+    let property = format!(":{}: {}", NOTE_ID_PROPERTY, id);
+
+    let mut drawer = headline_line + 1;
+    while drawer < refs.len() && PLANNING_REGEX.is_match(refs[drawer]) {
+        drawer += 1;
+    }
+    let has_drawer = drawer < refs.len()
+        && refs[drawer].trim().eq_ignore_ascii_case(":PROPERTIES:")
+        && refs[drawer + 1..]
+            .iter()
+            .take_while(|l| parse_headline(l).is_none())
+            .any(|l| l.trim().eq_ignore_ascii_case(":END:"));
+
+    if has_drawer {
+        let mut line = drawer + 1;
+        loop {
+            let trimmed = lines[line].trim();
+            if trimmed.eq_ignore_ascii_case(":END:") {
+                lines.insert(line, property);
+                break;
+            }
+            let is_id = PROPERTY_REGEX
+                .captures(trimmed)
+                .is_some_and(|caps| caps[1].eq_ignore_ascii_case(NOTE_ID_PROPERTY));
+            if is_id {
+                lines[line] = property;
+                break;
+            }
+            line += 1;
+        }
+    } else {
+        lines.splice(
+            drawer..drawer,
+            [":PROPERTIES:".to_string(), property, ":END:".to_string()],
+        );
+    }
+
+    let mut result = lines.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_drill_headline_with_answer_subheading_when_parsing_then_creates_basic_card() {
+        let content = "#+ANKI_DECK: Geography\n\
+* Capital of France                         :drill:europe:\n\
+  What is the *capital* of France?\n\
+** Answer\n\
+   Paris\n\
+* Not a card\n\
+  Text\n";
+
+        let cards = parse_org_cards(content);
+
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].headline_line, 1);
+        assert_eq!(cards[0].front_md, "What is the **capital** of France?");
+        assert_eq!(cards[0].back_md.as_deref(), Some("Paris"));
+        assert_eq!(cards[0].tags, vec!["europe"]);
+        assert_eq!(cards[0].deck.as_deref(), Some("Geography"));
+        assert_eq!(cards[0].id, None);
+    }
+
+    #[test]
+    fn given_anki_editor_headline_when_parsing_then_uses_front_back_and_properties() {
+        let content = "* Rust ownership :anki:\n\
+:PROPERTIES:\n\
+:ANKI_DECK: Programming::Rust\n\
+:ANKI_NOTE_ID: 1686\n\
+:END:\n\
+** Front\n\
+Who owns a =String= after ~let b = a~?\n\
+** Back\n\
+#+BEGIN_SRC rust\n\
+let b = a; // a is moved\n\
+#+END_SRC\n";
+
+        let cards = parse_org_cards(content);
+
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].id, Some(1686));
+        assert_eq!(cards[0].deck.as_deref(), Some("Programming::Rust"));
+        assert_eq!(cards[0].front_md, "Who owns a `String` after `let b = a`?");
+        assert_eq!(
+            cards[0].back_md.as_deref(),
+            Some("```rust\nlet b = a; // a is moved\n```")
+        );
+    }
+
+    #[test]
+    fn given_malformed_note_id_when_parsing_then_keeps_value_as_malformed() {
+        let content =
+            "* Q :anki:\n:PROPERTIES:\n:ANKI_NOTE_ID: 16x86\n:END:\n** Front\nQ\n** Back\nA\n";
+
+        let cards = parse_org_cards(content);
+
+        assert_eq!(cards[0].id, None);
+        assert_eq!(cards[0].malformed_id.as_deref(), Some("16x86"));
+    }
+
+    #[test]
+    fn given_drill_clozes_without_answer_when_parsing_then_creates_cloze_card() {
+        let content = "* Capitals :drill:\n\
+The capital of [France] is [Paris||city], see [[https://example.com][here]].\n\
+* Nothing to ask :drill:\n\
+Just text\n";
+
+        let cards = parse_org_cards(content);
+
+        assert_eq!(cards.len(), 1);
+        assert!(cards[0].is_cloze());
+        assert_eq!(
+            cards[0].front_md,
+            "The capital of {{c1::France}} is {{c2::Paris::city}}, see [here](https://example.com)."
+        );
+    }
+
+    #[test]
+    fn given_image_link_when_converting_then_becomes_markdown_image() {
+        let markdown = org_to_markdown(&["See [[file:images/cell.png]] and /this/ +old+."]);
+
+        assert_eq!(markdown, "See ![](images/cell.png) and *this* ~~old~~.");
+    }
+
+    #[test]
+    fn given_headline_without_drawer_when_setting_id_then_adds_drawer() {
+        let content = "* Q :drill:\nSCHEDULED: <2024-06-01>\nBody\n** A\nAnswer\n";
+
+        let updated = set_note_id(content, 0, 42);
+
+        assert_eq!(
+            updated,
+            "* Q :drill:\nSCHEDULED: <2024-06-01>\n:PROPERTIES:\n:ANKI_NOTE_ID: 42\n:END:\nBody\n** A\nAnswer\n"
+        );
+        assert_eq!(parse_org_cards(&updated)[0].id, Some(42));
+    }
+
+    #[test]
+    fn given_drawer_with_id_when_setting_id_then_replaces_it() {
+        let content = "* Q :anki:\n:PROPERTIES:\n:ANKI_NOTE_ID: 1\n:END:\nQuestion\n** A\nAnswer";
+
+        let updated = set_note_id(content, 0, 2);
+
+        assert_eq!(
+            updated,
+            "* Q :anki:\n:PROPERTIES:\n:ANKI_NOTE_ID: 2\n:END:\nQuestion\n** A\nAnswer"
+        );
+        assert_eq!(parse_org_cards(&updated)[0].id, Some(2));
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeSet, HashSet};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+/// Words of `words` that `dictionary` doesn't know, by running hunspell
+/// (or a compatible `command`) as `<command> -l -i UTF-8 -d <dictionary>`
+pub fn misspelled_words<'a>(
+    command: &str,
+    dictionary: &str,
+    words: impl IntoIterator<Item = &'a str>,
+) -> Result<HashSet<String>> {
+    let words: BTreeSet<&str> = words.into_iter().collect();
+    if words.is_empty() {
+        return Ok(HashSet::new());
+    }
+    debug!(command, dictionary, words = words.len(), "Spell checking");
+
+    let mut child = Command::new(command)
+        // Input is UTF-8 whatever the dictionary's own encoding
+        .args(["-l", "-i", "UTF-8", "-d", dictionary])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start spell checker '{}'", command))?;
+
+    // Written from a thread, so a full stdout pipe can't block the input
+    let mut stdin = child.stdin.take().context("Spell checker has no stdin")?;
+    let input: String = words.iter().map(|word| format!("{}\n", word)).collect();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Failed to run spell checker '{}'", command))?;
+    let written = writer
+        .join()
+        .map_err(|_| anyhow::anyhow!("Spell checker input thread panicked"))?;
+
+    // A checker that fails early stops reading; its error explains why
+    if !output.status.success() {
+        anyhow::bail!(
+            "Spell checker '{} -d {}' failed ({}): {}",
+            command,
+            dictionary,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    written.with_context(|| format!("Failed to write to spell checker '{}'", command))?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    // Stand-in for hunspell that only knows the words of its dictionary file
+    fn fake_hunspell(dir: &TempDir, known: &str) -> String {
+        let dictionary = dir.path().join("known.txt");
+        std::fs::write(&dictionary, known).unwrap();
+        let script = dir.path().join("hunspell");
+        std::fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\n[ \"$2 $3\" = \"-i UTF-8\" ] || exit 2\n[ \"$5\" = en_US ] || {{ echo \"no dictionary $5\" >&2; exit 1; }}\ngrep -vxFf {} || true\n",
+                dictionary.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        script.display().to_string()
+    }
+
+    #[test]
+    fn given_unknown_words_when_checking_then_returns_them() {
+        let temp_dir = TempDir::new().unwrap();
+        let command = fake_hunspell(&temp_dir, "the\nborrow\nchecker\n");
+
+        let misspelled =
+            misspelled_words(&command, "en_US", ["teh", "borrow", "chekcer", "teh"]).unwrap();
+
+        assert_eq!(
+            misspelled,
+            HashSet::from(["teh".to_string(), "chekcer".to_string()])
+        );
+    }
+
+    #[test]
+    fn given_missing_dictionary_when_checking_then_errors_with_stderr() {
+        let temp_dir = TempDir::new().unwrap();
+        let command = fake_hunspell(&temp_dir, "the\n");
+
+        let err = misspelled_words(&command, "xx_XX", ["teh"]).unwrap_err();
+
+        assert!(err.to_string().contains("no dictionary xx_XX"));
+    }
+}
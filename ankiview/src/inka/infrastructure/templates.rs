@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use regex::{Captures, Regex};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// `{{name}}` placeholder; `{{c1::...}}` clozes don't match
+static PLACEHOLDER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_-]*)\s*\}\}")
+        .expect("Failed to compile placeholder regex")
+});
+
+/// Directory with the user's note templates: `ankiview/templates` in the
+/// user config directory (`$XDG_CONFIG_HOME`, `~/.config`)
+pub fn templates_dir() -> Option<PathBuf> {
+    crate::util::xdg::config_home().map(|dir| dir.join("ankiview").join("templates"))
+}
+
+/// Names of the `.md` templates in `dir`, sorted
+pub fn available_templates(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+        .filter_map(|path| path.file_stem().map(|s| s.to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Content of template `name` (`<dir>/<name>.md`)
+pub fn load_template(dir: &Path, name: &str) -> Result<String> {
+    let path = dir.join(format!("{}.md", name));
+    if !path.is_file() {
+        let available = available_templates(dir);
+        return Err(anyhow::anyhow!(
+            "No template '{}' in {}; available: {}",
+            name,
+            dir.display(),
+            if available.is_empty() {
+                "none".to_string()
+            } else {
+                available.join(", ")
+            }
+        ));
+    }
+    std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read template {}", path.display()))
+}
+
+/// Placeholder names of a template in order of first use
+pub fn placeholders(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for caps in PLACEHOLDER_REGEX.captures_iter(template) {
+        if !names.iter().any(|name| name == &caps[1]) {
+            names.push(caps[1].to_string());
+        }
+    }
+    names
+}
+
+/// Fill every placeholder of `template` with its value from `vars`.
+/// Placeholders without a value and values without a placeholder are errors,
+/// so a typo doesn't silently produce a half-empty note.
+pub fn render_template(template: &str, vars: &[(String, String)]) -> Result<String> {
+    let names = placeholders(template);
+    let missing: Vec<&str> = names
+        .iter()
+        .filter(|name| !vars.iter().any(|(key, _)| key == *name))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow::anyhow!("Missing --var for {}", missing.join(", ")));
+    }
+    if let Some((key, _)) = vars.iter().find(|(key, _)| !names.contains(key)) {
+        return Err(anyhow::anyhow!(
+            "Template has no placeholder '{}'; placeholders: {}",
+            key,
+            names.join(", ")
+        ));
+    }
+
+    Ok(PLACEHOLDER_REGEX
+        .replace_all(template, |caps: &Captures| {
+            // Later --var values win, like repeated options elsewhere
+            vars.iter()
+                .rev()
+                .find(|(key, _)| key == &caps[1])
+                .map(|(_, value)| value.clone())
+                .unwrap_or_default()
+        })
+        .into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn given_vars_when_rendering_then_fills_placeholders_and_keeps_clozes() {
+        let template = "1. What does *{{word}}* mean?\n> {{ translation }}\n\n2. {{word}} is {{c1::{{translation}}}}";
+
+        let rendered = render_template(
+            template,
+            &vars(&[("word", "perro"), ("translation", "dog")]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            rendered,
+            "1. What does *perro* mean?\n> dog\n\n2. perro is {{c1::dog}}"
+        );
+    }
+
+    #[test]
+    fn given_missing_or_unknown_var_when_rendering_then_names_it() {
+        let template = "1. {{word}}\n> {{translation}}";
+
+        let missing = render_template(template, &vars(&[("word", "perro")])).unwrap_err();
+        let unknown = render_template(
+            template,
+            &vars(&[("word", "perro"), ("translation", "dog"), ("gender", "m")]),
+        )
+        .unwrap_err();
+
+        assert_eq!(missing.to_string(), "Missing --var for translation");
+        assert!(unknown.to_string().contains("no placeholder 'gender'"));
+    }
+
+    #[test]
+    fn given_unknown_template_when_loading_then_lists_available() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("vocab.md"), "1. {{word}}\n> x").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        let error = load_template(dir.path(), "verb").unwrap_err();
+
+        assert!(error.to_string().ends_with("available: vocab"));
+        assert!(load_template(dir.path(), "vocab").is_ok());
+    }
+}
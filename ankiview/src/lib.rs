@@ -24,34 +24,615 @@ pub mod inka;
 pub mod ports;
 pub mod util;
 
-use crate::application::NoteRepository;
-use crate::cli::args::{Args, Command, TagCommand};
+use crate::application::{NotePick, NoteRepository};
+use crate::cli::args::{Args, CacheCommand, Command, NotetypeCommand, TagCommand};
+use crate::domain::{
+    CountGroup, DomainError, Note, NoteRecord, NotetypeInfo, ProblemNote, TemplateUpdate,
+};
+use crate::inka::application::card_collector::{
+    source_files, CardCollector, CollectorConfig, SourceFormat,
+};
+use crate::inka::application::conflicts::{ConflictResolver, DefaultResolver};
+use crate::inka::application::events::{EventSink, NoopEventSink};
+use crate::inka::application::occluder::OcclusionRequest;
+use crate::inka::application::orphans::{OrphanFinder, OrphanNote};
+use crate::inka::application::template_notes::TemplateRequest;
+use crate::inka::application::timings::CollectTimings;
+use crate::inka::infrastructure::config::{CollectionEntry, LintConfig};
+use crate::inka::infrastructure::hasher::{CacheLocation, HashCache};
+use crate::util::line_template::LineTemplate;
+use crate::util::text::TextFormat;
 use anyhow::{Context, Result};
+use infrastructure::renderer::OpenOutcome;
 use infrastructure::AnkiRepository;
 use ports::HtmlPresenter;
-use std::path::PathBuf;
-use tracing::{debug, info};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// Library entry point for embedding ankiview in other Rust tools.
+///
+/// Each method opens the collection, performs one operation and closes it
+/// again, so the collection is only locked for the duration of a call.
+/// Results are returned as domain types; nothing is printed.
+///
+/// ```no_run
+/// use ankiview::AnkiView;
+///
+/// let ankiview = AnkiView::from_profile(None)?;
+/// for note in ankiview.list(Some("rust"))? {
+///     println!("{}\t{}", note.id, note.front);
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnkiView {
+    collection_path: PathBuf,
+}
+
+/// Outcome of [`AnkiView::collect_path`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct CollectSummary {
+    /// Number of cards created or updated
+    pub cards: usize,
+    /// Per-file errors collected when `ignore_errors` is set
+    pub errors: Vec<String>,
+    /// Non-fatal problems (`path: message`), already reported to the event sink
+    pub warnings: Vec<String>,
+    /// Cards `--update-ids` found in several notes (`path: message`); also in `warnings`
+    pub multi_matches: Vec<String>,
+    /// Notes whose cards `--move-on-deck-change` moved (`path: message`)
+    pub deck_moves: Vec<String>,
+    /// Items that start like a card but produced none (`path:line: first line`);
+    /// also in `warnings`
+    pub malformed_cards: Vec<String>,
+    /// Notes recreated for an orphaned ID, with restored scheduling or tagged
+    /// `reimported` (`path: message`)
+    pub recreated_notes: Vec<String>,
+    /// Changes a `check` run found but didn't make (`path: description`)
+    pub pending_changes: Vec<String>,
+    /// Time spent per phase (parse, convert, media, ...)
+    pub timings: CollectTimings,
+}
+
+impl AnkiView {
+    /// Use the collection file at `collection_path`.
+    pub fn new(collection_path: impl Into<PathBuf>) -> Self {
+        Self {
+            collection_path: collection_path.into(),
+        }
+    }
+
+    /// Locate the collection of an Anki profile (the first valid profile if `None`).
+    pub fn from_profile(profile: Option<&str>) -> Result<Self> {
+        Ok(Self::new(find_collection_path(profile)?))
+    }
+
+    pub fn collection_path(&self) -> &Path {
+        &self.collection_path
+    }
+
+    /// Anki's media folder (`collection.media/`) next to the collection file.
+    pub fn media_dir(&self) -> PathBuf {
+        self.collection_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("collection.media")
+    }
+
+    fn open(&self) -> Result<AnkiRepository> {
+        AnkiRepository::new(&self.collection_path)
+    }
+
+    /// Fetch a note by ID.
+    pub fn view_note(&self, note_id: i64) -> Result<Note> {
+        let mut viewer = application::NoteViewer::new(self.open()?);
+        Ok(viewer.view_note(note_id)?)
+    }
+
+    /// ID of a random or the latest note matching an Anki search query.
+    pub fn pick_note_id(&self, query: Option<&str>, pick: NotePick) -> Result<i64> {
+        let mut viewer = application::NoteViewer::new(self.open()?);
+        let note_id = viewer.pick_note_id(query, pick)?;
+        debug!(note_id, ?pick, "Picked note");
+        Ok(note_id)
+    }
+
+    /// List notes, optionally filtered by front field content.
+    pub fn list(&self, search_query: Option<&str>) -> Result<Vec<Note>> {
+        let mut lister = application::NoteLister::new(self.open()?);
+        Ok(lister.list_notes(search_query)?)
+    }
+
+    /// Number of notes matching an Anki search query.
+    pub fn count(&self, query: Option<&str>) -> Result<usize> {
+        let mut counter = application::NoteCounter::new(self.open()?);
+        counter
+            .count(query)
+            .with_context(|| "Failed to count notes")
+    }
+
+    /// Matching notes per deck, tag or notetype, largest groups first.
+    pub fn count_by(&self, query: Option<&str>, group: CountGroup) -> Result<Vec<(String, usize)>> {
+        let mut counter = application::NoteCounter::new(self.open()?);
+        counter
+            .count_by(query, group)
+            .with_context(|| "Failed to count notes")
+    }
+
+    /// Notes matching the Anki search `query` as front/back/tags TSV, with
+    /// the number of notes.
+    pub fn export_tsv(&self, query: Option<&str>, format: TextFormat) -> Result<(String, usize)> {
+        let mut exporter = application::NoteExporter::new(self.open()?);
+        exporter
+            .export_tsv(query, format)
+            .with_context(|| "Failed to export notes")
+    }
+
+    /// Notes with cards due today and their due card counts, most due first.
+    pub fn list_due(&self, search_query: Option<&str>) -> Result<Vec<(Note, usize)>> {
+        let mut lister = application::NoteLister::new(self.open()?);
+        lister
+            .list_due_notes(search_query)
+            .with_context(|| "Failed to list due notes")
+    }
+
+    /// Stream notes to `f` as they are read, stopping after `limit` notes.
+    /// Returns the number of notes passed to `f`.
+    pub fn for_each_note(
+        &self,
+        search_query: Option<&str>,
+        limit: Option<usize>,
+        mut f: impl FnMut(Note) -> Result<()>,
+    ) -> Result<usize> {
+        let mut repository = self.open()?;
+        let mut count = 0;
+        for note in repository
+            .list_notes_iter(search_query)?
+            .take(limit.unwrap_or(usize::MAX))
+        {
+            f(note?)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Stream complete notes matching the Anki search `query` to `f` as they
+    /// are read, stopping after `limit` notes. Returns the number of notes
+    /// passed to `f`.
+    pub fn for_each_note_record(
+        &self,
+        query: Option<&str>,
+        limit: Option<usize>,
+        mut f: impl FnMut(NoteRecord) -> Result<()>,
+    ) -> Result<usize> {
+        let mut repository = self.open()?;
+        let mut count = 0;
+        for record in repository
+            .note_records_iter(query)?
+            .take(limit.unwrap_or(usize::MAX))
+        {
+            f(record?)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Delete a note. Returns the number of cards removed.
+    pub fn delete(&self, note_id: i64) -> Result<usize> {
+        let mut deleter = application::NoteDeleter::new(self.open()?);
+        deleter
+            .delete_note(note_id)
+            .with_context(|| format!("Failed to delete note {}", note_id))
+    }
+
+    /// Copy a note into a new one, in `deck` or the original's deck.
+    /// Returns the new note ID.
+    pub fn clone_note(&self, note_id: i64, deck: Option<&str>) -> Result<i64> {
+        let mut cloner = application::NoteCloner::new(self.open()?);
+        cloner
+            .clone_note(note_id, deck)
+            .with_context(|| format!("Failed to clone note {}", note_id))
+    }
+
+    /// Copy the notes matching `query` into the collection of `target`, with
+    /// their note types, decks and media files. With `move_notes`, they are
+    /// deleted here afterwards.
+    pub fn transfer_to(
+        &self,
+        target: &AnkiView,
+        query: Option<&str>,
+        force: bool,
+        move_notes: bool,
+    ) -> Result<application::TransferReport> {
+        let mut transferer = application::NoteTransferer::new(self.open()?, target.open()?);
+        transferer
+            .transfer(
+                query,
+                &self.media_dir(),
+                &target.media_dir(),
+                force,
+                move_notes,
+            )
+            .with_context(|| "Failed to transfer notes")
+    }
+
+    /// Create one Image Occlusion Enhanced note per rectangle of `request`.
+    /// Returns the created note IDs.
+    pub fn occlude(&self, request: &OcclusionRequest) -> Result<Vec<i64>> {
+        let mut repository = self.open()?;
+        inka::application::occluder::create_occlusion_notes(&mut repository, request)
+            .with_context(|| format!("Failed to occlude {}", request.image.display()))
+    }
+
+    /// Create notes from a rendered template, converted with `settings`'
+    /// markdown and math options. Returns the created note IDs.
+    pub fn add_from_template(
+        &self,
+        request: &TemplateRequest,
+        settings: &inka::infrastructure::config::Config,
+    ) -> Result<Vec<i64>> {
+        let mut repository = self.open()?;
+        inka::application::template_notes::create_template_notes(&mut repository, request, settings)
+            .with_context(|| "Failed to create notes from template")
+    }
+
+    /// List available card types (notetypes) as `(id, name)` pairs.
+    pub fn list_card_types(&self) -> Result<Vec<(i64, String)>> {
+        Ok(self.open()?.list_notetypes()?)
+    }
+
+    /// Field and card template names of a notetype.
+    pub fn notetype_info(&self, name: &str) -> Result<NotetypeInfo> {
+        Ok(self.open()?.notetype_info(name)?)
+    }
+
+    /// Replace a notetype's CSS and/or card template HTML.
+    pub fn update_notetype_templates(&self, name: &str, update: &TemplateUpdate) -> Result<()> {
+        self.open()?
+            .update_notetype_templates(name, update)
+            .with_context(|| format!("Failed to update notetype '{}'", name))
+    }
+
+    /// Import markdown cards from a file or directory.
+    ///
+    /// Directories are scanned for `.md` files; subdirectories only if `recursive`.
+    pub fn collect_path(
+        &self,
+        path: impl AsRef<Path>,
+        recursive: bool,
+        config: CollectorConfig,
+    ) -> Result<CollectSummary> {
+        self.collect_path_with_events(path, recursive, config, NoopEventSink)
+    }
+
+    /// Like [`AnkiView::collect_path`], reporting progress to `events`.
+    pub fn collect_path_with_events(
+        &self,
+        path: impl AsRef<Path>,
+        recursive: bool,
+        config: CollectorConfig,
+        events: impl EventSink + 'static,
+    ) -> Result<CollectSummary> {
+        self.collect_path_with_resolver(path, recursive, config, events, DefaultResolver)
+    }
+
+    /// Like [`AnkiView::collect_path_with_events`], deciding conflicts
+    /// (orphaned IDs, ambiguous `--update-ids` matches, media name clashes)
+    /// with `resolver`.
+    pub fn collect_path_with_resolver(
+        &self,
+        path: impl AsRef<Path>,
+        recursive: bool,
+        config: CollectorConfig,
+        events: impl EventSink + 'static,
+        resolver: impl ConflictResolver + 'static,
+    ) -> Result<CollectSummary> {
+        let started = std::time::Instant::now();
+        let mut collector = CardCollector::new(&self.collection_path, config)?
+            .with_event_sink(events)
+            .with_conflict_resolver(resolver);
+
+        let cards = collector.process_path(path, recursive)?;
+        collector.reposition_new_cards()?;
+        if let Err(e) = collector.save_cache() {
+            // Retried (and reported) when the collector is dropped
+            warn!(error = %e, "Failed to save hash cache");
+        }
+
+        let mut timings = collector.timings().clone();
+        timings.set_total(started.elapsed());
+        Ok(CollectSummary {
+            cards,
+            errors: collector.errors().to_vec(),
+            warnings: collector.warnings().to_vec(),
+            multi_matches: collector.multi_matches().to_vec(),
+            deck_moves: collector.deck_moves().to_vec(),
+            malformed_cards: collector.malformed_cards().to_vec(),
+            recreated_notes: collector.recreated_notes().to_vec(),
+            pending_changes: collector.pending_changes().to_vec(),
+            timings,
+        })
+    }
+
+    /// Decks without cards (in them or their subdecks), as `(id, name)` pairs.
+    pub fn empty_decks(&self) -> Result<Vec<(i64, String)>> {
+        let mut cleaner = application::DeckCleaner::new(self.open()?);
+        cleaner
+            .empty_decks()
+            .with_context(|| "Failed to find empty decks")
+    }
+
+    /// Delete the decks without cards and return them.
+    pub fn delete_empty_decks(&self) -> Result<Vec<(i64, String)>> {
+        let mut cleaner = application::DeckCleaner::new(self.open()?);
+        cleaner
+            .delete_empty_decks()
+            .with_context(|| "Failed to delete empty decks")
+    }
+
+    /// Notes collected from files under `dir` that are gone from them, with
+    /// relative footer paths resolved from the current directory.
+    pub fn orphan_notes(&self, dir: &Path) -> Result<Vec<OrphanNote>> {
+        let mut finder = OrphanFinder::new(self.open()?);
+        finder
+            .find(dir, &std::env::current_dir()?)
+            .with_context(|| format!("Failed to find orphaned notes in {}", dir.display()))
+    }
+
+    /// Delete `orphans`, as found by `orphan_notes`; returns how many cards
+    /// were deleted.
+    pub fn delete_orphan_notes(&self, orphans: &[OrphanNote]) -> Result<usize> {
+        let mut finder = OrphanFinder::new(self.open()?);
+        finder
+            .delete(orphans)
+            .with_context(|| "Failed to delete orphaned notes")
+    }
+
+    /// File of the hash cache `collect` keeps for this collection in
+    /// `location` (`cache.location`).
+    pub fn hash_cache_path(&self, location: &CacheLocation) -> PathBuf {
+        location.cache_path(&self.collection_path)
+    }
+
+    /// The hash cache as it is, read-only; `None` if `collect` hasn't
+    /// written one yet.
+    pub fn read_hash_cache(&self, location: &CacheLocation) -> Result<Option<HashCache>> {
+        location.open_existing(&self.collection_path)
+    }
+
+    /// Remove the hash cache entries of files that no longer exist; returns
+    /// their paths.
+    pub fn prune_hash_cache(&self, location: &CacheLocation) -> Result<Vec<String>> {
+        let mut cache = location.open(&self.collection_path)?;
+        let pruned = cache.prune();
+        cache.save().context("Failed to save hash cache")?;
+        Ok(pruned)
+    }
+
+    /// Remove all hash cache entries, so the next collect processes every
+    /// file; returns how many there were.
+    pub fn clear_hash_cache(&self, location: &CacheLocation) -> Result<usize> {
+        let mut cache = location.open(&self.collection_path)?;
+        let entries = cache.len();
+        cache.clear();
+        cache.save().context("Failed to save hash cache")?;
+        Ok(entries)
+    }
+
+    /// Rename a deck (full names, e.g. `Old::Name`); subdecks move along.
+    pub fn rename_deck(&self, old: &str, new: &str) -> Result<()> {
+        let mut renamer = application::DeckRenamer::new(self.open()?);
+        renamer
+            .rename_deck(old, new)
+            .with_context(|| format!("Failed to rename deck '{}'", old))
+    }
+
+    /// Leech-tagged or often-lapsed notes, most lapses first.
+    pub fn problem_notes(&self, min_lapses: u32, deck: Option<&str>) -> Result<Vec<ProblemNote>> {
+        let mut reporter = application::LeechReporter::new(self.open()?);
+        reporter
+            .problem_notes(min_lapses, deck)
+            .with_context(|| "Failed to find problem notes")
+    }
+
+    /// Review statistics for the last `days` days, optionally limited to a deck.
+    pub fn review_stats(&self, deck: Option<&str>, days: u32) -> Result<domain::ReviewStats> {
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_millis() as i64;
+        infrastructure::revlog::RevlogReader::open(&self.collection_path)?
+            .review_stats(deck, days, now_ms)
+            .with_context(|| "Failed to compute review statistics")
+    }
+
+    /// Add tags to a note.
+    pub fn add_tags(&self, note_id: i64, tags: &[String]) -> Result<()> {
+        let mut updater = application::NoteUpdater::new(self.open()?);
+        updater
+            .add_tags(note_id, tags)
+            .with_context(|| format!("Failed to add tags to note {}", note_id))
+    }
+
+    /// Remove tags from a note.
+    pub fn remove_tags(&self, note_id: i64, tags: &[String]) -> Result<()> {
+        let mut updater = application::NoteUpdater::new(self.open()?);
+        updater
+            .remove_tags(note_id, tags)
+            .with_context(|| format!("Failed to remove tags from note {}", note_id))
+    }
+
+    /// Rename, bulk-add (`old` empty) or bulk-remove (`new` empty) a tag.
+    /// Returns the number of affected notes.
+    pub fn replace_tag(&self, query: Option<&str>, old: &str, new: &str) -> Result<usize> {
+        let mut manager = application::TagManager::new(self.open()?);
+        manager
+            .replace_tag(query, old, new)
+            .with_context(|| "Failed to replace tags")
+    }
+
+    /// Set the flag on all cards of a note; returns the number of cards
+    pub fn flag_note(&self, note_id: i64, flag: domain::Flag) -> Result<usize> {
+        let mut flagger = application::NoteFlagger::new(self.open()?);
+        flagger
+            .flag_note(note_id, flag)
+            .with_context(|| format!("Failed to flag note {}", note_id))
+    }
+
+    /// Set the flag on a single card
+    pub fn flag_card(&self, card_id: i64, flag: domain::Flag) -> Result<()> {
+        let mut flagger = application::NoteFlagger::new(self.open()?);
+        flagger
+            .flag_card(card_id, flag)
+            .with_context(|| format!("Failed to flag card {}", card_id))
+    }
+
+    /// Add or remove Anki's `marked` tag on a note
+    pub fn set_marked(&self, note_id: i64, marked: bool) -> Result<()> {
+        let mut flagger = application::NoteFlagger::new(self.open()?);
+        flagger
+            .set_marked(note_id, marked)
+            .with_context(|| format!("Failed to mark note {}", note_id))
+    }
+
+    /// Apply maintenance rules (tagging, deck moves, field values) in order
+    pub fn apply_rules(&self, rules: &[domain::Rule]) -> Result<Vec<application::RuleReport>> {
+        let mut applier = application::RuleApplier::new(self.open()?);
+        applier
+            .apply(rules)
+            .with_context(|| "Failed to apply rules")
+    }
+
+    /// Regex find-and-replace over the fields of the notes matching `query`.
+    /// Returns the changed fields; with `dry_run` nothing is saved.
+    pub fn replace_in_fields(
+        &self,
+        query: Option<&str>,
+        find: &regex::Regex,
+        replacement: &str,
+        dry_run: bool,
+    ) -> Result<Vec<application::FieldReplacement>> {
+        let mut replacer = application::FieldReplacer::new(self.open()?);
+        replacer
+            .replace(query, find, replacement, dry_run)
+            .with_context(|| "Failed to replace in note fields")
+    }
+}
 
 pub fn run(args: Args) -> Result<()> {
     debug!(?args, "Starting ankiview with arguments");
+    util::output::set_quiet(args.quiet);
 
-    // Resolve collection path from global flags
-    let collection_path = match args.collection {
-        Some(path) => {
-            debug!(?path, "Using provided collection path");
-            path
+    // Work on markdown alone, so they need no collection
+    match &args.command {
+        Command::Preview { path, port } => {
+            return handle_preview_command(path, *port, args.config.as_deref());
         }
-        None => {
-            debug!(?args.profile, "Finding collection path for profile");
-            find_collection_path(args.profile.as_deref())?
+        Command::Convert { path, card } => {
+            return handle_convert_command(path, *card, args.config.as_deref());
         }
-    };
+        Command::Lint { path, recursive } => {
+            return handle_lint_command(path, *recursive, args.config.as_deref());
+        }
+        Command::VaultStats { path, json } => {
+            return handle_vault_stats_command(path, *json, args.config.as_deref());
+        }
+        Command::Transfer {
+            from,
+            to,
+            query,
+            force,
+            move_notes,
+        } => {
+            return handle_transfer_command(from, to, query.as_deref(), *force, *move_notes);
+        }
+        _ => {}
+    }
+
+    // Resolve collection paths from global flags (or the config for commands
+    // that aggregate several collections)
+    let mut collections = resolve_collections(&args)?;
+    if collections.len() > 1 {
+        return handle_multi_collection_command(args.command, &collections);
+    }
+    let (_, collection_path) = collections.remove(0);
+    debug!(?collection_path, "Using collection");
+
+    let ankiview = AnkiView::new(collection_path);
 
     // Route to appropriate handler based on command
     match args.command {
-        Command::View { note_id, json } => handle_view_command(note_id, json, collection_path),
-        Command::Delete { note_id } => handle_delete_command(note_id, collection_path),
-        Command::List { search } => handle_list_command(search.as_deref(), collection_path),
+        Command::View {
+            note_id: Some(note_id),
+            in_anki: true,
+            ..
+        } => handle_view_in_anki_command(note_id),
+        Command::View {
+            note_id,
+            json,
+            random,
+            query,
+            browser,
+            stdout,
+            sanitize,
+            ..
+        } => {
+            let note_id = match note_id {
+                Some(note_id) => note_id,
+                None => {
+                    let pick = if random {
+                        NotePick::Random
+                    } else {
+                        NotePick::Latest
+                    };
+                    match ports::daemon::DaemonClient::connect_for(ankiview.collection_path()) {
+                        Some(mut daemon) => daemon.pick_note_id(query.as_deref(), pick)?,
+                        None => ankiview.pick_note_id(query.as_deref(), pick)?,
+                    }
+                }
+            };
+            handle_view_command(
+                note_id,
+                json,
+                stdout,
+                sanitize,
+                browser.as_deref(),
+                args.config.as_deref(),
+                &ankiview,
+            )
+        }
+        Command::Delete { note_id } => {
+            handle_delete_command(note_id, args.config.as_deref(), &ankiview)
+        }
+        Command::History { note_id, json } => {
+            handle_history_command(note_id, json, args.config.as_deref(), &ankiview)
+        }
+        Command::Clone { note_id, deck } => {
+            handle_clone_command(note_id, deck.as_deref(), &ankiview)
+        }
+        Command::Flag { id, card, color } => handle_flag_command(id, card, color.flag(), &ankiview),
+        Command::Mark { note_id, clear } => handle_mark_command(note_id, !clear, &ankiview),
+        Command::List {
+            search,
+            limit,
+            due,
+            template,
+        } => {
+            if due {
+                handle_list_due_command(search.as_deref(), limit, &ankiview)
+            } else if let Some(template) = template {
+                handle_list_template_command(search.as_deref(), limit, &template, &ankiview)
+            } else {
+                handle_list_command(search.as_deref(), limit, &ankiview)
+            }
+        }
+        Command::Pick { query, multi } => handle_pick_command(query.as_deref(), multi, &ankiview),
+        Command::Count { query, by } => handle_count_command(query.as_deref(), by, &ankiview),
+        Command::ExportTsv { query, out, format } => {
+            handle_export_tsv_command(query.as_deref(), out.as_deref(), format, &ankiview)
+        }
+        Command::ExportJsonl { query, out } => {
+            handle_export_jsonl_command(query.as_deref(), out.as_deref(), &ankiview)
+        }
         Command::Collect {
             path,
             recursive,
@@ -59,33 +640,161 @@ pub fn run(args: Args) -> Result<()> {
             ignore_errors,
             full_sync,
             update_ids,
+            same_deck,
+            move_on_deck_change,
             card_type,
+            timings,
+            strict,
+            check,
+            deck,
+            deck_prefix,
+            tags,
+            sync_tags,
+            tag_namespace,
+            new_position,
+            on_duplicate,
+            format,
+            index_notes,
+            restore_from,
+            non_interactive,
+            lint,
+            ci,
         } => {
-            let config = crate::inka::application::card_collector::CollectorConfig {
+            let config = CollectorConfig {
                 force,
                 full_sync,
                 update_ids,
+                same_deck,
+                move_on_deck_change,
                 ignore_errors,
                 card_type,
+                strict,
+                check,
+                deck,
+                deck_prefix,
+                extra_tags: tags,
+                sync_tags,
+                tag_namespace,
+                new_position,
+                on_duplicate,
+                format,
+                index_notes,
+                lint: lint.then(LintConfig::default),
+                restore_from,
+                ..Default::default()
+            };
+            handle_collect_command(
+                path,
+                recursive,
+                config,
+                timings,
+                if ci {
+                    CollectUi::Ci
+                } else if non_interactive {
+                    CollectUi::NonInteractive
+                } else {
+                    CollectUi::Terminal
+                },
+                args.config.as_deref(),
+                &ankiview,
+            )
+        }
+        Command::Occlude {
+            image,
+            rects,
+            mode,
+            deck,
+            header,
+            tags,
+            notetype,
+        } => {
+            let request = OcclusionRequest {
+                image,
+                rects,
+                mode,
+                deck,
+                header,
+                tags,
+                notetype,
             };
-            handle_collect_command(path, recursive, config, collection_path)
+            handle_occlude_command(&request, &ankiview)
+        }
+        Command::Add {
+            template,
+            vars,
+            deck,
+            tags,
+            card_type,
+        } => handle_add_command(
+            &template,
+            &vars,
+            deck,
+            tags,
+            card_type,
+            args.config.as_deref(),
+            &ankiview,
+        ),
+        Command::ListCardTypes => handle_list_card_types_command(&ankiview),
+        Command::Fields { notetype, json } => handle_fields_command(&notetype, json, &ankiview),
+        Command::EmptyDecks { delete } => {
+            handle_empty_decks_command(delete, args.config.as_deref(), &ankiview)
+        }
+        Command::Orphans { dir, delete, yes } => {
+            handle_orphans_command(&dir, delete, yes, args.config.as_deref(), &ankiview)
+        }
+        Command::RenameDeck { old, new } => handle_rename_deck_command(&old, &new, &ankiview),
+        Command::Leeches {
+            min_lapses,
+            deck,
+            json,
+        } => handle_leeches_command(min_lapses, deck.as_deref(), json, &ankiview),
+        Command::ReviewStats { deck, days, json } => {
+            handle_review_stats_command(deck.as_deref(), days, json, &ankiview)
+        }
+        Command::Tag { subcommand } => handle_tag_command(subcommand, &ankiview),
+        Command::Notetype { subcommand } => {
+            handle_notetype_command(subcommand, args.config.as_deref(), &ankiview)
+        }
+        Command::Cache { subcommand } => {
+            handle_cache_command(subcommand, args.config.as_deref(), &ankiview)
+        }
+        Command::Replace {
+            query,
+            find,
+            replacement,
+            dry_run,
+        } => handle_replace_command(query.as_deref(), &find, &replacement, dry_run, &ankiview),
+        Command::ApplyRules { path } => handle_apply_rules_command(&path, &ankiview),
+        Command::Edit { note_id } => handle_edit_command(note_id, ankiview.collection_path()),
+        Command::Preview { .. }
+        | Command::Convert { .. }
+        | Command::Lint { .. }
+        | Command::VaultStats { .. }
+        | Command::Transfer { .. } => {
+            unreachable!("handled before resolving the collection")
+        }
+        Command::Serve => handle_serve_command(ankiview.collection_path()),
+        Command::Daemon { socket, stop } => {
+            handle_daemon_command(socket, stop, ankiview.collection_path())
         }
-        Command::ListCardTypes => handle_list_card_types_command(collection_path),
-        Command::Tag { subcommand } => handle_tag_command(subcommand, collection_path),
-        Command::Edit { note_id } => handle_edit_command(note_id, collection_path),
     }
 }
 
-fn handle_view_command(note_id: i64, json: bool, collection_path: PathBuf) -> Result<()> {
-    let repository = AnkiRepository::new(&collection_path)?;
-    let media_dir = repository.media_dir().to_path_buf();
-
-    // Initialize application
-    let mut viewer = application::NoteViewer::new(repository);
-
+fn handle_view_command(
+    note_id: i64,
+    json: bool,
+    stdout: bool,
+    sanitize: bool,
+    browser: Option<&str>,
+    config_file: Option<&Path>,
+    ankiview: &AnkiView,
+) -> Result<()> {
     // Execute use case
     info!(note_id = note_id, "Viewing note");
-    let note = viewer.view_note(note_id)?;
+    let note = match ports::daemon::DaemonClient::connect_for(ankiview.collection_path()) {
+        Some(mut daemon) => daemon.view_note(note_id)?,
+        None => ankiview.view_note(note_id)?,
+    };
     debug!(?note, "Retrieved note");
 
     // Branch on output format
@@ -94,93 +803,745 @@ fn handle_view_command(note_id: i64, json: bool, collection_path: PathBuf) -> Re
         let json_output =
             serde_json::to_string_pretty(&note).context("Failed to serialize note to JSON")?;
         println!("{}", json_output);
+        return Ok(());
+    }
+
+    let (settings, config_file) =
+        inka::infrastructure::config::Config::discover(config_file, &std::env::current_dir()?)?;
+    let presenter = HtmlPresenter::with_media_dir(ankiview.media_dir())
+        .sanitized(sanitize || settings.viewer.sanitize)
+        .with_mathjax_config(util::math::mathjax_config(
+            &settings.math.macros,
+            &settings.math.inline_delimiters,
+            &settings.math.display_delimiters,
+        ));
+    if stdout {
+        // Rendered page for pipes and headless systems
+        println!("{}", presenter.render(&note));
     } else {
         // Browser output path (existing behavior)
-        let presenter = HtmlPresenter::with_media_dir(media_dir);
-        let mut renderer = infrastructure::renderer::ContentRenderer::new();
+        let browser = infrastructure::renderer::browser_command(
+            browser,
+            settings.viewer.browser.as_deref(),
+            std::env::var("BROWSER").ok().as_deref(),
+        );
+        debug!(?browser, "Opening note");
+        let mut renderer = infrastructure::renderer::ContentRenderer::new().with_browser(browser);
+        if let Some(delay_ms) = settings.viewer.launch_delay_ms {
+            renderer = renderer.with_launch_delay(std::time::Duration::from_millis(delay_ms));
+        }
 
         let html = presenter.render(&note);
         debug!(?html, "Generated HTML");
 
-        // Create temporary file and open in browser
-        let temp_path = renderer.create_temp_file(&html)?;
-        renderer.open_in_browser(&temp_path)?;
+        // Write to the configured output directory (or a temp file) and open in browser
+        let path = match settings.viewer.resolved_output_dir(config_file.as_deref()) {
+            Some(dir) => {
+                let keep_days = settings
+                    .viewer
+                    .keep_days
+                    .unwrap_or(constants::RENDERED_NOTE_KEEP_DAYS);
+                let keep = std::time::Duration::from_secs(keep_days * 24 * 60 * 60);
+                renderer.create_output_file(&dir, note.id, &html, keep)?
+            }
+            None => renderer.create_temp_file(&html)?,
+        };
+        if let OpenOutcome::NoBrowser(reason) = renderer.open_in_browser(&path)? {
+            // The path is the result now, so it goes to stdout
+            warn!(%reason, "No browser available");
+            eprintln!(
+                "No browser available ({}); open the rendered note yourself or use --stdout:",
+                reason
+            );
+            println!("{}", path.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_view_in_anki_command(note_id: i64) -> Result<()> {
+    // Anki holds the collection lock while it runs, so ask Anki itself
+    info!(note_id = note_id, "Showing note in Anki's browser");
+    let cards = infrastructure::anki_connect::AnkiConnectClient::default()
+        .gui_browse(&format!("nid:{}", note_id))?;
+    if cards.is_empty() {
+        return Err(DomainError::NoteNotFound(note_id).into());
+    }
+    Ok(())
+}
+
+fn handle_clone_command(note_id: i64, deck: Option<&str>, ankiview: &AnkiView) -> Result<()> {
+    info!(note_id = note_id, ?deck, "Cloning note");
+    let new_id = ankiview.clone_note(note_id, deck)?;
+
+    // The new ID is the requested data, so it goes to stdout even with --quiet
+    println!("{}", new_id);
+    Ok(())
+}
+
+fn handle_transfer_command(
+    from: &Path,
+    to: &Path,
+    query: Option<&str>,
+    force: bool,
+    move_notes: bool,
+) -> Result<()> {
+    info!(?from, ?to, ?query, force, move_notes, "Transferring notes");
+    for path in [from, to] {
+        if !path.exists() {
+            return Err(DomainError::CollectionNotFound(path.display().to_string()).into());
+        }
+    }
+    if from.canonicalize()? == to.canonicalize()? {
+        return Err(anyhow::anyhow!(
+            "Source and target are the same collection: {}",
+            from.display()
+        ));
+    }
+    let source = AnkiView::new(from);
+    let report = source.transfer_to(&AnkiView::new(to), query, force, move_notes)?;
+
+    // The ID mapping is the requested data, so it goes to stdout even with --quiet
+    for (old_id, new_id) in &report.mapping {
+        println!("{}\t{}", old_id, new_id);
+    }
+    for name in &report.media_missing {
+        eprintln!(
+            "Warning: media file {} not found in source collection",
+            name
+        );
+    }
+    for name in &report.media_rejected {
+        eprintln!(
+            "Warning: skipped media reference {} (not a plain file name)",
+            name
+        );
+    }
+    let action = if move_notes { "Moved" } else { "Copied" };
+    eprintln!(
+        "{} {} notes, {} media files",
+        action,
+        report.mapping.len(),
+        report.media_copied
+    );
+    Ok(())
+}
+
+fn handle_flag_command(id: i64, card: bool, flag: domain::Flag, ankiview: &AnkiView) -> Result<()> {
+    info!(id, card, ?flag, "Flagging");
+    let action = match flag {
+        domain::Flag::None => "Cleared flag".to_string(),
+        flag => format!("Set {} flag", format!("{:?}", flag).to_lowercase()),
+    };
+    if card {
+        ankiview.flag_card(id, flag)?;
+        status!("{} on card {}.", action, id);
+    } else {
+        let cards = ankiview.flag_note(id, flag)?;
+        status!("{} on note {} ({} card(s)).", action, id, cards);
+    }
+    Ok(())
+}
+
+fn handle_mark_command(note_id: i64, marked: bool, ankiview: &AnkiView) -> Result<()> {
+    info!(note_id, marked, "Marking note");
+    ankiview.set_marked(note_id, marked)?;
+
+    if marked {
+        status!("Marked note {}.", note_id);
+    } else {
+        status!("Unmarked note {}.", note_id);
+    }
+    Ok(())
+}
+
+fn handle_delete_command(
+    note_id: i64,
+    config_file: Option<&Path>,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    use inka::infrastructure::history::{self, History, HistoryAction, HistoryEntry};
+
+    let (settings, config_file) =
+        inka::infrastructure::config::Config::discover(config_file, &std::env::current_dir()?)?;
+    let location = settings.cache.resolved(config_file.as_deref());
+    let history = settings
+        .history
+        .enabled
+        .then(|| History::for_collection(ankiview.collection_path(), &location));
+    // Hash the content while the note still exists
+    let before = match &history {
+        Some(_) => AnkiRepository::new(ankiview.collection_path())?
+            .note_content(note_id)?
+            .map(|(fields, tags)| history::content_hash(&fields, &tags)),
+        None => None,
+    };
+
+    // Execute use case
+    info!(note_id = note_id, "Deleting note");
+    let deleted_cards = ankiview.delete(note_id)?;
+    if let Some(history) = &history {
+        history.append(&HistoryEntry {
+            before,
+            ..HistoryEntry::now(HistoryAction::Deleted, note_id)
+        })?;
+    }
+
+    // Print success message to stdout (unlike view which is silent)
+    status!(
+        "Successfully deleted note {} ({} card{} removed)",
+        note_id,
+        deleted_cards,
+        if deleted_cards == 1 { "" } else { "s" }
+    );
+
+    Ok(())
+}
+
+fn handle_history_command(
+    note_id: i64,
+    json: bool,
+    config_file: Option<&Path>,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    let (settings, config_file) =
+        inka::infrastructure::config::Config::discover(config_file, &std::env::current_dir()?)?;
+    let location = settings.cache.resolved(config_file.as_deref());
+    let history = inka::infrastructure::history::History::for_collection(
+        ankiview.collection_path(),
+        &location,
+    );
+    info!(note_id, path = ?history.path(), "Reading note history");
+    let entries = history.entries_for(note_id)?;
+    if entries.is_empty() {
+        eprintln!(
+            "No history for note {} in {}",
+            note_id,
+            history.path().display()
+        );
+        return Ok(());
+    }
+
+    for entry in &entries {
+        if json {
+            println!("{}", serde_json::to_string(entry)?);
+            continue;
+        }
+        let seconds = entry.timestamp.rem_euclid(86_400);
+        println!(
+            "{} {:02}:{:02}:{:02}\t{}\t{} -> {}\t{}",
+            infrastructure::revlog::civil_date(entry.timestamp.div_euclid(86_400)),
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60,
+            entry.action.as_str(),
+            entry.before.as_deref().unwrap_or("-"),
+            entry.after.as_deref().unwrap_or("-"),
+            entry.file.as_deref().unwrap_or("")
+        );
+    }
+    Ok(())
+}
+
+fn handle_list_command(
+    search_query: Option<&str>,
+    limit: Option<usize>,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    // Execute use case, printing rows as notes are read
+    info!(?search_query, ?limit, "Listing notes");
+    let note_count = match ports::daemon::DaemonClient::connect_for(ankiview.collection_path()) {
+        Some(mut daemon) => {
+            let notes = daemon.list(search_query, limit)?;
+            let count = notes.len();
+            notes.into_iter().try_for_each(print_note_row)?;
+            count
+        }
+        None => ankiview.for_each_note(search_query, limit, print_note_row)?,
+    };
+    debug!(note_count, "Listed notes");
+
+    Ok(())
+}
+
+fn handle_list_template_command(
+    search_query: Option<&str>,
+    limit: Option<usize>,
+    template: &LineTemplate,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    use std::io::Write;
+
+    info!(?search_query, ?limit, "Listing notes with template");
+    // Same front field search as a plain `list`
+    let query = search_query
+        .filter(|search| !search.is_empty())
+        .map(|search| format!("front:*{}*", search));
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    let note_count = ankiview.for_each_note_record(query.as_deref(), limit, |record| {
+        writeln!(out, "{}", template.render(&record))?;
+        Ok(())
+    })?;
+    out.flush().context("Failed to write notes")?;
+    debug!(note_count, "Listed notes");
+    Ok(())
+}
+
+fn handle_list_due_command(
+    search_query: Option<&str>,
+    limit: Option<usize>,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    info!(?search_query, ?limit, "Listing notes with due cards");
+    let notes = ankiview.list_due(search_query)?;
+
+    let total_due: usize = notes.iter().map(|(_, due)| due).sum();
+    for (note, due) in notes.iter().take(limit.unwrap_or(usize::MAX)) {
+        let first_line = util::text::extract_first_line(&note.front);
+        println!("{}\t{}\t{}", note.id, due, first_line);
+    }
+    status!("{} card(s) due in {} note(s).", total_due, notes.len());
+    Ok(())
+}
+
+fn handle_pick_command(query: Option<&str>, multi: bool, ankiview: &AnkiView) -> Result<()> {
+    info!(?query, multi, "Picking notes");
+    let mut items = Vec::new();
+    ankiview.for_each_note_record(query, None, |record| {
+        items.push(ports::picker::PickItem::from_record(&record));
+        Ok(())
+    })?;
+    if items.is_empty() {
+        return Err(DomainError::NoMatchingNotes(query.unwrap_or("").to_string()).into());
+    }
+    // Newest first, like Anki's browser sorted by creation
+    items.reverse();
+
+    let picked = ports::picker::NotePicker::stderr().pick(items, multi)?;
+    if picked.is_empty() {
+        return Err(anyhow::anyhow!("No note picked"));
+    }
+    for note_id in picked {
+        println!("{}", note_id);
+    }
+    Ok(())
+}
+
+/// Print one `list` row: note ID and first line of the front field
+fn print_note_row(note: Note) -> Result<()> {
+    let first_line = util::text::extract_first_line(&note.front);
+    println!("{}\t{}", note.id, first_line);
+    Ok(())
+}
+
+fn handle_occlude_command(request: &OcclusionRequest, ankiview: &AnkiView) -> Result<()> {
+    info!(
+        image = %request.image.display(),
+        rects = request.rects.len(),
+        "Creating image occlusion notes"
+    );
+    let note_ids = ankiview.occlude(request)?;
+
+    for note_id in &note_ids {
+        println!("{}", note_id);
+    }
+    status!(
+        "Created {} image occlusion note(s) in deck '{}'.",
+        note_ids.len(),
+        request.deck
+    );
+    Ok(())
+}
+
+fn handle_add_command(
+    template: &str,
+    vars: &[(String, String)],
+    deck: Option<String>,
+    tags: Vec<String>,
+    card_type: Option<String>,
+    config_file: Option<&Path>,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    use inka::infrastructure::templates;
+
+    info!(template, vars = vars.len(), "Creating notes from template");
+    let (settings, _) =
+        inka::infrastructure::config::Config::discover(config_file, &std::env::current_dir()?)?;
+    let dir = templates::templates_dir().context("Could not find config directory")?;
+    let content = templates::load_template(&dir, template)?;
+    let markdown = templates::render_template(&content, vars)
+        .with_context(|| format!("Failed to fill template '{}'", template))?;
+    let request = TemplateRequest {
+        markdown,
+        deck,
+        tags,
+        card_type,
+    };
+    let note_ids = ankiview.add_from_template(&request, &settings)?;
+
+    for note_id in &note_ids {
+        println!("{}", note_id);
+    }
+    status!(
+        "Created {} note(s) from template '{}'.",
+        note_ids.len(),
+        template
+    );
+    Ok(())
+}
+
+fn handle_fields_command(name: &str, json: bool, ankiview: &AnkiView) -> Result<()> {
+    info!(notetype = name, "Showing notetype fields");
+    let info = ankiview.notetype_info(name)?;
+
+    if json {
+        let json_output =
+            serde_json::to_string_pretty(&info).context("Failed to serialize notetype to JSON")?;
+        println!("{}", json_output);
+        return Ok(());
+    }
+
+    let kind = if info.cloze { ", cloze" } else { "" };
+    println!("{} (ID {}{})", info.name, info.id, kind);
+    println!("Fields:");
+    for (ord, field) in info.fields.iter().enumerate() {
+        println!("  {:<4} {}", ord, field);
+    }
+    println!("Templates:");
+    for (ord, template) in info.templates.iter().enumerate() {
+        println!("  {:<4} {}", ord, template);
+    }
+    Ok(())
+}
+
+fn handle_count_command(
+    query: Option<&str>,
+    by: Option<CountGroup>,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    info!(?query, ?by, "Counting notes");
+    match by {
+        None => println!("{}", ankiview.count(query)?),
+        Some(group) => {
+            for (name, count) in ankiview.count_by(query, group)? {
+                println!("{}\t{}", name, count);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_export_tsv_command(
+    query: Option<&str>,
+    out: Option<&Path>,
+    format: TextFormat,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    info!(?query, ?out, ?format, "Exporting notes as TSV");
+    let (tsv, count) = ankiview.export_tsv(query, format)?;
+    match out {
+        Some(path) => {
+            std::fs::write(path, tsv)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            status!("Exported {} note(s) to {}", count, path.display());
+        }
+        // No summary line: it would end up in the TSV
+        None => print!("{}", tsv),
+    }
+    Ok(())
+}
+
+fn handle_export_jsonl_command(
+    query: Option<&str>,
+    out: Option<&Path>,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    use std::io::Write;
+
+    info!(?query, ?out, "Exporting notes as JSON Lines");
+    let mut writer: Box<dyn Write> = match out {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path)
+                .with_context(|| format!("Failed to create {}", path.display()))?,
+        )),
+        None => Box::new(std::io::BufWriter::new(std::io::stdout().lock())),
+    };
+    let count = ankiview.for_each_note_record(query, None, |record| {
+        serde_json::to_writer(&mut writer, &record)?;
+        writer.write_all(b"\n")?;
+        Ok(())
+    })?;
+    writer.flush().context("Failed to write notes")?;
+
+    // No summary line on stdout: it would end up in the stream
+    if let Some(path) = out {
+        status!("Exported {} note(s) to {}", count, path.display());
+    }
+    Ok(())
+}
+
+fn handle_list_card_types_command(ankiview: &AnkiView) -> Result<()> {
+    // List all available notetypes
+    info!("Listing card types");
+    let notetypes = ankiview.list_card_types()?;
+    debug!(count = notetypes.len(), "Retrieved notetypes");
+
+    // Print header
+    println!("Available card types:");
+    println!("{:<15} Name", "ID");
+    println!("{}", "-".repeat(60));
+
+    // Format and print each notetype
+    for (id, name) in notetypes {
+        println!("{:<15} {}", id, name);
+    }
+
+    Ok(())
+}
+
+fn handle_empty_decks_command(
+    delete: bool,
+    config_file: Option<&Path>,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    use inka::infrastructure::history::{self, History, HistoryAction, HistoryEntry};
+
+    info!(delete, "Finding empty decks");
+    if !delete {
+        let decks = ankiview.empty_decks()?;
+        for (id, name) in &decks {
+            println!("{:<15} {}", id, name);
+        }
+        if decks.is_empty() {
+            status!("No empty decks.");
+        }
+        return Ok(());
+    }
+
+    let (settings, config_file) =
+        inka::infrastructure::config::Config::discover(config_file, &std::env::current_dir()?)?;
+    let location = settings.cache.resolved(config_file.as_deref());
+    let history = settings
+        .history
+        .enabled
+        .then(|| History::for_collection(ankiview.collection_path(), &location));
+    // Cards whose home deck is deleted go with it; hash their notes while
+    // they still exist
+    let mut entries = Vec::new();
+    if history.is_some() {
+        let ids: Vec<String> = ankiview
+            .empty_decks()?
+            .iter()
+            .map(|(id, _)| id.to_string())
+            .collect();
+        if !ids.is_empty() {
+            let mut repository = AnkiRepository::new(ankiview.collection_path())?;
+            for note_id in repository.note_ids(Some(&format!("did:{}", ids.join(","))))? {
+                let before = repository
+                    .note_content(note_id)?
+                    .map(|(fields, tags)| history::content_hash(&fields, &tags));
+                entries.push(HistoryEntry {
+                    before,
+                    ..HistoryEntry::now(HistoryAction::Deleted, note_id)
+                });
+            }
+        }
+    }
+
+    let decks = ankiview.delete_empty_decks()?;
+    if let Some(history) = &history {
+        if !entries.is_empty() {
+            let repository = AnkiRepository::new(ankiview.collection_path())?;
+            for entry in &entries {
+                if !repository.note_exists(entry.note_id)? {
+                    history.append(entry)?;
+                }
+            }
+        }
     }
 
+    for (id, name) in &decks {
+        println!("{:<15} {}", id, name);
+    }
+    status!("Deleted {} empty deck(s).", decks.len());
     Ok(())
 }
 
-fn handle_delete_command(note_id: i64, collection_path: PathBuf) -> Result<()> {
-    let repository = AnkiRepository::new(&collection_path)?;
+fn handle_orphans_command(
+    dir: &Path,
+    delete: bool,
+    yes: bool,
+    config_file: Option<&Path>,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    use inka::infrastructure::history::{self, History, HistoryAction, HistoryEntry};
 
-    // Initialize application
-    let mut deleter = application::NoteDeleter::new(repository);
+    info!(?dir, delete, "Finding orphaned notes");
+    let orphans = ankiview.orphan_notes(dir)?;
 
-    // Execute use case
-    info!(note_id = note_id, "Deleting note");
-    let deleted_cards = deleter
-        .delete_note(note_id)
-        .with_context(|| format!("Failed to delete note {}", note_id))?;
+    for orphan in &orphans {
+        println!("{:<15} {}\t{}", orphan.note_id, orphan.file, orphan.reason);
+    }
+    if orphans.is_empty() {
+        status!("No orphaned notes.");
+        return Ok(());
+    }
+    if !delete {
+        return Ok(());
+    }
+    if !yes && !confirm(&format!("Delete {} orphaned note(s)?", orphans.len()))? {
+        status!("Nothing deleted.");
+        return Ok(());
+    }
 
-    // Print success message to stdout (unlike view which is silent)
-    println!(
-        "Successfully deleted note {} ({} card{} removed)",
-        note_id,
-        deleted_cards,
-        if deleted_cards == 1 { "" } else { "s" }
-    );
+    let (settings, config_file) =
+        inka::infrastructure::config::Config::discover(config_file, &std::env::current_dir()?)?;
+    let location = settings.cache.resolved(config_file.as_deref());
+    let history = settings
+        .history
+        .enabled
+        .then(|| History::for_collection(ankiview.collection_path(), &location));
+    // Hash the contents while the notes still exist
+    let mut entries = Vec::new();
+    if history.is_some() {
+        let repository = AnkiRepository::new(ankiview.collection_path())?;
+        for orphan in &orphans {
+            let before = repository
+                .note_content(orphan.note_id)?
+                .map(|(fields, tags)| history::content_hash(&fields, &tags));
+            entries.push(HistoryEntry {
+                file: Some(orphan.file.clone()),
+                before,
+                ..HistoryEntry::now(HistoryAction::Deleted, orphan.note_id)
+            });
+        }
+    }
 
+    ankiview.delete_orphan_notes(&orphans)?;
+    if let Some(history) = &history {
+        for entry in &entries {
+            history.append(entry)?;
+        }
+    }
+    status!("Deleted {} orphaned note(s).", orphans.len());
     Ok(())
 }
 
-fn handle_list_command(search_query: Option<&str>, collection_path: PathBuf) -> Result<()> {
-    let repository = AnkiRepository::new(&collection_path)?;
+/// Ask `question` on stderr and read a yes/no answer from stdin; refuses
+/// (with a hint to pass `--yes`) when stdin isn't a terminal
+fn confirm(question: &str) -> Result<bool> {
+    use std::io::{IsTerminal, Write};
 
-    // Initialize application
-    let mut lister = application::NoteLister::new(repository);
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("{} Pass --yes to confirm without a terminal.", question);
+    }
+    eprint!("{} [y/N] ", question);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
 
-    // Execute use case
-    info!(?search_query, "Listing notes");
-    let notes = lister.list_notes(search_query)?;
-    debug!(note_count = notes.len(), "Retrieved notes");
+fn handle_rename_deck_command(old: &str, new: &str, ankiview: &AnkiView) -> Result<()> {
+    info!(old, new, "Renaming deck");
+    ankiview.rename_deck(old, new)?;
 
-    // Format and print output
-    for note in notes {
-        let first_line = util::text::extract_first_line(&note.front);
-        println!("{}\t{}", note.id, first_line);
+    status!("Renamed deck '{}' to '{}'.", old, new);
+    Ok(())
+}
+
+fn handle_leeches_command(
+    min_lapses: u32,
+    deck: Option<&str>,
+    json: bool,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    info!(min_lapses, ?deck, "Listing problem notes");
+    let notes = ankiview.problem_notes(min_lapses, deck)?;
+
+    if json {
+        let json_output = serde_json::to_string_pretty(&notes)
+            .context("Failed to serialize problem notes to JSON")?;
+        println!("{}", json_output);
+        return Ok(());
     }
 
+    for note in &notes {
+        println!(
+            "{}\t{}{}\t{}\t{}",
+            note.note_id,
+            note.lapses,
+            if note.leech { " (leech)" } else { "" },
+            note.deck,
+            note.first_line
+        );
+    }
+    status!("{} problem note(s).", notes.len());
     Ok(())
 }
 
-fn handle_list_card_types_command(collection_path: PathBuf) -> Result<()> {
-    let mut repository = AnkiRepository::new(&collection_path)?;
-
-    // List all available notetypes
-    info!("Listing card types");
-    let notetypes = repository.list_notetypes()?;
-    debug!(count = notetypes.len(), "Retrieved notetypes");
+fn handle_review_stats_command(
+    deck: Option<&str>,
+    days: u32,
+    json: bool,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    info!(?deck, days, "Computing review statistics");
+    let stats = ankiview.review_stats(deck, days)?;
 
-    // Print header
-    println!("Available card types:");
-    println!("{:<15} Name", "ID");
-    println!("{}", "-".repeat(60));
+    if json {
+        let json_output = serde_json::to_string_pretty(&stats)
+            .context("Failed to serialize review statistics to JSON")?;
+        println!("{}", json_output);
+        return Ok(());
+    }
+    print_review_stats(&stats, deck);
+    Ok(())
+}
 
-    // Format and print each notetype
-    for (id, name) in notetypes {
-        println!("{:<15} {}", id, name);
+fn print_review_stats(stats: &domain::ReviewStats, deck: Option<&str>) {
+    println!(
+        "Reviews in the last {} day(s){}: {}",
+        stats.days,
+        deck.map(|d| format!(" in {}", d)).unwrap_or_default(),
+        stats.total_reviews
+    );
+    match stats.retention {
+        Some(retention) => println!("Retention: {:.1}%", retention * 100.0),
+        None => println!("Retention: -"),
+    }
+    match stats.average_answer_seconds {
+        Some(seconds) => println!("Average answer time: {:.1}s", seconds),
+        None => println!("Average answer time: -"),
     }
+    println!();
+    for day in &stats.reviews_per_day {
+        println!("{}  {:>5}", day.date, day.reviews);
+    }
+}
 
-    Ok(())
+/// How `collect` talks to the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CollectUi {
+    /// Ask about conflicts when stdin is a terminal
+    Terminal,
+    /// Never ask (`--non-interactive`)
+    NonInteractive,
+    /// Never ask; annotations and a summary line for CI job logs (`--ci`)
+    Ci,
 }
 
 fn handle_collect_command(
     path: PathBuf,
     recursive: bool,
-    config: crate::inka::application::card_collector::CollectorConfig,
-    collection_path: PathBuf,
+    config: CollectorConfig,
+    timings: bool,
+    ui: CollectUi,
+    config_file: Option<&Path>,
+    ankiview: &AnkiView,
 ) -> Result<()> {
-    use crate::inka::application::card_collector::CardCollector;
+    use std::io::IsTerminal;
 
     info!(
         ?path,
@@ -189,48 +1550,114 @@ fn handle_collect_command(
         ignore_errors = config.ignore_errors,
         full_sync = config.full_sync,
         update_ids = config.update_ids,
+        same_deck = config.same_deck,
+        move_on_deck_change = config.move_on_deck_change,
         card_type = ?config.card_type,
+        strict = config.strict,
+        check = config.check,
+        deck = ?config.deck,
+        deck_prefix = ?config.deck_prefix,
+        extra_tags = ?config.extra_tags,
+        sync_tags = config.sync_tags,
+        tag_namespace = ?config.tag_namespace,
+        new_position = ?config.new_position,
+        on_duplicate = ?config.on_duplicate,
+        format = ?config.format,
         "Collecting markdown cards"
     );
+    let strict = config.strict;
+    let check = config.check;
+
+    let explicit_config = config_file;
+    let (settings, config_file) =
+        inka::infrastructure::config::Config::discover(explicit_config, &path)?;
+    // Hook commands are relative to the config file that defines them
+    let hooks_file =
+        inka::infrastructure::config::hooks_file(explicit_config, config_file.as_deref());
+    let hook_dir = match hooks_file.as_deref().and_then(Path::parent) {
+        Some(dir) => dir.to_path_buf(),
+        None => std::env::current_dir()?,
+    };
+    let hook_env = vec![
+        (
+            "ANKIVIEW_COLLECTION",
+            ankiview.collection_path().display().to_string(),
+        ),
+        ("ANKIVIEW_PATH", path.display().to_string()),
+        ("ANKIVIEW_CHECK", if check { "1" } else { "0" }.to_string()),
+    ];
+    if let Some(command) = &settings.hooks.pre_collect {
+        inka::infrastructure::hooks::run_hook("pre_collect", command, &hook_env, &hook_dir)?;
+    }
 
-    // Initialize collector
-    let mut collector = CardCollector::new(&collection_path, config)?;
-
-    // Process based on path type
-    let total_cards = if path.is_file() {
-        // Single file
-        collector.process_file(&path)?
-    } else if path.is_dir() {
-        if recursive {
-            // Recursive directory processing
-            collector.process_directory(&path)?
-        } else {
-            // Non-recursive - only process .md files in the directory
-            let mut count = 0;
-            for entry in std::fs::read_dir(&path)? {
-                let entry = entry?;
-                let entry_path = entry.path();
-                if entry_path.is_file()
-                    && entry_path.extension().and_then(|s| s.to_str()) == Some("md")
-                {
-                    count += collector.process_file(&entry_path)?;
-                }
-            }
-            count
-        }
+    let config = CollectorConfig {
+        history: settings.history.enabled,
+        source_link: settings.footer.link.clone(),
+        cache: settings.cache.resolved(config_file.as_deref()),
+        lint: config.lint.as_ref().map(|_| settings.lint.clone()),
+        image_alt: settings.lint.image_alt,
+        source_root: config_file
+            .as_deref()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf),
+        media: settings.media.clone(),
+        deck_options: settings.defaults.deck_options.clone(),
+        tag_namespace: config
+            .tag_namespace
+            .clone()
+            .or_else(|| settings.defaults.tag_namespace.clone()),
+        card_formats: settings.cards.formats.clone(),
+        section_start: settings.cards.section_start.clone(),
+        section_end: settings.cards.section_end.clone(),
+        markdown: settings.markdown.clone(),
+        math: settings.math.clone(),
+        ..config
+    };
+    // Conflicts are only asked about when someone can answer
+    let summary = if ui == CollectUi::Ci {
+        ankiview.collect_path_with_events(
+            &path,
+            recursive,
+            config,
+            inka::cli::ci::CiEventSink::stdout(),
+        )?
+    } else if ui == CollectUi::Terminal && !check && std::io::stdin().is_terminal() {
+        ankiview.collect_path_with_resolver(
+            &path,
+            recursive,
+            config,
+            inka::cli::console::ConsoleEventSink,
+            inka::cli::prompt::PromptResolver::stdio(),
+        )?
     } else {
-        return Err(anyhow::anyhow!("Path does not exist: {:?}", path));
+        ankiview.collect_path_with_events(
+            &path,
+            recursive,
+            config,
+            inka::cli::console::ConsoleEventSink,
+        )?
     };
 
     // Print summary
-    println!(
-        "Successfully processed {} card{}",
-        total_cards,
-        if total_cards == 1 { "" } else { "s" }
-    );
+    if check {
+        for change in &summary.pending_changes {
+            println!("{}", change);
+        }
+        status!(
+            "Checked {} card{}",
+            summary.cards,
+            if summary.cards == 1 { "" } else { "s" }
+        );
+    } else {
+        status!(
+            "Successfully processed {} card{}",
+            summary.cards,
+            if summary.cards == 1 { "" } else { "s" }
+        );
+    }
 
     // Print error summary if there were any errors
-    let errors = collector.errors();
+    let errors = &summary.errors;
     if !errors.is_empty() {
         eprintln!(
             "\n{} error{} occurred:",
@@ -242,33 +1669,124 @@ fn handle_collect_command(
         }
     }
 
+    let multi_matches = &summary.multi_matches;
+    if !multi_matches.is_empty() {
+        eprintln!(
+            "\n{} card{} matched several notes; deduplicate them in Anki:",
+            multi_matches.len(),
+            if multi_matches.len() == 1 { "" } else { "s" }
+        );
+        for entry in multi_matches {
+            eprintln!("  {}", entry);
+        }
+    }
+
+    let malformed_cards = &summary.malformed_cards;
+    if !malformed_cards.is_empty() {
+        eprintln!(
+            "\n{} item{} looked like a card but produced none (no answer or cloze):",
+            malformed_cards.len(),
+            if malformed_cards.len() == 1 { "" } else { "s" }
+        );
+        for entry in malformed_cards {
+            eprintln!("  {}", entry);
+        }
+    }
+
+    let deck_moves = &summary.deck_moves;
+    if !deck_moves.is_empty() {
+        status!(
+            "\nMoved the cards of {} note{} to a new deck:",
+            deck_moves.len(),
+            if deck_moves.len() == 1 { "" } else { "s" }
+        );
+        for entry in deck_moves {
+            status!("  {}", entry);
+        }
+    }
+
+    let recreated_notes = &summary.recreated_notes;
+    if !recreated_notes.is_empty() {
+        status!(
+            "\nRecreated {} deleted note{}:",
+            recreated_notes.len(),
+            if recreated_notes.len() == 1 { "" } else { "s" }
+        );
+        for entry in recreated_notes {
+            status!("  {}", entry);
+        }
+    }
+
+    // Requested explicitly, so printed even with --quiet
+    if timings {
+        eprintln!("{}", summary.timings.report());
+    }
+    if ui == CollectUi::Ci {
+        println!(
+            "ankiview collect: {} card(s), {} error(s), {} warning(s), {} malformed card(s), {} pending change(s)",
+            summary.cards,
+            summary.errors.len(),
+            summary.warnings.len(),
+            summary.malformed_cards.len(),
+            summary.pending_changes.len()
+        );
+    }
+
+    // The collection is closed again at this point, so hooks may sync it
+    if let Some(command) = &settings.hooks.post_collect {
+        let mut report = tempfile::NamedTempFile::new().context("Failed to create hook report")?;
+        serde_json::to_writer_pretty(&mut report, &summary)
+            .context("Failed to write hook report")?;
+        let mut env = hook_env;
+        env.extend([
+            ("ANKIVIEW_CARDS", summary.cards.to_string()),
+            ("ANKIVIEW_ERRORS", summary.errors.len().to_string()),
+            ("ANKIVIEW_WARNINGS", summary.warnings.len().to_string()),
+            (
+                "ANKIVIEW_PENDING_CHANGES",
+                summary.pending_changes.len().to_string(),
+            ),
+            ("ANKIVIEW_REPORT", report.path().display().to_string()),
+        ]);
+        inka::infrastructure::hooks::run_hook("post_collect", command, &env, &hook_dir)?;
+    }
+
+    let pending = summary.pending_changes.len();
+    if check && pending > 0 {
+        anyhow::bail!(
+            "Markdown is out of sync with Anki ({} pending change{}). Run `ankiview collect` to apply.",
+            pending,
+            if pending == 1 { "" } else { "s" }
+        );
+    }
+
+    // With --ignore-errors, strict failures were collected instead of aborting the run
+    if strict && !errors.is_empty() {
+        return Err(DomainError::StrictWarning(format!(
+            "{} file{} failed in strict mode",
+            errors.len(),
+            if errors.len() == 1 { "" } else { "s" }
+        ))
+        .into());
+    }
+
     Ok(())
 }
 
-fn handle_tag_command(subcommand: TagCommand, collection_path: PathBuf) -> Result<()> {
+fn handle_tag_command(subcommand: TagCommand, ankiview: &AnkiView) -> Result<()> {
     match subcommand {
         TagCommand::Add { note_id, tags } => {
-            let repository = AnkiRepository::new(&collection_path)?;
-            let mut updater = application::NoteUpdater::new(repository);
-
             info!(note_id, ?tags, "Adding tags");
-            updater
-                .add_tags(note_id, &tags)
-                .with_context(|| format!("Failed to add tags to note {}", note_id))?;
+            ankiview.add_tags(note_id, &tags)?;
 
-            println!("Added {} tag(s) to note {}.", tags.len(), note_id);
+            status!("Added {} tag(s) to note {}.", tags.len(), note_id);
             Ok(())
         }
         TagCommand::Remove { note_id, tags } => {
-            let repository = AnkiRepository::new(&collection_path)?;
-            let mut updater = application::NoteUpdater::new(repository);
-
             info!(note_id, ?tags, "Removing tags");
-            updater
-                .remove_tags(note_id, &tags)
-                .with_context(|| format!("Failed to remove tags from note {}", note_id))?;
+            ankiview.remove_tags(note_id, &tags)?;
 
-            println!("Removed {} tag(s) from note {}.", tags.len(), note_id);
+            status!("Removed {} tag(s) from note {}.", tags.len(), note_id);
             Ok(())
         }
         TagCommand::Replace { old, new, query } => {
@@ -278,21 +1796,16 @@ fn handle_tag_command(subcommand: TagCommand, collection_path: PathBuf) -> Resul
                 ));
             }
 
-            let repository = AnkiRepository::new(&collection_path)?;
-            let mut manager = application::TagManager::new(repository);
-
             info!(old_tag = %old, new_tag = %new, ?query, "Replacing tags");
-            let affected = manager
-                .replace_tag(query.as_deref(), &old, &new)
-                .with_context(|| "Failed to replace tags")?;
+            let affected = ankiview.replace_tag(query.as_deref(), &old, &new)?;
 
             // Format output based on mode
             if old.is_empty() {
-                println!("Added tag '{}' to {} note(s).", new, affected);
+                status!("Added tag '{}' to {} note(s).", new, affected);
             } else if new.is_empty() {
-                println!("Removed tag '{}' from {} note(s).", old, affected);
+                status!("Removed tag '{}' from {} note(s).", old, affected);
             } else {
-                println!(
+                status!(
                     "Replaced tag '{}' → '{}' on {} note(s).",
                     old, new, affected
                 );
@@ -302,19 +1815,573 @@ fn handle_tag_command(subcommand: TagCommand, collection_path: PathBuf) -> Resul
     }
 }
 
-fn handle_edit_command(note_id: i64, collection_path: PathBuf) -> Result<()> {
-    let repository = AnkiRepository::new(&collection_path)?;
+fn handle_cache_command(
+    subcommand: CacheCommand,
+    config_file: Option<&Path>,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    let (settings, config_file) =
+        inka::infrastructure::config::Config::discover(config_file, &std::env::current_dir()?)?;
+    let location = settings.cache.resolved(config_file.as_deref());
+
+    match subcommand {
+        CacheCommand::Show { json } => {
+            let Some(cache) = ankiview.read_hash_cache(&location)? else {
+                status!(
+                    "No hash cache at {}.",
+                    ankiview.hash_cache_path(&location).display()
+                );
+                return Ok(());
+            };
+            let entries = cache.entries();
+
+            if json {
+                let map: std::collections::BTreeMap<_, _> = entries.into_iter().collect();
+                let json_output = serde_json::to_string_pretty(&map)
+                    .context("Failed to serialize hash cache to JSON")?;
+                println!("{}", json_output);
+                return Ok(());
+            }
+            for (path, hash) in &entries {
+                let status = if cache.file_path(path).exists() {
+                    ""
+                } else {
+                    " (deleted)"
+                };
+                println!("{}  {}{}", &hash[..hash.len().min(12)], path, status);
+            }
+            status!("{} file(s) cached.", entries.len());
+            Ok(())
+        }
+        CacheCommand::Path => {
+            println!("{}", ankiview.hash_cache_path(&location).display());
+            Ok(())
+        }
+        CacheCommand::Prune => {
+            info!("Pruning hash cache");
+            let pruned = ankiview.prune_hash_cache(&location)?;
+
+            for path in &pruned {
+                println!("{}", path);
+            }
+            status!(
+                "Removed {} deleted file(s) from the hash cache.",
+                pruned.len()
+            );
+            Ok(())
+        }
+        CacheCommand::Clear => {
+            info!("Clearing hash cache");
+            let entries = ankiview.clear_hash_cache(&location)?;
+
+            status!("Cleared {} file(s) from the hash cache.", entries);
+            Ok(())
+        }
+    }
+}
+
+fn handle_notetype_command(
+    subcommand: NotetypeCommand,
+    config_file: Option<&Path>,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    match subcommand {
+        NotetypeCommand::Push {
+            name,
+            css,
+            front,
+            back,
+            template,
+            math_macros,
+        } => {
+            let read = |path: Option<PathBuf>| {
+                path.map(|path| {
+                    std::fs::read_to_string(&path)
+                        .with_context(|| format!("Failed to read {}", path.display()))
+                })
+                .transpose()
+            };
+            let math_macros = if math_macros {
+                let (settings, _) = inka::infrastructure::config::Config::discover(
+                    config_file,
+                    &std::env::current_dir()?,
+                )?;
+                Some(util::math::macro_definitions(&settings.math.macros)?)
+            } else {
+                None
+            };
+            let update = TemplateUpdate {
+                css: read(css)?,
+                front: read(front)?,
+                back: read(back)?,
+                template,
+                math_macros,
+            };
+            if update.css.is_none()
+                && update.front.is_none()
+                && update.back.is_none()
+                && update.math_macros.is_none()
+            {
+                return Err(anyhow::anyhow!(
+                    "Nothing to push; give at least one of --css, --front, --back or --math-macros."
+                ));
+            }
+
+            info!(notetype = %name, "Pushing notetype templates");
+            ankiview.update_notetype_templates(&name, &update)?;
+
+            let parts: Vec<&str> = [
+                update.css.as_ref().map(|_| "CSS"),
+                update.front.as_ref().map(|_| "front"),
+                update.back.as_ref().map(|_| "back"),
+                update.math_macros.as_ref().map(|_| "math macros"),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            status!("Updated {} of notetype '{}'.", parts.join(", "), name);
+            Ok(())
+        }
+    }
+}
+
+fn handle_replace_command(
+    query: Option<&str>,
+    find: &regex::Regex,
+    replacement: &str,
+    dry_run: bool,
+    ankiview: &AnkiView,
+) -> Result<()> {
+    info!(?query, find = %find, replacement, dry_run, "Replacing in note fields");
+    let changes = ankiview.replace_in_fields(query, find, replacement, dry_run)?;
+
+    // The changed fields are the requested data (the preview), so they go to stdout
+    for change in &changes {
+        println!("Note {}, field {}:", change.note_id, change.field + 1);
+        println!("  - {}", change.before);
+        println!("  + {}", change.after);
+    }
+
+    let notes = changes
+        .iter()
+        .map(|c| c.note_id)
+        .collect::<std::collections::BTreeSet<_>>()
+        .len();
+    if dry_run {
+        status!(
+            "Would change {} field(s) in {} note(s); nothing saved (dry run).",
+            changes.len(),
+            notes
+        );
+    } else {
+        status!("Changed {} field(s) in {} note(s).", changes.len(), notes);
+    }
+    Ok(())
+}
+
+fn handle_apply_rules_command(path: &Path, ankiview: &AnkiView) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read rules file {}", path.display()))?;
+    let rules = domain::parse_rules(&content)
+        .with_context(|| format!("Failed to parse rules file {}", path.display()))?;
+
+    info!(?path, rules = rules.len(), "Applying rules");
+    let reports = ankiview.apply_rules(&rules)?;
+
+    for report in &reports {
+        let mut actions = Vec::new();
+        if report.tags_added > 0 {
+            actions.push(format!("{} tag(s) added", report.tags_added));
+        }
+        if report.tags_removed > 0 {
+            actions.push(format!("{} tag(s) removed", report.tags_removed));
+        }
+        if report.cards_moved > 0 {
+            actions.push(format!("{} card(s) moved", report.cards_moved));
+        }
+        if report.fields_set > 0 {
+            actions.push(format!("{} note(s) with fields set", report.fields_set));
+        }
+        if actions.is_empty() {
+            actions.push("no changes".to_string());
+        }
+        status!("{}: {}", report.query, actions.join(", "));
+    }
+    status!("Applied {} rule(s).", reports.len());
+    Ok(())
+}
+
+fn handle_edit_command(note_id: i64, collection_path: &Path) -> Result<()> {
+    let repository = AnkiRepository::new(collection_path)?;
     let mut editor = application::NoteEditor::new(repository);
 
     info!(note_id, "Editing note");
     let changed = editor.edit(note_id)?;
 
     if changed {
-        println!("Note {} updated successfully.", note_id);
+        status!("Note {} updated successfully.", note_id);
     } else {
-        println!("No changes detected.");
+        status!("No changes detected.");
+    }
+
+    Ok(())
+}
+
+fn handle_preview_command(path: &Path, port: u16, config_file: Option<&Path>) -> Result<()> {
+    if !path.is_file() {
+        return Err(DomainError::PathNotFound(path.display().to_string()).into());
+    }
+    let (settings, _) = inka::infrastructure::config::Config::discover(config_file, path)?;
+    ports::preview::run_preview_server(path, port, &settings)
+}
+
+fn handle_lint_command(path: &Path, recursive: bool, config_file: Option<&Path>) -> Result<()> {
+    let files = source_files(path, SourceFormat::Markdown, recursive)?;
+
+    let mut issue_count = 0;
+    let mut files_with_issues = 0;
+    for file in &files {
+        let (settings, _) = inka::infrastructure::config::Config::discover(config_file, file)?;
+        let content = inka::infrastructure::file_writer::read_markdown_file_with_encoding(file)
+            .with_context(|| format!("Failed to read markdown file: {}", file.display()))?
+            .0;
+        let issues = inka::application::lint::lint_content(&content, &settings)
+            .with_context(|| format!("Failed to lint {}", file.display()))?;
+        for issue in &issues {
+            println!(
+                "{}:{}: [{}] {}",
+                file.display(),
+                issue.line,
+                issue.rule,
+                issue.message
+            );
+        }
+        issue_count += issues.len();
+        files_with_issues += usize::from(!issues.is_empty());
+    }
+
+    if issue_count > 0 {
+        anyhow::bail!(
+            "{} lint issue{} in {} file{}",
+            issue_count,
+            if issue_count == 1 { "" } else { "s" },
+            files_with_issues,
+            if files_with_issues == 1 { "" } else { "s" }
+        );
+    }
+    status!("No lint issues in {} file(s).", files.len());
+    Ok(())
+}
+
+fn handle_vault_stats_command(path: &Path, json: bool, config_file: Option<&Path>) -> Result<()> {
+    use inka::application::vault_stats::{CardCounts, VaultStats};
+
+    let files = source_files(path, SourceFormat::Markdown, true)?;
+    let mut stats = VaultStats::default();
+    for file in &files {
+        let (settings, _) = inka::infrastructure::config::Config::discover(config_file, file)?;
+        let content = inka::infrastructure::file_writer::read_markdown_file_with_encoding(file)
+            .with_context(|| format!("Failed to read markdown file: {}", file.display()))?
+            .0;
+        stats.add_file(file, &content, &settings);
+    }
+
+    if json {
+        let json_output = serde_json::to_string_pretty(&stats)
+            .context("Failed to serialize vault statistics to JSON")?;
+        println!("{}", json_output);
+        return Ok(());
+    }
+
+    let summary = |counts: &CardCounts| {
+        format!(
+            "{:>5} cards  {:>5} basic  {:>5} cloze ({:>4})  {:>6} words  avg answer {:>6} chars {:>5} words",
+            counts.cards,
+            counts.basic,
+            counts.cloze,
+            counts
+                .cloze_ratio
+                .map(|ratio| format!("{:.0}%", ratio * 100.0))
+                .unwrap_or_else(|| "-".to_string()),
+            counts.words,
+            counts
+                .average_answer_length
+                .map(|length| format!("{:.1}", length))
+                .unwrap_or_else(|| "-".to_string()),
+            counts
+                .average_answer_words
+                .map(|words| format!("{:.1}", words))
+                .unwrap_or_else(|| "-".to_string())
+        )
+    };
+    let print_group = |title: &str, groups: &std::collections::BTreeMap<String, CardCounts>| {
+        let width = groups
+            .keys()
+            .map(|name| name.chars().count())
+            .max()
+            .unwrap_or(0);
+        println!("{}:", title);
+        for (name, counts) in groups {
+            println!("  {:<width$}  {}", name, summary(counts), width = width);
+        }
+    };
+
+    println!("Files: {}", stats.files);
+    println!("Total: {}", summary(&stats.total));
+    println!();
+    print_group("By deck", &stats.by_deck);
+    println!();
+    print_group("By file", &stats.by_file);
+    Ok(())
+}
+
+fn handle_convert_command(
+    path: &Path,
+    card: Option<u32>,
+    config_file: Option<&Path>,
+) -> Result<()> {
+    use inka::infrastructure::media_handler;
+
+    let (settings, _) = inka::infrastructure::config::Config::discover(config_file, path)?;
+    let content = inka::infrastructure::file_writer::read_markdown_file_with_encoding(path)
+        .with_context(|| format!("Failed to read markdown file: {}", path.display()))?
+        .0;
+    let cards = inka::application::preview::preview_cards(&content, path, &settings)?;
+    let selected: Vec<_> = match card {
+        Some(number) => {
+            let index = number as usize - 1;
+            let card = cards.get(index).with_context(|| {
+                format!(
+                    "No card {} in {} ({} cards)",
+                    number,
+                    path.display(),
+                    cards.len()
+                )
+            })?;
+            vec![(index, card)]
+        }
+        None => cards.iter().enumerate().collect(),
+    };
+    let markdown_dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    for (index, card) in selected {
+        let kind = if card.cloze { "cloze" } else { "basic" };
+        let id = card
+            .note_id
+            .map(|id| format!(", note {}", id))
+            .unwrap_or_default();
+        println!(
+            "=== Card {} of {} ({}, deck {}{}) ===",
+            index + 1,
+            cards.len(),
+            kind,
+            card.deck,
+            id
+        );
+        println!("--- Source ---\n{}", card.source);
+
+        // Map images like collect does; missing files are reported instead of failing
+        let mut mapping = std::collections::HashMap::new();
+        let mut media_lines = Vec::new();
+        for image in &card.images {
+            let source = media_handler::resolve_media_path(markdown_dir, image);
+            if !source.exists() {
+                media_lines.push(format!("{} -> missing ({})", image, source.display()));
+                continue;
+            }
+            let filename = media_handler::media_filename(&source, &settings.media)?;
+            media_lines.push(format!("{} -> {} ({})", image, filename, source.display()));
+            mapping.insert(image.clone(), filename);
+        }
+
+        let names: &[&str] = if card.cloze {
+            &["Text"]
+        } else {
+            &["Front", "Back"]
+        };
+        for ((name, markdown), html) in names.iter().zip(&card.markdown).zip(&card.fields) {
+            let html = media_handler::update_media_paths_in_html(html, &mapping);
+            println!("--- {} (markdown) ---\n{}", name, markdown);
+            println!("--- {} (HTML) ---\n{}", name, html.trim_end());
+        }
+        if !media_lines.is_empty() {
+            println!("--- Media ---\n{}", media_lines.join("\n"));
+        }
+        println!();
+    }
+    Ok(())
+}
+
+fn handle_serve_command(collection_path: &Path) -> Result<()> {
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+
+    ports::json_rpc::JsonRpcServer::new(collection_path).serve(stdin.lock(), stdout.lock())
+}
+
+fn handle_daemon_command(
+    socket: Option<PathBuf>,
+    stop: bool,
+    collection_path: &Path,
+) -> Result<()> {
+    let socket_path = match socket {
+        Some(socket) => socket,
+        // A daemon started with --socket is found through its record
+        None if stop => ports::daemon::socket_path_for(collection_path),
+        None => ports::daemon::default_socket_path(collection_path),
+    };
+
+    if stop {
+        ports::daemon::DaemonClient::connect(&socket_path)?.shutdown()?;
+        status!("Daemon on {} stopped.", socket_path.display());
+        return Ok(());
+    }
+
+    ports::daemon::run_daemon(collection_path, &socket_path)
+}
+
+/// Collections given with `--collection` and `--profile`, in that order, each
+/// with the name its output is prefixed with. Without either, `list`, `count`
+/// and `review-stats` use `defaults.collections` from the config, and
+/// everything else the first profile found
+fn resolve_collections(args: &Args) -> Result<Vec<(String, PathBuf)>> {
+    let mut collections: Vec<(String, PathBuf)> = args
+        .collection
+        .iter()
+        .map(|path| (collection_label(path), path.clone()))
+        .collect();
+    for profile in &args.profile {
+        collections.push((profile.clone(), find_collection_path(Some(profile))?));
     }
 
+    let aggregates = matches!(
+        args.command,
+        Command::List { .. } | Command::Count { .. } | Command::ReviewStats { .. }
+    );
+    if collections.is_empty() && aggregates {
+        let (settings, config_file) = inka::infrastructure::config::Config::discover(
+            args.config.as_deref(),
+            &std::env::current_dir()?,
+        )?;
+        for entry in settings
+            .defaults
+            .resolved_collections(config_file.as_deref())
+        {
+            collections.push(match entry {
+                CollectionEntry::Profile(profile) => {
+                    let path = find_collection_path(Some(&profile))?;
+                    (profile, path)
+                }
+                CollectionEntry::Path(path) => (collection_label(&path), path),
+            });
+        }
+    }
+
+    if collections.is_empty() {
+        debug!("Finding collection path for first profile");
+        let path = find_collection_path(None)?;
+        collections.push((collection_label(&path), path));
+    }
+    Ok(collections)
+}
+
+/// Name of a collection in aggregated output: its profile directory's name
+pub fn collection_label(collection_path: &Path) -> String {
+    collection_path
+        .parent()
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| collection_path.display().to_string())
+}
+
+/// Run `list`, `count` or `review-stats` on each collection, prefixing the
+/// output with the collection's name
+fn handle_multi_collection_command(
+    command: Command,
+    collections: &[(String, PathBuf)],
+) -> Result<()> {
+    info!(collections = collections.len(), "Aggregating collections");
+    let collections = collections
+        .iter()
+        .map(|(label, path)| (label.as_str(), AnkiView::new(path)));
+
+    match command {
+        Command::List {
+            search,
+            mut limit,
+            due,
+            template,
+        } => {
+            for (label, ankiview) in collections {
+                if limit == Some(0) {
+                    break;
+                }
+                let count = if due {
+                    let notes = ankiview.list_due(search.as_deref())?;
+                    let shown = notes.len().min(limit.unwrap_or(usize::MAX));
+                    for (note, due) in notes.iter().take(shown) {
+                        let first_line = util::text::extract_first_line(&note.front);
+                        println!("{}\t{}\t{}\t{}", label, note.id, due, first_line);
+                    }
+                    shown
+                } else if let Some(template) = &template {
+                    let query = search
+                        .as_deref()
+                        .filter(|search| !search.is_empty())
+                        .map(|search| format!("front:*{}*", search));
+                    ankiview.for_each_note_record(query.as_deref(), limit, |record| {
+                        println!("{}\t{}", label, template.render(&record));
+                        Ok(())
+                    })?
+                } else {
+                    ankiview.for_each_note(search.as_deref(), limit, |note| {
+                        let first_line = util::text::extract_first_line(&note.front);
+                        println!("{}\t{}\t{}", label, note.id, first_line);
+                        Ok(())
+                    })?
+                };
+                limit = limit.map(|limit| limit - count);
+            }
+        }
+        Command::Count { query, by } => {
+            for (label, ankiview) in collections {
+                match by {
+                    None => println!("{}\t{}", label, ankiview.count(query.as_deref())?),
+                    Some(group) => {
+                        for (name, count) in ankiview.count_by(query.as_deref(), group)? {
+                            println!("{}\t{}\t{}", label, name, count);
+                        }
+                    }
+                }
+            }
+        }
+        Command::ReviewStats { deck, days, json } => {
+            if json {
+                let mut all = Vec::new();
+                for (label, ankiview) in collections {
+                    let stats = ankiview.review_stats(deck.as_deref(), days)?;
+                    all.push(serde_json::json!({ "collection": label, "stats": stats }));
+                }
+                let json_output = serde_json::to_string_pretty(&all)
+                    .context("Failed to serialize review statistics to JSON")?;
+                println!("{}", json_output);
+            } else {
+                for (index, (label, ankiview)) in collections.enumerate() {
+                    if index > 0 {
+                        println!();
+                    }
+                    println!("== {} ==", label);
+                    let stats = ankiview.review_stats(deck.as_deref(), days)?;
+                    print_review_stats(&stats, deck.as_deref());
+                }
+            }
+        }
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Only list, count and review-stats work on several collections"
+            ))
+        }
+    }
     Ok(())
 }
 
@@ -361,6 +2428,7 @@ pub fn find_collection_path(profile: Option<&str>) -> Result<PathBuf> {
 /// must be public to be used from integration tests
 mod tests {
     use super::*;
+    use clap::Parser;
 
     #[test]
     fn given_explicit_profile_when_finding_path_then_constructs_correct_path() {
@@ -421,6 +2489,28 @@ mod tests {
         // The integration tests cover the happy path with real collections.
     }
 
+    #[test]
+    fn given_collection_path_when_labelling_then_uses_profile_directory() {
+        let label = collection_label(Path::new("/anki/Work/collection.anki2"));
+
+        assert_eq!(label, "Work");
+    }
+
+    #[test]
+    fn given_several_profiles_when_resolving_collections_then_keeps_order_and_names() {
+        let args = Args::try_parse_from(["ankiview", "count", "-p", "Work", "-p", "Personal"])
+            .expect("Should parse");
+
+        let collections = resolve_collections(&args).expect("Should resolve");
+
+        let labels: Vec<&str> = collections
+            .iter()
+            .map(|(label, _)| label.as_str())
+            .collect();
+        assert_eq!(labels, vec!["Work", "Personal"]);
+        assert!(collections[1].1.ends_with("Personal/collection.anki2"));
+    }
+
     // Note: Testing the "find first valid profile" behavior requires
     // either a real Anki installation or complex filesystem mocking.
     // This is better covered by integration tests with fixture collections.
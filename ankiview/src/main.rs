@@ -1,26 +1,99 @@
-use ankiview::cli::args::Args;
+use ankiview::cli::args::{Args, Command, LogFormat};
+use ankiview::cli::error::{report_error, ErrorKind};
 // src/main.rs
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
+use std::process::ExitCode;
 use tracing::Level;
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::{fmt, prelude::*, EnvFilter, Layer, Registry};
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
 
+fn main() -> ExitCode {
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(e) if e.use_stderr() => {
+            // Usage errors get their own exit code instead of clap's default (2)
+            let _ = e.print();
+            return ExitCode::from(ErrorKind::Usage.exit_code());
+        }
+        // --help / --version
+        Err(e) => e.exit(),
+    };
+    let json_errors = args.json_errors;
+
+    let result = init_logging(&args).and_then(|_| ankiview::run(args));
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => ExitCode::from(report_error(&e, json_errors).exit_code()),
+    }
+}
+
+/// Initialize logging: console output on stderr, plus an optional daily-rotated log file.
+fn init_logging(args: &Args) -> Result<()> {
     // Initialize logging based on verbosity
-    let filter = match args.verbose {
+    let level = match args.verbose {
         0 => Level::INFO,
         1 => Level::DEBUG,
         _ => Level::TRACE,
     };
+    // --quiet only silences the console; the log file keeps the full level
+    let console_level = if args.quiet { Level::ERROR } else { level };
+    // CI logs show escape codes verbatim
+    let ansi = !matches!(args.command, Command::Collect { ci: true, .. });
+
+    let mut layers: Vec<BoxedLayer> = vec![format_layer(
+        args.log_format,
+        std::io::stderr,
+        ansi,
+        console_level,
+    )];
+
+    if let Some(path) = &args.log_file {
+        let directory = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or_else(|| std::path::Path::new("."));
+        let file_name = path
+            .file_name()
+            .with_context(|| format!("Invalid log file path: {}", path.display()))?;
+        std::fs::create_dir_all(directory)
+            .with_context(|| format!("Failed to create log directory: {}", directory.display()))?;
+
+        let appender = tracing_appender::rolling::daily(directory, file_name);
+        layers.push(format_layer(args.log_format, appender, false, level));
+    }
+
+    tracing_subscriber::registry().with(layers).init();
+
+    Ok(())
+}
 
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::from_default_env()
-                .add_directive(format!("ankiview={}", filter).parse().unwrap()),
-        )
-        .init();
+/// Build a fmt layer for the given format, writer and level.
+///
+/// JSON events are flattened so that fields like `file`, `note_id` and `action`
+/// appear as top-level keys next to `timestamp`, `level` and `message`.
+fn format_layer<W>(format: LogFormat, writer: W, ansi: bool, level: Level) -> BoxedLayer
+where
+    W: for<'w> fmt::MakeWriter<'w> + Send + Sync + 'static,
+{
+    let filter =
+        EnvFilter::from_default_env().add_directive(format!("ankiview={}", level).parse().unwrap());
 
-    ankiview::run(args)
+    match format {
+        LogFormat::Text => fmt::layer()
+            .with_ansi(ansi)
+            .with_writer(writer)
+            .with_filter(filter)
+            .boxed(),
+        LogFormat::Json => fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_span_list(false)
+            .with_writer(writer)
+            .with_filter(filter)
+            .boxed(),
+    }
 }
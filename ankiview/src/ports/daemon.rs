@@ -0,0 +1,348 @@
+// src/ports/daemon.rs
+//
+// Keep-alive daemon: serves the JSON-RPC protocol from `json_rpc` on a unix
+// socket next to the collection, so `view`/`list` can reuse an already-open
+// collection instead of opening collection.anki2 on every invocation.
+use crate::application::NotePick;
+use crate::domain::Note;
+use anyhow::Result;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Default socket location for a collection: `<collection dir>/ankiview.sock`
+pub fn default_socket_path(collection_path: &Path) -> PathBuf {
+    collection_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join("ankiview.sock")
+}
+
+/// File next to the collection naming the socket of a daemon started with
+/// `--socket`: `<collection dir>/ankiview.sock.path`
+pub fn socket_record_path(collection_path: &Path) -> PathBuf {
+    default_socket_path(collection_path).with_extension("sock.path")
+}
+
+/// Socket of the daemon for a collection: the one recorded by a daemon
+/// started with `--socket`, otherwise the default
+pub fn socket_path_for(collection_path: &Path) -> PathBuf {
+    std::fs::read_to_string(socket_record_path(collection_path))
+        .ok()
+        .map(|recorded| PathBuf::from(recorded.trim_end()))
+        .filter(|recorded| !recorded.as_os_str().is_empty())
+        .unwrap_or_else(|| default_socket_path(collection_path))
+}
+
+#[cfg(unix)]
+pub use unix::{run_daemon, DaemonClient};
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use crate::cli::error::{ErrorKind, RemoteError};
+    use crate::ports::json_rpc::JsonRpcServer;
+    use anyhow::Context;
+    use serde_json::json;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::time::Duration;
+    use tracing::{debug, info, warn};
+
+    // Generous enough for large list results, short enough not to hang a CLI call
+    const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Serve `collection_path` on `socket_path` until a client sends `shutdown`.
+    ///
+    /// Connections are handled one at a time; all share the same open collection.
+    /// A socket other than the default is recorded next to the collection while
+    /// the daemon runs (see `socket_path_for`).
+    pub fn run_daemon(collection_path: &Path, socket_path: &Path) -> Result<()> {
+        if socket_path.exists() {
+            if UnixStream::connect(socket_path).is_ok() {
+                anyhow::bail!(
+                    "A daemon is already listening on {}. Stop it with `ankiview daemon --stop`.",
+                    socket_path.display()
+                );
+            }
+            // Left behind by a daemon that did not shut down cleanly
+            std::fs::remove_file(socket_path).with_context(|| {
+                format!("Failed to remove stale socket: {}", socket_path.display())
+            })?;
+        }
+
+        let listener = UnixListener::bind(socket_path)
+            .with_context(|| format!("Failed to bind socket: {}", socket_path.display()))?;
+        info!(socket = ?socket_path, collection = ?collection_path, "Daemon listening");
+        crate::status!("ankiview daemon listening on {}", socket_path.display());
+
+        let record = socket_record_path(collection_path);
+        let socket_path = std::path::absolute(socket_path)?;
+        let recorded = socket_path != std::path::absolute(default_socket_path(collection_path))?;
+        if recorded {
+            std::fs::write(&record, format!("{}\n", socket_path.display()))
+                .with_context(|| format!("Failed to record socket in {}", record.display()))?;
+            debug!(record = ?record, "Recorded daemon socket");
+        }
+
+        let mut server = JsonRpcServer::new(collection_path);
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!(error = %e, "Failed to accept connection");
+                    continue;
+                }
+            };
+            debug!("Client connected");
+            let reader = BufReader::new(stream.try_clone()?);
+            if let Err(e) = server.serve(reader, &stream) {
+                warn!(error = %e, "Client connection failed");
+            }
+            if server.is_shutdown() {
+                break;
+            }
+        }
+
+        drop(server);
+        let _ = std::fs::remove_file(&socket_path);
+        // Leave a record alone that another daemon has taken over meanwhile
+        if recorded && socket_path_for(collection_path) == socket_path {
+            let _ = std::fs::remove_file(&record);
+        }
+        info!("Daemon stopped");
+        Ok(())
+    }
+
+    /// Compare paths after resolving symlinks and relative components (when they exist)
+    fn same_file(a: &Path, b: &Path) -> bool {
+        let canonical = |p: &Path| std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+        canonical(a) == canonical(b)
+    }
+
+    /// Connection to a running daemon
+    pub struct DaemonClient {
+        reader: BufReader<UnixStream>,
+        writer: UnixStream,
+        next_id: u64,
+    }
+
+    impl DaemonClient {
+        /// Connect to the daemon on `socket_path`.
+        pub fn connect(socket_path: &Path) -> Result<Self> {
+            let stream = UnixStream::connect(socket_path).with_context(|| {
+                format!("No ankiview daemon listening on {}", socket_path.display())
+            })?;
+            stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+            stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+            Ok(Self {
+                reader: BufReader::new(stream.try_clone()?),
+                writer: stream,
+                next_id: 1,
+            })
+        }
+
+        /// Connect to the daemon socket of `collection_path` (see
+        /// `socket_path_for`), if a daemon is running there and serving that
+        /// same collection.
+        pub fn connect_for(collection_path: &Path) -> Option<Self> {
+            let socket_path = socket_path_for(collection_path);
+            if !socket_path.exists() {
+                return None;
+            }
+            let mut client = Self::connect(&socket_path).ok()?;
+            let status = client.call("status", Value::Null).ok()?;
+            let served = status.get("collectionPath")?.as_str()?;
+            if !same_file(Path::new(served), collection_path) {
+                debug!(served, "Daemon serves a different collection, not using it");
+                return None;
+            }
+            debug!(socket = ?socket_path, "Using running daemon");
+            Some(client)
+        }
+
+        /// Send a request and wait for its result.
+        pub fn call(&mut self, method: &str, params: Value) -> Result<Value> {
+            let id = self.next_id;
+            self.next_id += 1;
+            let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+            writeln!(self.writer, "{}", request).context("Failed to send request to daemon")?;
+
+            let mut line = String::new();
+            self.reader
+                .read_line(&mut line)
+                .context("Failed to read response from daemon")?;
+            let response: Value =
+                serde_json::from_str(&line).context("Invalid response from daemon")?;
+
+            if let Some(error) = response.get("error") {
+                let kind = error
+                    .pointer("/data/kind")
+                    .and_then(Value::as_str)
+                    .and_then(ErrorKind::from_name)
+                    .unwrap_or(ErrorKind::General);
+                let message = error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("Unknown daemon error")
+                    .to_string();
+                return Err(RemoteError { kind, message }.into());
+            }
+            Ok(response.get("result").cloned().unwrap_or(Value::Null))
+        }
+
+        pub fn view_note(&mut self, note_id: i64) -> Result<Note> {
+            let result = self.call("viewNote", json!({ "noteId": note_id }))?;
+            serde_json::from_value(result).context("Invalid note in daemon response")
+        }
+
+        pub fn list(
+            &mut self,
+            search_query: Option<&str>,
+            limit: Option<usize>,
+        ) -> Result<Vec<Note>> {
+            let result = self.call(
+                "listNotes",
+                json!({ "search": search_query, "limit": limit }),
+            )?;
+            serde_json::from_value(result).context("Invalid notes in daemon response")
+        }
+
+        pub fn pick_note_id(&mut self, query: Option<&str>, pick: NotePick) -> Result<i64> {
+            let result = self.call("pickNote", json!({ "query": query, "pick": pick.as_str() }))?;
+            serde_json::from_value(result).context("Invalid note ID in daemon response")
+        }
+
+        /// Ask the daemon to exit (closing the collection).
+        pub fn shutdown(&mut self) -> Result<()> {
+            self.call("shutdown", Value::Null).map(|_| ())
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub use fallback::{run_daemon, DaemonClient};
+
+#[cfg(not(unix))]
+mod fallback {
+    use super::*;
+
+    pub fn run_daemon(_collection_path: &Path, _socket_path: &Path) -> Result<()> {
+        anyhow::bail!("The daemon is only supported on Unix-like systems")
+    }
+
+    /// Placeholder: commands always open the collection directly
+    pub struct DaemonClient;
+
+    impl DaemonClient {
+        pub fn connect(_socket_path: &Path) -> Result<Self> {
+            anyhow::bail!("The daemon is only supported on Unix-like systems")
+        }
+
+        pub fn connect_for(_collection_path: &Path) -> Option<Self> {
+            None
+        }
+
+        pub fn call(&mut self, _method: &str, _params: Value) -> Result<Value> {
+            unreachable!("DaemonClient cannot be constructed on this platform")
+        }
+
+        pub fn view_note(&mut self, _note_id: i64) -> Result<Note> {
+            unreachable!("DaemonClient cannot be constructed on this platform")
+        }
+
+        pub fn list(
+            &mut self,
+            _search_query: Option<&str>,
+            _limit: Option<usize>,
+        ) -> Result<Vec<Note>> {
+            unreachable!("DaemonClient cannot be constructed on this platform")
+        }
+
+        pub fn pick_note_id(&mut self, _query: Option<&str>, _pick: NotePick) -> Result<i64> {
+            unreachable!("DaemonClient cannot be constructed on this platform")
+        }
+
+        pub fn shutdown(&mut self) -> Result<()> {
+            unreachable!("DaemonClient cannot be constructed on this platform")
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::cli::error::ErrorKind;
+    use tempfile::TempDir;
+
+    #[test]
+    fn given_collection_path_when_deriving_socket_then_next_to_collection() {
+        let socket = default_socket_path(Path::new("/anki/User 1/collection.anki2"));
+
+        assert_eq!(socket, PathBuf::from("/anki/User 1/ankiview.sock"));
+    }
+
+    #[test]
+    fn given_no_daemon_when_connecting_for_collection_then_none() {
+        let temp_dir = TempDir::new().unwrap();
+        let collection_path = temp_dir.path().join("collection.anki2");
+
+        assert!(DaemonClient::connect_for(&collection_path).is_none());
+    }
+
+    #[test]
+    fn given_running_daemon_when_calling_then_relays_results_and_error_kinds() {
+        let temp_dir = TempDir::new().unwrap();
+        let collection_path = temp_dir.path().join("collection.anki2");
+        let socket_path = default_socket_path(&collection_path);
+
+        let daemon = {
+            let collection_path = collection_path.clone();
+            let socket_path = socket_path.clone();
+            std::thread::spawn(move || run_daemon(&collection_path, &socket_path))
+        };
+        while !socket_path.exists() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        let mut client = DaemonClient::connect_for(&collection_path).expect("daemon running");
+        // Collection file doesn't exist: error kind must survive the round trip
+        let err = client.list(None, None).unwrap_err();
+        assert_eq!(ErrorKind::classify(&err), ErrorKind::NotFound);
+        let err = client.pick_note_id(None, NotePick::Random).unwrap_err();
+        assert_eq!(ErrorKind::classify(&err), ErrorKind::NotFound);
+
+        client.shutdown().unwrap();
+        drop(client);
+        daemon.join().unwrap().unwrap();
+        assert!(!socket_path.exists());
+    }
+
+    #[test]
+    fn given_daemon_on_custom_socket_when_connecting_for_collection_then_finds_it() {
+        let temp_dir = TempDir::new().unwrap();
+        let collection_path = temp_dir.path().join("collection.anki2");
+        let socket_path = temp_dir.path().join("run").join("custom.sock");
+        std::fs::create_dir(socket_path.parent().unwrap()).unwrap();
+
+        let daemon = {
+            let collection_path = collection_path.clone();
+            let socket_path = socket_path.clone();
+            std::thread::spawn(move || run_daemon(&collection_path, &socket_path))
+        };
+        while !socket_record_path(&collection_path).exists() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        assert_eq!(socket_path_for(&collection_path), socket_path);
+        let mut client = DaemonClient::connect_for(&collection_path).expect("daemon running");
+
+        client.shutdown().unwrap();
+        drop(client);
+        daemon.join().unwrap().unwrap();
+        assert!(!socket_record_path(&collection_path).exists());
+        assert_eq!(
+            socket_path_for(&collection_path),
+            default_socket_path(&collection_path)
+        );
+    }
+}
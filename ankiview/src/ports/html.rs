@@ -1,7 +1,10 @@
 // src/ports/html.rs
 use crate::domain::Note;
+use crate::util::math::{mathjax_config, unwrap_math_code_blocks};
 use html_escape::decode_html_entities;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
 use regex::Regex;
+use std::collections::BTreeMap;
 use std::path::Path;
 use tracing::instrument;
 
@@ -11,6 +14,9 @@ use tracing::instrument;
 /// Domain (Note) -> Application (NoteViewer) -> Port (HtmlPresenter) -> Infrastructure (ContentRenderer)
 pub struct HtmlPresenter {
     media_dir: Option<String>,
+    sanitize: bool,
+    /// `window.MathJax` configuration
+    mathjax: String,
 }
 
 impl Default for HtmlPresenter {
@@ -21,29 +27,67 @@ impl Default for HtmlPresenter {
 
 impl HtmlPresenter {
     pub fn new() -> Self {
-        Self { media_dir: None }
+        Self {
+            media_dir: None,
+            sanitize: false,
+            mathjax: mathjax_config(&BTreeMap::new(), &[], &[]),
+        }
     }
 
     pub fn with_media_dir<P: AsRef<Path>>(media_dir: P) -> Self {
         Self {
             media_dir: Some(media_dir.as_ref().to_string_lossy().into_owned()),
+            ..Self::new()
         }
     }
 
+    /// Typeset math with this `window.MathJax` configuration (see
+    /// [`mathjax_config`]) instead of the default one
+    pub fn with_mathjax_config(mut self, config: String) -> Self {
+        self.mathjax = config;
+        self
+    }
+
+    /// Strip scripts, styles, iframes, event handlers and `javascript:` URLs
+    /// from the fields, for notes from untrusted shared decks
+    pub fn sanitized(mut self, sanitize: bool) -> Self {
+        self.sanitize = sanitize;
+        self
+    }
+
+    /// Field HTML with everything but formatting, images and links removed
+    fn sanitize_field(html: &str) -> String {
+        ammonia::Builder::default()
+            // Media resolved by `process_content`, and links between notes
+            .add_url_schemes(["file", "data", "ankiview"])
+            // Code highlighting, note links and right-to-left text rely on these
+            .add_generic_attributes(["class", "dir"])
+            // Inlined images are the only data URIs a note needs; elsewhere
+            // (e.g. `<a href="data:text/html,...">`) they carry active content
+            .attribute_filter(|element, attribute, value| {
+                let lower = value.trim_start().to_ascii_lowercase();
+                let keep = !lower.starts_with("data:")
+                    || (element == "img" && attribute == "src" && lower.starts_with("data:image/"));
+                keep.then(|| value.into())
+            })
+            .clean(html)
+            .to_string()
+    }
+
     #[instrument(level = "debug", ret)]
     fn process_content(&self, content: &str) -> String {
         // First decode any HTML entities
         let decoded = decode_html_entities(&content).to_string();
 
         // Replace code blocks containing LaTeX with just the LaTeX content
-        let code_block_re =
-            Regex::new(r"<pre><code[^>]*>(?s)\s*((?:\$\$.*?\$\$)|(?:\$.*?\$))\s*</code></pre>")
-                .unwrap();
+        let processed = unwrap_math_code_blocks(&decoded);
 
-        let processed = code_block_re
-            .replace_all(&decoded, |caps: &regex::Captures| {
-                caps.get(1).map_or("", |m| m.as_str().trim()).to_string()
-            })
+        // Render Anki furigana (` 漢字[かんじ]`) as ruby text, like the
+        // {{furigana:}} template filter does
+        let furigana_re =
+            Regex::new(r" ?([^\s<>\[\]]+)\[([\p{Hiragana}\p{Katakana}ー]+)\]").unwrap();
+        let processed = furigana_re
+            .replace_all(&processed, "<ruby>$1<rt>$2</rt></ruby>")
             .into_owned();
 
         // Handle image tags if media directory is set
@@ -55,8 +99,13 @@ impl HtmlPresenter {
                     let attrs = caps.get(2).map_or("", |m| m.as_str());
 
                     // If src is a URL, leave it unchanged
-                    if src.starts_with("http://") || src.starts_with("https://") {
+                    if src.starts_with("http://")
+                        || src.starts_with("https://")
+                        || src.starts_with("data:")
+                    {
                         format!(r#"<img src="{src}"{attrs}>"#)
+                    } else if let Some(data_uri) = svg_data_uri(media_dir, src) {
+                        format!(r#"<img src="{data_uri}"{attrs}>"#)
                     } else {
                         // Otherwise, prefix with media directory
                         format!(r#"<img src="file://{media_dir}/{src}"{attrs}>"#)
@@ -69,9 +118,13 @@ impl HtmlPresenter {
     }
 
     pub fn render(&self, note: &Note) -> String {
-        let front = self.process_content(&note.front);
-        let back = self.process_content(&note.back);
-        let tags = note.tags.join(", ");
+        let mut front = self.process_content(&note.front);
+        let mut back = self.process_content(&note.back);
+        if self.sanitize {
+            front = Self::sanitize_field(&front);
+            back = Self::sanitize_field(&back);
+        }
+        let tags = html_escape::encode_text(&note.tags.join(", ")).into_owned();
 
         format!(
             r#"<!DOCTYPE html>
@@ -79,6 +132,9 @@ impl HtmlPresenter {
 <head>
     <meta charset="UTF-8">
     <title>Anki Note {}</title>
+    <script>
+        window.MathJax = {mathjax};
+    </script>
     <script src="https://cdnjs.cloudflare.com/ajax/libs/mathjax/3.2.2/es5/tex-mml-chtml.js"></script>
     <link rel="stylesheet" href="https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/styles/github.min.css">
     <script src="https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/highlight.min.js"></script>
@@ -105,22 +161,6 @@ impl HtmlPresenter {
     <script src="https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/languages/kotlin.min.js"></script>
     <script src="https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/languages/sql.min.js"></script>
     <script>
-        window.MathJax = {{
-            tex: {{
-                inlineMath: [['$', '$']],
-                displayMath: [['$$', '$$']],
-                processEscapes: true,
-                packages: ['base', 'ams', 'noerrors', 'noundefined']
-            }},
-            options: {{
-                processHtmlClass: 'tex2jax_process'
-            }},
-            startup: {{
-                ready: () => {{
-                    MathJax.startup.defaultReady();
-                }}
-            }}
-        }};
         document.addEventListener('DOMContentLoaded', (event) => {{
             document.querySelectorAll('pre code').forEach((block) => {{
                 hljs.highlightBlock(block);
@@ -196,17 +236,41 @@ impl HtmlPresenter {
         .tex2jax_process {{
             margin: 1em 0;
         }}
+        .note-ref {{
+            text-decoration: underline dotted;
+        }}
+        .table-wrapper {{
+            overflow-x: auto;
+            margin: 1rem 0;
+        }}
+        table {{
+            border-collapse: collapse;
+        }}
+        th, td {{
+            border: 1px solid #dee2e6;
+            padding: 0.4em 0.75em;
+            text-align: left;
+        }}
+        th {{
+            background-color: #f8f9fa;
+        }}
+        dt {{
+            font-weight: 600;
+        }}
+        dd {{
+            margin: 0 0 0.5em 1.5em;
+        }}
     </style>
 </head>
 <body>
     <div class="card">
         <div class="card-front">
             <h2>Question</h2>
-            <div class="tex2jax_process">{front}</div>
+            <div class="tex2jax_process" dir="auto">{front}</div>
         </div>
         <div class="card-back">
             <h2>Answer</h2>
-            <div class="tex2jax_process">{back}</div>
+            <div class="tex2jax_process" dir="auto">{back}</div>
         </div>
         <div class="note-info">
             <div>Note ID: {note_id}</div>
@@ -219,10 +283,11 @@ impl HtmlPresenter {
 </body>
 </html>"#,
             note.id,
+            mathjax = self.mathjax,
             front = front,
             back = back,
             note_id = note.id,
-            model = note.model_name,
+            model = html_escape::encode_text(&note.model_name),
             tags = if tags.is_empty() {
                 "No tags".to_string()
             } else {
@@ -232,6 +297,19 @@ impl HtmlPresenter {
     }
 }
 
+/// Inline an SVG from the media directory as a data URI; browsers don't
+/// reliably render SVGs loaded via `file://` (no MIME type to go by)
+fn svg_data_uri(media_dir: &str, src: &str) -> Option<String> {
+    if !src.to_ascii_lowercase().ends_with(".svg") {
+        return None;
+    }
+    let svg = std::fs::read_to_string(Path::new(media_dir).join(src)).ok()?;
+    Some(format!(
+        "data:image/svg+xml;charset=utf-8,{}",
+        utf8_percent_encode(&svg, NON_ALPHANUMERIC)
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -254,13 +332,109 @@ mod tests {
                 r#"<img src="https://example.com/test.jpg" alt="test">"#,
                 Some("/media"),
             ),
+            (
+                r#"<img src="data:image/png;base64,AAAA" alt="test">"#,
+                r#"<img src="data:image/png;base64,AAAA" alt="test">"#,
+                Some("/media"),
+            ),
+            (
+                r#"<img src="missing.svg" alt="test">"#,
+                r#"<img src="file:///media/missing.svg" alt="test">"#,
+                Some("/media"),
+            ),
+            (
+                "<p>これは 漢字[かんじ]です。array[0]</p>",
+                "<p>これは<ruby>漢字<rt>かんじ</rt></ruby>です。array[0]</p>",
+                None,
+            ),
         ];
         for (input, expected, media_dir) in cases {
             let presenter = match media_dir {
                 Some(dir) => HtmlPresenter::with_media_dir(dir),
                 None => HtmlPresenter::new(),
             };
-            assert_eq!(&presenter.process_content(input), expected, "input: {input}");
+            assert_eq!(
+                &presenter.process_content(input),
+                expected,
+                "input: {input}"
+            );
         }
     }
+
+    #[test]
+    fn given_svg_in_media_dir_when_processing_then_inlines_data_uri() {
+        let media_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(media_dir.path().join("diagram.svg"), "<svg/>").unwrap();
+        let presenter = HtmlPresenter::with_media_dir(media_dir.path());
+
+        let html = presenter.process_content(r#"<img src="diagram.svg" alt="Diagram">"#);
+
+        assert_eq!(
+            html,
+            r#"<img src="data:image/svg+xml;charset=utf-8,%3Csvg%2F%3E" alt="Diagram">"#
+        );
+    }
+
+    #[test]
+    fn given_untrusted_note_when_rendering_sanitized_then_strips_active_content() {
+        let note = Note {
+            id: 1,
+            front: r#"<b>Q</b><script>alert(1)</script><iframe src="https://evil.example"></iframe>"#
+                .to_string(),
+            back: r#"<a href="javascript:alert(1)" onclick="steal()">A</a><style>body{}</style><img src="a.png">"#
+                .to_string(),
+            tags: vec![],
+            model_name: "Basic".to_string(),
+        };
+        let presenter = HtmlPresenter::with_media_dir("/media").sanitized(true);
+
+        let html = presenter.render(&note);
+
+        assert!(html.contains("<b>Q</b>"));
+        assert!(!html.contains("alert(1)"));
+        assert!(!html.contains("<iframe"));
+        assert!(!html.contains("steal()"));
+        assert!(!html.contains("body{}"));
+        assert!(html.contains(r#"<img src="file:///media/a.png">"#));
+    }
+
+    #[test]
+    fn given_untrusted_metadata_when_rendering_sanitized_then_escapes_it_and_drops_data_links() {
+        let note = Note {
+            id: 1,
+            front: r#"<a href="data:text/html,<script>alert(1)</script>">Q</a>"#.to_string(),
+            back: r#"<img src="data:image/png;base64,AAAA" alt="A">"#.to_string(),
+            tags: vec!["<script>tag()</script>".to_string()],
+            model_name: "<img src=x onerror=model()>".to_string(),
+        };
+        let presenter = HtmlPresenter::with_media_dir("/media").sanitized(true);
+
+        let html = presenter.render(&note);
+
+        assert!(!html.contains("data:text/html"));
+        assert!(html.contains(r#"<img src="data:image/png;base64,AAAA" alt="A">"#));
+        assert!(!html.contains("<script>tag()"));
+        assert!(html.contains("&lt;script&gt;tag()&lt;/script&gt;"));
+        assert!(!html.contains("<img src=x"));
+    }
+
+    #[test]
+    fn given_mathjax_config_when_rendering_then_configures_mathjax_before_loading_it() {
+        let note = Note {
+            id: 1,
+            front: r"\(\R\)".to_string(),
+            back: "A".to_string(),
+            tags: vec![],
+            model_name: "Basic".to_string(),
+        };
+        let macros = BTreeMap::from([("R".to_string(), r"\mathbb{R}".to_string())]);
+        let presenter = HtmlPresenter::new().with_mathjax_config(mathjax_config(&macros, &[], &[]));
+
+        let html = presenter.render(&note);
+
+        let config = html.find(r#"window.MathJax = {"options""#).unwrap();
+        let loader = html.find("mathjax/3.2.2").unwrap();
+        assert!(config < loader);
+        assert!(html.contains(r#""macros":{"R":"\\mathbb{R}"}"#));
+    }
 }
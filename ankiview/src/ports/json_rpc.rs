@@ -0,0 +1,501 @@
+// src/ports/json_rpc.rs
+//
+// Line-delimited JSON-RPC 2.0 adapter for editor integrations.
+//
+// One request per line in, one response per line out; notifications
+// (requests without an `id`) are handled but not answered. The collection is
+// opened on the first request and kept open until the client disconnects
+// (or sends `shutdown`), so repeated calls don't pay the open cost.
+use crate::application::{NotePick, NoteRepository};
+use crate::cli::error::ErrorKind;
+use crate::domain::DomainError;
+use crate::infrastructure::AnkiRepository;
+use crate::inka::application::card_collector::{CardCollector, CollectorConfig};
+use crate::inka::infrastructure::config::Config;
+use crate::CollectSummary;
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+// Standard JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+// Implementation-defined server error; `data.kind` carries the ankiview error kind
+const SERVER_ERROR: i64 = -32000;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NoteParams {
+    note_id: i64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListParams {
+    search: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PickParams {
+    /// Anki search query; all notes if omitted
+    query: Option<String>,
+    /// `random` or `latest`, as for `view --random`/`--latest`
+    pick: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CollectParams {
+    path: PathBuf,
+    #[serde(default)]
+    recursive: bool,
+    #[serde(default)]
+    force: bool,
+    #[serde(default)]
+    full_sync: bool,
+    #[serde(default)]
+    update_ids: bool,
+    #[serde(default)]
+    same_deck: bool,
+    #[serde(default)]
+    move_on_deck_change: bool,
+    #[serde(default)]
+    ignore_errors: bool,
+    card_type: Option<String>,
+    #[serde(default)]
+    strict: bool,
+    #[serde(default)]
+    check: bool,
+    deck: Option<String>,
+    deck_prefix: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    sync_tags: bool,
+    tag_namespace: Option<String>,
+    /// `start`, `end` or a due position, as for `collect --new-position`
+    new_position: Option<String>,
+    /// `create`, `skip` or `update`, as for `collect --on-duplicate`
+    on_duplicate: Option<String>,
+    /// `md` (default) or `org`, as for `collect --format`
+    format: Option<String>,
+    #[serde(default)]
+    index_notes: bool,
+    /// Report `[lint]` rule violations as warnings, as for `collect --lint`
+    #[serde(default)]
+    lint: bool,
+    /// Backup collection to restore the scheduling of recreated notes from,
+    /// as for `collect --restore-from`
+    restore_from: Option<PathBuf>,
+}
+
+/// Failure of a single request, rendered as a JSON-RPC error object
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(err: anyhow::Error) -> Self {
+        let kind = ErrorKind::classify(&err);
+        Self {
+            code: SERVER_ERROR,
+            message: format!("{:#}", err),
+            data: Some(json!({ "kind": kind.as_str(), "exitCode": kind.exit_code() })),
+        }
+    }
+}
+
+impl From<DomainError> for RpcError {
+    fn from(err: DomainError) -> Self {
+        anyhow::Error::from(err).into()
+    }
+}
+
+/// JSON-RPC session bound to one collection.
+///
+/// Methods: `viewNote {noteId}`, `listNotes {search?, limit?}`, `listCardTypes`,
+/// `collectFile {path, recursive?, force?, fullSync?, updateIds?, ignoreErrors?, cardType?,
+/// strict?, check?, deck?, deckPrefix?, tags?}`,
+/// `status` and `shutdown`.
+pub struct JsonRpcServer {
+    collection_path: PathBuf,
+    repository: Option<AnkiRepository>,
+    shutdown: bool,
+}
+
+impl JsonRpcServer {
+    pub fn new(collection_path: impl Into<PathBuf>) -> Self {
+        Self {
+            collection_path: collection_path.into(),
+            repository: None,
+            shutdown: false,
+        }
+    }
+
+    /// Serve requests from `reader` until EOF or `shutdown`.
+    pub fn serve<R: BufRead, W: Write>(&mut self, reader: R, mut writer: W) -> Result<()> {
+        debug!(collection = ?self.collection_path, "JSON-RPC session started");
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle_line(&line) {
+                writeln!(writer, "{}", response)?;
+                writer.flush()?;
+            }
+            if self.shutdown {
+                break;
+            }
+        }
+        debug!("JSON-RPC session ended");
+        Ok(())
+    }
+
+    /// Whether a client has requested `shutdown`
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown
+    }
+
+    /// Handle one request line and return the serialized response, `None`
+    /// for a notification.
+    pub fn handle_line(&mut self, line: &str) -> Option<String> {
+        let value: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(e) => {
+                return Some(error_response(
+                    Value::Null,
+                    RpcError::new(PARSE_ERROR, e.to_string()),
+                ))
+            }
+        };
+        // An explicit `"id": null` is still a request and gets its response
+        let notification = value.get("id").is_none();
+        let request: Request = match serde_json::from_value(value) {
+            Ok(request) => request,
+            Err(e) => {
+                return Some(error_response(
+                    Value::Null,
+                    RpcError::new(INVALID_REQUEST, e.to_string()),
+                ))
+            }
+        };
+
+        debug!(method = %request.method, id = %request.id, notification, "Handling request");
+        let outcome = self.dispatch(&request.method, request.params);
+        if notification {
+            if let Err(err) = outcome {
+                warn!(method = %request.method, error = %err.message, "Notification failed");
+            }
+            return None;
+        }
+        Some(match outcome {
+            Ok(result) => {
+                json!({ "jsonrpc": "2.0", "id": request.id, "result": result }).to_string()
+            }
+            Err(err) => error_response(request.id, err),
+        })
+    }
+
+    fn dispatch(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "viewNote" => {
+                let params: NoteParams = parse_params(params)?;
+                let note = self.repository()?.get_note(params.note_id)?;
+                Ok(json!(note))
+            }
+            "listNotes" => {
+                let params: ListParams = if params.is_null() {
+                    ListParams::default()
+                } else {
+                    parse_params(params)?
+                };
+                let notes = self
+                    .repository()?
+                    .list_notes_iter(params.search.as_deref())?
+                    .take(params.limit.unwrap_or(usize::MAX))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(json!(notes))
+            }
+            "pickNote" => {
+                let params: PickParams = parse_params(params)?;
+                let pick: NotePick = params
+                    .pick
+                    .parse()
+                    .map_err(|e: String| RpcError::new(INVALID_PARAMS, e))?;
+                let query = params.query.as_deref();
+                let note_ids = self.repository()?.note_ids(query)?;
+                Ok(json!(pick.choose(&note_ids, query)?))
+            }
+            "listCardTypes" => {
+                let notetypes = self.repository()?.list_notetypes()?;
+                let notetypes: Vec<Value> = notetypes
+                    .into_iter()
+                    .map(|(id, name)| json!({ "id": id, "name": name }))
+                    .collect();
+                Ok(json!(notetypes))
+            }
+            "collectFile" => {
+                let params: CollectParams = parse_params(params)?;
+                Ok(json!(self.collect(params)?))
+            }
+            "status" => Ok(json!({
+                "collectionPath": self.collection_path,
+                "open": self.repository.is_some(),
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Method not found: {}", method),
+            )),
+        }
+    }
+
+    fn collect(&mut self, params: CollectParams) -> Result<CollectSummary> {
+        // `[media]`, `[cards]`, `[markdown]`, `[math]`, `[footer]`, `[cache]`,
+        // `defaults.deck_options` and `defaults.tag_namespace` from the
+        // ankiview.toml nearest to the path
+        let (settings, config_file) = Config::discover(None, &params.path)?;
+        let new_position = params
+            .new_position
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .map_err(anyhow::Error::msg)?;
+        let on_duplicate = params
+            .on_duplicate
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .map_err(anyhow::Error::msg)?;
+        let format = params
+            .format
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .map_err(anyhow::Error::msg)?
+            .unwrap_or_default();
+        let config = CollectorConfig {
+            force: params.force,
+            full_sync: params.full_sync,
+            update_ids: params.update_ids,
+            same_deck: params.same_deck,
+            move_on_deck_change: params.move_on_deck_change,
+            ignore_errors: params.ignore_errors,
+            card_type: params.card_type,
+            strict: params.strict,
+            check: params.check,
+            deck: params.deck,
+            deck_prefix: params.deck_prefix,
+            extra_tags: params.tags,
+            sync_tags: params.sync_tags,
+            tag_namespace: params.tag_namespace.or(settings.defaults.tag_namespace),
+            media: settings.media,
+            deck_options: settings.defaults.deck_options,
+            card_formats: settings.cards.formats,
+            section_start: settings.cards.section_start,
+            section_end: settings.cards.section_end,
+            markdown: settings.markdown,
+            math: settings.math,
+            new_position,
+            on_duplicate,
+            format,
+            index_notes: params.index_notes,
+            history: settings.history.enabled,
+            source_link: settings.footer.link,
+            cache: settings.cache.resolved(config_file.as_deref()),
+            image_alt: settings.lint.image_alt,
+            lint: params.lint.then_some(settings.lint),
+            restore_from: params.restore_from,
+            source_root: config_file
+                .as_deref()
+                .and_then(Path::parent)
+                .map(Path::to_path_buf),
+        };
+        let collection_path = self.collection_path.clone();
+        let started = std::time::Instant::now();
+        let mut collector =
+            CardCollector::with_repository(collection_path, self.repository()?, config)?;
+        let cards = collector.process_path(&params.path, params.recursive)?;
+        collector.reposition_new_cards()?;
+        collector.save_cache()?;
+
+        let mut timings = collector.timings().clone();
+        timings.set_total(started.elapsed());
+        Ok(CollectSummary {
+            cards,
+            errors: collector.errors().to_vec(),
+            warnings: collector.warnings().to_vec(),
+            multi_matches: collector.multi_matches().to_vec(),
+            deck_moves: collector.deck_moves().to_vec(),
+            malformed_cards: collector.malformed_cards().to_vec(),
+            recreated_notes: collector.recreated_notes().to_vec(),
+            pending_changes: collector.pending_changes().to_vec(),
+            timings,
+        })
+    }
+
+    /// Open the collection on first use and keep it open afterwards
+    fn repository(&mut self) -> Result<&mut AnkiRepository> {
+        if self.repository.is_none() {
+            self.repository = Some(AnkiRepository::new(&self.collection_path)?);
+        }
+        Ok(self
+            .repository
+            .as_mut()
+            .expect("repository was just opened"))
+    }
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn error_response(id: Value, err: RpcError) -> String {
+    let mut error = json!({ "code": err.code, "message": err.message });
+    if let Some(data) = err.data {
+        error["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "id": id, "error": error }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> JsonRpcServer {
+        JsonRpcServer::new("/nonexistent/collection.anki2")
+    }
+
+    #[test]
+    fn given_invalid_json_when_handling_then_returns_parse_error() {
+        let response: Value =
+            serde_json::from_str(&server().handle_line("{not json").unwrap()).unwrap();
+
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        assert_eq!(response["id"], Value::Null);
+    }
+
+    #[test]
+    fn given_unknown_method_when_handling_then_returns_method_not_found() {
+        let response: Value = serde_json::from_str(
+            &server()
+                .handle_line(r#"{"jsonrpc":"2.0","id":1,"method":"frobnicate"}"#)
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(response["id"], 1);
+    }
+
+    #[test]
+    fn given_missing_params_when_viewing_note_then_returns_invalid_params() {
+        let response: Value = serde_json::from_str(
+            &server()
+                .handle_line(r#"{"jsonrpc":"2.0","id":2,"method":"viewNote","params":{}}"#)
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[test]
+    fn given_unknown_pick_when_picking_note_then_returns_invalid_params() {
+        let response: Value = serde_json::from_str(
+            &server()
+                .handle_line(
+                    r#"{"jsonrpc":"2.0","id":4,"method":"pickNote","params":{"pick":"best"}}"#,
+                )
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+        assert!(response["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("expected random or latest"));
+    }
+
+    #[test]
+    fn given_missing_collection_when_listing_then_returns_not_found_kind() {
+        let response: Value = serde_json::from_str(
+            &server()
+                .handle_line(r#"{"jsonrpc":"2.0","id":3,"method":"listNotes"}"#)
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(response["error"]["code"], SERVER_ERROR);
+        assert_eq!(response["error"]["data"]["kind"], "not_found");
+        assert_eq!(response["error"]["data"]["exitCode"], 2);
+    }
+
+    #[test]
+    fn given_notifications_when_serving_then_answers_only_requests() {
+        let input = "{\"jsonrpc\":\"2.0\",\"method\":\"frobnicate\"}\n\
+                     {\"jsonrpc\":\"2.0\",\"method\":\"listNotes\"}\n\
+                     {\"jsonrpc\":\"2.0\",\"id\":null,\"method\":\"frobnicate\"}\n\
+                     {\"jsonrpc\":\"2.0\",\"method\":\"shutdown\"}\n\
+                     {\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"frobnicate\"}\n";
+        let mut server = server();
+        let mut output = Vec::new();
+
+        server.serve(input.as_bytes(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().count(), 1, "only the request with id: null");
+        let response: Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        assert!(server.is_shutdown());
+    }
+
+    #[test]
+    fn given_shutdown_request_when_serving_then_stops_before_next_line() {
+        let input = "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"shutdown\"}\n\
+                     {\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"frobnicate\"}\n";
+        let mut output = Vec::new();
+
+        server().serve(input.as_bytes(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().count(), 1);
+        let response: Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"], Value::Null);
+    }
+}
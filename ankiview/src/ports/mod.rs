@@ -1,3 +1,7 @@
 // src/ports/mod.rs
+pub mod daemon;
 pub mod html;
+pub mod json_rpc;
+pub mod picker;
+pub mod preview;
 pub use html::HtmlPresenter;
@@ -0,0 +1,114 @@
+// src/ports/picker.rs
+use crate::domain::NoteRecord;
+use crate::util::text::extract_first_line;
+use anyhow::{Context, Result};
+use dialoguer::console::Term;
+use dialoguer::FuzzySelect;
+
+/// Entry of the `pick` list for one note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickItem {
+    pub note_id: i64,
+    pub label: String,
+}
+
+impl PickItem {
+    /// First line of the first field, then decks and tags, so all of them can
+    /// be searched
+    pub fn from_record(record: &NoteRecord) -> Self {
+        let front = record
+            .fields
+            .first()
+            .map(|(_, value)| extract_first_line(value))
+            .unwrap_or_default();
+        let mut label = format!("{}  [{}]", front, record.decks.join(", "));
+        if !record.tags.is_empty() {
+            label.push_str(&format!("  #{}", record.tags.join(" #")));
+        }
+        Self {
+            note_id: record.id,
+            label,
+        }
+    }
+}
+
+/// Fuzzy-searchable note list drawn on stderr, so stdout only gets the
+/// picked IDs
+pub struct NotePicker {
+    term: Term,
+}
+
+impl NotePicker {
+    pub fn stderr() -> Self {
+        Self {
+            term: Term::stderr(),
+        }
+    }
+
+    /// Let the user pick from `items`. With `multi`, the list is shown again
+    /// without the picked notes until Esc; otherwise one pick ends it.
+    /// Returns the picked IDs in order, empty if the first list was dismissed
+    pub fn pick(&self, mut items: Vec<PickItem>, multi: bool) -> Result<Vec<i64>> {
+        if !self.term.is_term() {
+            return Err(anyhow::anyhow!("pick needs a terminal on stderr"));
+        }
+
+        let mut picked = Vec::new();
+        while !items.is_empty() {
+            let prompt = if picked.is_empty() {
+                "Note".to_string()
+            } else {
+                format!("Note ({} picked, Esc to finish)", picked.len())
+            };
+            let labels: Vec<&str> = items.iter().map(|item| item.label.as_str()).collect();
+            let selection = FuzzySelect::new()
+                .with_prompt(prompt)
+                .items(&labels)
+                .default(0)
+                .max_length(20)
+                .interact_on_opt(&self.term)
+                .context("Failed to show note picker")?;
+            let Some(index) = selection else {
+                break;
+            };
+            picked.push(items.remove(index).note_id);
+            if !multi {
+                break;
+            }
+        }
+        Ok(picked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_note_record_when_building_pick_item_then_label_has_front_decks_and_tags() {
+        let record = NoteRecord {
+            id: 1234567890,
+            guid: "abc".to_string(),
+            notetype: "Basic".to_string(),
+            fields: vec![
+                (
+                    "Front".to_string(),
+                    "<p>What is <b>ownership</b>?</p><p>More</p>".to_string(),
+                ),
+                ("Back".to_string(), "One owner".to_string()),
+            ],
+            tags: vec!["rust".to_string(), "memory".to_string()],
+            decks: vec!["Rust::Basics".to_string()],
+            created: 0,
+            modified: 0,
+        };
+
+        let item = PickItem::from_record(&record);
+
+        assert_eq!(item.note_id, 1234567890);
+        assert_eq!(
+            item.label,
+            "What is ownership?  [Rust::Basics]  #rust #memory"
+        );
+    }
+}
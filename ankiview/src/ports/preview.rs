@@ -0,0 +1,388 @@
+// src/ports/preview.rs
+//
+// Live preview for `ankiview preview`: a small HTTP server on localhost that
+// converts a markdown file's cards like `collect` would and shows them as
+// Anki renders them. The page polls `/version` and reloads when the file is saved.
+use crate::inka::application::preview::{preview_cards, PreviewCard};
+use crate::inka::infrastructure::config::Config;
+use crate::inka::infrastructure::file_writer;
+use crate::util::math::mathjax_config;
+use anyhow::{Context, Result};
+use html_escape::encode_text;
+use percent_encoding::percent_decode_str;
+use regex::{Captures, Regex};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::UNIX_EPOCH;
+use tracing::{debug, info, warn};
+
+/// `{{c1::text}}` or `{{c1::text::hint}}` in converted field HTML
+static CLOZE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\{\{c(\d+)::([\s\S]*?)(?:::([\s\S]*?))?\}\}")
+        .expect("Failed to compile cloze regex")
+});
+
+/// Serve a live preview of `markdown_path` on `127.0.0.1:port` until interrupted
+pub fn run_preview_server(markdown_path: &Path, port: u16, settings: &Config) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .with_context(|| format!("Failed to listen on port {}", port))?;
+    let address = listener.local_addr()?;
+    info!(file = %markdown_path.display(), %address, "Preview server listening");
+    crate::status!(
+        "Previewing {} at http://{} (Ctrl-C to stop)",
+        markdown_path.display(),
+        address
+    );
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = handle_connection(stream, markdown_path, settings) {
+                    debug!(error = %e, "Preview request failed");
+                }
+            }
+            Err(e) => warn!(error = %e, "Failed to accept connection"),
+        }
+    }
+    Ok(())
+}
+
+/// Answer one HTTP request: `/` is the page, `/version` changes whenever the
+/// file is saved, anything else is a file next to the markdown (images)
+fn handle_connection(mut stream: TcpStream, markdown_path: &Path, settings: &Config) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Headers aren't needed, but must be read before answering
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let target = target.split('?').next().unwrap_or(target);
+    debug!(target, "Preview request");
+    match target {
+        "/" => {
+            let page = render_page(markdown_path, settings, &file_version(markdown_path));
+            respond(
+                &mut stream,
+                "200 OK",
+                "text/html; charset=utf-8",
+                page.as_bytes(),
+            )
+        }
+        "/version" => respond(
+            &mut stream,
+            "200 OK",
+            "text/plain",
+            file_version(markdown_path).as_bytes(),
+        ),
+        _ => match asset_path(markdown_path, target).and_then(|path| std::fs::read(&path).ok()) {
+            Some(bytes) => respond(&mut stream, "200 OK", content_type(target), &bytes),
+            None => respond(&mut stream, "404 Not Found", "text/plain", b"Not found"),
+        },
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Modification time of the file; the page reloads when it changes
+fn file_version(path: &Path) -> String {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|age| age.as_nanos().to_string())
+        .unwrap_or_default()
+}
+
+/// File under the markdown's directory for a request target; `None` for
+/// targets escaping that directory
+fn asset_path(markdown_path: &Path, target: &str) -> Option<PathBuf> {
+    let relative = percent_decode_str(target.trim_start_matches('/'))
+        .decode_utf8()
+        .ok()?
+        .into_owned();
+    let relative = Path::new(&relative);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+    Some(markdown_path.parent()?.join(relative))
+}
+
+fn content_type(target: &str) -> &'static str {
+    match Path::new(target)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Cloze numbers used in a field, ascending; Anki makes one card per number
+fn cloze_ordinals(html: &str) -> Vec<u32> {
+    let mut ordinals: Vec<u32> = CLOZE_REGEX
+        .captures_iter(html)
+        .filter_map(|caps| caps[1].parse().ok())
+        .collect();
+    ordinals.sort_unstable();
+    ordinals.dedup();
+    ordinals
+}
+
+/// A cloze card's side: cloze `ordinal` hidden (front) or highlighted (back),
+/// every other cloze shown as plain text
+fn cloze_side(html: &str, ordinal: u32, reveal: bool) -> String {
+    CLOZE_REGEX
+        .replace_all(html, |caps: &Captures| {
+            if caps[1].parse::<u32>() != Ok(ordinal) {
+                caps[2].to_string()
+            } else if reveal {
+                format!(r#"<span class="cloze">{}</span>"#, &caps[2])
+            } else {
+                let hint = caps.get(3).map_or("...", |hint| hint.as_str());
+                format!(r#"<span class="cloze">[{}]</span>"#, hint)
+            }
+        })
+        .into_owned()
+}
+
+fn render_page(markdown_path: &Path, settings: &Config, version: &str) -> String {
+    let body = match file_writer::read_markdown_file_with_encoding(markdown_path)
+        .with_context(|| format!("Failed to read {}", markdown_path.display()))
+        .and_then(|(content, _)| preview_cards(&content, markdown_path, settings))
+    {
+        Ok(cards) if cards.is_empty() => {
+            r#"<p class="empty">No cards found in this file.</p>"#.to_string()
+        }
+        Ok(cards) => cards
+            .iter()
+            .enumerate()
+            .map(|(index, card)| render_card(index + 1, card))
+            .collect(),
+        Err(e) => format!(
+            r#"<pre class="error">{}</pre>"#,
+            encode_text(&format!("{:#}", e))
+        ),
+    };
+
+    let title = encode_text(&markdown_path.display().to_string()).into_owned();
+    PAGE_TEMPLATE
+        .replace("{title}", &title)
+        .replace("{version}", version)
+        .replace(
+            "{mathjax}",
+            &mathjax_config(
+                &settings.math.macros,
+                &settings.math.inline_delimiters,
+                &settings.math.display_delimiters,
+            ),
+        )
+        .replace("{body}", &body)
+}
+
+fn render_card(number: usize, card: &PreviewCard) -> String {
+    let mut info = format!("#{} · {}", number, encode_text(&card.deck));
+    if let Some(note_id) = card.note_id {
+        info.push_str(&format!(" · note {}", note_id));
+    }
+    for tag in &card.tags {
+        info.push_str(&format!(
+            r#" <span class="tag">{}</span>"#,
+            encode_text(tag)
+        ));
+    }
+
+    let sides: Vec<(String, String, String)> = if card.cloze {
+        let text = card.fields.first().map(String::as_str).unwrap_or_default();
+        cloze_ordinals(text)
+            .into_iter()
+            .map(|ordinal| {
+                (
+                    format!("Cloze {}", ordinal),
+                    cloze_side(text, ordinal, false),
+                    cloze_side(text, ordinal, true),
+                )
+            })
+            .collect()
+    } else {
+        let field = |index: usize| card.fields.get(index).cloned().unwrap_or_default();
+        vec![(String::new(), field(0), field(1))]
+    };
+
+    let mut html = format!(r#"<div class="card"><div class="info">{}</div>"#, info);
+    for (label, front, back) in sides {
+        if !label.is_empty() {
+            html.push_str(&format!(r#"<div class="label">{}</div>"#, label));
+        }
+        html.push_str(&format!(
+            r#"<div class="side front tex2jax_process" dir="auto">{}</div><div class="side back tex2jax_process" dir="auto">{}</div>"#,
+            front, back
+        ));
+    }
+    html.push_str("</div>");
+    html
+}
+
+const PAGE_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <title>Preview: {title}</title>
+    <script>
+        window.MathJax = {mathjax};
+    </script>
+    <script src="https://cdnjs.cloudflare.com/ajax/libs/mathjax/3.2.2/es5/tex-mml-chtml.js"></script>
+    <link rel="stylesheet" href="https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/styles/github.min.css">
+    <script src="https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/highlight.min.js"></script>
+    <script>
+        document.addEventListener('DOMContentLoaded', () => {
+            document.querySelectorAll('pre code').forEach((block) => hljs.highlightElement(block));
+        });
+        // Reload when the markdown file is saved
+        const version = "{version}";
+        setInterval(async () => {
+            try {
+                const response = await fetch('/version', { cache: 'no-store' });
+                if ((await response.text()) !== version) {
+                    location.reload();
+                }
+            } catch (e) {
+                // Server stopped; keep the last rendering
+            }
+        }, 1000);
+    </script>
+    <style>
+        body {
+            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif;
+            line-height: 1.6;
+            max-width: 800px;
+            margin: 2rem auto;
+            padding: 0 1rem;
+            background-color: #f5f5f5;
+        }
+        .card {
+            background: white;
+            border-radius: 8px;
+            padding: 1rem 2rem;
+            margin-bottom: 1.5rem;
+            box-shadow: 0 2px 4px rgba(0,0,0,0.1);
+        }
+        .info, .label {
+            font-size: 0.85em;
+            color: #666;
+        }
+        .front {
+            padding-bottom: 0.5rem;
+            border-bottom: 2px solid #eee;
+        }
+        .cloze {
+            font-weight: bold;
+            color: blue;
+        }
+        .tag {
+            background: #e9ecef;
+            padding: 2px 8px;
+            border-radius: 4px;
+        }
+        img {
+            max-width: 100%;
+            height: auto;
+        }
+        pre {
+            white-space: pre-wrap;
+            background-color: #f8f9fa;
+            padding: 1rem;
+            border-radius: 4px;
+        }
+        .error {
+            color: #b00020;
+        }
+    </style>
+</head>
+<body>
+    <h1>{title}</h1>
+    {body}
+</body>
+</html>"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn given_cloze_with_hint_when_rendering_sides_then_hides_only_that_ordinal() {
+        let html = "<p>{{c1::Rust}} is {{c2::memory safe::property}}</p>";
+
+        assert_eq!(cloze_ordinals(html), vec![1, 2]);
+        assert_eq!(
+            cloze_side(html, 2, false),
+            r#"<p>Rust is <span class="cloze">[property]</span></p>"#
+        );
+        assert_eq!(
+            cloze_side(html, 1, true),
+            r#"<p><span class="cloze">Rust</span> is memory safe</p>"#
+        );
+    }
+
+    #[test]
+    fn given_request_outside_markdown_dir_when_resolving_asset_then_refuses() {
+        let markdown = Path::new("/notes/rust.md");
+
+        assert_eq!(
+            asset_path(markdown, "/images/my%20diagram.png"),
+            Some(PathBuf::from("/notes/images/my diagram.png"))
+        );
+        assert_eq!(asset_path(markdown, "/../secret.txt"), None);
+    }
+
+    #[test]
+    fn given_page_request_when_serving_then_renders_cards() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let markdown = dir.path().join("notes.md");
+        std::fs::write(&markdown, "---\n1. What is Rust?\n> A language\n---\n").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            handle_connection(stream, &markdown, &Config::default()).unwrap();
+        });
+
+        let mut client = TcpStream::connect(address).unwrap();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        server.join().unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("What is Rust?"));
+        assert!(response.contains("A language"));
+    }
+}
@@ -0,0 +1,233 @@
+// src/util/line_template.rs
+use crate::domain::NoteRecord;
+use crate::util::text::html_to_text;
+
+/// Output line for one note, e.g. `{id}\t{deck}\t{front:60}`.
+///
+/// - `{name}` is replaced by a value: `id`, `guid`, `notetype`, `deck`,
+///   `tags`, `front` (first field), `back` (second field), `created` and
+///   `modified` (Unix seconds), or any other name for the field of that name
+///   (empty if the note has none). Fields are plain text on one line.
+/// - `{name:N}` truncates the value to N characters, ending in `…`.
+/// - `{deck|SEP}` and `{tags|SEP}` join with SEP instead of `, ` and ` `.
+/// - `{{` and `}}` are literal braces; `\t`, `\n` and `\\` are escapes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineTemplate {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Value {
+        name: String,
+        separator: Option<String>,
+        width: Option<usize>,
+    },
+}
+
+impl LineTemplate {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some('\\') => literal.push('\\'),
+                    Some(other) => {
+                        literal.push('\\');
+                        literal.push(other);
+                    }
+                    None => literal.push('\\'),
+                },
+                '}' => {
+                    if chars.next() != Some('}') {
+                        return Err("Unmatched '}' (write '}}' for a literal brace)".to_string());
+                    }
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    if let Some(after) = rest.strip_prefix('{') {
+                        literal.push('{');
+                        chars = after.chars();
+                        continue;
+                    }
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| "Unclosed '{' in template".to_string())?;
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(parse_placeholder(&rest[..end])?);
+                    chars = rest[end + 1..].chars();
+                }
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Self { segments })
+    }
+
+    pub fn render(&self, record: &NoteRecord) -> String {
+        let mut line = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => line.push_str(text),
+                Segment::Value {
+                    name,
+                    separator,
+                    width,
+                } => {
+                    let value = value(record, name, separator.as_deref());
+                    match width {
+                        Some(width) => line.push_str(&truncate(&value, *width)),
+                        None => line.push_str(&value),
+                    }
+                }
+            }
+        }
+        line
+    }
+}
+
+impl std::str::FromStr for LineTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// `name[|SEP][:N]`
+fn parse_placeholder(spec: &str) -> Result<Segment, String> {
+    let (rest, width) = match spec.rsplit_once(':') {
+        Some((rest, digits))
+            if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            let width = digits
+                .parse()
+                .map_err(|_| format!("Width too large in {{{}}}", spec))?;
+            (rest, Some(width))
+        }
+        _ => (spec, None),
+    };
+    let (name, separator) = match rest.split_once('|') {
+        Some((name, separator)) => (name, Some(separator.to_string())),
+        None => (rest, None),
+    };
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("Empty placeholder {{{}}}", spec));
+    }
+    if separator.is_some() && name != "deck" && name != "tags" {
+        return Err(format!(
+            "Only {{deck|SEP}} and {{tags|SEP}} take a separator, not {{{}}}",
+            spec
+        ));
+    }
+    Ok(Segment::Value {
+        name: name.to_string(),
+        separator,
+        width,
+    })
+}
+
+fn value(record: &NoteRecord, name: &str, separator: Option<&str>) -> String {
+    match name {
+        "id" => record.id.to_string(),
+        "guid" => record.guid.clone(),
+        "notetype" => record.notetype.clone(),
+        "deck" => record.decks.join(separator.unwrap_or(", ")),
+        "tags" => record.tags.join(separator.unwrap_or(" ")),
+        "front" => field_text(record.fields.first().map(|(_, value)| value.as_str())),
+        "back" => field_text(record.fields.get(1).map(|(_, value)| value.as_str())),
+        "created" => record.created.to_string(),
+        "modified" => record.modified.to_string(),
+        _ => field_text(
+            record
+                .fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value.as_str()),
+        ),
+    }
+}
+
+/// Field HTML as plain text on one line
+fn field_text(html: Option<&str>) -> String {
+    html.map(|html| {
+        html_to_text(html)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    })
+    .unwrap_or_default()
+}
+
+fn truncate(value: &str, width: usize) -> String {
+    if value.chars().count() <= width {
+        return value.to_string();
+    }
+    let mut truncated: String = value.chars().take(width.saturating_sub(1)).collect();
+    if width > 0 {
+        truncated.push('…');
+    }
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> NoteRecord {
+        NoteRecord {
+            id: 1234567890,
+            guid: "abc".to_string(),
+            notetype: "Basic".to_string(),
+            fields: vec![
+                (
+                    "Front".to_string(),
+                    "<p>What is <b>ownership</b>?</p><p>Explain &amp; give an example</p>"
+                        .to_string(),
+                ),
+                ("Back".to_string(), "One owner".to_string()),
+            ],
+            tags: vec!["rust".to_string(), "memory".to_string()],
+            decks: vec!["Rust".to_string(), "Rust::Basics".to_string()],
+            created: 1700000000,
+            modified: 1700000100,
+        }
+    }
+
+    #[test]
+    fn given_template_when_rendering_then_fills_truncates_and_joins() {
+        let template =
+            LineTemplate::parse(r"{id}\t{deck|;}\t{front:20}\t{tags|,}\t{Back}\t{Extra}|{{x}}")
+                .unwrap();
+
+        let line = template.render(&record());
+
+        assert_eq!(
+            line,
+            "1234567890\tRust;Rust::Basics\tWhat is ownership? …\trust,memory\tOne owner\t|{x}"
+        );
+    }
+
+    #[test]
+    fn given_invalid_template_when_parsing_then_explains() {
+        assert!(LineTemplate::parse("{id").unwrap_err().contains("Unclosed"));
+        assert!(LineTemplate::parse("{id}}")
+            .unwrap_err()
+            .contains("Unmatched"));
+        assert!(LineTemplate::parse("{front|,}")
+            .unwrap_err()
+            .contains("separator"));
+        assert!(LineTemplate::parse("{}").unwrap_err().contains("Empty"));
+    }
+}
@@ -1,4 +1,5 @@
 // src/util/lock.rs
+use crate::domain::DomainError;
 use anyhow::{bail, Context, Result};
 use rusqlite::{Connection, OpenFlags};
 use std::fs::File;
@@ -40,7 +41,9 @@ pub fn check_collection_not_locked(path: &Path) -> Result<()> {
 
     match conn.execute_batch("BEGIN EXCLUSIVE; ROLLBACK;") {
         Ok(()) => Ok(()),
-        Err(e) if is_rusqlite_busy(&e) => bail!(locked_message(path)),
+        Err(e) if is_rusqlite_busy(&e) => {
+            Err(DomainError::CollectionLocked(locked_message(path)).into())
+        }
         Err(e) => Err(e).with_context(|| "Unexpected error probing collection lock"),
     }
 }
@@ -0,0 +1,232 @@
+// src/util/math.rs
+use anyhow::Result;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+/// Around the macro definitions `notetype push --math-macros` puts at the top
+/// of card templates, so a later push replaces them
+const MACROS_START: &str = "<!-- ankiview math macros -->";
+const MACROS_END: &str = "<!-- /ankiview math macros -->";
+
+static CODE_BLOCK_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<pre><code[^>]*>(.*?)</code></pre>").unwrap());
+// Nothing but display (`$$...$$`, `\[...\]`) or inline (`$...$`, `\(...\)`) math
+static MATH_ONLY_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?s)^\s*(?:\$\$([^$]*)\$\$|\\\[(.*)\\\]|\$([^$]*)\$|\\\((.*)\\\))\s*$").unwrap()
+});
+
+/// Replace code blocks that hold only math, like a ```` ```latex ```` fence
+/// around `$$...$$`, with the math itself, so it is typeset instead of shown
+/// as code.
+///
+/// `view` applies this to the fields it shows, with the [`code_block_math`]
+/// rule `collect` uses on the markdown, so a note renders its math the same
+/// in Anki and in the browser, including notes collected before.
+pub fn unwrap_math_code_blocks(html: &str) -> String {
+    CODE_BLOCK_REGEX
+        .replace_all(html, |cap: &regex::Captures| {
+            match code_block_math(&cap[1]) {
+                Some(_) => cap[1].trim().to_string(),
+                None => cap[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// The TeX of a code block that holds nothing but math, and whether it is
+/// display math
+pub fn code_block_math(code: &str) -> Option<(&str, bool)> {
+    let cap = MATH_ONLY_REGEX.captures(code)?;
+    let (index, tex) = (1..=4).find_map(|index| Some((index, cap.get(index)?.as_str())))?;
+    Some((tex, index <= 2))
+}
+
+/// `window.MathJax` configuration (a JSON object) for pages showing notes:
+/// TeX `macros` and the math delimiters. Without delimiters, both the ones
+/// `collect` writes (`\(...\)`, `\[...\]`) and `$...$`/`$$...$$` are typeset.
+pub fn mathjax_config(
+    macros: &BTreeMap<String, String>,
+    inline: &[[String; 2]],
+    display: &[[String; 2]],
+) -> String {
+    let delimiters = |configured: &[[String; 2]], default: [[&str; 2]; 2]| {
+        if configured.is_empty() {
+            json!(default)
+        } else {
+            json!(configured)
+        }
+    };
+    let macros: serde_json::Map<String, Value> = macros
+        .iter()
+        .map(|(name, body)| {
+            let definition = match arity(body) {
+                0 => json!(body),
+                arguments => json!([body, arguments]),
+            };
+            (name.clone(), definition)
+        })
+        .collect();
+    let config = json!({
+        "tex": {
+            "inlineMath": delimiters(inline, [["\\(", "\\)"], ["$", "$"]]),
+            "displayMath": delimiters(display, [["\\[", "\\]"], ["$$", "$$"]]),
+            "processEscapes": true,
+            "macros": macros,
+        },
+        "options": {
+            "processHtmlClass": "tex2jax_process",
+        },
+    });
+    // Goes into a <script> element, which a macro body must not end
+    config.to_string().replace("</", "<\\/")
+}
+
+/// `\def` definitions of `macros` for card templates, e.g. `\def\R{\mathbb{R}}`
+/// or `\def\norm#1{\lVert #1 \rVert}`
+pub fn macro_definitions(macros: &BTreeMap<String, String>) -> Result<String> {
+    let mut definitions = String::new();
+    for (name, body) in macros {
+        let valid = name.chars().all(|c| c.is_ascii_alphabetic())
+            || (name.chars().count() == 1 && !name.chars().all(char::is_whitespace));
+        if !valid {
+            return Err(anyhow::anyhow!(
+                "Invalid TeX macro name '{}': use letters only, or a single character",
+                name
+            ));
+        }
+        definitions.push_str(&format!("\\def\\{}", name));
+        for argument in 1..=arity(body) {
+            definitions.push_str(&format!("#{}", argument));
+        }
+        definitions.push_str(&format!("{{{}}}", body));
+    }
+    Ok(definitions)
+}
+
+/// `template` with a hidden block at the top running `definitions` through
+/// MathJax, replacing the block of an earlier push; empty `definitions` only
+/// remove it
+pub fn with_template_macros(template: &str, definitions: &str) -> String {
+    let template = match (template.find(MACROS_START), template.find(MACROS_END)) {
+        (Some(start), Some(end)) if start < end => format!(
+            "{}{}",
+            &template[..start],
+            template[end + MACROS_END.len()..].trim_start_matches('\n')
+        ),
+        _ => template.to_string(),
+    };
+    if definitions.is_empty() {
+        return template;
+    }
+    format!(
+        "{}<span style=\"display:none\">\\({}\\)</span>{}\n{}",
+        MACROS_START, definitions, MACROS_END, template
+    )
+}
+
+/// Number of arguments a macro body uses (`#1` to `#9`)
+fn arity(body: &str) -> usize {
+    body.split('#')
+        .skip(1)
+        .filter_map(|rest| rest.chars().next()?.to_digit(10))
+        .max()
+        .unwrap_or(0) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_math_only_code_blocks_when_unwrapping_then_keeps_the_math() {
+        let cases = [
+            (
+                "<pre><code class=\"language-latex\">$$\nx^2\n$$\n</code></pre>",
+                "$$\nx^2\n$$",
+            ),
+            ("<pre><code>$a &lt; b$</code></pre>", "$a &lt; b$"),
+            (
+                "<p>Before</p><pre><code class=\"language-tex\">\\[x\\]</code></pre>",
+                "<p>Before</p>\\[x\\]",
+            ),
+        ];
+        for (html, expected) in cases {
+            assert_eq!(unwrap_math_code_blocks(html), expected, "html: {html}");
+        }
+    }
+
+    #[test]
+    fn given_code_with_more_than_math_when_unwrapping_then_unchanged() {
+        let html = "<pre><code class=\"language-sh\">echo $HOME and $PATH\n</code></pre>";
+
+        assert_eq!(unwrap_math_code_blocks(html), html);
+    }
+
+    #[test]
+    fn given_code_when_checking_for_math_then_tells_display_from_inline() {
+        assert_eq!(code_block_math("$$\nx^2\n$$\n"), Some(("\nx^2\n", true)));
+        assert_eq!(code_block_math(r"\[x\]"), Some(("x", true)));
+        assert_eq!(code_block_math(" $x$ "), Some(("x", false)));
+        assert_eq!(code_block_math(r"\(x\)"), Some(("x", false)));
+        assert_eq!(code_block_math("$a$ and $b$"), None);
+        assert_eq!(code_block_math("echo $HOME"), None);
+    }
+
+    fn macros() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("R".to_string(), r"\mathbb{R}".to_string()),
+            ("norm".to_string(), r"\lVert #1 \rVert_{#2}".to_string()),
+        ])
+    }
+
+    #[test]
+    fn given_macros_and_delimiters_when_configuring_mathjax_then_includes_them() {
+        let inline = [["$".to_string(), "$".to_string()]];
+
+        let config: Value = serde_json::from_str(&mathjax_config(&macros(), &inline, &[])).unwrap();
+
+        assert_eq!(config["tex"]["macros"]["R"], r"\mathbb{R}");
+        assert_eq!(
+            config["tex"]["macros"]["norm"],
+            json!([r"\lVert #1 \rVert_{#2}", 2])
+        );
+        assert_eq!(config["tex"]["inlineMath"], json!([["$", "$"]]));
+        assert_eq!(
+            config["tex"]["displayMath"],
+            json!([[r"\[", r"\]"], ["$$", "$$"]])
+        );
+    }
+
+    #[test]
+    fn given_macros_when_pushing_to_template_then_replaces_earlier_block() {
+        let definitions = macro_definitions(&macros()).unwrap();
+        assert_eq!(
+            definitions,
+            r"\def\R{\mathbb{R}}\def\norm#1#2{\lVert #1 \rVert_{#2}}"
+        );
+
+        let template = with_template_macros("{{Front}}", r"\def\E{\mathbb{E}}");
+        let template = with_template_macros(&template, &definitions);
+
+        assert_eq!(
+            template,
+            format!(
+                "{}<span style=\"display:none\">\\({}\\)</span>{}\n{{{{Front}}}}",
+                MACROS_START, definitions, MACROS_END
+            )
+        );
+        assert_eq!(with_template_macros(&template, ""), "{{Front}}");
+    }
+
+    #[test]
+    fn given_invalid_macro_name_when_defining_then_fails() {
+        let macros = BTreeMap::from([("R2".to_string(), r"\mathbb{R}^2".to_string())]);
+
+        assert!(macro_definitions(&macros)
+            .unwrap_err()
+            .to_string()
+            .contains("R2"));
+    }
+}
@@ -1,3 +1,7 @@
+pub mod line_template;
 pub mod lock;
+pub mod math;
+pub mod output;
 pub mod testing;
 pub mod text;
+pub mod xdg;
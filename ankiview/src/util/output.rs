@@ -0,0 +1,29 @@
+// src/util/output.rs
+//
+// Process-wide console output settings driven by global CLI flags.
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable quiet mode (`-q/--quiet`).
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Whether informational stdout output should be suppressed.
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Print an informational status line to stdout unless `--quiet` is active.
+///
+/// Use plain `println!` for data the user explicitly asked for (list rows,
+/// JSON output); use `status!` for confirmations and summaries.
+#[macro_export]
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !$crate::util::output::is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
@@ -1,7 +1,7 @@
 // src/util/testing.rs
 
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use tracing::{debug, info};
 use tracing_subscriber::{
@@ -12,7 +12,10 @@ use tracing_subscriber::{
 };
 
 use crate::application::NoteRepository;
-use crate::domain::{DomainError, Note};
+use crate::domain::{
+    CardTemplateDefinition, CountGroup, DomainError, Flag, Note, NotetypeDefinition, NotetypeInfo,
+    ProblemNote, TemplateUpdate, TransferNote,
+};
 
 // Common test environment variables
 pub const TEST_ENV_VARS: &[&str] = &["RUST_LOG", "NO_CLEANUP"];
@@ -49,6 +52,9 @@ pub struct MockNoteRepository {
     delete_behaviors: HashMap<i64, DeleteBehavior>,
     search_results: HashMap<Option<String>, Vec<Note>>,
     notetypes: Vec<(i64, String)>,
+    decks: Vec<(i64, String, usize)>,
+    problem_notes: Vec<ProblemNote>,
+    due_cards: HashMap<i64, usize>,
 }
 
 impl MockNoteRepository {
@@ -92,10 +98,83 @@ impl NoteRepository for MockNoteRepository {
         }
     }
 
+    fn list_due_notes(
+        &mut self,
+        search_query: Option<&str>,
+    ) -> Result<Vec<(Note, usize)>, DomainError> {
+        Ok(self
+            .list_notes(search_query)?
+            .into_iter()
+            .filter_map(|n| self.due_cards.get(&n.id).map(|&due| (n, due)))
+            .collect())
+    }
+
+    fn search_notes(&mut self, _query: Option<&str>) -> Result<Vec<Note>, DomainError> {
+        let mut notes: Vec<_> = self.notes.values().cloned().collect();
+        notes.sort_by_key(|n| n.id);
+        Ok(notes)
+    }
+
+    fn note_ids(&mut self, _query: Option<&str>) -> Result<Vec<i64>, DomainError> {
+        let mut ids: Vec<_> = self.notes.keys().copied().collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn count_notes(&mut self, _query: Option<&str>) -> Result<usize, DomainError> {
+        Ok(self.notes.len())
+    }
+
+    fn count_notes_by(
+        &mut self,
+        _query: Option<&str>,
+        group: CountGroup,
+    ) -> Result<Vec<(String, usize)>, DomainError> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for note in self.notes.values() {
+            let names = match group {
+                // Decks aren't modelled; every note is in the default deck
+                CountGroup::Deck => vec!["Default".to_string()],
+                CountGroup::Tag if note.tags.is_empty() => vec![CountGroup::UNTAGGED.to_string()],
+                CountGroup::Tag => note.tags.clone(),
+                CountGroup::Notetype => vec![note.model_name.clone()],
+            };
+            for name in names {
+                *counts.entry(name).or_default() += 1;
+            }
+        }
+        Ok(counts.into_iter().collect())
+    }
+
     fn list_notetypes(&mut self) -> Result<Vec<(i64, String)>, DomainError> {
         Ok(self.notetypes.clone())
     }
 
+    fn notetype_info(&mut self, name: &str) -> Result<NotetypeInfo, DomainError> {
+        let (id, name) = self
+            .notetypes
+            .iter()
+            .find(|(_, n)| n == name)
+            .cloned()
+            .ok_or_else(|| DomainError::NotetypeNotFound(name.to_string()))?;
+        Ok(NotetypeInfo {
+            id,
+            name,
+            cloze: false,
+            fields: vec!["Front".to_string(), "Back".to_string()],
+            templates: vec!["Card 1".to_string()],
+        })
+    }
+
+    fn update_notetype_templates(
+        &mut self,
+        name: &str,
+        _update: &TemplateUpdate,
+    ) -> Result<(), DomainError> {
+        // Templates aren't modelled; only the notetype has to exist
+        self.notetype_info(name).map(|_| ())
+    }
+
     fn add_tags(&mut self, id: i64, tags: &[String]) -> Result<(), DomainError> {
         let note = self.notes.get_mut(&id).ok_or(DomainError::NoteNotFound(id))?;
         for tag in tags {
@@ -164,6 +243,163 @@ impl NoteRepository for MockNoteRepository {
         }
         Ok(affected)
     }
+
+    fn note_fields(
+        &mut self,
+        _query: Option<&str>,
+    ) -> Result<Vec<(i64, Vec<String>)>, DomainError> {
+        let mut notes: Vec<_> = self
+            .notes
+            .values()
+            .map(|n| (n.id, vec![n.front.clone(), n.back.clone()]))
+            .collect();
+        notes.sort();
+        Ok(notes)
+    }
+
+    fn set_note_fields(&mut self, id: i64, fields: &[String]) -> Result<(), DomainError> {
+        let note = self.notes.get_mut(&id).ok_or(DomainError::NoteNotFound(id))?;
+        if let Some(front) = fields.first() {
+            note.front = front.clone();
+        }
+        if let Some(back) = fields.get(1) {
+            note.back = back.clone();
+        }
+        Ok(())
+    }
+
+    fn move_notes(&mut self, _query: Option<&str>, _deck: &str) -> Result<usize, DomainError> {
+        // Decks aren't modelled; every note counts as one moved card
+        Ok(self.notes.len())
+    }
+
+    fn set_fields_by_name(
+        &mut self,
+        _query: Option<&str>,
+        fields: &BTreeMap<String, String>,
+    ) -> Result<usize, DomainError> {
+        let mut affected = 0;
+        for note in self.notes.values_mut() {
+            let mut changed = false;
+            for (name, value) in fields {
+                let field = match name.as_str() {
+                    "Front" => &mut note.front,
+                    "Back" => &mut note.back,
+                    _ => continue,
+                };
+                if field != value {
+                    *field = value.clone();
+                    changed = true;
+                }
+            }
+            if changed {
+                affected += 1;
+            }
+        }
+        Ok(affected)
+    }
+
+    fn set_note_flag(&mut self, id: i64, _flag: Flag) -> Result<usize, DomainError> {
+        // Cards aren't modelled; every note has one
+        self.get_note(id).map(|_| 1)
+    }
+
+    fn set_card_flag(&mut self, _card_id: i64, _flag: Flag) -> Result<(), DomainError> {
+        Ok(())
+    }
+
+    fn list_decks(&mut self) -> Result<Vec<(i64, String, usize)>, DomainError> {
+        Ok(self.decks.clone())
+    }
+
+    fn delete_decks(&mut self, ids: &[i64]) -> Result<(), DomainError> {
+        self.decks.retain(|(id, _, _)| !ids.contains(id));
+        Ok(())
+    }
+
+    fn rename_deck(&mut self, old: &str, new: &str) -> Result<(), DomainError> {
+        if !self.decks.iter().any(|(_, name, _)| name == old) {
+            return Err(DomainError::DeckNotFound(old.to_string()));
+        }
+        let prefix = format!("{}::", old);
+        for (_, name, _) in &mut self.decks {
+            if name == old {
+                *name = new.to_string();
+            } else if let Some(child) = name.strip_prefix(&prefix) {
+                *name = format!("{}::{}", new, child);
+            }
+        }
+        Ok(())
+    }
+
+    fn problem_notes(
+        &mut self,
+        min_lapses: u32,
+        _deck: Option<&str>,
+    ) -> Result<Vec<ProblemNote>, DomainError> {
+        Ok(self
+            .problem_notes
+            .iter()
+            .filter(|n| n.leech || n.lapses >= min_lapses)
+            .cloned()
+            .collect())
+    }
+
+    fn clone_note(&mut self, id: i64, _deck: Option<&str>) -> Result<i64, DomainError> {
+        let mut note = self.get_note(id)?;
+        let new_id = self.notes.keys().max().copied().unwrap_or(id) + 1;
+        note.id = new_id;
+        self.notes.insert(new_id, note);
+        Ok(new_id)
+    }
+
+    fn transfer_notes(&mut self, query: Option<&str>) -> Result<Vec<TransferNote>, DomainError> {
+        let notes = match self.search_results.get(&query.map(String::from)) {
+            Some(notes) => notes.clone(),
+            None => {
+                let mut notes: Vec<Note> = self.notes.values().cloned().collect();
+                notes.sort_by_key(|note| note.id);
+                notes
+            }
+        };
+        Ok(notes
+            .into_iter()
+            .map(|note| TransferNote {
+                id: note.id,
+                notetype: NotetypeDefinition {
+                    name: note.model_name.clone(),
+                    cloze: false,
+                    fields: vec!["Front".to_string(), "Back".to_string()],
+                    templates: vec![CardTemplateDefinition {
+                        name: "Card 1".to_string(),
+                        front: "{{Front}}".to_string(),
+                        back: "{{Back}}".to_string(),
+                    }],
+                    css: String::new(),
+                },
+                media: crate::util::text::referenced_media(&format!("{}{}", note.front, note.back)),
+                fields: vec![note.front, note.back],
+                tags: note.tags,
+                deck: "Default".to_string(),
+            })
+            .collect())
+    }
+
+    fn add_transferred_note(&mut self, note: &TransferNote) -> Result<i64, DomainError> {
+        let new_id = self.notes.keys().max().copied().unwrap_or(0).max(note.id) + 1;
+        let field = |index: usize| note.fields.get(index).cloned().unwrap_or_default();
+        self.notes.insert(
+            new_id,
+            Note {
+                id: new_id,
+                front: field(0),
+                back: field(1),
+                tags: note.tags.clone(),
+                model_name: note.notetype.name.clone(),
+            },
+        );
+        Ok(new_id)
+    }
 }
 
 /// Builder for MockNoteRepository
@@ -174,6 +410,9 @@ pub struct MockNoteRepositoryBuilder {
     delete_behaviors: HashMap<i64, DeleteBehavior>,
     search_results: HashMap<Option<String>, Vec<Note>>,
     notetypes: Vec<(i64, String)>,
+    decks: Vec<(i64, String, usize)>,
+    problem_notes: Vec<ProblemNote>,
+    due_cards: HashMap<i64, usize>,
 }
 
 impl MockNoteRepositoryBuilder {
@@ -183,6 +422,9 @@ impl MockNoteRepositoryBuilder {
             delete_behaviors: HashMap::new(),
             search_results: HashMap::new(),
             notetypes: vec![],
+            decks: vec![],
+            problem_notes: vec![],
+            due_cards: HashMap::new(),
         }
     }
 
@@ -222,12 +464,33 @@ impl MockNoteRepositoryBuilder {
         self
     }
 
+    /// Add a deck that can be listed, with its number of cards
+    pub fn with_deck(mut self, id: i64, name: &str, cards: usize) -> Self {
+        self.decks.push((id, name.to_string(), cards));
+        self
+    }
+
+    /// Add a candidate for problem_notes (filtered by its min_lapses argument)
+    pub fn with_problem_note(mut self, note: ProblemNote) -> Self {
+        self.problem_notes.push(note);
+        self
+    }
+
+    /// Give a note cards due today (returned by list_due_notes)
+    pub fn with_due_cards(mut self, note_id: i64, due: usize) -> Self {
+        self.due_cards.insert(note_id, due);
+        self
+    }
+
     pub fn build(self) -> MockNoteRepository {
         MockNoteRepository {
             notes: self.notes,
             delete_behaviors: self.delete_behaviors,
             search_results: self.search_results,
             notetypes: self.notetypes,
+            decks: self.decks,
+            problem_notes: self.problem_notes,
+            due_cards: self.due_cards,
         }
     }
 }
@@ -1,6 +1,196 @@
 // src/util/text.rs
 use html_escape::decode_html_entities;
-use regex::Regex;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+static BLOCK_TAG_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)</?(p|div|br|li|ul|ol|tr|pre|blockquote|h[1-6])\b[^>]*>").unwrap()
+});
+static TAG_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]+>").unwrap());
+static BLANK_LINES_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\n{3,}").unwrap());
+static PRE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<pre\b[^>]*>(.*?)</pre>").unwrap());
+static CODE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<code\b[^>]*>(.*?)</code>").unwrap());
+static BOLD_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<(?:b|strong)\b[^>]*>(.*?)</(?:b|strong)>").unwrap());
+static ITALIC_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<(?:i|em)\b[^>]*>(.*?)</(?:i|em)>").unwrap());
+static LINK_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?is)<a\b[^>]*\bhref="([^"]*)"[^>]*>(.*?)</a>"#).unwrap());
+static IMAGE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?is)<img\b[^>]*\bsrc="([^"]*)"[^>]*>"#).unwrap());
+static HEADING_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<h([1-6])\b[^>]*>(.*?)</h[1-6]>").unwrap());
+static SOUND_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[sound:([^\]]+)\]").unwrap());
+// The `File: <path>` footer collect appends, with or without a source link
+static FILE_FOOTER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?:<p>)?<span style="font-size: 9pt;">File: (?:<a href="[^"]*">)?([^<]*)(?:</a>)?</span>(?:</p>)?"#,
+    )
+    .unwrap()
+});
+// Closing tag of the previous item included, so items aren't blank-line separated
+static LIST_ITEM_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(?:</li>\s*)?<li\b[^>]*>").unwrap());
+
+/// How HTML field content is written for tools that don't render HTML
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextFormat {
+    /// Field HTML unchanged
+    #[default]
+    Html,
+    /// Tags removed and entities decoded
+    Text,
+    /// Emphasis, code, links, images, headings and lists as markdown
+    Markdown,
+}
+
+impl TextFormat {
+    pub fn render(self, html: &str) -> String {
+        match self {
+            Self::Html => html.to_string(),
+            Self::Text => html_to_text(html),
+            Self::Markdown => html_to_markdown(html),
+        }
+    }
+}
+
+impl std::str::FromStr for TextFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "html" => Ok(Self::Html),
+            "text" => Ok(Self::Text),
+            "markdown" | "md" => Ok(Self::Markdown),
+            _ => Err(format!("'{}' is not html, text or markdown", s)),
+        }
+    }
+}
+
+/// Plain text of HTML content: block tags become line breaks, other tags
+/// are removed and entities decoded.
+///
+/// # Examples
+///
+/// ```
+/// use ankiview::util::text::html_to_text;
+///
+/// assert_eq!(html_to_text("Trees &amp; <b>Graphs</b><br>BFS"), "Trees & Graphs\nBFS");
+/// ```
+pub fn html_to_text(html: &str) -> String {
+    let with_newlines = BLOCK_TAG_REGEX.replace_all(html, "\n");
+    let no_tags = TAG_REGEX.replace_all(&with_newlines, "");
+    tidy_lines(&decode_html_entities(&no_tags))
+}
+
+/// Markdown for HTML content, covering what notes usually contain: emphasis,
+/// inline and block code, links, images, headings and list items. Other tags
+/// are removed like in [`html_to_text`].
+pub fn html_to_markdown(html: &str) -> String {
+    // Code blocks first, so their content keeps its line breaks and loses
+    // highlighting markup
+    let markdown = PRE_REGEX.replace_all(html, |caps: &Captures| {
+        let code = TAG_REGEX.replace_all(&caps[1], "");
+        format!("\n```\n{}\n```\n", code.trim_matches('\n'))
+    });
+    let markdown = CODE_REGEX.replace_all(&markdown, "`$1`");
+    let markdown = BOLD_REGEX.replace_all(&markdown, "**$1**");
+    let markdown = ITALIC_REGEX.replace_all(&markdown, "*$1*");
+    let markdown = LINK_REGEX.replace_all(&markdown, "[$2]($1)");
+    let markdown = IMAGE_REGEX.replace_all(&markdown, "![]($1)");
+    let markdown = HEADING_REGEX.replace_all(&markdown, |caps: &Captures| {
+        let level: usize = caps[1].parse().unwrap_or(1);
+        format!("\n{} {}\n", "#".repeat(level), caps[2].trim())
+    });
+    let markdown = LIST_ITEM_REGEX.replace_all(&markdown, "\n- ");
+    let markdown = BLOCK_TAG_REGEX.replace_all(&markdown, "\n");
+    let markdown = TAG_REGEX.replace_all(&markdown, "");
+    tidy_lines(&decode_html_entities(&markdown))
+}
+
+/// Names of the collection.media files a field references: `<img src>`
+/// attributes that aren't URLs, and `[sound:...]` tags. In order of first
+/// reference, without duplicates.
+///
+/// # Examples
+///
+/// ```
+/// use ankiview::util::text::referenced_media;
+///
+/// let html = r#"<img src="tree.png"> [sound:tree.mp3] <img src="https://x.org/a.png">"#;
+/// assert_eq!(referenced_media(html), vec!["tree.png", "tree.mp3"]);
+/// ```
+pub fn referenced_media(html: &str) -> Vec<String> {
+    let images = IMAGE_REGEX
+        .captures_iter(html)
+        .map(|caps| caps[1].to_string());
+    let sounds = SOUND_REGEX
+        .captures_iter(html)
+        .map(|caps| caps[1].to_string());
+    let mut names: Vec<String> = Vec::new();
+    for name in images.chain(sounds) {
+        let name = decode_html_entities(&name).into_owned();
+        if name.contains("://") || name.starts_with("data:") || names.contains(&name) {
+            continue;
+        }
+        names.push(name);
+    }
+    names
+}
+
+/// Source path in the `File:` footer `collect` adds to a note's last field
+///
+/// # Examples
+///
+/// ```
+/// use ankiview::util::text::file_footer_path;
+///
+/// let html = r#"<p>A</p><p><span style="font-size: 9pt;">File: notes/a&amp;b.md</span></p>"#;
+/// assert_eq!(file_footer_path(html), Some("notes/a&b.md".to_string()));
+/// assert_eq!(file_footer_path("<p>No footer</p>"), None);
+/// ```
+pub fn file_footer_path(html: &str) -> Option<String> {
+    let cap = FILE_FOOTER_REGEX.captures(html)?;
+    Some(decode_html_entities(&cap[1]).into_owned())
+}
+
+/// `html` without the `File:` footer `collect` adds
+pub fn strip_file_footer(html: &str) -> Cow<'_, str> {
+    FILE_FOOTER_REGEX.replace_all(html, "")
+}
+
+/// Whether `name` is a bare file name that stays inside the directory it is
+/// joined to: one normal path component, no separators, no `..`, not absolute.
+///
+/// # Examples
+///
+/// ```
+/// use ankiview::util::text::is_plain_file_name;
+///
+/// assert!(is_plain_file_name("tree.png"));
+/// assert!(!is_plain_file_name("../collection.anki2"));
+/// assert!(!is_plain_file_name("/etc/passwd"));
+/// assert!(!is_plain_file_name("sub\\tree.png"));
+/// ```
+pub fn is_plain_file_name(name: &str) -> bool {
+    let mut components = std::path::Path::new(name).components();
+    !name.contains(['/', '\\'])
+        && matches!(components.next(), Some(std::path::Component::Normal(_)))
+        && components.next().is_none()
+}
+
+/// Trailing whitespace removed, at most one blank line in a row, no leading
+/// or trailing blank lines
+fn tidy_lines(text: &str) -> String {
+    let trimmed: Vec<&str> = text.lines().map(str::trim_end).collect();
+    BLANK_LINES_REGEX
+        .replace_all(&trimmed.join("\n"), "\n\n")
+        .trim_matches('\n')
+        .to_string()
+}
 
 /// Extract the first line of plain text from HTML content.
 ///
@@ -91,4 +281,35 @@ mod tests {
         let html = "<p>\nWhat is a Tree?\n</p><p>Second</p>";
         assert_eq!(extract_first_line(html), "What is a Tree?");
     }
+
+    #[test]
+    fn given_html_with_breaks_when_converting_to_text_then_keeps_lines() {
+        let html = "Line one<br>Line <i>two</i><div>&lt;tag&gt;</div>";
+        assert_eq!(html_to_text(html), "Line one\nLine two\n<tag>");
+    }
+
+    #[test]
+    fn given_formatted_html_when_converting_to_markdown_then_uses_markdown_syntax() {
+        let html =
+            "<h2>Ownership</h2><p>Use <b>moves</b>, <em>borrows</em> and <code>&amp;T</code>.</p>\
+                    <ul><li>See <a href=\"https://doc.rust-lang.org\">docs</a></li>\
+                    <li><img src=\"diagram.png\"></li></ul>";
+        assert_eq!(
+            html_to_markdown(html),
+            "## Ownership\n\nUse **moves**, *borrows* and `&T`.\n\n- See [docs](https://doc.rust-lang.org)\n- ![](diagram.png)"
+        );
+    }
+
+    #[test]
+    fn given_highlighted_code_block_when_converting_to_markdown_then_fences_plain_code() {
+        let html = "<pre><code><span class=\"kw\">fn</span> main() {}\n</code></pre>";
+        assert_eq!(html_to_markdown(html), "```\nfn main() {}\n```");
+    }
+
+    #[test]
+    fn given_images_sounds_and_urls_when_listing_media_then_returns_local_files_once() {
+        let html = "<img src=\"a&amp;b.png\"><img src=\"data:image/png;base64,xx\">\
+                    [sound:say.mp3]<img src=\"http://x.org/c.png\"><img src=\"a&amp;b.png\">";
+        assert_eq!(referenced_media(html), vec!["a&b.png", "say.mp3"]);
+    }
 }
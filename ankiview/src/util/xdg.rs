@@ -0,0 +1,66 @@
+// src/util/xdg.rs
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// `$XDG_CONFIG_HOME`, or `~/.config`, also on macOS (where `dirs` would pick
+/// `~/Library/Application Support`). The platform config directory on Windows
+pub fn config_home() -> Option<PathBuf> {
+    base_dir("XDG_CONFIG_HOME", ".config", dirs::config_dir)
+}
+
+/// `$XDG_CACHE_HOME`, or `~/.cache`, also on macOS (where `dirs` would pick
+/// `~/Library/Caches`). The platform cache directory on Windows
+pub fn cache_home() -> Option<PathBuf> {
+    base_dir("XDG_CACHE_HOME", ".cache", dirs::cache_dir)
+}
+
+fn base_dir(
+    variable: &str,
+    default: &str,
+    platform_dir: fn() -> Option<PathBuf>,
+) -> Option<PathBuf> {
+    if cfg!(windows) {
+        return platform_dir();
+    }
+    resolve(std::env::var_os(variable), dirs::home_dir(), default)
+}
+
+/// The variable's value if it is an absolute path (the spec says to ignore
+/// relative ones), otherwise `default` in the home directory
+fn resolve(value: Option<OsString>, home: Option<PathBuf>, default: &str) -> Option<PathBuf> {
+    value
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home.map(|home| home.join(default)))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_absolute_variable_when_resolving_then_uses_it() {
+        let dir = resolve(
+            Some(OsString::from("/xdg/cache")),
+            Some(PathBuf::from("/home/me")),
+            ".cache",
+        );
+
+        assert_eq!(dir, Some(PathBuf::from("/xdg/cache")));
+    }
+
+    #[test]
+    fn given_unset_or_relative_variable_when_resolving_then_uses_home_default() {
+        let home = Some(PathBuf::from("/home/me"));
+
+        assert_eq!(
+            resolve(None, home.clone(), ".cache"),
+            Some(PathBuf::from("/home/me/.cache"))
+        );
+        assert_eq!(
+            resolve(Some(OsString::from("cache")), home, ".cache"),
+            Some(PathBuf::from("/home/me/.cache"))
+        );
+        assert_eq!(resolve(None, None, ".cache"), None);
+    }
+}
@@ -159,6 +159,43 @@ fn given_collection_when_listing_all_notes_then_returns_all_notes() -> Result<()
     Ok(())
 }
 
+#[test]
+fn given_collection_when_listing_all_notes_then_matches_individual_lookup() -> Result<()> {
+    // Arrange
+    let test_collection = TestCollection::new()?;
+    let mut repo = test_collection.open_repository()?;
+
+    // Act
+    let notes = repo.list_notes(None)?;
+
+    // Assert: bulk listing yields the same data as per-note retrieval
+    for listed in &notes {
+        let fetched = repo.get_note(listed.id)?;
+        assert_eq!(listed.front, fetched.front);
+        assert_eq!(listed.back, fetched.back);
+        assert_eq!(listed.tags, fetched.tags);
+        assert_eq!(listed.model_name, fetched.model_name);
+    }
+    Ok(())
+}
+
+#[test]
+fn given_collection_when_iterating_notes_with_take_then_stops_early() -> Result<()> {
+    // Arrange
+    let test_collection = TestCollection::new()?;
+    let mut repo = test_collection.open_repository()?;
+
+    // Act
+    let notes = repo
+        .list_notes_iter(None)?
+        .take(3)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Assert
+    assert_eq!(notes.len(), 3);
+    Ok(())
+}
+
 #[test]
 fn given_collection_when_listing_with_search_then_returns_filtered_notes() -> Result<()> {
     // Arrange
@@ -1,4 +1,8 @@
-use ankiview::cli::args::{Args, Command};
+use ankiview::cli::args::{Args, CacheCommand, Command, LogFormat, NotetypeCommand};
+use ankiview::domain::{CountGroup, Flag};
+use ankiview::inka::application::card_collector::{NewPosition, OnDuplicate, SourceFormat};
+use ankiview::inka::infrastructure::occlusion::OcclusionMode;
+use ankiview::util::text::TextFormat;
 use clap::Parser;
 
 #[test]
@@ -21,14 +25,14 @@ fn given_explicit_view_command_when_parsing_then_succeeds() {
 
     // Assert
     match parsed.command {
-        Command::View { note_id, json } => {
-            assert_eq!(note_id, 1234567890);
+        Command::View { note_id, json, .. } => {
+            assert_eq!(note_id, Some(1234567890));
             assert!(!json);
         }
         _ => panic!("Expected View command"),
     }
-    assert_eq!(parsed.collection, None);
-    assert_eq!(parsed.profile, None);
+    assert!(parsed.collection.is_empty());
+    assert!(parsed.profile.is_empty());
 }
 
 #[test]
@@ -46,8 +50,8 @@ fn given_delete_command_when_parsing_then_succeeds() {
         }
         _ => panic!("Expected Delete command"),
     }
-    assert_eq!(parsed.collection, None);
-    assert_eq!(parsed.profile, None);
+    assert!(parsed.collection.is_empty());
+    assert!(parsed.profile.is_empty());
 }
 
 #[test]
@@ -73,9 +77,9 @@ fn given_global_collection_flag_when_parsing_then_succeeds() {
     }
     assert_eq!(
         parsed.collection,
-        Some(std::path::PathBuf::from("/path/to/collection.anki2"))
+        vec![std::path::PathBuf::from("/path/to/collection.anki2")]
     );
-    assert_eq!(parsed.profile, None);
+    assert!(parsed.profile.is_empty());
 }
 
 #[test]
@@ -88,14 +92,14 @@ fn given_global_profile_flag_when_parsing_then_succeeds() {
 
     // Assert
     match parsed.command {
-        Command::View { note_id, json } => {
-            assert_eq!(note_id, 1234567890);
+        Command::View { note_id, json, .. } => {
+            assert_eq!(note_id, Some(1234567890));
             assert!(!json);
         }
         _ => panic!("Expected View command"),
     }
-    assert_eq!(parsed.collection, None);
-    assert_eq!(parsed.profile, Some("User 1".to_string()));
+    assert!(parsed.collection.is_empty());
+    assert_eq!(parsed.profile, vec!["User 1".to_string()]);
 }
 
 #[test]
@@ -133,7 +137,7 @@ fn given_collection_flag_after_subcommand_when_parsing_then_succeeds() {
     }
     assert_eq!(
         parsed.collection,
-        Some(std::path::PathBuf::from("/path/to/collection.anki2"))
+        vec![std::path::PathBuf::from("/path/to/collection.anki2")]
     );
 }
 
@@ -147,8 +151,8 @@ fn given_json_flag_when_parsing_view_command_then_json_is_true() {
 
     // Assert
     match parsed.command {
-        Command::View { note_id, json } => {
-            assert_eq!(note_id, 1234567890);
+        Command::View { note_id, json, .. } => {
+            assert_eq!(note_id, Some(1234567890));
             assert!(json);
         }
         _ => panic!("Expected View command"),
@@ -165,8 +169,8 @@ fn given_no_json_flag_when_parsing_view_command_then_json_is_false() {
 
     // Assert
     match parsed.command {
-        Command::View { note_id, json } => {
-            assert_eq!(note_id, 1234567890);
+        Command::View { note_id, json, .. } => {
+            assert_eq!(note_id, Some(1234567890));
             assert!(!json);
         }
         _ => panic!("Expected View command"),
@@ -183,8 +187,8 @@ fn given_json_flag_with_global_flags_when_parsing_then_succeeds() {
 
     // Assert
     match parsed.command {
-        Command::View { note_id, json } => {
-            assert_eq!(note_id, 1234567890);
+        Command::View { note_id, json, .. } => {
+            assert_eq!(note_id, Some(1234567890));
             assert!(json);
         }
         _ => panic!("Expected View command"),
@@ -202,7 +206,7 @@ fn given_list_command_without_search_when_parsing_then_succeeds() {
 
     // Assert
     match parsed.command {
-        Command::List { search } => {
+        Command::List { search, .. } => {
             assert_eq!(search, None);
         }
         _ => panic!("Expected List command"),
@@ -219,7 +223,7 @@ fn given_list_command_with_search_when_parsing_then_succeeds() {
 
     // Assert
     match parsed.command {
-        Command::List { search } => {
+        Command::List { search, .. } => {
             assert_eq!(search, Some("tree".to_string()));
         }
         _ => panic!("Expected List command"),
@@ -236,7 +240,7 @@ fn given_list_command_with_global_flags_when_parsing_then_succeeds() {
 
     // Assert
     match parsed.command {
-        Command::List { search } => {
+        Command::List { search, .. } => {
             assert_eq!(search, Some("graph".to_string()));
         }
         _ => panic!("Expected List command"),
@@ -300,3 +304,1219 @@ fn given_collect_without_card_type_when_parsing_then_defaults_to_none() {
         _ => panic!("Expected Collect command"),
     }
 }
+
+#[test]
+fn given_quiet_flag_when_parsing_then_quiet_is_true() {
+    // Arrange
+    let args = vec!["ankiview", "collect", "-q", "notes.md"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    assert!(parsed.quiet);
+    assert_eq!(parsed.log_file, None);
+}
+
+#[test]
+fn given_log_file_flag_when_parsing_then_stores_path() {
+    // Arrange
+    let args = vec![
+        "ankiview",
+        "--log-file",
+        "/tmp/ankiview.log",
+        "collect",
+        "notes.md",
+    ];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    assert!(!parsed.quiet);
+    assert_eq!(
+        parsed.log_file,
+        Some(std::path::PathBuf::from("/tmp/ankiview.log"))
+    );
+}
+
+#[test]
+fn given_log_format_json_flag_when_parsing_then_json_format() {
+    // Arrange
+    let args = vec!["ankiview", "--log-format", "json", "list"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    assert_eq!(parsed.log_format, LogFormat::Json);
+}
+
+#[test]
+fn given_no_log_format_flag_when_parsing_then_defaults_to_text() {
+    // Arrange
+    let args = vec!["ankiview", "list"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    assert_eq!(parsed.log_format, LogFormat::Text);
+}
+
+#[test]
+fn given_json_errors_flag_when_parsing_then_json_errors_is_true() {
+    // Arrange
+    let args = vec!["ankiview", "delete", "123", "--json-errors"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    assert!(parsed.json_errors);
+}
+
+#[test]
+fn given_serve_command_when_parsing_then_succeeds() {
+    // Arrange
+    let args = vec!["ankiview", "-c", "/path/to/collection.anki2", "serve"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    assert!(matches!(parsed.command, Command::Serve));
+}
+
+#[test]
+fn given_daemon_stop_flag_when_parsing_then_stop_is_true() {
+    // Arrange
+    let args = vec!["ankiview", "daemon", "--stop", "--socket", "/tmp/a.sock"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Daemon { socket, stop } => {
+            assert!(stop);
+            assert_eq!(socket, Some(std::path::PathBuf::from("/tmp/a.sock")));
+        }
+        _ => panic!("Expected Daemon command"),
+    }
+}
+
+#[test]
+fn given_list_command_with_limit_when_parsing_then_stores_limit() {
+    // Arrange
+    let args = vec!["ankiview", "list", "rust", "--limit", "20"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::List { search, limit, .. } => {
+            assert_eq!(search, Some("rust".to_string()));
+            assert_eq!(limit, Some(20));
+        }
+        _ => panic!("Expected List command"),
+    }
+}
+
+#[test]
+fn given_collect_with_timings_flag_when_parsing_then_timings_is_true() {
+    // Arrange
+    let args = vec!["ankiview", "collect", "notes.md", "--timings"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Collect { timings, .. } => assert!(timings),
+        _ => panic!("Expected Collect command"),
+    }
+}
+
+#[test]
+fn given_collect_with_strict_flag_when_parsing_then_strict_is_true() {
+    // Arrange
+    let args = vec!["ankiview", "collect", "--strict", "notes.md"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Collect { strict, .. } => assert!(strict),
+        _ => panic!("Expected Collect command"),
+    }
+}
+
+#[test]
+fn given_collect_with_check_flag_when_parsing_then_check_is_true() {
+    // Arrange
+    let args = vec!["ankiview", "collect", "--check", "-r", "notes/"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Collect {
+            check, recursive, ..
+        } => {
+            assert!(check);
+            assert!(recursive);
+        }
+        _ => panic!("Expected Collect command"),
+    }
+}
+
+#[test]
+fn given_config_flag_when_parsing_then_stores_config_path() {
+    // Arrange
+    let args = vec![
+        "ankiview",
+        "collect",
+        "notes.md",
+        "--config",
+        "vault/ankiview.toml",
+    ];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    assert_eq!(
+        parsed.config,
+        Some(std::path::PathBuf::from("vault/ankiview.toml"))
+    );
+}
+
+#[test]
+fn given_collect_with_deck_override_when_parsing_then_stores_deck_and_prefix() {
+    // Arrange
+    let args = vec![
+        "ankiview",
+        "collect",
+        "vault/",
+        "--deck",
+        "Scratch",
+        "--deck-prefix",
+        "Imported::",
+    ];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Collect {
+            deck, deck_prefix, ..
+        } => {
+            assert_eq!(deck.as_deref(), Some("Scratch"));
+            assert_eq!(deck_prefix.as_deref(), Some("Imported::"));
+        }
+        _ => panic!("Expected Collect command"),
+    }
+}
+
+#[test]
+fn given_collect_with_repeated_tag_flag_when_parsing_then_collects_all_tags() {
+    // Arrange
+    let args = vec![
+        "ankiview",
+        "collect",
+        "vault/",
+        "--tag",
+        "source::vault",
+        "--tag",
+        "sync::2024-06-01",
+    ];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Collect { tags, .. } => {
+            assert_eq!(tags, vec!["source::vault", "sync::2024-06-01"]);
+        }
+        _ => panic!("Expected Collect command"),
+    }
+}
+
+#[test]
+fn given_view_with_note_link_when_parsing_then_extracts_note_id() {
+    // Arrange
+    let args = vec!["ankiview", "view", "ankiview://view/1686433857327"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::View { note_id, .. } => assert_eq!(note_id, Some(1686433857327)),
+        _ => panic!("Expected View command"),
+    }
+    assert!(Args::try_parse_from(vec!["ankiview", "view", "ankiview://view/x"]).is_err());
+}
+
+#[test]
+fn given_view_with_in_anki_flag_when_parsing_then_sets_in_anki() {
+    // Arrange
+    let args = vec!["ankiview", "view", "1234567890", "--in-anki"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::View {
+            note_id, in_anki, ..
+        } => {
+            assert_eq!(note_id, Some(1234567890));
+            assert!(in_anki);
+        }
+        _ => panic!("Expected View command"),
+    }
+    assert!(
+        Args::try_parse_from(vec!["ankiview", "view", "1", "--in-anki", "--json"]).is_err(),
+        "--in-anki and --json conflict"
+    );
+}
+
+#[test]
+fn given_clone_command_with_deck_when_parsing_then_succeeds() {
+    // Arrange
+    let args = vec!["ankiview", "clone", "1234567890", "--deck", "Scratch"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Clone { note_id, deck } => {
+            assert_eq!(note_id, 1234567890);
+            assert_eq!(deck.as_deref(), Some("Scratch"));
+        }
+        _ => panic!("Expected Clone command"),
+    }
+}
+
+#[test]
+fn given_history_command_when_parsing_then_succeeds() {
+    // Arrange
+    let args = vec!["ankiview", "history", "1234567890", "--json"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::History { note_id, json } => {
+            assert_eq!(note_id, 1234567890);
+            assert!(json);
+        }
+        _ => panic!("Expected History command"),
+    }
+}
+
+#[test]
+fn given_transfer_command_when_parsing_then_succeeds() {
+    // Arrange
+    let args = vec![
+        "ankiview",
+        "transfer",
+        "--from",
+        "a/collection.anki2",
+        "--to",
+        "b/collection.anki2",
+        "--query",
+        "deck:Shared",
+        "--move",
+    ];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Transfer {
+            from,
+            to,
+            query,
+            force,
+            move_notes,
+        } => {
+            assert_eq!(from, std::path::PathBuf::from("a/collection.anki2"));
+            assert_eq!(to, std::path::PathBuf::from("b/collection.anki2"));
+            assert_eq!(query.as_deref(), Some("deck:Shared"));
+            assert!(!force);
+            assert!(move_notes);
+        }
+        _ => panic!("Expected Transfer command"),
+    }
+}
+
+#[test]
+fn given_replace_command_when_parsing_then_succeeds() {
+    // Arrange
+    let args = vec![
+        "ankiview",
+        "replace",
+        "--query",
+        "deck:CS",
+        "--find",
+        r"colou?r",
+        "--with",
+        "color",
+        "--dry-run",
+    ];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Replace {
+            query,
+            find,
+            replacement,
+            dry_run,
+        } => {
+            assert_eq!(query.as_deref(), Some("deck:CS"));
+            assert_eq!(find.as_str(), r"colou?r");
+            assert_eq!(replacement, "color");
+            assert!(dry_run);
+        }
+        _ => panic!("Expected Replace command"),
+    }
+}
+
+#[test]
+fn given_replace_with_invalid_regex_when_parsing_then_fails() {
+    // Arrange
+    let args = vec!["ankiview", "replace", "--find", "(unclosed", "--with", "x"];
+
+    // Act
+    let result = Args::try_parse_from(args);
+
+    // Assert
+    assert!(result.is_err());
+}
+
+#[test]
+fn given_apply_rules_command_when_parsing_then_succeeds() {
+    // Arrange
+    let args = vec!["ankiview", "apply-rules", "rules.toml"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::ApplyRules { path } => assert_eq!(path, std::path::PathBuf::from("rules.toml")),
+        _ => panic!("Expected ApplyRules command"),
+    }
+}
+
+#[test]
+fn given_flag_command_with_color_when_parsing_then_maps_to_flag() {
+    // Arrange
+    let args = vec!["ankiview", "flag", "--red", "1234567890"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Flag { id, card, color } => {
+            assert_eq!(id, 1234567890);
+            assert!(!card);
+            assert_eq!(color.flag(), Flag::Red);
+        }
+        _ => panic!("Expected Flag command"),
+    }
+}
+
+#[test]
+fn given_flag_command_without_or_with_two_colors_when_parsing_then_fails() {
+    // Act & Assert
+    assert!(Args::try_parse_from(vec!["ankiview", "flag", "1"]).is_err());
+    assert!(Args::try_parse_from(vec!["ankiview", "flag", "--red", "--blue", "1"]).is_err());
+}
+
+#[test]
+fn given_mark_command_with_clear_when_parsing_then_succeeds() {
+    // Arrange
+    let args = vec!["ankiview", "mark", "1234567890", "--clear"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Mark { note_id, clear } => {
+            assert_eq!(note_id, 1234567890);
+            assert!(clear);
+        }
+        _ => panic!("Expected Mark command"),
+    }
+}
+
+#[test]
+fn given_collect_with_new_position_when_parsing_then_accepts_start_end_and_number() {
+    // Arrange
+    let parse = |position: &str| {
+        Args::try_parse_from(vec![
+            "ankiview",
+            "collect",
+            "notes.md",
+            "--new-position",
+            position,
+        ])
+    };
+
+    // Act & Assert
+    for (value, expected) in [
+        ("start", NewPosition::Start),
+        ("end", NewPosition::End),
+        ("42", NewPosition::At(42)),
+    ] {
+        match parse(value).unwrap().command {
+            Command::Collect { new_position, .. } => assert_eq!(new_position, Some(expected)),
+            _ => panic!("Expected Collect command"),
+        }
+    }
+    assert!(parse("top").is_err());
+}
+
+#[test]
+fn given_collect_with_same_deck_when_parsing_then_requires_update_ids() {
+    // Arrange
+    let parse = |args: &[&str]| {
+        Args::try_parse_from(["ankiview", "collect", "notes.md"].iter().chain(args))
+    };
+
+    // Act & Assert
+    assert!(parse(&["--same-deck"]).is_err());
+    match parse(&["--update-ids", "--same-deck"]).unwrap().command {
+        Command::Collect {
+            update_ids,
+            same_deck,
+            ..
+        } => assert!(update_ids && same_deck),
+        _ => panic!("Expected Collect command"),
+    }
+}
+
+#[test]
+fn given_collect_with_on_duplicate_when_parsing_then_accepts_create_skip_update() {
+    // Arrange
+    let parse = |mode: &str| {
+        Args::try_parse_from(vec![
+            "ankiview",
+            "collect",
+            "notes.md",
+            "--on-duplicate",
+            mode,
+        ])
+    };
+
+    // Act & Assert
+    for (value, expected) in [
+        ("create", OnDuplicate::Create),
+        ("skip", OnDuplicate::Skip),
+        ("update", OnDuplicate::Update),
+    ] {
+        match parse(value).unwrap().command {
+            Command::Collect { on_duplicate, .. } => assert_eq!(on_duplicate, Some(expected)),
+            _ => panic!("Expected Collect command"),
+        }
+    }
+    assert!(parse("merge").is_err());
+}
+
+#[test]
+fn given_orphans_command_with_delete_when_parsing_then_succeeds() {
+    // Arrange
+    let args = vec!["ankiview", "orphans", "notes/", "--delete"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Orphans { dir, delete, yes } => {
+            assert_eq!(dir, std::path::PathBuf::from("notes/"));
+            assert!(delete);
+            assert!(!yes);
+        }
+        _ => panic!("Expected Orphans command"),
+    }
+}
+
+#[test]
+fn given_orphans_command_with_yes_when_parsing_then_requires_delete() {
+    // Act
+    let parsed = Args::try_parse_from(["ankiview", "orphans", "notes/", "--delete", "--yes"]);
+    let without_delete = Args::try_parse_from(["ankiview", "orphans", "notes/", "--yes"]);
+
+    // Assert
+    match parsed.unwrap().command {
+        Command::Orphans { yes, .. } => assert!(yes),
+        _ => panic!("Expected Orphans command"),
+    }
+    assert!(without_delete.is_err());
+}
+
+#[test]
+fn given_empty_decks_command_with_delete_when_parsing_then_succeeds() {
+    // Arrange
+    let args = vec!["ankiview", "empty-decks", "--delete"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::EmptyDecks { delete } => assert!(delete),
+        _ => panic!("Expected EmptyDecks command"),
+    }
+}
+
+#[test]
+fn given_rename_deck_command_when_parsing_then_succeeds() {
+    // Arrange
+    let args = vec!["ankiview", "rename-deck", "Old::Name", "New::Name"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::RenameDeck { old, new } => {
+            assert_eq!(old, "Old::Name");
+            assert_eq!(new, "New::Name");
+        }
+        _ => panic!("Expected RenameDeck command"),
+    }
+}
+
+#[test]
+fn given_review_stats_command_when_parsing_then_defaults_to_30_days() {
+    // Arrange
+    let args = vec!["ankiview", "review-stats", "--deck", "Rust", "--json"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::ReviewStats { deck, days, json } => {
+            assert_eq!(deck.as_deref(), Some("Rust"));
+            assert_eq!(days, 30);
+            assert!(json);
+        }
+        _ => panic!("Expected ReviewStats command"),
+    }
+}
+
+#[test]
+fn given_leeches_command_when_parsing_then_defaults_to_anki_leech_threshold() {
+    // Arrange
+    let args = vec!["ankiview", "leeches", "--deck", "Rust"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Leeches {
+            min_lapses,
+            deck,
+            json,
+        } => {
+            assert_eq!(min_lapses, 8);
+            assert_eq!(deck.as_deref(), Some("Rust"));
+            assert!(!json);
+        }
+        _ => panic!("Expected Leeches command"),
+    }
+}
+
+#[test]
+fn given_list_command_with_due_flag_when_parsing_then_due_is_true() {
+    // Arrange
+    let args = vec!["ankiview", "list", "--due"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::List { search, due, .. } => {
+            assert_eq!(search, None);
+            assert!(due);
+        }
+        _ => panic!("Expected List command"),
+    }
+}
+
+#[test]
+fn given_count_command_with_query_and_by_when_parsing_then_stores_both() {
+    // Arrange
+    let args = vec![
+        "ankiview",
+        "count",
+        "--query",
+        "tag:rust is:new",
+        "--by",
+        "deck",
+    ];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Count { query, by } => {
+            assert_eq!(query, Some("tag:rust is:new".to_string()));
+            assert_eq!(by, Some(CountGroup::Deck));
+        }
+        _ => panic!("Expected Count command"),
+    }
+}
+
+#[test]
+fn given_count_command_with_unknown_group_when_parsing_then_fails() {
+    let result = Args::try_parse_from(vec!["ankiview", "count", "--by", "color"]);
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn given_fields_command_with_json_when_parsing_then_stores_notetype() {
+    // Arrange
+    let args = vec!["ankiview", "fields", "Inka Basic", "--json"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Fields { notetype, json } => {
+            assert_eq!(notetype, "Inka Basic");
+            assert!(json);
+        }
+        _ => panic!("Expected Fields command"),
+    }
+}
+
+#[test]
+fn given_notetype_push_command_when_parsing_then_stores_files() {
+    // Arrange
+    let args = vec![
+        "ankiview",
+        "notetype",
+        "push",
+        "Inka Basic",
+        "--css",
+        "style.css",
+        "--front",
+        "front.html",
+    ];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Notetype {
+            subcommand:
+                NotetypeCommand::Push {
+                    name,
+                    css,
+                    front,
+                    back,
+                    template,
+                    math_macros,
+                },
+        } => {
+            assert_eq!(name, "Inka Basic");
+            assert_eq!(css, Some(std::path::PathBuf::from("style.css")));
+            assert_eq!(front, Some(std::path::PathBuf::from("front.html")));
+            assert_eq!(back, None);
+            assert_eq!(template, None);
+            assert!(!math_macros);
+        }
+        _ => panic!("Expected Notetype push command"),
+    }
+}
+
+#[test]
+fn given_notetype_push_with_math_macros_when_parsing_then_sets_flag() {
+    // Arrange
+    let args = vec!["ankiview", "notetype", "push", "Basic", "--math-macros"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Notetype {
+            subcommand: NotetypeCommand::Push {
+                name, math_macros, ..
+            },
+        } => {
+            assert_eq!(name, "Basic");
+            assert!(math_macros);
+        }
+        _ => panic!("Expected Notetype push command"),
+    }
+}
+
+#[test]
+fn given_cache_prune_command_when_parsing_then_succeeds() {
+    // Arrange
+    let args = vec!["ankiview", "cache", "prune"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Cache {
+            subcommand: CacheCommand::Prune,
+        } => {}
+        _ => panic!("Expected Cache prune command"),
+    }
+}
+
+#[test]
+fn given_cache_show_command_with_json_when_parsing_then_sets_flag() {
+    // Arrange
+    let args = vec!["ankiview", "cache", "show", "--json"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Cache {
+            subcommand: CacheCommand::Show { json },
+        } => assert!(json),
+        _ => panic!("Expected Cache show command"),
+    }
+}
+
+#[test]
+fn given_occlude_command_with_rects_when_parsing_then_stores_rects_and_defaults() {
+    // Arrange
+    let args = vec![
+        "ankiview",
+        "occlude",
+        "skull.png",
+        "--rect",
+        "40,30,120,40",
+        "--rect",
+        "40,90,120,40",
+        "--mode",
+        "hide-one",
+    ];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Occlude {
+            image,
+            rects,
+            mode,
+            deck,
+            notetype,
+            ..
+        } => {
+            assert_eq!(image, std::path::PathBuf::from("skull.png"));
+            assert_eq!(rects.len(), 2);
+            assert_eq!(rects[1].y, 90);
+            assert_eq!(mode, OcclusionMode::HideOne);
+            assert_eq!(deck, "Default");
+            assert_eq!(notetype, "Image Occlusion Enhanced");
+        }
+        _ => panic!("Expected Occlude command"),
+    }
+}
+
+#[test]
+fn given_occlude_command_without_rect_when_parsing_then_fails() {
+    let result = Args::try_parse_from(vec!["ankiview", "occlude", "skull.png"]);
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn given_collect_with_org_format_when_parsing_then_stores_format() {
+    // Arrange
+    let args = vec!["ankiview", "collect", "notes/", "--format", "org"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Collect { format, .. } => assert_eq!(format, SourceFormat::Org),
+        _ => panic!("Expected Collect command"),
+    }
+}
+
+#[test]
+fn given_export_tsv_command_when_parsing_then_stores_query_out_and_format() {
+    // Arrange
+    let args = vec![
+        "ankiview",
+        "export-tsv",
+        "--query",
+        "deck:Spanish",
+        "--out",
+        "spanish.tsv",
+        "--format",
+        "text",
+    ];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::ExportTsv { query, out, format } => {
+            assert_eq!(query.as_deref(), Some("deck:Spanish"));
+            assert_eq!(out, Some(std::path::PathBuf::from("spanish.tsv")));
+            assert_eq!(format, TextFormat::Text);
+        }
+        _ => panic!("Expected ExportTsv command"),
+    }
+}
+
+#[test]
+fn given_export_jsonl_command_when_parsing_then_stores_query_and_out() {
+    // Arrange
+    let args = vec!["ankiview", "export-jsonl", "--query", "deck:Rust"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::ExportJsonl { query, out } => {
+            assert_eq!(query.as_deref(), Some("deck:Rust"));
+            assert!(out.is_none());
+        }
+        _ => panic!("Expected ExportJsonl command"),
+    }
+}
+
+#[test]
+fn given_add_with_template_and_vars_when_parsing_then_splits_vars() {
+    // Arrange
+    let args = vec![
+        "ankiview",
+        "add",
+        "--template",
+        "vocab",
+        "--var",
+        "word=perro",
+        "--var",
+        "translation=dog = perro",
+    ];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Add { template, vars, .. } => {
+            assert_eq!(template, "vocab");
+            assert_eq!(
+                vars,
+                vec![
+                    ("word".to_string(), "perro".to_string()),
+                    ("translation".to_string(), "dog = perro".to_string()),
+                ]
+            );
+        }
+        _ => panic!("Expected Add command"),
+    }
+}
+
+#[test]
+fn given_add_with_malformed_var_when_parsing_then_fails() {
+    // Arrange
+    let args = vec!["ankiview", "add", "--template", "vocab", "--var", "perro"];
+
+    // Act
+    let result = Args::try_parse_from(args);
+
+    // Assert
+    assert!(result.is_err());
+}
+
+#[test]
+fn given_collect_with_non_interactive_when_parsing_then_sets_flag() {
+    // Arrange
+    let args = vec!["ankiview", "collect", "notes/", "--non-interactive"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Collect {
+            non_interactive, ..
+        } => assert!(non_interactive),
+        _ => panic!("Expected Collect command"),
+    }
+}
+
+#[test]
+fn given_collect_with_ci_when_parsing_then_sets_flag() {
+    // Arrange
+    let args = vec!["ankiview", "collect", "notes/", "--ci"];
+
+    // Act
+    let parsed = Args::try_parse_from(args).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Collect { ci, .. } => assert!(ci),
+        _ => panic!("Expected Collect command"),
+    }
+}
+
+#[test]
+fn given_view_random_with_query_when_parsing_then_needs_no_note_id() {
+    // Act
+    let parsed =
+        Args::try_parse_from(["ankiview", "view", "--random", "--query", "deck:Spanish"]).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::View {
+            note_id,
+            random,
+            latest,
+            query,
+            ..
+        } => {
+            assert_eq!(note_id, None);
+            assert!(random);
+            assert!(!latest);
+            assert_eq!(query.as_deref(), Some("deck:Spanish"));
+        }
+        _ => panic!("Expected View command"),
+    }
+    assert!(Args::try_parse_from(["ankiview", "view"]).is_err());
+    assert!(Args::try_parse_from(["ankiview", "view", "1", "--latest"]).is_err());
+    assert!(Args::try_parse_from(["ankiview", "view", "--random", "--latest"]).is_err());
+}
+
+#[test]
+fn given_view_with_browser_when_parsing_then_keeps_command() {
+    // Act
+    let parsed =
+        Args::try_parse_from(["ankiview", "view", "1234567890", "--browser", "firefox %s"])
+            .unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::View { browser, .. } => assert_eq!(browser.as_deref(), Some("firefox %s")),
+        _ => panic!("Expected View command"),
+    }
+    assert!(
+        Args::try_parse_from(["ankiview", "view", "1", "--json", "--browser", "firefox"]).is_err()
+    );
+}
+
+#[test]
+fn given_preview_command_when_parsing_then_defaults_port() {
+    // Act
+    let parsed = Args::try_parse_from(["ankiview", "preview", "notes/rust.md"]).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Preview { path, port } => {
+            assert_eq!(path, std::path::PathBuf::from("notes/rust.md"));
+            assert_eq!(port, 8777);
+        }
+        _ => panic!("Expected Preview command"),
+    }
+}
+
+#[test]
+fn given_convert_command_with_card_when_parsing_then_rejects_card_zero() {
+    // Act
+    let parsed =
+        Args::try_parse_from(["ankiview", "convert", "notes/rust.md", "--card", "3"]).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Convert { path, card } => {
+            assert_eq!(path, std::path::PathBuf::from("notes/rust.md"));
+            assert_eq!(card, Some(3));
+        }
+        _ => panic!("Expected Convert command"),
+    }
+    assert!(Args::try_parse_from(["ankiview", "convert", "notes/rust.md", "--card", "0"]).is_err());
+}
+
+#[test]
+fn given_lint_command_when_parsing_then_parses_path_and_recursive() {
+    // Act
+    let parsed = Args::try_parse_from(["ankiview", "lint", "notes/", "-r"]).unwrap();
+    let collect = Args::try_parse_from(["ankiview", "collect", "notes/", "--lint"]).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Lint { path, recursive } => {
+            assert_eq!(path, std::path::PathBuf::from("notes/"));
+            assert!(recursive);
+        }
+        _ => panic!("Expected Lint command"),
+    }
+    match collect.command {
+        Command::Collect { lint, .. } => assert!(lint),
+        _ => panic!("Expected Collect command"),
+    }
+}
+
+#[test]
+fn given_collect_with_restore_from_when_parsing_then_sets_backup_path() {
+    // Act
+    let parsed = Args::try_parse_from([
+        "ankiview",
+        "collect",
+        "notes/",
+        "--restore-from",
+        "backup/collection.anki2",
+    ])
+    .unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Collect { restore_from, .. } => assert_eq!(
+            restore_from,
+            Some(std::path::PathBuf::from("backup/collection.anki2"))
+        ),
+        _ => panic!("Expected Collect command"),
+    }
+}
+
+#[test]
+fn given_vault_stats_command_with_json_when_parsing_then_parses_path() {
+    // Act
+    let parsed = Args::try_parse_from(["ankiview", "vault-stats", "notes/", "--json"]).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::VaultStats { path, json } => {
+            assert_eq!(path, std::path::PathBuf::from("notes/"));
+            assert!(json);
+        }
+        _ => panic!("Expected VaultStats command"),
+    }
+}
+
+#[test]
+fn given_view_with_stdout_when_parsing_then_conflicts_with_json() {
+    // Act
+    let parsed = Args::try_parse_from(["ankiview", "view", "1234567890", "--stdout"]).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::View { stdout, .. } => assert!(stdout),
+        _ => panic!("Expected View command"),
+    }
+    assert!(Args::try_parse_from(["ankiview", "view", "1", "--stdout", "--json"]).is_err());
+}
+
+#[test]
+fn given_view_with_sanitize_when_parsing_then_sets_flag() {
+    // Act
+    let parsed =
+        Args::try_parse_from(["ankiview", "view", "1234567890", "--sanitize", "--stdout"]).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::View {
+            sanitize, stdout, ..
+        } => assert!(sanitize && stdout),
+        _ => panic!("Expected View command"),
+    }
+    assert!(Args::try_parse_from(["ankiview", "view", "1", "--sanitize", "--json"]).is_err());
+}
+
+#[test]
+fn given_list_with_template_when_parsing_then_parses_template() {
+    // Act
+    let parsed = Args::try_parse_from([
+        "ankiview",
+        "list",
+        "--template",
+        r"{id}\t{deck}\t{front:60}",
+    ]);
+
+    // Assert
+    match parsed.unwrap().command {
+        Command::List { template, .. } => assert!(template.is_some()),
+        _ => panic!("Expected List command"),
+    }
+    assert!(Args::try_parse_from(["ankiview", "list", "--template", "{id"]).is_err());
+    assert!(Args::try_parse_from(["ankiview", "list", "--due", "--template", "{id}"]).is_err());
+    assert!(Args::try_parse_from(["ankiview", "list", "--template", "{id}", "--due"]).is_err());
+}
+
+#[test]
+fn given_repeated_collection_flags_when_parsing_then_keeps_all() {
+    // Act
+    let parsed = Args::try_parse_from([
+        "ankiview",
+        "count",
+        "-c",
+        "/anki/Work/collection.anki2",
+        "-c",
+        "/anki/Personal/collection.anki2",
+        "-p",
+        "Old",
+    ])
+    .unwrap();
+
+    // Assert
+    assert_eq!(
+        parsed.collection,
+        vec![
+            std::path::PathBuf::from("/anki/Work/collection.anki2"),
+            std::path::PathBuf::from("/anki/Personal/collection.anki2"),
+        ]
+    );
+    assert_eq!(parsed.profile, vec!["Old".to_string()]);
+}
+
+#[test]
+fn given_pick_command_when_parsing_then_reads_query_and_multi() {
+    // Act
+    let parsed =
+        Args::try_parse_from(["ankiview", "pick", "--query", "deck:Rust", "--multi"]).unwrap();
+
+    // Assert
+    match parsed.command {
+        Command::Pick { query, multi } => {
+            assert_eq!(query.as_deref(), Some("deck:Rust"));
+            assert!(multi);
+        }
+        _ => panic!("Expected Pick command"),
+    }
+}
@@ -337,3 +337,165 @@ Deck: OrphanTest
 
     Ok(())
 }
+
+/// Note ID written into the first card of `markdown_path`
+fn first_note_id(markdown_path: &std::path::Path) -> Result<i64> {
+    let content = fs::read_to_string(markdown_path)?;
+    Ok(content
+        .split("<!--ID:")
+        .nth(1)
+        .and_then(|rest| rest.split("-->").next())
+        .and_then(|id| id.trim().parse().ok())
+        .expect("Should have an ID"))
+}
+
+/// Collect `markdown_path` with `config`; returns the notes recreated for orphaned IDs
+fn collect_file(
+    test_collection: &TestCollection,
+    markdown_path: &std::path::Path,
+    config: ankiview::inka::application::card_collector::CollectorConfig,
+) -> Result<Vec<String>> {
+    let mut collector = ankiview::inka::application::card_collector::CardCollector::new(
+        &test_collection.collection_path,
+        config,
+    )?;
+    collector.process_file(markdown_path)?;
+    Ok(collector.recreated_notes().to_vec())
+}
+
+#[test]
+fn given_orphaned_note_id_without_backup_when_collecting_then_tags_new_note_reimported(
+) -> Result<()> {
+    // Arrange
+    let test_collection = TestCollection::new()?;
+    let temp_dir = TempDir::new()?;
+    let markdown_path = temp_dir.path().join("orphaned.md");
+    fs::write(
+        &markdown_path,
+        "---\nDeck: OrphanTest\n\n1. What is lost with a deleted note?\n> Its review history\n---",
+    )?;
+    let config = ankiview::inka::application::card_collector::CollectorConfig::new();
+    collect_file(&test_collection, &markdown_path, config)?;
+    let old_id = first_note_id(&markdown_path)?;
+    test_collection.open_repository()?.delete_note(old_id)?;
+
+    // Act
+    let mut config = ankiview::inka::application::card_collector::CollectorConfig::new();
+    config.full_sync = true;
+    let recreated = collect_file(&test_collection, &markdown_path, config)?;
+
+    // Assert
+    assert_eq!(recreated.len(), 1);
+    assert!(recreated[0].contains(&format!("note {} recreated as", old_id)));
+    let new_id = first_note_id(&markdown_path)?;
+    let note = test_collection.open_repository()?.get_note(new_id)?;
+    assert!(note.tags.contains(&"reimported".to_string()));
+
+    Ok(())
+}
+
+#[test]
+fn given_tag_namespace_when_recreating_orphaned_note_then_namespaces_reimported_tag() -> Result<()>
+{
+    // Arrange
+    let test_collection = TestCollection::new()?;
+    let temp_dir = TempDir::new()?;
+    let markdown_path = temp_dir.path().join("orphaned.md");
+    fs::write(
+        &markdown_path,
+        "---\nDeck: OrphanTest\n\n1. Where does the tag go?\n> Into the namespace\n---",
+    )?;
+    let mut config = ankiview::inka::application::card_collector::CollectorConfig::new();
+    config.tag_namespace = Some("inka".to_string());
+    collect_file(&test_collection, &markdown_path, config.clone())?;
+    let old_id = first_note_id(&markdown_path)?;
+    test_collection.open_repository()?.delete_note(old_id)?;
+
+    // Act
+    config.full_sync = true;
+    let recreated = collect_file(&test_collection, &markdown_path, config)?;
+
+    // Assert
+    assert!(recreated[0].ends_with("tagged 'inka::reimported', its cards start as new"));
+    let new_id = first_note_id(&markdown_path)?;
+    let note = test_collection.open_repository()?.get_note(new_id)?;
+    assert!(note.tags.contains(&"inka::reimported".to_string()));
+    assert!(!note.tags.contains(&"reimported".to_string()));
+
+    Ok(())
+}
+
+#[test]
+fn given_orphaned_note_id_with_backup_when_collecting_then_restores_scheduling() -> Result<()> {
+    // Arrange
+    let test_collection = TestCollection::new()?;
+    let temp_dir = TempDir::new()?;
+    let markdown_path = temp_dir.path().join("orphaned.md");
+    let backup_path = temp_dir.path().join("backup.anki2");
+    fs::write(
+        &markdown_path,
+        "---\nDeck: OrphanTest\n\n1. What keeps the history?\n> A backup\n---",
+    )?;
+    let config = ankiview::inka::application::card_collector::CollectorConfig::new();
+    collect_file(&test_collection, &markdown_path, config)?;
+    fs::copy(&test_collection.collection_path, &backup_path)?;
+    let old_id = first_note_id(&markdown_path)?;
+    test_collection.open_repository()?.delete_note(old_id)?;
+
+    // Act
+    let mut config = ankiview::inka::application::card_collector::CollectorConfig::new();
+    config.full_sync = true;
+    config.restore_from = Some(backup_path);
+    let recreated = collect_file(&test_collection, &markdown_path, config)?;
+
+    // Assert
+    assert_eq!(recreated.len(), 1);
+    assert!(recreated[0].ends_with("scheduling of 1 card(s) restored from backup"));
+    let new_id = first_note_id(&markdown_path)?;
+    let note = test_collection.open_repository()?.get_note(new_id)?;
+    assert!(!note.tags.contains(&"reimported".to_string()));
+
+    Ok(())
+}
+
+#[test]
+fn given_new_position_start_when_collecting_then_new_cards_go_before_existing_ones() -> Result<()> {
+    use ankiview::infrastructure::backup::BackupReader;
+    use ankiview::inka::application::card_collector::{CollectorConfig, NewPosition};
+
+    // Arrange
+    let test_collection = TestCollection::new()?;
+    let temp_dir = TempDir::new()?;
+    let queued_path = temp_dir.path().join("queued.md");
+    fs::write(
+        &queued_path,
+        "---\nDeck: QueueTest\n\n1. Which card was queued first?\n> This one\n---",
+    )?;
+    collect_file(&test_collection, &queued_path, CollectorConfig::new())?;
+    let imported_path = temp_dir.path().join("imported.md");
+    fs::write(
+        &imported_path,
+        "---\nDeck: QueueTest\n\n1. Which card is studied first?\n> The imported one\n---",
+    )?;
+
+    // Act
+    let mut config = CollectorConfig::new();
+    config.new_position = Some(NewPosition::Start);
+    ankiview::AnkiView::new(&test_collection.collection_path).collect_path(
+        &imported_path,
+        false,
+        config,
+    )?;
+
+    // Assert: a new card's due is its position in the new-card queue
+    let reader = BackupReader::open(&test_collection.collection_path)?;
+    let imported = reader.card_scheduling(first_note_id(&imported_path)?)?;
+    let queued = reader.card_scheduling(first_note_id(&queued_path)?)?;
+    assert_eq!(imported[0].due, 0);
+    assert!(
+        queued[0].due > imported[0].due,
+        "Existing new cards should be shifted back"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,108 @@
+mod helpers;
+
+use ankiview::cli::error::ErrorKind;
+use ankiview::inka::application::card_collector::CollectorConfig;
+use ankiview::AnkiView;
+use anyhow::Result;
+use helpers::{test_notes, TestCollection};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn given_valid_note_id_when_viewing_via_facade_then_returns_note() -> Result<()> {
+    // Arrange
+    let test_collection = TestCollection::new()?;
+    let ankiview = AnkiView::new(&test_collection.collection_path);
+
+    // Act
+    let note = ankiview.view_note(test_notes::TREE)?;
+
+    // Assert
+    assert_eq!(note.id, test_notes::TREE);
+    assert!(!note.front.is_empty());
+    Ok(())
+}
+
+#[test]
+fn given_nonexistent_note_id_when_viewing_via_facade_then_returns_not_found() -> Result<()> {
+    // Arrange
+    let test_collection = TestCollection::new()?;
+    let ankiview = AnkiView::new(&test_collection.collection_path);
+
+    // Act
+    let err = ankiview.view_note(test_notes::NONEXISTENT).unwrap_err();
+
+    // Assert
+    assert_eq!(ErrorKind::classify(&err), ErrorKind::NotFound);
+    Ok(())
+}
+
+#[test]
+fn given_collection_when_listing_via_facade_then_returns_notes() -> Result<()> {
+    // Arrange
+    let test_collection = TestCollection::new()?;
+    let ankiview = AnkiView::new(&test_collection.collection_path);
+
+    // Act
+    let notes = ankiview.list(None)?;
+
+    // Assert
+    assert!(notes.iter().any(|n| n.id == test_notes::TREE));
+    Ok(())
+}
+
+#[test]
+fn given_note_when_deleting_via_facade_then_note_is_gone() -> Result<()> {
+    // Arrange
+    let test_collection = TestCollection::new()?;
+    let ankiview = AnkiView::new(&test_collection.collection_path);
+
+    // Act
+    let deleted_cards = ankiview.delete(test_notes::TREE)?;
+
+    // Assert
+    assert!(deleted_cards >= 1);
+    assert!(ankiview.view_note(test_notes::TREE).is_err());
+    Ok(())
+}
+
+#[test]
+fn given_markdown_file_when_collecting_via_facade_then_returns_summary() -> Result<()> {
+    // Arrange
+    let test_collection = TestCollection::new()?;
+    let ankiview = AnkiView::new(&test_collection.collection_path);
+    let temp_dir = TempDir::new()?;
+    let markdown_path = temp_dir.path().join("facade.md");
+    fs::write(
+        &markdown_path,
+        "---\nDeck: FacadeTest\n\n1. What is the facade for?\n> Embedding\n---",
+    )?;
+
+    // Act
+    let summary = ankiview.collect_path(&markdown_path, false, CollectorConfig::new())?;
+
+    // Assert
+    assert_eq!(summary.cards, 1);
+    assert!(summary.errors.is_empty());
+    assert!(ankiview
+        .list(Some("facade"))?
+        .iter()
+        .any(|n| n.front.contains("facade")));
+    Ok(())
+}
+
+#[test]
+fn given_missing_path_when_collecting_via_facade_then_returns_not_found() -> Result<()> {
+    // Arrange
+    let test_collection = TestCollection::new()?;
+    let ankiview = AnkiView::new(&test_collection.collection_path);
+
+    // Act
+    let err = ankiview
+        .collect_path("/nonexistent/notes", false, CollectorConfig::new())
+        .unwrap_err();
+
+    // Assert
+    assert_eq!(ErrorKind::classify(&err), ErrorKind::NotFound);
+    Ok(())
+}